@@ -0,0 +1,231 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+
+use meshtastic::types::MeshChannel;
+
+use super::*;
+use crate::message::{Destination, MessageContext, Response};
+use crate::modules::build_registry;
+use crate::settings;
+use crate::util::{format_bytes, parse_node_id};
+
+type VacuumResult = Result<(u64, u64), Box<dyn std::error::Error + Send + Sync>>;
+
+/// An `!admin db vacuum` running on a blocking thread, and the admin to tell
+/// when it's done.
+pub(super) struct DbMaintenance {
+    ctx: MessageContext,
+    task: tokio::task::JoinHandle<VacuumResult>,
+}
+
+/// `[bot] admin_nodes` as node IDs; entries that don't parse are skipped.
+pub(super) fn parse_admins(admin_nodes: &[String]) -> HashSet<u32> {
+    admin_nodes
+        .iter()
+        .filter_map(|n| parse_node_id(n))
+        .collect()
+}
+
+impl Bot {
+    /// Set the config file `!admin reload` reads.
+    pub fn with_config_path(mut self, path: PathBuf) -> Self {
+        self.config_path = Some(path);
+        self
+    }
+
+    /// Whether `node_id` is listed in `[bot] admin_nodes`.
+    pub(super) fn is_admin(&self, node_id: u32) -> bool {
+        self.admins.read().unwrap().contains(&node_id)
+    }
+
+    /// The current `[bot] admin_nodes`, in ID order, for alert DMs. Follows
+    /// `!admin reload` like `is_admin`.
+    pub(super) fn admin_ids(&self) -> Vec<u32> {
+        let mut admins: Vec<u32> = self.admins.read().unwrap().iter().copied().collect();
+        admins.sort_unstable();
+        admins
+    }
+
+    /// Check the sender of an admin-only command holds the public key its
+    /// node announced: the DM must have been PKI-encrypted with that key. A
+    /// node with no stored key can't be checked and passes.
+    pub(super) async fn admin_sender_verified(&self, ctx: &MessageContext) -> bool {
+        let sender_id = ctx.sender_id;
+        let verified = match self.db.run(move |db| db.get_public_key(sender_id)).await {
+            Ok(Some(key)) => ctx.pki_key.as_ref() == Some(&key),
+            Ok(None) => true,
+            Err(e) => {
+                log::error!("Failed to look up public key: {}", e);
+                false
+            }
+        };
+        if !verified {
+            log::warn!(
+                "Admin command from !{:08x} not PKI-encrypted with its stored key",
+                ctx.sender_id
+            );
+        }
+        verified
+    }
+
+    /// The gate for admin-only commands: from `[bot] admin_nodes`, by DM,
+    /// and PKI-encrypted with the key the node announced, so a spoofed node
+    /// ID isn't enough.
+    pub(super) async fn is_verified_admin(&self, ctx: &MessageContext, is_dm: bool) -> bool {
+        is_dm && self.is_admin(ctx.sender_id) && self.admin_sender_verified(ctx).await
+    }
+
+    /// Answer the `!admin` subcommands that need the bot itself: `queue`,
+    /// `broadcast`, `db vacuum`, `reload` and `node`. `None` leaves the rest to the admin module.
+    /// Callers check `is_admin` first.
+    pub(super) async fn admin_command(
+        &self,
+        my_node_id: u32,
+        ctx: &MessageContext,
+        args: &str,
+    ) -> Option<Vec<Response>> {
+        let (subcommand, rest) = match args.split_once(' ') {
+            Some((sub, rest)) => (sub, rest.trim()),
+            None => (args, ""),
+        };
+        let reply = |text: String| Response {
+            text,
+            destination: Destination::Sender,
+            channel: ctx.channel,
+            reply_id: Some(ctx.packet_id),
+            data: None,
+        };
+        let responses = match subcommand.to_lowercase().as_str() {
+            "queue" => {
+                let depth = self.outgoing.depth_handle().load(Ordering::Relaxed);
+                vec![reply(format!("Outgoing queue: {} message(s)", depth))]
+            }
+            "broadcast" => {
+                let (channel, text) = rest.split_once(' ').unwrap_or((rest, ""));
+                let text = text.trim();
+                match channel.parse::<u32>() {
+                    Ok(channel) if !text.is_empty() && MeshChannel::new(channel).is_ok() => {
+                        log::info!(
+                            "Admin {} broadcast on channel {}: {}",
+                            ctx.sender_name,
+                            channel,
+                            text
+                        );
+                        vec![
+                            Response {
+                                text: text.to_string(),
+                                destination: Destination::Broadcast,
+                                channel,
+                                reply_id: None,
+                                data: None,
+                            },
+                            reply(format!("Broadcast queued on channel {}", channel)),
+                        ]
+                    }
+                    _ => vec![reply("Usage: !admin broadcast <ch> <text>".to_string())],
+                }
+            }
+            "db" if rest.eq_ignore_ascii_case("vacuum") => {
+                vec![reply(self.start_db_maintenance(ctx))]
+            }
+            "reload" => vec![reply(self.reload_config().await)],
+            "node" => vec![reply(self.node_admin_command(my_node_id, ctx, rest).await)],
+            _ => return None,
+        };
+        Some(responses)
+    }
+
+    /// Start VACUUM and ANALYZE on a blocking thread; `finish_db_maintenance`
+    /// tells the admin when it's done. `Db::vacuum` uses its own connection,
+    /// so packets keep being handled meanwhile; their writes wait on SQLite's
+    /// lock, up to the busy timeout.
+    fn start_db_maintenance(&self, ctx: &MessageContext) -> String {
+        let mut maintenance = self.db_maintenance.lock().unwrap();
+        if maintenance.is_some() {
+            return "Database maintenance already running.".to_string();
+        }
+        log::info!("Database VACUUM requested by admin {}", ctx.sender_name);
+        let db = Arc::clone(&self.db);
+        *maintenance = Some(DbMaintenance {
+            ctx: ctx.clone(),
+            task: tokio::task::spawn_blocking(move || db.vacuum()),
+        });
+        "Database maintenance started, you'll get a DM when it's done.".to_string()
+    }
+
+    pub(super) fn db_maintenance_running(&self) -> bool {
+        self.db_maintenance.lock().unwrap().is_some()
+    }
+
+    /// If the `!admin db vacuum` task has finished, DM its result to the
+    /// admin who started it.
+    pub(super) async fn finish_db_maintenance(&self, my_node_id: u32) {
+        let maintenance = {
+            let mut maintenance = self.db_maintenance.lock().unwrap();
+            match maintenance.as_ref() {
+                Some(m) if m.task.is_finished() => maintenance.take(),
+                _ => None,
+            }
+        };
+        let Some(DbMaintenance { ctx, task }) = maintenance else {
+            return;
+        };
+        let text = match task.await {
+            Ok(Ok((before, after))) => {
+                log::info!("Database VACUUM done: {} -> {} bytes", before, after);
+                format!(
+                    "Database maintenance done: {} -> {}.",
+                    format_bytes(before),
+                    format_bytes(after)
+                )
+            }
+            Ok(Err(e)) => {
+                log::error!("Database VACUUM failed: {}", e);
+                format!("Database maintenance failed: {}", e)
+            }
+            Err(e) => {
+                log::error!("Database VACUUM task failed: {}", e);
+                "Database maintenance failed.".to_string()
+            }
+        };
+        let response = Response {
+            text,
+            destination: Destination::Sender,
+            channel: ctx.channel,
+            reply_id: None,
+            data: None,
+        };
+        self.queue_responses(&ctx, &[response], my_node_id);
+    }
+
+    /// Read the config file again and swap in what can change while the
+    /// bot runs: the modules (with their `[modules.*]` and `[welcome]`
+    /// options and the dashboard's stored settings) and `[bot] admin_nodes`.
+    /// Everything else (connections, bridges, the dashboard, schedules,
+    /// alerts, rate limits) is held elsewhere and needs a restart.
+    async fn reload_config(&self) -> String {
+        let Some(path) = &self.config_path else {
+            return "No config file to reload.".to_string();
+        };
+        let mut config = match Config::load(path) {
+            Ok(config) => config,
+            Err(e) => {
+                log::warn!("Admin reload refused, config invalid: {}", e);
+                return format!("Config error, not reloading: {}", e);
+            }
+        };
+        match self.db.run(|db| db.settings()).await {
+            Ok(overrides) => settings::apply(&mut config, &overrides),
+            Err(e) => {
+                log::error!("Admin reload failed to read stored settings: {}", e);
+                return "Failed to read stored settings, not reloading.".to_string();
+            }
+        }
+        *self.registry.write().unwrap() = Arc::new(build_registry(&config, &self.hooks));
+        *self.admins.write().unwrap() = parse_admins(&config.bot.admin_nodes);
+        *self.modules_config.write().unwrap() = Arc::new(config);
+        log::info!("Config reloaded by admin: modules and admin_nodes");
+        "Reloaded modules and admin_nodes. Other changes need a restart.".to_string()
+    }
+}
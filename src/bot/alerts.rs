@@ -0,0 +1,379 @@
+use meshtastic::packet::PacketDestination;
+use meshtastic::types::{MeshChannel, NodeId};
+
+use crate::bridge::{BridgeEvent, MeshBridgeMessage};
+use crate::config::AlertRuleConfig;
+use crate::event_hooks::HookEvent;
+use crate::util::{format_ago, parse_node_id};
+
+use super::*;
+
+/// How long after its first packet a node still matches `new_node`.
+const NEW_NODE_WINDOW_SECS: i64 = 60 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Condition {
+    NodeSilent,
+    BatteryLow,
+    ChannelUtilization,
+    NewNode,
+    MeshQuiet,
+    ChannelQuiet,
+}
+
+impl Condition {
+    fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "node_silent" => Some(Condition::NodeSilent),
+            "battery_low" => Some(Condition::BatteryLow),
+            "channel_utilization" => Some(Condition::ChannelUtilization),
+            "new_node" => Some(Condition::NewNode),
+            "mesh_quiet" => Some(Condition::MeshQuiet),
+            "channel_quiet" => Some(Condition::ChannelQuiet),
+            _ => None,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Condition::NodeSilent => "node_silent",
+            Condition::BatteryLow => "battery_low",
+            Condition::ChannelUtilization => "channel_utilization",
+            Condition::NewNode => "new_node",
+            Condition::MeshQuiet => "mesh_quiet",
+            Condition::ChannelQuiet => "channel_quiet",
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Rule {
+    name: String,
+    condition: Condition,
+    node: Option<u32>,
+    threshold: f64,
+    /// Channel and window (hours) `channel_quiet` counts messages over
+    watch_channel: u32,
+    window_hours: u32,
+    broadcast: bool,
+    admins: bool,
+    bridges: bool,
+    webhooks: bool,
+    channel: u32,
+}
+
+/// Compiled `[[alerts]]` rules. Which rules are firing for which nodes is
+/// kept in the `alert_state` table, so a restart doesn't repeat alerts.
+pub(super) struct AlertRules {
+    rules: Vec<Rule>,
+}
+
+impl AlertRules {
+    pub(super) fn new(configs: &[AlertRuleConfig]) -> Self {
+        let mut rules: Vec<Rule> = Vec::new();
+        for c in configs {
+            let condition = match Condition::parse(&c.condition) {
+                Some(condition) => condition,
+                None => {
+                    log::warn!(
+                        "Ignoring alert {:?}: unknown condition {:?}",
+                        c.name,
+                        c.condition
+                    );
+                    continue;
+                }
+            };
+            if rules.iter().any(|r| r.name == c.name) {
+                log::warn!("Ignoring alert {:?}: name used twice", c.name);
+                continue;
+            }
+            let threshold = match (condition, c.threshold) {
+                (Condition::NewNode, _) => 0.0,
+                (_, Some(threshold)) => threshold,
+                (_, None) => {
+                    log::warn!("Ignoring alert {:?}: needs a `threshold`", c.name);
+                    continue;
+                }
+            };
+            let node = if c.node.is_empty() {
+                None
+            } else {
+                match parse_node_id(&c.node) {
+                    Some(id) => Some(id),
+                    None => {
+                        log::warn!("Ignoring alert {:?}: bad node {:?}", c.name, c.node);
+                        continue;
+                    }
+                }
+            };
+            if condition == Condition::NodeSilent && node.is_none() {
+                log::warn!("Ignoring alert {:?}: node_silent needs a `node`", c.name);
+                continue;
+            }
+            if condition == Condition::ChannelQuiet
+                && (c.watch_channel.is_none() || c.window_hours == 0)
+            {
+                log::warn!(
+                    "Ignoring alert {:?}: channel_quiet needs a `watch_channel` and `window_hours` > 0",
+                    c.name
+                );
+                continue;
+            }
+            let notifies = |target: &str| c.notify.iter().any(|n| n.eq_ignore_ascii_case(target));
+            rules.push(Rule {
+                name: c.name.clone(),
+                condition,
+                node,
+                threshold,
+                watch_channel: c.watch_channel.unwrap_or_default(),
+                window_hours: c.window_hours,
+                broadcast: notifies("broadcast"),
+                admins: notifies("admins"),
+                bridges: notifies("bridges"),
+                webhooks: notifies("webhooks"),
+                channel: c.channel,
+            });
+        }
+        Self { rules }
+    }
+
+    pub(super) fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+}
+
+/// Nodes `rule` currently holds for (0 for mesh-wide conditions), each
+/// with its alert text.
+fn alert_matches(
+    db: &Db,
+    rule: &Rule,
+    now: i64,
+) -> Result<Vec<(u32, String)>, Box<dyn std::error::Error + Send + Sync>> {
+    let watched = |node_id: u32| rule.node.is_none_or(|n| n == node_id);
+    let mut matches = Vec::new();
+    match rule.condition {
+        Condition::NodeSilent => {
+            let node_id = rule.node.unwrap_or_default();
+            if let Some(last_seen) = db.node_last_seen(node_id)? {
+                if (now - last_seen) as f64 > rule.threshold * 3600.0 {
+                    matches.push((
+                        node_id,
+                        format!(
+                            "{}: {} last heard {}",
+                            rule.name,
+                            db.get_node_name(node_id)?,
+                            format_ago(now - last_seen)
+                        ),
+                    ));
+                }
+            }
+        }
+        Condition::BatteryLow => {
+            for (node_id, telemetry) in db.latest_device_telemetry()? {
+                let Some(level) = telemetry.battery_level else {
+                    continue;
+                };
+                if watched(node_id) && (level as f64) < rule.threshold {
+                    matches.push((
+                        node_id,
+                        format!(
+                            "{}: {} battery {}% (below {}%)",
+                            rule.name,
+                            db.get_node_name(node_id)?,
+                            level,
+                            rule.threshold
+                        ),
+                    ));
+                }
+            }
+        }
+        Condition::ChannelUtilization => {
+            for (node_id, telemetry) in db.latest_device_telemetry()? {
+                let Some(utilization) = telemetry.channel_utilization else {
+                    continue;
+                };
+                if watched(node_id) && utilization > rule.threshold {
+                    matches.push((
+                        node_id,
+                        format!(
+                            "{}: {} channel utilization {:.1}% (above {}%)",
+                            rule.name,
+                            db.get_node_name(node_id)?,
+                            utilization,
+                            rule.threshold
+                        ),
+                    ));
+                }
+            }
+        }
+        Condition::NewNode => {
+            for node_id in db.nodes_first_seen_since(now - NEW_NODE_WINDOW_SECS)? {
+                if watched(node_id) {
+                    matches.push((
+                        node_id,
+                        format!("{}: new node {}", rule.name, db.get_node_name(node_id)?),
+                    ));
+                }
+            }
+        }
+        Condition::MeshQuiet => {
+            if let Some(last_packet) = db.last_packet_in_at()? {
+                if (now - last_packet) as f64 > rule.threshold * 3600.0 {
+                    matches.push((
+                        0,
+                        format!(
+                            "{}: nothing heard since {}",
+                            rule.name,
+                            format_ago(now - last_packet)
+                        ),
+                    ));
+                }
+            }
+        }
+        Condition::ChannelQuiet => {
+            let since = now - rule.window_hours as i64 * 3600;
+            // Not until the packet log reaches back over the whole window,
+            // so a fresh database doesn't read as a quiet channel
+            let covered = db.first_packet_in_at()?.is_some_and(|first| first <= since);
+            let count = db.text_messages_in_since(rule.watch_channel, since)?;
+            if covered && (count as f64) < rule.threshold {
+                matches.push((
+                    0,
+                    format!(
+                        "{}: {} message(s) on channel {} in {}h (expected {})",
+                        rule.name, count, rule.watch_channel, rule.window_hours, rule.threshold
+                    ),
+                ));
+            }
+        }
+    }
+    Ok(matches)
+}
+
+/// Bring `rule`'s stored state up to date: nodes it now holds for are
+/// marked fired and those it stopped holding for cleared. Returns the nodes
+/// it started to hold for, with their alert text.
+fn update_alert_state(db: &Db, rule: &Rule, now: i64) -> Vec<(u32, String)> {
+    let matches = match alert_matches(db, rule, now) {
+        Ok(matches) => matches,
+        Err(e) => {
+            log::error!("Failed to evaluate alert {:?}: {}", rule.name, e);
+            return Vec::new();
+        }
+    };
+    let firing = match db.alert_firing_nodes(&rule.name) {
+        Ok(firing) => firing,
+        Err(e) => {
+            log::error!("Failed to load alert state: {}", e);
+            return Vec::new();
+        }
+    };
+    let mut started = Vec::new();
+    for (node_id, text) in &matches {
+        if firing.contains(node_id) {
+            continue;
+        }
+        if let Err(e) = db.set_alert_fired(&rule.name, *node_id, Some(now)) {
+            log::error!("Failed to store alert state: {}", e);
+            continue;
+        }
+        started.push((*node_id, text.clone()));
+    }
+    for node_id in firing {
+        if matches.iter().any(|(n, _)| *n == node_id) {
+            continue;
+        }
+        log::info!("Alert {:?} cleared for !{:08x}", rule.name, node_id);
+        if let Err(e) = db.set_alert_fired(&rule.name, node_id, None) {
+            log::error!("Failed to clear alert state: {}", e);
+        }
+    }
+    started
+}
+
+impl Bot {
+    /// Check every `[[alerts]]` rule against the database, notifying for
+    /// conditions that started to hold and clearing those that stopped.
+    pub(super) async fn evaluate_alerts(&self, my_node_id: u32, now: i64) {
+        for rule in &self.alert_rules.rules {
+            let owned_rule = rule.clone();
+            let started = match self
+                .db
+                .run(move |db| Ok(update_alert_state(db, &owned_rule, now)))
+                .await
+            {
+                Ok(started) => started,
+                Err(e) => {
+                    log::error!("Failed to evaluate alert {:?}: {}", rule.name, e);
+                    continue;
+                }
+            };
+            for (node_id, text) in &started {
+                self.send_alert(my_node_id, rule, *node_id, text).await;
+            }
+        }
+    }
+
+    /// Notify `rule`'s targets that it started to hold for `node_id`.
+    async fn send_alert(&self, my_node_id: u32, rule: &Rule, node_id: u32, text: &str) {
+        log::warn!("Alert: {}", text);
+
+        if rule.webhooks {
+            self.hooks.emit(HookEvent::Alert {
+                rule: rule.name.clone(),
+                condition: rule.condition.as_str().to_string(),
+                node: (node_id != 0).then(|| format!("!{:08x}", node_id)),
+                text: text.to_string(),
+            });
+        }
+
+        if rule.bridges {
+            let (sender_id, sender_name) = if node_id == 0 {
+                (my_node_id, self.config.bot.name.clone())
+            } else {
+                (node_id, self.node_name(node_id).await)
+            };
+            self.bridge.relay(MeshBridgeMessage {
+                sender_id,
+                sender_name,
+                text: text.to_string(),
+                channel: rule.channel,
+                is_dm: false,
+                event: BridgeEvent::Alert,
+                data: None,
+                packet_id: None,
+            });
+        }
+
+        let channel = match MeshChannel::new(rule.channel) {
+            Ok(ch) => ch,
+            Err(e) => {
+                log::error!("Invalid channel {}: {}", rule.channel, e);
+                return;
+            }
+        };
+        let mut destinations = Vec::new();
+        if rule.broadcast {
+            destinations.push(None);
+        }
+        if rule.admins {
+            destinations.extend(self.admin_ids().into_iter().map(Some));
+        }
+        for to_node in destinations {
+            self.queue_message(OutgoingMeshMessage {
+                kind: OutgoingKind::Text,
+                text: text.to_string(),
+                destination: match to_node {
+                    Some(id) => PacketDestination::Node(NodeId::from(id)),
+                    None => PacketDestination::Broadcast,
+                },
+                channel,
+                from_node: my_node_id,
+                to_node,
+                mesh_channel: rule.channel,
+                reply_id: None,
+                expires_at: None,
+            });
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use chrono::Utc;
+use meshtastic::packet::PacketDestination;
+use meshtastic::types::MeshChannel;
+
+use super::*;
+
+/// `bot_state` key holding when the last banner was queued (unix seconds).
+const BANNER_SENT_KEY: &str = "startup_banner_sent_at";
+
+impl Bot {
+    /// Broadcast the "bot online" banner, unless disabled or one was already
+    /// sent within `min_interval_hours`.
+    pub(super) async fn send_startup_banner(&self, my_node_id: u32) {
+        let banner = &self.config.startup_banner;
+        if !banner.enabled {
+            return;
+        }
+
+        let now = Utc::now().timestamp();
+        let last_sent = self
+            .db
+            .run(|db| db.get_state(BANNER_SENT_KEY))
+            .await
+            .unwrap_or(None)
+            .and_then(|v| v.parse::<i64>().ok());
+        if let Some(last_sent) = last_sent {
+            if now - last_sent < banner.min_interval_hours as i64 * 3600 {
+                log::info!("Startup banner skipped, last one sent at {}", last_sent);
+                return;
+            }
+        }
+
+        let channel = match MeshChannel::new(banner.channel) {
+            Ok(ch) => ch,
+            Err(e) => {
+                log::error!("Invalid startup banner channel {}: {}", banner.channel, e);
+                return;
+            }
+        };
+        let text = banner
+            .message
+            .replace("{name}", &self.config.bot.name)
+            .replace("{version}", env!("CARGO_PKG_VERSION"))
+            .replace("{prefix}", &self.config.bot.command_prefix);
+        log::info!("Broadcasting startup banner: {}", text);
+        self.queue_message(OutgoingMeshMessage {
+            kind: OutgoingKind::Text,
+            text,
+            destination: PacketDestination::Broadcast,
+            channel,
+            from_node: my_node_id,
+            to_node: None,
+            mesh_channel: banner.channel,
+            reply_id: None,
+            expires_at: None,
+        });
+
+        let sent_at = now.to_string();
+        if let Err(e) = self
+            .db
+            .run(move |db| db.set_state(BANNER_SENT_KEY, &sent_at))
+            .await
+        {
+            log::error!("Failed to record startup banner: {}", e);
+        }
+    }
+}
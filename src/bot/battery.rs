@@ -0,0 +1,154 @@
+use meshtastic::packet::PacketDestination;
+use meshtastic::types::{MeshChannel, NodeId};
+
+use crate::bridge::{BridgeEvent, MeshBridgeMessage};
+use crate::config::BatteryAlertsConfig;
+use crate::util::parse_node_id;
+
+use super::*;
+
+/// Meshtastic reports a battery level above 100 when running on external power.
+const EXTERNAL_POWER_LEVEL: u32 = 101;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum BatteryAlert {
+    Low,
+    Critical,
+}
+
+impl BatteryAlert {
+    fn as_str(self) -> &'static str {
+        match self {
+            BatteryAlert::Low => "low",
+            BatteryAlert::Critical => "critical",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "low" => Some(BatteryAlert::Low),
+            "critical" => Some(BatteryAlert::Critical),
+            _ => None,
+        }
+    }
+
+    /// Given the alert already raised for a node and its new battery level,
+    /// returns the alert state to keep and whether to notify. An alert only
+    /// clears once the level climbs `hysteresis_pct` above its threshold.
+    pub(super) fn next(
+        current: Option<Self>,
+        level: u32,
+        config: &BatteryAlertsConfig,
+    ) -> (Option<Self>, bool) {
+        let recovered = |threshold: u32| level >= threshold + config.hysteresis_pct;
+        match current {
+            _ if level >= EXTERNAL_POWER_LEVEL => (None, false),
+            Some(BatteryAlert::Critical) if !recovered(config.critical_pct) => {
+                (Some(BatteryAlert::Critical), false)
+            }
+            Some(_) if level < config.critical_pct => (Some(BatteryAlert::Critical), true),
+            Some(_) if !recovered(config.low_pct) => (Some(BatteryAlert::Low), false),
+            Some(_) => (None, false),
+            None if level < config.critical_pct => (Some(BatteryAlert::Critical), true),
+            None if level < config.low_pct => (Some(BatteryAlert::Low), true),
+            None => (None, false),
+        }
+    }
+}
+
+impl Bot {
+    /// Check a node's reported battery level against `[battery_alerts]` and
+    /// notify admins and bridges when it crosses the low or critical level.
+    pub(super) async fn check_battery(
+        &self,
+        my_node_id: u32,
+        node_id: u32,
+        level: u32,
+        channel: u32,
+    ) {
+        let config = &self.config.battery_alerts;
+        if !config.enabled {
+            return;
+        }
+        if !config.nodes.is_empty()
+            && !config
+                .nodes
+                .iter()
+                .any(|n| parse_node_id(n) == Some(node_id))
+        {
+            return;
+        }
+
+        let owned_config = Arc::clone(&self.config);
+        let state = self
+            .db
+            .run(move |db| {
+                let current = db
+                    .battery_alert_level(node_id)?
+                    .as_deref()
+                    .and_then(BatteryAlert::parse);
+                let (next, notify) =
+                    BatteryAlert::next(current, level, &owned_config.battery_alerts);
+                if next != current {
+                    if let Err(e) =
+                        db.set_battery_alert_level(node_id, next.map(BatteryAlert::as_str), level)
+                    {
+                        log::error!("Failed to record battery alert state: {}", e);
+                    }
+                }
+                Ok((next, notify))
+            })
+            .await;
+        let next = match state {
+            Ok((next, true)) => next,
+            Ok((_, false)) => return,
+            Err(e) => {
+                log::error!("Failed to read battery alert state: {}", e);
+                return;
+            }
+        };
+
+        let name = self.node_name(node_id).await;
+        let severity = match next {
+            Some(BatteryAlert::Critical) => "critical",
+            _ => "low",
+        };
+        let text = format!("Battery {}: {} at {}%", severity, name, level);
+        log::warn!("{} (!{:08x})", text, node_id);
+
+        if config.notify_bridges {
+            self.bridge.relay(MeshBridgeMessage {
+                sender_id: node_id,
+                sender_name: name,
+                text: text.clone(),
+                channel,
+                is_dm: false,
+                event: BridgeEvent::BatteryLow,
+                data: None,
+                packet_id: None,
+            });
+        }
+        if config.notify_admins {
+            let mesh_channel = match MeshChannel::new(channel) {
+                Ok(ch) => ch,
+                Err(e) => {
+                    log::error!("Invalid channel {}: {}", channel, e);
+                    return;
+                }
+            };
+            for admin in self.admin_ids() {
+                self.queue_message(OutgoingMeshMessage {
+                    kind: OutgoingKind::Text,
+                    text: text.clone(),
+                    destination: PacketDestination::Node(NodeId::from(admin)),
+                    channel: mesh_channel,
+                    from_node: my_node_id,
+                    to_node: Some(admin),
+                    mesh_channel: channel,
+                    reply_id: None,
+                    expires_at: None,
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use chrono::{DateTime, Local, Timelike};
+use meshtastic::packet::PacketDestination;
+use meshtastic::types::MeshChannel;
+use rand::Rng;
+
+use super::*;
+use crate::config::BeaconConfig;
+
+/// Radio telemetry older than this says nothing about the channel now.
+const TELEMETRY_MAX_AGE_SECS: i64 = 2 * 60 * 60;
+
+/// `bot_state` key holding when a beacon is next due (unix seconds). Keyed
+/// by content like `[[schedule]]` runs, so an edited beacon starts fresh.
+fn next_due_key(beacon: &BeaconConfig) -> String {
+    format!(
+        "beacon_next_due:{}|{}|{}",
+        beacon.interval_mins, beacon.channel, beacon.text
+    )
+}
+
+fn in_active_hours(beacon: &BeaconConfig, hour: u32) -> bool {
+    if beacon.start_hour < beacon.end_hour {
+        hour >= beacon.start_hour && hour < beacon.end_hour
+    } else {
+        hour >= beacon.start_hour || hour < beacon.end_hour
+    }
+}
+
+/// Seconds until the beacon after one sent now: the interval plus up to
+/// `jitter_pct` of it.
+fn next_wait_secs(beacon: &BeaconConfig) -> i64 {
+    let base = beacon.interval_mins.saturating_mul(60) as f64;
+    let jitter_pct = if beacon.jitter_pct.is_finite() {
+        beacon.jitter_pct.clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let jitter = if jitter_pct > 0.0 {
+        rand::thread_rng().gen_range(0.0..=base * jitter_pct)
+    } else {
+        0.0
+    };
+    (base + jitter) as i64
+}
+
+impl Bot {
+    /// Queue the `[[beacons]]` that are due at `now`. A beacon is due
+    /// right away the first time it is seen; after that its next time is
+    /// kept in the database, so restarts don't send it early. A due beacon
+    /// outside its hours, or while the radio reports a busy channel, waits
+    /// for a later check.
+    pub(super) async fn run_beacons(&self, my_node_id: u32, now: DateTime<Local>) {
+        let ts = now.timestamp();
+        for beacon in &self.config.beacons {
+            let key = next_due_key(beacon);
+            let state_key = key.clone();
+            let next_due = self
+                .db
+                .run(move |db| db.get_state(&state_key))
+                .await
+                .unwrap_or(None)
+                .and_then(|v| v.parse::<i64>().ok())
+                .unwrap_or(0);
+            if ts < next_due || !in_active_hours(beacon, now.hour()) {
+                continue;
+            }
+            if let Some(reason) = self.airtime_busy(my_node_id, beacon, ts).await {
+                log::debug!("Holding beacon {:?}: {}", beacon.text, reason);
+                continue;
+            }
+            let channel = match MeshChannel::new(beacon.channel) {
+                Ok(channel) => channel,
+                Err(e) => {
+                    log::error!("Invalid beacon channel {}: {}", beacon.channel, e);
+                    continue;
+                }
+            };
+            let (state_key, next_due) = (key.clone(), ts + next_wait_secs(beacon));
+            if let Err(e) = self
+                .db
+                .run(move |db| db.set_state(&state_key, &next_due.to_string()))
+                .await
+            {
+                log::error!("Failed to record beacon state {}: {}", key, e);
+                continue;
+            }
+            log::info!("Queueing beacon on channel {}", beacon.channel);
+            self.queue_message(OutgoingMeshMessage {
+                kind: OutgoingKind::Text,
+                text: beacon.text.clone(),
+                destination: PacketDestination::Broadcast,
+                channel,
+                from_node: my_node_id,
+                to_node: None,
+                mesh_channel: beacon.channel,
+                reply_id: None,
+                expires_at: None,
+            });
+        }
+    }
+
+    /// Why the channel is too busy for `beacon`, going by the bot's radio's
+    /// latest recent device telemetry; `None` when it may go out.
+    async fn airtime_busy(
+        &self,
+        my_node_id: u32,
+        beacon: &BeaconConfig,
+        now: i64,
+    ) -> Option<String> {
+        let telemetry = match self.db.run(|db| db.latest_device_telemetry()).await {
+            Ok(rows) => rows
+                .into_iter()
+                .find(|(node_id, t)| {
+                    *node_id == my_node_id && now - t.timestamp <= TELEMETRY_MAX_AGE_SECS
+                })
+                .map(|(_, t)| t)?,
+            Err(e) => {
+                log::error!("Failed to load telemetry for beacons: {}", e);
+                return None;
+            }
+        };
+        if let Some(utilization) = telemetry.channel_utilization {
+            if beacon.max_channel_utilization > 0.0 && utilization >= beacon.max_channel_utilization
+            {
+                return Some(format!("channel utilization {:.1}%", utilization));
+            }
+        }
+        if let Some(air_util_tx) = telemetry.air_util_tx {
+            if beacon.max_air_util_tx > 0.0 && air_util_tx >= beacon.max_air_util_tx {
+                return Some(format!("transmit airtime {:.1}%", air_util_tx));
+            }
+        }
+        None
+    }
+}
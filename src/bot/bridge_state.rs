@@ -1,13 +1,73 @@
-use crate::bridge::{MeshMessageSender, OutgoingMessageReceiver};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use crate::bridge::{MeshBridgeMessage, MeshMessageSender, OutgoingMessageReceiver};
+use crate::bridge_filter::BridgeFilter;
+
+/// DM passthrough conversations kept before the oldest is forgotten.
+const MAX_DM_SESSIONS: usize = 256;
+
+/// Relayed in place of an alert's text that a block rule matched.
+const ALERT_TEXT_WITHHELD: &str = "(text withheld by a bridge rule)";
+
+/// A mesh node whose DMs are passed through to the operator chat.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) struct DmSession {
+    pub(super) node_id: u32,
+    /// Channel the node last DMed us on; replies go out on it.
+    pub(super) channel: u32,
+}
 
 pub(super) struct BridgeState {
     tx: Option<MeshMessageSender>,
     rx: Option<tokio::sync::Mutex<OutgoingMessageReceiver>>,
+    filter: Option<Arc<BridgeFilter>>,
+    /// DM passthrough conversations by number. Numbers only grow, so the
+    /// smallest one is the oldest.
+    dm_sessions: Mutex<BTreeMap<u32, DmSession>>,
 }
 
 impl BridgeState {
     pub(super) fn new() -> Self {
-        Self { tx: None, rx: None }
+        Self {
+            tx: None,
+            rx: None,
+            filter: None,
+            dm_sessions: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Conversation number for DMs from `node_id`, starting one if needed.
+    pub(super) fn dm_session(&self, node_id: u32, channel: u32) -> u32 {
+        let mut sessions = self.dm_sessions.lock().unwrap();
+        if let Some((&number, session)) = sessions.iter_mut().find(|(_, s)| s.node_id == node_id) {
+            session.channel = channel;
+            return number;
+        }
+        let number = sessions.keys().next_back().map_or(1, |n| n + 1);
+        sessions.insert(number, DmSession { node_id, channel });
+        if sessions.len() > MAX_DM_SESSIONS {
+            sessions.pop_first();
+        }
+        number
+    }
+
+    /// The node behind a conversation number, if it is still known.
+    pub(super) fn dm_session_node(&self, number: u32) -> Option<DmSession> {
+        self.dm_sessions.lock().unwrap().get(&number).copied()
+    }
+
+    pub(super) fn set_filter(&mut self, filter: Arc<BridgeFilter>) {
+        self.filter = Some(filter);
+    }
+
+    /// Run mesh text through the configured bridge rules.
+    /// Returns `None` if the message must not be relayed.
+    fn filter_text(&self, text: &str) -> Option<String> {
+        match &self.filter {
+            Some(filter) => filter.apply(text),
+            None => Some(text.to_string()),
+        }
     }
 
     pub(super) fn set_channels(&mut self, tx: MeshMessageSender, rx: OutgoingMessageReceiver) {
@@ -15,8 +75,41 @@ impl BridgeState {
         self.rx = Some(tokio::sync::Mutex::new(rx));
     }
 
-    pub(super) fn tx(&self) -> Option<&MeshMessageSender> {
-        self.tx.as_ref()
+    /// Send an event to the bridges, if any are listening, with its text run
+    /// through the bridge rules. A blocked message is dropped, except for
+    /// alerts, which still go out with the text withheld.
+    pub(super) fn relay(&self, mut msg: MeshBridgeMessage) {
+        let Some(tx) = &self.tx else {
+            return;
+        };
+        match self.filter_text(&msg.text) {
+            Some(text) => msg.text = text,
+            None if msg.is_alert() => {
+                log::info!(
+                    "Bridge rule blocked the text of a {:?} alert from {}",
+                    msg.event,
+                    msg.sender_name
+                );
+                msg.text = ALERT_TEXT_WITHHELD.to_string();
+            }
+            None => {
+                log::info!(
+                    "Bridge rule blocked message from {} [packet_id={:?}]",
+                    msg.sender_name,
+                    msg.packet_id
+                );
+                return;
+            }
+        }
+        let alert = msg.is_alert().then(|| format!("{:?}", msg.event));
+        if tx.send(msg).is_err() {
+            match alert {
+                Some(event) => {
+                    log::warn!("{} alert not relayed: no bridge receivers listening", event)
+                }
+                None => log::debug!("No bridge receivers listening"),
+            }
+        }
     }
 
     pub(super) fn rx(&self) -> Option<&tokio::sync::Mutex<OutgoingMessageReceiver>> {
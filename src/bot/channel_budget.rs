@@ -0,0 +1,71 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::config::ChannelBudgetConfig;
+
+use super::outgoing::{OutgoingKind, OutgoingMeshMessage};
+
+const WINDOW: Duration = Duration::from_secs(60 * 60);
+
+/// `[[channel_budgets]]`: how many messages each radio may still send on a
+/// channel, going by what it sent in the last hour.
+pub(super) struct ChannelBudgets {
+    max_per_hour: HashMap<u32, usize>,
+    /// Send times by (radio, channel), oldest first.
+    sent: Mutex<HashMap<(u32, u32), VecDeque<Instant>>>,
+}
+
+impl ChannelBudgets {
+    pub(super) fn new(configs: &[ChannelBudgetConfig]) -> Self {
+        Self {
+            max_per_hour: configs
+                .iter()
+                .map(|c| (c.channel, c.max_per_hour))
+                .collect(),
+            sent: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether `msg` may go out now without exceeding its channel's budget.
+    pub(super) fn has_room(&self, msg: &OutgoingMeshMessage, now: Instant) -> bool {
+        let Some(&max) = self.max_per_hour.get(&msg.mesh_channel) else {
+            return true;
+        };
+        let mut sent = self.sent.lock().unwrap();
+        let Some(times) = sent.get_mut(&(msg.from_node, msg.mesh_channel)) else {
+            return true;
+        };
+        while times
+            .front()
+            .is_some_and(|t| now.saturating_duration_since(*t) >= WINDOW)
+        {
+            times.pop_front();
+        }
+        times.len() < max
+    }
+
+    pub(super) fn record(&self, msg: &OutgoingMeshMessage, now: Instant) {
+        if self.max_per_hour.contains_key(&msg.mesh_channel) {
+            self.sent
+                .lock()
+                .unwrap()
+                .entry((msg.from_node, msg.mesh_channel))
+                .or_default()
+                .push_back(now);
+        }
+    }
+}
+
+/// Whether someone is waiting for `msg` (a reply, a DM, chat from a
+/// bridge, an `!admin node` action), so over budget it waits for room instead of being dropped.
+pub(super) fn waits_for_budget(msg: &OutgoingMeshMessage) -> bool {
+    msg.reply_id.is_some()
+        || (msg.to_node.is_some() && matches!(msg.kind, OutgoingKind::Text))
+        || matches!(
+            msg.kind,
+            OutgoingKind::RetryText { .. }
+                | OutgoingKind::BridgeText { .. }
+                | OutgoingKind::NodeAdmin { .. }
+        )
+}
@@ -0,0 +1,43 @@
+//! The time source behind the bot's queue expiry, probe and command
+//! cooldowns and startup grace period. Tests swap in a `ManualClock` to
+//! step through hours of scheduling without sleeping.
+
+use std::time::Instant;
+
+pub(super) trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+}
+
+pub(super) struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A clock that only moves when told to.
+#[cfg(test)]
+pub(super) struct ManualClock {
+    now: std::sync::Mutex<Instant>,
+}
+
+#[cfg(test)]
+impl ManualClock {
+    pub(super) fn new() -> Self {
+        Self {
+            now: std::sync::Mutex::new(Instant::now()),
+        }
+    }
+
+    pub(super) fn advance(&self, by: std::time::Duration) {
+        *self.now.lock().unwrap() += by;
+    }
+}
+
+#[cfg(test)]
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        *self.now.lock().unwrap()
+    }
+}
@@ -1,7 +1,48 @@
 use crate::message::{Destination, MessageContext, Response};
+use crate::util::levenshtein;
 
 use super::*;
 
+/// Appended to a command sent on a channel to get the reply on that channel.
+const PUBLIC_FLAG: &str = "--public";
+
+/// Where a command's replies addressed to the sender end up.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ReplyRoute {
+    /// As the module addressed them (the sender by DM).
+    AsIs,
+    /// On the channel the command came from.
+    Public,
+    /// Only ever by DM, even replies a module broadcasts.
+    DmOnly,
+}
+
+/// `bot.mqtt_commands`: what to do with commands heard via MQTT.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum MqttCommandPolicy {
+    Reply,
+    DmOnly,
+    Ignore,
+}
+
+impl MqttCommandPolicy {
+    fn from_config(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "dm" => MqttCommandPolicy::DmOnly,
+            "ignore" => MqttCommandPolicy::Ignore,
+            _ => MqttCommandPolicy::Reply,
+        }
+    }
+
+    fn action(self) -> &'static str {
+        match self {
+            MqttCommandPolicy::Reply => "replied",
+            MqttCommandPolicy::DmOnly => "dm",
+            MqttCommandPolicy::Ignore => "ignored",
+        }
+    }
+}
+
 impl Bot {
     pub(super) async fn dispatch_command_from_text(
         &self,
@@ -15,34 +56,169 @@ impl Bot {
             None => return,
         };
 
-        // Rate limit check
-        if !self.rate_limiter.check(ctx.sender_id) {
+        // Rate limit check; emergencies are never throttled
+        let is_emergency = self
+            .config
+            .emergency
+            .matched_keyword(trimmed_text)
+            .is_some();
+
+        // Replies to far-away MQTT nodes still go out over RF
+        let mqtt_policy = MqttCommandPolicy::from_config(&self.config.bot.mqtt_commands);
+        if ctx.via_mqtt && !is_emergency && self.is_known_command(command) {
+            let (sender_id, owned_command) = (ctx.sender_id, command.to_string());
+            let action = mqtt_policy.action();
+            if let Err(e) = self
+                .db
+                .run(move |db| db.log_mqtt_command(sender_id, &owned_command, action))
+                .await
+            {
+                log::error!("Failed to record MQTT command: {}", e);
+            }
+            if mqtt_policy == MqttCommandPolicy::Ignore {
+                log::info!(
+                    "Ignoring command from {} heard via MQTT: {}",
+                    ctx.sender_name,
+                    command
+                );
+                return;
+            }
+        }
+        let registry = self.registry();
+        let rate_limiter = registry
+            .find_by_command(command)
+            .and_then(|m| self.module_rate_limiters.get(m.name()))
+            .unwrap_or(&self.rate_limiter);
+        if !is_emergency && !rate_limiter.check(ctx.sender_id, self.now()) {
             log::warn!("Rate limited: {} ({})", ctx.sender_name, ctx.sender_id);
+            self.metrics.rate_limited();
+            return;
+        }
+        if !is_emergency && self.ignores_traceroute_abuser(ctx.sender_id) {
+            log::warn!(
+                "Ignoring command from traceroute flooder {} ({})",
+                ctx.sender_name,
+                ctx.sender_id
+            );
             return;
         }
 
+        let (args, force_public) = Self::take_public_flag(args);
+        let args = args.as_str();
+        let module_name = registry.find_by_command(command).map(|m| m.name());
+        let route = if ctx.via_mqtt && mqtt_policy == MqttCommandPolicy::DmOnly {
+            ReplyRoute::DmOnly
+        } else if !is_dm && (force_public || self.replies_publicly(module_name)) {
+            ReplyRoute::Public
+        } else {
+            ReplyRoute::AsIs
+        };
+
         // Special handling for help: generate text from registry
         if command == "help" {
-            let help_text = self.generate_help_text();
+            let help_text = self.generate_help_text(args);
             let responses = vec![Response {
                 text: help_text,
                 destination: Destination::Sender,
                 channel: ctx.channel,
                 reply_id: Some(ctx.packet_id),
+                data: None,
+            }];
+            self.queue_command_responses(ctx, responses, route, my_node_id);
+            return;
+        }
+
+        // Store & Forward requests go out as StoreForwardApp packets, so the
+        // bot handles them rather than a module
+        if command == "sf" && self.config.store_forward.enabled {
+            let responses = vec![Response {
+                text: self
+                    .store_forward_command(my_node_id, ctx, args, is_dm)
+                    .await,
+                destination: Destination::Sender,
+                channel: ctx.channel,
+                reply_id: Some(ctx.packet_id),
+                data: None,
             }];
-            self.queue_responses(ctx, &responses, my_node_id);
+            self.queue_command_responses(ctx, responses, route, my_node_id);
             return;
         }
 
-        let module = match self.registry.find_by_command(command) {
+        // Admin-only commands (`!admin`, `!poll start`, adding a `!note`):
+        // only from `[bot] admin_nodes`, only by DM, and PKI-encrypted with the
+        // key the node announced, so a spoofed node ID isn't enough
+        if registry
+            .find_by_command(command)
+            .is_some_and(|m| m.admin_only(command, args))
+        {
+            if !self.is_verified_admin(ctx, is_dm).await {
+                log::warn!(
+                    "Refused admin command {} from {} ({:08x})",
+                    command,
+                    ctx.sender_name,
+                    ctx.sender_id
+                );
+                // Stay quiet about `!admin` itself
+                if command != "admin" {
+                    let responses = vec![Response {
+                        text: "Only admins can do that, by DM.".to_string(),
+                        destination: Destination::Sender,
+                        channel: ctx.channel,
+                        reply_id: Some(ctx.packet_id),
+                        data: None,
+                    }];
+                    self.queue_responses(ctx, &responses, my_node_id);
+                }
+                return;
+            }
+            if command == "admin" {
+                if let Some(responses) = self.admin_command(my_node_id, ctx, args).await {
+                    self.queue_responses(ctx, &responses, my_node_id);
+                    return;
+                }
+            }
+        }
+
+        let module = match registry.find_by_command(command) {
             Some(m) => m,
-            None => return,
+            None => {
+                if let Some(suggestion) = self.suggest_command(command) {
+                    let responses = vec![Response {
+                        text: format!(
+                            "Unknown command, did you mean {}{}?",
+                            self.config.bot.command_prefix, suggestion
+                        ),
+                        destination: Destination::Sender,
+                        channel: ctx.channel,
+                        reply_id: Some(ctx.packet_id),
+                        data: None,
+                    }];
+                    self.queue_responses(ctx, &responses, my_node_id);
+                }
+                return;
+            }
         };
 
         if !module.scope().allows(is_dm) {
             return;
         }
 
+        // `!trace` sends a TracerouteApp packet and reports back when the
+        // reply arrives, so the bot handles it once the node is known
+        if command == "trace" {
+            if let Some(responses) = self.trace_command(my_node_id, ctx, args).await {
+                self.queue_command_responses(ctx, responses, route, my_node_id);
+                return;
+            }
+        }
+
+        // The outgoing queue is the bot's, so it answers `!queue`
+        if command == "queue" {
+            let responses = self.queue_command(ctx);
+            self.queue_command_responses(ctx, responses, route, my_node_id);
+            return;
+        }
+
         match module.handle_command(command, args, ctx, &self.db).await {
             Ok(Some(mut responses)) => {
                 // Tag the first response as a reply to the incoming message
@@ -51,16 +227,75 @@ impl Bot {
                         first.reply_id = Some(ctx.packet_id);
                     }
                 }
-                self.queue_responses(ctx, &responses, my_node_id);
+                self.queue_command_responses(ctx, responses, route, my_node_id);
             }
             Ok(None) => {}
             Err(e) => {
                 log::error!("Module {} error: {}", module.name(), e);
             }
         }
+        // Reading mail leaves its senders owed a read receipt
+        if module.name() == "mail" && self.config.mail.read_receipts {
+            self.load_owed_deliveries().await;
+        }
+    }
+
+    /// Remove a `--public` token from the arguments, reporting whether it was there.
+    fn take_public_flag(args: &str) -> (String, bool) {
+        let mut found = false;
+        let rest: Vec<&str> = args
+            .split_whitespace()
+            .filter(|word| {
+                let flag = word.eq_ignore_ascii_case(PUBLIC_FLAG);
+                found |= flag;
+                !flag
+            })
+            .collect();
+        if found {
+            (rest.join(" "), true)
+        } else {
+            (args.to_string(), false)
+        }
+    }
+
+    /// `[modules.<name>] public_replies` if set, otherwise `bot.public_replies`.
+    fn replies_publicly(&self, module_name: Option<&str>) -> bool {
+        module_name
+            .and_then(|name| self.config.modules.get(name))
+            .and_then(|m| m.public_replies)
+            .unwrap_or(self.config.bot.public_replies)
+    }
+
+    /// Queue a command's responses along `route`.
+    fn queue_command_responses(
+        &self,
+        ctx: &MessageContext,
+        mut responses: Vec<Response>,
+        route: ReplyRoute,
+        my_node_id: u32,
+    ) {
+        for response in &mut responses {
+            match (route, &response.destination) {
+                (ReplyRoute::Public, Destination::Sender) => {
+                    response.destination = Destination::Broadcast;
+                }
+                (ReplyRoute::DmOnly, Destination::Broadcast) => {
+                    response.destination = Destination::Sender;
+                }
+                _ => {}
+            }
+        }
+        self.queue_responses(ctx, &responses, my_node_id);
+    }
+
+    /// Whether `command` is answered by the bot or a registered module.
+    fn is_known_command(&self, command: &str) -> bool {
+        command == "help"
+            || (command == "sf" && self.config.store_forward.enabled)
+            || self.registry().find_by_command(command).is_some()
     }
 
-    fn parse_command<'a>(&self, trimmed_text: &'a str) -> Option<(&'a str, &'a str)> {
+    pub(super) fn parse_command<'a>(&self, trimmed_text: &'a str) -> Option<(&'a str, &'a str)> {
         let prefix = &self.config.bot.command_prefix;
         let (raw_command, args) = match trimmed_text.split_once(' ') {
             Some((cmd, rest)) => (cmd, rest.trim()),
@@ -72,10 +307,52 @@ impl Bot {
             .map(|cmd| (cmd, args))
     }
 
-    pub(super) fn generate_help_text(&self) -> String {
+    /// The known command closest to a mistyped one, if suggestions are on and
+    /// one is within two edits.
+    fn suggest_command(&self, command: &str) -> Option<String> {
+        if !self.config.bot.suggest_commands || command.is_empty() {
+            return None;
+        }
+        let command = command.to_lowercase();
+        let store_forward: &[&str] = if self.config.store_forward.enabled {
+            &["sf"]
+        } else {
+            &[]
+        };
+        self.registry()
+            .all()
+            .iter()
+            .flat_map(|m| m.commands().iter().copied())
+            .chain(["help"])
+            .chain(store_forward.iter().copied())
+            .map(|known| (levenshtein(&command, known), known))
+            .filter(|(distance, known)| *distance <= 2 && *distance < known.len())
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, known)| known.to_string())
+    }
+
+    /// Help text in the language named by `args` if it has translations,
+    /// otherwise in `[help] language`.
+    pub(super) fn generate_help_text(&self, args: &str) -> String {
+        let help = &self.config.help;
+        let requested = args.trim().to_lowercase();
+        let language = if help.translations.contains_key(&requested) {
+            requested.as_str()
+        } else {
+            help.language.as_str()
+        };
+        let translations = help.translations.get(language);
+        let translate = |key: &str, default: &str| {
+            translations
+                .and_then(|t| t.get(key))
+                .map(String::as_str)
+                .unwrap_or(default)
+                .to_string()
+        };
+
         let prefix = &self.config.bot.command_prefix;
         let mut lines = Vec::new();
-        for module in self.registry.all() {
+        for module in self.registry().all() {
             let cmds = module.commands();
             if !cmds.is_empty() {
                 let cmd_str = cmds
@@ -83,11 +360,22 @@ impl Bot {
                     .map(|c| format!("{}{}", prefix, c))
                     .collect::<Vec<_>>()
                     .join(", ");
-                lines.push(format!("{} - {}", cmd_str, module.description()));
+                lines.push(format!(
+                    "{} - {}",
+                    cmd_str,
+                    translate(module.name(), module.description())
+                ));
             }
         }
+        if self.config.store_forward.enabled {
+            lines.push(format!(
+                "{}sf - {}",
+                prefix,
+                translate("sf", "Store & Forward history")
+            ));
+        }
         if lines.is_empty() {
-            "No commands available.".to_string()
+            translate("no_commands", "No commands available.")
         } else {
             lines.join("\n")
         }
@@ -1,5 +1,9 @@
+use tokio::sync::broadcast;
+
+use crate::live::LiveEvent;
+
 pub(super) struct DashboardNotifier {
-    tx: Option<tokio::sync::broadcast::Sender<()>>,
+    tx: Option<broadcast::Sender<LiveEvent>>,
 }
 
 impl DashboardNotifier {
@@ -7,13 +11,19 @@ impl DashboardNotifier {
         Self { tx: None }
     }
 
-    pub(super) fn set_sender(&mut self, tx: tokio::sync::broadcast::Sender<()>) {
+    pub(super) fn set_sender(&mut self, tx: broadcast::Sender<LiveEvent>) {
         self.tx = Some(tx);
     }
 
     pub(super) fn notify(&self) {
-        if let Some(tx) = &self.tx {
-            let _ = tx.send(());
+        self.publish(|| LiveEvent::Refresh);
+    }
+
+    /// Send an event to connected dashboards, building it only if one is
+    /// listening.
+    pub(super) fn publish(&self, event: impl FnOnce() -> LiveEvent) {
+        if let Some(tx) = self.tx.as_ref().filter(|tx| tx.receiver_count() > 0) {
+            let _ = tx.send(event());
         }
     }
 }
@@ -0,0 +1,120 @@
+use meshtastic::packet::PacketDestination;
+use meshtastic::types::{MeshChannel, NodeId};
+
+use crate::bridge::{BridgeEvent, MeshBridgeMessage};
+use crate::message::{MessageContext, ResponseData};
+
+use super::*;
+
+impl Bot {
+    /// Handle a text containing an emergency keyword: record the incident,
+    /// alert every bridge with the sender's last known position, and
+    /// acknowledge the sender ahead of everything else in the queue.
+    /// `text` is `None` when privacy settings withhold the message body.
+    pub(super) async fn raise_emergency(
+        &self,
+        my_node_id: u32,
+        ctx: &MessageContext,
+        keyword: &str,
+        text: Option<&str>,
+    ) {
+        let (sender_id, packet_id, channel) = (ctx.sender_id, ctx.packet_id, ctx.channel);
+        let owned_keyword = keyword.to_string();
+        let body = text.unwrap_or("").to_string();
+        let logged = self
+            .db
+            .run(move |db| {
+                let position = db.get_node_position(sender_id).unwrap_or(None);
+                let incident = match db.log_incident(
+                    sender_id,
+                    packet_id,
+                    channel,
+                    &owned_keyword,
+                    &body,
+                    position,
+                ) {
+                    Ok(incident) => incident,
+                    Err(e) => {
+                        log::error!("Failed to log emergency incident: {}", e);
+                        Some(0)
+                    }
+                };
+                Ok((position, incident))
+            })
+            .await;
+        let (position, incident) = match logged {
+            Ok((position, Some(id))) => (position, id),
+            // Already handled this packet (heard again via another path)
+            Ok((_, None)) => return,
+            Err(e) => {
+                log::error!("Failed to log emergency incident: {}", e);
+                (None, 0)
+            }
+        };
+
+        let details = ResponseData::KeyValue(match position {
+            Some((lat, lon)) => vec![
+                ("Position".to_string(), format!("{:.5}, {:.5}", lat, lon)),
+                (
+                    "Map".to_string(),
+                    format!(
+                        "https://www.openstreetmap.org/?mlat={:.5}&mlon={:.5}",
+                        lat, lon
+                    ),
+                ),
+            ],
+            None => vec![("Position".to_string(), "unknown".to_string())],
+        });
+        log::warn!(
+            "EMERGENCY #{} from {} (!{:08x}) on ch {} [keyword={:?}, msg_id={}]: {} | {}",
+            incident,
+            ctx.sender_name,
+            ctx.sender_id,
+            ctx.channel,
+            keyword,
+            ctx.packet_id,
+            text.unwrap_or("<omitted>"),
+            details.render_compact()
+        );
+
+        // Bridge rules redact the alert like any relayed text
+        self.bridge.relay(MeshBridgeMessage {
+            sender_id: ctx.sender_id,
+            sender_name: ctx.sender_name.clone(),
+            text: format!(
+                "EMERGENCY ({}): {}",
+                keyword.to_uppercase(),
+                text.unwrap_or("keyword seen in a private message")
+            ),
+            channel: ctx.channel,
+            is_dm: ctx.is_dm,
+            event: BridgeEvent::Emergency,
+            data: Some(details),
+            packet_id: None,
+        });
+
+        let ack = &self.config.emergency.ack_message;
+        if !ack.is_empty() {
+            match MeshChannel::new(ctx.channel) {
+                Ok(channel) => self.outgoing.push_front(
+                    OutgoingMeshMessage {
+                        kind: OutgoingKind::Text,
+                        text: ack.clone(),
+                        destination: PacketDestination::Node(NodeId::from(ctx.sender_id)),
+                        channel,
+                        from_node: my_node_id,
+                        to_node: Some(ctx.sender_id),
+                        mesh_channel: ctx.channel,
+                        reply_id: Some(ctx.packet_id),
+                        expires_at: None,
+                    },
+                    self.now(),
+                ),
+                Err(e) => log::error!("Invalid channel {}: {}", ctx.channel, e),
+            }
+            self.publish_queue_depth();
+        }
+
+        self.notify_dashboard();
+    }
+}
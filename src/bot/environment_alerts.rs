@@ -0,0 +1,280 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use meshtastic::packet::PacketDestination;
+use meshtastic::types::{MeshChannel, NodeId};
+
+use crate::bridge::{BridgeEvent, MeshBridgeMessage};
+use crate::config::EnvironmentAlertConfig;
+use crate::util::parse_node_id;
+
+use super::*;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Metric {
+    Temperature,
+    Humidity,
+    Pressure,
+}
+
+impl Metric {
+    fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "temperature" => Some(Metric::Temperature),
+            "humidity" => Some(Metric::Humidity),
+            "pressure" => Some(Metric::Pressure),
+            _ => None,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Metric::Temperature => "temperature",
+            Metric::Humidity => "humidity",
+            Metric::Pressure => "pressure",
+        }
+    }
+
+    fn format(self, value: f64) -> String {
+        match self {
+            Metric::Temperature => format!("{:.1}°C", value),
+            Metric::Humidity => format!("{:.0}%", value),
+            Metric::Pressure => format!("{:.1} hPa", value),
+        }
+    }
+}
+
+/// One sensor reading, as decoded from EnvironmentMetrics telemetry.
+pub(super) struct SensorReading {
+    pub(super) temperature: Option<f32>,
+    pub(super) relative_humidity: Option<f32>,
+    pub(super) barometric_pressure: Option<f32>,
+}
+
+impl SensorReading {
+    fn get(&self, metric: Metric) -> Option<f64> {
+        match metric {
+            Metric::Temperature => self.temperature,
+            Metric::Humidity => self.relative_humidity,
+            Metric::Pressure => self.barometric_pressure,
+        }
+        .map(|v| v as f64)
+    }
+}
+
+struct Rule {
+    name: String,
+    node: Option<u32>,
+    metric: Metric,
+    below: Option<f64>,
+    above: Option<f64>,
+    broadcast: bool,
+    admins: bool,
+    bridges: bool,
+    channel: u32,
+    cooldown: Duration,
+}
+
+impl Rule {
+    /// The crossed threshold, described for the alert text.
+    fn breach(&self, value: f64) -> Option<String> {
+        match (self.below, self.above) {
+            (Some(below), _) if value < below => {
+                Some(format!("below {}", self.metric.format(below)))
+            }
+            (_, Some(above)) if value > above => {
+                Some(format!("above {}", self.metric.format(above)))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Default)]
+struct SensorState {
+    breached: bool,
+    last_alert: Option<Instant>,
+}
+
+/// Compiled `[[environment_alerts]]` plus, per rule and node, whether the
+/// threshold is currently crossed and when it last alerted.
+pub(super) struct EnvironmentAlerts {
+    rules: Vec<Rule>,
+    state: Mutex<HashMap<(usize, u32), SensorState>>,
+}
+
+impl EnvironmentAlerts {
+    pub(super) fn new(configs: &[EnvironmentAlertConfig]) -> Self {
+        let rules = configs
+            .iter()
+            .filter_map(|c| {
+                let metric = match Metric::parse(&c.metric) {
+                    Some(metric) => metric,
+                    None => {
+                        log::warn!(
+                            "Ignoring environment alert {:?}: unknown metric {:?}",
+                            c.name,
+                            c.metric
+                        );
+                        return None;
+                    }
+                };
+                if c.below.is_none() && c.above.is_none() {
+                    log::warn!(
+                        "Ignoring environment alert {:?}: needs `below` or `above`",
+                        c.name
+                    );
+                    return None;
+                }
+                let node = if c.node.is_empty() {
+                    None
+                } else {
+                    match parse_node_id(&c.node) {
+                        Some(id) => Some(id),
+                        None => {
+                            log::warn!(
+                                "Ignoring environment alert {:?}: bad node {:?}",
+                                c.name,
+                                c.node
+                            );
+                            return None;
+                        }
+                    }
+                };
+                let notifies =
+                    |target: &str| c.notify.iter().any(|n| n.eq_ignore_ascii_case(target));
+                Some(Rule {
+                    name: c.name.clone(),
+                    node,
+                    metric,
+                    below: c.below,
+                    above: c.above,
+                    broadcast: notifies("broadcast"),
+                    admins: notifies("admins"),
+                    bridges: notifies("bridges"),
+                    channel: c.channel,
+                    cooldown: Duration::from_secs(c.cooldown_mins * 60),
+                })
+            })
+            .collect();
+        Self {
+            rules,
+            state: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Rules (by index) that just crossed their threshold for `node` and are
+    /// out of cooldown, with the alert's value and threshold description.
+    fn crossed(
+        &self,
+        node: u32,
+        reading: &SensorReading,
+        now: Instant,
+    ) -> Vec<(usize, f64, String)> {
+        let mut state = self.state.lock().unwrap();
+        let mut fired = Vec::new();
+        for (index, rule) in self.rules.iter().enumerate() {
+            if rule.node.is_some_and(|n| n != node) {
+                continue;
+            }
+            let value = match reading.get(rule.metric) {
+                Some(value) => value,
+                None => continue,
+            };
+            let sensor = state.entry((index, node)).or_default();
+            let breach = rule.breach(value);
+            let was_breached = sensor.breached;
+            sensor.breached = breach.is_some();
+            let breach = match breach {
+                Some(breach) if !was_breached => breach,
+                _ => continue,
+            };
+            if sensor
+                .last_alert
+                .is_some_and(|at| now.saturating_duration_since(at) < rule.cooldown)
+            {
+                continue;
+            }
+            sensor.last_alert = Some(now);
+            fired.push((index, value, breach));
+        }
+        fired
+    }
+}
+
+impl Bot {
+    /// Check a sensor reading against `[[environment_alerts]]` and notify
+    /// each rule's targets when it crosses a threshold.
+    pub(super) async fn check_environment_alerts(
+        &self,
+        my_node_id: u32,
+        node_id: u32,
+        reading: &SensorReading,
+    ) {
+        let fired = self
+            .environment_alerts
+            .crossed(node_id, reading, self.now());
+        if fired.is_empty() {
+            return;
+        }
+        let name = self.node_name(node_id).await;
+
+        for (index, value, breach) in fired {
+            let rule = &self.environment_alerts.rules[index];
+            let text = format!(
+                "{}: {} {} {} ({})",
+                rule.name,
+                name,
+                rule.metric.label(),
+                rule.metric.format(value),
+                breach
+            );
+            log::warn!("Environment alert: {} (!{:08x})", text, node_id);
+
+            if rule.bridges {
+                self.bridge.relay(MeshBridgeMessage {
+                    sender_id: node_id,
+                    sender_name: name.clone(),
+                    text: text.clone(),
+                    channel: rule.channel,
+                    is_dm: false,
+                    event: BridgeEvent::SensorAlert,
+                    data: None,
+                    packet_id: None,
+                });
+            }
+
+            let channel = match MeshChannel::new(rule.channel) {
+                Ok(ch) => ch,
+                Err(e) => {
+                    log::error!("Invalid channel {}: {}", rule.channel, e);
+                    continue;
+                }
+            };
+            let mut destinations = Vec::new();
+            if rule.broadcast {
+                destinations.push(None);
+            }
+            if rule.admins {
+                destinations.extend(self.admin_ids().into_iter().map(Some));
+            }
+            for to_node in destinations {
+                self.queue_message(OutgoingMeshMessage {
+                    kind: OutgoingKind::Text,
+                    text: text.clone(),
+                    destination: match to_node {
+                        Some(id) => PacketDestination::Node(NodeId::from(id)),
+                        None => PacketDestination::Broadcast,
+                    },
+                    channel,
+                    from_node: my_node_id,
+                    to_node,
+                    mesh_channel: rule.channel,
+                    reply_id: None,
+                    expires_at: None,
+                });
+            }
+        }
+    }
+}
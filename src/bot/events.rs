@@ -1,7 +1,11 @@
-use crate::message::{MeshEvent, MessageContext};
+use crate::message::{Destination, MeshEvent, MessageContext};
 
 use super::*;
 
+/// Longest a module may take to handle one event before its response is
+/// dropped.
+const MODULE_EVENT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
 impl Bot {
     pub(super) async fn dispatch_deferred_events(&self, my_node_id: u32) {
         let events = self.startup_state.take_deferred();
@@ -15,7 +19,7 @@ impl Bot {
             events.len()
         );
 
-        for event in &events {
+        for (event, nodedb_heard_at) in &events {
             if let MeshEvent::NodeDiscovered {
                 node_id,
                 long_name,
@@ -23,12 +27,26 @@ impl Bot {
                 via_mqtt,
             } = event
             {
+                // Checked before dispatch: the welcome module upserts the node
+                let node = *node_id;
+                let is_new = self
+                    .db
+                    .run(move |db| db.is_node_new(node))
+                    .await
+                    .unwrap_or(false);
                 self.dispatch_event_to_modules(event, my_node_id).await;
 
                 // Upsert after module dispatch (was deferred along with the event)
                 if let Err(e) = self
-                    .db
-                    .upsert_node(*node_id, short_name, long_name, *via_mqtt)
+                    .store_node(
+                        *node_id,
+                        short_name,
+                        long_name,
+                        *via_mqtt,
+                        *nodedb_heard_at,
+                        is_new,
+                    )
+                    .await
                 {
                     log::error!("Failed to upsert deferred node: {}", e);
                 }
@@ -38,39 +56,76 @@ impl Bot {
 
     /// Dispatch an event to all modules, queuing any responses.
     pub(super) async fn dispatch_event_to_modules(&self, event: &MeshEvent, my_node_id: u32) {
-        let (node_id, long_name) = match event {
+        let (node_id, long_name, channel) = match event {
             MeshEvent::NodeDiscovered {
                 node_id, long_name, ..
-            } => (*node_id, long_name.clone()),
-            MeshEvent::PositionUpdate { node_id, .. } => (*node_id, String::new()),
+            } => (*node_id, long_name.clone(), 0),
+            MeshEvent::PositionUpdate { node_id, .. } => (*node_id, String::new(), 0),
+            MeshEvent::TextMessage {
+                sender_id, channel, ..
+            } => (*sender_id, String::new(), *channel),
+            MeshEvent::GeofenceTransition {
+                node_id, channel, ..
+            } => (*node_id, String::new(), *channel),
+            MeshEvent::NodeOffline { node_id, .. } | MeshEvent::NodeBackOnline { node_id, .. } => {
+                (*node_id, String::new(), 0)
+            }
         };
 
-        for module in self.registry.all() {
-            match module.handle_event(event, &self.db).await {
-                Ok(Some(responses)) => {
-                    let ctx = MessageContext {
-                        sender_id: node_id,
-                        sender_name: if !long_name.is_empty() {
-                            long_name.clone()
-                        } else {
-                            format!("!{:08x}", node_id)
-                        },
-                        channel: 0,
-                        is_dm: false,
-                        rssi: 0,
-                        snr: 0.0,
-                        hop_count: 0,
-                        hop_start: 0,
-                        hop_limit: 0,
-                        via_mqtt: false,
-                        packet_id: 0,
-                    };
-                    self.queue_responses(&ctx, &responses, my_node_id);
+        let ctx = MessageContext {
+            sender_id: node_id,
+            sender_name: if !long_name.is_empty() {
+                long_name
+            } else {
+                format!("!{:08x}", node_id)
+            },
+            channel,
+            is_dm: false,
+            rssi: 0,
+            snr: 0.0,
+            hop_count: 0,
+            hop_start: 0,
+            hop_limit: 0,
+            via_mqtt: false,
+            pki_key: None,
+            packet_id: 0,
+        };
+
+        // Modules run concurrently so a slow one (network call) doesn't hold
+        // up the rest; responses are queued in registration order.
+        let registry = self.registry();
+        let results = futures_util::future::join_all(registry.all().iter().map(|module| {
+            tokio::time::timeout(MODULE_EVENT_TIMEOUT, module.handle_event(event, &self.db))
+        }))
+        .await;
+        for (module, result) in registry.all().iter().zip(results) {
+            match result {
+                Ok(Ok(Some(responses))) => {
+                    // Event replies are unsolicited; drop DMs to muted nodes
+                    let mut unmuted = Vec::with_capacity(responses.len());
+                    for response in responses {
+                        let muted = match response.destination {
+                            Destination::Sender => self.is_muted(ctx.sender_id).await,
+                            Destination::Node(id) => self.is_muted(id).await,
+                            Destination::Broadcast => false,
+                        };
+                        if !muted {
+                            unmuted.push(response);
+                        }
+                    }
+                    self.queue_responses(&ctx, &unmuted, my_node_id)
                 }
-                Ok(None) => {}
-                Err(e) => {
+                Ok(Ok(None)) => {}
+                Ok(Err(e)) => {
                     log::error!("Module {} event error: {}", module.name(), e);
                 }
+                Err(_) => {
+                    log::warn!(
+                        "Module {} took over {}s to handle an event, skipped",
+                        module.name(),
+                        MODULE_EVENT_TIMEOUT.as_secs()
+                    );
+                }
             }
         }
     }
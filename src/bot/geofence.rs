@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use meshtastic::packet::PacketDestination;
+use meshtastic::types::{MeshChannel, NodeId};
+
+use crate::bridge::{BridgeEvent, MeshBridgeMessage};
+use crate::config::GeofenceConfig;
+use crate::message::MeshEvent;
+use crate::util::{distance_km, parse_node_id};
+
+use super::*;
+
+/// A node inside a fence only counts as leaving once it is this far out
+/// relative to the radius, so GPS jitter at the edge doesn't flap.
+const LEAVE_MARGIN: f64 = 1.1;
+
+struct Fence {
+    name: String,
+    center: (f64, f64),
+    radius_m: f64,
+    /// Empty watches every node
+    nodes: Vec<u32>,
+    broadcast: bool,
+    admins: bool,
+    bridges: bool,
+    channel: u32,
+}
+
+/// Compiled `[[geofence]]` entries plus, per fence and node, whether the
+/// node was last seen inside. A node's first position only records where
+/// it is; transitions are reported from the second one on.
+pub(super) struct Geofences {
+    fences: Vec<Fence>,
+    inside: Mutex<HashMap<(usize, u32), bool>>,
+}
+
+impl Geofences {
+    pub(super) fn new(configs: &[GeofenceConfig]) -> Self {
+        let fences = configs
+            .iter()
+            .filter_map(|c| {
+                if c.radius_m.is_nan() || c.radius_m <= 0.0 {
+                    log::warn!("Ignoring geofence {:?}: radius_m must be positive", c.name);
+                    return None;
+                }
+                let mut nodes = Vec::new();
+                for node in &c.nodes {
+                    match parse_node_id(node) {
+                        Some(id) => nodes.push(id),
+                        None => {
+                            log::warn!("Ignoring geofence {:?}: bad node {:?}", c.name, node);
+                            return None;
+                        }
+                    }
+                }
+                let notifies =
+                    |target: &str| c.notify.iter().any(|n| n.eq_ignore_ascii_case(target));
+                Some(Fence {
+                    name: c.name.clone(),
+                    center: (c.lat, c.lon),
+                    radius_m: c.radius_m,
+                    nodes,
+                    broadcast: notifies("broadcast"),
+                    admins: notifies("admins"),
+                    bridges: notifies("bridges"),
+                    channel: c.channel,
+                })
+            })
+            .collect();
+        Self {
+            fences,
+            inside: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Fences (by index) that `node` just entered (`true`) or left
+    /// (`false`) by moving to `position`.
+    fn transitions(&self, node: u32, position: (f64, f64)) -> Vec<(usize, bool)> {
+        let mut inside = self.inside.lock().unwrap();
+        let mut crossed = Vec::new();
+        for (index, fence) in self.fences.iter().enumerate() {
+            if !fence.nodes.is_empty() && !fence.nodes.contains(&node) {
+                continue;
+            }
+            let distance_m = distance_km(fence.center, position) * 1000.0;
+            let was_inside = inside.get(&(index, node)).copied();
+            let is_inside = if was_inside == Some(true) {
+                distance_m <= fence.radius_m * LEAVE_MARGIN
+            } else {
+                distance_m <= fence.radius_m
+            };
+            inside.insert((index, node), is_inside);
+            if was_inside.is_some_and(|was| was != is_inside) {
+                crossed.push((index, is_inside));
+            }
+        }
+        crossed
+    }
+}
+
+impl Bot {
+    /// Check a node's new position against `[[geofence]]`, dispatching a
+    /// `GeofenceTransition` to modules and notifying the fence's targets
+    /// for each fence it entered or left.
+    pub(super) async fn check_geofences(&self, my_node_id: u32, node_id: u32, lat: f64, lon: f64) {
+        let crossed = self.geofences.transitions(node_id, (lat, lon));
+        if crossed.is_empty() {
+            return;
+        }
+        let name = self.node_name(node_id).await;
+
+        for (index, entered) in crossed {
+            let fence = &self.geofences.fences[index];
+            let text = format!(
+                "{}: {} {}",
+                fence.name,
+                name,
+                if entered { "entered" } else { "left" }
+            );
+            log::info!("Geofence: {} (!{:08x})", text, node_id);
+
+            let event = MeshEvent::GeofenceTransition {
+                node_id,
+                fence: fence.name.clone(),
+                entered,
+                lat,
+                lon,
+                channel: fence.channel,
+            };
+            self.dispatch_event_to_modules(&event, my_node_id).await;
+
+            if fence.bridges {
+                self.bridge.relay(MeshBridgeMessage {
+                    sender_id: node_id,
+                    sender_name: name.clone(),
+                    text: text.clone(),
+                    channel: fence.channel,
+                    is_dm: false,
+                    event: BridgeEvent::Geofence,
+                    data: None,
+                    packet_id: None,
+                });
+            }
+
+            let channel = match MeshChannel::new(fence.channel) {
+                Ok(ch) => ch,
+                Err(e) => {
+                    log::error!("Invalid channel {}: {}", fence.channel, e);
+                    continue;
+                }
+            };
+            let mut destinations = Vec::new();
+            if fence.broadcast {
+                destinations.push(None);
+            }
+            if fence.admins {
+                destinations.extend(self.admin_ids().into_iter().map(Some));
+            }
+            for to_node in destinations {
+                self.queue_message(OutgoingMeshMessage {
+                    kind: OutgoingKind::Text,
+                    text: text.clone(),
+                    destination: match to_node {
+                        Some(id) => PacketDestination::Node(NodeId::from(id)),
+                        None => PacketDestination::Broadcast,
+                    },
+                    channel,
+                    from_node: my_node_id,
+                    to_node,
+                    mesh_channel: fence.channel,
+                    reply_id: None,
+                    expires_at: None,
+                });
+            }
+        }
+    }
+}
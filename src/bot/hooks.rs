@@ -0,0 +1,50 @@
+use super::*;
+use crate::event_hooks::HookEvent;
+
+impl Bot {
+    /// Emit `[event_hooks]` webhooks through `hooks`.
+    pub fn with_event_hooks(mut self, hooks: EventHooks) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    /// Send `queue_overflow` when the queue just grew to the configured depth.
+    pub(super) fn check_queue_overflow(&self) {
+        let threshold = self.config.event_hooks.queue_overflow_depth;
+        let depth = self
+            .queue_depth()
+            .load(std::sync::atomic::Ordering::Relaxed);
+        if threshold > 0 && depth == threshold {
+            log::warn!("Outgoing queue reached {} messages", depth);
+            self.hooks.emit(HookEvent::QueueOverflow { depth });
+        }
+    }
+
+    /// Send `node_offline` for RF nodes that passed `[freshness] stale_mins`
+    /// since the last check.
+    pub(super) async fn check_nodes_offline(&self, now: i64) {
+        let stale_secs = self.config.freshness.stale_mins as i64 * 60;
+        let last_check = std::mem::replace(&mut *self.offline_checked_at.lock().unwrap(), now);
+        let nodes = match self
+            .db
+            .run(move |db| {
+                db.rf_nodes_last_heard_between(last_check - stale_secs, now - stale_secs)
+            })
+            .await
+        {
+            Ok(nodes) => nodes,
+            Err(e) => {
+                log::error!("Failed to check for offline nodes: {}", e);
+                return;
+            }
+        };
+        for node in nodes {
+            log::info!("Node {} (!{:08x}) went offline", node.name, node.node_id);
+            self.hooks.emit(HookEvent::NodeOffline {
+                node: format!("!{:08x}", node.node_id),
+                name: node.name,
+                last_seen: node.last_seen,
+            });
+        }
+    }
+}
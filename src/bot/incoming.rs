@@ -1,12 +1,22 @@
-use crate::bridge::{MeshBridgeMessage, OutgoingBridgeMessage};
-use crate::message::{MeshEvent, MessageContext};
+use crate::bridge::{BridgeEvent, MeshBridgeMessage, OutgoingBridgeMessage};
+use crate::db::{DeviceTelemetry, TrackPoint};
+use crate::event_hooks::HookEvent;
+use crate::message::{Destination, MeshEvent, MessageContext, Response, ResponseData};
+use crate::packet_type::PacketType;
 use chrono::Utc;
 use meshtastic::packet::PacketDestination;
 use meshtastic::protobufs::{self, from_radio, mesh_packet};
-use meshtastic::types::MeshChannel;
+use meshtastic::types::{MeshChannel, NodeId};
+use std::time::{Duration, Instant};
 
+use super::environment_alerts::SensorReading;
+use super::outgoing::chunk_bridge_message;
 use super::*;
 
+/// NodeDB `last_heard` values before this (2020-01-01) come from radios
+/// without a clock, which count from boot, and aren't usable as dates.
+const MIN_CLOCK_TIMESTAMP: i64 = 1_577_836_800;
+
 impl Bot {
     fn decode_traceroute_routes(data: &protobufs::Data) -> (Vec<u32>, Vec<u32>) {
         match meshtastic::Message::decode(data.payload.as_slice()) {
@@ -27,6 +37,14 @@ impl Bot {
         format!("in:{:08x}:{}:{}", mesh_packet.from, to_node, mesh_packet.id)
     }
 
+    async fn traceroute_session_exists(&self, trace_key: &str, since: i64) -> bool {
+        let trace_key = trace_key.to_string();
+        self.db
+            .run(move |db| db.traceroute_session_exists_since(&trace_key, since))
+            .await
+            .unwrap_or(false)
+    }
+
     pub(super) async fn process_radio_packet(&self, my_node_id: u32, packet: protobufs::FromRadio) {
         let variant = match packet.payload_variant {
             Some(v) => v,
@@ -39,35 +57,169 @@ impl Bot {
                 self.notify_dashboard();
             }
             from_radio::PayloadVariant::NodeInfo(node_info) => {
-                self.handle_node_info(my_node_id, &node_info).await;
+                self.handle_node_info(my_node_id, &node_info, true).await;
                 self.notify_dashboard();
             }
+            from_radio::PayloadVariant::Metadata(metadata) => {
+                log::info!("Radio firmware version: {}", metadata.firmware_version);
+                if let Ok(mut version) = self.firmware_version.write() {
+                    *version = Some(metadata.firmware_version);
+                }
+            }
             _ => {}
         }
     }
 
+    /// Handle a packet decoded from the MQTT broker. Brokers carry node info
+    /// as NodeinfoApp packets rather than the node database a radio dumps.
+    pub(super) async fn process_mqtt_packet(
+        &self,
+        my_node_id: u32,
+        mesh_packet: protobufs::MeshPacket,
+    ) {
+        if let Some(mesh_packet::PayloadVariant::Decoded(data)) = &mesh_packet.payload_variant {
+            if data.portnum() == protobufs::PortNum::NodeinfoApp {
+                if let Ok(user) = meshtastic::Message::decode(data.payload.as_slice()) {
+                    let node_info = protobufs::NodeInfo {
+                        num: mesh_packet.from,
+                        user: Some(user),
+                        via_mqtt: true,
+                        ..Default::default()
+                    };
+                    self.handle_node_info(my_node_id, &node_info, false).await;
+                    self.notify_dashboard();
+                }
+                return;
+            }
+        }
+        self.handle_mesh_packet(my_node_id, &mesh_packet).await;
+        self.notify_dashboard();
+    }
+
     /// Handle a message from an external bridge (Telegram, Discord, etc.)
-    pub(super) fn handle_bridge_message(&self, my_node_id: u32, msg: OutgoingBridgeMessage) {
+    pub(super) async fn handle_bridge_message(&self, my_node_id: u32, msg: OutgoingBridgeMessage) {
         log::info!("Bridge message from {}: {}", msg.source, msg.text);
 
-        let channel = match MeshChannel::new(msg.channel) {
+        // Operator replies to a passed-through DM go back to its sender
+        let mut destination = PacketDestination::Broadcast;
+        let mut to_node = None;
+        let mut mesh_channel = msg.channel;
+        if let Some(number) = msg.dm_session {
+            let Some(session) = self.bridge.dm_session_node(number) else {
+                log::warn!("Reply to unknown DM conversation #{}", number);
+                self.bridge.relay(MeshBridgeMessage {
+                    sender_id: my_node_id,
+                    sender_name: self.config.bot.name.clone(),
+                    text: "Unknown conversation, reply not sent (the bot may have restarted)."
+                        .to_string(),
+                    channel: msg.channel,
+                    is_dm: true,
+                    event: BridgeEvent::DirectMessage { session: number },
+                    data: None,
+                    packet_id: None,
+                });
+                return;
+            };
+            destination = PacketDestination::Node(NodeId::from(session.node_id));
+            to_node = Some(session.node_id);
+            mesh_channel = session.channel;
+        } else if let Some(node_id) = msg.to_node {
+            destination = PacketDestination::Node(NodeId::from(node_id));
+            to_node = Some(node_id);
+        }
+
+        let channel = match MeshChannel::new(mesh_channel) {
             Ok(ch) => ch,
             Err(e) => {
-                log::error!("Invalid channel {}: {}", msg.channel, e);
+                log::error!("Invalid channel {}: {}", mesh_channel, e);
                 return;
             }
         };
 
-        self.queue_message(OutgoingMeshMessage {
-            kind: OutgoingKind::Text,
-            text: msg.text,
-            destination: PacketDestination::Broadcast,
-            channel,
-            from_node: my_node_id,
-            to_node: None,
-            mesh_channel: msg.channel,
-            reply_id: None,
-        });
+        // Logged before the age check, so dropped messages show up unsent
+        let kind = match self.log_bridge_message(&msg, mesh_channel).await {
+            Some(log_id) => OutgoingKind::BridgeText { log_id },
+            None => OutgoingKind::Text,
+        };
+
+        // Messages that waited out a radio outage are dropped, not flooded
+        let max_age = self.config.bridge.max_queue_age_secs;
+        let expires_at = (max_age > 0).then(|| msg.received_at + Duration::from_secs(max_age));
+        let now = self.now();
+        if expires_at.is_some_and(|t| now >= t) {
+            log::warn!(
+                "Dropping bridge message from {} queued {}s ago",
+                msg.source,
+                now.saturating_duration_since(msg.received_at).as_secs()
+            );
+            return;
+        }
+
+        let text = match &self.links {
+            Some(links) => {
+                let links = Arc::clone(links);
+                let text = msg.text.clone();
+                self.db
+                    .run(move |db| Ok(links.shorten(&text, db)))
+                    .await
+                    .unwrap_or(msg.text)
+            }
+            None => msg.text,
+        };
+        for chunk in chunk_bridge_message(&text, self.config.bot.max_message_len) {
+            self.queue_message(OutgoingMeshMessage {
+                kind: kind.clone(),
+                text: chunk,
+                destination,
+                channel,
+                from_node: my_node_id,
+                to_node,
+                mesh_channel,
+                reply_id: None,
+                expires_at,
+            });
+        }
+    }
+
+    /// Count a chat message for the mesh and record it in `bridge_log`,
+    /// unless `[bridge] log_days` is 0. Returns the row to note its packet
+    /// ID in.
+    async fn log_bridge_message(
+        &self,
+        msg: &OutgoingBridgeMessage,
+        mesh_channel: u32,
+    ) -> Option<i64> {
+        self.metrics.bridge_message(&msg.source, "to_mesh");
+        if self.config.bridge.log_days == 0 {
+            return None;
+        }
+        let event = if msg.dm_session.is_some() || msg.to_node.is_some() {
+            "direct_message"
+        } else {
+            "message"
+        };
+        let (source, sender, text) = (msg.source.clone(), msg.sender.clone(), msg.text.clone());
+        let logged = self
+            .db
+            .run(move |db| {
+                db.log_bridge_relay(
+                    &source,
+                    "to_mesh",
+                    event,
+                    &sender,
+                    mesh_channel,
+                    &text,
+                    None,
+                )
+            })
+            .await;
+        match logged {
+            Ok(id) => Some(id),
+            Err(e) => {
+                log::error!("Failed to log {} relay: {}", msg.source, e);
+                None
+            }
+        }
     }
 
     /// Extract RF metadata from a mesh packet for logging.
@@ -93,31 +245,57 @@ impl Bot {
         (rssi, snr, hop_count, hop_start)
     }
 
-    fn log_incoming_packet(
+    #[allow(clippy::too_many_arguments)]
+    async fn log_incoming_packet(
         &self,
+        my_node_id: u32,
         mesh_packet: &protobufs::MeshPacket,
         to_node: Option<u32>,
         rssi: Option<i32>,
         snr: Option<f32>,
         hop_count: Option<u32>,
         hop_start: Option<u32>,
-        kind: &str,
+        kind: PacketType,
     ) -> Option<i64> {
-        self.db
-            .log_packet_with_mesh_id(
+        self.metrics.packet("in", kind);
+        self.publish_live(|| {
+            LiveEvent::packet(
                 mesh_packet.from,
                 to_node,
+                kind,
                 mesh_packet.channel,
-                "",
-                "in",
                 mesh_packet.via_mqtt,
                 rssi,
                 snr,
                 hop_count,
-                hop_start,
-                Some(mesh_packet.id),
-                kind,
+                None,
             )
+        });
+        let radio = self.radio_label(my_node_id);
+        let (from, channel, via_mqtt, id) = (
+            mesh_packet.from,
+            mesh_packet.channel,
+            mesh_packet.via_mqtt,
+            mesh_packet.id,
+        );
+        self.db
+            .run(move |db| {
+                db.log_received_packet(
+                    radio.as_deref(),
+                    from,
+                    to_node,
+                    channel,
+                    "",
+                    via_mqtt,
+                    rssi,
+                    snr,
+                    hop_count,
+                    hop_start,
+                    Some(id),
+                    kind,
+                )
+            })
+            .await
             .ok()
     }
 
@@ -131,6 +309,18 @@ impl Bot {
             _ => return,
         };
 
+        self.observe_dm_delivery(my_node_id, mesh_packet, data)
+            .await;
+        // A node heard only through MQTT may be out of the radio's reach
+        if mesh_packet.from != my_node_id && !mesh_packet.via_mqtt {
+            self.deliver_owed(my_node_id, mesh_packet.from, mesh_packet.channel)
+                .await;
+        }
+        if self.config.selftest.enabled {
+            self.observe_selftest_response(my_node_id, mesh_packet, data)
+                .await;
+        }
+
         let (rssi, snr, hop_count, hop_start) = Self::rf_metadata(mesh_packet);
         let to_node = if mesh_packet.to == 0 {
             None
@@ -141,14 +331,16 @@ impl Bot {
         match data.portnum() {
             protobufs::PortNum::PositionApp => {
                 self.log_incoming_packet(
+                    my_node_id,
                     mesh_packet,
                     to_node,
                     rssi,
                     snr,
                     hop_count,
                     hop_start,
-                    "position",
-                );
+                    PacketType::Position,
+                )
+                .await;
                 // Update position in DB
                 if let Ok(pos) = meshtastic::Message::decode(data.payload.as_slice()) {
                     let pos: protobufs::Position = pos;
@@ -163,21 +355,82 @@ impl Bot {
                                 lat,
                                 lon
                             );
-                            let _ = self.db.update_position(mesh_packet.from, lat, lon);
+                            let from = mesh_packet.from;
+                            let _ = self
+                                .db
+                                .run(move |db| db.update_position(from, lat, lon))
+                                .await;
+                            self.log_position(from, &pos).await;
+                            self.check_geofences(my_node_id, mesh_packet.from, lat, lon)
+                                .await;
                         }
                     }
                 }
             }
             protobufs::PortNum::TelemetryApp => {
                 self.log_incoming_packet(
+                    my_node_id,
                     mesh_packet,
                     to_node,
                     rssi,
                     snr,
                     hop_count,
                     hop_start,
-                    "telemetry",
-                );
+                    PacketType::Telemetry,
+                )
+                .await;
+                if let Ok(telemetry) = meshtastic::Message::decode(data.payload.as_slice()) {
+                    let telemetry: protobufs::Telemetry = telemetry;
+                    match telemetry.variant {
+                        Some(protobufs::telemetry::Variant::DeviceMetrics(metrics)) => {
+                            let from = mesh_packet.from;
+                            let device = DeviceTelemetry {
+                                timestamp: Utc::now().timestamp(),
+                                battery_level: metrics.battery_level,
+                                voltage: metrics.voltage.map(f64::from),
+                                channel_utilization: metrics.channel_utilization.map(f64::from),
+                                air_util_tx: metrics.air_util_tx.map(f64::from),
+                                uptime_seconds: metrics.uptime_seconds,
+                            };
+                            if let Err(e) = self
+                                .db
+                                .run(move |db| db.log_device_telemetry(from, &device))
+                                .await
+                            {
+                                log::error!("Failed to log device telemetry: {}", e);
+                            }
+                            if let Some(level) = metrics.battery_level {
+                                self.check_battery(my_node_id, from, level, mesh_packet.channel)
+                                    .await;
+                            }
+                        }
+                        Some(protobufs::telemetry::Variant::EnvironmentMetrics(metrics)) => {
+                            let reading = SensorReading {
+                                temperature: metrics.temperature,
+                                relative_humidity: metrics.relative_humidity,
+                                barometric_pressure: metrics.barometric_pressure,
+                            };
+                            let from = mesh_packet.from;
+                            if let Err(e) = self
+                                .db
+                                .run(move |db| {
+                                    db.log_environment(
+                                        from,
+                                        reading.temperature,
+                                        reading.relative_humidity,
+                                        reading.barometric_pressure,
+                                    )
+                                })
+                                .await
+                            {
+                                log::error!("Failed to log environment telemetry: {}", e);
+                            }
+                            self.check_environment_alerts(my_node_id, from, &reading)
+                                .await;
+                        }
+                        _ => {}
+                    }
+                }
             }
             protobufs::PortNum::TracerouteApp => {
                 let (request_route, response_route) = Self::decode_traceroute_routes(data);
@@ -198,15 +451,19 @@ impl Bot {
                     mesh_packet.rx_rssi,
                     mesh_packet.rx_snr
                 );
-                if let Some(packet_row_id) = self.log_incoming_packet(
-                    mesh_packet,
-                    to_node,
-                    rssi,
-                    snr,
-                    hop_count,
-                    hop_start,
-                    "traceroute",
-                ) {
+                if let Some(packet_row_id) = self
+                    .log_incoming_packet(
+                        my_node_id,
+                        mesh_packet,
+                        to_node,
+                        rssi,
+                        snr,
+                        hop_count,
+                        hop_start,
+                        PacketType::Traceroute,
+                    )
+                    .await
+                {
                     // Attempt to correlate this packet with an existing traceroute session.
                     // data.request_id echoes the original request's MeshPacket.id.
                     //
@@ -225,11 +482,7 @@ impl Bot {
                                 "req:{:08x}:{:08x}:{}",
                                 my_node_id, mesh_packet.from, data.request_id
                             );
-                            if self
-                                .db
-                                .traceroute_session_exists_since(&candidate, since)
-                                .unwrap_or(false)
-                            {
+                            if self.traceroute_session_exists(&candidate, since).await {
                                 Some((candidate, my_node_id, false))
                             } else {
                                 None
@@ -239,11 +492,7 @@ impl Bot {
                                 "in:{:08x}:{:08x}:{}",
                                 initiator, mesh_packet.from, data.request_id
                             );
-                            if self
-                                .db
-                                .traceroute_session_exists_since(&candidate, since)
-                                .unwrap_or(false)
-                            {
+                            if self.traceroute_session_exists(&candidate, since).await {
                                 Some((candidate, initiator, true))
                             } else {
                                 None
@@ -257,20 +506,30 @@ impl Bot {
 
                     let is_third_party_reply =
                         correlated.as_ref().map(|(_, _, tp)| *tp).unwrap_or(false);
+                    let answers_our_probe = correlated.is_some() && !is_third_party_reply;
 
                     // Derive session fields from correlation result.
                     // For a correlated reply: preserve request direction (src=initiator, dst=responder).
                     // For all other packets: use the packet's own src/dst.
+                    //
+                    // A reply heard via MQTT carries hop metadata for the path to the
+                    // uplinking gateway, not to the initiator, so fall back to the
+                    // route_back length instead. The DB keeps RF hop info over MQTT.
                     let (trace_key, obs_src, obs_dst, req_hops, req_start, res_hops, res_start) =
                         if let Some((key, src, _)) = correlated {
+                            let (res_hops, res_start) = if mesh_packet.via_mqtt {
+                                (Some(response_route.len() as u32), None)
+                            } else {
+                                (hop_count.or(Some(response_route.len() as u32)), hop_start)
+                            };
                             (
                                 key,
                                 src,
                                 Some(mesh_packet.from),
                                 Some(request_route.len() as u32),
                                 None,
-                                hop_count,
-                                hop_start,
+                                res_hops,
+                                res_start,
                             )
                         } else {
                             (
@@ -287,50 +546,139 @@ impl Bot {
                     // For third-party correlated replies, the request hops were already
                     // inserted when the RouteRequest was first observed; only add the
                     // response path (route_back) to avoid duplicate hop rows.
-                    let req_route_for_log: &[u32] = if is_third_party_reply {
-                        &[]
+                    let req_route_for_log: Vec<u32> = if is_third_party_reply {
+                        Vec::new()
                     } else {
-                        &request_route
+                        request_route.clone()
                     };
 
-                    let _ = self.db.log_traceroute_observation(
-                        packet_row_id,
-                        &trace_key,
-                        obs_src,
-                        obs_dst,
-                        mesh_packet.via_mqtt,
-                        req_hops,
-                        req_start,
-                        res_hops,
-                        res_start,
-                        req_route_for_log,
-                        &response_route,
-                    );
+                    let via_mqtt = mesh_packet.via_mqtt;
+                    let res_route_for_log = response_route.clone();
+                    if let Ok(session_id) = self
+                        .db
+                        .run(move |db| {
+                            db.log_traceroute_observation(
+                                packet_row_id,
+                                &trace_key,
+                                obs_src,
+                                obs_dst,
+                                via_mqtt,
+                                req_hops,
+                                req_start,
+                                res_hops,
+                                res_start,
+                                &req_route_for_log,
+                                &res_route_for_log,
+                            )
+                        })
+                        .await
+                    {
+                        self.publish_live(|| LiveEvent::Traceroute { session_id });
+                    }
+
+                    if answers_our_probe {
+                        self.relay_traceroute(mesh_packet.from, &request_route, &response_route)
+                            .await;
+                        self.answer_trace_requests(
+                            my_node_id,
+                            mesh_packet.from,
+                            &request_route,
+                            &response_route,
+                        )
+                        .await;
+                    } else if to_node == Some(my_node_id) {
+                        self.check_traceroute_abuse(
+                            my_node_id,
+                            mesh_packet.from,
+                            mesh_packet.channel,
+                        )
+                        .await;
+                    }
                 }
             }
             protobufs::PortNum::NeighborinfoApp => {
                 self.log_incoming_packet(
+                    my_node_id,
                     mesh_packet,
                     to_node,
                     rssi,
                     snr,
                     hop_count,
                     hop_start,
-                    "neighborinfo",
-                );
+                    PacketType::NeighborInfo,
+                )
+                .await;
+                if let Ok(info) = meshtastic::Message::decode(data.payload.as_slice()) {
+                    let info: protobufs::NeighborInfo = info;
+                    let neighbors: Vec<(u32, f32)> =
+                        info.neighbors.iter().map(|n| (n.node_id, n.snr)).collect();
+                    log::debug!(
+                        "NeighborInfo from !{:08x}: {} neighbor(s)",
+                        mesh_packet.from,
+                        neighbors.len()
+                    );
+                    let from = mesh_packet.from;
+                    if let Err(e) = self
+                        .db
+                        .run(move |db| {
+                            db.record_neighbors(from, &neighbors, Utc::now().timestamp())
+                        })
+                        .await
+                    {
+                        log::error!("Failed to record neighbors: {}", e);
+                    }
+                }
             }
             protobufs::PortNum::RoutingApp => {
                 self.log_incoming_packet(
+                    my_node_id,
                     mesh_packet,
                     to_node,
                     rssi,
                     snr,
                     hop_count,
                     hop_start,
-                    "routing",
-                );
+                    PacketType::Routing,
+                )
+                .await;
+            }
+            protobufs::PortNum::StoreForwardApp => {
+                self.log_incoming_packet(
+                    my_node_id,
+                    mesh_packet,
+                    to_node,
+                    rssi,
+                    snr,
+                    hop_count,
+                    hop_start,
+                    PacketType::StoreForward,
+                )
+                .await;
+                if self.config.store_forward.enabled && mesh_packet.from != my_node_id {
+                    self.handle_store_forward(my_node_id, mesh_packet, data)
+                        .await;
+                }
+            }
+            protobufs::PortNum::AdminApp => {
+                self.log_incoming_packet(
+                    my_node_id,
+                    mesh_packet,
+                    to_node,
+                    rssi,
+                    snr,
+                    hop_count,
+                    hop_start,
+                    PacketType::Other,
+                )
+                .await;
+                if mesh_packet.to == my_node_id {
+                    self.handle_node_admin_response(my_node_id, mesh_packet, data)
+                        .await;
+                }
             }
             protobufs::PortNum::TextMessageApp => {
+                let received = Instant::now();
+                let pushed = self.outgoing.pushed();
                 self.handle_text_message(
                     my_node_id,
                     mesh_packet,
@@ -341,17 +689,23 @@ impl Bot {
                     hop_start,
                 )
                 .await;
+                if self.outgoing.pushed() != pushed {
+                    self.record_handling_latency(received.elapsed());
+                }
             }
+            // Ports logged without further handling
             _ => {
                 self.log_incoming_packet(
+                    my_node_id,
                     mesh_packet,
                     to_node,
                     rssi,
                     snr,
                     hop_count,
                     hop_start,
-                    "other",
-                );
+                    PacketType::from_portnum(data.portnum()),
+                )
+                .await;
             }
         }
     }
@@ -376,10 +730,13 @@ impl Bot {
         let is_dm = mesh_packet.to == my_node_id;
         let hops = hop_count.unwrap_or(0);
 
-        let sender_name = self
-            .db
-            .get_node_name(mesh_packet.from)
-            .unwrap_or_else(|_| format!("!{:08x}", mesh_packet.from));
+        // A DM between two other nodes that we only overheard. Metadata is
+        // always logged; the body is dropped if the operator opted out.
+        let is_third_party_dm =
+            mesh_packet.to != 0 && mesh_packet.to != u32::MAX && mesh_packet.to != my_node_id;
+        let omit_text = is_third_party_dm && self.config.privacy.omits_dm_text(mesh_packet.channel);
+
+        let sender_name = self.node_name(mesh_packet.from).await;
 
         let ctx = MessageContext {
             sender_id: mesh_packet.from,
@@ -392,59 +749,410 @@ impl Bot {
             hop_start: mesh_packet.hop_start,
             hop_limit: mesh_packet.hop_limit,
             via_mqtt: mesh_packet.via_mqtt,
+            pki_key: mesh_packet
+                .pki_encrypted
+                .then(|| mesh_packet.public_key.clone()),
             packet_id: mesh_packet.id,
         };
 
+        if omit_text {
+            log::info!(
+                "Text from {} (DM to !{:08x}) [msg_id={}]: <omitted>",
+                ctx.sender_name,
+                mesh_packet.to,
+                ctx.packet_id
+            );
+        } else {
+            log::info!(
+                "Text from {} ({}) [msg_id={}]: {}",
+                ctx.sender_name,
+                if is_dm { "DM" } else { "public" },
+                ctx.packet_id,
+                trimmed_text
+            );
+        }
+
+        // Log incoming text message with RF metadata
+        self.metrics.packet("in", PacketType::Text);
+        self.publish_live(|| {
+            LiveEvent::packet(
+                mesh_packet.from,
+                (mesh_packet.to != 0).then_some(mesh_packet.to),
+                PacketType::Text,
+                mesh_packet.channel,
+                mesh_packet.via_mqtt,
+                rssi,
+                snr,
+                hop_count,
+                (!omit_text).then_some(text),
+            )
+        });
+        let radio = self.radio_label(my_node_id);
+        let (from, channel, via_mqtt, id) = (
+            mesh_packet.from,
+            mesh_packet.channel,
+            mesh_packet.via_mqtt,
+            mesh_packet.id,
+        );
+        let to_node = (mesh_packet.to != 0).then_some(mesh_packet.to);
+        let logged_text = if omit_text { "" } else { text }.to_string();
+        let _ = self
+            .db
+            .run(move |db| {
+                db.log_received_packet(
+                    radio.as_deref(),
+                    from,
+                    to_node,
+                    channel,
+                    &logged_text,
+                    via_mqtt,
+                    rssi,
+                    snr,
+                    hop_count,
+                    hop_start,
+                    Some(id),
+                    PacketType::Text,
+                )
+            })
+            .await;
+
+        if mesh_packet.from != my_node_id {
+            if let Some(keyword) = self.config.emergency.matched_keyword(trimmed_text) {
+                let keyword = keyword.to_string();
+                let body = if omit_text { None } else { Some(trimmed_text) };
+                self.raise_emergency(my_node_id, &ctx, &keyword, body).await;
+            }
+        }
+
+        if omit_text {
+            // Modules may still react to who wrote to whom (e.g. away
+            // auto-replies), but they get the event without the text.
+            self.dispatch_text_event(my_node_id, mesh_packet, "").await;
+            return;
+        }
+
+        // Broadcast to bridges (only public messages, never our own
+        // transmissions heard back, whatever their format)
+        if !is_dm && !self.is_own_packet(mesh_packet) {
+            self.bridge.relay(MeshBridgeMessage {
+                sender_id: mesh_packet.from,
+                sender_name: ctx.sender_name.clone(),
+                text: trimmed_text.to_string(),
+                channel: mesh_packet.channel,
+                is_dm,
+                event: BridgeEvent::Message,
+                data: None,
+                packet_id: Some(mesh_packet.id),
+            });
+        }
+
+        if self.parse_command(trimmed_text).is_some() {
+            self.dispatch_command_from_text(my_node_id, &ctx, trimmed_text, is_dm)
+                .await;
+        } else if is_dm {
+            if self.config.bridge.dm_passthrough() && mesh_packet.from != my_node_id {
+                self.pass_dm_through(&ctx, trimmed_text);
+            }
+        } else {
+            self.dispatch_text_event(my_node_id, mesh_packet, trimmed_text)
+                .await;
+        }
+    }
+
+    /// Pass a DM to the bot through to the operator chat (see
+    /// `[bridge.telegram] dm_chat_id`).
+    fn pass_dm_through(&self, ctx: &MessageContext, text: &str) {
+        let session = self.bridge.dm_session(ctx.sender_id, ctx.channel);
         log::info!(
-            "Text from {} ({}) [msg_id={}]: {}",
+            "Passing DM from {} through to the operator chat (#{})",
             ctx.sender_name,
-            if is_dm { "DM" } else { "public" },
-            ctx.packet_id,
-            trimmed_text
+            session
         );
+        self.bridge.relay(MeshBridgeMessage {
+            sender_id: ctx.sender_id,
+            sender_name: ctx.sender_name.clone(),
+            text: text.to_string(),
+            channel: ctx.channel,
+            is_dm: true,
+            event: BridgeEvent::DirectMessage { session },
+            data: None,
+            packet_id: Some(ctx.packet_id),
+        });
+    }
 
-        // Log incoming text message with RF metadata
-        let _ = self.db.log_packet_with_mesh_id(
-            mesh_packet.from,
-            if mesh_packet.to == 0 {
+    async fn dispatch_text_event(
+        &self,
+        my_node_id: u32,
+        mesh_packet: &protobufs::MeshPacket,
+        text: &str,
+    ) {
+        if mesh_packet.from == my_node_id {
+            return;
+        }
+        let event = MeshEvent::TextMessage {
+            sender_id: mesh_packet.from,
+            to_node: if mesh_packet.to == 0 || mesh_packet.to == u32::MAX {
                 None
             } else {
                 Some(mesh_packet.to)
             },
-            mesh_packet.channel,
-            text,
-            "in",
-            mesh_packet.via_mqtt,
-            rssi,
-            snr,
-            hop_count,
-            hop_start,
-            Some(mesh_packet.id),
-            "text",
-        );
+            channel: mesh_packet.channel,
+            text: text.to_string(),
+        };
+        self.dispatch_event_to_modules(&event, my_node_id).await;
+    }
 
-        // Broadcast to bridges (only public messages, skip messages that look like they came from a bridge)
-        if !is_dm && !text.starts_with("[TG:") && !text.starts_with("[DC:") {
-            if let Some(tx) = self.bridge.tx() {
-                let bridge_msg = MeshBridgeMessage {
-                    sender_id: mesh_packet.from,
-                    sender_name: ctx.sender_name.clone(),
-                    text: trimmed_text.to_string(),
-                    channel: mesh_packet.channel,
-                    is_dm,
-                };
-                // Don't block on send, just log if it fails
-                if tx.send(bridge_msg).is_err() {
-                    log::debug!("No bridge receivers listening [msg_id={}]", ctx.packet_id);
-                }
+    /// Reload which nodes are owed a mail notice, read receipt or DM retry.
+    pub(super) async fn load_owed_deliveries(&self) {
+        match self.db.run(|db| db.nodes_owed_deliveries(None)).await {
+            Ok(nodes) => *self.owed_deliveries.lock().unwrap() = nodes.into_iter().collect(),
+            Err(e) => log::error!("Failed to load owed deliveries: {}", e),
+        }
+    }
+
+    /// Send a node that was just heard whatever it is owed. Nodes owed
+    /// nothing are skipped without touching the database.
+    async fn deliver_owed(&self, my_node_id: u32, node_id: u32, channel: u32) {
+        if !self.owed_deliveries.lock().unwrap().contains(&node_id) {
+            return;
+        }
+        self.notify_expired_mail(my_node_id, node_id, channel).await;
+        self.notify_mail_receipts(my_node_id, node_id, channel)
+            .await;
+        self.retry_pending_dms(my_node_id, node_id).await;
+        match self
+            .db
+            .run(move |db| db.nodes_owed_deliveries(Some(node_id)))
+            .await
+        {
+            Ok(owed) if owed.is_empty() => {
+                self.owed_deliveries.lock().unwrap().remove(&node_id);
+            }
+            Ok(_) => {}
+            Err(e) => log::error!("Failed to check owed deliveries: {}", e),
+        }
+    }
+
+    /// Tell a node that was just heard about any of its mail that expired unread.
+    async fn notify_expired_mail(&self, my_node_id: u32, node_id: u32, channel: u32) {
+        if self.config.mail.expire_unread_after_days == 0
+            || !self.config.is_module_enabled("mail")
+            || self.is_muted(node_id).await
+        {
+            return;
+        }
+        let parts = self
+            .db
+            .run(move |db| {
+                let expired = db.take_expired_mail_for_sender(node_id)?;
+                Ok(expired
+                    .iter()
+                    .map(|(to_node, count)| {
+                        let name = db
+                            .get_node_name(*to_node)
+                            .unwrap_or_else(|_| format!("!{:08x}", to_node));
+                        format!("{} to {}", count, name)
+                    })
+                    .collect::<Vec<String>>())
+            })
+            .await;
+        let parts = match parts {
+            Ok(parts) => parts,
+            Err(e) => {
+                log::error!("Failed to load expired mail for !{:08x}: {}", node_id, e);
+                return;
+            }
+        };
+        if parts.is_empty() {
+            return;
+        }
+
+        let ctx = MessageContext {
+            sender_id: node_id,
+            sender_name: format!("!{:08x}", node_id),
+            channel,
+            is_dm: true,
+            rssi: 0,
+            snr: 0.0,
+            hop_count: 0,
+            hop_start: 0,
+            hop_limit: 0,
+            via_mqtt: false,
+            pki_key: None,
+            packet_id: 0,
+        };
+        let response = Response {
+            text: format!("Mail expired unread: {}", parts.join(", ")),
+            destination: Destination::Sender,
+            channel,
+            reply_id: None,
+            data: None,
+        };
+        self.queue_responses(&ctx, &[response], my_node_id);
+    }
+
+    /// Tell a node that was just heard which of its mail has been read.
+    async fn notify_mail_receipts(&self, my_node_id: u32, node_id: u32, channel: u32) {
+        // Muted nodes keep their receipts until they unmute
+        if !self.config.mail.read_receipts
+            || !self.config.is_module_enabled("mail")
+            || self.is_muted(node_id).await
+        {
+            return;
+        }
+        let parts = self
+            .db
+            .run(move |db| {
+                let receipts = db.take_mail_receipts(node_id)?;
+                Ok(receipts
+                    .iter()
+                    .map(|(id, to_node)| {
+                        let name = db
+                            .get_node_name(*to_node)
+                            .unwrap_or_else(|_| format!("!{:08x}", to_node));
+                        format!("#{} to {}", id, name)
+                    })
+                    .collect::<Vec<String>>())
+            })
+            .await;
+        let parts = match parts {
+            Ok(parts) => parts,
+            Err(e) => {
+                log::error!("Failed to load mail receipts for !{:08x}: {}", node_id, e);
+                return;
             }
+        };
+        if parts.is_empty() {
+            return;
         }
 
-        self.dispatch_command_from_text(my_node_id, &ctx, trimmed_text, is_dm)
+        let ctx = MessageContext {
+            sender_id: node_id,
+            sender_name: format!("!{:08x}", node_id),
+            channel,
+            is_dm: true,
+            rssi: 0,
+            snr: 0.0,
+            hop_count: 0,
+            hop_start: 0,
+            hop_limit: 0,
+            via_mqtt: false,
+            pki_key: None,
+            packet_id: 0,
+        };
+        // One DM however many were read, to spare airtime
+        let text = match parts.as_slice() {
+            [part] => format!("Your mail {} was read", part),
+            _ => format!("Your mail was read: {}", parts.join(", ")),
+        };
+        let response = Response {
+            text,
+            destination: Destination::Sender,
+            channel,
+            reply_id: None,
+            data: None,
+        };
+        self.queue_responses(&ctx, &[response], my_node_id);
+    }
+
+    fn relay_node_joined(&self, node_id: u32, long_name: &str, short_name: &str, via_mqtt: bool) {
+        let name = if long_name.is_empty() {
+            format!("!{:08x}", node_id)
+        } else {
+            long_name.to_string()
+        };
+        self.hooks.emit(HookEvent::NodeNew {
+            node: format!("!{:08x}", node_id),
+            name: name.clone(),
+            short_name: short_name.to_string(),
+            via_mqtt,
+        });
+        self.note_node_joined(&name, via_mqtt);
+        self.bridge.relay(MeshBridgeMessage {
+            sender_id: node_id,
+            sender_name: name.clone(),
+            text: format!("New node joined: {}", name),
+            channel: 0,
+            is_dm: false,
+            event: BridgeEvent::NodeJoined,
+            data: Some(ResponseData::KeyValue(vec![
+                ("Node".to_string(), format!("!{:08x}", node_id)),
+                ("Short name".to_string(), short_name.to_string()),
+                (
+                    "Via".to_string(),
+                    if via_mqtt { "MQTT" } else { "RF" }.to_string(),
+                ),
+            ])),
+            packet_id: None,
+        });
+    }
+
+    /// Relay a completed traceroute (a reply to one of our probes) with both
+    /// paths spelled out by node name.
+    async fn relay_traceroute(&self, responder: u32, towards: &[u32], back: &[u32]) {
+        let (hops_towards, hops_back) = (towards.to_vec(), back.to_vec());
+        let names = self
+            .db
+            .run(move |db| {
+                let node_name = |id: u32| {
+                    db.get_node_name(id)
+                        .unwrap_or_else(|_| format!("!{:08x}", id))
+                };
+                let route = |hops: &[u32]| {
+                    if hops.is_empty() {
+                        "direct".to_string()
+                    } else {
+                        hops.iter()
+                            .map(|&id| node_name(id))
+                            .collect::<Vec<_>>()
+                            .join(" > ")
+                    }
+                };
+                Ok((
+                    node_name(responder),
+                    route(&hops_towards),
+                    route(&hops_back),
+                ))
+            })
             .await;
+        let (name, route_towards, route_back) = match names {
+            Ok(names) => names,
+            Err(e) => {
+                log::error!("Failed to name traceroute hops: {}", e);
+                return;
+            }
+        };
+        self.hooks.emit(HookEvent::TracerouteCompleted {
+            node: format!("!{:08x}", responder),
+            name: name.clone(),
+            hops_towards: towards.len(),
+            hops_back: back.len(),
+        });
+        self.bridge.relay(MeshBridgeMessage {
+            sender_id: responder,
+            sender_name: name.clone(),
+            text: format!("Traceroute to {} completed", name),
+            channel: 0,
+            is_dm: false,
+            event: BridgeEvent::Traceroute,
+            data: Some(ResponseData::KeyValue(vec![
+                ("Hops towards".to_string(), towards.len().to_string()),
+                ("Route towards".to_string(), route_towards),
+                ("Hops back".to_string(), back.len().to_string()),
+                ("Route back".to_string(), route_back),
+            ])),
+            packet_id: None,
+        });
     }
 
-    pub(super) async fn handle_node_info(&self, my_node_id: u32, node_info: &protobufs::NodeInfo) {
+    /// Handle a NodeInfo, either an entry of the radio's NodeDB
+    /// (`from_nodedb`, dumped on connect) or a node announcing itself.
+    pub(super) async fn handle_node_info(
+        &self,
+        my_node_id: u32,
+        node_info: &protobufs::NodeInfo,
+        from_nodedb: bool,
+    ) {
         let node_id = node_info.num;
         let (long_name, short_name) = match &node_info.user {
             Some(user) => (user.long_name.clone(), user.short_name.clone()),
@@ -452,13 +1160,56 @@ impl Bot {
         };
 
         let via_mqtt = node_info.via_mqtt;
+        // Entries the radio never heard, or heard without a clock, date from now
+        let nodedb_heard_at = from_nodedb.then(|| {
+            let last_heard = node_info.last_heard as i64;
+            if last_heard >= MIN_CLOCK_TIMESTAMP {
+                last_heard
+            } else {
+                Utc::now().timestamp()
+            }
+        });
 
         log::debug!("NodeInfo: !{:08x} {} ({})", node_id, long_name, short_name);
 
         // Log nodeinfo packet (no RF metadata on NodeInfo)
-        let _ = self.db.log_packet_with_mesh_id(
-            node_id, None, 0, "", "in", via_mqtt, None, None, None, None, None, "nodeinfo",
-        );
+        self.metrics.packet("in", PacketType::NodeInfo);
+        self.publish_live(|| {
+            LiveEvent::packet(
+                node_id,
+                None,
+                PacketType::NodeInfo,
+                0,
+                via_mqtt,
+                None,
+                None,
+                None,
+                None,
+            )
+        });
+        let radio = self.radio_label(my_node_id);
+        // Checked before dispatch: the welcome module upserts the node
+        let is_new = self
+            .db
+            .run(move |db| {
+                let _ = db.log_received_packet(
+                    radio.as_deref(),
+                    node_id,
+                    None,
+                    0,
+                    "",
+                    via_mqtt,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    PacketType::NodeInfo,
+                );
+                db.is_node_new(node_id)
+            })
+            .await
+            .unwrap_or(false);
 
         // Skip dispatching events for our own node
         if node_id == my_node_id {
@@ -469,19 +1220,22 @@ impl Bot {
             // dumps all known nodes on connect — greeting them all would be spam)
             let in_grace_period = self
                 .startup_state
-                .in_grace_period(self.config.bot.startup_grace_secs);
+                .in_grace_period(self.config.bot.startup_grace_secs, self.now());
 
             if in_grace_period {
                 log::debug!(
                     "Deferring event dispatch for !{:08x} (startup grace period)",
                     node_id
                 );
-                self.startup_state.defer_event(MeshEvent::NodeDiscovered {
-                    node_id,
-                    long_name: long_name.clone(),
-                    short_name: short_name.clone(),
-                    via_mqtt,
-                });
+                self.startup_state.defer_event(
+                    MeshEvent::NodeDiscovered {
+                        node_id,
+                        long_name: long_name.clone(),
+                        short_name: short_name.clone(),
+                        via_mqtt,
+                    },
+                    nodedb_heard_at,
+                );
                 // Skip upsert/position during grace period so nodes stay "new"
                 // until deferred events are dispatched
                 return;
@@ -495,16 +1249,46 @@ impl Bot {
 
                 // Dispatch event to all modules, queuing any responses
                 self.dispatch_event_to_modules(&event, my_node_id).await;
+
+                if is_new {
+                    self.relay_node_joined(node_id, &long_name, &short_name, via_mqtt);
+                }
             }
         }
 
         // Always upsert the node (welcome module may have already done this,
         // but upsert is idempotent and updates last_seen)
         if let Err(e) = self
-            .db
-            .upsert_node(node_id, &short_name, &long_name, via_mqtt)
+            .store_node(
+                node_id,
+                &short_name,
+                &long_name,
+                via_mqtt,
+                nodedb_heard_at,
+                is_new,
+            )
+            .await
         {
             log::error!("Failed to upsert node: {}", e);
+        } else {
+            self.publish_live(|| LiveEvent::node(node_id, &short_name, &long_name, via_mqtt));
+        }
+
+        // Kept for checking who sends admin commands
+        if let Some(user) = node_info.user.as_ref().filter(|u| !u.public_key.is_empty()) {
+            let key = user.public_key.clone();
+            match self
+                .db
+                .run(move |db| db.remember_public_key(node_id, &key))
+                .await
+            {
+                Ok(true) => {}
+                Ok(false) => log::warn!(
+                    "!{:08x} announced a different public key, keeping the stored one",
+                    node_id
+                ),
+                Err(e) => log::error!("Failed to store public key: {}", e),
+            }
         }
 
         // Extract position from NodeInfo if available
@@ -513,9 +1297,56 @@ impl Bot {
                 let lat = lat_i as f64 * 1e-7;
                 let lon = lon_i as f64 * 1e-7;
                 if lat != 0.0 || lon != 0.0 {
-                    let _ = self.db.update_position(node_id, lat, lon);
+                    let _ = self
+                        .db
+                        .run(move |db| db.update_position(node_id, lat, lon))
+                        .await;
                 }
             }
         }
     }
+
+    /// Upsert a node, dated by when the radio last heard it for NodeDB
+    /// entries (`nodedb_heard_at`) so an import doesn't look like new arrivals.
+    pub(super) async fn store_node(
+        &self,
+        node_id: u32,
+        short_name: &str,
+        long_name: &str,
+        via_mqtt: bool,
+        nodedb_heard_at: Option<i64>,
+        is_new: bool,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let (short_name, long_name) = (short_name.to_string(), long_name.to_string());
+        self.db
+            .run(move |db| match nodedb_heard_at {
+                Some(heard_at) => {
+                    db.import_node(node_id, &short_name, &long_name, via_mqtt, heard_at, is_new)
+                }
+                None => db.upsert_node(node_id, &short_name, &long_name, via_mqtt),
+            })
+            .await
+    }
+
+    /// Add a Position report to the node's track, unless
+    /// `[privacy] position_history_days` is 0.
+    async fn log_position(&self, node_id: u32, pos: &protobufs::Position) {
+        if self.config.privacy.position_history_days == 0 {
+            return;
+        }
+        let point = TrackPoint {
+            timestamp: Utc::now().timestamp(),
+            latitude: pos.latitude_i.unwrap_or(0) as f64 * 1e-7,
+            longitude: pos.longitude_i.unwrap_or(0) as f64 * 1e-7,
+            altitude: pos.altitude,
+            speed: pos.ground_speed,
+        };
+        if let Err(e) = self
+            .db
+            .run(move |db| db.log_position(node_id, &point))
+            .await
+        {
+            log::error!("Failed to log position history: {}", e);
+        }
+    }
 }
@@ -0,0 +1,104 @@
+use std::time::Duration;
+
+use super::*;
+use crate::db::LatencyWindow;
+
+/// Samples kept per stage between flushes; more are dropped.
+const MAX_SAMPLES: usize = 10_000;
+/// Stored windows older than this are deleted.
+const KEEP_SECS: i64 = 30 * 24 * 60 * 60;
+
+/// Per-message latencies since the last flush, in milliseconds.
+#[derive(Default)]
+pub(super) struct LatencySamples {
+    /// Packet received to its replies queued
+    handling: Vec<u32>,
+    /// Queued to handed to the radio
+    queue: Vec<u32>,
+}
+
+fn push_sample(samples: &mut Vec<u32>, elapsed: Duration) {
+    if samples.len() < MAX_SAMPLES {
+        samples.push(elapsed.as_millis().min(u32::MAX as u128) as u32);
+    }
+}
+
+/// Nearest-rank p50, p90, p99 and max of `samples`, which is sorted in place.
+fn percentiles(samples: &mut [u32]) -> (u32, u32, u32, u32) {
+    samples.sort_unstable();
+    let rank = |p: usize| samples[(samples.len() * p).div_ceil(100).max(1) - 1];
+    (rank(50), rank(90), rank(99), samples[samples.len() - 1])
+}
+
+impl Bot {
+    /// Note how long a packet took from arrival to its replies being queued.
+    pub(super) fn record_handling_latency(&self, elapsed: Duration) {
+        log::debug!("Replies queued {}ms after receipt", elapsed.as_millis());
+        push_sample(&mut self.latency.lock().unwrap().handling, elapsed);
+    }
+
+    /// Note how long a message waited in the outgoing queue.
+    pub(super) fn record_queue_latency(&self, elapsed: Duration) {
+        log::debug!("Sent after {}ms in the queue", elapsed.as_millis());
+        push_sample(&mut self.latency.lock().unwrap().queue, elapsed);
+    }
+
+    /// Store and log the percentiles of the samples collected since the
+    /// last call, for `/api/latency`.
+    pub(super) async fn flush_latency(&self, now: i64) {
+        let samples = std::mem::take(&mut *self.latency.lock().unwrap());
+        let mut windows = Vec::new();
+        for (stage, mut samples) in [("handling", samples.handling), ("queue", samples.queue)] {
+            if samples.is_empty() {
+                continue;
+            }
+            let (p50_ms, p90_ms, p99_ms, max_ms) = percentiles(&mut samples);
+            log::info!(
+                "Latency ({}): {} messages, p50 {}ms, p90 {}ms, p99 {}ms, max {}ms",
+                stage,
+                samples.len(),
+                p50_ms,
+                p90_ms,
+                p99_ms,
+                max_ms
+            );
+            windows.push(LatencyWindow {
+                timestamp: now,
+                stage: stage.to_string(),
+                samples: samples.len() as u32,
+                p50_ms,
+                p90_ms,
+                p99_ms,
+                max_ms,
+            });
+        }
+        if windows.is_empty() {
+            return;
+        }
+        let stored = self
+            .db
+            .run(move |db| {
+                for window in &windows {
+                    db.record_latency(window, KEEP_SECS)?;
+                }
+                Ok(())
+            })
+            .await;
+        if let Err(e) = stored {
+            log::error!("Failed to store latency stats: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentiles() {
+        let mut samples: Vec<u32> = (1..=100).rev().collect();
+        assert_eq!(percentiles(&mut samples), (50, 90, 99, 100));
+        assert_eq!(percentiles(&mut [7]), (7, 7, 7, 7));
+        assert_eq!(percentiles(&mut [30, 10, 20]), (20, 30, 30, 30));
+    }
+}
@@ -0,0 +1,50 @@
+use crate::message::MeshEvent;
+
+use super::*;
+
+impl Bot {
+    /// Flip `nodes.online` for nodes that crossed `[freshness] stale_mins`
+    /// either way and, unless this is the baseline check made when the
+    /// startup grace period ends, tell modules with `NodeOffline` and
+    /// `NodeBackOnline`.
+    pub(super) async fn check_node_liveness(&self, my_node_id: u32, now: i64, baseline: bool) {
+        let cutoff = now - self.config.freshness.stale_mins as i64 * 60;
+        let changes = match self.db.run(move |db| db.update_node_liveness(cutoff)).await {
+            Ok(changes) => changes,
+            Err(e) => {
+                log::error!("Failed to update node liveness: {}", e);
+                return;
+            }
+        };
+        if baseline {
+            return;
+        }
+        for change in changes {
+            if change.node_id == my_node_id {
+                continue;
+            }
+            log::debug!(
+                "Node !{:08x} is {}",
+                change.node_id,
+                if change.online {
+                    "back online"
+                } else {
+                    "offline"
+                }
+            );
+            let event = if change.online {
+                MeshEvent::NodeBackOnline {
+                    node_id: change.node_id,
+                    via_mqtt: change.via_mqtt,
+                }
+            } else {
+                MeshEvent::NodeOffline {
+                    node_id: change.node_id,
+                    last_seen: change.last_seen,
+                    via_mqtt: change.via_mqtt,
+                }
+            };
+            self.dispatch_event_to_modules(&event, my_node_id).await;
+        }
+    }
+}
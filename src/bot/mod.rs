@@ -1,49 +1,134 @@
-use std::sync::atomic::{AtomicU32, AtomicUsize};
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize};
+use std::sync::{Arc, Mutex, RwLock};
 
 use crate::bridge::{MeshMessageSender, OutgoingMessageReceiver};
+use crate::bridge_filter::BridgeFilter;
 use crate::config::Config;
 use crate::db::Db;
-use crate::module::ModuleRegistry;
+use crate::event_hooks::EventHooks;
+use crate::links::LinkShortener;
+use crate::live::LiveEvent;
+use crate::metrics::Metrics;
+use crate::module::{Module, ModuleRegistry, RateLimit};
 
+mod admin;
+mod alerts;
+mod banner;
+mod battery;
+mod beacons;
 mod bridge_state;
+mod channel_budget;
+mod clock;
 mod command_handler;
 mod dashboard_notifier;
+mod emergency;
+mod environment_alerts;
 mod events;
+mod geofence;
+mod hooks;
 mod incoming;
+mod latency;
+mod liveness;
+mod node_activity;
+mod node_admin;
 mod outgoing;
+mod pending_sends;
 mod rate_limit;
 mod runtime;
+mod schedule;
+mod selftest;
+mod snapshot;
 mod startup_state;
+mod store_forward;
+mod trace;
+mod traceroute_abuse;
 mod traceroute_state;
+mod weather_alerts;
 
 #[cfg(test)]
 mod tests;
 
+use alerts::AlertRules;
 use bridge_state::BridgeState;
+use channel_budget::ChannelBudgets;
+use clock::{Clock, SystemClock};
 use dashboard_notifier::DashboardNotifier;
+use environment_alerts::EnvironmentAlerts;
+use geofence::Geofences;
+use node_activity::NodeActivity;
+use node_admin::NodeAdmin;
 use outgoing::{OutgoingKind, OutgoingMeshMessage, OutgoingQueue};
 use rate_limit::RateLimiter;
+use runtime::RecentPackets;
 use startup_state::StartupState;
 use traceroute_state::TracerouteState;
 
 pub struct Bot {
     config: Arc<Config>,
     db: Arc<Db>,
-    registry: Arc<ModuleRegistry>,
+    /// Replaced by `!admin reload`; read with `registry()`
+    registry: RwLock<Arc<ModuleRegistry>>,
+    /// `[bot] admin_nodes`, replaced by `!admin reload`
+    admins: RwLock<HashSet<u32>>,
+    /// Config the modules were last built from, replaced by `!admin reload`
+    modules_config: Arc<RwLock<Arc<Config>>>,
     rate_limiter: RateLimiter,
+    /// Limiters for modules with their own limit, keyed by module name.
+    module_rate_limiters: HashMap<String, RateLimiter>,
     /// Tracks startup timing + deferred events for grace-period handling.
     startup_state: StartupState,
     /// Channel state for bridge in/out communication.
     bridge: BridgeState,
     /// Outgoing message queue drained by the event loop timer
     outgoing: OutgoingQueue,
+    /// `[[channel_budgets]]` and what each radio sent against them
+    channel_budgets: ChannelBudgets,
     /// SSE broadcast sender for real-time dashboard updates
     notifier: DashboardNotifier,
     /// Last traceroute probe send time per target node
     traceroute: TracerouteState,
-    /// Node ID of the connected local node (0 until MyInfo is received)
+    /// Node ID of the primary connection's local node (0 until MyInfo is received)
     local_node_id: Arc<AtomicU32>,
+    /// Whether the primary connection is up, shared with bridges
+    mesh_online: Arc<AtomicBool>,
+    /// Connected radios by node ID, with their connection label
+    radios: RwLock<HashMap<u32, String>>,
+    /// Recently handled packets, shared by all connections for deduplication
+    recent_packets: Mutex<RecentPackets>,
+    /// Text packets we transmitted, so they aren't relayed back to bridges
+    sent_packets: Mutex<RecentPackets>,
+    /// Firmware version reported by the radio's device metadata
+    firmware_version: Arc<RwLock<Option<String>>>,
+    /// Shortens long URLs in chat relayed from bridges, when enabled
+    links: Option<Arc<LinkShortener>>,
+    /// Threshold rules on environment telemetry and their per-sensor state
+    environment_alerts: EnvironmentAlerts,
+    /// `[[geofence]]` circles and which nodes are inside each
+    geofences: Geofences,
+    /// `[[alerts]]` rules, evaluated every minute
+    alert_rules: AlertRules,
+    /// Config file read by `!admin reload`
+    config_path: Option<std::path::PathBuf>,
+    /// `[event_hooks]` webhooks (disabled unless set)
+    hooks: EventHooks,
+    /// When RF nodes were last checked for going offline (unix seconds)
+    offline_checked_at: Mutex<i64>,
+    /// Nodes that may be owed a mail notice, read receipt or DM retry, so
+    /// packets from everyone else skip those lookups
+    owed_deliveries: Mutex<HashSet<u32>>,
+    /// `[bridge.node_events]` waiting for the next post
+    node_activity: Mutex<NodeActivity>,
+    /// `[remote_admin]` nodes and their admin sessions
+    node_admin: NodeAdmin,
+    /// `!admin db vacuum` in progress
+    db_maintenance: Mutex<Option<admin::DbMaintenance>>,
+    /// Handling and queue latencies since they were last stored
+    latency: Mutex<latency::LatencySamples>,
+    /// Counters for `/metrics`, shared with the bridges and dashboard
+    metrics: Arc<Metrics>,
+    /// Read for queue expiry, cooldowns and the grace period
+    clock: Arc<dyn Clock>,
 }
 
 impl Bot {
@@ -52,17 +137,82 @@ impl Bot {
             config.bot.rate_limit_commands,
             config.bot.rate_limit_window_secs,
         );
+        let module_rate_limiters = registry
+            .all()
+            .iter()
+            .filter_map(|module| {
+                let limit = Self::module_rate_limit(&config, module.as_ref())?;
+                log::info!(
+                    "Rate limit for {}: {} per {}s",
+                    module.name(),
+                    limit.max_commands,
+                    limit.window_secs
+                );
+                Some((
+                    module.name().to_string(),
+                    RateLimiter::new(limit.max_commands, limit.window_secs),
+                ))
+            })
+            .collect();
+        let links = LinkShortener::from_config(&config.bridge.links).map(Arc::new);
+        let environment_alerts = EnvironmentAlerts::new(&config.environment_alerts);
+        let geofences = Geofences::new(&config.geofence);
+        let alert_rules = AlertRules::new(&config.alerts);
+        let channel_budgets = ChannelBudgets::new(&config.channel_budgets);
+        let node_admin = NodeAdmin::new(&config.remote_admin.nodes);
+        let admins = admin::parse_admins(&config.bot.admin_nodes);
+        let modules_config = Arc::new(RwLock::new(Arc::clone(&config)));
         Self {
             config,
             db,
-            registry: Arc::new(registry),
+            registry: RwLock::new(Arc::new(registry)),
+            admins: RwLock::new(admins),
+            modules_config,
             rate_limiter,
+            module_rate_limiters,
             startup_state: StartupState::new(),
             bridge: BridgeState::new(),
             outgoing: OutgoingQueue::new(),
+            channel_budgets,
             notifier: DashboardNotifier::new(),
             traceroute: TracerouteState::new(),
             local_node_id: Arc::new(AtomicU32::new(0)),
+            mesh_online: Arc::new(AtomicBool::new(false)),
+            radios: RwLock::new(HashMap::new()),
+            recent_packets: Mutex::new(RecentPackets::new(1024)),
+            sent_packets: Mutex::new(RecentPackets::new(256)),
+            firmware_version: Arc::new(RwLock::new(None)),
+            links,
+            environment_alerts,
+            geofences,
+            alert_rules,
+            config_path: None,
+            hooks: EventHooks::default(),
+            offline_checked_at: Mutex::new(chrono::Utc::now().timestamp()),
+            owed_deliveries: Mutex::new(HashSet::new()),
+            node_activity: Mutex::new(NodeActivity::new(chrono::Utc::now().timestamp())),
+            node_admin,
+            db_maintenance: Mutex::new(None),
+            latency: Mutex::new(Default::default()),
+            metrics: Arc::new(Metrics::default()),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// The module's own command limit: `[modules.<name>] rate_limit` if set,
+    /// otherwise whatever the module declares.
+    fn module_rate_limit(config: &Config, module: &dyn Module) -> Option<RateLimit> {
+        let declared = module.rate_limit();
+        let module_config = config.modules.get(module.name());
+        match module_config.and_then(|m| m.rate_limit) {
+            Some(max_commands) => Some(RateLimit {
+                max_commands,
+                window_secs: module_config
+                    .and_then(|m| m.rate_limit_window_secs)
+                    .or(declared.map(|d| d.window_secs))
+                    .unwrap_or(config.bot.rate_limit_window_secs),
+            }),
+            None => declared,
         }
     }
 
@@ -76,6 +226,27 @@ impl Bot {
         Arc::clone(&self.local_node_id)
     }
 
+    /// Returns a handle that is true while the primary connection is up (for bridges).
+    pub fn mesh_online(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.mesh_online)
+    }
+
+    /// Returns the radio firmware version handle (`None` until metadata is received).
+    pub fn firmware_version(&self) -> Arc<RwLock<Option<String>>> {
+        Arc::clone(&self.firmware_version)
+    }
+
+    /// Returns the config the modules run with, as `!admin reload` last
+    /// built them (for the dashboard's settings).
+    pub fn modules_config(&self) -> Arc<RwLock<Arc<Config>>> {
+        Arc::clone(&self.modules_config)
+    }
+
+    /// Returns the `/metrics` counters (for the bridges and dashboard).
+    pub fn metrics(&self) -> Arc<Metrics> {
+        Arc::clone(&self.metrics)
+    }
+
     /// Set bridge channels for communication with external platforms.
     pub fn with_bridge_channels(
         mut self,
@@ -86,18 +257,89 @@ impl Bot {
         self
     }
 
+    /// Set the redaction/blocking rules applied to mesh text before bridging.
+    pub fn with_bridge_filter(mut self, filter: Arc<BridgeFilter>) -> Self {
+        self.bridge.set_filter(filter);
+        self
+    }
+
     /// Set the SSE broadcast sender for real-time dashboard notifications.
-    pub fn with_sse_sender(mut self, tx: tokio::sync::broadcast::Sender<()>) -> Self {
+    pub fn with_sse_sender(mut self, tx: tokio::sync::broadcast::Sender<LiveEvent>) -> Self {
         self.notifier.set_sender(tx);
         self
     }
 
+    /// Replace the clock, to step through timers in tests.
+    #[cfg(test)]
+    fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    fn now(&self) -> std::time::Instant {
+        self.clock.now()
+    }
+
+    /// The current modules. Taken once per command or event, so a reload
+    /// halfway through doesn't mix two registries.
+    fn registry(&self) -> Arc<ModuleRegistry> {
+        Arc::clone(&self.registry.read().unwrap())
+    }
+
+    /// Whether `node_id` opted out of unsolicited DMs with `!mute`. Checked
+    /// wherever the bot messages a node that didn't just send it a command.
+    async fn is_muted(&self, node_id: u32) -> bool {
+        self.db
+            .run(move |db| db.is_muted(node_id))
+            .await
+            .unwrap_or_else(|e| {
+                log::error!("Failed to check mute for !{:08x}: {}", node_id, e);
+                false
+            })
+    }
+
+    /// A node's name for messages, its `!id` when the lookup fails.
+    async fn node_name(&self, node_id: u32) -> String {
+        self.db
+            .run(move |db| db.get_node_name(node_id))
+            .await
+            .unwrap_or_else(|_| format!("!{:08x}", node_id))
+    }
+
     /// Notify the dashboard that data has changed (non-blocking, best-effort).
     fn notify_dashboard(&self) {
         self.notifier.notify();
     }
 
+    /// Stream what changed to the dashboard's live view.
+    fn publish_live(&self, event: impl FnOnce() -> LiveEvent) {
+        self.notifier.publish(event);
+    }
+
+    fn publish_queue_depth(&self) {
+        self.publish_live(|| LiveEvent::QueueDepth {
+            depth: self
+                .outgoing
+                .depth_handle()
+                .load(std::sync::atomic::Ordering::Relaxed),
+        });
+    }
+
     fn queue_message(&self, msg: OutgoingMeshMessage) {
-        self.outgoing.push(msg);
+        self.outgoing.push(msg, self.now());
+        self.publish_queue_depth();
+        self.check_queue_overflow();
+    }
+
+    /// Label of the connection whose local node is `my_node_id`.
+    fn radio_label(&self, my_node_id: u32) -> Option<String> {
+        self.radios.read().unwrap().get(&my_node_id).cloned()
+    }
+
+    /// Whether the radio `my_node_id` should send `from_node`'s message: its
+    /// own, plus (on the primary) any whose radio is not connected.
+    fn sends_for(&self, my_node_id: u32, primary: bool, from_node: u32) -> bool {
+        from_node == my_node_id
+            || (primary && !self.radios.read().unwrap().contains_key(&from_node))
     }
 }
@@ -0,0 +1,116 @@
+use super::*;
+use crate::bridge::{BridgeEvent, MeshBridgeMessage};
+
+/// Names listed per line of a summary; the rest are counted.
+const MAX_NAMES: usize = 10;
+
+/// Joins and departures waiting to be posted to the bridges together, so a
+/// burst of NodeInfo (a reconnect, a busy evening) becomes one message.
+pub(super) struct NodeActivity {
+    joined: Vec<String>,
+    offline: Vec<String>,
+    /// When RF nodes were last checked for `offline_hours` (unix seconds)
+    checked_at: i64,
+}
+
+impl NodeActivity {
+    pub(super) fn new(now: i64) -> Self {
+        Self {
+            joined: Vec::new(),
+            offline: Vec::new(),
+            checked_at: now,
+        }
+    }
+}
+
+/// `"A, B and 3 more"`.
+fn name_list(names: &[String]) -> String {
+    let shown = names[..names.len().min(MAX_NAMES)].join(", ");
+    if names.len() > MAX_NAMES {
+        format!("{} and {} more", shown, names.len() - MAX_NAMES)
+    } else {
+        shown
+    }
+}
+
+/// One line per kind of activity, or `None` when there is nothing to post.
+pub(super) fn activity_text(
+    joined: &[String],
+    offline: &[String],
+    offline_hours: u32,
+) -> Option<String> {
+    let mut lines = Vec::new();
+    if !joined.is_empty() {
+        lines.push(format!("Joined the mesh: {}", name_list(joined)));
+    }
+    if !offline.is_empty() {
+        lines.push(format!(
+            "Not heard for {}h: {}",
+            offline_hours,
+            name_list(offline)
+        ));
+    }
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
+
+impl Bot {
+    /// Queue a new RF node for the next `[bridge.node_events]` post.
+    pub(super) fn note_node_joined(&self, name: &str, via_mqtt: bool) {
+        if self.config.bridge.node_events.joined && !via_mqtt {
+            self.node_activity
+                .lock()
+                .unwrap()
+                .joined
+                .push(name.to_string());
+        }
+    }
+
+    /// Add RF nodes that passed `offline_hours` since the last call, then
+    /// post everything collected as one bridge message.
+    pub(super) async fn post_node_activity(&self, my_node_id: u32, now: i64) {
+        let config = &self.config.bridge.node_events;
+        if config.offline_hours > 0 {
+            let offline_secs = config.offline_hours as i64 * 3600;
+            let last_check =
+                std::mem::replace(&mut self.node_activity.lock().unwrap().checked_at, now);
+            match self
+                .db
+                .run(move |db| {
+                    db.rf_nodes_last_heard_between(last_check - offline_secs, now - offline_secs)
+                })
+                .await
+            {
+                Ok(nodes) => self
+                    .node_activity
+                    .lock()
+                    .unwrap()
+                    .offline
+                    .extend(nodes.into_iter().map(|n| n.name)),
+                Err(e) => log::error!("Failed to check for silent nodes: {}", e),
+            }
+        }
+        let mut activity = self.node_activity.lock().unwrap();
+        let joined = std::mem::take(&mut activity.joined);
+        let offline = std::mem::take(&mut activity.offline);
+        drop(activity);
+
+        let Some(text) = activity_text(&joined, &offline, config.offline_hours) else {
+            return;
+        };
+        log::info!(
+            "Posting node activity to bridges: {} joined, {} silent",
+            joined.len(),
+            offline.len()
+        );
+        self.bridge.relay(MeshBridgeMessage {
+            sender_id: my_node_id,
+            sender_name: self.config.bot.name.clone(),
+            text,
+            channel: config.channel,
+            is_dm: false,
+            event: BridgeEvent::NodeActivity,
+            data: None,
+            packet_id: None,
+        });
+    }
+}
@@ -0,0 +1,242 @@
+//! `!admin node`: reboot or rename nodes listed in `[remote_admin] nodes`
+//! with Meshtastic admin messages. Since firmware 2.5 a remote node only
+//! accepts a change carrying the session passkey from one of its own admin
+//! replies, so each action first asks the node for its metadata and goes out
+//! when the reply (and its passkey) comes back.
+
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use meshtastic::packet::PacketDestination;
+use meshtastic::protobufs::{self, admin_message, AdminMessage};
+use meshtastic::types::{MeshChannel, NodeId};
+
+use crate::message::{Destination, MessageContext, Response};
+use crate::util::parse_node_id;
+
+use super::*;
+
+/// Nodes keep a passkey for 300s; reuse it a little less than that.
+const SESSION_TTL: Duration = Duration::from_secs(270);
+/// An action still waiting for its passkey is dropped after this.
+const PENDING_TTL: Duration = Duration::from_secs(120);
+/// Delay before a remote reboot, as the official clients use.
+const DEFAULT_REBOOT_SECS: i32 = 5;
+const MAX_SHORT_NAME_LEN: usize = 4;
+const MAX_LONG_NAME_LEN: usize = 39;
+
+const USAGE: &str =
+    "Usage: !admin node reboot <node> [secs] | set-owner <node> <short> <long name>";
+
+/// An action waiting for the node's passkey, and the admin who asked.
+struct PendingAction {
+    action: admin_message::PayloadVariant,
+    ctx: MessageContext,
+    requested_at: Instant,
+}
+
+pub(super) struct NodeAdmin {
+    nodes: HashSet<u32>,
+    /// Latest passkey per node, with when it arrived
+    sessions: Mutex<HashMap<u32, (Instant, Vec<u8>)>>,
+    pending: Mutex<HashMap<u32, PendingAction>>,
+}
+
+impl NodeAdmin {
+    pub(super) fn new(nodes: &[String]) -> Self {
+        Self {
+            nodes: nodes.iter().filter_map(|n| parse_node_id(n)).collect(),
+            sessions: Mutex::new(HashMap::new()),
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn session(&self, node: u32, now: Instant) -> Option<Vec<u8>> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .get(&node)
+            .filter(|(at, _)| now.duration_since(*at) < SESSION_TTL)
+            .map(|(_, key)| key.clone())
+    }
+}
+
+/// What the admin is told an action was.
+fn describe(action: &admin_message::PayloadVariant) -> &'static str {
+    match action {
+        admin_message::PayloadVariant::RebootSeconds(_) => "reboot",
+        admin_message::PayloadVariant::SetOwner(_) => "owner change",
+        _ => "admin request",
+    }
+}
+
+impl Bot {
+    /// `!admin node reboot <node> [secs]` and `!admin node set-owner <node>
+    /// <short> <long name>`. Callers check `is_admin` first.
+    pub(super) async fn node_admin_command(
+        &self,
+        my_node_id: u32,
+        ctx: &MessageContext,
+        args: &str,
+    ) -> String {
+        let mut parts = args.splitn(3, ' ').map(str::trim);
+        let (Some(action), Some(target)) = (parts.next(), parts.next()) else {
+            return USAGE.to_string();
+        };
+        let rest = parts.next().unwrap_or("");
+        let query = target.to_string();
+        let found = self.db.run(move |db| db.find_node_by_name(&query)).await;
+        let node = match found.ok().flatten().or_else(|| parse_node_id(target)) {
+            Some(node) => node,
+            None => return format!("Unknown node: {}", target),
+        };
+        if !self.node_admin.nodes.contains(&node) {
+            log::warn!(
+                "Refused remote admin of !{:08x} by {}: not in [remote_admin] nodes",
+                node,
+                ctx.sender_name
+            );
+            return format!("!{:08x} is not in [remote_admin] nodes.", node);
+        }
+        let action = match action.to_lowercase().as_str() {
+            "reboot" => match rest {
+                "" => admin_message::PayloadVariant::RebootSeconds(DEFAULT_REBOOT_SECS),
+                secs => match secs.parse::<i32>() {
+                    Ok(secs) if secs >= 0 => admin_message::PayloadVariant::RebootSeconds(secs),
+                    _ => return USAGE.to_string(),
+                },
+            },
+            "set-owner" => {
+                let Some((short_name, long_name)) = rest.split_once(' ') else {
+                    return USAGE.to_string();
+                };
+                let long_name = long_name.trim();
+                if short_name.chars().count() > MAX_SHORT_NAME_LEN
+                    || long_name.len() > MAX_LONG_NAME_LEN
+                {
+                    return format!(
+                        "Names too long (short {} chars, long {} bytes max).",
+                        MAX_SHORT_NAME_LEN, MAX_LONG_NAME_LEN
+                    );
+                }
+                admin_message::PayloadVariant::SetOwner(protobufs::User {
+                    id: format!("!{:08x}", node),
+                    short_name: short_name.to_string(),
+                    long_name: long_name.to_string(),
+                    ..Default::default()
+                })
+            }
+            _ => return USAGE.to_string(),
+        };
+        let name = self.node_name(node).await;
+        log::info!(
+            "Remote {} of !{:08x} requested by admin {}",
+            describe(&action),
+            node,
+            ctx.sender_name
+        );
+        if let Some(passkey) = self.node_admin.session(node, self.now()) {
+            let text = format!("Sent {} to {}.", describe(&action), name);
+            self.queue_node_admin(my_node_id, node, action, passkey);
+            return text;
+        }
+        self.node_admin.pending.lock().unwrap().insert(
+            node,
+            PendingAction {
+                action,
+                ctx: ctx.clone(),
+                requested_at: self.now(),
+            },
+        );
+        self.queue_node_admin(
+            my_node_id,
+            node,
+            admin_message::PayloadVariant::GetDeviceMetadataRequest(true),
+            Vec::new(),
+        );
+        format!("Asking {} for an admin session, you'll get a DM.", name)
+    }
+
+    /// An AdminApp packet from a managed node: keep its passkey and send the
+    /// action that was waiting for it.
+    pub(super) async fn handle_node_admin_response(
+        &self,
+        my_node_id: u32,
+        mesh_packet: &protobufs::MeshPacket,
+        data: &protobufs::Data,
+    ) {
+        let node = mesh_packet.from;
+        if !self.node_admin.nodes.contains(&node) {
+            return;
+        }
+        let message: AdminMessage = match meshtastic::Message::decode(data.payload.as_slice()) {
+            Ok(message) => message,
+            Err(_) => return,
+        };
+        if message.session_passkey.is_empty() {
+            return;
+        }
+        let now = self.now();
+        self.node_admin
+            .sessions
+            .lock()
+            .unwrap()
+            .insert(node, (now, message.session_passkey.clone()));
+        let pending = self.node_admin.pending.lock().unwrap().remove(&node);
+        let Some(pending) = pending else {
+            return;
+        };
+        if now.duration_since(pending.requested_at) > PENDING_TTL {
+            log::warn!(
+                "Dropped remote {} of !{:08x}: passkey came too late",
+                describe(&pending.action),
+                node
+            );
+            return;
+        }
+        let name = self.node_name(node).await;
+        let text = format!("Sent {} to {}.", describe(&pending.action), name);
+        self.queue_node_admin(my_node_id, node, pending.action, message.session_passkey);
+        let response = Response {
+            text,
+            destination: Destination::Sender,
+            channel: pending.ctx.channel,
+            reply_id: None,
+            data: None,
+        };
+        self.queue_responses(&pending.ctx, &[response], my_node_id);
+    }
+
+    fn queue_node_admin(
+        &self,
+        my_node_id: u32,
+        node: u32,
+        action: admin_message::PayloadVariant,
+        session_passkey: Vec<u8>,
+    ) {
+        let channel = match MeshChannel::new(0) {
+            Ok(ch) => ch,
+            Err(e) => {
+                log::error!("Invalid channel 0: {}", e);
+                return;
+            }
+        };
+        self.queue_message(OutgoingMeshMessage {
+            kind: OutgoingKind::NodeAdmin {
+                target_node: node,
+                request: AdminMessage {
+                    session_passkey,
+                    payload_variant: Some(action),
+                },
+            },
+            text: String::new(),
+            destination: PacketDestination::Node(NodeId::from(node)),
+            channel,
+            from_node: my_node_id,
+            to_node: Some(node),
+            mesh_channel: 0,
+            reply_id: None,
+            expires_at: None,
+        });
+    }
+}
@@ -1,6 +1,7 @@
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
 use meshtastic::packet::PacketDestination;
 use meshtastic::protobufs;
@@ -8,15 +9,40 @@ use meshtastic::types::{MeshChannel, NodeId};
 use meshtastic::utils::generate_rand_id;
 use meshtastic::Message;
 
+use crate::db::PendingSend;
 use crate::message::{Destination, MessageContext, Response};
+use crate::packet_type::PacketType;
+use crate::util::format_duration;
 
-use super::runtime::BotPacketRouter;
+use super::channel_budget::waits_for_budget;
+use super::runtime::Uplink;
 use super::*;
 
 #[derive(Debug, Clone)]
 pub(super) enum OutgoingKind {
     Text,
-    Traceroute { target_node: u32 },
+    /// Another try at an unacknowledged DM, tracked in `pending_sends`.
+    RetryText {
+        pending_id: i64,
+    },
+    Traceroute {
+        target_node: u32,
+    },
+    StoreForward {
+        target_node: u32,
+        request: protobufs::StoreAndForward,
+    },
+    /// `!admin node` request, PKI-encrypted to the node.
+    NodeAdmin {
+        target_node: u32,
+        request: protobufs::AdminMessage,
+    },
+    /// Scheduled test broadcast; its packet ID is recorded to match acks.
+    SelfTest,
+    /// Chat from a bridge; its packet ID is noted in the `bridge_log` row.
+    BridgeText {
+        log_id: i64,
+    },
 }
 
 #[derive(Debug, Clone)]
@@ -33,11 +59,16 @@ pub(super) struct OutgoingMeshMessage {
     pub(super) mesh_channel: u32,
     /// If set, this message is a reply to the incoming packet with this ID
     pub(super) reply_id: Option<u32>,
+    /// Bridge messages are dropped instead of sent once this has passed
+    pub(super) expires_at: Option<Instant>,
 }
 
+/// Messages waiting to be sent, each with when it was queued.
 pub(super) struct OutgoingQueue {
-    queue: Mutex<VecDeque<OutgoingMeshMessage>>,
+    queue: Mutex<VecDeque<(Instant, OutgoingMeshMessage)>>,
     depth: Arc<AtomicUsize>,
+    /// Messages ever queued
+    pushed: AtomicUsize,
 }
 
 impl OutgoingQueue {
@@ -45,6 +76,7 @@ impl OutgoingQueue {
         Self {
             queue: Mutex::new(VecDeque::new()),
             depth: Arc::new(AtomicUsize::new(0)),
+            pushed: AtomicUsize::new(0),
         }
     }
 
@@ -52,26 +84,70 @@ impl OutgoingQueue {
         Arc::clone(&self.depth)
     }
 
-    pub(super) fn push(&self, msg: OutgoingMeshMessage) {
-        self.queue.lock().unwrap().push_back(msg);
+    /// How many messages were ever queued, to tell whether handling a
+    /// packet queued any.
+    pub(super) fn pushed(&self) -> usize {
+        self.pushed.load(Ordering::Relaxed)
+    }
+
+    pub(super) fn push(&self, msg: OutgoingMeshMessage, queued_at: Instant) {
+        self.queue.lock().unwrap().push_back((queued_at, msg));
+        self.depth.fetch_add(1, Ordering::Relaxed);
+        self.pushed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Queue a message ahead of everything else (emergency acknowledgements).
+    pub(super) fn push_front(&self, msg: OutgoingMeshMessage, queued_at: Instant) {
+        self.queue.lock().unwrap().push_front((queued_at, msg));
         self.depth.fetch_add(1, Ordering::Relaxed);
+        self.pushed.fetch_add(1, Ordering::Relaxed);
     }
 
-    pub(super) fn pop(&self) -> Option<OutgoingMeshMessage> {
-        let msg = self.queue.lock().unwrap().pop_front();
-        if msg.is_some() {
+    /// Remove the first queued message matching `pred`, with when it was
+    /// queued.
+    pub(super) fn pop_where(
+        &self,
+        pred: impl Fn(&OutgoingMeshMessage) -> bool,
+    ) -> Option<(Instant, OutgoingMeshMessage)> {
+        let mut queue = self.queue.lock().unwrap();
+        let index = queue.iter().position(|(_, msg)| pred(msg))?;
+        let entry = queue.remove(index);
+        if entry.is_some() {
             self.depth.fetch_sub(1, Ordering::Relaxed);
         }
-        msg
+        entry
+    }
+
+    /// How many queued messages are addressed to `node`, and how many
+    /// messages their radios send up to and including the last of them.
+    pub(super) fn queued_for(&self, node: u32) -> (usize, usize) {
+        let queue = self.queue.lock().unwrap();
+        let mut per_radio: HashMap<u32, usize> = HashMap::new();
+        let mut count = 0;
+        let mut last = 0;
+        for (_, msg) in queue.iter() {
+            let position = per_radio.entry(msg.from_node).or_default();
+            *position += 1;
+            if msg.to_node == Some(node) {
+                count += 1;
+                last = last.max(*position);
+            }
+        }
+        (count, last)
     }
 
-    pub(super) fn is_empty(&self) -> bool {
-        self.queue.lock().unwrap().is_empty()
+    pub(super) fn any(&self, pred: impl Fn(&OutgoingMeshMessage) -> bool) -> bool {
+        self.queue.lock().unwrap().iter().any(|(_, msg)| pred(msg))
     }
 
     #[cfg(test)]
     pub(super) fn snapshot(&self) -> Vec<OutgoingMeshMessage> {
-        self.queue.lock().unwrap().iter().cloned().collect()
+        self.queue
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(_, msg)| msg.clone())
+            .collect()
     }
 }
 
@@ -103,7 +179,7 @@ impl Bot {
                 Destination::Broadcast => None,
             };
 
-            let chunks = chunk_message(&response.text, self.config.bot.max_message_len);
+            let chunks = chunk_message(&response.mesh_text(), self.config.bot.max_message_len);
             for (i, chunk) in chunks.into_iter().enumerate() {
                 self.queue_message(OutgoingMeshMessage {
                     kind: OutgoingKind::Text,
@@ -115,24 +191,145 @@ impl Bot {
                     mesh_channel: response.channel,
                     // Only the first chunk carries the reply_id
                     reply_id: if i == 0 { response.reply_id } else { None },
+                    expires_at: None,
                 });
             }
         }
     }
 
-    /// Pop and send the next message from the outgoing queue.
+    pub(super) fn has_queued_messages_for(&self, my_node_id: u32, primary: bool) -> bool {
+        self.outgoing
+            .any(|msg| self.sends_for(my_node_id, primary, msg.from_node))
+    }
+
+    /// `!queue`: how many messages wait for the sender and roughly when the
+    /// last goes out, one per `[bot] send_delay_ms` from each radio.
+    pub(super) fn queue_command(&self, ctx: &MessageContext) -> Vec<Response> {
+        let (count, last) = self.outgoing.queued_for(ctx.sender_id);
+        let text = if count == 0 {
+            format!(
+                "Nothing queued for you ({} message(s) in the queue).",
+                self.outgoing.depth_handle().load(Ordering::Relaxed)
+            )
+        } else {
+            let wait_ms = self.config.bot.send_delay_ms.saturating_mul(last as u64);
+            format!(
+                "{} message(s) queued for you, all sent in about {}.",
+                count,
+                format_duration(wait_ms.div_ceil(1000))
+            )
+        };
+        vec![Response {
+            text,
+            destination: Destination::Sender,
+            channel: ctx.channel,
+            reply_id: Some(ctx.packet_id),
+            data: None,
+        }]
+    }
+
+    /// Pop the next message this radio is responsible for, dropping bridge
+    /// messages that expired while waiting. Over a channel's budget, replies
+    /// and chat stay queued while other messages are dropped.
+    pub(super) fn pop_sendable(
+        &self,
+        my_node_id: u32,
+        primary: bool,
+    ) -> Option<OutgoingMeshMessage> {
+        loop {
+            let now = self.now();
+            let (queued_at, msg) = self.outgoing.pop_where(|msg| {
+                self.sends_for(my_node_id, primary, msg.from_node)
+                    && (!waits_for_budget(msg) || self.channel_budgets.has_room(msg, now))
+            })?;
+            self.publish_queue_depth();
+            if msg.expires_at.is_some_and(|t| now >= t) {
+                log::warn!("Dropping expired bridge message: {:?}", msg.text);
+                continue;
+            }
+            if !self.channel_budgets.has_room(&msg, now) {
+                log::warn!(
+                    "Dropping message over channel {} budget: {:?}",
+                    msg.mesh_channel,
+                    msg.text
+                );
+                continue;
+            }
+            self.channel_budgets.record(&msg, now);
+            self.record_queue_latency(now.saturating_duration_since(queued_at));
+            return Some(msg);
+        }
+    }
+
+    /// Record a text packet we transmitted, see `is_own_packet`.
+    fn remember_sent(&self, from: u32, id: u32) {
+        self.sent_packets.lock().unwrap().insert(from, id);
+    }
+
+    /// Whether a received packet is one of our own transmissions heard back.
+    pub(super) fn is_own_packet(&self, mesh_packet: &protobufs::MeshPacket) -> bool {
+        self.sent_packets
+            .lock()
+            .unwrap()
+            .contains(mesh_packet.from, mesh_packet.id)
+    }
+
+    /// Pop and send the next message this radio is responsible for.
+    /// Log a packet we're sending (no RF metadata); returns its row ID.
+    async fn log_sent_packet(
+        &self,
+        from_node: u32,
+        to_node: Option<u32>,
+        channel: u32,
+        text: &str,
+        mesh_packet_id: Option<u32>,
+        kind: PacketType,
+    ) -> Option<i64> {
+        let text = text.to_string();
+        self.db
+            .run(move |db| {
+                db.log_packet_with_mesh_id(
+                    from_node,
+                    to_node,
+                    channel,
+                    &text,
+                    "out",
+                    false,
+                    None,
+                    None,
+                    None,
+                    None,
+                    mesh_packet_id,
+                    kind,
+                )
+            })
+            .await
+            .ok()
+    }
+
     pub(super) async fn send_next_queued_message(
         &self,
-        api: &mut meshtastic::api::ConnectedStreamApi,
-        router: &mut BotPacketRouter,
+        my_node_id: u32,
+        primary: bool,
+        uplink: &mut Uplink,
     ) {
-        let msg = match self.outgoing.pop() {
+        let msg = match self.pop_sendable(my_node_id, primary) {
             Some(m) => m,
             None => return,
         };
 
+        let retry_of = match msg.kind {
+            OutgoingKind::RetryText { pending_id } => Some(pending_id),
+            _ => None,
+        };
+        let bridge_log_id = match msg.kind {
+            OutgoingKind::BridgeText { log_id } => Some(log_id),
+            _ => None,
+        };
         match msg.kind {
-            OutgoingKind::Text => {
+            OutgoingKind::Text
+            | OutgoingKind::RetryText { .. }
+            | OutgoingKind::BridgeText { .. } => {
                 if let Some(reply_to_msg_id) = msg.reply_id {
                     log::info!(
                         "Sending queued reply [reply_to_msg_id={}]: {:?} -> {:?}",
@@ -144,40 +341,73 @@ impl Bot {
                     log::info!("Sending queued: {:?} -> {:?}", msg.text, msg.destination);
                 }
 
+                // Built here rather than by the API so the ID is known: our
+                // own messages heard back (another radio, the broker) must
+                // not be relayed to the bridges they came from, and ACKs are
+                // matched to the logged packet
+                let packet_id: u32 = generate_rand_id();
+
                 // Log outgoing message (no RF metadata for outgoing)
-                let _ = self.db.log_packet(
+                self.metrics.packet("out", PacketType::Text);
+                self.publish_live(|| {
+                    LiveEvent::message_sent(msg.from_node, msg.to_node, msg.mesh_channel, &msg.text)
+                });
+                self.log_sent_packet(
                     msg.from_node,
                     msg.to_node,
                     msg.mesh_channel,
                     &msg.text,
-                    "out",
-                    false,
-                    None,
-                    None,
-                    None,
-                    None,
-                    "text",
-                );
-
-                let result = if msg.reply_id.is_some() {
-                    let byte_data = msg.text.into_bytes().into();
-                    api.send_mesh_packet(
-                        router,
-                        byte_data,
-                        protobufs::PortNum::TextMessageApp,
-                        msg.destination,
-                        msg.channel,
-                        true,  // want_ack
-                        false, // want_response
-                        true,  // echo_response
-                        msg.reply_id,
-                        None, // emoji
-                    )
-                    .await
-                } else {
-                    api.send_text(router, msg.text, msg.destination, true, msg.channel)
-                        .await
+                    Some(packet_id),
+                    PacketType::Text,
+                )
+                .await;
+                let to = match msg.destination {
+                    PacketDestination::Node(id) => id.id(),
+                    _ => u32::MAX,
+                };
+                // DMs are retried until acknowledged
+                let dm = msg.to_node.map(|to_node| PendingSend {
+                    id: 0,
+                    to_node,
+                    mesh_channel: msg.mesh_channel,
+                    text: msg.text.clone(),
+                    reply_id: msg.reply_id,
+                    attempts: 1,
+                });
+                let mut mesh_packet = protobufs::MeshPacket {
+                    payload_variant: Some(protobufs::mesh_packet::PayloadVariant::Decoded(
+                        protobufs::Data {
+                            portnum: protobufs::PortNum::TextMessageApp as i32,
+                            payload: msg.text.into_bytes(),
+                            reply_id: msg.reply_id.unwrap_or(0),
+                            ..Default::default()
+                        },
+                    )),
+                    from: msg.from_node,
+                    to,
+                    id: packet_id,
+                    want_ack: true,
+                    channel: msg.channel.channel(),
+                    ..Default::default()
                 };
+                if let Uplink::Mqtt(_) = uplink {
+                    mesh_packet.hop_limit = 3;
+                }
+                self.remember_sent(msg.from_node, packet_id);
+                let result = uplink.send_packet(mesh_packet).await;
+                if let Some(dm) = dm {
+                    self.track_dm_send(retry_of, packet_id, dm, result.is_ok())
+                        .await;
+                }
+                if let (Some(log_id), Ok(_)) = (bridge_log_id, &result) {
+                    if let Err(e) = self
+                        .db
+                        .run(move |db| db.set_bridge_log_packet(log_id, packet_id))
+                        .await
+                    {
+                        log::error!("Failed to update bridge log: {}", e);
+                    }
+                }
                 if let Err(e) = result {
                     if let Some(reply_to_msg_id) = msg.reply_id {
                         log::error!(
@@ -197,22 +427,17 @@ impl Bot {
                 // the incoming RouteReply back to this probe session.
                 let request_id: u32 = generate_rand_id();
 
+                self.metrics.packet("out", PacketType::Traceroute);
                 let packet_row_id = self
-                    .db
-                    .log_packet_with_mesh_id(
+                    .log_sent_packet(
                         msg.from_node,
                         Some(target_node),
                         msg.mesh_channel,
                         "",
-                        "out",
-                        false,
-                        None,
-                        None,
-                        None,
-                        None,
                         Some(request_id),
-                        "traceroute",
+                        PacketType::Traceroute,
                     )
+                    .await
                     .unwrap_or(-1);
 
                 let routing = protobufs::Routing {
@@ -242,9 +467,7 @@ impl Bot {
                     channel: msg.mesh_channel,
                     ..Default::default()
                 };
-                let payload_variant =
-                    Some(protobufs::to_radio::PayloadVariant::Packet(mesh_packet));
-                let result = api.send_to_radio_packet(payload_variant).await;
+                let result = uplink.send_packet(mesh_packet).await;
 
                 if let Err(e) = result {
                     log::error!(
@@ -257,21 +480,165 @@ impl Bot {
                         "req:{:08x}:{:08x}:{}",
                         msg.from_node, target_node, request_id
                     );
-                    let _ = self.db.log_traceroute_observation(
-                        packet_row_id,
-                        &trace_key,
-                        msg.from_node,
-                        Some(target_node),
-                        false,
-                        None,
-                        None,
-                        None,
-                        None,
-                        &[],
-                        &[],
+                    let from_node = msg.from_node;
+                    if let Ok(session_id) = self
+                        .db
+                        .run(move |db| {
+                            db.log_traceroute_observation(
+                                packet_row_id,
+                                &trace_key,
+                                from_node,
+                                Some(target_node),
+                                false,
+                                None,
+                                None,
+                                None,
+                                None,
+                                &[],
+                                &[],
+                            )
+                        })
+                        .await
+                    {
+                        self.publish_live(|| LiveEvent::Traceroute { session_id });
+                    }
+                }
+            }
+            OutgoingKind::StoreForward {
+                target_node,
+                request,
+            } => {
+                log::info!(
+                    "Sending queued Store & Forward request to !{:08x}: {:?}",
+                    target_node,
+                    request.rr()
+                );
+                self.metrics.packet("out", PacketType::StoreForward);
+                self.log_sent_packet(
+                    msg.from_node,
+                    Some(target_node),
+                    msg.mesh_channel,
+                    "",
+                    None,
+                    PacketType::StoreForward,
+                )
+                .await;
+                let mesh_packet = protobufs::MeshPacket {
+                    payload_variant: Some(protobufs::mesh_packet::PayloadVariant::Decoded(
+                        protobufs::Data {
+                            portnum: protobufs::PortNum::StoreForwardApp as i32,
+                            payload: request.encode_to_vec(),
+                            want_response: true,
+                            ..Default::default()
+                        },
+                    )),
+                    from: msg.from_node,
+                    to: target_node,
+                    id: generate_rand_id(),
+                    want_ack: true,
+                    channel: msg.mesh_channel,
+                    ..Default::default()
+                };
+                if let Err(e) = uplink.send_packet(mesh_packet).await {
+                    log::error!(
+                        "Failed to send Store & Forward request to !{:08x}: {}",
+                        target_node,
+                        e
+                    );
+                }
+            }
+            OutgoingKind::NodeAdmin {
+                target_node,
+                request,
+            } => {
+                log::info!(
+                    "Sending queued admin request to !{:08x}: {:?}",
+                    target_node,
+                    request.payload_variant
+                );
+                self.metrics.packet("out", PacketType::Other);
+                self.log_sent_packet(
+                    msg.from_node,
+                    Some(target_node),
+                    msg.mesh_channel,
+                    "",
+                    None,
+                    PacketType::Other,
+                )
+                .await;
+                // Nodes only take remote admin from an admin key, so the
+                // radio must encrypt it with the target's public key
+                let mesh_packet = protobufs::MeshPacket {
+                    payload_variant: Some(protobufs::mesh_packet::PayloadVariant::Decoded(
+                        protobufs::Data {
+                            portnum: protobufs::PortNum::AdminApp as i32,
+                            payload: request.encode_to_vec(),
+                            want_response: true,
+                            ..Default::default()
+                        },
+                    )),
+                    from: msg.from_node,
+                    to: target_node,
+                    id: generate_rand_id(),
+                    want_ack: true,
+                    channel: msg.mesh_channel,
+                    pki_encrypted: true,
+                    ..Default::default()
+                };
+                if let Err(e) = uplink.send_packet(mesh_packet).await {
+                    log::error!(
+                        "Failed to send admin request to !{:08x}: {}",
+                        target_node,
+                        e
                     );
                 }
             }
+            OutgoingKind::SelfTest => {
+                log::info!("Sending self-test broadcast: {:?}", msg.text);
+                let packet_id: u32 = generate_rand_id();
+                self.metrics.packet("out", PacketType::Text);
+                self.publish_live(|| {
+                    LiveEvent::message_sent(msg.from_node, None, msg.mesh_channel, &msg.text)
+                });
+                self.log_sent_packet(
+                    msg.from_node,
+                    None,
+                    msg.mesh_channel,
+                    &msg.text,
+                    Some(packet_id),
+                    PacketType::Text,
+                )
+                .await;
+                let mesh_packet = protobufs::MeshPacket {
+                    payload_variant: Some(protobufs::mesh_packet::PayloadVariant::Decoded(
+                        protobufs::Data {
+                            portnum: protobufs::PortNum::TextMessageApp as i32,
+                            payload: msg.text.into_bytes(),
+                            ..Default::default()
+                        },
+                    )),
+                    from: msg.from_node,
+                    to: u32::MAX,
+                    id: packet_id,
+                    want_ack: true,
+                    channel: msg.mesh_channel,
+                    ..Default::default()
+                };
+                self.remember_sent(msg.from_node, packet_id);
+                match uplink.send_packet(mesh_packet).await {
+                    Ok(()) => {
+                        let channel = msg.mesh_channel;
+                        if let Err(e) = self
+                            .db
+                            .run(move |db| db.record_selftest_run(packet_id, channel))
+                            .await
+                        {
+                            log::error!("Failed to record self-test: {}", e);
+                        }
+                    }
+                    Err(e) => log::error!("Failed to send self-test broadcast: {}", e),
+                }
+            }
         }
     }
 }
@@ -358,3 +725,29 @@ pub(super) fn chunk_message(text: &str, max_len: usize) -> Vec<String> {
 
     chunks
 }
+
+/// Split chat text relayed from a bridge. Every chunk keeps the leading
+/// `[TG:name]` tag, so it stays attributed, and gets an `(i/n)`
+/// continuation marker.
+pub(super) fn chunk_bridge_message(text: &str, max_len: usize) -> Vec<String> {
+    if text.len() <= max_len {
+        return vec![text.to_string()];
+    }
+    let (tag, body) = match text.split_once("] ") {
+        Some((tag, body)) if tag.starts_with('[') => (format!("{}] ", tag), body),
+        _ => (String::new(), text),
+    };
+    // Room for the tag and a marker like "(2/3) ", sized for the worst-case count
+    let max_parts = body.len() / max_len.saturating_sub(tag.len()).max(1) + 1;
+    let reserved = tag.len() + 2 * max_parts.to_string().len() + 4;
+    if max_len <= reserved {
+        return chunk_message(text, max_len);
+    }
+    let parts = chunk_message(body, max_len - reserved);
+    let total = parts.len();
+    parts
+        .into_iter()
+        .enumerate()
+        .map(|(i, part)| format!("{}({}/{}) {}", tag, i + 1, total, part))
+        .collect()
+}
@@ -0,0 +1,188 @@
+use meshtastic::packet::PacketDestination;
+use meshtastic::protobufs;
+use meshtastic::types::{MeshChannel, NodeId};
+use meshtastic::Message;
+
+use super::*;
+use crate::db::PendingSend;
+
+/// An unacknowledged DM is retried once this has passed, doubled per attempt.
+const ACK_TIMEOUT_SECS: i64 = 120;
+/// DMs still undelivered after this long are given up on.
+const MAX_PENDING_SECS: i64 = 24 * 60 * 60;
+
+impl Bot {
+    /// Track a DM we transmitted, or failed to, until its destination
+    /// acknowledges it. `retry_of` is the pending DM this was a retry of.
+    pub(super) async fn track_dm_send(
+        &self,
+        retry_of: Option<i64>,
+        packet_id: u32,
+        send: PendingSend,
+        sent: bool,
+    ) {
+        if self.config.bot.dm_retries == 0 {
+            return;
+        }
+        let to_node = send.to_node;
+        let result = self
+            .db
+            .run(move |db| match retry_of {
+                Some(id) => db.pending_send_transmitted(id, packet_id, !sent),
+                None => db
+                    .add_pending_send(packet_id, &send, !sent, chrono::Utc::now().timestamp())
+                    .map(|_| ()),
+            })
+            .await;
+        match result {
+            Ok(()) => {
+                self.owed_deliveries.lock().unwrap().insert(to_node);
+            }
+            Err(e) => log::error!("Failed to track DM to !{:08x}: {}", to_node, e),
+        }
+    }
+
+    /// Match routing ACKs and errors addressed to us against pending DMs and
+    /// the delivery status of logged packets. Our own radio's implicit ACK
+    /// only means a neighbour rebroadcast the DM, so only an ACK from the
+    /// destination counts.
+    pub(super) async fn observe_dm_delivery(
+        &self,
+        my_node_id: u32,
+        mesh_packet: &protobufs::MeshPacket,
+        data: &protobufs::Data,
+    ) {
+        if data.portnum() != protobufs::PortNum::RoutingApp
+            || data.request_id == 0
+            || mesh_packet.to != my_node_id
+        {
+            return;
+        }
+        let Ok(routing) = protobufs::Routing::decode(data.payload.as_slice()) else {
+            return;
+        };
+        let Some(protobufs::routing::Variant::ErrorReason(reason)) = routing.variant else {
+            return;
+        };
+        let acked = reason == protobufs::routing::Error::None as i32;
+        if acked && mesh_packet.from == my_node_id {
+            return;
+        }
+        let (request_id, from) = (data.request_id, mesh_packet.from);
+        let result = self
+            .db
+            .run(move |db| {
+                if let Err(e) =
+                    db.record_packet_ack(request_id, from, acked, chrono::Utc::now().timestamp())
+                {
+                    log::error!("Failed to record delivery status: {}", e);
+                }
+                if acked {
+                    db.ack_pending_send(request_id, from)
+                } else {
+                    db.fail_pending_send(request_id)
+                }
+            })
+            .await;
+        if acked {
+            match result {
+                Ok(true) => log::info!(
+                    "DM {} acknowledged by !{:08x}",
+                    data.request_id,
+                    mesh_packet.from
+                ),
+                Ok(false) => {}
+                Err(e) => log::error!("Failed to record DM ACK: {}", e),
+            }
+        } else {
+            match result {
+                Ok(true) => log::warn!(
+                    "DM {} not delivered ({:?}), retrying when the node is heard",
+                    data.request_id,
+                    protobufs::routing::Error::try_from(reason).unwrap_or_default()
+                ),
+                Ok(false) => {}
+                Err(e) => log::error!("Failed to record DM error: {}", e),
+            }
+        }
+    }
+
+    /// Queue DMs to `node`, just heard from, that are due for another try.
+    /// After `[bot] dm_retries` retries a DM is dropped.
+    pub(super) async fn retry_pending_dms(&self, my_node_id: u32, node: u32) {
+        let max_retries = self.config.bot.dm_retries;
+        if max_retries == 0 {
+            return;
+        }
+        let now = chrono::Utc::now().timestamp();
+        let due = match self
+            .db
+            .run(move |db| {
+                db.due_pending_sends(node, ACK_TIMEOUT_SECS, now - MAX_PENDING_SECS, now)
+            })
+            .await
+        {
+            Ok(due) => due,
+            Err(e) => {
+                log::error!("Failed to load pending DMs: {}", e);
+                return;
+            }
+        };
+        for send in due {
+            if send.attempts > max_retries {
+                log::warn!(
+                    "Giving up on DM to !{:08x} after {} attempts: {:?}",
+                    node,
+                    send.attempts,
+                    send.text
+                );
+                let id = send.id;
+                if let Err(e) = self.db.run(move |db| db.delete_pending_send(id)).await {
+                    log::error!("Failed to drop pending DM: {}", e);
+                }
+                continue;
+            }
+            let channel = match MeshChannel::new(send.mesh_channel) {
+                Ok(channel) => channel,
+                Err(e) => {
+                    log::error!("Invalid channel {}: {}", send.mesh_channel, e);
+                    continue;
+                }
+            };
+            let id = send.id;
+            if let Err(e) = self.db.run(move |db| db.retry_pending_send(id, now)).await {
+                log::error!("Failed to update pending DM: {}", e);
+                continue;
+            }
+            log::info!(
+                "!{:08x} heard again, retrying DM (attempt {}): {:?}",
+                node,
+                send.attempts + 1,
+                send.text
+            );
+            self.queue_message(OutgoingMeshMessage {
+                kind: OutgoingKind::RetryText {
+                    pending_id: send.id,
+                },
+                text: send.text,
+                destination: PacketDestination::Node(NodeId::from(node)),
+                channel,
+                from_node: my_node_id,
+                to_node: Some(node),
+                mesh_channel: send.mesh_channel,
+                reply_id: send.reply_id,
+                expires_at: None,
+            });
+        }
+    }
+
+    /// Forget DMs that were never delivered within `MAX_PENDING_SECS`.
+    pub(super) async fn prune_pending_dms(&self) {
+        let before = chrono::Utc::now().timestamp() - MAX_PENDING_SECS;
+        match self.db.run(move |db| db.prune_pending_sends(before)).await {
+            Ok(pruned) if pruned > 0 => log::info!("Dropped {} undelivered DM(s)", pruned),
+            Ok(_) => {}
+            Err(e) => log::error!("Failed to prune pending DMs: {}", e),
+        }
+    }
+}
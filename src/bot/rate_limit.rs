@@ -17,12 +17,11 @@ impl RateLimiter {
         }
     }
 
-    pub(super) fn check(&self, node_id: u32) -> bool {
+    pub(super) fn check(&self, node_id: u32, now: Instant) -> bool {
         if self.max_commands == 0 {
             return true;
         }
         let mut map = self.commands.lock().unwrap();
-        let now = Instant::now();
         let window = std::time::Duration::from_secs(self.window_secs);
 
         let timestamps = map.entry(node_id).or_default();
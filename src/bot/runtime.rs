@@ -1,45 +1,84 @@
 use meshtastic::api::StreamApi;
-use meshtastic::packet::{PacketDestination, PacketRouter};
+use meshtastic::packet::PacketDestination;
 use meshtastic::protobufs::{self, from_radio};
 use meshtastic::types::{MeshChannel, NodeId};
 use meshtastic::utils;
 use meshtastic::utils::stream::build_tcp_stream;
 use rand::Rng;
-use std::collections::HashSet;
+use std::collections::{HashSet, VecDeque};
 use std::sync::atomic::Ordering;
 use tokio::sync::mpsc::UnboundedReceiver;
 
 use super::*;
+use crate::config::ConnectionConfig;
+use crate::mesh_graph::{self, MeshGraph};
+use crate::message::{Destination, MessageContext, Response};
+use crate::modules::AlertSeverity;
+use crate::mqtt::{self, MqttLink};
+use crate::packet_type::PacketType;
 
-#[derive(Debug)]
-pub(super) struct RouterError(String);
+/// Where queued packets go out: the connected node, or the MQTT broker when
+/// running without one.
+pub(super) enum Uplink {
+    Radio {
+        api: meshtastic::api::ConnectedStreamApi,
+    },
+    Mqtt(MqttLink),
+}
 
-impl std::fmt::Display for RouterError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
+impl Uplink {
+    /// Send a fully built packet.
+    pub(super) async fn send_packet(
+        &mut self,
+        packet: protobufs::MeshPacket,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        match self {
+            Uplink::Radio { api } => {
+                api.send_to_radio_packet(Some(protobufs::to_radio::PayloadVariant::Packet(packet)))
+                    .await?
+            }
+            Uplink::Mqtt(link) => link.publish(packet).await?,
+        }
+        Ok(())
     }
 }
 
-impl std::error::Error for RouterError {}
-
-pub(super) struct BotPacketRouter {
-    node_id: u32,
+/// Packets seen recently, by sender and packet ID, so traffic heard by
+/// several radios or both on the radio and via a broker is only handled once.
+pub(super) struct RecentPackets {
+    seen: HashSet<(u32, u32)>,
+    order: VecDeque<(u32, u32)>,
+    capacity: usize,
 }
 
-impl PacketRouter<(), RouterError> for BotPacketRouter {
-    fn handle_packet_from_radio(
-        &mut self,
-        _packet: protobufs::FromRadio,
-    ) -> Result<(), RouterError> {
-        Ok(())
+impl RecentPackets {
+    pub(super) fn new(capacity: usize) -> Self {
+        Self {
+            seen: HashSet::new(),
+            order: VecDeque::new(),
+            capacity,
+        }
     }
 
-    fn handle_mesh_packet(&mut self, _packet: protobufs::MeshPacket) -> Result<(), RouterError> {
-        Ok(())
+    pub(super) fn contains(&self, from: u32, id: u32) -> bool {
+        self.seen.contains(&(from, id))
     }
 
-    fn source_node_id(&self) -> NodeId {
-        NodeId::from(self.node_id)
+    /// Record a packet; false if it was already seen.
+    pub(super) fn insert(&mut self, from: u32, id: u32) -> bool {
+        if id == 0 {
+            return true;
+        }
+        if !self.seen.insert((from, id)) {
+            return false;
+        }
+        self.order.push_back((from, id));
+        if self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+        true
     }
 }
 
@@ -102,47 +141,112 @@ where
 }
 
 impl Bot {
+    /// Run every configured connection until the process exits. Each one
+    /// reconnects on its own; they share the database, modules and queue.
     pub async fn run(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let reconnect_delay =
-            std::time::Duration::from_secs(self.config.connection.reconnect_delay_secs);
+        self.load_owed_deliveries().await;
+        futures_util::future::join_all(
+            self.config
+                .connections
+                .iter()
+                .enumerate()
+                .map(|(index, connection)| self.run_connection(index == 0, connection)),
+        )
+        .await;
+        Ok(())
+    }
+
+    async fn run_connection(&self, primary: bool, connection: &ConnectionConfig) {
+        let reconnect_delay = std::time::Duration::from_secs(connection.reconnect_delay_secs);
+        let label = connection.label();
+        self.metrics.set_connected(label, false);
 
         loop {
-            match self.connect_and_run().await {
+            match self.connect_and_run(primary, connection).await {
                 Ok(()) => {
-                    log::warn!("Connection closed cleanly");
+                    log::warn!("Connection {} closed cleanly", label);
                 }
                 Err(e) => {
-                    log::error!("Connection error: {}", e);
+                    log::error!("Connection {} error: {}", label, e);
                 }
             }
 
-            log::info!("Reconnecting in {} seconds...", reconnect_delay.as_secs());
+            log::info!(
+                "Reconnecting {} in {} seconds...",
+                label,
+                reconnect_delay.as_secs()
+            );
+            self.metrics.reconnect(label);
             tokio::time::sleep(reconnect_delay).await;
         }
     }
 
-    async fn connect_and_run(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let address = &self.config.connection.address;
-        log::info!("Connecting to meshtastic node at {}...", address);
+    async fn connect_and_run(
+        &self,
+        primary: bool,
+        connection: &ConnectionConfig,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let reconnect_delay = std::time::Duration::from_secs(connection.reconnect_delay_secs);
+
+        let (mqtt_link, mqtt_rx) = if connection.uses_mqtt() {
+            let (link, rx) = mqtt::connect(&connection.mqtt, reconnect_delay)?;
+            (Some(link), Some(rx))
+        } else {
+            (None, None)
+        };
 
-        let tcp_stream = build_tcp_stream(address.to_string()).await?;
-        let (mut packet_rx, stream_api) = StreamApi::new().connect(tcp_stream).await;
+        let (my_node_id, radio_rx, mut uplink) = if connection.uses_tcp() {
+            let address = &connection.address;
+            log::info!("Connecting to meshtastic node at {}...", address);
 
-        let config_id = utils::generate_rand_id();
-        let configured_api = stream_api.configure(config_id).await?;
+            let tcp_stream = build_tcp_stream(address.to_string()).await?;
+            let (mut packet_rx, stream_api) = StreamApi::new().connect(tcp_stream).await;
 
-        log::info!("Connected and configured (config_id={})", config_id);
+            let config_id = utils::generate_rand_id();
+            let configured_api = stream_api.configure(config_id).await?;
 
-        let my_node_id = self.wait_for_my_node_id(&mut packet_rx).await?;
-        self.local_node_id.store(my_node_id, Ordering::Relaxed);
-        log::info!("Bot node ID: !{:08x}", my_node_id);
+            log::info!("Connected and configured (config_id={})", config_id);
 
-        let mut router = BotPacketRouter {
-            node_id: my_node_id,
+            let my_node_id = self.wait_for_my_node_id(&mut packet_rx).await?;
+            let uplink = Uplink::Radio {
+                api: configured_api,
+            };
+            (my_node_id, Some(packet_rx), uplink)
+        } else {
+            // Checked by Config::load
+            let link = mqtt_link.ok_or("MQTT connection missing")?;
+            let my_node_id = link.node_id().ok_or("connection.mqtt.node_id is not set")?;
+            (my_node_id, None, Uplink::Mqtt(link))
         };
+        if primary {
+            self.local_node_id.store(my_node_id, Ordering::Relaxed);
+            if let Err(e) = self
+                .db
+                .run(move |db| db.set_local_node_id(my_node_id))
+                .await
+            {
+                log::error!("Failed to store local node ID: {}", e);
+            }
+        }
+        log::info!("Bot node ID on {}: !{:08x}", connection.label(), my_node_id);
 
-        self.event_loop(my_node_id, &mut packet_rx, configured_api, &mut router)
-            .await
+        self.radios
+            .write()
+            .unwrap()
+            .insert(my_node_id, connection.label().to_string());
+        if primary {
+            self.mesh_online.store(true, Ordering::Relaxed);
+        }
+        self.metrics.set_connected(connection.label(), true);
+        let result = self
+            .event_loop(my_node_id, primary, radio_rx, mqtt_rx, &mut uplink)
+            .await;
+        if primary {
+            self.mesh_online.store(false, Ordering::Relaxed);
+        }
+        self.metrics.set_connected(connection.label(), false);
+        self.radios.write().unwrap().remove(&my_node_id);
+        result
     }
 
     async fn wait_for_my_node_id(
@@ -160,12 +264,18 @@ impl Bot {
     async fn event_loop(
         &self,
         my_node_id: u32,
-        packet_rx: &mut UnboundedReceiver<protobufs::FromRadio>,
-        mut api: meshtastic::api::ConnectedStreamApi,
-        router: &mut BotPacketRouter,
+        primary: bool,
+        mut radio_rx: Option<UnboundedReceiver<protobufs::FromRadio>>,
+        mut mqtt_rx: Option<UnboundedReceiver<protobufs::MeshPacket>>,
+        uplink: &mut Uplink,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         log::info!("Entering event loop...");
-        self.startup_state.mark_connected_and_reset();
+        // Another radio connecting must not drop events the primary deferred.
+        if primary {
+            self.startup_state.mark_connected_and_reset(self.now());
+        } else {
+            self.startup_state.mark_connected(self.now());
+        }
 
         // Timer to dispatch deferred events after the grace period
         let grace_period = std::time::Duration::from_secs(self.config.bot.startup_grace_secs);
@@ -178,7 +288,8 @@ impl Bot {
         let send_timer = tokio::time::sleep(send_delay);
         tokio::pin!(send_timer);
 
-        let traceroute_enabled = self.config.traceroute_probe.enabled;
+        // Maintenance and scheduled posts run once, on the primary connection.
+        let traceroute_enabled = self.config.traceroute_probe.enabled && primary;
         let traceroute_base_interval =
             std::time::Duration::from_secs(self.config.traceroute_probe.interval_secs.max(60));
         let traceroute_jitter_pct =
@@ -194,18 +305,114 @@ impl Bot {
         let stale_node_purge_timer = tokio::time::sleep(stale_node_purge_interval);
         tokio::pin!(stale_node_purge_timer);
 
+        // Traceroute session expiry, hop pruning and hop stats backfill share the
+        // hourly maintenance cadence.
+        let traceroute_cleanup_interval = std::time::Duration::from_secs(60 * 60);
+        let traceroute_cleanup_timer = tokio::time::sleep(traceroute_cleanup_interval);
+        tokio::pin!(traceroute_cleanup_timer);
+
+        // Net rosters are posted shortly after a scheduled net window closes.
+        let netlog_enabled = self.config.is_module_enabled("netlog") && primary;
+        let netlog_interval = std::time::Duration::from_secs(60);
+        let netlog_timer = tokio::time::sleep(netlog_interval);
+        tokio::pin!(netlog_timer);
+
+        // Polls started with a time limit are closed, and their results
+        // announced, within a minute of it.
+        let poll_enabled = self.config.is_module_enabled("poll") && primary;
+        let poll_interval = std::time::Duration::from_secs(60);
+        let poll_timer = tokio::time::sleep(poll_interval);
+        tokio::pin!(poll_timer);
+
+        // Scheduled self-test broadcasts and their weekly digest.
+        let selftest_enabled = self.config.selftest.enabled && primary;
+        let selftest_interval = std::time::Duration::from_secs(60);
+        let selftest_timer = tokio::time::sleep(selftest_interval);
+        tokio::pin!(selftest_timer);
+
+        // [[schedule]] announcements, checked every minute.
+        let schedule_enabled = !self.config.schedule.is_empty() && primary;
+        let schedule_interval = std::time::Duration::from_secs(60);
+        let schedule_timer = tokio::time::sleep(schedule_interval);
+        tokio::pin!(schedule_timer);
+
+        // [[beacons]], checked every minute.
+        let beacons_enabled = !self.config.beacons.is_empty() && primary;
+        let beacons_interval = std::time::Duration::from_secs(60);
+        let beacons_timer = tokio::time::sleep(beacons_interval);
+        tokio::pin!(beacons_timer);
+
+        // [snapshot] file, checked every minute against its daily time.
+        let snapshot_enabled = self.config.snapshot.enabled && primary;
+        let snapshot_interval = std::time::Duration::from_secs(60);
+        let snapshot_timer = tokio::time::sleep(snapshot_interval);
+        tokio::pin!(snapshot_timer);
+
+        // Nodes going offline, for [event_hooks].
+        let offline_check_enabled = self.hooks.is_enabled() && primary;
+        let offline_check_interval = std::time::Duration::from_secs(60);
+        let offline_check_timer = tokio::time::sleep(offline_check_interval);
+        tokio::pin!(offline_check_timer);
+
+        // [[alerts]] rules, checked every minute.
+        let alerts_enabled = !self.alert_rules.is_empty() && primary;
+        let alerts_interval = std::time::Duration::from_secs(60);
+        let alerts_timer = tokio::time::sleep(alerts_interval);
+        tokio::pin!(alerts_timer);
+
+        // Severe weather alerts for the [weather] location.
+        let weather_alerts_enabled = self.config.weather.alerts.enabled && primary;
+        if weather_alerts_enabled
+            && AlertSeverity::parse(&self.config.weather.alerts.min_severity).is_none()
+        {
+            log::warn!(
+                "Unknown [weather.alerts] min_severity {:?}, using \"severe\"",
+                self.config.weather.alerts.min_severity
+            );
+        }
+        let weather_alerts_interval =
+            std::time::Duration::from_secs(self.config.weather.alerts.interval_mins.max(1) * 60);
+        let weather_alerts_timer = tokio::time::sleep(weather_alerts_interval);
+        tokio::pin!(weather_alerts_timer);
+
+        // Nodes going offline and coming back, for modules and the
+        // dashboard; from the end of the grace period on.
+        let liveness_interval = std::time::Duration::from_secs(60);
+        let liveness_timer = tokio::time::sleep(liveness_interval);
+        tokio::pin!(liveness_timer);
+
+        // [bridge.node_events] posts, batched.
+        let node_events_enabled = self.config.bridge.node_events.enabled() && primary;
+        let node_events_interval =
+            std::time::Duration::from_secs(self.config.bridge.node_events.batch_secs.max(1));
+        let node_events_timer = tokio::time::sleep(node_events_interval);
+        tokio::pin!(node_events_timer);
+
+        // Completion of `!admin db vacuum`, checked while one is running.
+        let maintenance_interval = std::time::Duration::from_secs(5);
+        let maintenance_timer = tokio::time::sleep(maintenance_interval);
+        tokio::pin!(maintenance_timer);
+
+        // Latency percentiles for /api/latency: one window every 10 minutes.
+        let latency_interval = std::time::Duration::from_secs(10 * 60);
+        let latency_timer = tokio::time::sleep(latency_interval);
+        tokio::pin!(latency_timer);
+
         // PRAGMA optimize: run every 6 hours to keep query planner stats fresh.
         let optimize_interval = std::time::Duration::from_secs(6 * 60 * 60);
         let optimize_timer = tokio::time::sleep(optimize_interval);
         tokio::pin!(optimize_timer);
 
-        self.purge_stale_nodes(stale_node_max_age);
+        if primary {
+            self.purge_stale_nodes(stale_node_max_age).await;
+            self.evict_excess_nodes().await;
+        }
 
         // Bridge active flag: set to false when the bridge channel closes.
-        let mut bridge_active = self.bridge.rx().is_some();
+        let mut bridge_active = self.bridge.rx().is_some() && primary;
 
         loop {
-            let queue_has_messages = !self.outgoing.is_empty();
+            let queue_has_messages = self.has_queued_messages_for(my_node_id, primary);
 
             tokio::select! {
                 // Handle messages from bridges; disabled when no bridge or after channel close.
@@ -215,7 +422,7 @@ impl Bot {
                     if bridge_active =>
                 {
                     match msg {
-                        Some(msg) => self.handle_bridge_message(my_node_id, msg),
+                        Some(msg) => self.handle_bridge_message(my_node_id, msg).await,
                         None => {
                             bridge_active = false;
                             log::warn!("Bridge outgoing channel closed; disabling bridge receive path");
@@ -224,9 +431,16 @@ impl Bot {
                 }
 
                 // Handle packets from mesh
-                packet = packet_rx.recv() => {
+                packet = async { radio_rx.as_mut().unwrap().recv().await }, if radio_rx.is_some() => {
                     match packet {
-                        Some(p) => self.process_radio_packet(my_node_id, p).await,
+                        Some(p) => {
+                            if let Some(from_radio::PayloadVariant::Packet(mp)) = &p.payload_variant {
+                                if !self.recent_packets.lock().unwrap().insert(mp.from, mp.id) {
+                                    continue;
+                                }
+                            }
+                            self.process_radio_packet(my_node_id, p).await
+                        }
                         None => {
                             log::warn!("Packet channel closed, exiting event loop");
                             return Ok(());
@@ -234,47 +448,170 @@ impl Bot {
                     }
                 }
 
+                // Handle packets from the MQTT broker
+                packet = async { mqtt_rx.as_mut().unwrap().recv().await }, if mqtt_rx.is_some() => {
+                    match packet {
+                        Some(p) => {
+                            let fresh = self.recent_packets.lock().unwrap().insert(p.from, p.id);
+                            if fresh {
+                                self.process_mqtt_packet(my_node_id, p).await;
+                            }
+                        }
+                        None => {
+                            log::warn!("MQTT channel closed, exiting event loop");
+                            return Ok(());
+                        }
+                    }
+                }
+
                 // Dispatch deferred events after grace period
                 _ = &mut grace_timer, if !grace_period_done => {
                     grace_period_done = true;
                     self.dispatch_deferred_events(my_node_id).await;
+                    if primary {
+                        self.send_startup_banner(my_node_id).await;
+                        self.check_node_liveness(my_node_id, chrono::Utc::now().timestamp(), true)
+                            .await;
+                    }
                 }
 
                 // Drain outgoing message queue
                 _ = &mut send_timer, if queue_has_messages => {
-                    self.send_next_queued_message(&mut api, router).await;
+                    self.send_next_queued_message(my_node_id, primary, uplink).await;
                     self.notify_dashboard();
                     send_timer.as_mut().reset(tokio::time::Instant::now() + send_delay);
                 }
 
                 // Periodic traceroute probe
                 _ = &mut traceroute_timer, if traceroute_enabled => {
-                    self.maybe_queue_traceroute_probe(my_node_id);
+                    self.maybe_queue_traceroute_probe(my_node_id).await;
                     traceroute_timer.as_mut().reset(
                         tokio::time::Instant::now()
                             + next_traceroute_interval(traceroute_base_interval, traceroute_jitter_pct),
                     );
                 }
 
-                // Periodic stale node purge
-                _ = &mut stale_node_purge_timer => {
-                    self.purge_stale_nodes(stale_node_max_age);
+                // Periodic stale node purge, node cap eviction, node count
+                // snapshot, mail expiry, and packet, position history and
+                // bridge log pruning
+                _ = &mut stale_node_purge_timer, if primary => {
+                    self.purge_stale_nodes(stale_node_max_age).await;
+                    self.evict_excess_nodes().await;
+                    self.record_node_counts().await;
+                    self.expire_unread_mail().await;
+                    self.prune_packets().await;
+                    self.prune_position_history().await;
+                    self.prune_bridge_log().await;
                     stale_node_purge_timer.as_mut().reset(tokio::time::Instant::now() + stale_node_purge_interval);
                 }
 
+                // Periodic traceroute session expiry, hop pruning, hop stats
+                // backfill and mesh graph recomputation
+                _ = &mut traceroute_cleanup_timer, if primary => {
+                    self.cleanup_traceroute_sessions().await;
+                    self.refresh_mesh_graph().await;
+                    traceroute_cleanup_timer.as_mut().reset(tokio::time::Instant::now() + traceroute_cleanup_interval);
+                }
+
+                // Post rosters of nets that just ended
+                _ = &mut netlog_timer, if netlog_enabled => {
+                    self.post_net_rosters(my_node_id).await;
+                    netlog_timer.as_mut().reset(tokio::time::Instant::now() + netlog_interval);
+                }
+
+                // Close polls whose time is up
+                _ = &mut poll_timer, if poll_enabled => {
+                    self.close_expired_polls(my_node_id).await;
+                    poll_timer.as_mut().reset(tokio::time::Instant::now() + poll_interval);
+                }
+
+                // Send self-tests and the weekly digest when due
+                _ = &mut selftest_timer, if selftest_enabled => {
+                    self.run_selftest_schedule(my_node_id, chrono::Local::now()).await;
+                    selftest_timer.as_mut().reset(tokio::time::Instant::now() + selftest_interval);
+                }
+
+                // Broadcast scheduled announcements that are due
+                _ = &mut schedule_timer, if schedule_enabled => {
+                    self.run_schedule(my_node_id, chrono::Local::now()).await;
+                    schedule_timer.as_mut().reset(tokio::time::Instant::now() + schedule_interval);
+                }
+
+                // Write the daily mesh snapshot when due
+                _ = &mut snapshot_timer, if snapshot_enabled => {
+                    self.run_snapshot_schedule(my_node_id, chrono::Local::now()).await;
+                    snapshot_timer.as_mut().reset(tokio::time::Instant::now() + snapshot_interval);
+                }
+
+                // Broadcast beacons that are due
+                _ = &mut beacons_timer, if beacons_enabled => {
+                    self.run_beacons(my_node_id, chrono::Local::now()).await;
+                    beacons_timer.as_mut().reset(tokio::time::Instant::now() + beacons_interval);
+                }
+
+                // Report RF nodes that just went offline
+                _ = &mut offline_check_timer, if offline_check_enabled => {
+                    self.check_nodes_offline(chrono::Utc::now().timestamp())
+                        .await;
+                    offline_check_timer.as_mut().reset(tokio::time::Instant::now() + offline_check_interval);
+                }
+
+                // Notify [[alerts]] rules that started to hold
+                _ = &mut alerts_timer, if alerts_enabled => {
+                    self.evaluate_alerts(my_node_id, chrono::Utc::now().timestamp()).await;
+                    alerts_timer.as_mut().reset(tokio::time::Instant::now() + alerts_interval);
+                }
+
+                // Broadcast new severe weather alerts
+                _ = &mut weather_alerts_timer, if weather_alerts_enabled => {
+                    self.poll_weather_alerts(my_node_id).await;
+                    weather_alerts_timer.as_mut().reset(tokio::time::Instant::now() + weather_alerts_interval);
+                }
+
+                // Mark nodes offline or back online and tell modules
+                _ = &mut liveness_timer, if primary && grace_period_done => {
+                    self.check_node_liveness(my_node_id, chrono::Utc::now().timestamp(), false)
+                        .await;
+                    liveness_timer.as_mut().reset(tokio::time::Instant::now() + liveness_interval);
+                }
+
+                // Post nodes that joined or went silent to the bridges
+                _ = &mut node_events_timer, if node_events_enabled => {
+                    self.post_node_activity(my_node_id, chrono::Utc::now().timestamp()).await;
+                    node_events_timer.as_mut().reset(tokio::time::Instant::now() + node_events_interval);
+                }
+
+                // Report a finished database VACUUM to the admin
+                _ = &mut maintenance_timer, if primary && self.db_maintenance_running() => {
+                    self.finish_db_maintenance(my_node_id).await;
+                    maintenance_timer.as_mut().reset(tokio::time::Instant::now() + maintenance_interval);
+                }
+
+                // Store latency percentiles
+                _ = &mut latency_timer, if primary => {
+                    self.flush_latency(chrono::Utc::now().timestamp()).await;
+                    latency_timer.as_mut().reset(tokio::time::Instant::now() + latency_interval);
+                }
+
                 // Periodic PRAGMA optimize
-                _ = &mut optimize_timer => {
-                    if let Err(e) = self.db.optimize() {
+                _ = &mut optimize_timer, if primary => {
+                    if let Err(e) = self.db.run(|db| db.optimize()).await {
                         log::warn!("PRAGMA optimize failed: {}", e);
                     }
+                    self.prune_pending_dms().await;
                     optimize_timer.as_mut().reset(tokio::time::Instant::now() + optimize_interval);
                 }
             }
         }
     }
 
-    fn purge_stale_nodes(&self, max_age: std::time::Duration) {
-        match self.db.purge_nodes_not_seen_within(max_age.as_secs()) {
+    async fn purge_stale_nodes(&self, max_age: std::time::Duration) {
+        let max_age_secs = max_age.as_secs();
+        match self
+            .db
+            .run(move |db| db.purge_nodes_not_seen_within(max_age_secs))
+            .await
+        {
             Ok(purged) if purged > 0 => {
                 let days = max_age.as_secs() / (24 * 60 * 60);
                 log::info!(
@@ -291,27 +628,397 @@ impl Bot {
         }
     }
 
-    fn maybe_queue_traceroute_probe(&self, my_node_id: u32) {
+    /// Snapshot the node list's size for `/api/node-history`.
+    async fn record_node_counts(&self) {
+        let now = chrono::Utc::now().timestamp();
+        match self.db.run(move |db| db.record_node_counts(now)).await {
+            Ok(counts) => log::debug!(
+                "Node counts: {} total, {} RF, {} MQTT",
+                counts.total,
+                counts.rf,
+                counts.mqtt
+            ),
+            Err(e) => log::error!("Failed to record node counts: {}", e),
+        }
+    }
+
+    /// Enforce `bot.max_nodes` by evicting the least recently seen
+    /// MQTT-only nodes.
+    async fn evict_excess_nodes(&self) {
+        let max_nodes = self.config.bot.max_nodes;
+        if max_nodes == 0 {
+            return;
+        }
+        let admins = self.admin_ids();
+        match self
+            .db
+            .run(move |db| db.evict_nodes_over(max_nodes, &admins))
+            .await
+        {
+            Ok(evicted) if evicted > 0 => {
+                log::info!(
+                    "Evicted {} node(s) to stay within max_nodes = {}",
+                    evicted,
+                    max_nodes
+                );
+                self.notify_dashboard();
+            }
+            Ok(_) => {}
+            Err(e) => {
+                log::error!("Failed to evict nodes: {}", e);
+            }
+        }
+    }
+
+    async fn expire_unread_mail(&self) {
+        let days = self.config.mail.expire_unread_after_days;
+        if days == 0 || !self.config.is_module_enabled("mail") {
+            return;
+        }
+        let max_age_secs = days.saturating_mul(24 * 60 * 60);
+        match self
+            .db
+            .run(move |db| db.expire_unread_mail(max_age_secs))
+            .await
+        {
+            Ok(expired) if expired > 0 => {
+                log::info!(
+                    "Expired {} unread mail(s) older than {} day(s)",
+                    expired,
+                    days
+                );
+                self.load_owed_deliveries().await;
+            }
+            Ok(_) => {}
+            Err(e) => {
+                log::error!("Failed to expire mail: {}", e);
+            }
+        }
+    }
+
+    /// Drop packets older than their `[db]` retention, then hand the freed
+    /// pages back with `[db] incremental_vacuum`, in one `Db::run` as the
+    /// first prune on a big table takes a while.
+    async fn prune_packets(&self) {
+        let db_config = &self.config.db;
+        let retention: Vec<(PacketType, u64)> = PacketType::ALL
+            .iter()
+            .map(|t| (*t, db_config.packet_retention_days(*t)))
+            .filter(|(_, days)| *days > 0)
+            .collect();
+        if retention.is_empty() {
+            return;
+        }
+        let vacuum = db_config.incremental_vacuum;
+        let result = self
+            .db
+            .run(move |db| {
+                let mut pruned = 0;
+                for (packet_type, days) in retention {
+                    pruned += db
+                        .prune_packets_older_than(packet_type, days.saturating_mul(24 * 60 * 60))?;
+                }
+                let released = if vacuum && pruned > 0 {
+                    db.incremental_vacuum()?
+                } else {
+                    0
+                };
+                Ok((pruned, released))
+            })
+            .await;
+        match result {
+            Ok((pruned, released)) if pruned > 0 => {
+                log::info!(
+                    "Pruned {} packet(s) past retention, released {} bytes",
+                    pruned,
+                    released
+                );
+                self.notify_dashboard();
+            }
+            Ok(_) => {}
+            Err(e) => log::error!("Failed to prune packets: {}", e),
+        }
+    }
+
+    /// Drop track points older than `[privacy] position_history_days` (all
+    /// of them once it is set to 0).
+    async fn prune_position_history(&self) {
+        let days = self.config.privacy.position_history_days;
+        let max_age_secs = days.saturating_mul(24 * 60 * 60);
+        match self
+            .db
+            .run(move |db| db.prune_position_history_older_than(max_age_secs))
+            .await
+        {
+            Ok(pruned) if pruned > 0 => {
+                log::info!(
+                    "Pruned {} position history row(s) older than {} day(s)",
+                    pruned,
+                    days
+                );
+            }
+            Ok(_) => {}
+            Err(e) => {
+                log::error!("Failed to prune position history: {}", e);
+            }
+        }
+    }
+
+    /// Drop bridge log rows older than `[bridge] log_days`.
+    async fn prune_bridge_log(&self) {
+        let days = self.config.bridge.log_days;
+        if days == 0 {
+            return;
+        }
+        let max_age_secs = days.saturating_mul(24 * 60 * 60);
+        match self
+            .db
+            .run(move |db| db.prune_bridge_log_older_than(max_age_secs))
+            .await
+        {
+            Ok(pruned) if pruned > 0 => {
+                log::info!(
+                    "Pruned {} bridge log row(s) older than {} day(s)",
+                    pruned,
+                    days
+                );
+            }
+            Ok(_) => {}
+            Err(e) => {
+                log::error!("Failed to prune bridge log: {}", e);
+            }
+        }
+    }
+
+    async fn post_net_rosters(&self, my_node_id: u32) {
+        let now = chrono::Utc::now().timestamp();
+        let nets = match self.db.run(move |db| db.take_finished_nets(now)).await {
+            Ok(nets) => nets,
+            Err(e) => {
+                log::error!("Failed to load finished nets: {}", e);
+                return;
+            }
+        };
+        for net in nets {
+            let roster: Vec<String> = net
+                .checkins
+                .iter()
+                .map(|c| match &c.comment {
+                    Some(comment) => format!("{} ({})", c.callsign, comment),
+                    None => c.callsign.clone(),
+                })
+                .collect();
+            log::info!(
+                "Net {:?} closed with {} check-in(s)",
+                net.name,
+                roster.len()
+            );
+            let ctx = MessageContext {
+                sender_id: my_node_id,
+                sender_name: String::new(),
+                channel: net.channel,
+                is_dm: false,
+                rssi: 0,
+                snr: 0.0,
+                hop_count: 0,
+                hop_start: 0,
+                hop_limit: 0,
+                via_mqtt: false,
+                pki_key: None,
+                packet_id: 0,
+            };
+            let response = Response {
+                text: format!(
+                    "{} closed, {} check-in(s): {}",
+                    net.name,
+                    roster.len(),
+                    roster.join(", ")
+                ),
+                destination: Destination::Broadcast,
+                channel: net.channel,
+                reply_id: None,
+                data: None,
+            };
+            self.queue_responses(&ctx, &[response], my_node_id);
+        }
+    }
+
+    /// Close polls past their `closes_at` and broadcast the results on the
+    /// channel each was started on.
+    pub(super) async fn close_expired_polls(&self, my_node_id: u32) {
+        let now = chrono::Utc::now().timestamp();
+        let polls = self
+            .db
+            .run(move |db| {
+                let polls = db.take_expired_polls(now)?;
+                Ok(polls
+                    .into_iter()
+                    .filter_map(|poll| match db.poll_tally(poll.id, poll.options.len()) {
+                        Ok(tally) => Some((poll, tally)),
+                        Err(e) => {
+                            log::error!("Failed to count votes for poll {}: {}", poll.id, e);
+                            None
+                        }
+                    })
+                    .collect::<Vec<_>>())
+            })
+            .await;
+        let polls = match polls {
+            Ok(polls) => polls,
+            Err(e) => {
+                log::error!("Failed to load expired polls: {}", e);
+                return;
+            }
+        };
+        for (poll, tally) in polls {
+            log::info!("Poll {:?} closed on its own", poll.question);
+            let ctx = MessageContext {
+                sender_id: my_node_id,
+                sender_name: String::new(),
+                channel: poll.channel,
+                is_dm: false,
+                rssi: 0,
+                snr: 0.0,
+                hop_count: 0,
+                hop_start: 0,
+                hop_limit: 0,
+                via_mqtt: false,
+                pki_key: None,
+                packet_id: 0,
+            };
+            let response = Response {
+                text: crate::modules::poll_closed_text(&poll, &tally),
+                destination: Destination::Broadcast,
+                channel: poll.channel,
+                reply_id: None,
+                data: None,
+            };
+            self.queue_responses(&ctx, &[response], my_node_id);
+        }
+    }
+
+    async fn cleanup_traceroute_sessions(&self) {
+        let cfg = &self.config.traceroute_sessions;
+        if cfg.expire_after_secs > 0 {
+            let expire_after_secs = cfg.expire_after_secs;
+            match self
+                .db
+                .run(move |db| db.expire_stale_traceroute_sessions(expire_after_secs))
+                .await
+            {
+                Ok(expired) if expired > 0 => {
+                    log::info!(
+                        "Expired {} incomplete traceroute session(s) idle for over {}s",
+                        expired,
+                        cfg.expire_after_secs
+                    );
+                    self.notify_dashboard();
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    log::error!("Failed to expire traceroute sessions: {}", e);
+                }
+            }
+        }
+
+        if cfg.hop_retention_days > 0 {
+            let max_age_secs = cfg.hop_retention_days.saturating_mul(24 * 60 * 60);
+            match self
+                .db
+                .run(move |db| db.prune_traceroute_hops_older_than(max_age_secs))
+                .await
+            {
+                Ok(pruned) if pruned > 0 => {
+                    log::info!(
+                        "Pruned {} traceroute hop row(s) older than {} day(s)",
+                        pruned,
+                        cfg.hop_retention_days
+                    );
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    log::error!("Failed to prune traceroute hops: {}", e);
+                }
+            }
+        }
+
+        match self.db.run(|db| db.backfill_traceroute_hop_stats()).await {
+            Ok(nodes) => {
+                log::debug!("Recomputed traceroute hop stats for {} node(s)", nodes);
+            }
+            Err(e) => {
+                log::error!("Failed to backfill traceroute hop stats: {}", e);
+            }
+        }
+    }
+
+    /// Recompute the hop matrix and articulation points from recent links.
+    async fn refresh_mesh_graph(&self) {
+        let now = chrono::Utc::now().timestamp();
+        let links = match self
+            .db
+            .run(move |db| db.mesh_links(now - mesh_graph::LINK_WINDOW_SECS))
+            .await
+        {
+            Ok(links) => links,
+            Err(e) => {
+                log::error!("Failed to load mesh links: {}", e);
+                return;
+            }
+        };
+        let graph = MeshGraph::new(&links);
+        let hops: Vec<(u32, u32, u32)> = graph
+            .nodes()
+            .flat_map(|from| {
+                graph
+                    .hops_from(from)
+                    .into_iter()
+                    .filter(move |&(to, _)| to > from)
+                    .map(move |(to, count)| (from, to, count))
+            })
+            .collect();
+        let cut_nodes = graph.articulation_points();
+        let (pairs, cut_count) = (hops.len(), cut_nodes.len());
+        match self
+            .db
+            .run(move |db| db.store_mesh_graph(&hops, &cut_nodes, now))
+            .await
+        {
+            Ok(()) => log::debug!(
+                "Recomputed mesh graph: {} reachable pair(s), {} articulation point(s)",
+                pairs,
+                cut_count
+            ),
+            Err(e) => log::error!("Failed to store mesh graph: {}", e),
+        }
+    }
+
+    pub(super) async fn maybe_queue_traceroute_probe(&self, my_node_id: u32) {
         let cfg = &self.config.traceroute_probe;
         if !cfg.enabled {
             log::info!("Traceroute probe skipped: feature disabled");
             return;
         }
 
+        // One query for the widest window; the narrower ones are its prefixes
         let limits = [10usize, 25, 50, 100];
+        let (seen_within, widest) = (cfg.recent_seen_within_secs, limits[limits.len() - 1]);
+        let candidates = self
+            .db
+            .run(move |db| db.recent_rf_nodes_missing_hops(seen_within, Some(my_node_id), widest))
+            .await;
         let selection = match select_probe_target_adaptive(
             &limits,
             |limit| {
-                self.db.recent_rf_nodes_missing_hops(
-                    cfg.recent_seen_within_secs,
-                    Some(my_node_id),
-                    limit,
-                )
+                candidates
+                    .as_ref()
+                    .map(|nodes| nodes[..limit.min(nodes.len())].to_vec())
+                    .map_err(|e| e.to_string())
             },
             |node_id| {
-                let can_send = self
-                    .traceroute
-                    .can_send(node_id, cfg.per_node_cooldown_secs);
+                let can_send =
+                    self.traceroute
+                        .can_send(node_id, cfg.per_node_cooldown_secs, self.now());
                 if !can_send {
                     log::trace!(
                         "Traceroute probe candidate !{:08x} skipped due to cooldown ({}s)",
@@ -381,9 +1088,10 @@ impl Bot {
             to_node: Some(target),
             mesh_channel: cfg.mesh_channel,
             reply_id: None,
+            expires_at: None,
         });
 
-        self.traceroute.mark_sent(target);
+        self.traceroute.mark_sent(target, self.now());
         log::info!("Queued traceroute probe for !{:08x}", target);
     }
 }
@@ -435,6 +1143,19 @@ mod tests {
         assert_eq!(next_traceroute_interval(base, 0.0), base);
     }
 
+    #[test]
+    fn recent_packets_drops_duplicates_and_forgets_oldest() {
+        let mut recent = RecentPackets::new(2);
+        assert!(recent.insert(1, 10));
+        assert!(!recent.insert(1, 10));
+        assert!(recent.insert(2, 10));
+        // Packets without an ID are never treated as duplicates
+        assert!(recent.insert(1, 0));
+        assert!(recent.insert(1, 0));
+        assert!(recent.insert(3, 10));
+        assert!(recent.insert(1, 10));
+    }
+
     #[test]
     fn select_probe_target_adaptive_expands_and_finds_candidate() {
         let limits = [10usize, 25, 50, 100];
@@ -0,0 +1,59 @@
+use chrono::{DateTime, Local};
+use meshtastic::packet::PacketDestination;
+use meshtastic::types::MeshChannel;
+
+use super::selftest::MAX_LATENESS_SECS;
+use super::*;
+use crate::config::ScheduleConfig;
+use crate::scheduler::CronSchedule;
+
+/// `bot_state` key holding the last run of an entry (unix seconds). Keyed by
+/// content, so an edited entry starts fresh and reordering changes nothing.
+fn last_run_key(entry: &ScheduleConfig) -> String {
+    format!(
+        "schedule_last_run:{}|{}|{}",
+        entry.cron, entry.channel, entry.text
+    )
+}
+
+impl Bot {
+    /// Queue the `[[schedule]]` announcements due at `now`. Each run is
+    /// recorded, so a restart doesn't send it twice; runs missed by more
+    /// than a few minutes are skipped.
+    pub(super) async fn run_schedule(&self, my_node_id: u32, now: DateTime<Local>) {
+        for entry in &self.config.schedule {
+            // Invalid entries are rejected by `Config::load`
+            let Ok(cron) = CronSchedule::parse(&entry.cron) else {
+                continue;
+            };
+            let due = cron.latest_run(now, MAX_LATENESS_SECS / 60);
+            if !self
+                .claim_slot(&last_run_key(entry), due, now.timestamp())
+                .await
+            {
+                continue;
+            }
+            match MeshChannel::new(entry.channel) {
+                Ok(channel) => {
+                    log::info!(
+                        "Queueing scheduled announcement ({}) on channel {}",
+                        entry.cron,
+                        entry.channel
+                    );
+                    self.queue_message(OutgoingMeshMessage {
+                        kind: OutgoingKind::Text,
+                        text: entry.text.clone(),
+                        destination: PacketDestination::Broadcast,
+                        channel,
+                        from_node: my_node_id,
+                        to_node: None,
+                        mesh_channel: entry.channel,
+                        reply_id: None,
+                        expires_at: None,
+                    });
+                }
+                Err(e) => log::error!("Invalid schedule channel {}: {}", entry.channel, e),
+            }
+        }
+    }
+}
@@ -0,0 +1,244 @@
+use chrono::{DateTime, Datelike, Local, NaiveTime, TimeZone, Weekday};
+use meshtastic::packet::PacketDestination;
+use meshtastic::protobufs;
+use meshtastic::types::MeshChannel;
+
+use super::*;
+use crate::bridge::{BridgeEvent, MeshBridgeMessage};
+use crate::message::ResponseData;
+
+/// `bot_state` key holding the scheduled time of the last test sent (unix seconds).
+const SELFTEST_SLOT_KEY: &str = "selftest_last_slot";
+/// `bot_state` key holding the scheduled time of the last weekly digest.
+const SELFTEST_DIGEST_KEY: &str = "selftest_last_digest";
+/// A slot missed by more than this (e.g. during an outage) is skipped, not sent late.
+pub(super) const MAX_LATENESS_SECS: i64 = 10 * 60;
+const REPORT_PERIOD_SECS: i64 = 7 * 24 * 60 * 60;
+
+/// The latest scheduled time at or before `now`, as unix seconds. Invalid
+/// entries are skipped; `Config::load` rejects them up front.
+pub(super) fn latest_slot(times: &[String], days: &[String], now: DateTime<Local>) -> Option<i64> {
+    let times: Vec<NaiveTime> = times
+        .iter()
+        .filter_map(|t| NaiveTime::parse_from_str(t, "%H:%M").ok())
+        .collect();
+    let days: Vec<Weekday> = days.iter().filter_map(|d| d.parse().ok()).collect();
+    let today = now.date_naive();
+    [today, today.pred_opt()?]
+        .into_iter()
+        .filter(|date| days.is_empty() || days.contains(&date.weekday()))
+        .flat_map(|date| times.iter().map(move |t| date.and_time(*t)))
+        .filter_map(|slot| Local.from_local_datetime(&slot).earliest())
+        .filter(|slot| *slot <= now)
+        .map(|slot| slot.timestamp())
+        .max()
+}
+
+impl Bot {
+    /// Whether the slot due at `now` is new and recent enough to act on; if
+    /// so it is recorded under `key` so it runs once, also across restarts.
+    pub(super) async fn claim_slot(&self, key: &str, slot: Option<i64>, now: i64) -> bool {
+        let Some(slot) = slot else {
+            return false;
+        };
+        let owned_key = key.to_string();
+        let claimed = self
+            .db
+            .run(move |db| {
+                let last = db
+                    .get_state(&owned_key)
+                    .unwrap_or(None)
+                    .and_then(|v| v.parse::<i64>().ok())
+                    .unwrap_or(0);
+                if slot <= last || now - slot > MAX_LATENESS_SECS {
+                    return Ok(false);
+                }
+                db.set_state(&owned_key, &slot.to_string())?;
+                Ok(true)
+            })
+            .await;
+        match claimed {
+            Ok(claimed) => claimed,
+            Err(e) => {
+                log::error!("Failed to record schedule state {}: {}", key, e);
+                false
+            }
+        }
+    }
+
+    /// Queue the test broadcast and post the weekly digest when they are due.
+    pub(super) async fn run_selftest_schedule(&self, my_node_id: u32, now: DateTime<Local>) {
+        let cfg = &self.config.selftest;
+        let ts = now.timestamp();
+
+        if self
+            .claim_slot(
+                SELFTEST_SLOT_KEY,
+                latest_slot(&cfg.times, &cfg.days, now),
+                ts,
+            )
+            .await
+        {
+            match MeshChannel::new(cfg.channel) {
+                Ok(channel) => {
+                    log::info!("Queueing self-test broadcast on channel {}", cfg.channel);
+                    self.queue_message(OutgoingMeshMessage {
+                        kind: OutgoingKind::SelfTest,
+                        text: cfg.message.clone(),
+                        destination: PacketDestination::Broadcast,
+                        channel,
+                        from_node: my_node_id,
+                        to_node: None,
+                        mesh_channel: cfg.channel,
+                        reply_id: None,
+                        expires_at: None,
+                    });
+                }
+                Err(e) => log::error!("Invalid self-test channel {}: {}", cfg.channel, e),
+            }
+        }
+
+        if !cfg.digest_day.is_empty()
+            && self
+                .claim_slot(
+                    SELFTEST_DIGEST_KEY,
+                    latest_slot(
+                        std::slice::from_ref(&cfg.digest_time),
+                        std::slice::from_ref(&cfg.digest_day),
+                        now,
+                    ),
+                    ts,
+                )
+                .await
+        {
+            self.post_selftest_digest(my_node_id, ts).await;
+        }
+    }
+
+    /// Relay the last week's reachability report to the bridges.
+    async fn post_selftest_digest(&self, my_node_id: u32, now: i64) {
+        let since = now - REPORT_PERIOD_SECS;
+        let report = match self.db.run(move |db| db.selftest_report(since)).await {
+            Ok(report) => report,
+            Err(e) => {
+                log::error!("Failed to build self-test report: {}", e);
+                return;
+            }
+        };
+        let answered = report.nodes.iter().filter(|n| n.responses > 0).count();
+        let text = format!(
+            "Weekly self-test: {} test(s), {} of {} RF node(s) answered",
+            report.runs,
+            answered,
+            report.nodes.len()
+        );
+        log::info!("{}", text);
+        let rows = report
+            .nodes
+            .iter()
+            .map(|n| {
+                let name = if n.node_name.is_empty() {
+                    n.node_id.clone()
+                } else {
+                    n.node_name.clone()
+                };
+                vec![name, format!("{}/{}", n.responses, report.runs)]
+            })
+            .collect();
+        self.bridge.relay(MeshBridgeMessage {
+            sender_id: my_node_id,
+            sender_name: self.config.bot.name.clone(),
+            text,
+            channel: self.config.selftest.channel,
+            is_dm: false,
+            event: BridgeEvent::SelfTestReport,
+            data: Some(ResponseData::Table {
+                headers: vec!["Node".to_string(), "Answered".to_string()],
+                rows,
+            }),
+            packet_id: None,
+        });
+    }
+
+    /// Record acks of our test broadcasts, and rebroadcasts of them heard back
+    /// (attributed through the packet's relay node).
+    pub(super) async fn observe_selftest_response(
+        &self,
+        my_node_id: u32,
+        mesh_packet: &protobufs::MeshPacket,
+        data: &protobufs::Data,
+    ) {
+        let is_routing = data.portnum() == protobufs::PortNum::RoutingApp;
+        let (packet_id, sender, relay, kind) = if is_routing && mesh_packet.from != my_node_id {
+            (data.request_id, Some(mesh_packet.from), 0, "ack")
+        } else if is_routing || mesh_packet.from == my_node_id {
+            // Our radio's implicit ack, or our own packet heard back
+            let packet_id = if is_routing {
+                data.request_id
+            } else {
+                mesh_packet.id
+            };
+            let relay = (mesh_packet.relay_node & 0xff) as u8;
+            if relay == 0 || relay == (my_node_id & 0xff) as u8 {
+                return;
+            }
+            (packet_id, None, relay, "rebroadcast")
+        } else {
+            return;
+        };
+        if packet_id == 0 {
+            return;
+        }
+        let window = self.config.selftest.response_window_secs;
+        let recorded = self
+            .db
+            .run(move |db| {
+                let node = match sender {
+                    Some(node) => Some(node),
+                    None => db.node_for_relay_byte(relay).unwrap_or(None),
+                };
+                let Some(node) = node else {
+                    return Ok(None);
+                };
+                Ok(Some((
+                    node,
+                    db.record_selftest_response(packet_id, node, kind, window)?,
+                )))
+            })
+            .await;
+        match recorded {
+            Ok(Some((node, true))) => log::info!("Self-test {}: !{:08x}", kind, node),
+            Ok(_) => {}
+            Err(e) => log::error!("Failed to record self-test response: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local(y: i32, m: u32, d: u32, h: u32, min: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(y, m, d, h, min, 0).unwrap()
+    }
+
+    #[test]
+    fn test_latest_slot() {
+        let times = vec!["03:00".to_string(), "15:00".to_string()];
+        // 2026-10-14 is a Wednesday
+        let now = local(2026, 10, 14, 16, 0);
+        assert_eq!(
+            latest_slot(&times, &[], now),
+            Some(local(2026, 10, 14, 15, 0).timestamp())
+        );
+        assert_eq!(
+            latest_slot(&times, &[], local(2026, 10, 14, 2, 0)),
+            Some(local(2026, 10, 13, 15, 0).timestamp())
+        );
+        assert_eq!(
+            latest_slot(&times, &["tue".to_string()], now),
+            Some(local(2026, 10, 13, 15, 0).timestamp())
+        );
+        assert_eq!(latest_slot(&times, &["fri".to_string()], now), None);
+    }
+}
@@ -0,0 +1,247 @@
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local};
+use serde::Serialize;
+
+use super::selftest::latest_slot;
+use super::*;
+use crate::config::SnapshotConfig;
+use crate::db::{DeviceTelemetry, MqttFilter};
+use crate::util::parse_node_id;
+
+/// `bot_state` key holding the scheduled time of the last snapshot written.
+const SNAPSHOT_SLOT_KEY: &str = "snapshot_last_slot";
+
+/// The whole mesh as one JSON document, for community map sites.
+#[derive(Debug, Serialize)]
+struct MeshSnapshot {
+    generated_at: i64,
+    /// The bot's own node, if connected.
+    node_id: Option<String>,
+    /// Nodes and links are those heard within this many hours.
+    hours: u32,
+    nodes: Vec<SnapshotNode>,
+    /// Radio links from NeighborInfo and traceroutes, each pair once.
+    edges: Vec<SnapshotEdge>,
+    /// Packet and message counts over the last 24 hours.
+    stats: SnapshotStats,
+}
+
+#[derive(Debug, Serialize)]
+struct SnapshotNode {
+    id: String,
+    short_name: String,
+    long_name: String,
+    last_seen: i64,
+    via_mqtt: bool,
+    online: bool,
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+    /// Fewest hops the node was heard from, packets and traceroutes combined.
+    hops: Option<u32>,
+    /// Latest DeviceMetrics report, if one came within the window.
+    telemetry: Option<DeviceTelemetry>,
+}
+
+#[derive(Debug, Serialize)]
+struct SnapshotEdge {
+    from: String,
+    to: String,
+}
+
+#[derive(Debug, Serialize)]
+struct SnapshotStats {
+    node_count: u64,
+    messages_in: u64,
+    messages_out: u64,
+    packets_in: u64,
+    packets_out: u64,
+}
+
+fn build_snapshot(
+    db: &Db,
+    cfg: &SnapshotConfig,
+    bot_name: &str,
+    my_node_id: u32,
+    now: i64,
+) -> Result<MeshSnapshot, Box<dyn std::error::Error + Send + Sync>> {
+    let since = now - cfg.hours as i64 * 3600;
+    let mut telemetry: HashMap<u32, DeviceTelemetry> = db
+        .latest_device_telemetry()?
+        .into_iter()
+        .filter(|(_, t)| t.timestamp > since)
+        .collect();
+    let round = |v: Option<f64>| match cfg.position_decimals {
+        Some(decimals) => {
+            let scale = 10f64.powi(decimals as i32);
+            v.map(|v| (v * scale).round() / scale)
+        }
+        None => v,
+    };
+    let nodes = db
+        .dashboard_nodes(cfg.hours, MqttFilter::All)?
+        .into_iter()
+        .map(|n| SnapshotNode {
+            telemetry: parse_node_id(&n.node_id).and_then(|id| telemetry.remove(&id)),
+            id: n.node_id,
+            short_name: n.short_name,
+            long_name: n.long_name,
+            last_seen: n.last_seen,
+            via_mqtt: n.via_mqtt,
+            online: n.online,
+            latitude: round(n.latitude),
+            longitude: round(n.longitude),
+            hops: n.combined_min_hop,
+        })
+        .collect();
+    let edges = db
+        .mesh_links(since)?
+        .into_iter()
+        .filter(|(a, b)| a != b)
+        .map(|(a, b)| (a.min(b), a.max(b)))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .map(|(a, b)| SnapshotEdge {
+            from: format!("!{:08x}", a),
+            to: format!("!{:08x}", b),
+        })
+        .collect();
+    let overview = db.dashboard_overview(24, MqttFilter::All, bot_name)?;
+    Ok(MeshSnapshot {
+        generated_at: now,
+        node_id: (my_node_id != 0).then(|| format!("!{:08x}", my_node_id)),
+        hours: cfg.hours,
+        nodes,
+        edges,
+        stats: SnapshotStats {
+            node_count: overview.node_count,
+            messages_in: overview.messages_in,
+            messages_out: overview.messages_out,
+            packets_in: overview.packets_in,
+            packets_out: overview.packets_out,
+        },
+    })
+}
+
+fn rotated(path: &Path, n: u32) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
+
+/// Replace the file at `path` with `contents`, first shifting up to `keep`
+/// older copies along (`<path>.1` is the previous one). The new file is
+/// renamed into place, so readers never see half of it.
+fn write_rotated(path: &Path, keep: u32, contents: &[u8]) -> std::io::Result<()> {
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)?;
+    }
+    let tmp = {
+        let mut name = path.as_os_str().to_owned();
+        name.push(".tmp");
+        PathBuf::from(name)
+    };
+    std::fs::write(&tmp, contents)?;
+    if keep > 0 && path.exists() {
+        for n in (1..keep).rev() {
+            let from = rotated(path, n);
+            if from.exists() {
+                std::fs::rename(&from, rotated(path, n + 1))?;
+            }
+        }
+        std::fs::rename(path, rotated(path, 1))?;
+    }
+    std::fs::rename(&tmp, path)
+}
+
+impl Bot {
+    /// Write `[snapshot]` once a day at its time.
+    pub(super) async fn run_snapshot_schedule(&self, my_node_id: u32, now: DateTime<Local>) {
+        let cfg = &self.config.snapshot;
+        if self
+            .claim_slot(
+                SNAPSHOT_SLOT_KEY,
+                latest_slot(std::slice::from_ref(&cfg.time), &[], now),
+                now.timestamp(),
+            )
+            .await
+        {
+            self.write_snapshot(my_node_id, now.timestamp()).await;
+        }
+    }
+
+    async fn write_snapshot(&self, my_node_id: u32, now: i64) {
+        let config = Arc::clone(&self.config);
+        let snapshot = self
+            .db
+            .run(move |db| build_snapshot(db, &config.snapshot, &config.bot.name, my_node_id, now))
+            .await;
+        let cfg = &self.config.snapshot;
+        let snapshot = match snapshot {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                log::error!("Failed to build mesh snapshot: {}", e);
+                return;
+            }
+        };
+        let json = match serde_json::to_vec(&snapshot) {
+            Ok(json) => json,
+            Err(e) => {
+                log::error!("Failed to encode mesh snapshot: {}", e);
+                return;
+            }
+        };
+        match write_rotated(Path::new(&cfg.path), cfg.keep, &json) {
+            Ok(()) => log::info!(
+                "Wrote mesh snapshot to {} ({} node(s), {} edge(s))",
+                cfg.path,
+                snapshot.nodes.len(),
+                snapshot.edges.len()
+            ),
+            Err(e) => log::error!("Failed to write mesh snapshot to {}: {}", cfg.path, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_snapshot() {
+        let db = Db::open(Path::new(":memory:")).unwrap();
+        db.upsert_node(0xaaaa0001, "RDGE", "Ridge", false).unwrap();
+        db.update_position(0xaaaa0001, 25.033_333, 121.566_666)
+            .unwrap();
+        db.upsert_node(0xaaaa0002, "VLY", "Valley", false).unwrap();
+        let cfg = SnapshotConfig {
+            position_decimals: Some(2),
+            ..SnapshotConfig::default()
+        };
+        let now = chrono::Utc::now().timestamp();
+
+        let snapshot = build_snapshot(&db, &cfg, "Meshenger", 0xaaaa0002, now).unwrap();
+        assert_eq!(snapshot.node_id.as_deref(), Some("!aaaa0002"));
+        assert_eq!(snapshot.nodes.len(), 2);
+        let ridge = snapshot.nodes.iter().find(|n| n.id == "!aaaa0001").unwrap();
+        assert_eq!(ridge.latitude, Some(25.03));
+        assert_eq!(ridge.longitude, Some(121.57));
+        assert_eq!(snapshot.stats.node_count, 2);
+    }
+
+    #[test]
+    fn test_write_rotated() {
+        let dir = std::env::temp_dir().join(format!("meshenger-snapshot-{}", std::process::id()));
+        let path = dir.join("mesh.json");
+        for day in 1..=4 {
+            write_rotated(&path, 2, format!("day {}", day).as_bytes()).unwrap();
+        }
+        let read = |p: PathBuf| std::fs::read_to_string(p).unwrap();
+        assert_eq!(read(path.clone()), "day 4");
+        assert_eq!(read(rotated(&path, 1)), "day 3");
+        assert_eq!(read(rotated(&path, 2)), "day 2");
+        assert!(!rotated(&path, 3).exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
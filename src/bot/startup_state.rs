@@ -5,7 +5,9 @@ use crate::message::MeshEvent;
 
 pub(super) struct StartupState {
     connected_at: Mutex<Option<Instant>>,
-    deferred_events: Mutex<Vec<MeshEvent>>,
+    /// Each with when the radio's NodeDB last heard the node, if that's
+    /// where it came from
+    deferred_events: Mutex<Vec<(MeshEvent, Option<i64>)>>,
 }
 
 impl StartupState {
@@ -16,24 +18,33 @@ impl StartupState {
         }
     }
 
-    pub(super) fn mark_connected_and_reset(&self) {
-        *self.connected_at.lock().unwrap() = Some(Instant::now());
+    pub(super) fn mark_connected_and_reset(&self, now: Instant) {
+        *self.connected_at.lock().unwrap() = Some(now);
         self.deferred_events.lock().unwrap().clear();
     }
 
-    pub(super) fn in_grace_period(&self, grace_secs: u64) -> bool {
+    /// Restart the grace period without dropping deferred events, for a
+    /// further radio connecting while others are up.
+    pub(super) fn mark_connected(&self, now: Instant) {
+        *self.connected_at.lock().unwrap() = Some(now);
+    }
+
+    pub(super) fn in_grace_period(&self, grace_secs: u64, now: Instant) -> bool {
         self.connected_at
             .lock()
             .unwrap()
-            .map(|t| t.elapsed() < Duration::from_secs(grace_secs))
+            .map(|t| now.saturating_duration_since(t) < Duration::from_secs(grace_secs))
             .unwrap_or(false)
     }
 
-    pub(super) fn defer_event(&self, event: MeshEvent) {
-        self.deferred_events.lock().unwrap().push(event);
+    pub(super) fn defer_event(&self, event: MeshEvent, nodedb_heard_at: Option<i64>) {
+        self.deferred_events
+            .lock()
+            .unwrap()
+            .push((event, nodedb_heard_at));
     }
 
-    pub(super) fn take_deferred(&self) -> Vec<MeshEvent> {
+    pub(super) fn take_deferred(&self) -> Vec<(MeshEvent, Option<i64>)> {
         let mut deferred = self.deferred_events.lock().unwrap();
         std::mem::take(&mut *deferred)
     }
@@ -0,0 +1,228 @@
+use chrono::Utc;
+use meshtastic::packet::PacketDestination;
+use meshtastic::protobufs::{self, store_and_forward, StoreAndForward};
+use meshtastic::types::{MeshChannel, NodeId};
+
+use crate::message::MessageContext;
+use crate::util::parse_node_id;
+
+use super::*;
+
+/// Largest history window a router is asked for, in minutes.
+const MAX_WINDOW_MINS: u32 = 24 * 60;
+
+const USAGE: &str = "Usage: !sf, !sf history [minutes] [!node]";
+
+impl Bot {
+    /// Handle a StoreForwardApp packet: note routers announcing themselves and
+    /// merge replayed history into the packets table.
+    pub(super) async fn handle_store_forward(
+        &self,
+        my_node_id: u32,
+        mesh_packet: &protobufs::MeshPacket,
+        data: &protobufs::Data,
+    ) {
+        let sf: StoreAndForward = match meshtastic::Message::decode(data.payload.as_slice()) {
+            Ok(sf) => sf,
+            Err(_) => return,
+        };
+        let rr = sf.rr();
+        match (rr, sf.variant) {
+            (
+                store_and_forward::RequestResponse::RouterTextBroadcast
+                | store_and_forward::RequestResponse::RouterTextDirect,
+                Some(store_and_forward::Variant::Text(text)),
+            ) => {
+                let text = match String::from_utf8(text) {
+                    Ok(text) => text,
+                    Err(_) => return,
+                };
+                let to_node = if rr == store_and_forward::RequestResponse::RouterTextDirect {
+                    Some(my_node_id)
+                } else {
+                    None
+                };
+                // Replayed packets keep the sender, ID and receive time of the original
+                let timestamp = if mesh_packet.rx_time != 0 {
+                    mesh_packet.rx_time as i64
+                } else {
+                    Utc::now().timestamp()
+                };
+                let (from, channel, id) = (mesh_packet.from, mesh_packet.channel, mesh_packet.id);
+                let merged = self
+                    .db
+                    .run(move |db| {
+                        let merged = db.merge_store_forward_text(
+                            from, to_node, channel, &text, id, timestamp,
+                        )?;
+                        Ok(merged.then_some(text))
+                    })
+                    .await;
+                match merged {
+                    Ok(Some(text)) => log::info!(
+                        "Merged S&F history from !{:08x} [msg_id={}]: {}",
+                        from,
+                        id,
+                        text
+                    ),
+                    Ok(None) => {}
+                    Err(e) => log::error!("Failed to merge S&F history: {}", e),
+                }
+            }
+            (
+                store_and_forward::RequestResponse::RouterHeartbeat
+                | store_and_forward::RequestResponse::RouterPong
+                | store_and_forward::RequestResponse::RouterHistory
+                | store_and_forward::RequestResponse::RouterStats,
+                variant,
+            ) => {
+                let stats = match &variant {
+                    Some(store_and_forward::Variant::Stats(stats)) => {
+                        Some((stats.messages_saved, stats.messages_max))
+                    }
+                    _ => None,
+                };
+                if let Some(store_and_forward::Variant::History(history)) = &variant {
+                    log::info!(
+                        "S&F router !{:08x} is replaying {} message(s) from the last {} min",
+                        mesh_packet.from,
+                        history.history_messages,
+                        history.window
+                    );
+                }
+                let from = mesh_packet.from;
+                if let Err(e) = self
+                    .db
+                    .run(move |db| db.record_store_forward_router(from, stats))
+                    .await
+                {
+                    log::error!("Failed to record S&F router: {}", e);
+                }
+            }
+            (
+                store_and_forward::RequestResponse::RouterBusy
+                | store_and_forward::RequestResponse::RouterError,
+                _,
+            ) => {
+                log::warn!(
+                    "S&F router !{:08x} rejected a request: {:?}",
+                    mesh_packet.from,
+                    rr
+                );
+            }
+            _ => {}
+        }
+    }
+
+    /// `!sf` lists known routers; `!sf history [minutes] [!node]` asks a
+    /// router (the most recently heard one by default) to replay its history.
+    pub(super) async fn store_forward_command(
+        &self,
+        my_node_id: u32,
+        ctx: &MessageContext,
+        args: &str,
+        is_dm: bool,
+    ) -> String {
+        let routers = self
+            .db
+            .run(|db| {
+                let routers = db.store_forward_routers()?;
+                Ok(routers
+                    .into_iter()
+                    .map(|r| {
+                        let name = db
+                            .get_node_name(r.node_id)
+                            .unwrap_or_else(|_| format!("!{:08x}", r.node_id));
+                        (r, name)
+                    })
+                    .collect::<Vec<_>>())
+            })
+            .await;
+        let routers = match routers {
+            Ok(routers) => routers,
+            Err(e) => {
+                log::error!("Failed to load S&F routers: {}", e);
+                return "Store & Forward is unavailable right now.".to_string();
+            }
+        };
+        let mut parts = args.split_whitespace();
+        match parts.next().map(str::to_lowercase).as_deref() {
+            None => {
+                if routers.is_empty() {
+                    return "No Store & Forward routers heard.".to_string();
+                }
+                let now = Utc::now().timestamp();
+                let list: Vec<String> = routers
+                    .iter()
+                    .map(|(r, name)| {
+                        let mut entry =
+                            format!("{} ({}m ago", name, (now - r.last_seen).max(0) / 60);
+                        if let (Some(saved), Some(max)) = (r.messages_saved, r.messages_max) {
+                            entry.push_str(&format!(", {}/{} msgs", saved, max));
+                        }
+                        entry.push(')');
+                        entry
+                    })
+                    .collect();
+                format!("S&F routers: {}", list.join(", "))
+            }
+            Some("history") => {
+                let config = &self.config.store_forward;
+                if config.admin_only && !self.is_verified_admin(ctx, is_dm).await {
+                    return "Only admins can request S&F history, by DM.".to_string();
+                }
+                let mut window = config.window_mins;
+                let mut router = routers.first().map(|(r, _)| r.node_id);
+                for arg in parts {
+                    if let Ok(mins) = arg.parse::<u32>() {
+                        window = mins;
+                    } else if let Some(id) = parse_node_id(arg) {
+                        router = Some(id);
+                    } else {
+                        return USAGE.to_string();
+                    }
+                }
+                let window = window.clamp(1, MAX_WINDOW_MINS);
+                let router = match router {
+                    Some(router) => router,
+                    None => return "No Store & Forward routers heard.".to_string(),
+                };
+                let channel = match MeshChannel::new(ctx.channel) {
+                    Ok(ch) => ch,
+                    Err(e) => {
+                        log::error!("Invalid channel {}: {}", ctx.channel, e);
+                        return "Store & Forward is unavailable right now.".to_string();
+                    }
+                };
+                self.queue_message(OutgoingMeshMessage {
+                    kind: OutgoingKind::StoreForward {
+                        target_node: router,
+                        request: StoreAndForward {
+                            rr: store_and_forward::RequestResponse::ClientHistory as i32,
+                            variant: Some(store_and_forward::Variant::History(
+                                store_and_forward::History {
+                                    window,
+                                    ..Default::default()
+                                },
+                            )),
+                        },
+                    },
+                    text: String::new(),
+                    destination: PacketDestination::Node(NodeId::from(router)),
+                    channel,
+                    from_node: my_node_id,
+                    to_node: Some(router),
+                    mesh_channel: ctx.channel,
+                    reply_id: None,
+                    expires_at: None,
+                });
+                format!(
+                    "Requested the last {} min of history from {}.",
+                    window,
+                    self.node_name(router).await
+                )
+            }
+            _ => USAGE.to_string(),
+        }
+    }
+}
@@ -1,21 +1,27 @@
+use super::clock::ManualClock;
 use super::outgoing::chunk_message;
 use super::*;
-use crate::bridge::OutgoingBridgeMessage;
+use crate::bridge::{BridgeEvent, OutgoingBridgeMessage};
 use crate::config::*;
 use crate::message::{Destination, MessageContext, Response};
 use crate::module::{Module, ModuleRegistry};
+use crate::test_support::{NodeInfoBuilder, PacketBuilder};
 use async_trait::async_trait;
 use meshtastic::packet::PacketDestination;
 use meshtastic::types::MeshChannel;
 use std::collections::HashMap;
 use std::path::Path;
+use std::time::{Duration, Instant};
 
 fn test_config() -> Config {
     Config {
-        connection: ConnectionConfig {
+        connections: vec![ConnectionConfig {
+            name: String::new(),
+            mode: "tcp".to_string(),
             address: "127.0.0.1:4403".to_string(),
             reconnect_delay_secs: 5,
-        },
+            mqtt: MqttConnectionConfig::default(),
+        }],
         bot: BotConfig {
             name: "TestBot".to_string(),
             db_path: ":memory:".to_string(),
@@ -25,6 +31,12 @@ fn test_config() -> Config {
             send_delay_ms: 1500,
             max_message_len: 220,
             startup_grace_secs: 30,
+            admin_nodes: Vec::new(),
+            suggest_commands: false,
+            public_replies: false,
+            mqtt_commands: "reply".to_string(),
+            max_nodes: 0,
+            dm_retries: 0,
         },
         welcome: WelcomeConfig {
             enabled: false,
@@ -32,16 +44,42 @@ fn test_config() -> Config {
             welcome_back_message: String::new(),
             absence_threshold_hours: 48,
             whitelist: Vec::new(),
+            rules: Vec::new(),
+            quiet_hours: Vec::new(),
         },
         weather: WeatherConfig {
             latitude: 0.0,
             longitude: 0.0,
             units: "metric".to_string(),
+            alerts: WeatherAlertsConfig::default(),
         },
         traceroute_probe: TracerouteProbeConfig::default(),
+        traceroute_sessions: TracerouteSessionsConfig::default(),
+        traceroute_abuse: TracerouteAbuseConfig::default(),
+        battery_alerts: BatteryAlertsConfig::default(),
+        environment_alerts: Vec::new(),
+        geofence: Vec::new(),
+        alerts: Vec::new(),
+        store_forward: StoreForwardConfig::default(),
+        remote_admin: RemoteAdminConfig::default(),
         modules: HashMap::new(),
         bridge: BridgeConfig::default(),
         dashboard: DashboardConfig::default(),
+        privacy: PrivacyConfig::default(),
+        db: DbConfig::default(),
+        mail: MailConfig::default(),
+        netlog: NetlogConfig::default(),
+        emergency: EmergencyConfig::default(),
+        startup_banner: StartupBannerConfig::default(),
+        help: HelpConfig::default(),
+        freshness: FreshnessConfig::default(),
+        selftest: SelfTestConfig::default(),
+        schedule: Vec::new(),
+        beacons: Vec::new(),
+        channel_budgets: Vec::new(),
+        snapshot: SnapshotConfig::default(),
+        event_hooks: EventHooksConfig::default(),
+        api_quotas: HashMap::new(),
     }
 }
 
@@ -77,13 +115,14 @@ impl Module for TestCommandModule {
         _command: &str,
         args: &str,
         _ctx: &MessageContext,
-        _db: &Db,
+        _db: &Arc<Db>,
     ) -> Result<Option<Vec<Response>>, Box<dyn std::error::Error + Send + Sync>> {
         Ok(Some(vec![Response {
             text: format!("echo:{args}"),
             destination: Destination::Sender,
             channel: 0,
             reply_id: None,
+            data: None,
         }]))
     }
 }
@@ -108,6 +147,7 @@ fn test_ctx(sender_id: u32, channel: u32) -> MessageContext {
         hop_start: 0,
         hop_limit: 0,
         via_mqtt: false,
+        pki_key: None,
         packet_id: 0,
     }
 }
@@ -127,6 +167,7 @@ fn test_queue_message_ordering() {
             to_node: None,
             mesh_channel: 0,
             reply_id: None,
+            expires_at: None,
         });
     }
 
@@ -156,6 +197,7 @@ fn test_queue_responses_chunking() {
         destination: Destination::Sender,
         channel: 0,
         reply_id: None,
+        data: None,
     }];
 
     bot.queue_responses(&ctx, &responses, my_node_id);
@@ -188,18 +230,21 @@ fn test_queue_responses_preserves_destination() {
             destination: Destination::Sender,
             channel: 3,
             reply_id: None,
+            data: None,
         },
         Response {
             text: "broadcast".to_string(),
             destination: Destination::Broadcast,
             channel: 0,
             reply_id: None,
+            data: None,
         },
         Response {
             text: "to node".to_string(),
             destination: Destination::Node(0xDEADBEEF),
             channel: 1,
             reply_id: None,
+            data: None,
         },
     ];
 
@@ -216,7 +261,6 @@ fn test_queue_responses_preserves_destination() {
     // Broadcast
     assert!(matches!(queue[1].destination, PacketDestination::Broadcast));
     assert_eq!(queue[1].to_node, None);
-    assert_eq!(queue[1].mesh_channel, 0);
 
     // Node(specific)
     assert!(matches!(queue[2].destination, PacketDestination::Node(_)));
@@ -225,7 +269,43 @@ fn test_queue_responses_preserves_destination() {
 }
 
 #[test]
-fn test_queue_message_from_bridge() {
+fn test_queue_command_reports_sender_messages() {
+    let bot = test_bot();
+    let ctx = test_ctx(0x12345678, 0);
+    assert_eq!(
+        bot.queue_command(&ctx)[0].text,
+        "Nothing queued for you (0 message(s) in the queue)."
+    );
+
+    let message = |from_node: u32, to_node: Option<u32>| OutgoingMeshMessage {
+        kind: OutgoingKind::Text,
+        text: "hi".to_string(),
+        destination: PacketDestination::Broadcast,
+        channel: MeshChannel::new(0).unwrap(),
+        from_node,
+        to_node,
+        mesh_channel: 0,
+        reply_id: None,
+        expires_at: None,
+    };
+    bot.queue_message(message(1, None));
+    bot.queue_message(message(1, Some(0x12345678)));
+    // Another radio's queue drains in parallel
+    bot.queue_message(message(2, None));
+    bot.queue_message(message(1, Some(0x12345678)));
+    bot.queue_message(message(1, Some(0x0000beef)));
+
+    // The last one is third in line at 1.5s apiece
+    let responses = bot.queue_command(&ctx);
+    assert_eq!(
+        responses[0].text,
+        "2 message(s) queued for you, all sent in about 5s."
+    );
+    assert!(matches!(responses[0].destination, Destination::Sender));
+}
+
+#[tokio::test]
+async fn test_queue_message_from_bridge() {
     let bot = test_bot();
     let my_node_id = 1;
 
@@ -233,9 +313,13 @@ fn test_queue_message_from_bridge() {
         text: "[TG:alice] Hello mesh!".to_string(),
         channel: 2,
         source: "telegram".to_string(),
+        sender: String::new(),
+        dm_session: None,
+        to_node: None,
+        received_at: Instant::now(),
     };
 
-    bot.handle_bridge_message(my_node_id, msg);
+    bot.handle_bridge_message(my_node_id, msg).await;
 
     let queue = bot.outgoing.snapshot();
     assert_eq!(queue.len(), 1);
@@ -246,6 +330,387 @@ fn test_queue_message_from_bridge() {
     assert_eq!(queue[0].to_node, None);
 }
 
+#[tokio::test]
+async fn test_dashboard_message_to_node_is_a_dm() {
+    let bot = test_bot();
+    bot.handle_bridge_message(
+        1,
+        OutgoingBridgeMessage {
+            text: "Net starts at 8".to_string(),
+            channel: 0,
+            source: "dashboard".to_string(),
+            sender: "dashboard".to_string(),
+            dm_session: None,
+            to_node: Some(0xaaaa0001),
+            received_at: Instant::now(),
+        },
+    )
+    .await;
+
+    let queue = bot.outgoing.snapshot();
+    assert_eq!(queue.len(), 1);
+    assert_eq!(queue[0].to_node, Some(0xaaaa0001));
+    assert!(matches!(queue[0].destination, PacketDestination::Node(_)));
+}
+
+#[tokio::test]
+async fn test_live_events_describe_what_changed() {
+    let (tx, mut rx) = tokio::sync::broadcast::channel(64);
+    let bot = test_bot().with_sse_sender(tx);
+
+    let packet = PacketBuilder::position(0xaaaa0001, 25.0, 121.5)
+        .hops(3, 2)
+        .build();
+    bot.handle_mesh_packet(1, &packet).await;
+    let info = NodeInfoBuilder::new(0xaaaa0002).short_name("RDGE").build();
+    bot.handle_node_info(1, &info, false).await;
+    let trace = PacketBuilder::traceroute_request(0xaaaa0003, 0xaaaa0004)
+        .id(9)
+        .build();
+    bot.handle_mesh_packet(1, &trace).await;
+    bot.handle_bridge_message(
+        1,
+        OutgoingBridgeMessage {
+            text: "[TG:alice] hi".to_string(),
+            channel: 0,
+            source: "telegram".to_string(),
+            sender: "alice".to_string(),
+            dm_session: None,
+            to_node: None,
+            received_at: Instant::now(),
+        },
+    )
+    .await;
+    bot.pop_sendable(1, true).unwrap();
+
+    let mut events = Vec::new();
+    while let Ok(event) = rx.try_recv() {
+        events.push(event);
+    }
+    assert!(events.iter().any(|e| matches!(
+        e,
+        LiveEvent::Packet { from_node, packet_type: "position", hops: Some(1), .. }
+            if from_node == "!aaaa0001"
+    )));
+    assert!(events.iter().any(|e| matches!(
+        e,
+        LiveEvent::Node { node_id, short_name, .. }
+            if node_id == "!aaaa0002" && short_name == "RDGE"
+    )));
+    assert!(events
+        .iter()
+        .any(|e| matches!(e, LiveEvent::Traceroute { .. })));
+    let depths: Vec<usize> = events
+        .iter()
+        .filter_map(|e| match e {
+            LiveEvent::QueueDepth { depth } => Some(*depth),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(depths, vec![1, 0]);
+}
+
+#[tokio::test]
+async fn test_bridge_messages_are_logged() {
+    let bot = test_bot();
+    let msg = |text: &str, age: Duration| OutgoingBridgeMessage {
+        text: text.to_string(),
+        channel: 1,
+        source: "telegram".to_string(),
+        sender: "alice".to_string(),
+        dm_session: None,
+        to_node: None,
+        received_at: Instant::now() - age,
+    };
+    let max_age = Duration::from_secs(bot.config.bridge.max_queue_age_secs);
+    bot.handle_bridge_message(1, msg("[TG:alice] old", max_age + Duration::from_secs(1)))
+        .await;
+    bot.handle_bridge_message(1, msg("[TG:alice] hi", Duration::ZERO))
+        .await;
+
+    let log = bot.db.bridge_log("telegram", 0, 10).unwrap();
+    assert_eq!(log.len(), 2);
+    assert_eq!(log[0].text, "[TG:alice] hi");
+    assert_eq!(log[0].direction, "to_mesh");
+    assert_eq!(log[0].source, "alice");
+    assert_eq!(log[0].channel, 1);
+    // Dropped for its age, so never sent
+    assert_eq!(log[1].mesh_packet_id, None);
+    let queue = bot.outgoing.snapshot();
+    assert_eq!(queue.len(), 1);
+    assert!(matches!(
+        queue[0].kind,
+        OutgoingKind::BridgeText { log_id } if log_id == log[0].id
+    ));
+}
+
+#[tokio::test]
+async fn test_stale_bridge_messages_are_dropped() {
+    let clock = Arc::new(ManualClock::new());
+    let bot = test_bot().with_clock(clock.clone());
+    let max_age = Duration::from_secs(bot.config.bridge.max_queue_age_secs);
+    let msg = |text: &str, age: Duration| OutgoingBridgeMessage {
+        text: text.to_string(),
+        channel: 0,
+        source: "telegram".to_string(),
+        sender: String::new(),
+        dm_session: None,
+        to_node: None,
+        received_at: clock.now() - age,
+    };
+
+    // Waited out the outage in the bridge channel
+    bot.handle_bridge_message(1, msg("[TG:alice] old", max_age + Duration::from_secs(1)))
+        .await;
+    assert!(bot.outgoing.snapshot().is_empty());
+
+    // Queued in time, expires before the radio sends it
+    bot.handle_bridge_message(
+        1,
+        msg("[TG:alice] late", max_age - Duration::from_millis(1)),
+    )
+    .await;
+    bot.handle_bridge_message(1, msg("[TG:alice] fresh", Duration::ZERO))
+        .await;
+    assert_eq!(bot.outgoing.snapshot().len(), 2);
+    clock.advance(Duration::from_millis(2));
+    let sent = bot.pop_sendable(1, true).unwrap();
+    assert_eq!(sent.text, "[TG:alice] fresh");
+    assert!(bot.pop_sendable(1, true).is_none());
+}
+
+#[test]
+fn test_channel_budget_defers_replies_and_drops_broadcasts() {
+    let mut config = test_config();
+    config.channel_budgets = vec![ChannelBudgetConfig {
+        channel: 0,
+        max_per_hour: 2,
+    }];
+    let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+    let clock = Arc::new(ManualClock::new());
+    let bot = Bot::new(Arc::new(config), db, ModuleRegistry::new()).with_clock(clock.clone());
+    let message = |text: &str, channel: u32, reply_id: Option<u32>| OutgoingMeshMessage {
+        kind: OutgoingKind::Text,
+        text: text.to_string(),
+        destination: PacketDestination::Broadcast,
+        channel: MeshChannel::new(channel).unwrap(),
+        from_node: 1,
+        to_node: None,
+        mesh_channel: channel,
+        reply_id,
+        expires_at: None,
+    };
+    for text in ["beacon 1", "beacon 2", "beacon 3"] {
+        bot.queue_message(message(text, 0, None));
+    }
+    bot.queue_message(message("reply", 0, Some(42)));
+    bot.queue_message(message("other channel", 1, None));
+
+    let sent = || bot.pop_sendable(1, true).map(|m| m.text);
+    assert_eq!(sent().as_deref(), Some("beacon 1"));
+    assert_eq!(sent().as_deref(), Some("beacon 2"));
+    // The third beacon is dropped, the reply waits
+    assert_eq!(sent().as_deref(), Some("other channel"));
+    assert_eq!(sent(), None);
+    assert_eq!(bot.outgoing.snapshot().len(), 1);
+
+    clock.advance(Duration::from_secs(59 * 60));
+    assert_eq!(sent(), None);
+    clock.advance(Duration::from_secs(60));
+    assert_eq!(sent().as_deref(), Some("reply"));
+}
+
+#[tokio::test]
+async fn test_simulated_hours_of_probes_and_grace_period() {
+    let mut config = test_config();
+    config.traceroute_probe.enabled = true;
+    config.traceroute_probe.per_node_cooldown_secs = 3600;
+    let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+    db.upsert_node(0xaaaa0001, "FAR", "Far", false).unwrap();
+    let clock = Arc::new(ManualClock::new());
+    let bot = Bot::new(Arc::new(config), db, ModuleRegistry::new()).with_clock(clock.clone());
+
+    // Node info is held back until the grace period runs out
+    bot.startup_state.mark_connected_and_reset(bot.now());
+    let info = NodeInfoBuilder::new(0xaaaa0002).long_name("Late").build();
+    bot.handle_node_info(1, &info, true).await;
+    clock.advance(Duration::from_secs(29));
+    assert!(bot
+        .startup_state
+        .in_grace_period(bot.config.bot.startup_grace_secs, bot.now()));
+    clock.advance(Duration::from_secs(1));
+    assert!(!bot
+        .startup_state
+        .in_grace_period(bot.config.bot.startup_grace_secs, bot.now()));
+    assert_eq!(bot.startup_state.take_deferred().len(), 1);
+
+    // A probe timer every 15 minutes for three hours, draining the queue
+    // in between: the one candidate is probed once per cooldown
+    let mut probed_at = Vec::new();
+    for tick in 0..12 {
+        bot.maybe_queue_traceroute_probe(1).await;
+        while let Some(msg) = bot.pop_sendable(1, true) {
+            assert_eq!(msg.to_node, Some(0xaaaa0001));
+            probed_at.push(tick * 15);
+        }
+        clock.advance(Duration::from_secs(15 * 60));
+    }
+    assert_eq!(probed_at, vec![0, 60, 120]);
+}
+
+#[tokio::test]
+async fn test_long_bridge_message_is_chunked_with_markers() {
+    let bot = test_bot();
+    let body = "word ".repeat(80);
+    let msg = OutgoingBridgeMessage {
+        text: format!("[DC:bob] {}", body.trim_end()),
+        channel: 0,
+        source: "discord".to_string(),
+        sender: String::new(),
+        dm_session: None,
+        to_node: None,
+        received_at: Instant::now(),
+    };
+
+    bot.handle_bridge_message(1, msg).await;
+
+    let queue = bot.outgoing.snapshot();
+    assert_eq!(queue.len(), 2);
+    assert!(queue[0].text.starts_with("[DC:bob] (1/2) word word"));
+    assert!(queue[1].text.starts_with("[DC:bob] (2/2) "));
+    for msg in &queue {
+        assert!(msg.text.len() <= 220);
+    }
+    let rejoined: String = queue
+        .iter()
+        .map(|m| m.text.split_once(") ").unwrap().1)
+        .collect();
+    assert_eq!(rejoined, body.trim_end());
+}
+
+#[tokio::test]
+async fn test_startup_banner_sent_at_most_once_per_interval() {
+    let mut config = test_config();
+    config.startup_banner.enabled = true;
+    let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+    let bot = Bot::new(Arc::new(config), Arc::clone(&db), ModuleRegistry::new());
+
+    bot.send_startup_banner(1).await;
+    bot.send_startup_banner(1).await;
+
+    let queue = bot.outgoing.snapshot();
+    assert_eq!(queue.len(), 1);
+    assert_eq!(
+        queue[0].text,
+        format!(
+            "TestBot v{} is online. Send !help for commands.",
+            env!("CARGO_PKG_VERSION")
+        )
+    );
+    assert!(matches!(queue[0].destination, PacketDestination::Broadcast));
+
+    // A restart with the interval disabled sends it again
+    let mut config = test_config();
+    config.startup_banner.enabled = true;
+    config.startup_banner.min_interval_hours = 0;
+    let bot = Bot::new(Arc::new(config), db, ModuleRegistry::new());
+    bot.send_startup_banner(1).await;
+    assert_eq!(bot.outgoing.snapshot().len(), 1);
+}
+
+#[tokio::test]
+async fn test_schedule_fires_once_across_restarts() {
+    use chrono::TimeZone;
+
+    let mut config = test_config();
+    config.schedule = vec![crate::config::ScheduleConfig {
+        cron: "0 18 * * *".to_string(),
+        text: "Net starts at 19:00".to_string(),
+        channel: 1,
+    }];
+    let config = Arc::new(config);
+    let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+    let at = |h, m| {
+        chrono::Local
+            .with_ymd_and_hms(2026, 10, 14, h, m, 0)
+            .unwrap()
+    };
+
+    let bot = Bot::new(Arc::clone(&config), Arc::clone(&db), ModuleRegistry::new());
+    bot.run_schedule(1, at(17, 59)).await;
+    assert!(bot.outgoing.snapshot().is_empty());
+    bot.run_schedule(1, at(18, 0)).await;
+    bot.run_schedule(1, at(18, 1)).await;
+    let queue = bot.outgoing.snapshot();
+    assert_eq!(queue.len(), 1);
+    assert_eq!(queue[0].text, "Net starts at 19:00");
+    assert_eq!(queue[0].mesh_channel, 1);
+    assert!(matches!(queue[0].destination, PacketDestination::Broadcast));
+
+    // A restart shortly after doesn't send it again
+    let bot = Bot::new(config, db, ModuleRegistry::new());
+    bot.run_schedule(1, at(18, 3)).await;
+    assert!(bot.outgoing.snapshot().is_empty());
+}
+
+#[tokio::test]
+async fn test_beacons_respect_interval_hours_and_airtime() {
+    use chrono::TimeZone;
+
+    let mut config = test_config();
+    config.beacons = vec![crate::config::BeaconConfig {
+        text: "Please keep channel 0 for short messages".to_string(),
+        channel: 1,
+        interval_mins: 60,
+        jitter_pct: 0.0,
+        start_hour: 8,
+        end_hour: 20,
+        max_channel_utilization: 25.0,
+        max_air_util_tx: 10.0,
+    }];
+    let config = Arc::new(config);
+    let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+    let at = |h, m| {
+        chrono::Local
+            .with_ymd_and_hms(2026, 10, 14, h, m, 0)
+            .unwrap()
+    };
+
+    let bot = Bot::new(Arc::clone(&config), Arc::clone(&db), ModuleRegistry::new());
+    bot.run_beacons(1, at(7, 59)).await;
+    assert!(bot.outgoing.snapshot().is_empty());
+    bot.run_beacons(1, at(8, 0)).await;
+    bot.run_beacons(1, at(8, 30)).await;
+    let queue = bot.outgoing.snapshot();
+    assert_eq!(queue.len(), 1);
+    assert_eq!(queue[0].text, "Please keep channel 0 for short messages");
+    assert_eq!(queue[0].mesh_channel, 1);
+    assert!(matches!(queue[0].destination, PacketDestination::Broadcast));
+
+    // A restart doesn't send it before the interval is up
+    let bot = Bot::new(config, Arc::clone(&db), ModuleRegistry::new());
+    bot.run_beacons(1, at(8, 59)).await;
+    assert!(bot.outgoing.snapshot().is_empty());
+
+    // Held while the radio reports a busy channel
+    let telemetry = |timestamp: i64, channel_utilization: f64| crate::db::DeviceTelemetry {
+        timestamp,
+        battery_level: None,
+        voltage: None,
+        channel_utilization: Some(channel_utilization),
+        air_util_tx: Some(2.0),
+        uptime_seconds: None,
+    };
+    db.log_device_telemetry(1, &telemetry(at(8, 55).timestamp(), 40.0))
+        .unwrap();
+    bot.run_beacons(1, at(9, 0)).await;
+    assert!(bot.outgoing.snapshot().is_empty());
+    db.log_device_telemetry(1, &telemetry(at(9, 3).timestamp(), 12.0))
+        .unwrap();
+    bot.run_beacons(1, at(9, 5)).await;
+    assert_eq!(bot.outgoing.snapshot().len(), 1);
+}
+
 #[test]
 fn test_queue_empty_response_not_enqueued() {
     let bot = test_bot();
@@ -312,6 +777,41 @@ async fn test_dispatch_command_help_enqueues_reply() {
     assert_eq!(queue[0].text, "No commands available.");
 }
 
+#[test]
+fn test_help_text_uses_configured_or_requested_language() {
+    let mut config = test_config();
+    config.help.language = "de".to_string();
+    for (lang, text) in [("de", "Test-Befehl"), ("es", "Comando de prueba")] {
+        config.help.translations.insert(
+            lang.to_string(),
+            HashMap::from([("test_cmd".to_string(), text.to_string())]),
+        );
+    }
+    let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+    let mut registry = ModuleRegistry::new();
+    registry.register(Box::new(TestCommandModule));
+    let bot = Bot::new(Arc::new(config), db, registry);
+
+    assert_eq!(bot.generate_help_text(""), "!echo - Test-Befehl");
+    assert_eq!(bot.generate_help_text("ES"), "!echo - Comando de prueba");
+    // Unknown languages use the configured one
+    assert_eq!(bot.generate_help_text("fr"), "!echo - Test-Befehl");
+    // Languages without an entry for a module fall back to its description
+    let mut config = test_config();
+    config
+        .help
+        .translations
+        .insert("de".to_string(), HashMap::new());
+    let mut registry = ModuleRegistry::new();
+    registry.register(Box::new(TestCommandModule));
+    let bot = Bot::new(
+        Arc::new(config),
+        Arc::new(Db::open(Path::new(":memory:")).unwrap()),
+        registry,
+    );
+    assert_eq!(bot.generate_help_text("de"), "!echo - test command module");
+}
+
 #[tokio::test]
 async fn test_dispatch_command_module_sets_reply_id_when_missing() {
     let bot = test_bot_with_module(Box::new(TestCommandModule));
@@ -328,6 +828,128 @@ async fn test_dispatch_command_module_sets_reply_id_when_missing() {
     assert_eq!(queue[0].text, "echo:hello");
 }
 
+#[tokio::test]
+async fn test_unknown_command_suggests_closest_when_enabled() {
+    let mut config = test_config();
+    config.bot.suggest_commands = true;
+    let mut registry = ModuleRegistry::new();
+    registry.register(Box::new(TestCommandModule));
+    let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+    let bot = Bot::new(Arc::new(config), db, registry);
+    let ctx = test_ctx(0x22222222, 0);
+
+    for text in ["!ecoh hi", "!hlep", "!xyzzy"] {
+        bot.dispatch_command_from_text(1, &ctx, text, false).await;
+    }
+    let replies: Vec<String> = bot
+        .outgoing
+        .snapshot()
+        .into_iter()
+        .map(|m| m.text)
+        .collect();
+    assert_eq!(
+        replies,
+        vec![
+            "Unknown command, did you mean !echo?",
+            "Unknown command, did you mean !help?",
+        ]
+    );
+
+    // Off by default: typos stay silent
+    let bot = test_bot_with_module(Box::new(TestCommandModule));
+    bot.dispatch_command_from_text(1, &ctx, "!ecoh hi", false)
+        .await;
+    assert!(bot.outgoing.snapshot().is_empty());
+}
+
+#[tokio::test]
+async fn test_public_reply_flag_and_module_override() {
+    let ctx = test_ctx(0x22222222, 2);
+
+    // Default: channel commands are answered by DM unless --public is given
+    let bot = test_bot_with_module(Box::new(TestCommandModule));
+    bot.dispatch_command_from_text(1, &ctx, "!echo hi", false)
+        .await;
+    bot.dispatch_command_from_text(1, &ctx, "!echo --public hi", false)
+        .await;
+    bot.dispatch_command_from_text(1, &ctx, "!echo hi --public", true)
+        .await;
+    let queue = bot.outgoing.snapshot();
+    assert_eq!(queue[0].to_node, Some(0x22222222));
+    assert_eq!(queue[1].to_node, None);
+    assert_eq!(queue[1].mesh_channel, 0);
+    assert_eq!(queue[1].text, "echo:hi");
+    // A DM command stays a DM
+    assert_eq!(queue[2].to_node, Some(0x22222222));
+
+    // public_replies on: answered on the channel, help included
+    let mut config = test_config();
+    config.bot.public_replies = true;
+    let mut registry = ModuleRegistry::new();
+    registry.register(Box::new(TestCommandModule));
+    let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+    let bot = Bot::new(Arc::new(config), Arc::clone(&db), registry);
+    bot.dispatch_command_from_text(1, &ctx, "!help", false)
+        .await;
+    bot.dispatch_command_from_text(1, &ctx, "!echo hi", false)
+        .await;
+    let queue = bot.outgoing.snapshot();
+    assert_eq!(queue[0].to_node, None);
+    assert_eq!(queue[1].to_node, None);
+
+    // ...unless the module opts out
+    let mut config = test_config();
+    config.bot.public_replies = true;
+    config.modules.insert(
+        "test_cmd".to_string(),
+        ModuleConfig {
+            enabled: true,
+            scope: "both".to_string(),
+            rate_limit: None,
+            rate_limit_window_secs: None,
+            public_replies: Some(false),
+        },
+    );
+    let mut registry = ModuleRegistry::new();
+    registry.register(Box::new(TestCommandModule));
+    let bot = Bot::new(Arc::new(config), db, registry);
+    bot.dispatch_command_from_text(1, &ctx, "!echo hi", false)
+        .await;
+    assert_eq!(bot.outgoing.snapshot()[0].to_node, Some(0x22222222));
+}
+
+#[tokio::test]
+async fn test_mqtt_command_policy() {
+    let mut ctx = test_ctx(0x22222222, 0);
+    ctx.via_mqtt = true;
+    for (policy, expected) in [
+        ("reply", Some(None)),
+        ("dm", Some(Some(0x22222222))),
+        ("ignore", None),
+    ] {
+        let mut config = test_config();
+        config.bot.mqtt_commands = policy.to_string();
+        config.bot.public_replies = true;
+        let mut registry = ModuleRegistry::new();
+        registry.register(Box::new(TestCommandModule));
+        let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+        let bot = Bot::new(Arc::new(config), Arc::clone(&db), registry);
+
+        bot.dispatch_command_from_text(1, &ctx, "!echo hi", false)
+            .await;
+        // Not a command: neither answered nor counted
+        bot.dispatch_command_from_text(1, &ctx, "!!!", false).await;
+
+        let queue = bot.outgoing.snapshot();
+        assert_eq!(queue.first().map(|m| m.to_node), expected, "{}", policy);
+        let counts = db
+            .dashboard_overview(24, crate::db::MqttFilter::All, "TestBot")
+            .unwrap()
+            .mqtt_commands;
+        assert_eq!(counts.replied + counts.dm + counts.ignored, 1, "{}", policy);
+    }
+}
+
 #[tokio::test]
 async fn test_dispatch_command_ignores_non_prefixed_text() {
     let bot = test_bot_with_module(Box::new(TestCommandModule));
@@ -339,3 +961,2096 @@ async fn test_dispatch_command_ignores_non_prefixed_text() {
     let queue = bot.outgoing.snapshot();
     assert!(queue.is_empty());
 }
+
+#[tokio::test]
+async fn test_traceroute_flood_flags_node_and_notifies_admins() {
+    let mut config = test_config();
+    config.bot.admin_nodes = vec!["!0000beef".to_string()];
+    config.traceroute_abuse.enabled = true;
+    config.traceroute_abuse.threshold = 3;
+    config.traceroute_abuse.ignore_commands = true;
+    let bot = Bot::new(
+        Arc::new(config),
+        Arc::new(Db::open(Path::new(":memory:")).unwrap()),
+        ModuleRegistry::new(),
+    );
+
+    for id in 1..=5 {
+        bot.handle_mesh_packet(
+            1,
+            &PacketBuilder::traceroute_request(0xaaaa0001, 1)
+                .id(id)
+                .build(),
+        )
+        .await;
+    }
+    let queue = bot.outgoing.snapshot();
+    assert_eq!(queue.len(), 1, "admins are notified once per window");
+    assert_eq!(queue[0].to_node, Some(0xbeef));
+    assert_eq!(
+        queue[0].text,
+        "Traceroute flood: !aaaa0001 sent 3 traceroutes to me in the last 1h"
+    );
+
+    // Commands from the flagged node are ignored, others still answered
+    bot.dispatch_command_from_text(1, &test_ctx(0xaaaa0001, 0), "!help", false)
+        .await;
+    assert_eq!(bot.outgoing.snapshot().len(), 1);
+    bot.dispatch_command_from_text(1, &test_ctx(0xaaaa0002, 0), "!help", false)
+        .await;
+    assert_eq!(bot.outgoing.snapshot().len(), 2);
+}
+
+#[tokio::test]
+async fn test_trace_command_dms_route_when_answered() {
+    use crate::packet_type::PacketType;
+    let mut config = test_config();
+    config.modules.insert(
+        "traceroute".to_string(),
+        ModuleConfig {
+            enabled: true,
+            scope: "both".to_string(),
+            rate_limit: None,
+            rate_limit_window_secs: None,
+            public_replies: None,
+        },
+    );
+    let registry = crate::modules::build_registry(&config, &Default::default());
+    let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+    let bot = Bot::new(Arc::new(config), Arc::clone(&db), registry);
+    db.upsert_node(0xaaaa0001, "ALC", "Alice", false).unwrap();
+    db.upsert_node(0xaaaa0002, "RLY", "Relay", false).unwrap();
+
+    bot.dispatch_command_from_text(1, &test_ctx(0x22222222, 0), "!trace alice", true)
+        .await;
+    bot.dispatch_command_from_text(1, &test_ctx(0x33333333, 0), "!trace ALC", true)
+        .await;
+    bot.dispatch_command_from_text(1, &test_ctx(0x33333333, 0), "!trace nobody", true)
+        .await;
+    let queue = bot.outgoing.snapshot();
+    assert_eq!(queue.len(), 4, "one traceroute, three replies");
+    assert!(matches!(
+        queue[0].kind,
+        OutgoingKind::Traceroute {
+            target_node: 0xaaaa0001
+        }
+    ));
+    assert_eq!(
+        queue[1].text,
+        "Tracing Alice, you'll get a DM with the route."
+    );
+    assert_eq!(
+        queue[2].text,
+        "Already tracing Alice, you'll get a DM with the route."
+    );
+    assert_eq!(queue[3].text, "Unknown node: nobody");
+
+    // The probe as the send loop records it, then Alice's reply
+    let row = db
+        .log_packet_with_mesh_id(
+            1,
+            Some(0xaaaa0001),
+            0,
+            "",
+            "out",
+            false,
+            None,
+            None,
+            None,
+            None,
+            Some(42),
+            PacketType::Traceroute,
+        )
+        .unwrap();
+    db.log_traceroute_observation(
+        row,
+        "req:00000001:aaaa0001:42",
+        1,
+        Some(0xaaaa0001),
+        false,
+        None,
+        None,
+        None,
+        None,
+        &[],
+        &[],
+    )
+    .unwrap();
+    bot.handle_mesh_packet(
+        1,
+        &PacketBuilder::traceroute_response(0xaaaa0001, 1, 42, &[0xaaaa0002], &[])
+            .id(43)
+            .build(),
+    )
+    .await;
+
+    let queue = bot.outgoing.snapshot();
+    assert_eq!(queue.len(), 6);
+    for (msg, requester) in queue[4..].iter().zip([0x22222222, 0x33333333]) {
+        assert_eq!(msg.to_node, Some(requester));
+        assert_eq!(msg.text, "Route to Alice: via Relay; back: direct");
+    }
+}
+
+#[tokio::test]
+async fn test_battery_alerts_with_hysteresis() {
+    let mut config = test_config();
+    config.bot.admin_nodes = vec!["!0000beef".to_string()];
+    config.battery_alerts.enabled = true;
+    let (bridge_tx, _outgoing_tx, outgoing_rx) = crate::bridge::create_bridge_channels();
+    let mut bridge_rx = bridge_tx.subscribe();
+    let bot = Bot::new(
+        Arc::new(config),
+        Arc::new(Db::open(Path::new(":memory:")).unwrap()),
+        ModuleRegistry::new(),
+    )
+    .with_bridge_channels(bridge_tx, outgoing_rx);
+
+    // low 20%, critical 10%, hysteresis 5%
+    for level in [50, 18, 17, 22, 9, 12, 16, 24, 26, 19, 101] {
+        bot.handle_mesh_packet(1, &PacketBuilder::battery(0xaaaa0001, level).build())
+            .await;
+    }
+    let alerts: Vec<String> = bot
+        .outgoing
+        .snapshot()
+        .into_iter()
+        .map(|m| m.text)
+        .collect();
+    assert_eq!(
+        alerts,
+        vec![
+            "Battery low: !aaaa0001 at 18%",
+            "Battery critical: !aaaa0001 at 9%",
+            "Battery low: !aaaa0001 at 19%",
+        ]
+    );
+    let relayed = bridge_rx.try_recv().unwrap();
+    assert_eq!(relayed.event, BridgeEvent::BatteryLow);
+    assert_eq!(relayed.text, "Battery low: !aaaa0001 at 18%");
+}
+
+#[tokio::test]
+async fn test_environment_alerts_fire_on_crossing_with_cooldown() {
+    let rule = |name: &str, metric: &str, below, above, cooldown_mins| EnvironmentAlertConfig {
+        name: name.to_string(),
+        node: "!aaaa0001".to_string(),
+        metric: metric.to_string(),
+        below,
+        above,
+        notify: vec!["broadcast".to_string(), "admins".to_string()],
+        channel: 0,
+        cooldown_mins,
+    };
+    let mut config = test_config();
+    config.bot.admin_nodes = vec!["!0000beef".to_string()];
+    config.environment_alerts = vec![
+        rule("Freeze", "temperature", Some(0.0), None, 60),
+        rule("Damp", "humidity", None, Some(90.0), 0),
+    ];
+    let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+    let bot = Bot::new(Arc::new(config), Arc::clone(&db), ModuleRegistry::new());
+
+    for (from, temperature, humidity) in [
+        (0xaaaa0001, 2.0, 50.0),
+        (0xaaaa0001, -1.0, 95.0),
+        (0xaaaa0001, -2.0, 96.0),
+        (0xaaaa0002, -5.0, 99.0),
+        (0xaaaa0001, 1.0, 80.0),
+        (0xaaaa0001, -1.5, 92.0),
+    ] {
+        bot.handle_mesh_packet(
+            1,
+            &PacketBuilder::environment(from, temperature, humidity).build(),
+        )
+        .await;
+    }
+
+    let queue = bot.outgoing.snapshot();
+    let broadcasts: Vec<&str> = queue
+        .iter()
+        .filter(|m| m.to_node.is_none())
+        .map(|m| m.text.as_str())
+        .collect();
+    assert_eq!(
+        broadcasts,
+        vec![
+            "Freeze: !aaaa0001 temperature -1.0°C (below 0.0°C)",
+            "Damp: !aaaa0001 humidity 95% (above 90%)",
+            "Damp: !aaaa0001 humidity 92% (above 90%)",
+        ]
+    );
+    assert_eq!(
+        queue.iter().filter(|m| m.to_node == Some(0xbeef)).count(),
+        3
+    );
+    assert_eq!(db.environment_sensors().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_store_forward_router_detection_and_history_merge() {
+    use meshtastic::protobufs::store_and_forward::{Heartbeat, RequestResponse, Variant};
+    use meshtastic::protobufs::StoreAndForward;
+
+    let mut config = test_config();
+    config.store_forward.enabled = true;
+    config.bot.admin_nodes = vec!["!0000beef".to_string()];
+    let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+    let bot = Bot::new(Arc::new(config), Arc::clone(&db), ModuleRegistry::new());
+
+    let admin_dm = MessageContext {
+        is_dm: true,
+        ..test_ctx(0xbeef, 0)
+    };
+    bot.dispatch_command_from_text(1, &admin_dm, "!sf history", true)
+        .await;
+    let heartbeat = StoreAndForward {
+        rr: RequestResponse::RouterHeartbeat as i32,
+        variant: Some(Variant::Heartbeat(Heartbeat {
+            period: 900,
+            secondary: 0,
+        })),
+    };
+    bot.handle_mesh_packet(
+        1,
+        &PacketBuilder::store_forward(0x5f5f0001, &heartbeat)
+            .id(1)
+            .build(),
+    )
+    .await;
+    bot.dispatch_command_from_text(1, &test_ctx(0x1234, 0), "!sf history", false)
+        .await;
+    bot.dispatch_command_from_text(1, &admin_dm, "!sf history 30", true)
+        .await;
+
+    let queue = bot.outgoing.snapshot();
+    let replies: Vec<&str> = queue
+        .iter()
+        .filter(|m| matches!(m.kind, OutgoingKind::Text))
+        .map(|m| m.text.as_str())
+        .collect();
+    assert_eq!(
+        replies,
+        vec![
+            "No Store & Forward routers heard.",
+            "Only admins can request S&F history, by DM.",
+            "Requested the last 30 min of history from !5f5f0001.",
+        ]
+    );
+    match &queue[2].kind {
+        OutgoingKind::StoreForward {
+            target_node,
+            request,
+        } => {
+            assert_eq!(*target_node, 0x5f5f0001);
+            assert_eq!(request.rr(), RequestResponse::ClientHistory);
+            assert!(matches!(
+                request.variant,
+                Some(Variant::History(ref h)) if h.window == 30
+            ));
+        }
+        other => panic!("expected a Store & Forward request, got {:?}", other),
+    }
+
+    let replay = StoreAndForward {
+        rr: RequestResponse::RouterTextBroadcast as i32,
+        variant: Some(Variant::Text(b"missed you".to_vec())),
+    };
+    let packet = PacketBuilder::store_forward(0xaaaa0001, &replay)
+        .id(42)
+        .rx_time(1_700_000_000)
+        .build();
+    bot.handle_mesh_packet(1, &packet).await;
+    bot.handle_mesh_packet(1, &packet).await;
+    assert!(!db
+        .merge_store_forward_text(0xaaaa0001, None, 0, "missed you", 42, 1_700_000_000)
+        .unwrap());
+}
+
+/// Broadcasts the text of every TextMessage event it is dispatched.
+struct TextWatcher;
+
+#[async_trait]
+impl Module for TextWatcher {
+    fn name(&self) -> &str {
+        "text_watcher"
+    }
+
+    fn description(&self) -> &str {
+        "text watcher"
+    }
+
+    fn commands(&self) -> &[&str] {
+        &[]
+    }
+
+    fn scope(&self) -> crate::message::CommandScope {
+        crate::message::CommandScope::Both
+    }
+
+    async fn handle_command(
+        &self,
+        _command: &str,
+        _args: &str,
+        _ctx: &MessageContext,
+        _db: &Arc<Db>,
+    ) -> Result<Option<Vec<Response>>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(None)
+    }
+
+    async fn handle_event(
+        &self,
+        event: &crate::message::MeshEvent,
+        _db: &Arc<Db>,
+    ) -> Result<Option<Vec<Response>>, Box<dyn std::error::Error + Send + Sync>> {
+        let crate::message::MeshEvent::TextMessage { text, .. } = event else {
+            return Ok(None);
+        };
+        Ok(Some(vec![Response {
+            text: format!("module saw: {:?}", text),
+            destination: Destination::Broadcast,
+            channel: 0,
+            reply_id: None,
+            data: None,
+        }]))
+    }
+}
+
+#[tokio::test]
+async fn test_third_party_dm_text_omitted_on_opted_out_channel() {
+    let mut config = test_config();
+    config.privacy.omit_dm_text_channels = vec![1];
+    let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+    let mut registry = ModuleRegistry::new();
+    registry.register(Box::new(TextWatcher));
+    let bot = Bot::new(Arc::new(config), Arc::clone(&db), registry);
+
+    bot.handle_mesh_packet(
+        1,
+        &PacketBuilder::text(0xaaaa0001, "secret")
+            .to(0xbbbb0002)
+            .channel(1)
+            .build(),
+    )
+    .await;
+    assert_eq!(db.last_packet_text().as_deref(), Some(""));
+    assert_eq!(db.message_count("in").unwrap(), 1);
+    // Modules still get the event, without the text
+    let seen: Vec<String> = bot
+        .outgoing
+        .snapshot()
+        .into_iter()
+        .map(|m| m.text)
+        .collect();
+    assert_eq!(seen, vec!["module saw: \"\"".to_string()]);
+
+    // Other channels and public messages keep their text
+    bot.handle_mesh_packet(
+        1,
+        &PacketBuilder::text(0xaaaa0001, "hello")
+            .to(0xbbbb0002)
+            .build(),
+    )
+    .await;
+    assert_eq!(db.last_packet_text().as_deref(), Some("hello"));
+    bot.handle_mesh_packet(
+        1,
+        &PacketBuilder::text(0xaaaa0001, "public")
+            .to(0)
+            .channel(1)
+            .build(),
+    )
+    .await;
+    assert_eq!(db.last_packet_text().as_deref(), Some("public"));
+}
+
+#[tokio::test]
+async fn test_packets_tagged_and_replies_routed_by_radio() {
+    let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+    let bot = Bot::new(
+        Arc::new(test_config()),
+        Arc::clone(&db),
+        ModuleRegistry::new(),
+    );
+    bot.radios.write().unwrap().insert(1, "north".to_string());
+    bot.radios.write().unwrap().insert(2, "south".to_string());
+
+    bot.handle_mesh_packet(2, &PacketBuilder::text(0xaaaa0001, "hi").build())
+        .await;
+    assert_eq!(db.last_packet_radio().as_deref(), Some("south"));
+    bot.handle_mesh_packet(1, &PacketBuilder::text(0xaaaa0001, "hi").build())
+        .await;
+    assert_eq!(db.last_packet_radio().as_deref(), Some("north"));
+
+    // Each radio sends its own replies; the primary also covers radios that
+    // are not connected.
+    assert!(bot.sends_for(2, false, 2));
+    assert!(!bot.sends_for(2, false, 1));
+    assert!(!bot.sends_for(1, true, 2));
+    assert!(bot.sends_for(1, true, 3));
+    assert!(!bot.sends_for(2, false, 3));
+}
+
+#[tokio::test]
+async fn test_new_node_is_relayed_to_bridges_once() {
+    let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+    let (bridge_tx, _outgoing_tx, outgoing_rx) = crate::bridge::create_bridge_channels();
+    let mut bridge_rx = bridge_tx.subscribe();
+    let bot = Bot::new(
+        Arc::new(test_config()),
+        Arc::clone(&db),
+        ModuleRegistry::new(),
+    )
+    .with_bridge_channels(bridge_tx, outgoing_rx);
+    let node_info = NodeInfoBuilder::new(0xaaaa0003)
+        .long_name("Ridge Relay")
+        .short_name("RDGE")
+        .build();
+
+    bot.handle_node_info(1, &node_info, true).await;
+    let joined = bridge_rx.try_recv().unwrap();
+    assert_eq!(joined.event, BridgeEvent::NodeJoined);
+    assert_eq!(joined.text, "New node joined: Ridge Relay");
+    assert_eq!(
+        joined.data.unwrap().render_compact(),
+        "Node: !aaaa0003 Short name: RDGE Via: RF"
+    );
+
+    bot.handle_node_info(1, &node_info, true).await;
+    assert!(bridge_rx.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn test_nodedb_import_keeps_radio_first_seen() {
+    let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+    let bot = Bot::new(
+        Arc::new(test_config()),
+        Arc::clone(&db),
+        ModuleRegistry::new(),
+    );
+    let now = chrono::Utc::now().timestamp();
+    let node_info =
+        |num: u32, last_heard: u32| NodeInfoBuilder::new(num).last_heard(last_heard).build();
+
+    // Deferred through the startup grace period like a real NodeDB dump
+    bot.startup_state.mark_connected_and_reset(bot.now());
+    bot.handle_node_info(1, &node_info(0xaaaa0001, (now - 86400) as u32), true)
+        .await;
+    // No clock on the radio: last_heard counts seconds since boot
+    bot.handle_node_info(1, &node_info(0xaaaa0002, 5000), true)
+        .await;
+    // Announcing itself over MQTT rather than from the NodeDB
+    bot.handle_node_info(1, &node_info(0xaaaa0003, 0), false)
+        .await;
+    bot.dispatch_deferred_events(1).await;
+
+    let nodes = db.get_all_nodes().unwrap();
+    let node = |id: u32| nodes.iter().find(|n| n.node_id == id).unwrap();
+    assert_eq!(node(0xaaaa0001).first_seen, now - 86400);
+    assert_eq!(node(0xaaaa0001).source, "nodedb");
+    assert!(node(0xaaaa0002).first_seen >= now);
+    assert_eq!(node(0xaaaa0002).source, "nodedb");
+    assert!(node(0xaaaa0003).first_seen >= now);
+    assert_eq!(node(0xaaaa0003).source, "observed");
+}
+
+#[tokio::test]
+async fn test_own_transmissions_are_not_relayed_to_bridges() {
+    let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+    let (bridge_tx, _outgoing_tx, outgoing_rx) = crate::bridge::create_bridge_channels();
+    let mut bridge_rx = bridge_tx.subscribe();
+    let bot = Bot::new(Arc::new(test_config()), db, ModuleRegistry::new())
+        .with_bridge_channels(bridge_tx, outgoing_rx);
+
+    // A bridged message we sent from another radio, in a custom format
+    bot.sent_packets.lock().unwrap().insert(2, 7);
+    bot.handle_mesh_packet(1, &PacketBuilder::text(2, "alice@tg: hi").id(7).build())
+        .await;
+    assert!(bridge_rx.try_recv().is_err());
+
+    // Someone else's message is relayed even if it looks bridged
+    bot.handle_mesh_packet(1, &PacketBuilder::text(3, "[TG:bob] hi").build())
+        .await;
+    assert_eq!(bridge_rx.try_recv().unwrap().text, "[TG:bob] hi");
+}
+
+#[tokio::test]
+async fn test_emergency_keyword_alerts_bridges_and_acks_first() {
+    let mut config = test_config();
+    config.emergency.keywords = vec!["SOS".to_string(), "need help".to_string()];
+    let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+    db.upsert_node(0xaaaa0001, "HIKR", "Hiker", false).unwrap();
+    db.update_position(0xaaaa0001, 25.03, 121.56).unwrap();
+    let (bridge_tx, _outgoing_tx, outgoing_rx) = crate::bridge::create_bridge_channels();
+    let mut bridge_rx = bridge_tx.subscribe();
+    let bot = Bot::new(Arc::new(config), Arc::clone(&db), ModuleRegistry::new())
+        .with_bridge_channels(bridge_tx, outgoing_rx);
+
+    // Words that merely contain a keyword do not trigger
+    bot.handle_mesh_packet(
+        1,
+        &PacketBuilder::text(0xaaaa0001, "sossage party")
+            .to(0)
+            .channel(3)
+            .build(),
+    )
+    .await;
+    let relayed = bridge_rx.try_recv().unwrap();
+    assert_eq!(relayed.event, BridgeEvent::Message);
+    assert!(db.recent_incidents(10).unwrap().is_empty());
+
+    bot.queue_responses(
+        &test_ctx(0xaaaa0002, 0),
+        &[Response {
+            text: "queued first".to_string(),
+            destination: Destination::Broadcast,
+            channel: 0,
+            reply_id: None,
+            data: None,
+        }],
+        1,
+    );
+
+    // A DM to the bot on another channel still reaches the bridges
+    let sos = PacketBuilder::text(0xaaaa0001, "SOS! fell off the trail")
+        .to(1)
+        .channel(3)
+        .build();
+    bot.handle_mesh_packet(1, &sos).await;
+    let alert = bridge_rx.try_recv().unwrap();
+    assert_eq!(alert.event, BridgeEvent::Emergency);
+    assert!(alert
+        .text
+        .starts_with("EMERGENCY (SOS): SOS! fell off the trail"));
+    assert!(alert
+        .data
+        .as_ref()
+        .unwrap()
+        .render_compact()
+        .starts_with("Position: 25.03000, 121.56000 Map: https://"));
+
+    let queue = bot.outgoing.snapshot();
+    assert_eq!(queue[0].text, "Emergency alert received and relayed.");
+    assert_eq!(queue[0].to_node, Some(0xaaaa0001));
+    assert_eq!(queue[1].text, "queued first");
+
+    // The same packet heard again is not escalated twice
+    bot.handle_mesh_packet(1, &sos).await;
+    assert!(bridge_rx.try_recv().is_err());
+    assert_eq!(db.recent_incidents(10).unwrap().len(), 1);
+}
+
+fn bridge_rule(pattern: &str, action: &str, replacement: &str) -> BridgeRuleConfig {
+    BridgeRuleConfig {
+        pattern: pattern.to_string(),
+        action: action.to_string(),
+        replacement: replacement.to_string(),
+    }
+}
+
+#[tokio::test]
+async fn test_emergency_alert_goes_through_bridge_rules() {
+    let mut config = test_config();
+    config.emergency.keywords = vec!["SOS".to_string()];
+    let filter = crate::bridge_filter::BridgeFilter::from_config(&[
+        bridge_rule(r"\+?\d[\d -]{6,}\d", "redact", "[phone]"),
+        bridge_rule("(?i)password", "block", ""),
+    ])
+    .unwrap();
+    let (bridge_tx, _outgoing_tx, outgoing_rx) = crate::bridge::create_bridge_channels();
+    let mut bridge_rx = bridge_tx.subscribe();
+    let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+    let bot = Bot::new(Arc::new(config), db, ModuleRegistry::new())
+        .with_bridge_channels(bridge_tx, outgoing_rx)
+        .with_bridge_filter(Arc::new(filter));
+
+    bot.handle_mesh_packet(
+        1,
+        &PacketBuilder::text(0xaaaa0001, "SOS call me on +1 555 123 4567")
+            .to(1)
+            .build(),
+    )
+    .await;
+    let alert = bridge_rx.try_recv().unwrap();
+    assert_eq!(alert.event, BridgeEvent::Emergency);
+    assert_eq!(alert.text, "EMERGENCY (SOS): SOS call me on [phone]");
+
+    // A block rule withholds the text, not the alert
+    bot.handle_mesh_packet(
+        1,
+        &PacketBuilder::text(0xaaaa0002, "SOS my password is hunter2")
+            .to(1)
+            .build(),
+    )
+    .await;
+    let alert = bridge_rx.try_recv().unwrap();
+    assert_eq!(alert.event, BridgeEvent::Emergency);
+    assert!(!alert.text.contains("hunter2"));
+    assert!(alert.data.is_some());
+}
+
+struct TestLimitedModule;
+
+#[async_trait]
+impl Module for TestLimitedModule {
+    fn name(&self) -> &str {
+        "test_limited"
+    }
+
+    fn description(&self) -> &str {
+        "module declaring its own rate limit"
+    }
+
+    fn commands(&self) -> &[&str] {
+        &["costly"]
+    }
+
+    fn scope(&self) -> crate::message::CommandScope {
+        crate::message::CommandScope::Both
+    }
+
+    fn rate_limit(&self) -> Option<crate::module::RateLimit> {
+        Some(crate::module::RateLimit {
+            max_commands: 1,
+            window_secs: 3600,
+        })
+    }
+
+    async fn handle_command(
+        &self,
+        _command: &str,
+        _args: &str,
+        _ctx: &MessageContext,
+        _db: &Arc<Db>,
+    ) -> Result<Option<Vec<Response>>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(Some(vec![Response {
+            text: "costly".to_string(),
+            destination: Destination::Sender,
+            channel: 0,
+            reply_id: None,
+            data: None,
+        }]))
+    }
+}
+
+#[tokio::test]
+async fn test_per_module_rate_limits() {
+    let mut config = test_config();
+    config.bot.rate_limit_commands = 2;
+    config.modules.insert(
+        "test_cmd".to_string(),
+        ModuleConfig {
+            enabled: true,
+            scope: "both".to_string(),
+            rate_limit: Some(0),
+            rate_limit_window_secs: None,
+            public_replies: None,
+        },
+    );
+    let mut registry = ModuleRegistry::new();
+    registry.register(Box::new(TestCommandModule));
+    registry.register(Box::new(TestLimitedModule));
+    let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+    let bot = Bot::new(Arc::new(config), db, registry);
+    let ctx = test_ctx(0x11111111, 0);
+
+    // Configured as unlimited, despite the global limit of 2
+    for _ in 0..4 {
+        bot.dispatch_command_from_text(1, &ctx, "!echo hi", false)
+            .await;
+    }
+    assert_eq!(bot.outgoing.snapshot().len(), 4);
+
+    // Module-declared limit of 1 per hour, tracked separately
+    bot.dispatch_command_from_text(1, &ctx, "!costly", false)
+        .await;
+    bot.dispatch_command_from_text(1, &ctx, "!costly", false)
+        .await;
+    let costly = bot
+        .outgoing
+        .snapshot()
+        .iter()
+        .filter(|m| m.text == "costly")
+        .count();
+    assert_eq!(costly, 1);
+}
+
+#[tokio::test]
+async fn test_dm_passthrough_and_operator_reply() {
+    let mut config = test_config();
+    config.bridge.telegram = Some(TelegramConfig {
+        enabled: true,
+        bot_token: String::new(),
+        chat_id: -100,
+        mesh_channel: 0,
+        direction: "both".to_string(),
+        format: String::new(),
+        mappings: Vec::new(),
+        dm_chat_id: -200,
+    });
+    let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+    db.upsert_node(0xaaaa0001, "HIKR", "Hiker", false).unwrap();
+    let (bridge_tx, _outgoing_tx, outgoing_rx) = crate::bridge::create_bridge_channels();
+    let mut bridge_rx = bridge_tx.subscribe();
+    let bot = Bot::new(Arc::new(config), Arc::clone(&db), ModuleRegistry::new())
+        .with_bridge_channels(bridge_tx, outgoing_rx);
+
+    bot.handle_mesh_packet(
+        1,
+        &PacketBuilder::text(0xaaaa0001, "anyone there?")
+            .to(1)
+            .channel(2)
+            .build(),
+    )
+    .await;
+    let relayed = bridge_rx.try_recv().unwrap();
+    assert_eq!(relayed.event, BridgeEvent::DirectMessage { session: 1 });
+    assert_eq!(
+        relayed.dm_text().unwrap(),
+        "#1 Hiker (!aaaa0001): anyone there?"
+    );
+    // Commands are answered by the bot, not passed through
+    bot.handle_mesh_packet(
+        1,
+        &PacketBuilder::text(0xaaaa0001, "!nope")
+            .to(1)
+            .channel(2)
+            .build(),
+    )
+    .await;
+    assert!(bridge_rx.try_recv().is_err());
+    // Later DMs from the same node stay in the same conversation
+    bot.handle_mesh_packet(
+        1,
+        &PacketBuilder::text(0xaaaa0001, "hello?")
+            .to(1)
+            .channel(2)
+            .build(),
+    )
+    .await;
+    let relayed = bridge_rx.try_recv().unwrap();
+    assert_eq!(relayed.event, BridgeEvent::DirectMessage { session: 1 });
+
+    let reply = |session: u32| OutgoingBridgeMessage {
+        text: "[TG:op] yes, go ahead".to_string(),
+        channel: 0,
+        source: "telegram".to_string(),
+        sender: String::new(),
+        dm_session: Some(session),
+        to_node: None,
+        received_at: Instant::now(),
+    };
+    bot.handle_bridge_message(1, reply(1)).await;
+    let queue = bot.outgoing.snapshot();
+    assert_eq!(queue.len(), 1);
+    assert!(matches!(queue[0].destination, PacketDestination::Node(_)));
+    assert_eq!(queue[0].to_node, Some(0xaaaa0001));
+    assert_eq!(queue[0].mesh_channel, 2);
+
+    // Unknown conversations are reported back instead of sent
+    bot.handle_bridge_message(1, reply(7)).await;
+    assert_eq!(bot.outgoing.snapshot().len(), 1);
+    let notice = bridge_rx.try_recv().unwrap();
+    assert_eq!(notice.event, BridgeEvent::DirectMessage { session: 7 });
+}
+
+#[tokio::test]
+async fn test_dm_passthrough_goes_through_bridge_rules() {
+    let mut config = test_config();
+    config.bridge.telegram = Some(TelegramConfig {
+        enabled: true,
+        bot_token: String::new(),
+        chat_id: -100,
+        mesh_channel: 0,
+        direction: "both".to_string(),
+        format: String::new(),
+        mappings: Vec::new(),
+        dm_chat_id: -200,
+    });
+    let filter = crate::bridge_filter::BridgeFilter::from_config(&[
+        bridge_rule(r"\+?\d[\d -]{6,}\d", "redact", "[phone]"),
+        bridge_rule("(?i)password", "block", ""),
+    ])
+    .unwrap();
+    let (bridge_tx, _outgoing_tx, outgoing_rx) = crate::bridge::create_bridge_channels();
+    let mut bridge_rx = bridge_tx.subscribe();
+    let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+    let bot = Bot::new(Arc::new(config), db, ModuleRegistry::new())
+        .with_bridge_channels(bridge_tx, outgoing_rx)
+        .with_bridge_filter(Arc::new(filter));
+
+    let dm = |text: &str| PacketBuilder::text(0xaaaa0001, text).to(1).build();
+    bot.handle_mesh_packet(1, &dm("call me on +1 555 123 4567"))
+        .await;
+    let relayed = bridge_rx.try_recv().unwrap();
+    assert_eq!(relayed.event, BridgeEvent::DirectMessage { session: 1 });
+    assert_eq!(relayed.text, "call me on [phone]");
+
+    bot.handle_mesh_packet(1, &dm("the password is hunter2"))
+        .await;
+    assert!(bridge_rx.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn test_admin_commands_only_from_admins_by_dm() {
+    let mut config = test_config();
+    config.bot.admin_nodes = vec!["!0000beef".to_string()];
+    config.modules.insert(
+        "admin".to_string(),
+        ModuleConfig {
+            enabled: true,
+            scope: "dm".to_string(),
+            rate_limit: None,
+            rate_limit_window_secs: None,
+            public_replies: None,
+        },
+    );
+    let registry = crate::modules::build_registry(&config, &Default::default());
+    let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+    let bot = Bot::new(Arc::new(config), Arc::clone(&db), registry);
+    let admin = test_ctx(0x0000beef, 0);
+
+    // Not an admin, or not by DM: ignored
+    bot.dispatch_command_from_text(1, &test_ctx(0x22222222, 0), "!admin queue", true)
+        .await;
+    bot.dispatch_command_from_text(1, &admin, "!admin queue", false)
+        .await;
+    assert!(bot.outgoing.snapshot().is_empty());
+
+    bot.dispatch_command_from_text(1, &admin, "!admin broadcast 2 Net in 10 min", true)
+        .await;
+    let queue = bot.outgoing.snapshot();
+    assert_eq!(queue.len(), 2);
+    assert_eq!(queue[0].text, "Net in 10 min");
+    assert!(matches!(queue[0].destination, PacketDestination::Broadcast));
+    assert_eq!(queue[0].mesh_channel, 2);
+    assert_eq!(queue[1].text, "Broadcast queued on channel 2");
+    assert_eq!(queue[1].to_node, Some(0x0000beef));
+
+    bot.dispatch_command_from_text(1, &admin, "!admin queue", true)
+        .await;
+    assert_eq!(
+        bot.outgoing.snapshot()[2].text,
+        "Outgoing queue: 2 message(s)"
+    );
+
+    // Without a config path there is nothing to reload
+    bot.dispatch_command_from_text(1, &admin, "!admin reload", true)
+        .await;
+    assert_eq!(bot.outgoing.snapshot()[3].text, "No config file to reload.");
+
+    // Everything else goes to the admin module
+    db.upsert_node(0x33333333, "TGT", "Target", false).unwrap();
+    bot.dispatch_command_from_text(1, &admin, "!admin purge TGT", true)
+        .await;
+    assert_eq!(
+        bot.outgoing.snapshot()[4].text,
+        "Purged !33333333: 1 nodes."
+    );
+}
+
+#[tokio::test]
+async fn test_admin_reload_swaps_modules_and_admins() {
+    let mut config = test_config();
+    config.bot.admin_nodes = vec!["!0000beef".to_string()];
+    config.battery_alerts.enabled = true;
+    config.modules.insert(
+        "admin".to_string(),
+        ModuleConfig {
+            enabled: true,
+            scope: "dm".to_string(),
+            rate_limit: None,
+            rate_limit_window_secs: None,
+            public_replies: None,
+        },
+    );
+    let path = std::env::temp_dir().join(format!("meshenger-reload-{}.toml", std::process::id()));
+    std::fs::write(
+        &path,
+        r#"
+        [connection]
+        address = "10.0.0.1:4403"
+
+        [bot]
+        name = "Meshenger"
+        admin_nodes = ["!0000cafe"]
+
+        [welcome]
+        enabled = false
+        message = "Welcome!"
+        welcome_back_message = "Welcome back!"
+        absence_threshold_hours = 48
+
+        [weather]
+        latitude = 0.0
+        longitude = 0.0
+        units = "metric"
+
+        [modules.admin]
+        enabled = true
+        scope = "dm"
+
+        [modules.ping]
+        enabled = true
+        scope = "both"
+        "#,
+    )
+    .unwrap();
+    let registry = crate::modules::build_registry(&config, &Default::default());
+    let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+    db.set_setting("welcome.message", Some("\"Hi {name}\""))
+        .unwrap();
+    let bot = Bot::new(Arc::new(config), db, registry).with_config_path(path.clone());
+    assert!(bot.registry().find_by_command("ping").is_none());
+
+    bot.dispatch_command_from_text(1, &test_ctx(0x0000beef, 0), "!admin reload", true)
+        .await;
+    assert_eq!(
+        bot.outgoing.snapshot()[0].text,
+        "Reloaded modules and admin_nodes. Other changes need a restart."
+    );
+    assert!(bot.registry().find_by_command("ping").is_some());
+    assert!(!bot.is_admin(0x0000beef));
+    assert!(bot.is_admin(0x0000cafe));
+    // The dashboard sees the stored welcome text as applied
+    assert_eq!(
+        bot.modules_config().read().unwrap().welcome.message,
+        "Hi {name}"
+    );
+
+    // Alert DMs go to the reloaded admins
+    bot.handle_mesh_packet(1, &PacketBuilder::battery(0xaaaa0001, 9).build())
+        .await;
+    let alert = bot.outgoing.snapshot().pop().unwrap();
+    assert_eq!(alert.text, "Battery critical: !aaaa0001 at 9%");
+    assert_eq!(alert.to_node, Some(0x0000cafe));
+
+    // A broken file leaves the running modules alone
+    std::fs::write(&path, "[bot").unwrap();
+    bot.dispatch_command_from_text(1, &test_ctx(0x0000cafe, 0), "!admin reload", true)
+        .await;
+    assert!(bot.outgoing.snapshot()[2]
+        .text
+        .starts_with("Config error, not reloading"));
+    assert!(bot.registry().find_by_command("ping").is_some());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn test_admin_node_actions_wait_for_session_passkey() {
+    use meshtastic::protobufs::{admin_message::PayloadVariant, AdminMessage};
+
+    let mut config = test_config();
+    config.bot.admin_nodes = vec!["!0000beef".to_string()];
+    config.remote_admin.nodes = vec!["!0000cafe".to_string()];
+    config.modules.insert(
+        "admin".to_string(),
+        ModuleConfig {
+            enabled: true,
+            scope: "dm".to_string(),
+            rate_limit: None,
+            rate_limit_window_secs: None,
+            public_replies: None,
+        },
+    );
+    let registry = crate::modules::build_registry(&config, &Default::default());
+    let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+    db.upsert_node(0x0000cafe, "HILL", "Hilltop", false)
+        .unwrap();
+    let bot = Bot::new(Arc::new(config), Arc::clone(&db), registry);
+    let admin = test_ctx(0x0000beef, 0);
+    let admin_request = |msg: &OutgoingMeshMessage| match &msg.kind {
+        OutgoingKind::NodeAdmin {
+            target_node,
+            request,
+        } => Some((*target_node, request.clone())),
+        _ => None,
+    };
+
+    // Only nodes in [remote_admin] nodes
+    bot.dispatch_command_from_text(1, &admin, "!admin node reboot !00001234", true)
+        .await;
+    assert_eq!(
+        bot.outgoing.snapshot()[0].text,
+        "!00001234 is not in [remote_admin] nodes."
+    );
+
+    // The first action asks for a passkey and waits for it
+    bot.dispatch_command_from_text(1, &admin, "!admin node reboot HILL", true)
+        .await;
+    let queue = bot.outgoing.snapshot();
+    let (target, request) = admin_request(&queue[1]).unwrap();
+    assert_eq!(target, 0x0000cafe);
+    assert!(request.session_passkey.is_empty());
+    assert_eq!(
+        request.payload_variant,
+        Some(PayloadVariant::GetDeviceMetadataRequest(true))
+    );
+    assert_eq!(
+        queue[2].text,
+        "Asking Hilltop for an admin session, you'll get a DM."
+    );
+
+    // Replies from anyone else are ignored
+    let reply = AdminMessage {
+        session_passkey: vec![1, 2, 3, 4],
+        payload_variant: Some(PayloadVariant::GetDeviceMetadataResponse(Default::default())),
+    };
+    bot.handle_mesh_packet(1, &PacketBuilder::admin(0x00001234, &reply).to(1).build())
+        .await;
+    assert_eq!(bot.outgoing.snapshot().len(), 3);
+
+    bot.handle_mesh_packet(1, &PacketBuilder::admin(0x0000cafe, &reply).to(1).build())
+        .await;
+    let queue = bot.outgoing.snapshot();
+    let (_, request) = admin_request(&queue[3]).unwrap();
+    assert_eq!(request.session_passkey, vec![1, 2, 3, 4]);
+    assert_eq!(
+        request.payload_variant,
+        Some(PayloadVariant::RebootSeconds(5))
+    );
+    assert_eq!(queue[4].text, "Sent reboot to Hilltop.");
+    assert_eq!(queue[4].to_node, Some(0x0000beef));
+
+    // While the session lasts, actions go out at once
+    bot.dispatch_command_from_text(
+        1,
+        &admin,
+        "!admin node set-owner !0000cafe HTOP Hilltop Relay",
+        true,
+    )
+    .await;
+    let queue = bot.outgoing.snapshot();
+    let (_, request) = admin_request(&queue[5]).unwrap();
+    assert_eq!(request.session_passkey, vec![1, 2, 3, 4]);
+    let Some(PayloadVariant::SetOwner(user)) = request.payload_variant else {
+        panic!("expected SetOwner");
+    };
+    assert_eq!(user.short_name, "HTOP");
+    assert_eq!(user.long_name, "Hilltop Relay");
+    assert_eq!(queue[6].text, "Sent owner change to Hilltop.");
+}
+
+#[tokio::test]
+async fn test_admin_db_vacuum_reports_when_done() {
+    let mut config = test_config();
+    config.bot.admin_nodes = vec!["!0000beef".to_string()];
+    config.modules.insert(
+        "admin".to_string(),
+        ModuleConfig {
+            enabled: true,
+            scope: "dm".to_string(),
+            rate_limit: None,
+            rate_limit_window_secs: None,
+            public_replies: None,
+        },
+    );
+    let registry = crate::modules::build_registry(&config, &Default::default());
+    let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+    let bot = Bot::new(Arc::new(config), Arc::clone(&db), registry);
+    let admin = test_ctx(0x0000beef, 0);
+
+    bot.dispatch_command_from_text(1, &admin, "!admin db vacuum", true)
+        .await;
+    bot.dispatch_command_from_text(1, &admin, "!admin db vacuum", true)
+        .await;
+    let queue = bot.outgoing.snapshot();
+    assert_eq!(
+        queue[0].text,
+        "Database maintenance started, you'll get a DM when it's done."
+    );
+    assert_eq!(queue[1].text, "Database maintenance already running.");
+
+    while bot.db_maintenance_running() {
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        bot.finish_db_maintenance(1).await;
+    }
+    let queue = bot.outgoing.snapshot();
+    assert_eq!(queue.len(), 3);
+    assert!(queue[2].text.starts_with("Database maintenance done: "));
+    assert_eq!(queue[2].to_node, Some(0x0000beef));
+}
+
+#[tokio::test]
+async fn test_admin_commands_need_stored_public_key() {
+    let mut config = test_config();
+    config.bot.admin_nodes = vec!["!0000beef".to_string()];
+    config.modules.insert(
+        "admin".to_string(),
+        ModuleConfig {
+            enabled: true,
+            scope: "dm".to_string(),
+            rate_limit: None,
+            rate_limit_window_secs: None,
+            public_replies: None,
+        },
+    );
+    let registry = crate::modules::build_registry(&config, &Default::default());
+    let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+    let bot = Bot::new(Arc::new(config), Arc::clone(&db), registry);
+    let node_info = |key: u8| {
+        NodeInfoBuilder::new(0x0000beef)
+            .long_name("Admin")
+            .public_key(vec![key; 32])
+            .build()
+    };
+
+    // No key on file yet: DM-only is all that can be checked
+    bot.handle_mesh_packet(
+        1,
+        &PacketBuilder::text(0x0000beef, "!admin queue")
+            .to(1)
+            .build(),
+    )
+    .await;
+    assert_eq!(bot.outgoing.snapshot().len(), 1);
+
+    // The first announced key sticks; a later different one is ignored
+    bot.handle_node_info(1, &node_info(0xAA), true).await;
+    bot.handle_node_info(1, &node_info(0xBB), true).await;
+    assert_eq!(db.get_public_key(0x0000beef).unwrap(), Some(vec![0xAA; 32]));
+
+    let mut packet = PacketBuilder::text(0x0000beef, "!admin queue")
+        .to(1)
+        .build();
+    bot.handle_mesh_packet(1, &packet).await;
+    packet.pki_encrypted = true;
+    packet.public_key = vec![0xBB; 32];
+    bot.handle_mesh_packet(1, &packet).await;
+    assert_eq!(bot.outgoing.snapshot().len(), 1);
+
+    packet.public_key = vec![0xAA; 32];
+    bot.handle_mesh_packet(1, &packet).await;
+    assert_eq!(bot.outgoing.snapshot().len(), 2);
+}
+
+#[tokio::test]
+async fn test_spoofed_poll_start_from_admin_id_refused() {
+    let mut config = test_config();
+    config.bot.admin_nodes = vec!["!0000beef".to_string()];
+    config.modules.insert(
+        "poll".to_string(),
+        ModuleConfig {
+            enabled: true,
+            scope: "both".to_string(),
+            rate_limit: None,
+            rate_limit_window_secs: None,
+            public_replies: None,
+        },
+    );
+    let registry = crate::modules::build_registry(&config, &Default::default());
+    let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+    let bot = Bot::new(Arc::new(config), Arc::clone(&db), registry);
+    let node_info = NodeInfoBuilder::new(0x0000beef)
+        .long_name("Admin")
+        .public_key(vec![0xAA; 32])
+        .build();
+    bot.handle_node_info(1, &node_info, true).await;
+
+    // A plain DM claiming the admin's ID is refused
+    let mut packet = PacketBuilder::text(0x0000beef, "!poll start \"Q?\" A B")
+        .to(1)
+        .build();
+    bot.handle_mesh_packet(1, &packet).await;
+    assert!(db.get_open_poll().unwrap().is_none());
+    let queue = bot.outgoing.snapshot();
+    assert_eq!(queue.len(), 1);
+    assert_eq!(queue[0].text, "Only admins can do that, by DM.");
+
+    // Encrypted with the stored key, it goes through
+    packet.pki_encrypted = true;
+    packet.public_key = vec![0xAA; 32];
+    packet.id += 1;
+    bot.handle_mesh_packet(1, &packet).await;
+    assert!(db.get_open_poll().unwrap().is_some());
+}
+
+#[tokio::test]
+async fn test_spoofed_sf_history_from_admin_id_refused() {
+    let mut config = test_config();
+    config.store_forward.enabled = true;
+    config.bot.admin_nodes = vec!["!0000beef".to_string()];
+    let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+    let bot = Bot::new(Arc::new(config), Arc::clone(&db), ModuleRegistry::new());
+    db.record_store_forward_router(0x5f5f0001, None).unwrap();
+    let node_info = NodeInfoBuilder::new(0x0000beef)
+        .long_name("Admin")
+        .public_key(vec![0xAA; 32])
+        .build();
+    bot.handle_node_info(1, &node_info, true).await;
+
+    // On a channel, or by DM without the admin's key: refused
+    bot.handle_mesh_packet(1, &PacketBuilder::text(0x0000beef, "!sf history").build())
+        .await;
+    let mut packet = PacketBuilder::text(0x0000beef, "!sf history")
+        .to(1)
+        .id(2)
+        .build();
+    bot.handle_mesh_packet(1, &packet).await;
+    let queue = bot.outgoing.snapshot();
+    assert_eq!(queue.len(), 2);
+    assert!(queue
+        .iter()
+        .all(|m| m.text == "Only admins can request S&F history, by DM."));
+
+    // Encrypted with the stored key, the request goes out
+    packet.pki_encrypted = true;
+    packet.public_key = vec![0xAA; 32];
+    packet.id += 1;
+    bot.handle_mesh_packet(1, &packet).await;
+    assert!(bot
+        .outgoing
+        .snapshot()
+        .iter()
+        .any(|m| matches!(m.kind, OutgoingKind::StoreForward { .. })));
+}
+
+#[tokio::test]
+async fn test_event_hooks_queue_overflow_and_offline_nodes() {
+    use crate::event_hooks::{EventHooks, HookEvent};
+
+    let mut config = test_config();
+    config.event_hooks.queue_overflow_depth = 2;
+    config.freshness.stale_mins = 60;
+    let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+    let (hooks, mut events) = EventHooks::capture();
+    let bot =
+        Bot::new(Arc::new(config), Arc::clone(&db), ModuleRegistry::new()).with_event_hooks(hooks);
+
+    let ctx = test_ctx(0x22222222, 0);
+    let reply = |text: &str| Response {
+        text: text.to_string(),
+        destination: Destination::Sender,
+        channel: 0,
+        reply_id: None,
+        data: None,
+    };
+    bot.queue_responses(&ctx, &[reply("a"), reply("b"), reply("c")], 1);
+    assert_eq!(
+        events.try_recv().unwrap(),
+        HookEvent::QueueOverflow { depth: 2 }
+    );
+    assert!(events.try_recv().is_err(), "sent once per crossing");
+
+    // Heard 61 minutes ago: went offline since the last check
+    db.upsert_node(0x33333333, "TGT", "Target", false).unwrap();
+    let now = chrono::Utc::now().timestamp();
+    *bot.offline_checked_at.lock().unwrap() = now - 120;
+    bot.check_nodes_offline(now + 61 * 60).await;
+    assert!(matches!(
+        events.try_recv().unwrap(),
+        HookEvent::NodeOffline { name, .. } if name == "Target"
+    ));
+    bot.check_nodes_offline(now + 62 * 60).await;
+    assert!(events.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn test_node_events_are_batched_to_bridges() {
+    let mut config = test_config();
+    config.bridge.node_events.joined = true;
+    config.bridge.node_events.offline_hours = 24;
+    let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+    let (bridge_tx, _outgoing_tx, outgoing_rx) = crate::bridge::create_bridge_channels();
+    let mut bridge_rx = bridge_tx.subscribe();
+    let bot = Bot::new(Arc::new(config), Arc::clone(&db), ModuleRegistry::new())
+        .with_bridge_channels(bridge_tx, outgoing_rx);
+    let node_info = |num: u32, name: &str| NodeInfoBuilder::new(num).long_name(name).build();
+
+    bot.handle_node_info(1, &node_info(0xaaaa0001, "Ridge"), true)
+        .await;
+    bot.handle_node_info(1, &node_info(0xaaaa0002, "Valley"), true)
+        .await;
+    // Only the per-node NodeJoined events so far
+    assert_eq!(bridge_rx.try_recv().unwrap().event, BridgeEvent::NodeJoined);
+    assert_eq!(bridge_rx.try_recv().unwrap().event, BridgeEvent::NodeJoined);
+    assert!(bridge_rx.try_recv().is_err());
+
+    let now = chrono::Utc::now().timestamp();
+    bot.post_node_activity(1, now).await;
+    let post = bridge_rx.try_recv().unwrap();
+    assert_eq!(post.event, BridgeEvent::NodeActivity);
+    assert_eq!(post.text, "Joined the mesh: Ridge, Valley");
+
+    // A day later both have gone silent
+    bot.post_node_activity(1, now + 24 * 3600 + 60).await;
+    assert_eq!(
+        bridge_rx.try_recv().unwrap().text,
+        "Not heard for 24h: Ridge, Valley"
+    );
+    bot.post_node_activity(1, now + 24 * 3600 + 120).await;
+    assert!(bridge_rx.try_recv().is_err());
+}
+
+#[test]
+fn test_node_activity_text_caps_names() {
+    let names: Vec<String> = (1..=12).map(|i| format!("N{}", i)).collect();
+    assert_eq!(node_activity::activity_text(&[], &[], 24), None);
+    assert_eq!(
+        node_activity::activity_text(&names[..1], &names, 24).unwrap(),
+        "Joined the mesh: N1\nNot heard for 24h: N1, N2, N3, N4, N5, N6, N7, N8, N9, N10 and 2 more"
+    );
+}
+
+#[tokio::test]
+async fn test_unacked_dm_is_retried_when_node_is_heard() {
+    use crate::db::PendingSend;
+    use meshtastic::protobufs::routing::Error;
+
+    let mut config = test_config();
+    config.bot.dm_retries = 1;
+    let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+    let bot = Bot::new(Arc::new(config), Arc::clone(&db), ModuleRegistry::new());
+    let dm = PendingSend {
+        id: 0,
+        to_node: 0xaaaa0001,
+        mesh_channel: 0,
+        text: "You have mail".to_string(),
+        reply_id: None,
+        attempts: 1,
+    };
+    let retries = |bot: &Bot| {
+        bot.outgoing
+            .snapshot()
+            .into_iter()
+            .filter(|m| matches!(m.kind, OutgoingKind::RetryText { .. }))
+            .count()
+    };
+
+    // Delivered: the destination's ACK settles it
+    bot.track_dm_send(None, 500, dm.clone(), true).await;
+    bot.handle_mesh_packet(1, &PacketBuilder::routing_ack(0xaaaa0001, 1, 500).build())
+        .await;
+    assert!(db
+        .due_pending_sends(0xaaaa0001, 0, 0, i64::MAX)
+        .unwrap()
+        .is_empty());
+
+    // Our radio gave up: retried as soon as the node is heard again
+    bot.track_dm_send(None, 501, dm.clone(), true).await;
+    bot.handle_mesh_packet(1, &PacketBuilder::routing_ack(1, 1, 501).build())
+        .await;
+    bot.handle_mesh_packet(
+        1,
+        &PacketBuilder::routing(1, 1, 501, Error::MaxRetransmit).build(),
+    )
+    .await;
+    assert_eq!(retries(&bot), 0);
+    bot.handle_mesh_packet(1, &PacketBuilder::text(0xaaaa0001, "back").build())
+        .await;
+    assert_eq!(retries(&bot), 1);
+    let retry = bot.outgoing.snapshot().pop().unwrap();
+    assert_eq!(retry.text, "You have mail");
+    assert_eq!(retry.to_node, Some(0xaaaa0001));
+
+    // Queued once, and dropped after the last retry fails too
+    bot.handle_mesh_packet(1, &PacketBuilder::text(0xaaaa0001, "still here").build())
+        .await;
+    assert_eq!(retries(&bot), 1);
+    let OutgoingKind::RetryText { pending_id } = retry.kind else {
+        unreachable!()
+    };
+    bot.track_dm_send(Some(pending_id), 502, dm.clone(), false)
+        .await;
+    bot.handle_mesh_packet(1, &PacketBuilder::text(0xaaaa0001, "hello?").build())
+        .await;
+    assert_eq!(retries(&bot), 1);
+    assert!(db
+        .due_pending_sends(0xaaaa0001, 0, 0, i64::MAX)
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn test_expired_poll_results_are_broadcast() {
+    let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+    let bot = Bot::new(
+        Arc::new(test_config()),
+        Arc::clone(&db),
+        ModuleRegistry::new(),
+    );
+    let options = vec!["Yes".to_string(), "No".to_string()];
+    let poll = db
+        .create_poll("Pizza night?", &options, 1, 0xaaaa0001, Some(0))
+        .unwrap();
+    db.cast_poll_vote(poll, 0xaaaa0002, 0).unwrap();
+    db.create_poll("Later?", &options, 0, 0xaaaa0001, None)
+        .unwrap();
+
+    bot.close_expired_polls(1).await;
+    let queued = bot.outgoing.snapshot();
+    assert_eq!(queued.len(), 1);
+    assert_eq!(
+        queued[0].text,
+        "Poll closed: Pizza night? Results: Yes: 1, No: 0 (1 votes)"
+    );
+    assert_eq!(queued[0].mesh_channel, 1);
+    assert_eq!(queued[0].to_node, None);
+
+    bot.close_expired_polls(1).await;
+    assert_eq!(bot.outgoing.snapshot().len(), 1);
+}
+
+#[tokio::test]
+async fn test_mail_read_receipt_sent_when_sender_is_heard() {
+    let mut config = test_config();
+    config.mail.read_receipts = true;
+    config.modules.insert(
+        "mail".to_string(),
+        ModuleConfig {
+            enabled: true,
+            scope: "dm".to_string(),
+            rate_limit: None,
+            rate_limit_window_secs: None,
+            public_replies: None,
+        },
+    );
+    let registry = crate::modules::build_registry(&config, &Default::default());
+    let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+    let bot = Bot::new(Arc::new(config), Arc::clone(&db), registry);
+    bot.load_owed_deliveries().await;
+    db.upsert_node(0xaaaa0002, "BOB", "Bob", false).unwrap();
+    let id = db.store_mail(0xaaaa0001, 0xaaaa0002, "hi Bob").unwrap();
+    bot.dispatch_command_from_text(1, &test_ctx(0xaaaa0002, 0), "!inbox", true)
+        .await;
+    assert_eq!(bot.outgoing.snapshot().len(), 1);
+
+    // The reader being heard doesn't trigger it, nor the sender heard only
+    // through MQTT; the sender heard over RF does, once
+    bot.handle_mesh_packet(1, &PacketBuilder::text(0xaaaa0002, "thanks").build())
+        .await;
+    bot.handle_mesh_packet(
+        1,
+        &PacketBuilder::text(0xaaaa0001, "hello").via_mqtt().build(),
+    )
+    .await;
+    assert_eq!(bot.outgoing.snapshot().len(), 1);
+    bot.handle_mesh_packet(1, &PacketBuilder::text(0xaaaa0001, "hello").build())
+        .await;
+    bot.handle_mesh_packet(1, &PacketBuilder::text(0xaaaa0001, "again").build())
+        .await;
+    let queued = bot.outgoing.snapshot();
+    assert_eq!(queued.len(), 2);
+    assert_eq!(queued[1].text, format!("Your mail #{} to Bob was read", id));
+    assert_eq!(queued[1].to_node, Some(0xaaaa0001));
+    assert!(bot.owed_deliveries.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_muted_nodes_get_command_replies_only() {
+    let mut config = test_config();
+    config.welcome.message = "Welcome, {name}!".to_string();
+    config.mail.read_receipts = true;
+    for module in ["welcome", "mail", "mute"] {
+        config.modules.insert(
+            module.to_string(),
+            ModuleConfig {
+                enabled: true,
+                scope: "dm".to_string(),
+                rate_limit: None,
+                rate_limit_window_secs: None,
+                public_replies: None,
+            },
+        );
+    }
+    let registry = crate::modules::build_registry(&config, &Default::default());
+    let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+    let bot = Bot::new(Arc::new(config), Arc::clone(&db), registry);
+    let discovered = |node_id: u32, name: &str| crate::message::MeshEvent::NodeDiscovered {
+        node_id,
+        long_name: name.to_string(),
+        short_name: String::new(),
+        via_mqtt: false,
+    };
+
+    bot.dispatch_command_from_text(1, &test_ctx(0xaaaa0001, 0), "!mute", true)
+        .await;
+    assert!(bot.outgoing.snapshot()[0].text.starts_with("Muted"));
+    bot.dispatch_event_to_modules(&discovered(0xaaaa0001, "Alice"), 1)
+        .await;
+    bot.dispatch_event_to_modules(&discovered(0xaaaa0002, "Bob"), 1)
+        .await;
+    let queued = bot.outgoing.snapshot();
+    assert_eq!(queued.len(), 2);
+    assert_eq!(queued[1].text, "Welcome, Bob!");
+
+    // Receipts wait for the sender to unmute
+    let id = db.store_mail(0xaaaa0001, 0xaaaa0002, "hi Bob").unwrap();
+    bot.dispatch_command_from_text(1, &test_ctx(0xaaaa0002, 0), "!inbox", true)
+        .await;
+    bot.handle_mesh_packet(1, &PacketBuilder::text(0xaaaa0001, "hello").build())
+        .await;
+    assert_eq!(bot.outgoing.snapshot().len(), 3);
+    bot.dispatch_command_from_text(1, &test_ctx(0xaaaa0001, 0), "!unmute", true)
+        .await;
+    bot.handle_mesh_packet(1, &PacketBuilder::text(0xaaaa0001, "hello").build())
+        .await;
+    let queued = bot.outgoing.snapshot();
+    assert_eq!(queued.len(), 5);
+    assert!(queued[3].text.starts_with("Unmuted"));
+    assert_eq!(queued[4].text, format!("Your mail #{} to Bob was read", id));
+}
+
+#[tokio::test]
+async fn test_reply_latency_is_recorded_per_stage() {
+    let bot = test_bot_with_module(Box::new(TestCommandModule));
+
+    // Text that queues nothing isn't counted
+    bot.handle_mesh_packet(1, &PacketBuilder::text(0x22222222, "hello").to(1).build())
+        .await;
+    bot.handle_mesh_packet(
+        1,
+        &PacketBuilder::text(0x22222222, "!echo hi").to(1).build(),
+    )
+    .await;
+    assert_eq!(bot.pop_sendable(1, true).unwrap().text, "echo:hi");
+    bot.flush_latency(chrono::Utc::now().timestamp()).await;
+
+    let windows = bot.db.latency_history(0).unwrap();
+    let stages: Vec<(&str, u32)> = windows
+        .iter()
+        .map(|w| (w.stage.as_str(), w.samples))
+        .collect();
+    assert_eq!(stages, vec![("handling", 1), ("queue", 1)]);
+
+    // Nothing new since the last flush
+    bot.flush_latency(chrono::Utc::now().timestamp() + 600)
+        .await;
+    assert_eq!(bot.db.latency_history(0).unwrap().len(), 2);
+}
+
+/// Answers every event with its name after `delay`.
+struct SlowEventModule {
+    name: &'static str,
+    delay: Duration,
+}
+
+#[async_trait]
+impl Module for SlowEventModule {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn description(&self) -> &str {
+        "slow event module"
+    }
+
+    fn commands(&self) -> &[&str] {
+        &[]
+    }
+
+    fn scope(&self) -> crate::message::CommandScope {
+        crate::message::CommandScope::Both
+    }
+
+    async fn handle_command(
+        &self,
+        _command: &str,
+        _args: &str,
+        _ctx: &MessageContext,
+        _db: &Arc<Db>,
+    ) -> Result<Option<Vec<Response>>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(None)
+    }
+
+    async fn handle_event(
+        &self,
+        _event: &crate::message::MeshEvent,
+        _db: &Arc<Db>,
+    ) -> Result<Option<Vec<Response>>, Box<dyn std::error::Error + Send + Sync>> {
+        tokio::time::sleep(self.delay).await;
+        Ok(Some(vec![Response {
+            text: self.name.to_string(),
+            destination: Destination::Broadcast,
+            channel: 0,
+            reply_id: None,
+            data: None,
+        }]))
+    }
+}
+
+#[tokio::test]
+async fn test_module_events_run_concurrently_in_order() {
+    let mut registry = ModuleRegistry::new();
+    for (name, delay) in [("slow", 200), ("slower", 300), ("fast", 0)] {
+        registry.register(Box::new(SlowEventModule {
+            name,
+            delay: Duration::from_millis(delay),
+        }));
+    }
+    let bot = Bot::new(
+        Arc::new(test_config()),
+        Arc::new(Db::open(Path::new(":memory:")).unwrap()),
+        registry,
+    );
+    let event = crate::message::MeshEvent::PositionUpdate {
+        node_id: 0xaaaa0001,
+        lat: 1.0,
+        lon: 2.0,
+        altitude: 0,
+    };
+
+    let started = Instant::now();
+    bot.dispatch_event_to_modules(&event, 1).await;
+    assert!(
+        started.elapsed() < Duration::from_millis(450),
+        "modules ran one after another: {:?}",
+        started.elapsed()
+    );
+    let texts: Vec<String> = bot
+        .outgoing
+        .snapshot()
+        .into_iter()
+        .map(|msg| msg.text)
+        .collect();
+    assert_eq!(texts, vec!["slow", "slower", "fast"]);
+}
+
+#[tokio::test]
+async fn test_position_reports_build_a_track() {
+    let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+    let bot = Bot::new(
+        Arc::new(test_config()),
+        Arc::clone(&db),
+        ModuleRegistry::new(),
+    );
+    db.upsert_node(0xaaaa0001, "CAR", "Car", false).unwrap();
+    bot.handle_mesh_packet(
+        1,
+        &PacketBuilder::position(0xaaaa0001, 25.0, 121.5)
+            .id(1)
+            .build(),
+    )
+    .await;
+    bot.handle_mesh_packet(
+        1,
+        &PacketBuilder::position(0xaaaa0001, 25.01, 121.5)
+            .id(2)
+            .build(),
+    )
+    .await;
+
+    let track = db.node_track(0xaaaa0001, 24).unwrap();
+    assert_eq!(track.len(), 2);
+    assert!((track[1].latitude - 25.01).abs() < 1e-6);
+    assert_eq!((track[1].altitude, track[1].speed), (Some(40), Some(3)));
+    // The latest position is still what `nodes` holds
+    let (lat, _) = db.get_node_position(0xaaaa0001).unwrap().unwrap();
+    assert!((lat - 25.01).abs() < 1e-6);
+
+    let mut config = test_config();
+    config.privacy.position_history_days = 0;
+    let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+    let bot = Bot::new(Arc::new(config), Arc::clone(&db), ModuleRegistry::new());
+    bot.handle_mesh_packet(
+        1,
+        &PacketBuilder::position(0xaaaa0001, 25.0, 121.5)
+            .id(1)
+            .build(),
+    )
+    .await;
+    assert!(db.node_track(0xaaaa0001, 0).unwrap().is_empty());
+}
+
+/// Broadcasts every geofence transition it is dispatched.
+struct GeofenceWatcher;
+
+#[async_trait]
+impl Module for GeofenceWatcher {
+    fn name(&self) -> &str {
+        "geofence_watcher"
+    }
+
+    fn description(&self) -> &str {
+        "geofence watcher"
+    }
+
+    fn commands(&self) -> &[&str] {
+        &[]
+    }
+
+    fn scope(&self) -> crate::message::CommandScope {
+        crate::message::CommandScope::Both
+    }
+
+    async fn handle_command(
+        &self,
+        _command: &str,
+        _args: &str,
+        _ctx: &MessageContext,
+        _db: &Arc<Db>,
+    ) -> Result<Option<Vec<Response>>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(None)
+    }
+
+    async fn handle_event(
+        &self,
+        event: &crate::message::MeshEvent,
+        _db: &Arc<Db>,
+    ) -> Result<Option<Vec<Response>>, Box<dyn std::error::Error + Send + Sync>> {
+        let crate::message::MeshEvent::GeofenceTransition { fence, entered, .. } = event else {
+            return Ok(None);
+        };
+        Ok(Some(vec![Response {
+            text: format!("module: {} {}", fence, entered),
+            destination: Destination::Broadcast,
+            channel: 0,
+            reply_id: None,
+            data: None,
+        }]))
+    }
+}
+
+#[tokio::test]
+async fn test_geofence_transitions_notify_and_reach_modules() {
+    let mut config = test_config();
+    config.bot.admin_nodes = vec!["!0000beef".to_string()];
+    config.geofence = vec![GeofenceConfig {
+        name: "Depot".to_string(),
+        lat: 25.0,
+        lon: 121.5,
+        radius_m: 1000.0,
+        nodes: vec!["!aaaa0001".to_string()],
+        notify: vec!["admins".to_string(), "bridges".to_string()],
+        channel: 0,
+    }];
+    let mut registry = ModuleRegistry::new();
+    registry.register(Box::new(GeofenceWatcher));
+    let (bridge_tx, _outgoing_tx, outgoing_rx) = crate::bridge::create_bridge_channels();
+    let mut bridge_rx = bridge_tx.subscribe();
+    let bot = Bot::new(
+        Arc::new(config),
+        Arc::new(Db::open(Path::new(":memory:")).unwrap()),
+        registry,
+    )
+    .with_bridge_channels(bridge_tx, outgoing_rx);
+
+    for (id, (from, lat)) in [
+        // First fix only records that the node is outside
+        (0xaaaa0001, 25.02),
+        (0xaaaa0001, 25.0),
+        // ~1.06 km out: still inside until 10% past the radius
+        (0xaaaa0001, 25.0095),
+        (0xaaaa0001, 25.02),
+        // Not a watched node
+        (0xaaaa0002, 25.0),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        bot.handle_mesh_packet(
+            1,
+            &PacketBuilder::position(from, lat, 121.5)
+                .id(id as u32)
+                .build(),
+        )
+        .await;
+    }
+
+    let texts: Vec<(Option<u32>, String)> = bot
+        .outgoing
+        .snapshot()
+        .into_iter()
+        .map(|msg| (msg.to_node, msg.text))
+        .collect();
+    assert_eq!(
+        texts,
+        vec![
+            (None, "module: Depot true".to_string()),
+            (Some(0xbeef), "Depot: !aaaa0001 entered".to_string()),
+            (None, "module: Depot false".to_string()),
+            (Some(0xbeef), "Depot: !aaaa0001 left".to_string()),
+        ]
+    );
+    let relayed = bridge_rx.try_recv().unwrap();
+    assert_eq!(relayed.event, BridgeEvent::Geofence);
+    assert_eq!(relayed.text, "Depot: !aaaa0001 entered");
+    assert_eq!(bridge_rx.try_recv().unwrap().text, "Depot: !aaaa0001 left");
+}
+
+/// Broadcasts every liveness change it is dispatched.
+struct LivenessWatcher;
+
+#[async_trait]
+impl Module for LivenessWatcher {
+    fn name(&self) -> &str {
+        "liveness_watcher"
+    }
+
+    fn description(&self) -> &str {
+        "liveness watcher"
+    }
+
+    fn commands(&self) -> &[&str] {
+        &[]
+    }
+
+    fn scope(&self) -> crate::message::CommandScope {
+        crate::message::CommandScope::Both
+    }
+
+    async fn handle_command(
+        &self,
+        _command: &str,
+        _args: &str,
+        _ctx: &MessageContext,
+        _db: &Arc<Db>,
+    ) -> Result<Option<Vec<Response>>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(None)
+    }
+
+    async fn handle_event(
+        &self,
+        event: &crate::message::MeshEvent,
+        _db: &Arc<Db>,
+    ) -> Result<Option<Vec<Response>>, Box<dyn std::error::Error + Send + Sync>> {
+        let text = match event {
+            crate::message::MeshEvent::NodeOffline { node_id, .. } => {
+                format!("offline !{:08x}", node_id)
+            }
+            crate::message::MeshEvent::NodeBackOnline { node_id, .. } => {
+                format!("online !{:08x}", node_id)
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(vec![Response {
+            text,
+            destination: Destination::Broadcast,
+            channel: 0,
+            reply_id: None,
+            data: None,
+        }]))
+    }
+}
+
+#[tokio::test]
+async fn test_node_liveness_dispatches_offline_and_back_online() {
+    let config = test_config();
+    let stale_secs = config.freshness.stale_mins as i64 * 60;
+    let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+    db.upsert_node(1, "ME", "Me", false).unwrap();
+    db.upsert_node(0xaaaa0001, "A", "Alpha", false).unwrap();
+    let mut registry = ModuleRegistry::new();
+    registry.register(Box::new(LivenessWatcher));
+    let bot = Bot::new(Arc::new(config), Arc::clone(&db), registry);
+    let now = chrono::Utc::now().timestamp();
+    let texts = |bot: &Bot| -> Vec<String> {
+        bot.outgoing
+            .snapshot()
+            .into_iter()
+            .map(|msg| msg.text)
+            .collect()
+    };
+
+    // The baseline check only records state
+    bot.check_node_liveness(1, now + stale_secs + 60, true)
+        .await;
+    assert!(texts(&bot).is_empty());
+
+    bot.check_node_liveness(1, now, false).await;
+    bot.check_node_liveness(1, now, false).await;
+    bot.check_node_liveness(1, now + stale_secs + 60, false)
+        .await;
+    assert_eq!(
+        texts(&bot),
+        vec![
+            "online !aaaa0001".to_string(),
+            "offline !aaaa0001".to_string()
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_alert_rules_fire_once_until_cleared() {
+    let rule = |name: &str, condition: &str, node: &str, threshold: Option<f64>| AlertRuleConfig {
+        name: name.to_string(),
+        condition: condition.to_string(),
+        node: node.to_string(),
+        threshold,
+        watch_channel: None,
+        window_hours: 24,
+        notify: vec!["admins".to_string(), "bridges".to_string()],
+        channel: 0,
+    };
+    let mut config = test_config();
+    config.bot.admin_nodes = vec!["!0000beef".to_string()];
+    config.alerts = vec![
+        rule("Ridge silent", "node_silent", "!aaaa0001", Some(6.0)),
+        rule("Battery", "battery_low", "", Some(20.0)),
+        rule("Busy", "channel_utilization", "", Some(40.0)),
+        rule("Newcomer", "new_node", "", None),
+        rule("Quiet", "mesh_quiet", "", Some(1.0)),
+        // Ignored: node_silent needs a node
+        rule("Anyone silent", "node_silent", "", Some(1.0)),
+    ];
+    let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+    let (bridge_tx, _outgoing_tx, outgoing_rx) = crate::bridge::create_bridge_channels();
+    let mut bridge_rx = bridge_tx.subscribe();
+    let bot = Bot::new(Arc::new(config), Arc::clone(&db), ModuleRegistry::new())
+        .with_bridge_channels(bridge_tx, outgoing_rx);
+    let now = chrono::Utc::now().timestamp();
+    let telemetry = |timestamp: i64, battery_level: u32| crate::db::DeviceTelemetry {
+        timestamp,
+        battery_level: Some(battery_level),
+        voltage: None,
+        channel_utilization: Some(45.0),
+        air_util_tx: None,
+        uptime_seconds: None,
+    };
+
+    db.upsert_node(0xaaaa0001, "RDGE", "Ridge", false).unwrap();
+    // Known from the radio's NodeDB for days, so not new
+    db.import_node(0xaaaa0002, "VLLY", "Valley", false, now - 2 * 86400, true)
+        .unwrap();
+    db.log_device_telemetry(0xaaaa0002, &telemetry(now, 15))
+        .unwrap();
+
+    bot.evaluate_alerts(1, now).await;
+    // Nothing repeats while the conditions hold
+    bot.evaluate_alerts(1, now).await;
+    // Ridge goes silent and is no longer new
+    bot.evaluate_alerts(1, now + 7 * 3600).await;
+    // The battery recovers, then drops again
+    db.log_device_telemetry(0xaaaa0002, &telemetry(now + 1, 80))
+        .unwrap();
+    bot.evaluate_alerts(1, now + 7 * 3600).await;
+    db.log_device_telemetry(0xaaaa0002, &telemetry(now + 2, 10))
+        .unwrap();
+    bot.evaluate_alerts(1, now + 7 * 3600).await;
+
+    let texts: Vec<(Option<u32>, String)> = bot
+        .outgoing
+        .snapshot()
+        .into_iter()
+        .map(|msg| (msg.to_node, msg.text))
+        .collect();
+    assert_eq!(
+        texts,
+        [
+            "Battery: Valley battery 15% (below 20%)",
+            "Busy: Valley channel utilization 45.0% (above 40%)",
+            "Newcomer: new node Ridge",
+            "Ridge silent: Ridge last heard 7h ago",
+            "Battery: Valley battery 10% (below 20%)",
+        ]
+        .map(|text| (Some(0xbeef), text.to_string()))
+    );
+    let relayed = bridge_rx.try_recv().unwrap();
+    assert_eq!(relayed.event, BridgeEvent::Alert);
+    assert_eq!(relayed.sender_id, 0xaaaa0002);
+    assert_eq!(
+        db.alert_firing_nodes("Newcomer").unwrap(),
+        Vec::<u32>::new()
+    );
+    assert_eq!(db.alert_firing_nodes("Battery").unwrap(), vec![0xaaaa0002]);
+}
+
+#[tokio::test]
+async fn test_channel_quiet_alert_waits_for_a_full_window() {
+    let rule = |name: &str, watch_channel: u32, window_hours: u32| AlertRuleConfig {
+        name: name.to_string(),
+        condition: "channel_quiet".to_string(),
+        node: String::new(),
+        threshold: Some(1.0),
+        watch_channel: Some(watch_channel),
+        window_hours,
+        notify: vec!["admins".to_string()],
+        channel: 0,
+    };
+    let mut config = test_config();
+    config.bot.admin_nodes = vec!["!0000beef".to_string()];
+    config.alerts = vec![rule("LongFast", 0, 1), rule("Local", 1, 3)];
+    let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+    let bot = Bot::new(Arc::new(config), Arc::clone(&db), ModuleRegistry::new());
+    let now = chrono::Utc::now().timestamp();
+    db.log_received_packet(
+        None,
+        0xaaaa0001,
+        None,
+        1,
+        "hi",
+        false,
+        None,
+        None,
+        None,
+        None,
+        None,
+        crate::packet_type::PacketType::Text,
+    )
+    .unwrap();
+
+    // The log doesn't cover an hour yet
+    bot.evaluate_alerts(1, now).await;
+    assert!(bot.outgoing.snapshot().is_empty());
+
+    bot.evaluate_alerts(1, now + 2 * 3600).await;
+    bot.evaluate_alerts(1, now + 2 * 3600).await;
+    let texts: Vec<String> = bot
+        .outgoing
+        .snapshot()
+        .into_iter()
+        .map(|msg| msg.text)
+        .collect();
+    assert_eq!(
+        texts,
+        vec!["LongFast: 0 message(s) on channel 0 in 1h (expected 1)".to_string()]
+    );
+    assert_eq!(db.alert_firing_nodes("LongFast").unwrap(), vec![0]);
+}
+
+#[tokio::test]
+async fn test_weather_alerts_broadcast_once() {
+    let mut config = test_config();
+    config.weather.alerts.enabled = true;
+    config.weather.alerts.channel = 2;
+    let bot = Bot::new(
+        Arc::new(config),
+        Arc::new(Db::open(Path::new(":memory:")).unwrap()),
+        ModuleRegistry::new(),
+    );
+    let alert =
+        |id: &str, references: &[&str], event: &str, severity| crate::modules::WeatherAlert {
+            id: id.to_string(),
+            references: references.iter().map(|r| r.to_string()).collect(),
+            event: event.to_string(),
+            severity,
+            headline: None,
+            expires: None,
+        };
+    let alerts = vec![
+        alert(
+            "w1",
+            &[],
+            "Tornado Warning",
+            crate::modules::AlertSeverity::Extreme,
+        ),
+        alert(
+            "a1",
+            &[],
+            "Wind Advisory",
+            crate::modules::AlertSeverity::Moderate,
+        ),
+    ];
+
+    bot.broadcast_weather_alerts(0x11111111, alerts.clone())
+        .await;
+    // The next poll returns the same alert, then an update of it
+    bot.broadcast_weather_alerts(0x11111111, alerts.clone())
+        .await;
+    bot.broadcast_weather_alerts(
+        0x11111111,
+        vec![alert(
+            "w2",
+            &["w1"],
+            "Tornado Warning",
+            crate::modules::AlertSeverity::Extreme,
+        )],
+    )
+    .await;
+
+    let queue = bot.outgoing.snapshot();
+    assert_eq!(queue.len(), 1);
+    assert_eq!(queue[0].text, "Weather alert: Tornado Warning");
+    assert!(matches!(queue[0].destination, PacketDestination::Broadcast));
+    assert_eq!(queue[0].mesh_channel, 2);
+}
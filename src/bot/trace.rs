@@ -0,0 +1,139 @@
+use meshtastic::packet::PacketDestination;
+use meshtastic::types::{MeshChannel, NodeId};
+
+use super::*;
+use crate::message::{Destination, MessageContext, Response};
+
+/// A node is traced for `!trace` at most this often, whoever asks; the
+/// firmware drops traceroutes sent in quick succession anyway.
+const TRACE_COOLDOWN_SECS: u64 = 30;
+
+impl Bot {
+    /// `!trace <node>`: queue a traceroute to the node and remember who
+    /// asked, so `answer_trace_requests` can DM them the route. `None` when
+    /// `args` doesn't name a known node, left to the traceroute module.
+    pub(super) async fn trace_command(
+        &self,
+        my_node_id: u32,
+        ctx: &MessageContext,
+        args: &str,
+    ) -> Option<Vec<Response>> {
+        let name_query = args.trim().to_string();
+        let target = match self
+            .db
+            .run(move |db| db.find_node_by_name(&name_query))
+            .await
+        {
+            Ok(Some(id)) => id,
+            Ok(None) => return None,
+            Err(e) => {
+                log::error!("Failed to look up node for trace: {}", e);
+                return None;
+            }
+        };
+        let name = self.node_name(target).await;
+        let text = if target == my_node_id {
+            "That's me.".to_string()
+        } else if self.traceroute.add_request(target, ctx, self.now()) {
+            format!("Already tracing {}, you'll get a DM with the route.", name)
+        } else if !self
+            .traceroute
+            .can_send(target, TRACE_COOLDOWN_SECS, self.now())
+        {
+            self.traceroute.take_requests(target, self.now());
+            format!("{} was traced moments ago, try again in a minute.", name)
+        } else {
+            let channel = match MeshChannel::new(ctx.channel) {
+                Ok(channel) => channel,
+                Err(e) => {
+                    log::error!("Invalid channel {}: {}", ctx.channel, e);
+                    self.traceroute.take_requests(target, self.now());
+                    return None;
+                }
+            };
+            log::info!(
+                "{} asked for a traceroute to !{:08x}",
+                ctx.sender_name,
+                target
+            );
+            self.queue_message(OutgoingMeshMessage {
+                kind: OutgoingKind::Traceroute {
+                    target_node: target,
+                },
+                text: String::new(),
+                destination: PacketDestination::Node(NodeId::from(target)),
+                channel,
+                from_node: my_node_id,
+                to_node: Some(target),
+                mesh_channel: ctx.channel,
+                reply_id: None,
+                expires_at: None,
+            });
+            self.traceroute.mark_sent(target, self.now());
+            format!("Tracing {}, you'll get a DM with the route.", name)
+        };
+        Some(vec![Response {
+            text,
+            destination: Destination::Sender,
+            channel: ctx.channel,
+            reply_id: Some(ctx.packet_id),
+            data: None,
+        }])
+    }
+
+    /// DM the route to everyone waiting on a `!trace` of `responder`, which
+    /// just answered our traceroute.
+    pub(super) async fn answer_trace_requests(
+        &self,
+        my_node_id: u32,
+        responder: u32,
+        towards: &[u32],
+        back: &[u32],
+    ) {
+        let requests = self.traceroute.take_requests(responder, self.now());
+        if requests.is_empty() {
+            return;
+        }
+        let (towards, back) = (towards.to_vec(), back.to_vec());
+        let text = match self
+            .db
+            .run(move |db| {
+                let node_name = |id: u32| {
+                    db.get_node_name(id)
+                        .unwrap_or_else(|_| format!("!{:08x}", id))
+                };
+                let route = |hops: &[u32]| {
+                    if hops.is_empty() {
+                        "direct".to_string()
+                    } else {
+                        let names: Vec<String> = hops.iter().map(|&id| node_name(id)).collect();
+                        format!("via {}", names.join(" > "))
+                    }
+                };
+                Ok(format!(
+                    "Route to {}: {}; back: {}",
+                    node_name(responder),
+                    route(&towards),
+                    route(&back)
+                ))
+            })
+            .await
+        {
+            Ok(text) => text,
+            Err(e) => {
+                log::error!("Failed to describe route to !{:08x}: {}", responder, e);
+                return;
+            }
+        };
+        for ctx in requests {
+            let response = Response {
+                text: text.clone(),
+                destination: Destination::Sender,
+                channel: ctx.channel,
+                reply_id: None,
+                data: None,
+            };
+            self.queue_responses(&ctx, &[response], my_node_id);
+        }
+    }
+}
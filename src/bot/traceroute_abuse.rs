@@ -0,0 +1,94 @@
+use std::time::Duration;
+
+use meshtastic::packet::PacketDestination;
+use meshtastic::types::{MeshChannel, NodeId};
+
+use crate::db::MqttFilter;
+
+use super::*;
+
+impl Bot {
+    fn traceroute_abuse_window(&self) -> Duration {
+        Duration::from_secs(self.config.traceroute_abuse.window_hours as u64 * 3600)
+    }
+
+    /// Called for each traceroute request addressed to us. Flags `from` once
+    /// its requests within the window reach the threshold and tells the admins.
+    pub(super) async fn check_traceroute_abuse(&self, my_node_id: u32, from: u32, channel: u32) {
+        let abuse = &self.config.traceroute_abuse;
+        if !abuse.enabled || from == my_node_id {
+            return;
+        }
+
+        let window_hours = abuse.window_hours;
+        let requesters = self
+            .db
+            .run(move |db| {
+                db.dashboard_traceroute_requesters(my_node_id, window_hours, MqttFilter::All)
+            })
+            .await;
+        let requesters = match requesters {
+            Ok(requesters) => requesters,
+            Err(e) => {
+                log::error!("Failed to count traceroute requests: {}", e);
+                return;
+            }
+        };
+        let node_id = format!("!{:08x}", from);
+        let requester = match requesters.into_iter().find(|r| r.node_id == node_id) {
+            Some(r) if r.request_count >= abuse.threshold => r,
+            _ => return,
+        };
+        if !self
+            .traceroute
+            .flag(from, self.traceroute_abuse_window(), self.now())
+        {
+            return;
+        }
+
+        let name = if requester.long_name.is_empty() {
+            node_id.clone()
+        } else {
+            format!("{} ({})", requester.long_name, node_id)
+        };
+        let text = format!(
+            "Traceroute flood: {} sent {} traceroutes to me in the last {}h",
+            name, requester.request_count, abuse.window_hours
+        );
+        log::warn!("{}", text);
+
+        if !abuse.notify_admins {
+            return;
+        }
+        let channel_index = match MeshChannel::new(channel) {
+            Ok(ch) => ch,
+            Err(e) => {
+                log::error!("Invalid channel {}: {}", channel, e);
+                return;
+            }
+        };
+        for admin in self.admin_ids() {
+            self.queue_message(OutgoingMeshMessage {
+                kind: OutgoingKind::Text,
+                text: text.clone(),
+                destination: PacketDestination::Node(NodeId::from(admin)),
+                channel: channel_index,
+                from_node: my_node_id,
+                to_node: Some(admin),
+                mesh_channel: channel,
+                reply_id: None,
+                expires_at: None,
+            });
+        }
+    }
+
+    /// Whether commands from `node` should be dropped because it is flagged
+    /// for flooding us with traceroutes.
+    pub(super) fn ignores_traceroute_abuser(&self, node: u32) -> bool {
+        self.config.traceroute_abuse.enabled
+            && self.config.traceroute_abuse.ignore_commands
+            && self
+                .traceroute
+                .is_flagged(node, self.traceroute_abuse_window(), self.now())
+    }
+}
@@ -2,29 +2,87 @@ use std::collections::HashMap;
 use std::sync::Mutex;
 use std::time::{Duration, Instant};
 
+use crate::message::MessageContext;
+
+/// How long a `!trace` waits for the target's reply. Matches the window
+/// replies are correlated with their probe in.
+const TRACE_REQUEST_TIMEOUT: Duration = Duration::from_secs(300);
+
 pub(super) struct TracerouteState {
     last_sent: Mutex<HashMap<u32, Instant>>,
+    /// Nodes flagged for flooding us with traceroutes, and when.
+    flagged: Mutex<HashMap<u32, Instant>>,
+    /// `!trace` requesters waiting for a reply, by target node.
+    requests: Mutex<HashMap<u32, Vec<(MessageContext, Instant)>>>,
 }
 
 impl TracerouteState {
     pub(super) fn new() -> Self {
         Self {
             last_sent: Mutex::new(HashMap::new()),
+            flagged: Mutex::new(HashMap::new()),
+            requests: Mutex::new(HashMap::new()),
         }
     }
 
-    pub(super) fn can_send(&self, target: u32, cooldown_secs: u64) -> bool {
+    pub(super) fn can_send(&self, target: u32, cooldown_secs: u64, now: Instant) -> bool {
         let last_sent = self.last_sent.lock().unwrap();
         if let Some(last) = last_sent.get(&target) {
-            return last.elapsed() >= Duration::from_secs(cooldown_secs);
+            return now.saturating_duration_since(*last) >= Duration::from_secs(cooldown_secs);
+        }
+        true
+    }
+
+    pub(super) fn mark_sent(&self, target: u32, now: Instant) {
+        self.last_sent.lock().unwrap().insert(target, now);
+    }
+
+    /// Flag `node` for `window`. Returns `false` if it was already flagged
+    /// within that window.
+    pub(super) fn flag(&self, node: u32, window: Duration, now: Instant) -> bool {
+        let mut flagged = self.flagged.lock().unwrap();
+        flagged.retain(|_, at| now.saturating_duration_since(*at) < window);
+        if flagged.contains_key(&node) {
+            return false;
         }
+        flagged.insert(node, now);
         true
     }
 
-    pub(super) fn mark_sent(&self, target: u32) {
-        self.last_sent
+    pub(super) fn is_flagged(&self, node: u32, window: Duration, now: Instant) -> bool {
+        self.flagged
+            .lock()
+            .unwrap()
+            .get(&node)
+            .is_some_and(|at| now.saturating_duration_since(*at) < window)
+    }
+
+    /// Remember that `ctx`'s sender wants the route to `target`. Returns
+    /// `true` if a traceroute to `target` is already waiting for its reply.
+    pub(super) fn add_request(&self, target: u32, ctx: &MessageContext, now: Instant) -> bool {
+        let mut requests = self.requests.lock().unwrap();
+        requests.retain(|_, waiting| {
+            waiting.retain(|(_, at)| now.saturating_duration_since(*at) < TRACE_REQUEST_TIMEOUT);
+            !waiting.is_empty()
+        });
+        let waiting = requests.entry(target).or_default();
+        let pending = !waiting.is_empty();
+        if !waiting.iter().any(|(c, _)| c.sender_id == ctx.sender_id) {
+            waiting.push((ctx.clone(), now));
+        }
+        pending
+    }
+
+    /// The requesters still waiting for `target`'s reply.
+    pub(super) fn take_requests(&self, target: u32, now: Instant) -> Vec<MessageContext> {
+        self.requests
             .lock()
             .unwrap()
-            .insert(target, Instant::now());
+            .remove(&target)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|(_, at)| now.saturating_duration_since(*at) < TRACE_REQUEST_TIMEOUT)
+            .map(|(ctx, _)| ctx)
+            .collect()
     }
 }
@@ -0,0 +1,121 @@
+use meshtastic::packet::PacketDestination;
+use meshtastic::types::MeshChannel;
+
+use crate::modules::{fetch_nws_alerts, AlertSeverity, WeatherAlert};
+
+use super::outgoing::chunk_message;
+use super::*;
+
+/// Name under which alert polls are counted in `api_usage`.
+const API_NAME: &str = "weather_alerts";
+
+/// Alerts without an expiry are remembered this long.
+const ALERT_MEMORY_SECS: u64 = 7 * 24 * 60 * 60;
+
+impl Bot {
+    /// Fetch the active alerts for the `[weather]` location and broadcast
+    /// the new ones.
+    pub(super) async fn poll_weather_alerts(&self, my_node_id: u32) {
+        let quota = self.config.api_quota(API_NAME);
+        match self
+            .db
+            .run(move |db| db.try_consume_api_call(API_NAME, quota))
+            .await
+        {
+            Ok(true) => {}
+            Ok(false) => {
+                log::debug!("Weather alerts daily quota reached, skipping poll");
+                return;
+            }
+            Err(e) => {
+                log::error!("Failed to count weather alerts API call: {}", e);
+                return;
+            }
+        }
+        let weather = &self.config.weather;
+        match fetch_nws_alerts(weather.latitude, weather.longitude).await {
+            Ok(alerts) => self.broadcast_weather_alerts(my_node_id, alerts).await,
+            Err(e) => log::warn!("Failed to fetch weather alerts: {}", e),
+        }
+        if let Err(e) = self
+            .db
+            .run(|db| db.prune_weather_alerts(ALERT_MEMORY_SECS))
+            .await
+        {
+            log::error!("Failed to prune weather alerts: {}", e);
+        }
+    }
+
+    /// Broadcast alerts at or above `[weather.alerts] min_severity` that
+    /// weren't broadcast before, counting updates of an earlier alert as
+    /// already seen.
+    pub(super) async fn broadcast_weather_alerts(
+        &self,
+        my_node_id: u32,
+        alerts: Vec<WeatherAlert>,
+    ) {
+        let config = &self.config.weather.alerts;
+        let min_severity =
+            AlertSeverity::parse(&config.min_severity).unwrap_or(AlertSeverity::Severe);
+        let channel = match MeshChannel::new(config.channel) {
+            Ok(ch) => ch,
+            Err(e) => {
+                log::error!("Invalid channel {}: {}", config.channel, e);
+                return;
+            }
+        };
+        let alerts: Vec<WeatherAlert> = alerts
+            .into_iter()
+            .filter(|alert| alert.severity >= min_severity)
+            .collect();
+        // Recorded in one go; each alert comes back with whether it's new
+        let alerts = match self
+            .db
+            .run(move |db| {
+                Ok(alerts
+                    .into_iter()
+                    .map(|alert| {
+                        let recorded =
+                            db.record_weather_alert(&alert.id, &alert.references, alert.expires);
+                        (alert, recorded)
+                    })
+                    .collect::<Vec<_>>())
+            })
+            .await
+        {
+            Ok(alerts) => alerts,
+            Err(e) => {
+                log::error!("Failed to record weather alerts: {}", e);
+                return;
+            }
+        };
+        for (alert, recorded) in alerts {
+            match recorded {
+                Ok(true) => {}
+                Ok(false) => continue,
+                Err(e) => {
+                    log::error!("Failed to record weather alert: {}", e);
+                    continue;
+                }
+            }
+            let text = format!(
+                "Weather alert: {}",
+                alert.headline.as_deref().unwrap_or(&alert.event)
+            );
+            log::info!("{}", text);
+            for chunk in chunk_message(&text, self.config.bot.max_message_len) {
+                self.queue_message(OutgoingMeshMessage {
+                    kind: OutgoingKind::Text,
+                    text: chunk,
+                    destination: PacketDestination::Broadcast,
+                    channel,
+                    from_node: my_node_id,
+                    to_node: None,
+                    mesh_channel: config.channel,
+                    reply_id: None,
+                    expires_at: None,
+                });
+            }
+        }
+    }
+}
@@ -1,7 +1,15 @@
 //! Bridge abstraction for connecting mesh to external platforms.
 
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
 use tokio::sync::{broadcast, mpsc};
 
+use crate::db::Db;
+use crate::message::ResponseData;
+use crate::metrics::Metrics;
+
 /// A message from the mesh network to be forwarded to external platforms.
 #[derive(Debug, Clone)]
 pub struct MeshBridgeMessage {
@@ -10,6 +18,106 @@ pub struct MeshBridgeMessage {
     pub text: String,
     pub channel: u32,
     pub is_dm: bool,
+    pub event: BridgeEvent,
+    /// Structured details rendered richly by bridges that support it.
+    pub data: Option<ResponseData>,
+    /// ID of the mesh packet relayed, for chat messages and DMs.
+    pub packet_id: Option<u32>,
+}
+
+impl MeshBridgeMessage {
+    /// Alerts (emergencies, low batteries, sensor thresholds, geofences,
+    /// `[[alerts]]` rules) and self-test reports are relayed regardless of
+    /// channel and DM filters.
+    pub fn is_alert(&self) -> bool {
+        matches!(
+            self.event,
+            BridgeEvent::Emergency
+                | BridgeEvent::BatteryLow
+                | BridgeEvent::SensorAlert
+                | BridgeEvent::Geofence
+                | BridgeEvent::Alert
+                | BridgeEvent::SelfTestReport
+        )
+    }
+
+    /// Posted as text by every bridge: chat, alerts and node activity.
+    /// Node joined and traceroute events need a Discord embed.
+    pub fn is_plain_text(&self) -> bool {
+        matches!(self.event, BridgeEvent::Message | BridgeEvent::NodeActivity) || self.is_alert()
+    }
+
+    /// Text shown in the operator chat for a DM passed through to it, tagged
+    /// with its conversation number so replies can be routed back.
+    pub fn dm_text(&self) -> Option<String> {
+        match self.event {
+            BridgeEvent::DirectMessage { session } => Some(format!(
+                "#{} {} (!{:08x}): {}",
+                session, self.sender_name, self.sender_id, self.text
+            )),
+            _ => None,
+        }
+    }
+
+    /// JSON form published by the MQTT and webhook bridges.
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut json = serde_json::json!({
+            "event": self.event.as_str(),
+            "from": format!("!{:08x}", self.sender_id),
+            "from_id": self.sender_id,
+            "name": self.sender_name,
+            "text": self.text,
+            "channel": self.channel,
+            "is_dm": self.is_dm,
+        });
+        if let Some(data) = &self.data {
+            json["details"] = serde_json::Value::String(data.render_compact());
+        }
+        json
+    }
+}
+
+/// What a relayed mesh message is about. Chat messages and alerts go to
+/// every bridge; the other events are only relayed where configured.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BridgeEvent {
+    Message,
+    Emergency,
+    NodeJoined,
+    Traceroute,
+    BatteryLow,
+    SensorAlert,
+    /// A node entered or left a `[[geofence]]`.
+    Geofence,
+    /// An `[[alerts]]` rule started to hold.
+    Alert,
+    SelfTestReport,
+    /// Batched `[bridge.node_events]` joins and silent nodes.
+    NodeActivity,
+    /// A DM to the bot, passed through to the operator chat. `session`
+    /// numbers the conversation; replies quoting it go back to the sender.
+    DirectMessage {
+        session: u32,
+    },
+}
+
+impl BridgeEvent {
+    /// Name used for this event in bridge config (e.g. `[bridge.discord.embeds.node_joined]`).
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BridgeEvent::Message => "message",
+            BridgeEvent::Emergency => "emergency",
+            BridgeEvent::NodeJoined => "node_joined",
+            BridgeEvent::Traceroute => "traceroute",
+            BridgeEvent::BatteryLow => "battery_low",
+            BridgeEvent::SensorAlert => "sensor_alert",
+            BridgeEvent::Geofence => "geofence",
+            BridgeEvent::Alert => "alert",
+            BridgeEvent::SelfTestReport => "selftest_report",
+            BridgeEvent::NodeActivity => "node_activity",
+            BridgeEvent::DirectMessage { .. } => "direct_message",
+        }
+    }
 }
 
 /// A message from an external platform to be sent to the mesh.
@@ -18,6 +126,160 @@ pub struct OutgoingBridgeMessage {
     pub text: String,
     pub channel: u32,
     pub source: String, // e.g., "telegram", "discord"
+    /// Who sent it on the platform (user name, MQTT topic), for the bridge log.
+    pub sender: String,
+    /// Reply to this DM passthrough conversation instead of the channel.
+    pub dm_session: Option<u32>,
+    /// Send as a DM to this node instead of the channel.
+    pub to_node: Option<u32>,
+    /// When the bridge received it; messages left waiting too long through
+    /// a radio outage are dropped (`[bridge] max_queue_age_secs`).
+    pub received_at: Instant,
+}
+
+/// Bridge names, as used in `OutgoingBridgeMessage::source` and the bridge log.
+/// "dashboard" is the chat view's `/api/send`.
+pub const BRIDGE_NAMES: &[&str] = &[
+    "telegram",
+    "discord",
+    "mqtt",
+    "signal",
+    "webhook",
+    "dashboard",
+];
+
+/// Records what a bridge posted to its platform: counted for `/metrics` and,
+/// with a database, logged in `bridge_log` for `/api/bridges/{name}/log`.
+/// Chat sent the other way is recorded by the bot.
+#[derive(Clone)]
+pub struct BridgeLog {
+    db: Option<Arc<Db>>,
+    metrics: Arc<Metrics>,
+    bridge: &'static str,
+}
+
+impl BridgeLog {
+    pub fn new(db: Option<Arc<Db>>, metrics: Arc<Metrics>, bridge: &'static str) -> Self {
+        Self {
+            db,
+            metrics,
+            bridge,
+        }
+    }
+
+    /// Record a mesh message the bridge delivered.
+    pub async fn relayed(&self, msg: &MeshBridgeMessage) {
+        self.metrics.bridge_message(self.bridge, "to_bridge");
+        let Some(db) = &self.db else {
+            return;
+        };
+        let bridge = self.bridge;
+        let event = msg.event.as_str();
+        let sender = format!("!{:08x}", msg.sender_id);
+        let (channel, text, packet_id) = (msg.channel, msg.text.clone(), msg.packet_id);
+        if let Err(e) = db
+            .run(move |db| {
+                db.log_bridge_relay(
+                    bridge,
+                    "to_bridge",
+                    event,
+                    &sender,
+                    channel,
+                    &text,
+                    packet_id,
+                )
+            })
+            .await
+        {
+            log::error!("Failed to log {} relay: {}", self.bridge, e);
+        }
+    }
+}
+
+/// Whether the bot is connected to the mesh, shared with the bridges so they
+/// can tell chat users their message is waiting (`[bridge] offline_reply`).
+#[derive(Debug, Clone, Default)]
+pub struct MeshStatus {
+    online: Arc<AtomicBool>,
+    offline_reply: bool,
+    max_queue_age_secs: u64,
+}
+
+impl MeshStatus {
+    pub fn new(online: Arc<AtomicBool>, offline_reply: bool, max_queue_age_secs: u64) -> Self {
+        Self {
+            online,
+            offline_reply,
+            max_queue_age_secs,
+        }
+    }
+
+    /// Feedback for the chat user while the mesh is offline, if enabled.
+    pub fn offline_notice(&self) -> Option<String> {
+        if !self.offline_reply || self.online.load(Ordering::Relaxed) {
+            return None;
+        }
+        Some(if self.max_queue_age_secs == 0 {
+            "The mesh is offline right now; your message is queued until it reconnects.".to_string()
+        } else {
+            format!(
+                "The mesh is offline right now; your message is queued and dropped if not sent within {} min.",
+                self.max_queue_age_secs.div_ceil(60)
+            )
+        })
+    }
+}
+
+/// Check chat text against the bridge length cap (`[bridge] max_message_bytes`,
+/// 0 disables it). The error is the feedback shown to the chat user.
+pub fn check_mesh_length(text: &str, max_bytes: usize) -> Result<(), String> {
+    if max_bytes > 0 && text.len() > max_bytes {
+        return Err(format!(
+            "Message too long for the mesh ({}/{} bytes), not sent.",
+            text.len(),
+            max_bytes
+        ));
+    }
+    Ok(())
+}
+
+/// Conversation number and text of an operator's reply in the DM chat:
+/// either a message starting with `#N`, or a reply to a relayed DM (whose
+/// text starts with `#N`).
+pub fn parse_dm_reply(text: &str, replied_to: Option<&str>) -> Option<(u32, String)> {
+    fn session_tag(text: &str) -> Option<(u32, &str)> {
+        let rest = text.trim_start().strip_prefix('#')?;
+        let end = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let session = rest[..end].parse().ok()?;
+        let rest = &rest[end..];
+        if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+            return None;
+        }
+        Some((session, rest.trim()))
+    }
+
+    let (session, body) = match session_tag(text) {
+        Some(tagged) => tagged,
+        None => (session_tag(replied_to?)?.0, text.trim()),
+    };
+    (!body.is_empty()).then(|| (session, body.to_string()))
+}
+
+/// Chats a mesh message is relayed to, given the mesh channel (0 = all) of
+/// each chat bridged towards the platform. Alerts go to every chat whose
+/// channel matches, or to the first chat when none does.
+pub fn route_targets<C: Copy>(routes: &[(C, u32)], msg: &MeshBridgeMessage) -> Vec<C> {
+    let matching: Vec<C> = routes
+        .iter()
+        .filter(|(_, mesh_channel)| *mesh_channel == 0 || *mesh_channel == msg.channel)
+        .map(|(chat, _)| *chat)
+        .collect();
+    if matching.is_empty() && msg.is_alert() {
+        return routes.iter().take(1).map(|(chat, _)| *chat).collect();
+    }
+    matching
 }
 
 /// Sender for mesh messages (bot broadcasts to bridges).
@@ -65,6 +327,9 @@ mod tests {
             text: "Hello".to_string(),
             channel: 0,
             is_dm: false,
+            event: BridgeEvent::Message,
+            data: None,
+            packet_id: None,
         };
 
         tx.send(msg.clone()).unwrap();
@@ -84,6 +349,10 @@ mod tests {
             text: "From Telegram".to_string(),
             channel: 0,
             source: "telegram".to_string(),
+            sender: String::new(),
+            dm_session: None,
+            to_node: None,
+            received_at: Instant::now(),
         };
 
         outgoing_tx.send(msg).await.unwrap();
@@ -92,4 +361,80 @@ mod tests {
         assert_eq!(received.text, "From Telegram");
         assert_eq!(received.source, "telegram");
     }
+
+    #[test]
+    fn test_offline_notice() {
+        let online = Arc::new(AtomicBool::new(false));
+        assert_eq!(
+            MeshStatus::new(Arc::clone(&online), false, 900).offline_notice(),
+            None
+        );
+        let status = MeshStatus::new(Arc::clone(&online), true, 900);
+        assert!(status.offline_notice().unwrap().contains("within 15 min"));
+        online.store(true, Ordering::Relaxed);
+        assert_eq!(status.offline_notice(), None);
+    }
+
+    #[test]
+    fn test_parse_dm_reply() {
+        assert_eq!(
+            parse_dm_reply("#3 on my way", None),
+            Some((3, "on my way".to_string()))
+        );
+        assert_eq!(
+            parse_dm_reply("on my way", Some("#12 Alice (!aabbccdd): help")),
+            Some((12, "on my way".to_string()))
+        );
+        // An explicit tag wins over the quoted message
+        assert_eq!(
+            parse_dm_reply("#4 hi", Some("#12 Alice (!aabbccdd): help")),
+            Some((4, "hi".to_string()))
+        );
+        assert_eq!(parse_dm_reply("#3", None), None);
+        assert_eq!(parse_dm_reply("#3rd place", None), None);
+        assert_eq!(parse_dm_reply("hello", Some("no tag here")), None);
+        assert_eq!(parse_dm_reply("hello", None), None);
+    }
+
+    #[test]
+    fn test_route_targets() {
+        let routes = [(10, 1), (20, 2), (30, 0)];
+        let msg = |channel: u32, event: BridgeEvent| MeshBridgeMessage {
+            sender_id: 0x12345678,
+            sender_name: "Alice".to_string(),
+            text: "Hello".to_string(),
+            channel,
+            is_dm: false,
+            event,
+            data: None,
+            packet_id: None,
+        };
+        assert_eq!(
+            route_targets(&routes, &msg(2, BridgeEvent::Message)),
+            [20, 30]
+        );
+        assert_eq!(
+            route_targets(&routes[..2], &msg(5, BridgeEvent::Message)),
+            [0; 0]
+        );
+        // Alerts from an unmapped channel still reach the first chat
+        assert_eq!(
+            route_targets(&routes[..2], &msg(5, BridgeEvent::Emergency)),
+            [10]
+        );
+        assert_eq!(
+            route_targets::<i64>(&[], &msg(5, BridgeEvent::Emergency)),
+            [0; 0]
+        );
+    }
+
+    #[test]
+    fn test_check_mesh_length() {
+        assert!(check_mesh_length("short", 10).is_ok());
+        assert!(check_mesh_length(&"x".repeat(5000), 0).is_ok());
+        assert_eq!(
+            check_mesh_length("too long", 5).unwrap_err(),
+            "Message too long for the mesh (8/5 bytes), not sent."
+        );
+    }
 }
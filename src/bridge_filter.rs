@@ -0,0 +1,170 @@
+//! Redaction and blocking rules applied to mesh text before it is relayed to bridges.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use regex::Regex;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::config::BridgeRuleConfig;
+
+enum RuleAction {
+    Redact(String),
+    Block,
+}
+
+struct Rule {
+    pattern: Regex,
+    action: RuleAction,
+    hits: AtomicU64,
+}
+
+/// Compiled `[[bridge.rules]]`, shared by the bot (which applies them) and the
+/// dashboard (which reports the counters).
+pub struct BridgeFilter {
+    rules: Vec<Rule>,
+    redactions: AtomicU64,
+    blocked: AtomicU64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BridgeRuleStats {
+    pub pattern: String,
+    pub action: &'static str,
+    pub hits: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BridgeFilterStats {
+    /// Total number of matches replaced across all redact rules.
+    pub redactions: u64,
+    /// Number of messages dropped by a block rule.
+    pub blocked: u64,
+    pub rules: Vec<BridgeRuleStats>,
+}
+
+impl BridgeFilter {
+    pub fn from_config(
+        rules: &[BridgeRuleConfig],
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let rules = rules
+            .iter()
+            .map(|r| {
+                let pattern = Regex::new(&r.pattern)
+                    .map_err(|e| format!("invalid bridge rule pattern {:?}: {}", r.pattern, e))?;
+                let action = match r.action.to_lowercase().as_str() {
+                    "redact" => RuleAction::Redact(r.replacement.clone()),
+                    "block" => RuleAction::Block,
+                    other => return Err(format!("unknown bridge rule action {:?}", other)),
+                };
+                Ok(Rule {
+                    pattern,
+                    action,
+                    hits: AtomicU64::new(0),
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(Self {
+            rules,
+            redactions: AtomicU64::new(0),
+            blocked: AtomicU64::new(0),
+        })
+    }
+
+    /// Apply all rules in order. Returns `None` when a block rule matched,
+    /// otherwise the (possibly redacted) text.
+    pub fn apply(&self, text: &str) -> Option<String> {
+        let mut text = text.to_string();
+        for rule in &self.rules {
+            let matches = rule.pattern.find_iter(&text).count() as u64;
+            if matches == 0 {
+                continue;
+            }
+            rule.hits.fetch_add(matches, Ordering::Relaxed);
+            match &rule.action {
+                RuleAction::Block => {
+                    self.blocked.fetch_add(1, Ordering::Relaxed);
+                    return None;
+                }
+                RuleAction::Redact(replacement) => {
+                    self.redactions.fetch_add(matches, Ordering::Relaxed);
+                    text = rule
+                        .pattern
+                        .replace_all(&text, replacement.as_str())
+                        .into_owned();
+                }
+            }
+        }
+        Some(text)
+    }
+
+    pub fn stats(&self) -> BridgeFilterStats {
+        BridgeFilterStats {
+            redactions: self.redactions.load(Ordering::Relaxed),
+            blocked: self.blocked.load(Ordering::Relaxed),
+            rules: self
+                .rules
+                .iter()
+                .map(|r| BridgeRuleStats {
+                    pattern: r.pattern.as_str().to_string(),
+                    action: match r.action {
+                        RuleAction::Redact(_) => "redact",
+                        RuleAction::Block => "block",
+                    },
+                    hits: r.hits.load(Ordering::Relaxed),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(pattern: &str, action: &str, replacement: &str) -> BridgeRuleConfig {
+        BridgeRuleConfig {
+            pattern: pattern.to_string(),
+            action: action.to_string(),
+            replacement: replacement.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_no_rules_passes_text_through() {
+        let filter = BridgeFilter::from_config(&[]).unwrap();
+        assert_eq!(filter.apply("hello").as_deref(), Some("hello"));
+    }
+
+    #[test]
+    fn test_redact_replaces_all_matches_and_counts() {
+        let filter =
+            BridgeFilter::from_config(&[rule(r"\+?\d[\d -]{6,}\d", "redact", "[phone]")]).unwrap();
+        let out = filter.apply("call +1 555 123 4567 or 0912345678");
+        assert_eq!(out.as_deref(), Some("call [phone] or [phone]"));
+        let stats = filter.stats();
+        assert_eq!(stats.redactions, 2);
+        assert_eq!(stats.blocked, 0);
+        assert_eq!(stats.rules[0].hits, 2);
+    }
+
+    #[test]
+    fn test_block_drops_message() {
+        let filter = BridgeFilter::from_config(&[
+            rule(r"(?i)\bspam\b", "block", ""),
+            rule("secret", "redact", "***"),
+        ])
+        .unwrap();
+        assert_eq!(filter.apply("buy SPAM now"), None);
+        assert_eq!(filter.apply("a secret").as_deref(), Some("a ***"));
+        let stats = filter.stats();
+        assert_eq!(stats.blocked, 1);
+        assert_eq!(stats.redactions, 1);
+    }
+
+    #[test]
+    fn test_invalid_rules_are_rejected() {
+        assert!(BridgeFilter::from_config(&[rule("(", "redact", "")]).is_err());
+        assert!(BridgeFilter::from_config(&[rule("x", "drop", "")]).is_err());
+    }
+}
@@ -2,18 +2,23 @@
 //!
 //! Bridges messages between a Discord channel and the Meshtastic mesh.
 
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Instant;
 
 use serenity::all::{
-    ChannelId, Context, CreateMessage, EventHandler, GatewayIntents, Message, Ready,
+    ChannelId, Context, CreateEmbed, CreateEmbedFooter, CreateMessage, EventHandler,
+    GatewayIntents, Message, Ready,
 };
 use serenity::async_trait;
 use serenity::Client;
 use tokio::sync::RwLock;
 
 use crate::bridge::{
-    MeshBridgeMessage, MeshMessageReceiver, OutgoingBridgeMessage, OutgoingMessageSender,
+    check_mesh_length, parse_dm_reply, route_targets, BridgeEvent, BridgeLog, MeshBridgeMessage,
+    MeshMessageReceiver, MeshStatus, OutgoingBridgeMessage, OutgoingMessageSender,
 };
+use crate::message::ResponseData;
 
 /// Direction of message bridging.
 #[derive(Debug, Clone, PartialEq)]
@@ -44,28 +49,85 @@ impl BridgeDirection {
     }
 }
 
+/// One Discord channel bridged to one mesh channel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChannelMapping {
+    pub channel_id: u64,
+    /// Mesh channel index; 0 relays all channels to the Discord channel and
+    /// sends to the primary channel.
+    pub mesh_channel: u32,
+    pub direction: BridgeDirection,
+}
+
 /// Configuration for the Discord bridge.
 #[derive(Debug, Clone)]
 pub struct DiscordBridgeConfig {
     pub bot_token: String,
-    pub channel_id: u64,
-    pub mesh_channel: u32,
-    pub direction: BridgeDirection,
+    /// Bridged channels; a channel listed twice uses its first mapping towards the mesh.
+    pub mappings: Vec<ChannelMapping>,
+    /// Private channel that DMs to the bot are passed through to (0 = off).
+    pub dm_channel_id: u64,
     pub format: String,
+    /// Event types sent as embeds, keyed by `BridgeEvent::as_str()`.
+    pub embeds: HashMap<String, EmbedStyle>,
+    /// Longest chat message relayed to the mesh, 0 = no cap.
+    pub max_message_bytes: usize,
+    /// Whether the mesh is reachable, for the offline notice.
+    pub mesh_status: MeshStatus,
 }
 
 impl Default for DiscordBridgeConfig {
     fn default() -> Self {
         Self {
             bot_token: String::new(),
-            channel_id: 0,
-            mesh_channel: 0,
-            direction: BridgeDirection::Both,
+            mappings: Vec::new(),
+            dm_channel_id: 0,
             format: "**{name}**: {message}".to_string(),
+            embeds: HashMap::new(),
+            max_message_bytes: 600,
+            mesh_status: MeshStatus::default(),
         }
     }
 }
 
+impl DiscordBridgeConfig {
+    /// Mesh channel that messages from `channel_id` are sent to, if any.
+    fn mesh_channel_for(&self, channel_id: u64) -> Option<u32> {
+        self.mappings
+            .iter()
+            .find(|m| m.channel_id == channel_id && m.direction.forwards_to_mesh())
+            .map(|m| m.mesh_channel)
+    }
+
+    /// Discord channels a mesh message is relayed to.
+    fn targets(&self, msg: &MeshBridgeMessage) -> Vec<u64> {
+        let routes: Vec<(u64, u32)> = self
+            .mappings
+            .iter()
+            .filter(|m| m.direction.forwards_to_discord())
+            .map(|m| (m.channel_id, m.mesh_channel))
+            .collect();
+        route_targets(&routes, msg)
+    }
+}
+
+/// How one event type is rendered as an embed.
+#[derive(Debug, Clone, Default)]
+pub struct EmbedStyle {
+    /// Empty uses a default title for the event type.
+    pub title: String,
+    pub color: Option<u32>,
+}
+
+/// Parse an embed color written as `#rrggbb` (the `#` is optional).
+pub fn parse_color(s: &str) -> Option<u32> {
+    let hex = s.trim().trim_start_matches('#');
+    if hex.len() != 6 {
+        return None;
+    }
+    u32::from_str_radix(hex, 16).ok()
+}
+
 /// Shared state for the Discord event handler.
 struct HandlerState {
     config: DiscordBridgeConfig,
@@ -79,25 +141,34 @@ struct Handler {
 
 #[async_trait]
 impl EventHandler for Handler {
-    async fn message(&self, _ctx: Context, msg: Message) {
+    async fn message(&self, ctx: Context, msg: Message) {
         // Ignore messages from bots (including ourselves)
         if msg.author.bot {
             return;
         }
 
         let state = self.state.read().await;
-
-        // Only process messages from the configured channel
-        if msg.channel_id.get() != state.config.channel_id {
-            return;
-        }
-
-        // Skip if not forwarding to mesh
-        if !state.config.direction.forwards_to_mesh() {
-            return;
-        }
-
-        let content = msg.content.trim();
+        let config = &state.config;
+
+        // In the DM channel, replies go back to a passed-through DM's sender;
+        // elsewhere only channels bridged towards the mesh are processed
+        let (content, mesh_channel, dm_session) =
+            if config.dm_channel_id != 0 && msg.channel_id.get() == config.dm_channel_id {
+                let replied_to = msg.referenced_message.as_ref().map(|m| m.content.as_str());
+                let Some((session, body)) = parse_dm_reply(&msg.content, replied_to) else {
+                    let hint = "Reply to a DM, or start with its #number, to answer it.";
+                    if let Err(e) = msg.reply(&ctx.http, hint).await {
+                        log::error!("Failed to send Discord feedback: {}", e);
+                    }
+                    return;
+                };
+                (body, 0, Some(session))
+            } else {
+                let Some(mesh_channel) = config.mesh_channel_for(msg.channel_id.get()) else {
+                    return;
+                };
+                (msg.content.trim().to_string(), mesh_channel, None)
+            };
         if content.is_empty() {
             return;
         }
@@ -105,16 +176,17 @@ impl EventHandler for Handler {
         // Get sender name
         let sender_name = msg.author.name.clone();
 
+        // Long messages are split by the bot; refuse ones over the cap
+        if let Err(feedback) = check_mesh_length(&content, config.max_message_bytes) {
+            if let Err(e) = msg.reply(&ctx.http, feedback).await {
+                log::error!("Failed to send Discord feedback: {}", e);
+            }
+            return;
+        }
+
         // Format message for mesh
         let mesh_text = format!("[DC:{}] {}", sender_name, content);
 
-        // Check message length (Meshtastic limit ~230 bytes)
-        let mesh_text = if mesh_text.len() > 220 {
-            format!("{}...", &mesh_text[..217])
-        } else {
-            mesh_text
-        };
-
         log::debug!("Forwarding to mesh: {}", mesh_text);
 
         // Send to mesh
@@ -122,12 +194,20 @@ impl EventHandler for Handler {
             .outgoing_tx
             .send(OutgoingBridgeMessage {
                 text: mesh_text,
-                channel: state.config.mesh_channel,
+                channel: mesh_channel,
                 source: "discord".to_string(),
+                sender: sender_name,
+                dm_session,
+                to_node: None,
+                received_at: Instant::now(),
             })
             .await
         {
             log::error!("Failed to send to mesh: {}", e);
+        } else if let Some(notice) = config.mesh_status.offline_notice() {
+            if let Err(e) = msg.reply(&ctx.http, notice).await {
+                log::error!("Failed to send Discord feedback: {}", e);
+            }
         }
     }
 
@@ -139,22 +219,89 @@ impl EventHandler for Handler {
 /// Discord bridge instance.
 pub struct DiscordBridge {
     config: DiscordBridgeConfig,
+    log: Option<BridgeLog>,
 }
 
 impl DiscordBridge {
     /// Create a new Discord bridge with the given configuration.
     pub fn new(config: DiscordBridgeConfig) -> Self {
-        Self { config }
+        Self { config, log: None }
+    }
+
+    /// Record relayed mesh messages in the bridge log.
+    pub fn with_log(mut self, log: BridgeLog) -> Self {
+        self.log = Some(log);
+        self
     }
 
     /// Format a mesh message for Discord.
     fn format_mesh_message(config: &DiscordBridgeConfig, msg: &MeshBridgeMessage) -> String {
-        config
+        let text = config
             .format
             .replace("{name}", &msg.sender_name)
             .replace("{id}", &format!("!{:08x}", msg.sender_id))
             .replace("{message}", &msg.text)
-            .replace("{channel}", &msg.channel.to_string())
+            .replace("{channel}", &msg.channel.to_string());
+        match &msg.data {
+            Some(data) => format!("{}\n{}", text, data.render_markdown()),
+            None => text,
+        }
+    }
+
+    fn default_title(event: BridgeEvent) -> &'static str {
+        match event {
+            BridgeEvent::Message => "Mesh message",
+            BridgeEvent::Emergency => "EMERGENCY",
+            BridgeEvent::NodeJoined => "Node joined",
+            BridgeEvent::Traceroute => "Traceroute completed",
+            BridgeEvent::BatteryLow => "Battery low",
+            BridgeEvent::SensorAlert => "Sensor alert",
+            BridgeEvent::Geofence => "Geofence",
+            BridgeEvent::Alert => "Alert",
+            BridgeEvent::SelfTestReport => "Weekly self-test",
+            BridgeEvent::NodeActivity => "Mesh activity",
+            BridgeEvent::DirectMessage { .. } => "Direct message",
+        }
+    }
+
+    /// Embed fields for structured data: one inline field per key-value pair.
+    /// Tables don't map to fields and go into the description instead.
+    fn embed_fields(data: &ResponseData) -> Vec<(String, String, bool)> {
+        match data {
+            ResponseData::KeyValue(pairs) => pairs
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone(), true))
+                .collect(),
+            ResponseData::Table { .. } => Vec::new(),
+        }
+    }
+
+    fn build_embed(style: &EmbedStyle, msg: &MeshBridgeMessage) -> CreateEmbed {
+        let title = if style.title.is_empty() {
+            Self::default_title(msg.event)
+        } else {
+            &style.title
+        };
+        let mut description = msg.text.clone();
+        let mut fields = Vec::new();
+        if let Some(data) = &msg.data {
+            fields = Self::embed_fields(data);
+            if fields.is_empty() {
+                description = format!("{}\n{}", description, data.render_markdown());
+            }
+        }
+        let mut embed = CreateEmbed::new()
+            .title(title)
+            .description(description)
+            .fields(fields)
+            .footer(CreateEmbedFooter::new(format!(
+                "{} (!{:08x})",
+                msg.sender_name, msg.sender_id
+            )));
+        if let Some(color) = style.color {
+            embed = embed.color(color);
+        }
+        embed
     }
 
     /// Run the Discord bridge.
@@ -164,13 +311,12 @@ impl DiscordBridge {
         outgoing_tx: OutgoingMessageSender,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         log::info!(
-            "Starting Discord bridge (channel_id={}, direction={:?})",
-            self.config.channel_id,
-            self.config.direction
+            "Starting Discord bridge ({} channel(s): {:?})",
+            self.config.mappings.len(),
+            self.config.mappings
         );
 
         let config = self.config.clone();
-        let channel_id = ChannelId::new(config.channel_id);
 
         // Create shared state for the handler
         let state = Arc::new(RwLock::new(HandlerState {
@@ -196,12 +342,18 @@ impl DiscordBridge {
         let http = client.http.clone();
 
         // Spawn mesh→discord forwarder
-        if config.direction.forwards_to_discord() {
+        if config
+            .mappings
+            .iter()
+            .any(|m| m.direction.forwards_to_discord())
+            || config.dm_channel_id != 0
+        {
             let config_clone = config.clone();
             let http_clone = http.clone();
+            let relay_log = self.log;
 
             tokio::spawn(async move {
-                Self::mesh_to_discord_task(http_clone, channel_id, config_clone, mesh_rx).await;
+                Self::mesh_to_discord_task(http_clone, config_clone, mesh_rx, relay_log).await;
             });
         }
 
@@ -217,33 +369,68 @@ impl DiscordBridge {
     /// Task that forwards mesh messages to Discord.
     async fn mesh_to_discord_task(
         http: Arc<serenity::http::Http>,
-        channel_id: ChannelId,
         config: DiscordBridgeConfig,
         mut mesh_rx: MeshMessageReceiver,
+        relay_log: Option<BridgeLog>,
     ) {
         log::info!("Mesh→Discord forwarder started");
 
         loop {
             match mesh_rx.recv().await {
                 Ok(msg) => {
-                    // Only forward messages from the configured mesh channel
-                    // Channel 0 means "all channels"
-                    if config.mesh_channel != 0 && msg.channel != config.mesh_channel {
+                    // DMs passed through to the operator channel
+                    if let Some(text) = msg.dm_text() {
+                        if config.dm_channel_id != 0 {
+                            match ChannelId::new(config.dm_channel_id).say(&http, text).await {
+                                Ok(_) => {
+                                    if let Some(relay_log) = &relay_log {
+                                        relay_log.relayed(&msg).await;
+                                    }
+                                }
+                                Err(e) => log::error!("Failed to send DM to Discord: {}", e),
+                            }
+                        }
                         continue;
                     }
 
                     // Skip DMs (only bridge public messages)
-                    if msg.is_dm {
+                    if msg.is_dm && !msg.is_alert() {
                         continue;
                     }
 
-                    let text = Self::format_mesh_message(&config, &msg);
-
-                    log::debug!("Forwarding to Discord: {}", text);
+                    // Only forward to channels mapped to the message's mesh channel
+                    let targets = config.targets(&msg);
+                    if targets.is_empty() {
+                        continue;
+                    }
 
-                    let builder = CreateMessage::new().content(&text);
-                    if let Err(e) = channel_id.send_message(&http, builder).await {
-                        log::error!("Failed to send to Discord: {}", e);
+                    let builder = match config.embeds.get(msg.event.as_str()) {
+                        Some(style) => {
+                            log::debug!("Forwarding {} embed to Discord", msg.event.as_str());
+                            CreateMessage::new().embed(Self::build_embed(style, &msg))
+                        }
+                        // Other events are only relayed when an embed is configured
+                        None if msg.is_plain_text() => {
+                            let text = Self::format_mesh_message(&config, &msg);
+                            log::debug!("Forwarding to Discord: {}", text);
+                            CreateMessage::new().content(&text)
+                        }
+                        None => continue,
+                    };
+                    let mut delivered = false;
+                    for channel_id in targets {
+                        match ChannelId::new(channel_id)
+                            .send_message(&http, builder.clone())
+                            .await
+                        {
+                            Ok(_) => delivered = true,
+                            Err(e) => log::error!("Failed to send to Discord: {}", e),
+                        }
+                    }
+                    if delivered {
+                        if let Some(relay_log) = &relay_log {
+                            relay_log.relayed(&msg).await;
+                        }
                     }
                 }
                 Err(tokio::sync::broadcast::error::RecvError::Lagged(n)) => {
@@ -309,6 +496,9 @@ mod tests {
             text: "Hello world".to_string(),
             channel: 0,
             is_dm: false,
+            event: BridgeEvent::Message,
+            data: None,
+            packet_id: None,
         };
 
         assert_eq!(
@@ -330,6 +520,9 @@ mod tests {
             text: "Test".to_string(),
             channel: 0,
             is_dm: false,
+            event: BridgeEvent::Message,
+            data: None,
+            packet_id: None,
         };
 
         assert_eq!(
@@ -337,4 +530,90 @@ mod tests {
             "`!12345678` **Bob**: Test"
         );
     }
+
+    #[test]
+    fn test_format_mesh_message_with_data() {
+        let config = DiscordBridgeConfig {
+            format: "**{name}**: {message}".to_string(),
+            ..Default::default()
+        };
+
+        let msg = MeshBridgeMessage {
+            sender_id: 0x12345678,
+            sender_name: "Bob".to_string(),
+            text: "EMERGENCY (SOS): help".to_string(),
+            channel: 0,
+            is_dm: false,
+            event: BridgeEvent::Emergency,
+            data: Some(ResponseData::KeyValue(vec![(
+                "Position".to_string(),
+                "unknown".to_string(),
+            )])),
+            packet_id: None,
+        };
+
+        assert_eq!(
+            DiscordBridge::format_mesh_message(&config, &msg),
+            "**Bob**: EMERGENCY (SOS): help\n**Position:** unknown"
+        );
+    }
+
+    #[test]
+    fn test_channel_mappings() {
+        let config = DiscordBridgeConfig {
+            mappings: vec![
+                ChannelMapping {
+                    channel_id: 111,
+                    mesh_channel: 1,
+                    direction: BridgeDirection::Both,
+                },
+                ChannelMapping {
+                    channel_id: 222,
+                    mesh_channel: 2,
+                    direction: BridgeDirection::ToMesh,
+                },
+            ],
+            ..Default::default()
+        };
+        assert_eq!(config.mesh_channel_for(111), Some(1));
+        assert_eq!(config.mesh_channel_for(222), Some(2));
+        assert_eq!(config.mesh_channel_for(333), None);
+
+        let msg = |channel: u32, event: BridgeEvent| MeshBridgeMessage {
+            sender_id: 0x12345678,
+            sender_name: "Bob".to_string(),
+            text: "Test".to_string(),
+            channel,
+            is_dm: false,
+            event,
+            data: None,
+            packet_id: None,
+        };
+        assert_eq!(config.targets(&msg(1, BridgeEvent::Message)), [111]);
+        // Channel 2 is only bridged towards the mesh
+        assert!(config.targets(&msg(2, BridgeEvent::Message)).is_empty());
+        assert_eq!(config.targets(&msg(2, BridgeEvent::Emergency)), [111]);
+    }
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(parse_color("#e74c3c"), Some(0xe74c3c));
+        assert_eq!(parse_color("2ecc71"), Some(0x2ecc71));
+        assert_eq!(parse_color("red"), None);
+        assert_eq!(parse_color("#fff"), None);
+    }
+
+    #[test]
+    fn test_embed_fields() {
+        let kv = ResponseData::KeyValue(vec![("Hops back".to_string(), "2".to_string())]);
+        assert_eq!(
+            DiscordBridge::embed_fields(&kv),
+            vec![("Hops back".to_string(), "2".to_string(), true)]
+        );
+        let table = ResponseData::Table {
+            headers: Vec::new(),
+            rows: vec![vec!["a".to_string()]],
+        };
+        assert!(DiscordBridge::embed_fields(&table).is_empty());
+    }
 }
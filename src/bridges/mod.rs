@@ -1,7 +1,13 @@
 //! Bridge implementations for external platforms.
 
 pub mod discord;
+pub mod mqtt;
+pub mod signal;
 pub mod telegram;
+pub mod webhook;
 
 pub use discord::{DiscordBridge, DiscordBridgeConfig};
+pub use mqtt::{MqttBridge, MqttBridgeConfig};
+pub use signal::{SignalBridge, SignalBridgeConfig};
 pub use telegram::{BridgeDirection, TelegramBridge, TelegramBridgeConfig};
+pub use webhook::{WebhookBridge, WebhookBridgeConfig, WebhookInbound};
@@ -0,0 +1,355 @@
+//! MQTT bridge for Meshenger.
+//!
+//! Publishes mesh messages as JSON to a topic and injects messages published
+//! to a command topic into the mesh, for Home Assistant, Node-RED and the like.
+
+use std::time::{Duration, Instant};
+
+use rumqttc::{AsyncClient, Event, MqttOptions, Packet, QoS, Transport};
+use serde::Deserialize;
+
+use crate::bridge::{
+    check_mesh_length, BridgeLog, MeshBridgeMessage, MeshMessageReceiver, MeshStatus,
+    OutgoingBridgeMessage, OutgoingMessageSender,
+};
+
+/// Direction of message bridging.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BridgeDirection {
+    /// Only publish mesh messages to MQTT
+    ToMqtt,
+    /// Only inject command topic messages into the mesh
+    ToMesh,
+    /// Bidirectional bridging
+    Both,
+}
+
+impl BridgeDirection {
+    pub fn from_str(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "to_mqtt" | "tomqtt" | "mesh_to_mqtt" => BridgeDirection::ToMqtt,
+            "to_mesh" | "tomesh" | "mqtt_to_mesh" => BridgeDirection::ToMesh,
+            _ => BridgeDirection::Both,
+        }
+    }
+
+    pub fn forwards_to_mqtt(&self) -> bool {
+        matches!(self, BridgeDirection::ToMqtt | BridgeDirection::Both)
+    }
+
+    pub fn forwards_to_mesh(&self) -> bool {
+        matches!(self, BridgeDirection::ToMesh | BridgeDirection::Both)
+    }
+}
+
+/// Configuration for the MQTT bridge.
+#[derive(Debug, Clone)]
+pub struct MqttBridgeConfig {
+    /// `mqtt://host:port` or `mqtts://host:port`.
+    pub broker: String,
+    pub username: String,
+    pub password: String,
+    /// Mesh messages are published here as JSON.
+    pub publish_topic: String,
+    /// Messages published here are sent to the mesh.
+    pub command_topic: String,
+    pub mesh_channel: u32,
+    pub direction: BridgeDirection,
+    /// Longest message injected into the mesh, 0 = no cap.
+    pub max_message_bytes: usize,
+    /// Whether the mesh is reachable, for the offline notice.
+    pub mesh_status: MeshStatus,
+}
+
+impl Default for MqttBridgeConfig {
+    fn default() -> Self {
+        Self {
+            broker: "mqtt://localhost:1883".to_string(),
+            username: String::new(),
+            password: String::new(),
+            publish_topic: "meshenger/messages".to_string(),
+            command_topic: "meshenger/send".to_string(),
+            mesh_channel: 0,
+            direction: BridgeDirection::Both,
+            max_message_bytes: 600,
+            mesh_status: MeshStatus::default(),
+        }
+    }
+}
+
+/// A message published to the command topic: either plain text or
+/// `{"text": "...", "channel": 1, "name": "ha"}`.
+#[derive(Debug, Deserialize)]
+struct Command {
+    text: String,
+    channel: Option<u32>,
+    name: Option<String>,
+}
+
+/// MQTT bridge instance.
+pub struct MqttBridge {
+    config: MqttBridgeConfig,
+    log: Option<BridgeLog>,
+}
+
+impl MqttBridge {
+    /// Create a new MQTT bridge with the given configuration.
+    pub fn new(config: MqttBridgeConfig) -> Self {
+        Self { config, log: None }
+    }
+
+    /// Record relayed mesh messages in the bridge log.
+    pub fn with_log(mut self, log: BridgeLog) -> Self {
+        self.log = Some(log);
+        self
+    }
+
+    /// JSON payload published for a mesh message.
+    fn format_mesh_message(msg: &MeshBridgeMessage) -> String {
+        msg.to_json().to_string()
+    }
+
+    /// Text (tagged `[MQ:name]`) and channel for a command topic payload.
+    fn parse_command(payload: &[u8], default_channel: u32) -> Option<(String, u32)> {
+        let raw = std::str::from_utf8(payload).ok()?.trim();
+        let command = match serde_json::from_str::<Command>(raw) {
+            Ok(command) => command,
+            Err(_) if raw.starts_with('{') => return None,
+            Err(_) => Command {
+                text: raw.to_string(),
+                channel: None,
+                name: None,
+            },
+        };
+        let text = command.text.trim();
+        if text.is_empty() {
+            return None;
+        }
+        let name = command.name.unwrap_or_else(|| "mqtt".to_string());
+        Some((
+            format!("[MQ:{}] {}", name, text),
+            command.channel.unwrap_or(default_channel),
+        ))
+    }
+
+    /// Run the MQTT bridge.
+    pub async fn run(
+        self,
+        mesh_rx: MeshMessageReceiver,
+        outgoing_tx: OutgoingMessageSender,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let config = self.config;
+        log::info!(
+            "Starting MQTT bridge (broker={}, direction={:?})",
+            config.broker,
+            config.direction
+        );
+
+        let (host, port, tls) = crate::mqtt::parse_broker(&config.broker)?;
+        let client_id = format!("meshenger-bridge-{:08x}", rand::random::<u32>());
+        let mut options = MqttOptions::new(client_id, host, port);
+        options.set_keep_alive(Duration::from_secs(30));
+        if !config.username.is_empty() {
+            options.set_credentials(config.username.clone(), config.password.clone());
+        }
+        if tls {
+            options.set_transport(Transport::tls_with_default_config());
+        }
+        let (client, mut eventloop) = AsyncClient::new(options, 64);
+
+        // Spawn mesh→MQTT publisher
+        if config.direction.forwards_to_mqtt() {
+            let config_clone = config.clone();
+            let client_clone = client.clone();
+            let relay_log = self.log;
+            tokio::spawn(async move {
+                Self::mesh_to_mqtt_task(client_clone, config_clone, mesh_rx, relay_log).await;
+            });
+        }
+
+        loop {
+            match eventloop.poll().await {
+                Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                    log::info!("MQTT bridge connected");
+                    if config.direction.forwards_to_mesh() {
+                        if let Err(e) =
+                            client.try_subscribe(config.command_topic.clone(), QoS::AtLeastOnce)
+                        {
+                            log::error!("MQTT bridge subscribe failed: {}", e);
+                        }
+                    }
+                }
+                Ok(Event::Incoming(Packet::Publish(publish))) => {
+                    if publish.topic != config.command_topic {
+                        continue;
+                    }
+                    let Some((text, channel)) =
+                        Self::parse_command(&publish.payload, config.mesh_channel)
+                    else {
+                        log::warn!("Ignoring malformed MQTT bridge command");
+                        continue;
+                    };
+                    // Long messages are split by the bot; refuse ones over the cap
+                    if let Err(feedback) = check_mesh_length(&text, config.max_message_bytes) {
+                        log::warn!("MQTT bridge: {}", feedback);
+                        continue;
+                    }
+                    log::debug!("Forwarding to mesh: {}", text);
+                    if let Err(e) = outgoing_tx
+                        .send(OutgoingBridgeMessage {
+                            text,
+                            channel,
+                            source: "mqtt".to_string(),
+                            sender: publish.topic.clone(),
+                            dm_session: None,
+                            to_node: None,
+                            received_at: Instant::now(),
+                        })
+                        .await
+                    {
+                        log::error!("Failed to send to mesh: {}", e);
+                    } else if let Some(notice) = config.mesh_status.offline_notice() {
+                        log::warn!("MQTT bridge: {}", notice);
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    log::error!("MQTT bridge connection error: {}", e);
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                }
+            }
+        }
+    }
+
+    /// Task that publishes mesh messages to MQTT.
+    async fn mesh_to_mqtt_task(
+        client: AsyncClient,
+        config: MqttBridgeConfig,
+        mut mesh_rx: MeshMessageReceiver,
+        relay_log: Option<BridgeLog>,
+    ) {
+        log::info!("Mesh→MQTT publisher started");
+
+        loop {
+            match mesh_rx.recv().await {
+                Ok(msg) => {
+                    // Channel 0 means "all channels"; alerts always pass
+                    if !msg.is_alert()
+                        && config.mesh_channel != 0
+                        && msg.channel != config.mesh_channel
+                    {
+                        continue;
+                    }
+
+                    // Skip DMs and node/traceroute events (only chat and alerts)
+                    if !msg.is_alert() && (msg.is_dm || !msg.is_plain_text()) {
+                        continue;
+                    }
+
+                    let payload = Self::format_mesh_message(&msg);
+                    log::debug!("Publishing to MQTT: {}", payload);
+                    match client
+                        .publish(
+                            config.publish_topic.clone(),
+                            QoS::AtLeastOnce,
+                            false,
+                            payload,
+                        )
+                        .await
+                    {
+                        Ok(()) => {
+                            if let Some(relay_log) = &relay_log {
+                                relay_log.relayed(&msg).await;
+                            }
+                        }
+                        Err(e) => log::error!("Failed to publish to MQTT: {}", e),
+                    }
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(n)) => {
+                    log::warn!("MQTT bridge lagged, missed {} messages", n);
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => {
+                    log::info!("Mesh channel closed, stopping MQTT publisher");
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::BridgeEvent;
+    use crate::message::ResponseData;
+
+    #[test]
+    fn test_bridge_direction_from_str() {
+        assert_eq!(
+            BridgeDirection::from_str("to_mqtt"),
+            BridgeDirection::ToMqtt
+        );
+        assert_eq!(
+            BridgeDirection::from_str("mqtt_to_mesh"),
+            BridgeDirection::ToMesh
+        );
+        assert_eq!(BridgeDirection::from_str("unknown"), BridgeDirection::Both);
+        assert!(!BridgeDirection::ToMqtt.forwards_to_mesh());
+        assert!(!BridgeDirection::ToMesh.forwards_to_mqtt());
+    }
+
+    #[test]
+    fn test_format_mesh_message() {
+        let msg = MeshBridgeMessage {
+            sender_id: 0xaabbccdd,
+            sender_name: "Alice".to_string(),
+            text: "Hello world".to_string(),
+            channel: 1,
+            is_dm: false,
+            event: BridgeEvent::Message,
+            data: None,
+            packet_id: None,
+        };
+        let json: serde_json::Value =
+            serde_json::from_str(&MqttBridge::format_mesh_message(&msg)).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "event": "message",
+                "from": "!aabbccdd",
+                "from_id": 0xaabbccddu32,
+                "name": "Alice",
+                "text": "Hello world",
+                "channel": 1,
+                "is_dm": false,
+            })
+        );
+
+        let alert = MeshBridgeMessage {
+            event: BridgeEvent::Emergency,
+            data: Some(ResponseData::KeyValue(vec![(
+                "Position".to_string(),
+                "unknown".to_string(),
+            )])),
+            ..msg
+        };
+        let json: serde_json::Value =
+            serde_json::from_str(&MqttBridge::format_mesh_message(&alert)).unwrap();
+        assert_eq!(json["event"], "emergency");
+        assert!(json["details"].as_str().unwrap().contains("unknown"));
+    }
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(
+            MqttBridge::parse_command(b"garage door open", 2),
+            Some(("[MQ:mqtt] garage door open".to_string(), 2))
+        );
+        assert_eq!(
+            MqttBridge::parse_command(br#"{"text": "hi", "channel": 1, "name": "ha"}"#, 0),
+            Some(("[MQ:ha] hi".to_string(), 1))
+        );
+        assert_eq!(MqttBridge::parse_command(br#"{"channel": 1}"#, 0), None);
+        assert_eq!(MqttBridge::parse_command(b"   ", 0), None);
+    }
+}
@@ -0,0 +1,408 @@
+//! Signal bridge for Meshenger.
+//!
+//! Bridges messages between a Signal group and the Meshtastic mesh through a
+//! signal-cli-rest-api instance (https://github.com/bbernhard/signal-cli-rest-api),
+//! running in its "normal" or "native" mode.
+
+use std::time::{Duration, Instant};
+
+use base64::Engine;
+use serde::Deserialize;
+
+use crate::bridge::{
+    check_mesh_length, BridgeLog, MeshBridgeMessage, MeshMessageReceiver, MeshStatus,
+    OutgoingBridgeMessage, OutgoingMessageSender,
+};
+
+/// How often new Signal messages are fetched.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// Give up on a request to signal-cli-rest-api that takes longer than this.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Direction of message bridging.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BridgeDirection {
+    /// Only forward mesh messages to Signal
+    ToSignal,
+    /// Only forward Signal messages to mesh
+    ToMesh,
+    /// Bidirectional bridging
+    Both,
+}
+
+impl BridgeDirection {
+    pub fn from_str(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "to_signal" | "tosignal" | "mesh_to_signal" => BridgeDirection::ToSignal,
+            "to_mesh" | "tomesh" | "signal_to_mesh" => BridgeDirection::ToMesh,
+            _ => BridgeDirection::Both,
+        }
+    }
+
+    pub fn forwards_to_signal(&self) -> bool {
+        matches!(self, BridgeDirection::ToSignal | BridgeDirection::Both)
+    }
+
+    pub fn forwards_to_mesh(&self) -> bool {
+        matches!(self, BridgeDirection::ToMesh | BridgeDirection::Both)
+    }
+}
+
+/// Configuration for the Signal bridge.
+#[derive(Debug, Clone)]
+pub struct SignalBridgeConfig {
+    /// Base URL of signal-cli-rest-api, e.g. "http://localhost:8080".
+    pub api_url: String,
+    /// Phone number registered with signal-cli, e.g. "+15551234567".
+    pub number: String,
+    /// Group to bridge, as listed by `GET /v1/groups/<number>` ("group.…").
+    pub group_id: String,
+    pub mesh_channel: u32,
+    pub direction: BridgeDirection,
+    pub format: String,
+    /// Longest chat message relayed to the mesh, 0 = no cap.
+    pub max_message_bytes: usize,
+    /// Whether the mesh is reachable, for the offline notice.
+    pub mesh_status: MeshStatus,
+}
+
+impl Default for SignalBridgeConfig {
+    fn default() -> Self {
+        Self {
+            api_url: "http://localhost:8080".to_string(),
+            number: String::new(),
+            group_id: String::new(),
+            mesh_channel: 0,
+            direction: BridgeDirection::Both,
+            format: "[{name}] {message}".to_string(),
+            max_message_bytes: 600,
+            mesh_status: MeshStatus::default(),
+        }
+    }
+}
+
+/// One entry of `GET /v1/receive/<number>`.
+#[derive(Debug, Deserialize)]
+struct Received {
+    envelope: Envelope,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Envelope {
+    #[serde(default)]
+    source: Option<String>,
+    #[serde(default)]
+    source_name: Option<String>,
+    #[serde(default)]
+    data_message: Option<DataMessage>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DataMessage {
+    #[serde(default)]
+    message: Option<String>,
+    #[serde(default)]
+    group_info: Option<GroupInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GroupInfo {
+    group_id: String,
+}
+
+/// Whether a received message's internal group ID belongs to the configured
+/// group. The send API's "group.<id>" is the internal ID, base64-encoded again.
+fn is_group(group_id: &str, internal_id: &str) -> bool {
+    if group_id == internal_id {
+        return true;
+    }
+    group_id
+        .strip_prefix("group.")
+        .and_then(|id| base64::engine::general_purpose::STANDARD.decode(id).ok())
+        .is_some_and(|decoded| decoded == internal_id.as_bytes())
+}
+
+/// Sender names and texts of the group messages in a receive response.
+fn group_messages(body: &str, group_id: &str) -> Vec<(String, String)> {
+    let received: Vec<Received> = match serde_json::from_str(body) {
+        Ok(received) => received,
+        Err(e) => {
+            log::warn!("Unexpected Signal receive response: {}", e);
+            return Vec::new();
+        }
+    };
+    received
+        .into_iter()
+        .filter_map(|r| {
+            let envelope = r.envelope;
+            let data = envelope.data_message?;
+            if !is_group(group_id, &data.group_info?.group_id) {
+                return None;
+            }
+            let text = data.message?.trim().to_string();
+            if text.is_empty() {
+                return None;
+            }
+            let name = envelope
+                .source_name
+                .filter(|n| !n.is_empty())
+                .or(envelope.source)
+                .unwrap_or_else(|| "unknown".to_string());
+            Some((name, text))
+        })
+        .collect()
+}
+
+fn render_mesh_message(format: &str, msg: &MeshBridgeMessage) -> String {
+    let text = format
+        .replace("{name}", &msg.sender_name)
+        .replace("{id}", &format!("!{:08x}", msg.sender_id))
+        .replace("{message}", &msg.text)
+        .replace("{channel}", &msg.channel.to_string());
+    match &msg.data {
+        Some(data) => format!("{}\n{}", text, data.render_compact()),
+        None => text,
+    }
+}
+
+/// Signal bridge instance.
+pub struct SignalBridge {
+    config: SignalBridgeConfig,
+    log: Option<BridgeLog>,
+}
+
+impl SignalBridge {
+    /// Create a new Signal bridge with the given configuration.
+    pub fn new(config: SignalBridgeConfig) -> Self {
+        Self { config, log: None }
+    }
+
+    /// Record relayed mesh messages in the bridge log.
+    pub fn with_log(mut self, log: BridgeLog) -> Self {
+        self.log = Some(log);
+        self
+    }
+
+    /// Post a message to the configured group.
+    async fn send_to_group(
+        client: &reqwest::Client,
+        config: &SignalBridgeConfig,
+        text: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!("{}/v2/send", config.api_url.trim_end_matches('/'));
+        client
+            .post(url)
+            .json(&serde_json::json!({
+                "message": text,
+                "number": config.number,
+                "recipients": [config.group_id],
+            }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    /// Run the Signal bridge.
+    pub async fn run(
+        self,
+        mesh_rx: MeshMessageReceiver,
+        outgoing_tx: OutgoingMessageSender,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let config = self.config;
+        log::info!(
+            "Starting Signal bridge (group={}, direction={:?})",
+            config.group_id,
+            config.direction
+        );
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()?;
+
+        // Spawn mesh→Signal forwarder
+        if config.direction.forwards_to_signal() {
+            let config_clone = config.clone();
+            let client_clone = client.clone();
+            let relay_log = self.log;
+            tokio::spawn(async move {
+                Self::mesh_to_signal_task(client_clone, config_clone, mesh_rx, relay_log).await;
+            });
+        }
+
+        if !config.direction.forwards_to_mesh() {
+            // If only mesh→Signal, just keep running
+            loop {
+                tokio::time::sleep(Duration::from_secs(3600)).await;
+            }
+        }
+
+        // Poll for Signal messages (this blocks)
+        let url = format!(
+            "{}/v1/receive/{}",
+            config.api_url.trim_end_matches('/'),
+            config.number
+        );
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            let body = match client.get(&url).send().await {
+                Ok(resp) => match resp.error_for_status() {
+                    Ok(resp) => resp.text().await.unwrap_or_default(),
+                    Err(e) => {
+                        log::error!("Signal receive failed: {}", e);
+                        continue;
+                    }
+                },
+                Err(e) => {
+                    log::error!("Signal receive failed: {}", e);
+                    continue;
+                }
+            };
+
+            for (sender_name, text) in group_messages(&body, &config.group_id) {
+                // Long messages are split by the bot; refuse ones over the cap
+                if let Err(feedback) = check_mesh_length(&text, config.max_message_bytes) {
+                    if let Err(e) = Self::send_to_group(&client, &config, &feedback).await {
+                        log::error!("Failed to send Signal feedback: {}", e);
+                    }
+                    continue;
+                }
+
+                let mesh_text = format!("[SG:{}] {}", sender_name, text);
+                log::debug!("Forwarding to mesh: {}", mesh_text);
+
+                if let Err(e) = outgoing_tx
+                    .send(OutgoingBridgeMessage {
+                        text: mesh_text,
+                        channel: config.mesh_channel,
+                        source: "signal".to_string(),
+                        sender: sender_name,
+                        dm_session: None,
+                        to_node: None,
+                        received_at: Instant::now(),
+                    })
+                    .await
+                {
+                    log::error!("Failed to send to mesh: {}", e);
+                } else if let Some(notice) = config.mesh_status.offline_notice() {
+                    if let Err(e) = Self::send_to_group(&client, &config, &notice).await {
+                        log::error!("Failed to send Signal feedback: {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Task that forwards mesh messages to Signal.
+    async fn mesh_to_signal_task(
+        client: reqwest::Client,
+        config: SignalBridgeConfig,
+        mut mesh_rx: MeshMessageReceiver,
+        relay_log: Option<BridgeLog>,
+    ) {
+        log::info!("Mesh→Signal forwarder started");
+
+        loop {
+            match mesh_rx.recv().await {
+                Ok(msg) => {
+                    // Channel 0 means "all channels"; alerts always pass
+                    if !msg.is_alert()
+                        && config.mesh_channel != 0
+                        && msg.channel != config.mesh_channel
+                    {
+                        continue;
+                    }
+
+                    // Skip DMs and node/traceroute events (only chat and alerts)
+                    if !msg.is_alert() && (msg.is_dm || !msg.is_plain_text()) {
+                        continue;
+                    }
+
+                    let text = render_mesh_message(&config.format, &msg);
+                    log::debug!("Forwarding to Signal: {}", text);
+                    match Self::send_to_group(&client, &config, &text).await {
+                        Ok(()) => {
+                            if let Some(relay_log) = &relay_log {
+                                relay_log.relayed(&msg).await;
+                            }
+                        }
+                        Err(e) => log::error!("Failed to send to Signal: {}", e),
+                    }
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(n)) => {
+                    log::warn!("Signal bridge lagged, missed {} messages", n);
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => {
+                    log::info!("Mesh channel closed, stopping Signal forwarder");
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::BridgeEvent;
+
+    #[test]
+    fn test_bridge_direction_from_str() {
+        assert_eq!(
+            BridgeDirection::from_str("to_signal"),
+            BridgeDirection::ToSignal
+        );
+        assert_eq!(
+            BridgeDirection::from_str("signal_to_mesh"),
+            BridgeDirection::ToMesh
+        );
+        assert_eq!(BridgeDirection::from_str("unknown"), BridgeDirection::Both);
+        assert!(!BridgeDirection::ToSignal.forwards_to_mesh());
+        assert!(!BridgeDirection::ToMesh.forwards_to_signal());
+    }
+
+    #[test]
+    fn test_group_messages() {
+        // "group." + base64("abc=")
+        let group_id = "group.YWJjPQ==";
+        let body = r#"[
+            {"envelope": {"source": "+15550001", "sourceName": "Alice",
+                "dataMessage": {"message": " hi mesh ", "groupInfo": {"groupId": "abc="}}}},
+            {"envelope": {"source": "+15550002", "sourceName": "",
+                "dataMessage": {"message": "no name", "groupInfo": {"groupId": "abc="}}}},
+            {"envelope": {"source": "+15550003",
+                "dataMessage": {"message": "other group", "groupInfo": {"groupId": "xyz="}}}},
+            {"envelope": {"source": "+15550004",
+                "dataMessage": {"message": "direct message"}}},
+            {"envelope": {"source": "+15550005", "typingMessage": {"action": "STARTED"}}}
+        ]"#;
+        assert_eq!(
+            group_messages(body, group_id),
+            vec![
+                ("Alice".to_string(), "hi mesh".to_string()),
+                ("+15550002".to_string(), "no name".to_string()),
+            ]
+        );
+        assert!(group_messages("not json", group_id).is_empty());
+    }
+
+    #[test]
+    fn test_format_mesh_message() {
+        let msg = MeshBridgeMessage {
+            sender_id: 0xaabbccdd,
+            sender_name: "Alice".to_string(),
+            text: "Hello world".to_string(),
+            channel: 0,
+            is_dm: false,
+            event: BridgeEvent::Message,
+            data: None,
+            packet_id: None,
+        };
+        assert_eq!(
+            render_mesh_message("[{name}] {message}", &msg),
+            "[Alice] Hello world"
+        );
+    }
+}
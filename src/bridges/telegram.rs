@@ -2,15 +2,26 @@
 //!
 //! Bridges messages between a Telegram group/channel and the Meshtastic mesh.
 
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::Instant;
 
+use chrono::{DateTime, Local};
 use teloxide::prelude::*;
-use teloxide::types::ParseMode;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup, ParseMode};
 use tokio::sync::mpsc;
 
 use crate::bridge::{
-    MeshBridgeMessage, MeshMessageReceiver, OutgoingBridgeMessage, OutgoingMessageSender,
+    check_mesh_length, parse_dm_reply, route_targets, BridgeLog, MeshBridgeMessage,
+    MeshMessageReceiver, MeshStatus, OutgoingBridgeMessage, OutgoingMessageSender,
 };
+use crate::db::Db;
+use crate::util::{escape_html, format_ago};
+
+/// Nodes listed by the control panel's node view.
+const PANEL_NODES: usize = 10;
+/// Messages listed by the control panel's message view.
+const PANEL_MESSAGES: usize = 5;
 
 /// Direction of message bridging.
 #[derive(Debug, Clone, PartialEq)]
@@ -41,47 +52,226 @@ impl BridgeDirection {
     }
 }
 
+/// One Telegram chat bridged to one mesh channel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChatMapping {
+    pub chat_id: i64,
+    /// Mesh channel index; 0 relays all channels to the chat and sends to
+    /// the primary channel.
+    pub mesh_channel: u32,
+    pub direction: BridgeDirection,
+}
+
 /// Configuration for the Telegram bridge.
 #[derive(Debug, Clone)]
 pub struct TelegramBridgeConfig {
     pub bot_token: String,
-    pub chat_id: i64,
-    pub mesh_channel: u32,
-    pub direction: BridgeDirection,
+    /// Bridged chats; a chat listed twice uses its first mapping towards the mesh.
+    pub mappings: Vec<ChatMapping>,
+    /// Private chat that DMs to the bot are passed through to (0 = off).
+    pub dm_chat_id: i64,
     pub format: String, // e.g., "[{name}] {message}"
+    /// Longest chat message relayed to the mesh, 0 = no cap.
+    pub max_message_bytes: usize,
+    /// Whether the mesh is reachable, for the offline notice.
+    pub mesh_status: MeshStatus,
 }
 
 impl Default for TelegramBridgeConfig {
     fn default() -> Self {
         Self {
             bot_token: String::new(),
-            chat_id: 0,
-            mesh_channel: 0,
-            direction: BridgeDirection::Both,
+            mappings: Vec::new(),
+            dm_chat_id: 0,
             format: "[{name}] {message}".to_string(),
+            max_message_bytes: 600,
+            mesh_status: MeshStatus::default(),
+        }
+    }
+}
+
+impl TelegramBridgeConfig {
+    fn is_dm_chat(&self, chat_id: i64) -> bool {
+        self.dm_chat_id != 0 && chat_id == self.dm_chat_id
+    }
+
+    fn is_bridged(&self, chat_id: i64) -> bool {
+        self.is_dm_chat(chat_id) || self.mappings.iter().any(|m| m.chat_id == chat_id)
+    }
+
+    /// Mesh channel that messages from `chat_id` are sent to, if any.
+    fn mesh_channel_for(&self, chat_id: i64) -> Option<u32> {
+        self.mappings
+            .iter()
+            .find(|m| m.chat_id == chat_id && m.direction.forwards_to_mesh())
+            .map(|m| m.mesh_channel)
+    }
+
+    /// Chats a mesh message is relayed to.
+    fn targets(&self, msg: &MeshBridgeMessage) -> Vec<i64> {
+        let routes: Vec<(i64, u32)> = self
+            .mappings
+            .iter()
+            .filter(|m| m.direction.forwards_to_telegram())
+            .map(|m| (m.chat_id, m.mesh_channel))
+            .collect();
+        route_targets(&routes, msg)
+    }
+}
+
+/// Bot state read by the `/status` control panel.
+#[derive(Clone)]
+pub struct TelegramStatusSource {
+    pub db: Arc<Db>,
+    pub queue_depth: Arc<AtomicUsize>,
+}
+
+/// The views behind the control panel's inline keyboard buttons.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PanelView {
+    Nodes,
+    Queue,
+    Messages,
+}
+
+impl PanelView {
+    fn from_callback(data: &str) -> Option<Self> {
+        match data {
+            "panel:nodes" => Some(PanelView::Nodes),
+            "panel:queue" => Some(PanelView::Queue),
+            "panel:messages" => Some(PanelView::Messages),
+            _ => None,
+        }
+    }
+
+    fn keyboard() -> InlineKeyboardMarkup {
+        InlineKeyboardMarkup::new(vec![vec![
+            InlineKeyboardButton::callback("Refresh nodes", "panel:nodes"),
+            InlineKeyboardButton::callback("Show queue", "panel:queue"),
+            InlineKeyboardButton::callback("Last 5 messages", "panel:messages"),
+        ]])
+    }
+
+    /// Render the view as Telegram HTML. The timestamp keeps repeated presses
+    /// from producing an identical (and rejected) message edit.
+    async fn render(
+        &self,
+        status: &TelegramStatusSource,
+        now: DateTime<Local>,
+    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let ts = now.timestamp();
+        let mut lines = Vec::new();
+        match self {
+            PanelView::Nodes => {
+                let (count, nodes) = status
+                    .db
+                    .run(|db| {
+                        Ok((
+                            db.node_count()?,
+                            db.get_recent_nodes_with_last_hop(PANEL_NODES)?,
+                        ))
+                    })
+                    .await?;
+                lines.push(format!("<b>Nodes seen:</b> {}", count));
+                for node in nodes {
+                    let name = if node.long_name.is_empty() {
+                        format!("!{:08x}", node.node_id)
+                    } else {
+                        node.long_name.clone()
+                    };
+                    let hops = node
+                        .last_hop
+                        .map(|h| format!(", {} hop(s)", h))
+                        .unwrap_or_default();
+                    lines.push(format!(
+                        "{} ({}{})",
+                        escape_html(&name),
+                        format_ago(ts - node.last_seen),
+                        hops
+                    ));
+                }
+            }
+            PanelView::Queue => {
+                let depth = status.queue_depth.load(Ordering::Relaxed);
+                lines.push(format!(
+                    "<b>Outgoing queue:</b> {} message(s) waiting",
+                    depth
+                ));
+            }
+            PanelView::Messages => {
+                lines.push(format!("<b>Last {} messages</b>", PANEL_MESSAGES));
+                let messages = status
+                    .db
+                    .run(|db| db.recent_chat_messages(PANEL_MESSAGES))
+                    .await?;
+                if messages.is_empty() {
+                    lines.push("No messages yet.".to_string());
+                }
+                for m in messages.iter().rev() {
+                    lines.push(format!(
+                        "{} ({}): {}",
+                        escape_html(&m.from_name),
+                        format_ago(ts - m.timestamp),
+                        escape_html(&m.text)
+                    ));
+                }
+            }
         }
+        lines.push(format!("<i>Updated {}</i>", now.format("%H:%M:%S")));
+        Ok(lines.join("\n"))
     }
 }
 
+/// Whether a chat message is the `/status` command (optionally `/status@botname`).
+fn is_status_command(text: &str) -> bool {
+    text.split_whitespace()
+        .next()
+        .and_then(|cmd| cmd.split('@').next())
+        == Some("/status")
+}
+
 /// Telegram bridge instance.
 pub struct TelegramBridge {
     config: TelegramBridgeConfig,
     bot: Bot,
+    status: Option<TelegramStatusSource>,
+    log: Option<BridgeLog>,
 }
 
 fn render_mesh_message(format: &str, msg: &MeshBridgeMessage) -> String {
-    format
+    let text = format
         .replace("{name}", &msg.sender_name)
         .replace("{id}", &format!("!{:08x}", msg.sender_id))
         .replace("{message}", &msg.text)
-        .replace("{channel}", &msg.channel.to_string())
+        .replace("{channel}", &msg.channel.to_string());
+    match &msg.data {
+        Some(data) => format!("{}\n{}", text, data.render_html()),
+        None => text,
+    }
 }
 
 impl TelegramBridge {
     /// Create a new Telegram bridge with the given configuration.
     pub fn new(config: TelegramBridgeConfig) -> Self {
         let bot = Bot::new(&config.bot_token);
-        Self { config, bot }
+        Self {
+            config,
+            bot,
+            status: None,
+            log: None,
+        }
+    }
+
+    /// Enable the `/status` control panel in the bridged chat.
+    pub fn with_status_source(mut self, status: TelegramStatusSource) -> Self {
+        self.status = Some(status);
+        self
+    }
+
+    /// Record relayed mesh messages in the bridge log.
+    pub fn with_log(mut self, log: BridgeLog) -> Self {
+        self.log = Some(log);
+        self
     }
 
     /// Run the Telegram bridge.
@@ -93,28 +283,34 @@ impl TelegramBridge {
         outgoing_tx: OutgoingMessageSender,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         log::info!(
-            "Starting Telegram bridge (chat_id={}, direction={:?})",
-            self.config.chat_id,
-            self.config.direction
+            "Starting Telegram bridge ({} chat(s): {:?})",
+            self.config.mappings.len(),
+            self.config.mappings
         );
 
         let config = Arc::new(self.config);
         let bot = self.bot;
+        let status = self.status;
+        let log = self.log;
+        let mappings = &config.mappings;
 
         // Spawn mesh→telegram forwarder
-        if config.direction.forwards_to_telegram() {
+        if mappings.iter().any(|m| m.direction.forwards_to_telegram()) || config.dm_chat_id != 0 {
             let bot_clone = bot.clone();
             let config_clone = config.clone();
             let mesh_rx = mesh_rx;
 
             tokio::spawn(async move {
-                Self::mesh_to_telegram_task(bot_clone, config_clone, mesh_rx).await;
+                Self::mesh_to_telegram_task(bot_clone, config_clone, mesh_rx, log).await;
             });
         }
 
-        // Run telegram→mesh listener (this blocks)
-        if config.direction.forwards_to_mesh() {
-            Self::telegram_to_mesh_task(bot, config, outgoing_tx).await;
+        // Run the chat listener (this blocks): telegram→mesh and the control panel
+        if mappings.iter().any(|m| m.direction.forwards_to_mesh())
+            || config.dm_chat_id != 0
+            || status.is_some()
+        {
+            Self::telegram_listener_task(bot, config, outgoing_tx, status).await;
         } else {
             // If only mesh→telegram, just keep running
             loop {
@@ -130,33 +326,63 @@ impl TelegramBridge {
         bot: Bot,
         config: Arc<TelegramBridgeConfig>,
         mut mesh_rx: MeshMessageReceiver,
+        relay_log: Option<BridgeLog>,
     ) {
         log::info!("Mesh→Telegram forwarder started");
 
         loop {
             match mesh_rx.recv().await {
                 Ok(msg) => {
-                    // Only forward messages from the configured mesh channel
-                    // Channel 0 means "all channels"
-                    if config.mesh_channel != 0 && msg.channel != config.mesh_channel {
+                    // DMs passed through to the operator chat
+                    if let Some(text) = msg.dm_text() {
+                        if config.dm_chat_id != 0 {
+                            match bot.send_message(ChatId(config.dm_chat_id), text).await {
+                                Ok(_) => {
+                                    if let Some(relay_log) = &relay_log {
+                                        relay_log.relayed(&msg).await;
+                                    }
+                                }
+                                Err(e) => log::error!("Failed to send DM to Telegram: {}", e),
+                            }
+                        }
+                        continue;
+                    }
+
+                    // Node and traceroute events are Discord embeds only
+                    if !msg.is_plain_text() {
                         continue;
                     }
 
                     // Skip DMs (only bridge public messages)
-                    if msg.is_dm {
+                    if msg.is_dm && !msg.is_alert() {
                         continue;
                     }
 
-                    let text = render_mesh_message(&config.format, &msg);
+                    // Only forward to chats mapped to the message's mesh channel
+                    let targets = config.targets(&msg);
+                    if targets.is_empty() {
+                        continue;
+                    }
 
-                    log::debug!("Forwarding to Telegram: {}", text);
+                    let text = render_mesh_message(&config.format, &msg);
 
-                    if let Err(e) = bot
-                        .send_message(ChatId(config.chat_id), &text)
-                        .parse_mode(ParseMode::Html)
-                        .await
-                    {
-                        log::error!("Failed to send to Telegram: {}", e);
+                    log::debug!("Forwarding to Telegram {:?}: {}", targets, text);
+
+                    let mut delivered = false;
+                    for chat_id in targets {
+                        match bot
+                            .send_message(ChatId(chat_id), &text)
+                            .parse_mode(ParseMode::Html)
+                            .await
+                        {
+                            Ok(_) => delivered = true,
+                            Err(e) => log::error!("Failed to send to Telegram: {}", e),
+                        }
+                    }
+                    if delivered {
+                        if let Some(relay_log) = &relay_log {
+                            relay_log.relayed(&msg).await;
+                        }
                     }
                 }
                 Err(tokio::sync::broadcast::error::RecvError::Lagged(n)) => {
@@ -170,22 +396,74 @@ impl TelegramBridge {
         }
     }
 
-    /// Task that forwards Telegram messages to mesh.
-    async fn telegram_to_mesh_task(
+    /// Send the control panel (node view) in reply to `/status`.
+    async fn send_panel(bot: &Bot, chat_id: ChatId, status: &TelegramStatusSource) {
+        let text = match PanelView::Nodes.render(status, Local::now()).await {
+            Ok(text) => text,
+            Err(e) => {
+                log::error!("Failed to render Telegram panel: {}", e);
+                return;
+            }
+        };
+        if let Err(e) = bot
+            .send_message(chat_id, text)
+            .parse_mode(ParseMode::Html)
+            .reply_markup(PanelView::keyboard())
+            .await
+        {
+            log::error!("Failed to send Telegram panel: {}", e);
+        }
+    }
+
+    /// Handle a control panel button press by editing the panel in place.
+    async fn handle_panel_callback(
+        bot: Bot,
+        query: CallbackQuery,
+        config: Arc<TelegramBridgeConfig>,
+        status: Option<TelegramStatusSource>,
+    ) -> ResponseResult<()> {
+        let view = query.data.as_deref().and_then(PanelView::from_callback);
+        let panel = query
+            .message
+            .as_ref()
+            .filter(|m| config.is_bridged(m.chat().id.0));
+        if let (Some(view), Some(panel), Some(status)) = (view, panel, status.as_ref()) {
+            match view.render(status, Local::now()).await {
+                Ok(text) => {
+                    if let Err(e) = bot
+                        .edit_message_text(panel.chat().id, panel.id(), text)
+                        .parse_mode(ParseMode::Html)
+                        .reply_markup(PanelView::keyboard())
+                        .await
+                    {
+                        log::warn!("Failed to update Telegram panel: {}", e);
+                    }
+                }
+                Err(e) => log::error!("Failed to render Telegram panel: {}", e),
+            }
+        }
+        bot.answer_callback_query(query.id).await?;
+        Ok(())
+    }
+
+    /// Task that forwards Telegram messages to mesh and serves the control panel.
+    async fn telegram_listener_task(
         bot: Bot,
         config: Arc<TelegramBridgeConfig>,
         outgoing_tx: OutgoingMessageSender,
+        status: Option<TelegramStatusSource>,
     ) {
-        log::info!("Telegram→Mesh listener started");
+        log::info!("Telegram listener started");
 
         // Create a handler for incoming messages
-        let handler = Update::filter_message().endpoint(
-            move |_bot: Bot,
+        let message_handler = Update::filter_message().endpoint(
+            move |bot: Bot,
                   msg: Message,
                   config: Arc<TelegramBridgeConfig>,
-                  tx: mpsc::Sender<OutgoingBridgeMessage>| async move {
-                // Only process messages from the configured chat
-                if msg.chat.id.0 != config.chat_id {
+                  tx: mpsc::Sender<OutgoingBridgeMessage>,
+                  status: Option<TelegramStatusSource>| async move {
+                // Only process messages from bridged chats
+                if !config.is_bridged(msg.chat.id.0) {
                     return respond(());
                 }
 
@@ -195,6 +473,31 @@ impl TelegramBridge {
                     None => return respond(()), // Ignore non-text messages
                 };
 
+                if is_status_command(text) {
+                    if let Some(status) = &status {
+                        Self::send_panel(&bot, msg.chat.id, status).await;
+                    }
+                    return respond(());
+                }
+
+                // In the DM chat, replies go back to a passed-through DM's sender
+                let (text, mesh_channel, dm_session) = if config.is_dm_chat(msg.chat.id.0) {
+                    let replied_to = msg.reply_to_message().and_then(|m| m.text());
+                    let Some((session, body)) = parse_dm_reply(text, replied_to) else {
+                        let hint = "Reply to a DM, or start with its #number, to answer it.";
+                        if let Err(e) = bot.send_message(msg.chat.id, hint).await {
+                            log::error!("Failed to send Telegram feedback: {}", e);
+                        }
+                        return respond(());
+                    };
+                    (body, 0, Some(session))
+                } else {
+                    let Some(mesh_channel) = config.mesh_channel_for(msg.chat.id.0) else {
+                        return respond(());
+                    };
+                    (text.to_string(), mesh_channel, None)
+                };
+
                 // Get sender name
                 let sender_name = msg
                     .from
@@ -202,37 +505,50 @@ impl TelegramBridge {
                     .map(|u| u.username.clone().unwrap_or_else(|| u.first_name.clone()))
                     .unwrap_or_else(|| "unknown".to_string());
 
+                // Long messages are split by the bot; refuse ones over the cap
+                if let Err(feedback) = check_mesh_length(&text, config.max_message_bytes) {
+                    if let Err(e) = bot.send_message(msg.chat.id, feedback).await {
+                        log::error!("Failed to send Telegram feedback: {}", e);
+                    }
+                    return respond(());
+                }
+
                 // Format message for mesh
                 let mesh_text = format!("[TG:{}] {}", sender_name, text);
 
-                // Check message length (Meshtastic limit ~230 bytes)
-                let mesh_text = if mesh_text.len() > 220 {
-                    format!("{}...", &mesh_text[..217])
-                } else {
-                    mesh_text
-                };
-
                 log::debug!("Forwarding to mesh: {}", mesh_text);
 
                 // Send to mesh
                 if let Err(e) = tx
                     .send(OutgoingBridgeMessage {
                         text: mesh_text,
-                        channel: config.mesh_channel,
+                        channel: mesh_channel,
                         source: "telegram".to_string(),
+                        sender: sender_name,
+                        dm_session,
+                        to_node: None,
+                        received_at: Instant::now(),
                     })
                     .await
                 {
                     log::error!("Failed to send to mesh: {}", e);
+                } else if let Some(notice) = config.mesh_status.offline_notice() {
+                    if let Err(e) = bot.send_message(msg.chat.id, notice).await {
+                        log::error!("Failed to send Telegram feedback: {}", e);
+                    }
                 }
 
                 respond(())
             },
         );
 
+        let handler = dptree::entry()
+            .branch(message_handler)
+            .branch(Update::filter_callback_query().endpoint(Self::handle_panel_callback));
+
         // Build dispatcher with dependencies
         Dispatcher::builder(bot, handler)
-            .dependencies(dptree::deps![config, outgoing_tx])
+            .dependencies(dptree::deps![config, outgoing_tx, status])
             .enable_ctrlc_handler()
             .build()
             .dispatch()
@@ -243,6 +559,8 @@ impl TelegramBridge {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::bridge::BridgeEvent;
+    use crate::packet_type::PacketType;
 
     #[test]
     fn test_bridge_direction_from_str() {
@@ -286,6 +604,9 @@ mod tests {
             text: "Hello world".to_string(),
             channel: 0,
             is_dm: false,
+            event: BridgeEvent::Message,
+            data: None,
+            packet_id: None,
         };
 
         assert_eq!(
@@ -302,6 +623,9 @@ mod tests {
             text: "Test".to_string(),
             channel: 0,
             is_dm: false,
+            event: BridgeEvent::Message,
+            data: None,
+            packet_id: None,
         };
 
         assert_eq!(
@@ -309,4 +633,86 @@ mod tests {
             "!12345678 (Bob): Test"
         );
     }
+
+    #[test]
+    fn test_chat_mappings() {
+        let config = TelegramBridgeConfig {
+            mappings: vec![
+                ChatMapping {
+                    chat_id: -100,
+                    mesh_channel: 0,
+                    direction: BridgeDirection::Both,
+                },
+                ChatMapping {
+                    chat_id: -200,
+                    mesh_channel: 2,
+                    direction: BridgeDirection::ToTelegram,
+                },
+            ],
+            ..Default::default()
+        };
+        assert_eq!(config.mesh_channel_for(-100), Some(0));
+        assert_eq!(config.mesh_channel_for(-200), None);
+        assert!(config.is_bridged(-200));
+        assert!(!config.is_bridged(-300));
+
+        let msg = |channel: u32| MeshBridgeMessage {
+            sender_id: 0x12345678,
+            sender_name: "Bob".to_string(),
+            text: "Test".to_string(),
+            channel,
+            is_dm: false,
+            event: BridgeEvent::Message,
+            data: None,
+            packet_id: None,
+        };
+        assert_eq!(config.targets(&msg(2)), [-100, -200]);
+        assert_eq!(config.targets(&msg(1)), [-100]);
+    }
+
+    #[test]
+    fn test_status_command_and_callbacks() {
+        assert!(is_status_command("/status"));
+        assert!(is_status_command("/status@meshenger_bot now"));
+        assert!(!is_status_command("/statuses"));
+        assert!(!is_status_command("status"));
+        assert_eq!(
+            PanelView::from_callback("panel:queue"),
+            Some(PanelView::Queue)
+        );
+        assert_eq!(PanelView::from_callback("other"), None);
+    }
+
+    #[tokio::test]
+    async fn test_panel_views_render_escaped_html() {
+        let db = Db::open(std::path::Path::new(":memory:")).unwrap();
+        db.upsert_node(0x11111111, "A&B", "A&B <relay>", false)
+            .unwrap();
+        db.log_packet(
+            0x11111111,
+            None,
+            0,
+            "hi <all>",
+            "in",
+            false,
+            None,
+            None,
+            None,
+            None,
+            PacketType::Text,
+        )
+        .unwrap();
+        let status = TelegramStatusSource {
+            db: Arc::new(db),
+            queue_depth: Arc::new(AtomicUsize::new(3)),
+        };
+        let now = Local::now();
+
+        let nodes = PanelView::Nodes.render(&status, now).await.unwrap();
+        assert!(nodes.starts_with("<b>Nodes seen:</b> 1\nA&amp;B &lt;relay&gt; ("));
+        let queue = PanelView::Queue.render(&status, now).await.unwrap();
+        assert!(queue.starts_with("<b>Outgoing queue:</b> 3 message(s) waiting\n<i>Updated "));
+        let messages = PanelView::Messages.render(&status, now).await.unwrap();
+        assert!(messages.contains("\nA&amp;B &lt;relay&gt; (0s ago): hi &lt;all&gt;\n"));
+    }
 }
@@ -0,0 +1,287 @@
+//! Webhook bridge for Meshenger.
+//!
+//! POSTs mesh messages as JSON to configured URLs, and accepts messages for
+//! the mesh at the dashboard's `/api/bridge/send` endpoint. A catch-all for
+//! services the other bridges don't cover.
+
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+use crate::bridge::{
+    check_mesh_length, BridgeEvent, BridgeLog, MeshMessageReceiver, MeshStatus,
+    OutgoingBridgeMessage, OutgoingMessageSender,
+};
+
+/// Give up on a webhook URL that takes longer than this to answer.
+const POST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Direction of message bridging.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BridgeDirection {
+    /// Only POST mesh messages to the webhook URLs
+    ToWebhook,
+    /// Only accept messages for the mesh at `/api/bridge/send`
+    ToMesh,
+    /// Bidirectional bridging
+    Both,
+}
+
+impl BridgeDirection {
+    pub fn from_str(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "to_webhook" | "towebhook" | "mesh_to_webhook" => BridgeDirection::ToWebhook,
+            "to_mesh" | "tomesh" | "webhook_to_mesh" => BridgeDirection::ToMesh,
+            _ => BridgeDirection::Both,
+        }
+    }
+
+    pub fn forwards_to_webhook(&self) -> bool {
+        matches!(self, BridgeDirection::ToWebhook | BridgeDirection::Both)
+    }
+
+    pub fn forwards_to_mesh(&self) -> bool {
+        matches!(self, BridgeDirection::ToMesh | BridgeDirection::Both)
+    }
+}
+
+/// Configuration for the outbound side of the webhook bridge.
+#[derive(Debug, Clone, Default)]
+pub struct WebhookBridgeConfig {
+    /// Every mesh message is POSTed to each of these.
+    pub urls: Vec<String>,
+    /// Only forward this channel (0 = all channels).
+    pub mesh_channel: u32,
+}
+
+/// Webhook bridge instance (outbound POSTs).
+pub struct WebhookBridge {
+    config: WebhookBridgeConfig,
+    log: Option<BridgeLog>,
+}
+
+impl WebhookBridge {
+    /// Create a new webhook bridge with the given configuration.
+    pub fn new(config: WebhookBridgeConfig) -> Self {
+        Self { config, log: None }
+    }
+
+    /// Record relayed mesh messages in the bridge log.
+    pub fn with_log(mut self, log: BridgeLog) -> Self {
+        self.log = Some(log);
+        self
+    }
+
+    /// Run the webhook bridge.
+    pub async fn run(
+        self,
+        mut mesh_rx: MeshMessageReceiver,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let config = self.config;
+        log::info!("Starting webhook bridge ({} URL(s))", config.urls.len());
+        let client = reqwest::Client::builder().timeout(POST_TIMEOUT).build()?;
+
+        loop {
+            match mesh_rx.recv().await {
+                Ok(msg) => {
+                    // Channel 0 means "all channels"; alerts always pass
+                    if !msg.is_alert()
+                        && config.mesh_channel != 0
+                        && msg.channel != config.mesh_channel
+                    {
+                        continue;
+                    }
+
+                    // Skip DMs and node/traceroute events (only chat and alerts)
+                    if !msg.is_alert() && (msg.is_dm || msg.event != BridgeEvent::Message) {
+                        continue;
+                    }
+
+                    let payload = msg.to_json();
+                    let mut delivered = false;
+                    for url in &config.urls {
+                        match client.post(url).json(&payload).send().await {
+                            Ok(resp) if !resp.status().is_success() => {
+                                log::warn!("Webhook {} answered {}", url, resp.status());
+                            }
+                            Ok(_) => delivered = true,
+                            Err(e) => log::error!("Failed to POST to webhook {}: {}", url, e),
+                        }
+                    }
+                    if delivered {
+                        if let Some(relay_log) = &self.log {
+                            relay_log.relayed(&msg).await;
+                        }
+                    }
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(n)) => {
+                    log::warn!("Webhook bridge lagged, missed {} messages", n);
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => {
+                    log::info!("Mesh channel closed, stopping webhook bridge");
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Body of `POST /api/bridge/send`: `{"text": "...", "channel": 1, "name": "ci"}`.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SendRequest {
+    pub text: String,
+    /// Mesh channel; `[bridge.webhook] mesh_channel` when absent.
+    pub channel: Option<u32>,
+    /// Shown as `[WH:name]` on the mesh; "webhook" when absent.
+    pub name: Option<String>,
+}
+
+impl SendRequest {
+    /// Who the message is from, shown as `[WH:name]` on the mesh.
+    fn sender(&self) -> &str {
+        self.name.as_deref().unwrap_or("webhook")
+    }
+}
+
+/// Why a `/api/bridge/send` request was refused.
+#[derive(Debug, PartialEq)]
+pub enum SendError {
+    /// No text after trimming.
+    Empty,
+    /// Over `[bridge] max_message_bytes`; carries the feedback text.
+    TooLong(String),
+    /// The bot is no longer taking bridge messages.
+    Closed,
+}
+
+/// Inbound side of the webhook bridge, served by the dashboard.
+#[derive(Debug, Clone)]
+pub struct WebhookInbound {
+    /// Bearer token callers must present.
+    pub token: String,
+    /// Channel used when a request doesn't name one.
+    pub mesh_channel: u32,
+    /// Longest message injected into the mesh, 0 = no cap.
+    pub max_message_bytes: usize,
+    /// Whether the mesh is reachable, for the offline notice.
+    pub mesh_status: MeshStatus,
+    pub outgoing_tx: OutgoingMessageSender,
+}
+
+impl WebhookInbound {
+    /// Whether an `Authorization` header value carries the configured token.
+    pub fn authorized(&self, authorization: Option<&str>) -> bool {
+        !self.token.is_empty()
+            && authorization.and_then(|v| v.strip_prefix("Bearer ")) == Some(self.token.as_str())
+    }
+
+    /// Text (tagged `[WH:name]`) and channel for a request.
+    fn prepare(&self, req: &SendRequest) -> Result<(String, u32), SendError> {
+        let text = req.text.trim();
+        if text.is_empty() {
+            return Err(SendError::Empty);
+        }
+        let text = format!("[WH:{}] {}", req.sender(), text);
+        // Long messages are split by the bot; refuse ones over the cap
+        check_mesh_length(&text, self.max_message_bytes).map_err(SendError::TooLong)?;
+        Ok((text, req.channel.unwrap_or(self.mesh_channel)))
+    }
+
+    /// Queue a request's text for the mesh. Returns the offline notice, if any.
+    pub async fn send(&self, req: SendRequest) -> Result<Option<String>, SendError> {
+        let (text, channel) = self.prepare(&req)?;
+        log::debug!("Forwarding to mesh: {}", text);
+        self.outgoing_tx
+            .send(OutgoingBridgeMessage {
+                text,
+                channel,
+                source: "webhook".to_string(),
+                sender: req.sender().to_string(),
+                dm_session: None,
+                to_node: None,
+                received_at: Instant::now(),
+            })
+            .await
+            .map_err(|e| {
+                log::error!("Failed to send to mesh: {}", e);
+                SendError::Closed
+            })?;
+        Ok(self.mesh_status.offline_notice())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::create_bridge_channels;
+
+    fn inbound(outgoing_tx: OutgoingMessageSender) -> WebhookInbound {
+        WebhookInbound {
+            token: "secret".to_string(),
+            mesh_channel: 2,
+            max_message_bytes: 40,
+            mesh_status: MeshStatus::default(),
+            outgoing_tx,
+        }
+    }
+
+    #[test]
+    fn test_bridge_direction_from_str() {
+        assert_eq!(
+            BridgeDirection::from_str("to_webhook"),
+            BridgeDirection::ToWebhook
+        );
+        assert_eq!(
+            BridgeDirection::from_str("webhook_to_mesh"),
+            BridgeDirection::ToMesh
+        );
+        assert_eq!(BridgeDirection::from_str("unknown"), BridgeDirection::Both);
+        assert!(!BridgeDirection::ToWebhook.forwards_to_mesh());
+        assert!(!BridgeDirection::ToMesh.forwards_to_webhook());
+    }
+
+    #[test]
+    fn test_authorized() {
+        let (_mesh_tx, outgoing_tx, _outgoing_rx) = create_bridge_channels();
+        let mut inbound = inbound(outgoing_tx);
+        assert!(inbound.authorized(Some("Bearer secret")));
+        assert!(!inbound.authorized(Some("Bearer wrong")));
+        assert!(!inbound.authorized(Some("secret")));
+        assert!(!inbound.authorized(None));
+        inbound.token.clear();
+        assert!(!inbound.authorized(Some("Bearer ")));
+    }
+
+    #[tokio::test]
+    async fn test_send() {
+        let (_mesh_tx, outgoing_tx, mut outgoing_rx) = create_bridge_channels();
+        let inbound = inbound(outgoing_tx);
+
+        let notice = inbound
+            .send(SendRequest {
+                text: " build passed ".to_string(),
+                channel: None,
+                name: Some("ci".to_string()),
+            })
+            .await
+            .unwrap();
+        assert_eq!(notice, None);
+        let sent = outgoing_rx.recv().await.unwrap();
+        assert_eq!(sent.text, "[WH:ci] build passed");
+        assert_eq!(sent.channel, 2);
+        assert_eq!(sent.source, "webhook");
+
+        let req = |text: &str| SendRequest {
+            text: text.to_string(),
+            channel: Some(1),
+            name: None,
+        };
+        assert_eq!(inbound.send(req("  ")).await, Err(SendError::Empty));
+        assert!(matches!(
+            inbound.send(req(&"x".repeat(50))).await,
+            Err(SendError::TooLong(_))
+        ));
+    }
+}
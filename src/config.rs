@@ -2,95 +2,1150 @@ use serde::Deserialize;
 use std::collections::HashMap;
 use std::path::Path;
 
+use crate::packet_type::PacketType;
+
 #[derive(Debug, Deserialize)]
 pub struct Config {
-    pub connection: ConnectionConfig,
+    /// `[connection]`, or several `[[connection]]` radios. The first is the
+    /// primary: it runs maintenance, bridges and scheduled posts.
+    #[serde(rename = "connection", deserialize_with = "one_or_many_connections")]
+    pub connections: Vec<ConnectionConfig>,
     pub bot: BotConfig,
     pub welcome: WelcomeConfig,
     pub weather: WeatherConfig,
     #[serde(default)]
     pub traceroute_probe: TracerouteProbeConfig,
+    #[serde(default)]
+    pub traceroute_sessions: TracerouteSessionsConfig,
+    #[serde(default)]
+    pub traceroute_abuse: TracerouteAbuseConfig,
+    #[serde(default)]
+    pub battery_alerts: BatteryAlertsConfig,
+    #[serde(default)]
+    pub environment_alerts: Vec<EnvironmentAlertConfig>,
+    #[serde(default)]
+    pub geofence: Vec<GeofenceConfig>,
+    #[serde(default)]
+    pub alerts: Vec<AlertRuleConfig>,
+    #[serde(default)]
+    pub store_forward: StoreForwardConfig,
+    #[serde(default)]
+    pub remote_admin: RemoteAdminConfig,
     pub modules: HashMap<String, ModuleConfig>,
     #[serde(default)]
-    pub bridge: BridgeConfig,
+    pub bridge: BridgeConfig,
+    #[serde(default)]
+    pub dashboard: DashboardConfig,
+    #[serde(default)]
+    pub privacy: PrivacyConfig,
+    #[serde(default)]
+    pub db: DbConfig,
+    #[serde(default)]
+    pub mail: MailConfig,
+    #[serde(default)]
+    pub netlog: NetlogConfig,
+    #[serde(default)]
+    pub emergency: EmergencyConfig,
+    #[serde(default)]
+    pub startup_banner: StartupBannerConfig,
+    #[serde(default)]
+    pub help: HelpConfig,
+    #[serde(default)]
+    pub freshness: FreshnessConfig,
+    #[serde(default)]
+    pub selftest: SelfTestConfig,
+    /// `[[schedule]]` announcements broadcast on a cron-style schedule.
+    #[serde(default)]
+    pub schedule: Vec<ScheduleConfig>,
+    /// `[[beacons]]` fixed texts broadcast every few minutes or hours.
+    #[serde(default)]
+    pub beacons: Vec<BeaconConfig>,
+    /// `[[channel_budgets]]` caps on the bot's transmissions per channel.
+    #[serde(default)]
+    pub channel_budgets: Vec<ChannelBudgetConfig>,
+    #[serde(default)]
+    pub snapshot: SnapshotConfig,
+    #[serde(default)]
+    pub event_hooks: EventHooksConfig,
+    /// Daily call quotas for external APIs, keyed by API name (e.g. "weather").
+    /// APIs without an entry are counted but not limited.
+    #[serde(default)]
+    pub api_quotas: HashMap<String, u64>,
+}
+
+/// Language of the `!help` text.
+#[derive(Debug, Deserialize)]
+pub struct HelpConfig {
+    /// Translation used when `!help` is sent without a language.
+    #[serde(default = "default_help_language")]
+    pub language: String,
+    /// Help text per language code: module name -> description, plus
+    /// `no_commands` for the empty-registry reply. Missing entries fall back
+    /// to the module's own (English) description.
+    #[serde(default)]
+    pub translations: HashMap<String, HashMap<String, String>>,
+}
+
+impl Default for HelpConfig {
+    fn default() -> Self {
+        Self {
+            language: default_help_language(),
+            translations: HashMap::new(),
+        }
+    }
+}
+
+fn default_help_language() -> String {
+    "en".to_string()
+}
+
+/// Scheduled test broadcasts on a quiet channel, with a weekly report of
+/// which nodes acked or rebroadcast them.
+#[derive(Debug, Deserialize)]
+pub struct SelfTestConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub channel: u32,
+    /// Local send times, "HH:MM".
+    #[serde(default)]
+    pub times: Vec<String>,
+    /// Weekdays to send on ("mon", "tue", ...); empty means every day.
+    #[serde(default)]
+    pub days: Vec<String>,
+    #[serde(default = "default_selftest_message")]
+    pub message: String,
+    /// Acks and rebroadcasts heard this long after a test count for it.
+    #[serde(default = "default_selftest_window_secs")]
+    pub response_window_secs: u64,
+    /// Weekday the digest is relayed to the bridges; empty disables it.
+    #[serde(default = "default_selftest_digest_day")]
+    pub digest_day: String,
+    /// Local time of the digest, "HH:MM".
+    #[serde(default = "default_selftest_digest_time")]
+    pub digest_time: String,
+}
+
+impl Default for SelfTestConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            channel: 0,
+            times: Vec::new(),
+            days: Vec::new(),
+            message: default_selftest_message(),
+            response_window_secs: default_selftest_window_secs(),
+            digest_day: default_selftest_digest_day(),
+            digest_time: default_selftest_digest_time(),
+        }
+    }
+}
+
+fn default_selftest_message() -> String {
+    "Test transmission, no reply needed.".to_string()
+}
+
+fn default_selftest_window_secs() -> u64 {
+    300
+}
+
+fn default_selftest_digest_day() -> String {
+    "mon".to_string()
+}
+
+fn default_selftest_digest_time() -> String {
+    "09:00".to_string()
+}
+
+/// One `[[schedule]]` entry: `text` is broadcast on `channel` whenever the
+/// local time matches `cron` ("minute hour day month weekday").
+#[derive(Debug, Deserialize, Clone)]
+pub struct ScheduleConfig {
+    pub cron: String,
+    pub text: String,
+    #[serde(default)]
+    pub channel: u32,
+}
+
+/// One `[[beacons]]` entry: `text` is broadcast on `channel` every
+/// `interval_mins` (plus up to `jitter_pct` of it) while the local hour is
+/// in `start_hour..end_hour`, unless the bot's radio reports a busy channel.
+#[derive(Debug, Deserialize, Clone)]
+pub struct BeaconConfig {
+    pub text: String,
+    #[serde(default)]
+    pub channel: u32,
+    #[serde(default = "default_beacon_interval_mins")]
+    pub interval_mins: u64,
+    /// Fraction of the interval (0.0-1.0) randomly added to each wait, so
+    /// bots with the same beacons don't transmit in lockstep.
+    #[serde(default = "default_beacon_jitter_pct")]
+    pub jitter_pct: f64,
+    /// Local hour beacons start (inclusive, 0-23).
+    #[serde(default)]
+    pub start_hour: u32,
+    /// Local hour beacons stop (exclusive, 1-24). A window with
+    /// `end_hour <= start_hour` wraps past midnight.
+    #[serde(default = "default_beacon_end_hour")]
+    pub end_hour: u32,
+    /// Hold the beacon while the radio's last reported channel utilization
+    /// is at or above this percent (0 = never).
+    #[serde(default = "default_beacon_max_channel_utilization")]
+    pub max_channel_utilization: f64,
+    /// Hold the beacon while the radio's last reported share of airtime it
+    /// transmitted in the past hour is at or above this percent (0 = never).
+    #[serde(default = "default_beacon_max_air_util_tx")]
+    pub max_air_util_tx: f64,
+}
+
+fn default_beacon_interval_mins() -> u64 {
+    60
+}
+
+fn default_beacon_jitter_pct() -> f64 {
+    0.10
+}
+
+fn default_beacon_end_hour() -> u32 {
+    24
+}
+
+fn default_beacon_max_channel_utilization() -> f64 {
+    25.0
+}
+
+fn default_beacon_max_air_util_tx() -> f64 {
+    10.0
+}
+
+/// One `[[channel_budgets]]` entry: each radio sends at most `max_per_hour`
+/// messages on `channel` in any hour. Over budget, messages someone is
+/// waiting for (replies, DMs, bridged chat) wait for room and everything
+/// else (beacons, scheduled posts, probes) is dropped.
+#[derive(Debug, Deserialize, Clone)]
+pub struct ChannelBudgetConfig {
+    pub channel: u32,
+    pub max_per_hour: usize,
+}
+
+/// `[snapshot]`: a daily JSON file of the whole mesh (nodes, links, latest
+/// telemetry) for community map sites to fetch.
+#[derive(Debug, Deserialize)]
+pub struct SnapshotConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Where the snapshot is written. The previous ones move to `<path>.1`,
+    /// `<path>.2`, ...
+    #[serde(default = "default_snapshot_path")]
+    pub path: String,
+    /// Local time to write it, "HH:MM".
+    #[serde(default = "default_snapshot_time")]
+    pub time: String,
+    /// Older snapshots kept besides the current one.
+    #[serde(default = "default_snapshot_keep")]
+    pub keep: u32,
+    /// Nodes and links heard within this many hours.
+    #[serde(default = "default_snapshot_hours")]
+    pub hours: u32,
+    /// Decimal places kept in coordinates; unset keeps full precision.
+    #[serde(default)]
+    pub position_decimals: Option<u32>,
+}
+
+impl Default for SnapshotConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: default_snapshot_path(),
+            time: default_snapshot_time(),
+            keep: default_snapshot_keep(),
+            hours: default_snapshot_hours(),
+            position_decimals: None,
+        }
+    }
+}
+
+fn default_snapshot_path() -> String {
+    "mesh-snapshot.json".to_string()
+}
+
+fn default_snapshot_time() -> String {
+    "03:30".to_string()
+}
+
+fn default_snapshot_keep() -> u32 {
+    7
+}
+
+fn default_snapshot_hours() -> u32 {
+    7 * 24
+}
+
+/// `[event_hooks]`: JSON POSTs for key events (see `event_hooks`).
+#[derive(Debug, Deserialize)]
+pub struct EventHooksConfig {
+    /// Send `queue_overflow` when the outgoing queue reaches this depth.
+    #[serde(default = "default_queue_overflow_depth")]
+    pub queue_overflow_depth: usize,
+    #[serde(default)]
+    pub webhooks: Vec<EventWebhookConfig>,
+}
+
+impl Default for EventHooksConfig {
+    fn default() -> Self {
+        Self {
+            queue_overflow_depth: default_queue_overflow_depth(),
+            webhooks: Vec::new(),
+        }
+    }
+}
+
+fn default_queue_overflow_depth() -> usize {
+    50
+}
+
+/// One `[[event_hooks.webhooks]]` URL.
+#[derive(Debug, Deserialize, Clone)]
+pub struct EventWebhookConfig {
+    pub url: String,
+    /// Signs each body with HMAC-SHA256 when set.
+    #[serde(default)]
+    pub secret: String,
+    /// Event names to send; empty sends all of them.
+    #[serde(default)]
+    pub events: Vec<String>,
+    /// Further attempts after a failed delivery.
+    #[serde(default = "default_event_webhook_retries")]
+    pub max_retries: u32,
+}
+
+fn default_event_webhook_retries() -> u32 {
+    3
+}
+
+/// "Bot online" broadcast sent when the startup grace period ends.
+#[derive(Debug, Deserialize)]
+pub struct StartupBannerConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Placeholders: `{name}`, `{version}`, `{prefix}`.
+    #[serde(default = "default_banner_message")]
+    pub message: String,
+    #[serde(default)]
+    pub channel: u32,
+    /// Skip the banner if one was sent less than this many hours ago, so
+    /// reconnect loops don't flood the mesh.
+    #[serde(default = "default_banner_interval_hours")]
+    pub min_interval_hours: u64,
+}
+
+impl Default for StartupBannerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            message: default_banner_message(),
+            channel: 0,
+            min_interval_hours: default_banner_interval_hours(),
+        }
+    }
+}
+
+fn default_banner_message() -> String {
+    "{name} v{version} is online. Send {prefix}help for commands.".to_string()
+}
+
+fn default_banner_interval_hours() -> u64 {
+    6
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EmergencyConfig {
+    /// Case-insensitive words or phrases that raise an incident (empty = disabled).
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    /// Reply sent to the sender ahead of other queued messages (empty = no reply).
+    #[serde(default = "default_emergency_ack")]
+    pub ack_message: String,
+}
+
+impl Default for EmergencyConfig {
+    fn default() -> Self {
+        Self {
+            keywords: Vec::new(),
+            ack_message: default_emergency_ack(),
+        }
+    }
+}
+
+fn default_emergency_ack() -> String {
+    "Emergency alert received and relayed.".to_string()
+}
+
+impl EmergencyConfig {
+    /// The first configured keyword found in `text`. Single words must match a
+    /// whole word; phrases containing spaces match as a substring.
+    pub fn matched_keyword(&self, text: &str) -> Option<&str> {
+        let lower = text.to_lowercase();
+        let words: Vec<&str> = lower
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .collect();
+        self.keywords
+            .iter()
+            .find(|keyword| {
+                let keyword = keyword.trim().to_lowercase();
+                if keyword.is_empty() {
+                    false
+                } else if keyword.contains(' ') {
+                    lower.contains(&keyword)
+                } else {
+                    words.contains(&keyword.as_str())
+                }
+            })
+            .map(|k| k.as_str())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MailConfig {
+    /// Refuse new mail once a recipient has this many unread messages (0 = unlimited).
+    #[serde(default = "default_mail_max_unread")]
+    pub max_unread_per_recipient: usize,
+    #[serde(default = "default_mail_max_body_len")]
+    pub max_body_len: usize,
+    /// Unread mail older than this is dropped and its sender notified (0 = never).
+    #[serde(default = "default_mail_expire_days")]
+    pub expire_unread_after_days: u64,
+    /// Tell senders, the next time they are heard, that their mail was read.
+    #[serde(default)]
+    pub read_receipts: bool,
+}
+
+impl Default for MailConfig {
+    fn default() -> Self {
+        Self {
+            max_unread_per_recipient: default_mail_max_unread(),
+            max_body_len: default_mail_max_body_len(),
+            expire_unread_after_days: default_mail_expire_days(),
+            read_receipts: false,
+        }
+    }
+}
+
+fn default_mail_max_unread() -> usize {
+    10
+}
+
+fn default_mail_max_body_len() -> usize {
+    200
+}
+
+fn default_mail_expire_days() -> u64 {
+    30
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct NetlogConfig {
+    #[serde(default)]
+    pub nets: Vec<NetScheduleConfig>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct NetScheduleConfig {
+    pub name: String,
+    /// Weekdays the net runs ("mon", "tue", ...); empty means every day.
+    #[serde(default)]
+    pub days: Vec<String>,
+    /// Local start time, "HH:MM".
+    pub start: String,
+    #[serde(default = "default_net_duration_mins")]
+    pub duration_mins: u64,
+    /// Mesh channel the final roster is posted to.
+    #[serde(default)]
+    pub channel: u32,
+}
+
+fn default_net_duration_mins() -> u64 {
+    60
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PrivacyConfig {
+    /// Drop the text body of DMs between third parties on every channel.
+    #[serde(default)]
+    pub omit_dm_text: bool,
+    /// Drop the text body of third-party DMs only on these mesh channels.
+    #[serde(default)]
+    pub omit_dm_text_channels: Vec<u32>,
+    /// Round node coordinates served by the dashboard API to this many
+    /// decimals; the database keeps full precision.
+    #[serde(default)]
+    pub position_decimals: Option<u32>,
+    /// Days of position reports kept as movement tracks; 0 keeps none.
+    #[serde(default = "default_position_history_days")]
+    pub position_history_days: u64,
+}
+
+impl Default for PrivacyConfig {
+    fn default() -> Self {
+        Self {
+            omit_dm_text: false,
+            omit_dm_text_channels: Vec::new(),
+            position_decimals: None,
+            position_history_days: default_position_history_days(),
+        }
+    }
+}
+
+fn default_position_history_days() -> u64 {
+    7
+}
+
+impl PrivacyConfig {
+    pub fn omits_dm_text(&self, channel: u32) -> bool {
+        self.omit_dm_text || self.omit_dm_text_channels.contains(&channel)
+    }
+}
+
+/// `[db]`: how long logged packets are kept, and whether the file shrinks
+/// after pruning. Pruning runs with the hourly stale-node purge.
+#[derive(Debug, Deserialize, Default)]
+pub struct DbConfig {
+    /// Days packets are kept; 0 keeps them forever.
+    #[serde(default)]
+    pub packet_retention_days: u64,
+    /// Days kept per packet type (e.g. `position = 14`), overriding
+    /// `packet_retention_days`; 0 keeps that type forever.
+    #[serde(default)]
+    pub packet_type_retention_days: HashMap<PacketType, u64>,
+    /// Switch the database to `auto_vacuum = INCREMENTAL` (a one-time VACUUM
+    /// at startup) and hand pages freed by pruning back to the filesystem.
+    #[serde(default)]
+    pub incremental_vacuum: bool,
+}
+
+impl DbConfig {
+    /// Days `packet_type` is kept, 0 for forever.
+    pub fn packet_retention_days(&self, packet_type: PacketType) -> u64 {
+        self.packet_type_retention_days
+            .get(&packet_type)
+            .copied()
+            .unwrap_or(self.packet_retention_days)
+    }
+}
+
+/// `[freshness]`: how recently a node must have been heard to count as
+/// online, recent or stale; anything older is offline.
+#[derive(Debug, Deserialize, Clone)]
+pub struct FreshnessConfig {
+    #[serde(default = "default_freshness_online_mins")]
+    pub online_mins: u64,
+    #[serde(default = "default_freshness_recent_mins")]
+    pub recent_mins: u64,
+    #[serde(default = "default_freshness_stale_mins")]
+    pub stale_mins: u64,
+}
+
+impl Default for FreshnessConfig {
+    fn default() -> Self {
+        Self {
+            online_mins: default_freshness_online_mins(),
+            recent_mins: default_freshness_recent_mins(),
+            stale_mins: default_freshness_stale_mins(),
+        }
+    }
+}
+
+impl FreshnessConfig {
+    /// "online", "recent", "stale" or "offline" for a node last heard
+    /// `age_secs` ago.
+    pub fn classify(&self, age_secs: i64) -> &'static str {
+        let age_mins = age_secs.max(0) as u64 / 60;
+        if age_mins < self.online_mins {
+            "online"
+        } else if age_mins < self.recent_mins {
+            "recent"
+        } else if age_mins < self.stale_mins {
+            "stale"
+        } else {
+            "offline"
+        }
+    }
+}
+
+fn default_freshness_online_mins() -> u64 {
+    15
+}
+
+fn default_freshness_recent_mins() -> u64 {
+    2 * 60
+}
+
+fn default_freshness_stale_mins() -> u64 {
+    24 * 60
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DashboardConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_dashboard_bind")]
+    pub bind_address: String,
+    /// Second listener serving only read-only endpoints (overview, nodes,
+    /// positions) for the public; empty disables it.
+    #[serde(default)]
+    pub public_bind_address: String,
+    /// Round coordinates on the public listener to this many decimals.
+    #[serde(default)]
+    pub public_position_decimals: Option<u32>,
+    /// Bearer token for `/api/admin/*` endpoints. Admin endpoints are disabled when unset.
+    #[serde(default)]
+    pub admin_token: Option<String>,
+    /// Read-only database connections the dashboard queries share, so they
+    /// don't wait for packet logging or each other; 0 runs them on the
+    /// writer connection.
+    #[serde(default)]
+    pub read_connections: usize,
+    /// Deprecated: `true` is one read-only connection. Use `read_connections`.
+    #[serde(default)]
+    pub read_replica: Option<bool>,
+    /// Header title; empty shows "<bot name> Dashboard".
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub logo_url: String,
+    #[serde(default)]
+    pub footer: String,
+    /// Directory of extra static pages, served under `/pages/`.
+    #[serde(default)]
+    pub pages_dir: String,
+    /// Links shown in the dashboard header.
+    #[serde(default)]
+    pub links: Vec<DashboardLink>,
+    /// How long `/api/public-status` reuses (and lets clients cache) a
+    /// response.
+    #[serde(default = "default_public_status_cache_secs")]
+    pub public_status_cache_secs: u64,
+    /// Requests per minute `/api/public-status` answers, across all
+    /// clients; 0 = unlimited.
+    #[serde(default = "default_public_status_rate_limit")]
+    pub public_status_rate_limit: u32,
+    /// `/api/health` fails once no packet was received for this long;
+    /// 0 = don't check.
+    #[serde(default)]
+    pub health_max_packet_age_secs: u64,
+}
+
+impl DashboardConfig {
+    /// Read-only connections to open: `read_connections`, or one for the
+    /// deprecated `read_replica = true` when that is unset.
+    pub fn read_pool_size(&self) -> usize {
+        match self.read_replica {
+            Some(true) if self.read_connections == 0 => 1,
+            _ => self.read_connections,
+        }
+    }
+}
+
+/// `[[dashboard.links]]`
+#[derive(Debug, Deserialize, Clone)]
+pub struct DashboardLink {
+    pub title: String,
+    /// e.g. "/pages/about.html" or an external URL.
+    pub url: String,
+}
+
+impl Default for DashboardConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind_address: default_dashboard_bind(),
+            public_bind_address: String::new(),
+            public_position_decimals: None,
+            admin_token: None,
+            read_connections: 0,
+            read_replica: None,
+            title: String::new(),
+            logo_url: String::new(),
+            footer: String::new(),
+            pages_dir: String::new(),
+            links: Vec::new(),
+            public_status_cache_secs: default_public_status_cache_secs(),
+            public_status_rate_limit: default_public_status_rate_limit(),
+            health_max_packet_age_secs: 0,
+        }
+    }
+}
+
+fn default_public_status_cache_secs() -> u64 {
+    60
+}
+
+fn default_public_status_rate_limit() -> u32 {
+    120
+}
+
+fn default_dashboard_bind() -> String {
+    "0.0.0.0:9000".to_string()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TracerouteProbeConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_traceroute_interval_secs")]
+    pub interval_secs: u64,
+    #[serde(default = "default_traceroute_interval_jitter_pct")]
+    pub interval_jitter_pct: f64,
+    #[serde(default = "default_traceroute_recent_secs")]
+    pub recent_seen_within_secs: u64,
+    #[serde(default = "default_traceroute_cooldown_secs")]
+    pub per_node_cooldown_secs: u64,
+    #[serde(default = "default_traceroute_channel")]
+    pub mesh_channel: u32,
+}
+
+impl Default for TracerouteProbeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_secs: default_traceroute_interval_secs(),
+            interval_jitter_pct: default_traceroute_interval_jitter_pct(),
+            recent_seen_within_secs: default_traceroute_recent_secs(),
+            per_node_cooldown_secs: default_traceroute_cooldown_secs(),
+            mesh_channel: default_traceroute_channel(),
+        }
+    }
+}
+
+fn default_traceroute_interval_secs() -> u64 {
+    900
+}
+
+fn default_traceroute_interval_jitter_pct() -> f64 {
+    0.20
+}
+
+fn default_traceroute_recent_secs() -> u64 {
+    3600
+}
+
+fn default_traceroute_cooldown_secs() -> u64 {
+    21600
+}
+
+fn default_traceroute_channel() -> u32 {
+    0
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TracerouteSessionsConfig {
+    #[serde(default = "default_traceroute_session_expire_secs")]
+    pub expire_after_secs: u64,
+    #[serde(default = "default_traceroute_hop_retention_days")]
+    pub hop_retention_days: u64,
+}
+
+impl Default for TracerouteSessionsConfig {
+    fn default() -> Self {
+        Self {
+            expire_after_secs: default_traceroute_session_expire_secs(),
+            hop_retention_days: default_traceroute_hop_retention_days(),
+        }
+    }
+}
+
+fn default_traceroute_session_expire_secs() -> u64 {
+    3600
+}
+
+fn default_traceroute_hop_retention_days() -> u64 {
+    30
+}
+
+/// Flags nodes that flood the local node with traceroute requests.
+#[derive(Debug, Deserialize)]
+pub struct TracerouteAbuseConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Requests from one node within `window_hours` that flag it.
+    #[serde(default = "default_traceroute_abuse_threshold")]
+    pub threshold: u64,
+    #[serde(default = "default_traceroute_abuse_window_hours")]
+    pub window_hours: u32,
+    /// DM every `[bot] admin_nodes` entry when a node is flagged.
+    #[serde(default = "default_traceroute_abuse_notify")]
+    pub notify_admins: bool,
+    /// Ignore bot commands from a flagged node until the window has passed.
+    #[serde(default)]
+    pub ignore_commands: bool,
+}
+
+impl Default for TracerouteAbuseConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            threshold: default_traceroute_abuse_threshold(),
+            window_hours: default_traceroute_abuse_window_hours(),
+            notify_admins: default_traceroute_abuse_notify(),
+            ignore_commands: false,
+        }
+    }
+}
+
+fn default_traceroute_abuse_threshold() -> u64 {
+    20
+}
+
+fn default_traceroute_abuse_window_hours() -> u32 {
+    1
+}
+
+fn default_traceroute_abuse_notify() -> bool {
+    true
+}
+
+/// Alerts when a node's reported battery level drops below a threshold.
+#[derive(Debug, Deserialize)]
+pub struct BatteryAlertsConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_battery_low_pct")]
+    pub low_pct: u32,
+    #[serde(default = "default_battery_critical_pct")]
+    pub critical_pct: u32,
+    /// The battery must recover this far above a threshold before the alert
+    /// can fire again.
+    #[serde(default = "default_battery_hysteresis_pct")]
+    pub hysteresis_pct: u32,
+    /// Nodes to watch (hex `!c7d93f4a` or decimal IDs); empty watches all.
+    #[serde(default)]
+    pub nodes: Vec<String>,
+    /// DM every `[bot] admin_nodes` entry.
+    #[serde(default = "default_battery_notify")]
+    pub notify_admins: bool,
+    /// Relay the alert to the Telegram/Discord bridges.
+    #[serde(default = "default_battery_notify")]
+    pub notify_bridges: bool,
+}
+
+impl Default for BatteryAlertsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            low_pct: default_battery_low_pct(),
+            critical_pct: default_battery_critical_pct(),
+            hysteresis_pct: default_battery_hysteresis_pct(),
+            nodes: Vec::new(),
+            notify_admins: default_battery_notify(),
+            notify_bridges: default_battery_notify(),
+        }
+    }
+}
+
+fn default_battery_low_pct() -> u32 {
+    20
+}
+
+fn default_battery_critical_pct() -> u32 {
+    10
+}
+
+fn default_battery_hysteresis_pct() -> u32 {
+    5
+}
+
+fn default_battery_notify() -> bool {
+    true
+}
+
+/// `!sf` commands that list Store & Forward routers heard on the mesh and
+/// request their message history.
+#[derive(Debug, Deserialize)]
+pub struct StoreForwardConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// History window requested by `!sf history` without an argument.
+    #[serde(default = "default_store_forward_window_mins")]
+    pub window_mins: u32,
+    /// Only `[bot] admin_nodes` may request history (it costs airtime).
+    #[serde(default = "default_store_forward_admin_only")]
+    pub admin_only: bool,
+}
+
+impl Default for StoreForwardConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            window_mins: default_store_forward_window_mins(),
+            admin_only: default_store_forward_admin_only(),
+        }
+    }
+}
+
+fn default_store_forward_window_mins() -> u32 {
+    60
+}
+
+fn default_store_forward_admin_only() -> bool {
+    true
+}
+
+/// `[remote_admin]`: nodes `[bot] admin_nodes` may reboot or rename with
+/// `!admin node`. Each must list the bot's radio among its admin keys.
+#[derive(Debug, Deserialize, Default)]
+pub struct RemoteAdminConfig {
+    #[serde(default)]
+    pub nodes: Vec<String>,
+}
+
+/// `[[environment_alerts]]`: fires when a sensor reading crosses a threshold.
+#[derive(Debug, Deserialize, Clone)]
+pub struct EnvironmentAlertConfig {
+    pub name: String,
+    /// Node to watch (hex `!c7d93f4a` or decimal); empty watches every node.
+    #[serde(default)]
+    pub node: String,
+    /// "temperature" (°C), "humidity" (%) or "pressure" (hPa).
+    pub metric: String,
+    #[serde(default)]
+    pub below: Option<f64>,
+    #[serde(default)]
+    pub above: Option<f64>,
+    /// Any of "broadcast", "admins", "bridges".
+    #[serde(default = "default_environment_alert_notify")]
+    pub notify: Vec<String>,
+    /// Channel for the broadcast and admin DMs.
+    #[serde(default)]
+    pub channel: u32,
+    /// Minimum time between two alerts of this rule for the same node.
+    #[serde(default = "default_environment_alert_cooldown_mins")]
+    pub cooldown_mins: u64,
+}
+
+fn default_environment_alert_notify() -> Vec<String> {
+    vec!["admins".to_string()]
+}
+
+fn default_environment_alert_cooldown_mins() -> u64 {
+    60
+}
+
+/// `[[geofence]]`: a circle that reports nodes entering and leaving it.
+#[derive(Debug, Deserialize, Clone)]
+pub struct GeofenceConfig {
+    pub name: String,
+    pub lat: f64,
+    pub lon: f64,
+    pub radius_m: f64,
+    /// Nodes to watch (hex `!c7d93f4a` or decimal); empty watches every node.
+    #[serde(default)]
+    pub nodes: Vec<String>,
+    /// Any of "broadcast", "admins", "bridges".
+    #[serde(default = "default_geofence_notify")]
+    pub notify: Vec<String>,
+    /// Channel for the broadcast and admin DMs.
+    #[serde(default)]
+    pub channel: u32,
+}
+
+fn default_geofence_notify() -> Vec<String> {
+    vec!["admins".to_string()]
+}
+
+/// `[[alerts]]`: a condition checked against the database every minute. It
+/// notifies once when it starts to hold, and again only after it cleared.
+#[derive(Debug, Deserialize, Clone)]
+pub struct AlertRuleConfig {
+    pub name: String,
+    /// "node_silent", "battery_low", "channel_utilization", "new_node",
+    /// "mesh_quiet" or "channel_quiet".
+    pub condition: String,
+    /// Node to watch (hex `!c7d93f4a` or decimal). Required for
+    /// "node_silent"; empty watches every node for the other node rules.
+    #[serde(default)]
+    pub node: String,
+    /// Hours for "node_silent" and "mesh_quiet", percent for "battery_low"
+    /// and "channel_utilization", the fewest text messages expected per
+    /// window for "channel_quiet"; not used by "new_node".
+    #[serde(default)]
+    pub threshold: Option<f64>,
+    /// Mesh channel "channel_quiet" counts messages on (required for it).
+    #[serde(default)]
+    pub watch_channel: Option<u32>,
+    /// Hours of traffic "channel_quiet" counts.
+    #[serde(default = "default_alert_window_hours")]
+    pub window_hours: u32,
+    /// Any of "broadcast", "admins", "bridges", "webhooks".
+    #[serde(default = "default_alert_notify")]
+    pub notify: Vec<String>,
+    /// Channel for the broadcast and admin DMs.
+    #[serde(default)]
+    pub channel: u32,
+}
+
+fn default_alert_notify() -> Vec<String> {
+    vec!["admins".to_string()]
+}
+
+fn default_alert_window_hours() -> u32 {
+    24
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BridgeConfig {
+    pub telegram: Option<TelegramConfig>,
+    pub discord: Option<DiscordConfig>,
+    pub mqtt: Option<MqttConfig>,
+    pub webhook: Option<WebhookConfig>,
+    pub signal: Option<SignalConfig>,
+    /// Redaction/blocking rules applied to mesh text before it reaches any bridge.
+    #[serde(default)]
+    pub rules: Vec<BridgeRuleConfig>,
+    /// Chat messages longer than this (in bytes) are refused instead of being
+    /// split into many mesh messages. 0 disables the cap.
+    #[serde(default = "default_bridge_max_message_bytes")]
+    pub max_message_bytes: usize,
+    /// Chat messages still waiting for the radio after this long are dropped
+    /// instead of sent as a stale flood on reconnect. 0 keeps them forever.
+    #[serde(default = "default_bridge_max_queue_age_secs")]
+    pub max_queue_age_secs: u64,
+    /// Tell chat users when their message is waiting for the mesh to reconnect.
+    #[serde(default)]
+    pub offline_reply: bool,
+    #[serde(default)]
+    pub links: LinkShortenerConfig,
+    #[serde(default)]
+    pub node_events: NodeEventsConfig,
+    /// Days relayed messages are kept in the bridge log; 0 disables it.
+    #[serde(default = "default_bridge_log_days")]
+    pub log_days: u64,
+}
+
+/// `[bridge.node_events]`: post RF nodes joining the mesh or going silent
+/// to the chats, batched.
+#[derive(Debug, Deserialize, Clone)]
+pub struct NodeEventsConfig {
+    /// Post nodes heard for the first time.
+    #[serde(default)]
+    pub joined: bool,
+    /// Post nodes not heard for this many hours (0 = off).
     #[serde(default)]
-    pub dashboard: DashboardConfig,
+    pub offline_hours: u32,
+    /// Events are collected and posted together at most this often.
+    #[serde(default = "default_node_events_batch_secs")]
+    pub batch_secs: u64,
+    /// Mesh channel whose bridged chats get the posts.
+    #[serde(default)]
+    pub channel: u32,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct DashboardConfig {
-    #[serde(default)]
-    pub enabled: bool,
-    #[serde(default = "default_dashboard_bind")]
-    pub bind_address: String,
+impl NodeEventsConfig {
+    pub fn enabled(&self) -> bool {
+        self.joined || self.offline_hours > 0
+    }
 }
 
-impl Default for DashboardConfig {
+impl Default for NodeEventsConfig {
     fn default() -> Self {
         Self {
-            enabled: false,
-            bind_address: default_dashboard_bind(),
+            joined: false,
+            offline_hours: 0,
+            batch_secs: default_node_events_batch_secs(),
+            channel: 0,
         }
     }
 }
 
-fn default_dashboard_bind() -> String {
-    "0.0.0.0:9000".to_string()
+fn default_node_events_batch_secs() -> u64 {
+    300
 }
 
-#[derive(Debug, Deserialize)]
-pub struct TracerouteProbeConfig {
+/// Shorten long URLs in chat messages relayed to the mesh to
+/// `<base_url>/l/<code>`, resolved by the dashboard.
+#[derive(Debug, Deserialize, Clone)]
+pub struct LinkShortenerConfig {
     #[serde(default)]
     pub enabled: bool,
-    #[serde(default = "default_traceroute_interval_secs")]
-    pub interval_secs: u64,
-    #[serde(default = "default_traceroute_interval_jitter_pct")]
-    pub interval_jitter_pct: f64,
-    #[serde(default = "default_traceroute_recent_secs")]
-    pub recent_seen_within_secs: u64,
-    #[serde(default = "default_traceroute_cooldown_secs")]
-    pub per_node_cooldown_secs: u64,
-    #[serde(default = "default_traceroute_channel")]
-    pub mesh_channel: u32,
+    /// Public address of the dashboard, e.g. "http://mesh.example.org:9000".
+    #[serde(default)]
+    pub base_url: String,
+    /// Only URLs at least this long are shortened.
+    #[serde(default = "default_link_min_length")]
+    pub min_length: usize,
 }
 
-impl Default for TracerouteProbeConfig {
+impl Default for LinkShortenerConfig {
     fn default() -> Self {
         Self {
             enabled: false,
-            interval_secs: default_traceroute_interval_secs(),
-            interval_jitter_pct: default_traceroute_interval_jitter_pct(),
-            recent_seen_within_secs: default_traceroute_recent_secs(),
-            per_node_cooldown_secs: default_traceroute_cooldown_secs(),
-            mesh_channel: default_traceroute_channel(),
+            base_url: String::new(),
+            min_length: default_link_min_length(),
         }
     }
 }
 
-fn default_traceroute_interval_secs() -> u64 {
+fn default_link_min_length() -> usize {
+    40
+}
+
+fn default_bridge_max_message_bytes() -> usize {
+    600
+}
+
+fn default_bridge_max_queue_age_secs() -> u64 {
     900
 }
 
-fn default_traceroute_interval_jitter_pct() -> f64 {
-    0.20
+fn default_bridge_log_days() -> u64 {
+    30
 }
 
-fn default_traceroute_recent_secs() -> u64 {
-    3600
+impl BridgeConfig {
+    /// Whether an enabled bridge has an operator chat for DM passthrough.
+    pub fn dm_passthrough(&self) -> bool {
+        let telegram = self.telegram.as_ref();
+        let discord = self.discord.as_ref();
+        telegram.is_some_and(|t| t.enabled && t.dm_chat_id != 0)
+            || discord.is_some_and(|d| d.enabled && d.dm_channel_id != 0)
+    }
 }
 
-fn default_traceroute_cooldown_secs() -> u64 {
-    21600
+impl Default for BridgeConfig {
+    fn default() -> Self {
+        Self {
+            telegram: None,
+            discord: None,
+            mqtt: None,
+            webhook: None,
+            signal: None,
+            rules: Vec::new(),
+            max_message_bytes: default_bridge_max_message_bytes(),
+            max_queue_age_secs: default_bridge_max_queue_age_secs(),
+            offline_reply: false,
+            links: LinkShortenerConfig::default(),
+            node_events: NodeEventsConfig::default(),
+            log_days: default_bridge_log_days(),
+        }
+    }
 }
 
-fn default_traceroute_channel() -> u32 {
-    0
+#[derive(Debug, Deserialize, Clone)]
+pub struct BridgeRuleConfig {
+    pub pattern: String,
+    /// "redact" (replace matches) or "block" (drop the whole message)
+    #[serde(default = "default_bridge_rule_action")]
+    pub action: String,
+    #[serde(default = "default_bridge_rule_replacement")]
+    pub replacement: String,
 }
 
-#[derive(Debug, Deserialize, Default)]
-pub struct BridgeConfig {
-    pub telegram: Option<TelegramConfig>,
-    pub discord: Option<DiscordConfig>,
+fn default_bridge_rule_action() -> String {
+    "redact".to_string()
+}
+
+fn default_bridge_rule_replacement() -> String {
+    "[redacted]".to_string()
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -98,6 +1153,8 @@ pub struct TelegramConfig {
     #[serde(default)]
     pub enabled: bool,
     pub bot_token: String,
+    /// Chat bridged to `mesh_channel`; 0 when only `mappings` are used.
+    #[serde(default)]
     pub chat_id: i64,
     #[serde(default)]
     pub mesh_channel: u32,
@@ -105,6 +1162,34 @@ pub struct TelegramConfig {
     pub direction: String,
     #[serde(default = "default_telegram_format")]
     pub format: String,
+    /// Further chats, each bridged to its own mesh channel.
+    #[serde(default)]
+    pub mappings: Vec<TelegramMappingConfig>,
+    /// Private chat that DMs to the bot are passed through to (0 = off).
+    #[serde(default)]
+    pub dm_chat_id: i64,
+}
+
+/// `[[bridge.telegram.mappings]]`
+#[derive(Debug, Deserialize, Clone)]
+pub struct TelegramMappingConfig {
+    pub chat_id: i64,
+    #[serde(default)]
+    pub mesh_channel: u32,
+    #[serde(default = "default_bridge_direction")]
+    pub direction: String,
+}
+
+impl TelegramConfig {
+    /// The top-level chat (if set) followed by `mappings`.
+    pub fn all_mappings(&self) -> Vec<TelegramMappingConfig> {
+        let top = (self.chat_id != 0).then(|| TelegramMappingConfig {
+            chat_id: self.chat_id,
+            mesh_channel: self.mesh_channel,
+            direction: self.direction.clone(),
+        });
+        top.into_iter().chain(self.mappings.clone()).collect()
+    }
 }
 
 fn default_bridge_direction() -> String {
@@ -124,6 +1209,8 @@ pub struct DiscordConfig {
     #[serde(default)]
     pub enabled: bool,
     pub bot_token: String,
+    /// Channel bridged to `mesh_channel`; 0 when only `mappings` are used.
+    #[serde(default)]
     pub channel_id: u64,
     #[serde(default)]
     pub mesh_channel: u32,
@@ -131,19 +1218,253 @@ pub struct DiscordConfig {
     pub direction: String,
     #[serde(default = "default_discord_format")]
     pub format: String,
+    /// Send these event types as embeds, keyed by event name
+    /// ("message", "emergency", "node_joined", "traceroute").
+    #[serde(default)]
+    pub embeds: HashMap<String, DiscordEmbedConfig>,
+    /// Further channels, each bridged to its own mesh channel.
+    #[serde(default)]
+    pub mappings: Vec<DiscordMappingConfig>,
+    /// Private channel that DMs to the bot are passed through to (0 = off).
+    #[serde(default)]
+    pub dm_channel_id: u64,
+}
+
+/// `[[bridge.discord.mappings]]`
+#[derive(Debug, Deserialize, Clone)]
+pub struct DiscordMappingConfig {
+    pub channel_id: u64,
+    #[serde(default)]
+    pub mesh_channel: u32,
+    #[serde(default = "default_bridge_direction")]
+    pub direction: String,
+}
+
+impl DiscordConfig {
+    /// The top-level channel (if set) followed by `mappings`.
+    pub fn all_mappings(&self) -> Vec<DiscordMappingConfig> {
+        let top = (self.channel_id != 0).then(|| DiscordMappingConfig {
+            channel_id: self.channel_id,
+            mesh_channel: self.mesh_channel,
+            direction: self.direction.clone(),
+        });
+        top.into_iter().chain(self.mappings.clone()).collect()
+    }
+}
+
+/// `[bridge.mqtt]`: mesh messages as JSON on a broker, for home automation.
+#[derive(Debug, Deserialize, Clone)]
+pub struct MqttConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// `mqtt://host:port` or `mqtts://host:port`.
+    #[serde(default = "default_mqtt_bridge_broker")]
+    pub broker: String,
+    #[serde(default)]
+    pub username: String,
+    #[serde(default)]
+    pub password: String,
+    #[serde(default = "default_mqtt_publish_topic")]
+    pub publish_topic: String,
+    #[serde(default = "default_mqtt_command_topic")]
+    pub command_topic: String,
+    #[serde(default)]
+    pub mesh_channel: u32,
+    #[serde(default = "default_bridge_direction")]
+    pub direction: String,
+}
+
+/// `[bridge.webhook]`: mesh messages POSTed as JSON to URLs, and messages
+/// accepted for the mesh at the dashboard's `/api/bridge/send`.
+#[derive(Debug, Deserialize, Clone)]
+pub struct WebhookConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub urls: Vec<String>,
+    /// Bearer token for `/api/bridge/send`. The endpoint is disabled when empty.
+    #[serde(default)]
+    pub token: String,
+    #[serde(default)]
+    pub mesh_channel: u32,
+    #[serde(default = "default_bridge_direction")]
+    pub direction: String,
+}
+
+/// `[bridge.signal]`: a Signal group bridged through signal-cli-rest-api.
+#[derive(Debug, Deserialize, Clone)]
+pub struct SignalConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_signal_api_url")]
+    pub api_url: String,
+    /// Phone number registered with signal-cli.
+    pub number: String,
+    /// "group.…" ID from signal-cli-rest-api's group list.
+    pub group_id: String,
+    #[serde(default)]
+    pub mesh_channel: u32,
+    #[serde(default = "default_bridge_direction")]
+    pub direction: String,
+    #[serde(default = "default_signal_format")]
+    pub format: String,
+}
+
+fn default_signal_api_url() -> String {
+    "http://localhost:8080".to_string()
+}
+
+fn default_signal_format() -> String {
+    "[{name}] {message}".to_string()
+}
+
+fn default_mqtt_bridge_broker() -> String {
+    "mqtt://localhost:1883".to_string()
+}
+
+fn default_mqtt_publish_topic() -> String {
+    "meshenger/messages".to_string()
+}
+
+fn default_mqtt_command_topic() -> String {
+    "meshenger/send".to_string()
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct DiscordEmbedConfig {
+    /// Embed title; empty uses a default for the event type.
+    #[serde(default)]
+    pub title: String,
+    /// Side bar color as "#rrggbb".
+    #[serde(default)]
+    pub color: String,
+}
+
+fn one_or_many_connections<'de, D>(deserializer: D) -> Result<Vec<ConnectionConfig>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(ConnectionConfig),
+        Many(Vec<ConnectionConfig>),
+    }
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(connection) => vec![connection],
+        OneOrMany::Many(connections) => connections,
+    })
 }
 
 #[derive(Debug, Deserialize)]
 pub struct ConnectionConfig {
+    /// Label for this radio in logs and the `packets.radio` column; defaults
+    /// to the address (or broker in "mqtt" mode).
+    #[serde(default)]
+    pub name: String,
+    /// "tcp" (a Meshtastic node), "mqtt" (a Meshtastic MQTT broker) or
+    /// "both" (the node, plus extra traffic from the broker).
+    #[serde(default = "default_connection_mode")]
+    pub mode: String,
+    /// TCP address of the node; unused in "mqtt" mode.
+    #[serde(default)]
     pub address: String,
     #[serde(default = "default_reconnect_delay")]
     pub reconnect_delay_secs: u64,
+    #[serde(default)]
+    pub mqtt: MqttConnectionConfig,
+}
+
+impl ConnectionConfig {
+    pub fn uses_tcp(&self) -> bool {
+        !self.mode.eq_ignore_ascii_case("mqtt")
+    }
+
+    pub fn uses_mqtt(&self) -> bool {
+        self.mode.eq_ignore_ascii_case("mqtt") || self.mode.eq_ignore_ascii_case("both")
+    }
+
+    pub fn label(&self) -> &str {
+        if !self.name.is_empty() {
+            &self.name
+        } else if self.uses_tcp() {
+            &self.address
+        } else {
+            &self.mqtt.broker
+        }
+    }
+}
+
+fn default_connection_mode() -> String {
+    "tcp".to_string()
 }
 
 fn default_reconnect_delay() -> u64 {
     5
 }
 
+/// `[connection.mqtt]`: a Meshtastic MQTT broker to ingest traffic from.
+#[derive(Debug, Deserialize)]
+pub struct MqttConnectionConfig {
+    /// `mqtt://host:port` or `mqtts://host:port`.
+    #[serde(default = "default_mqtt_broker")]
+    pub broker: String,
+    #[serde(default)]
+    pub username: String,
+    #[serde(default)]
+    pub password: String,
+    /// Topic root the mesh publishes under, e.g. `msh/US`.
+    #[serde(default = "default_mqtt_topic_root")]
+    pub topic_root: String,
+    /// Node ID the bot uses on MQTT: as its own ID in "mqtt" mode and as the
+    /// gateway ID when publishing.
+    #[serde(default)]
+    pub node_id: String,
+    /// Channels to decrypt; the position in the list is the channel index.
+    #[serde(default = "default_mqtt_channels")]
+    pub channels: Vec<MqttChannelConfig>,
+}
+
+impl Default for MqttConnectionConfig {
+    fn default() -> Self {
+        Self {
+            broker: default_mqtt_broker(),
+            username: String::new(),
+            password: String::new(),
+            topic_root: default_mqtt_topic_root(),
+            node_id: String::new(),
+            channels: default_mqtt_channels(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct MqttChannelConfig {
+    pub name: String,
+    /// Base64 PSK as shown in the Meshtastic apps; `AQ==` is the default key.
+    #[serde(default = "default_mqtt_channel_key")]
+    pub key: String,
+}
+
+fn default_mqtt_broker() -> String {
+    "mqtt://mqtt.meshtastic.org:1883".to_string()
+}
+
+fn default_mqtt_topic_root() -> String {
+    "msh/US".to_string()
+}
+
+fn default_mqtt_channel_key() -> String {
+    "AQ==".to_string()
+}
+
+fn default_mqtt_channels() -> Vec<MqttChannelConfig> {
+    vec![MqttChannelConfig {
+        name: "LongFast".to_string(),
+        key: default_mqtt_channel_key(),
+    }]
+}
+
 #[derive(Debug, Deserialize)]
 #[allow(dead_code)]
 pub struct BotConfig {
@@ -162,6 +1483,35 @@ pub struct BotConfig {
     pub max_message_len: usize,
     #[serde(default = "default_startup_grace_secs")]
     pub startup_grace_secs: u64,
+    /// Nodes allowed to run admin commands (hex `!c7d93f4a` or decimal IDs).
+    #[serde(default)]
+    pub admin_nodes: Vec<String>,
+    /// Reply "did you mean ...?" to a mistyped command instead of ignoring it.
+    #[serde(default)]
+    pub suggest_commands: bool,
+    /// Answer commands sent on a channel on that channel instead of by DM.
+    #[serde(default)]
+    pub public_replies: bool,
+    /// Commands heard via MQTT: "reply" (like any other), "dm" (only ever
+    /// answered by DM) or "ignore".
+    #[serde(default = "default_mqtt_commands")]
+    pub mqtt_commands: String,
+    /// Keep at most this many nodes, evicting the least recently seen
+    /// MQTT-only ones without notes (0 = unlimited).
+    #[serde(default)]
+    pub max_nodes: usize,
+    /// Resend an unacknowledged DM up to this many times, when its
+    /// destination is next heard (0 = off).
+    #[serde(default = "default_dm_retries")]
+    pub dm_retries: u32,
+}
+
+fn default_dm_retries() -> u32 {
+    3
+}
+
+fn default_mqtt_commands() -> String {
+    "reply".to_string()
 }
 
 fn default_rate_limit_commands() -> usize {
@@ -201,6 +1551,53 @@ pub struct WelcomeConfig {
     pub absence_threshold_hours: u64,
     #[serde(default)]
     pub whitelist: Vec<String>,
+    /// Alternative messages by time of day and RF/MQTT source; first match wins,
+    /// otherwise `message` / `welcome_back_message` are used.
+    #[serde(default)]
+    pub rules: Vec<WelcomeRuleConfig>,
+    /// `[start_hour, end_hour]` in local time during which nobody is greeted,
+    /// read like a rule's window; empty for none.
+    #[serde(default)]
+    pub quiet_hours: Vec<u32>,
+}
+
+/// Check `[welcome] quiet_hours`: empty, or a start hour (0-23) and a
+/// different end hour (1-24).
+pub fn validate_quiet_hours(hours: &[u32]) -> Result<(), String> {
+    match hours {
+        [] => Ok(()),
+        [start, end] if *start < 24 && (1..=24).contains(end) && start != end => Ok(()),
+        _ => Err(format!(
+            "welcome.quiet_hours must be [] or [start_hour, end_hour] with start 0-23, end 1-24 and different, got {:?}",
+            hours
+        )),
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct WelcomeRuleConfig {
+    pub message: String,
+    /// Local hour the rule starts applying (inclusive, 0-23).
+    #[serde(default)]
+    pub start_hour: u32,
+    /// Local hour the rule stops applying (exclusive, 1-24). A window with
+    /// `end_hour <= start_hour` wraps past midnight.
+    #[serde(default = "default_welcome_rule_end_hour")]
+    pub end_hour: u32,
+    /// "rf", "mqtt" or "any"
+    #[serde(default = "default_welcome_rule_match")]
+    pub source: String,
+    /// "new", "back" or "any"
+    #[serde(default = "default_welcome_rule_match")]
+    pub kind: String,
+}
+
+fn default_welcome_rule_end_hour() -> u32 {
+    24
+}
+
+fn default_welcome_rule_match() -> String {
+    "any".to_string()
 }
 
 #[derive(Debug, Deserialize)]
@@ -208,6 +1605,42 @@ pub struct WeatherConfig {
     pub latitude: f64,
     pub longitude: f64,
     pub units: String,
+    #[serde(default)]
+    pub alerts: WeatherAlertsConfig,
+}
+
+/// Broadcasts severe weather alerts for the `[weather]` location. Alerts
+/// come from the US National Weather Service, so this only works in the US.
+#[derive(Debug, Deserialize)]
+pub struct WeatherAlertsConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_weather_alerts_interval_mins")]
+    pub interval_mins: u64,
+    #[serde(default)]
+    pub channel: u32,
+    /// Lowest severity broadcast: "minor", "moderate", "severe" or "extreme"
+    #[serde(default = "default_weather_alerts_min_severity")]
+    pub min_severity: String,
+}
+
+impl Default for WeatherAlertsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_mins: default_weather_alerts_interval_mins(),
+            channel: 0,
+            min_severity: default_weather_alerts_min_severity(),
+        }
+    }
+}
+
+fn default_weather_alerts_interval_mins() -> u64 {
+    10
+}
+
+fn default_weather_alerts_min_severity() -> String {
+    "severe".to_string()
 }
 
 #[derive(Debug, Deserialize)]
@@ -215,12 +1648,161 @@ pub struct WeatherConfig {
 pub struct ModuleConfig {
     pub enabled: bool,
     pub scope: String,
+    /// Per-node command limit for this module (0 = unlimited); replaces the
+    /// global `bot.rate_limit_commands` and any limit the module declares.
+    #[serde(default)]
+    pub rate_limit: Option<usize>,
+    #[serde(default)]
+    pub rate_limit_window_secs: Option<u64>,
+    /// Overrides `bot.public_replies` for this module's commands.
+    #[serde(default)]
+    pub public_replies: Option<bool>,
 }
 
 impl Config {
+    /// Daily quota for an external API (0 = unlimited).
+    pub fn api_quota(&self, api: &str) -> u64 {
+        self.api_quotas.get(api).copied().unwrap_or(0)
+    }
+
     pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
         let content = std::fs::read_to_string(path)?;
         let config: Config = toml::from_str(&content)?;
+        if config.connections.is_empty() {
+            return Err("at least one [[connection]] is required".into());
+        }
+        let mut labels = std::collections::HashSet::new();
+        for connection in &config.connections {
+            if !["tcp", "mqtt", "both"].contains(&connection.mode.to_lowercase().as_str()) {
+                return Err(format!(
+                    "connection.mode must be \"tcp\", \"mqtt\" or \"both\", got {:?}",
+                    connection.mode
+                )
+                .into());
+            }
+            if connection.uses_tcp() && connection.address.is_empty() {
+                return Err(
+                    "connection.address is required unless connection.mode = \"mqtt\"".into(),
+                );
+            }
+            if connection.mode.eq_ignore_ascii_case("mqtt")
+                && crate::util::parse_node_id(&connection.mqtt.node_id).is_none()
+            {
+                return Err(
+                    "connection.mqtt.node_id is required when connection.mode = \"mqtt\"".into(),
+                );
+            }
+            if !labels.insert(connection.label()) {
+                return Err(format!(
+                    "connection {:?} is configured twice; give each [[connection]] a unique name",
+                    connection.label()
+                )
+                .into());
+            }
+        }
+        if let Some(telegram) = config.bridge.telegram.as_ref().filter(|t| t.enabled) {
+            if telegram.all_mappings().is_empty() {
+                return Err(
+                    "[bridge.telegram] needs a chat_id or [[bridge.telegram.mappings]]".into(),
+                );
+            }
+        }
+        if let Some(discord) = config.bridge.discord.as_ref().filter(|d| d.enabled) {
+            if discord.all_mappings().is_empty() {
+                return Err(
+                    "[bridge.discord] needs a channel_id or [[bridge.discord.mappings]]".into(),
+                );
+            }
+        }
+        validate_quiet_hours(&config.welcome.quiet_hours)?;
+        for rule in &config.welcome.rules {
+            if !["rf", "mqtt", "any"].contains(&rule.source.to_lowercase().as_str()) {
+                return Err(format!(
+                    "welcome rule source must be \"rf\", \"mqtt\" or \"any\", got {:?}",
+                    rule.source
+                )
+                .into());
+            }
+            if !["new", "back", "any"].contains(&rule.kind.to_lowercase().as_str()) {
+                return Err(format!(
+                    "welcome rule kind must be \"new\", \"back\" or \"any\", got {:?}",
+                    rule.kind
+                )
+                .into());
+            }
+        }
+        let selftest = &config.selftest;
+        for time in selftest.times.iter().chain([&selftest.digest_time]) {
+            if chrono::NaiveTime::parse_from_str(time, "%H:%M").is_err() {
+                return Err(format!("selftest time {:?} is not \"HH:MM\"", time).into());
+            }
+        }
+        let digest_day = (!selftest.digest_day.is_empty()).then_some(&selftest.digest_day);
+        for day in selftest.days.iter().chain(digest_day) {
+            if day.parse::<chrono::Weekday>().is_err() {
+                return Err(format!("selftest day {:?} is not a weekday", day).into());
+            }
+        }
+        for node in &config.remote_admin.nodes {
+            if crate::util::parse_node_id(node).is_none() {
+                return Err(format!("remote_admin node {:?} is not a node ID", node).into());
+            }
+        }
+        let mut budget_channels = std::collections::HashSet::new();
+        for budget in &config.channel_budgets {
+            if budget.max_per_hour == 0 {
+                return Err(format!(
+                    "channel_budgets channel {} needs max_per_hour > 0",
+                    budget.channel
+                )
+                .into());
+            }
+            if !budget_channels.insert(budget.channel) {
+                return Err(format!(
+                    "channel_budgets has channel {} more than once",
+                    budget.channel
+                )
+                .into());
+            }
+        }
+        if chrono::NaiveTime::parse_from_str(&config.snapshot.time, "%H:%M").is_err() {
+            return Err(
+                format!("snapshot time {:?} is not \"HH:MM\"", config.snapshot.time).into(),
+            );
+        }
+        for event in config.event_hooks.webhooks.iter().flat_map(|w| &w.events) {
+            if !crate::event_hooks::EVENT_NAMES.contains(&event.as_str()) {
+                return Err(format!(
+                    "event_hooks event {:?} is not one of {}",
+                    event,
+                    crate::event_hooks::EVENT_NAMES.join(", ")
+                )
+                .into());
+            }
+        }
+        for entry in &config.schedule {
+            if let Err(e) = crate::scheduler::CronSchedule::parse(&entry.cron) {
+                return Err(format!("schedule cron {:?}: {}", entry.cron, e).into());
+            }
+            if entry.text.trim().is_empty() {
+                return Err(format!("schedule {:?} has no text", entry.cron).into());
+            }
+        }
+        for beacon in &config.beacons {
+            if beacon.text.trim().is_empty() {
+                return Err("beacon has no text".into());
+            }
+            if beacon.interval_mins == 0 {
+                return Err(format!("beacon {:?} needs interval_mins > 0", beacon.text).into());
+            }
+            if beacon.start_hour > 23 || beacon.end_hour > 24 {
+                return Err(format!(
+                    "beacon {:?} hours must be 0-23 (start_hour) and 0-24 (end_hour)",
+                    beacon.text
+                )
+                .into());
+            }
+        }
         Ok(config)
     }
 
@@ -228,3 +1810,130 @@ impl Config {
         self.modules.get(name).map(|m| m.enabled).unwrap_or(false)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = r#"
+        [bot]
+        name = "Meshenger"
+
+        [welcome]
+        enabled = false
+        message = ""
+        welcome_back_message = ""
+        absence_threshold_hours = 48
+
+        [weather]
+        latitude = 0.0
+        longitude = 0.0
+        units = "metric"
+
+        [modules]
+    "#;
+
+    #[test]
+    fn test_connection_table_or_array() {
+        let single: Config = toml::from_str(&format!(
+            "[connection]\naddress = \"10.0.0.1:4403\"\n{}",
+            BASE
+        ))
+        .unwrap();
+        assert_eq!(single.connections.len(), 1);
+        assert_eq!(single.connections[0].label(), "10.0.0.1:4403");
+
+        let multi: Config = toml::from_str(&format!(
+            "[[connection]]\nname = \"north\"\naddress = \"10.0.0.1:4403\"\n\
+             [[connection]]\naddress = \"10.0.0.2:4403\"\n{}",
+            BASE
+        ))
+        .unwrap();
+        let labels: Vec<&str> = multi.connections.iter().map(|c| c.label()).collect();
+        assert_eq!(labels, ["north", "10.0.0.2:4403"]);
+    }
+
+    #[test]
+    fn test_welcome_rule_source_and_kind_checked() {
+        let path = std::env::temp_dir().join(format!(
+            "meshenger-welcome-rules-{}.toml",
+            std::process::id()
+        ));
+        let with_rule = |rule: &str| {
+            std::fs::write(
+                &path,
+                format!(
+                    "[connection]\naddress = \"10.0.0.1:4403\"\n{}\n[[welcome.rules]]\nmessage = \"Hi\"\n{}\n",
+                    BASE, rule
+                ),
+            )
+            .unwrap();
+            Config::load(&path)
+        };
+
+        assert!(with_rule("source = \"MQTT\"\nkind = \"back\"").is_ok());
+        let err = with_rule("source = \"mqtt_only\"").unwrap_err();
+        assert!(err.to_string().contains("welcome rule source"));
+        let err = with_rule("kind = \"returning\"").unwrap_err();
+        assert!(err.to_string().contains("welcome rule kind"));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_discord_mappings() {
+        let config: Config = toml::from_str(&format!(
+            "[connection]\naddress = \"10.0.0.1:4403\"\n{}\n\
+             [bridge.discord]\nbot_token = \"t\"\nchannel_id = 100\n\
+             [[bridge.discord.mappings]]\nchannel_id = 200\nmesh_channel = 2\ndirection = \"to_discord\"\n",
+            BASE
+        ))
+        .unwrap();
+        let mappings = config.bridge.discord.unwrap().all_mappings();
+        let routes: Vec<(u64, u32, &str)> = mappings
+            .iter()
+            .map(|m| (m.channel_id, m.mesh_channel, m.direction.as_str()))
+            .collect();
+        assert_eq!(routes, [(100, 0, "both"), (200, 2, "to_discord")]);
+    }
+
+    #[test]
+    fn test_packet_retention_overrides() {
+        let config: Config = toml::from_str(&format!(
+            "[connection]\naddress = \"10.0.0.1:4403\"\n{}\n\
+             [db]\npacket_retention_days = 90\n\
+             [db.packet_type_retention_days]\nposition = 14\ntext = 0\n",
+            BASE
+        ))
+        .unwrap();
+        assert_eq!(config.db.packet_retention_days(PacketType::Position), 14);
+        assert_eq!(config.db.packet_retention_days(PacketType::Text), 0);
+        assert_eq!(config.db.packet_retention_days(PacketType::Telemetry), 90);
+
+        let bogus = format!(
+            "[connection]\naddress = \"10.0.0.1:4403\"\n{}\n\
+             [db.packet_type_retention_days]\npositions = 14\n",
+            BASE
+        );
+        assert!(toml::from_str::<Config>(&bogus).is_err());
+    }
+
+    #[test]
+    fn test_dashboard_read_replica_alias() {
+        let parse = |dashboard: &str| -> Config {
+            toml::from_str(&format!(
+                "[connection]\naddress = \"10.0.0.1:4403\"\n{}\n[dashboard]\n{}\n",
+                BASE, dashboard
+            ))
+            .unwrap()
+        };
+        assert_eq!(parse("").dashboard.read_pool_size(), 0);
+        assert_eq!(parse("read_replica = true").dashboard.read_pool_size(), 1);
+        assert_eq!(parse("read_replica = false").dashboard.read_pool_size(), 0);
+        assert_eq!(
+            parse("read_replica = true\nread_connections = 4")
+                .dashboard
+                .read_pool_size(),
+            4
+        );
+    }
+}
@@ -1,22 +1,42 @@
 use std::convert::Infallible;
 use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
 
-use axum::extract::{Query, State};
-use axum::http::StatusCode;
+use axum::extract::{Path, Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
 use axum::response::sse::{Event, Sse};
-use axum::response::Json;
-use axum::routing::get;
+use axum::response::{IntoResponse, Json, Redirect, Response};
 use axum::Router;
 use futures_util::stream::Stream;
+use meshtastic::types::MeshChannel;
 use serde::{Deserialize, Serialize};
 use tokio_stream::wrappers::BroadcastStream;
 use tokio_stream::StreamExt;
 use tower_http::cors::CorsLayer;
 use tower_http::services::{ServeDir, ServeFile};
+use utoipa::{IntoParams, OpenApi, ToSchema};
+use utoipa_axum::router::OpenApiRouter;
+use utoipa_axum::routes;
 
-use crate::config::Config;
-use crate::db::{Db, MqttFilter};
+use crate::bridge::{check_mesh_length, OutgoingBridgeMessage, OutgoingMessageSender};
+use crate::bridge_filter::BridgeFilter;
+use crate::bridge_filter::BridgeFilterStats;
+use crate::bridges::webhook::{SendError, SendRequest, WebhookInbound};
+use crate::config::{Config, FreshnessConfig};
+use crate::db::{
+    BridgeLogEntry, DashboardMessage, DashboardNode, DashboardOverview, Db, DbStats, DedupStats,
+    DeviceTelemetry, DistributionBucket, EnvironmentReading, EnvironmentSensor, HopsToMe, Incident,
+    LatencyWindow, MeshHopMatrix, MqttFilter, NetLog, NetworkGraph, NodeCounts, NodeDelivery,
+    NodeNote, NodePurgeSummary, PacketQuery, PacketRecord, RouterSuggestion, SelfTestReport,
+    ThroughputBucket, TracerouteDestinationSummary, TracerouteEvent, TracerouteGeo,
+    TracerouteRequester, TracerouteSession, TrackPoint,
+};
+use crate::live::LiveEvent;
+use crate::metrics::{self, Metrics};
+use crate::packet_type::PacketType;
+use crate::settings::Setting;
+use crate::util::parse_node_id;
 
 fn to_json<T: Serialize>(value: T) -> Result<Json<serde_json::Value>, StatusCode> {
     serde_json::to_value(value).map(Json).map_err(|e| {
@@ -25,24 +45,128 @@ fn to_json<T: Serialize>(value: T) -> Result<Json<serde_json::Value>, StatusCode
     })
 }
 
+/// A node with its `[freshness]` class, so UIs don't each derive it from
+/// `last_seen`.
+#[derive(Serialize, ToSchema)]
+struct FreshNode {
+    #[serde(flatten)]
+    node: DashboardNode,
+    /// `online`, `recent`, `stale` or `offline` by `[freshness]`.
+    freshness: &'static str,
+}
+
+/// Round a coordinate to `decimals` places (2 is about 1 km).
+fn round_coordinate(value: f64, decimals: u32) -> f64 {
+    let scale = 10f64.powi(decimals as i32);
+    (value * scale).round() / scale
+}
+
+/// Round node coordinates to `decimals` places.
+fn reduce_precision(nodes: &mut [DashboardNode], decimals: u32) {
+    for node in nodes {
+        node.latitude = node.latitude.map(|v| round_coordinate(v, decimals));
+        node.longitude = node.longitude.map(|v| round_coordinate(v, decimals));
+    }
+}
+
+/// Round the positions along a traceroute's routes to `decimals` places.
+fn reduce_geo_precision(geo: &mut TracerouteGeo, decimals: u32) {
+    for hop in geo.request.iter_mut().chain(geo.response.iter_mut()) {
+        hop.latitude = hop.latitude.map(|v| round_coordinate(v, decimals));
+        hop.longitude = hop.longitude.map(|v| round_coordinate(v, decimals));
+    }
+}
+
+/// The coarser of two optional precisions.
+fn coarsest(a: Option<u32>, b: Option<u32>) -> Option<u32> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Decimals coordinates are rounded to on the listener being served.
+fn position_decimals(state: &AppState) -> Option<u32> {
+    // `[privacy] position_decimals` applies everywhere, the dashboard's
+    // own setting only on the public listener
+    let public_decimals = state
+        .config
+        .dashboard
+        .public_position_decimals
+        .filter(|_| state.public);
+    coarsest(state.config.privacy.position_decimals, public_decimals)
+}
+
+/// Coarsen coordinates as configured for the listener being served.
+fn public_view(state: &AppState, mut nodes: Vec<DashboardNode>) -> Vec<DashboardNode> {
+    if let Some(decimals) = position_decimals(state) {
+        reduce_precision(&mut nodes, decimals);
+    }
+    nodes
+}
+
+fn with_freshness(nodes: Vec<DashboardNode>, freshness: &FreshnessConfig) -> Vec<FreshNode> {
+    let now = chrono::Utc::now().timestamp();
+    nodes
+        .into_iter()
+        .map(|node| FreshNode {
+            freshness: freshness.classify(now - node.last_seen),
+            node,
+        })
+        .collect()
+}
+
 #[derive(Clone)]
 struct AppState {
     db: Arc<Db>,
     config: Arc<Config>,
+    /// The config the bot's modules were last built from, for the settings
+    /// `!admin reload` applies.
+    modules_config: Arc<RwLock<Arc<Config>>>,
     queue_depth: Arc<AtomicUsize>,
     local_node_id: Arc<std::sync::atomic::AtomicU32>,
-    sse_tx: tokio::sync::broadcast::Sender<()>,
+    firmware_version: Arc<RwLock<Option<String>>>,
+    started_at: Instant,
+    bridge_filter: Arc<BridgeFilter>,
+    sse_tx: tokio::sync::broadcast::Sender<LiveEvent>,
+    webhook: Option<WebhookInbound>,
+    /// Into the bot, for `/api/send`.
+    outgoing_tx: Option<OutgoingMessageSender>,
+    metrics: Arc<Metrics>,
+    /// Shared by both listeners, so the rate limit covers them together.
+    public_status: Arc<Mutex<PublicStatusCache>>,
+    /// Description of the routes this listener serves.
+    openapi: Arc<utoipa::openapi::OpenApi>,
+    /// Serving the public read-only listener: coordinates are also coarsened
+    /// to `[dashboard] public_position_decimals`.
+    public: bool,
 }
 
 fn default_mqtt() -> String {
     "all".to_string()
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 struct HoursParam {
+    /// Window in hours; 0 = all time.
     #[serde(default = "default_hours")]
+    #[param(default = 24, minimum = 0)]
     hours: u32,
+}
+
+/// A window of packets, optionally only those heard locally or via MQTT.
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct WindowParam {
+    /// Window in hours; 0 = all time. Buckets are hourly up to 48, daily above.
+    #[serde(default = "default_hours")]
+    #[param(default = 24, minimum = 0)]
+    hours: u32,
+    /// Count everything (`all`), only local RF (`local`) or only MQTT
+    /// (`mqtt_only`).
     #[serde(default = "default_mqtt")]
+    #[param(default = "all")]
     mqtt: String,
 }
 
@@ -50,27 +174,252 @@ fn default_hours() -> u32 {
     24
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 struct PacketThroughputParam {
+    /// Window in hours; 0 = all time. Buckets are hourly up to 48, daily above.
     #[serde(default = "default_hours")]
+    #[param(default = 24, minimum = 0)]
     hours: u32,
+    /// Count everything (`all`), only local RF (`local`) or only MQTT
+    /// (`mqtt_only`).
     #[serde(default = "default_mqtt")]
+    #[param(default = "all")]
     mqtt: String,
+    /// Comma-separated packet types, e.g. `text,position,telemetry`; empty = all.
     #[serde(default)]
     types: Option<String>,
 }
 
-#[derive(Serialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct TracerouteSessionsParam {
+    /// Window in hours; 0 = all time.
+    #[serde(default = "default_hours")]
+    #[param(default = 24, minimum = 0)]
+    hours: u32,
+    /// Also list sessions expired by `[traceroute_sessions]`.
+    #[serde(default)]
+    include_expired: bool,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct HopsToMeParam {
+    /// Window in hours; 0 = all time.
+    #[serde(default = "default_hours")]
+    #[param(default = 24, minimum = 0)]
+    hours: u32,
+    /// Add the preceding window of the same length as `previous`.
+    #[serde(default)]
+    compare: bool,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct LimitParam {
+    /// Most recent entries to return (at most 100).
+    #[serde(default = "default_limit")]
+    #[param(default = 20, minimum = 1, maximum = 100)]
+    limit: usize,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct BridgeLogParam {
+    /// Window in hours; 0 = all time.
+    #[serde(default = "default_hours")]
+    #[param(default = 24, minimum = 0)]
+    hours: u32,
+    /// Most recent entries to return (at most 100).
+    #[serde(default = "default_limit")]
+    #[param(default = 20, minimum = 1, maximum = 100)]
+    limit: usize,
+}
+
+fn default_limit() -> usize {
+    20
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct MessagesParam {
+    /// Window in hours; 0 = all time.
+    #[serde(default = "default_hours")]
+    #[param(default = 24, minimum = 0)]
+    hours: u32,
+    /// Only this mesh channel.
+    #[param(minimum = 0, maximum = 7)]
+    channel: Option<u32>,
+    /// Sender or recipient, hex `!id` or decimal.
+    node: Option<String>,
+    /// Row ID to page back from (`next_before` of the previous page).
+    before: Option<i64>,
+    /// Messages per page (at most 100).
+    #[serde(default = "default_limit")]
+    #[param(default = 20, minimum = 1, maximum = 100)]
+    limit: usize,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct PacketsParam {
+    /// Sender, hex `!id` or decimal.
+    from: Option<String>,
+    /// Recipient, hex `!id` or decimal.
+    to: Option<String>,
+    /// Comma-separated packet types, as for `/api/packet-throughput`.
+    #[serde(rename = "type")]
+    packet_type: Option<String>,
+    /// `in` or `out`
+    direction: Option<String>,
+    /// Everything (`all`), only local RF (`local`) or only MQTT (`mqtt_only`).
+    #[serde(default = "default_mqtt")]
+    #[param(default = "all")]
+    mqtt: String,
+    /// Unix time; only packets after it.
+    since: Option<i64>,
+    /// Packets per page (at most 100).
+    #[serde(default = "default_limit")]
+    #[param(default = 20, minimum = 1, maximum = 100)]
+    limit: usize,
+    /// Entries to skip: `next_offset` of the previous page.
+    #[serde(default)]
+    #[param(default = 0)]
+    offset: usize,
+}
+
+/// Body of `POST /api/send`.
+#[derive(Deserialize, ToSchema)]
+struct SendMessageRequest {
+    text: String,
+    #[serde(default)]
+    #[schema(default = 0, minimum = 0, maximum = 7)]
+    channel: u32,
+    /// DM this node (hex `!id` or decimal) instead of posting on the channel.
+    to: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct HealthResponse {
+    /// "ok", or "unhealthy" (served as 503) with the reasons in `problems`.
+    status: &'static str,
+    problems: Vec<String>,
+    radio: RadioHealth,
+    bridges: Vec<BridgeHealth>,
+    db_writable: bool,
+    api_usage: Vec<ApiUsageStatus>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct RadioHealth {
+    node_id: Option<String>,
+    /// `None` before the first packet.
+    last_packet_secs_ago: Option<i64>,
+    connections: Vec<ConnectionHealth>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct ConnectionHealth {
+    label: String,
+    /// "connected", "connecting" (never connected yet) or "reconnecting".
+    state: &'static str,
+    reconnects: u64,
+}
+
+#[derive(Serialize, ToSchema)]
+struct BridgeHealth {
+    name: String,
+    running: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+struct ApiUsageStatus {
+    api: String,
+    calls_today: u64,
+    /// `None` when the API has no configured quota.
+    daily_quota: Option<u64>,
+    remaining: Option<u64>,
+}
+
+#[derive(Serialize, ToSchema)]
 struct QueueResponse {
     depth: usize,
 }
 
+#[derive(Serialize, ToSchema)]
+struct InfoResponse {
+    version: &'static str,
+    git_hash: &'static str,
+    /// RFC 3339 timestamp of when the binary was built.
+    build_time: Option<String>,
+    uptime_secs: u64,
+    /// `None` until the radio has reported its node ID.
+    node_id: Option<String>,
+    /// `None` until the radio has sent its device metadata.
+    firmware_version: Option<String>,
+}
+
+/// A page of `/api/messages`.
+#[derive(Serialize, ToSchema)]
+struct MessagesPage {
+    messages: Vec<DashboardMessage>,
+    /// `before` for the next page; `None` when there may be no older messages.
+    next_before: Option<i64>,
+}
+
+/// A page of `/api/packets`.
+#[derive(Serialize, ToSchema)]
+struct PacketsPage {
+    packets: Vec<PacketRecord>,
+    /// `offset` for the next page; `None` when there may be no more packets.
+    next_offset: Option<usize>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct SendResponse {
+    queued: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+struct BridgeSendResponse {
+    queued: bool,
+    /// Warning that the radio is offline, so the message waits in the queue.
+    notice: Option<String>,
+}
+
+/// Database file size around a VACUUM.
+#[derive(Serialize, ToSchema)]
+struct VacuumResponse {
+    before_bytes: u64,
+    after_bytes: u64,
+}
+
+/// A dashboard-editable setting, for `/api/settings`.
+#[derive(Serialize, ToSchema)]
+struct SettingEntry {
+    key: &'static str,
+    description: &'static str,
+    /// Stored in the database, overriding the config file.
+    overridden: bool,
+    /// Whether the bot runs with `value` yet.
+    applied: bool,
+    value: serde_json::Value,
+}
+
 pub struct Dashboard {
     config: Arc<Config>,
+    modules_config: Arc<RwLock<Arc<Config>>>,
     db: Arc<Db>,
     queue_depth: Arc<AtomicUsize>,
     local_node_id: Arc<std::sync::atomic::AtomicU32>,
-    sse_tx: tokio::sync::broadcast::Sender<()>,
+    firmware_version: Arc<RwLock<Option<String>>>,
+    started_at: Instant,
+    bridge_filter: Arc<BridgeFilter>,
+    sse_tx: tokio::sync::broadcast::Sender<LiveEvent>,
+    webhook: Option<WebhookInbound>,
+    outgoing_tx: Option<OutgoingMessageSender>,
+    metrics: Arc<Metrics>,
 }
 
 impl Dashboard {
@@ -79,53 +428,174 @@ impl Dashboard {
         db: Arc<Db>,
         queue_depth: Arc<AtomicUsize>,
         local_node_id: Arc<std::sync::atomic::AtomicU32>,
-        sse_tx: tokio::sync::broadcast::Sender<()>,
+        firmware_version: Arc<RwLock<Option<String>>>,
+        bridge_filter: Arc<BridgeFilter>,
+        sse_tx: tokio::sync::broadcast::Sender<LiveEvent>,
     ) -> Self {
         Self {
+            modules_config: Arc::new(RwLock::new(Arc::clone(&config))),
             config,
             db,
             queue_depth,
             local_node_id,
+            firmware_version,
+            started_at: Instant::now(),
+            bridge_filter,
             sse_tx,
+            webhook: None,
+            outgoing_tx: None,
+            metrics: Arc::new(Metrics::default()),
         }
     }
 
+    /// Serve the bot's counters at `/metrics`.
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Report settings as applied once `!admin reload` rebuilds the modules
+    /// with them.
+    pub fn with_modules_config(mut self, modules_config: Arc<RwLock<Arc<Config>>>) -> Self {
+        self.modules_config = modules_config;
+        self
+    }
+
+    /// Accept messages from the chat view at `/api/send`.
+    pub fn with_sender(mut self, outgoing_tx: OutgoingMessageSender) -> Self {
+        self.outgoing_tx = Some(outgoing_tx);
+        self
+    }
+
+    /// Accept messages for the mesh at `/api/bridge/send` (`[bridge.webhook]`).
+    pub fn with_webhook(mut self, inbound: WebhookInbound) -> Self {
+        self.webhook = Some(inbound);
+        self
+    }
+
     pub async fn run(self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let bind = &self.config.dashboard.bind_address;
-        log::info!("Starting dashboard on {}", bind);
+        let dashboard = &self.config.dashboard;
+        log::info!("Starting dashboard on {}", dashboard.bind_address);
 
+        let title = format!("{} Dashboard API", self.config.bot.name);
+        let (full_routes, full_api) = Self::full_routes().split_for_parts();
         let state = AppState {
             db: self.db,
             config: self.config.clone(),
+            modules_config: self.modules_config,
             queue_depth: self.queue_depth,
             local_node_id: self.local_node_id,
+            firmware_version: self.firmware_version,
+            started_at: self.started_at,
+            bridge_filter: self.bridge_filter,
             sse_tx: self.sse_tx,
+            webhook: self.webhook,
+            outgoing_tx: self.outgoing_tx,
+            metrics: self.metrics,
+            public_status: Arc::new(Mutex::new(PublicStatusCache::default())),
+            openapi: Arc::new(crate::openapi::finish(full_api, &title)),
+            public: false,
         };
 
-        let api_routes = Router::new()
-            .route("/api/overview", get(handle_overview))
-            .route("/api/nodes", get(handle_nodes))
-            .route("/api/throughput", get(handle_throughput))
-            .route("/api/packet-throughput", get(handle_packet_throughput))
-            .route("/api/rssi", get(handle_rssi))
-            .route("/api/snr", get(handle_snr))
-            .route("/api/hops", get(handle_hops))
-            .route(
-                "/api/traceroute-requesters",
-                get(handle_traceroute_requesters),
-            )
-            .route("/api/traceroute-events", get(handle_traceroute_events))
-            .route(
-                "/api/traceroute-destinations",
-                get(handle_traceroute_destinations),
-            )
-            .route("/api/traceroute-sessions", get(handle_traceroute_sessions))
-            .route("/api/positions", get(handle_positions))
-            .route("/api/queue", get(handle_queue))
-            .route("/api/events", get(handle_sse));
+        if !dashboard.pages_dir.is_empty() {
+            log::info!("Serving dashboard pages from {}", dashboard.pages_dir);
+        }
+        let app = Self::app(full_routes, &dashboard.pages_dir, state.clone());
+        let listener = tokio::net::TcpListener::bind(&dashboard.bind_address).await?;
+        log::info!("Dashboard listening on {}", dashboard.bind_address);
+
+        if dashboard.public_bind_address.is_empty() {
+            axum::serve(listener, app).await?;
+            return Ok(());
+        }
 
-        // Serve static files from web/dist/ if the directory exists (prod mode)
-        let app = if std::path::Path::new("web/dist/index.html").exists() {
+        let (public_routes, public_api) = Self::public_routes().split_for_parts();
+        let public_state = AppState {
+            openapi: Arc::new(crate::openapi::finish(public_api, &title)),
+            public: true,
+            ..state
+        };
+        let public_app = Self::app(public_routes, &dashboard.pages_dir, public_state);
+        let public_listener = tokio::net::TcpListener::bind(&dashboard.public_bind_address).await?;
+        log::info!(
+            "Public read-only dashboard listening on {}",
+            dashboard.public_bind_address
+        );
+        tokio::try_join!(async { axum::serve(listener, app).await }, async {
+            axum::serve(public_listener, public_app).await
+        },)?;
+        Ok(())
+    }
+
+    /// Read-only endpoints, the only ones served on `[dashboard] public_bind_address`.
+    /// Each handler's `#[utoipa::path]` gives its route and its entry in
+    /// `/api/openapi.json`.
+    fn public_routes() -> OpenApiRouter<AppState> {
+        OpenApiRouter::with_openapi(crate::openapi::ApiDoc::openapi())
+            .routes(routes!(handle_openapi))
+            .routes(routes!(handle_info))
+            .routes(routes!(handle_branding))
+            .routes(routes!(handle_public_status))
+            .routes(routes!(handle_overview))
+            .routes(routes!(handle_nodes))
+            .routes(routes!(handle_positions))
+            .routes(routes!(handle_sse))
+            .routes(routes!(handle_short_link))
+    }
+
+    /// Every endpoint, served on `[dashboard] bind_address`.
+    fn full_routes() -> OpenApiRouter<AppState> {
+        Self::public_routes()
+            .routes(routes!(handle_metrics))
+            .routes(routes!(handle_health))
+            .routes(routes!(handle_node_notes))
+            .routes(routes!(handle_node_track))
+            .routes(routes!(handle_throughput))
+            .routes(routes!(handle_packet_throughput))
+            .routes(routes!(handle_rssi))
+            .routes(routes!(handle_snr))
+            .routes(routes!(handle_hops))
+            .routes(routes!(handle_hops_to_me))
+            .routes(routes!(handle_dedup))
+            .routes(routes!(handle_traceroute_requesters))
+            .routes(routes!(handle_traceroute_events))
+            .routes(routes!(handle_traceroute_destinations))
+            .routes(routes!(handle_traceroute_sessions))
+            .routes(routes!(handle_traceroute_session_geo))
+            .routes(routes!(handle_environment_sensors))
+            .routes(routes!(handle_environment_series))
+            .routes(routes!(handle_node_telemetry))
+            .routes(routes!(handle_network_graph))
+            .routes(routes!(handle_delivery))
+            .routes(routes!(handle_node_history))
+            .routes(routes!(handle_mesh_hops))
+            .routes(routes!(handle_router_suggestions))
+            .routes(routes!(handle_nets))
+            .routes(routes!(handle_incidents))
+            .routes(routes!(handle_selftest))
+            .routes(routes!(handle_queue))
+            .routes(routes!(handle_bridge_rules))
+            .routes(routes!(handle_bridge_log))
+            .routes(routes!(handle_messages))
+            .routes(routes!(handle_packets))
+            .routes(routes!(handle_db_stats))
+            .routes(routes!(handle_latency))
+            .routes(routes!(handle_purge_node))
+            .routes(routes!(handle_db_vacuum))
+            .routes(routes!(handle_settings, handle_put_settings))
+            .routes(routes!(handle_bridge_send))
+            .routes(routes!(handle_send))
+    }
+
+    /// API routes plus operator pages (`[dashboard] pages_dir`) and the built
+    /// frontend from web/dist/, if it exists (prod mode).
+    fn app(api_routes: Router<AppState>, pages_dir: &str, state: AppState) -> Router {
+        let api_routes = if pages_dir.is_empty() {
+            api_routes
+        } else {
+            api_routes.nest_service("/pages", ServeDir::new(pages_dir))
+        };
+        if std::path::Path::new("web/dist/index.html").exists() {
             let serve_dir =
                 ServeDir::new("web/dist").fallback(ServeFile::new("web/dist/index.html"));
             api_routes
@@ -134,205 +604,1594 @@ impl Dashboard {
                 .with_state(state)
         } else {
             api_routes.layer(CorsLayer::permissive()).with_state(state)
-        };
+        }
+    }
+}
+
+/// Run `f` against the database through [`Db::run`], turning a failure into
+/// a 500. `what` names the query in the error log.
+async fn with_db<T, F>(state: &AppState, what: &'static str, f: F) -> Result<T, StatusCode>
+where
+    T: Send + 'static,
+    F: FnOnce(&Db) -> Result<T, Box<dyn std::error::Error + Send + Sync>> + Send + 'static,
+{
+    state.db.run(f).await.map_err(|e| {
+        log::error!("Dashboard {} error: {}", what, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+/// Counters and gauges in the Prometheus text format.
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    responses(
+        (status = 200, description = "OK", content_type = "text/plain", body = String),
+    )
+)]
+async fn handle_metrics(State(state): State<AppState>) -> Response {
+    let db_size_bytes = with_db(&state, "metrics db size", |db| db.size_bytes())
+        .await
+        .ok();
+    let body = state.metrics.render(&metrics::Snapshot {
+        queue_depth: state.queue_depth.load(Ordering::Relaxed),
+        db_size_bytes,
+        uptime_secs: state.started_at.elapsed().as_secs(),
+    });
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body).into_response()
+}
+
+/// Radio connections, last packet, bridge tasks, database writability and
+/// today's external API calls against `[api_quotas]`.
+#[utoipa::path(
+    get,
+    path = "/api/health",
+    responses(
+        (status = 200, description = "Healthy", body = HealthResponse),
+        (status = 503, description = "Unhealthy; the body lists the `problems`", body = HealthResponse),
+    )
+)]
+async fn handle_health(State(state): State<AppState>) -> Result<Response, StatusCode> {
+    let usage = with_db(&state, "health", |db| db.api_usage_today()).await?;
+
+    // Configured APIs are listed even before their first call today.
+    let mut apis: Vec<String> = state.config.api_quotas.keys().cloned().collect();
+    apis.extend(usage.iter().map(|u| u.api.clone()));
+    apis.sort();
+    apis.dedup();
+
+    let api_usage = apis
+        .into_iter()
+        .map(|api| {
+            let calls_today = usage
+                .iter()
+                .find(|u| u.api == api)
+                .map(|u| u.calls)
+                .unwrap_or(0);
+            let daily_quota = Some(state.config.api_quota(&api)).filter(|q| *q > 0);
+            ApiUsageStatus {
+                remaining: daily_quota.map(|q| q.saturating_sub(calls_today)),
+                api,
+                calls_today,
+                daily_quota,
+            }
+        })
+        .collect();
+
+    let now = chrono::Utc::now().timestamp();
+    // A real write, so a full disk or a read-only file shows up here.
+    let db_writable = with_db(&state, "health db write", move |db| {
+        db.set_state("health_checked_at", &now.to_string())
+    })
+    .await
+    .is_ok();
+    let last_packet_secs_ago = with_db(&state, "health last packet", |db| db.last_packet_in_at())
+        .await
+        .ok()
+        .flatten()
+        .map(|at| now - at);
+    let node_id = state.local_node_id.load(Ordering::Relaxed);
+    let radio = RadioHealth {
+        node_id: (node_id != 0).then(|| format!("!{:08x}", node_id)),
+        last_packet_secs_ago,
+        connections: state
+            .metrics
+            .connections()
+            .into_iter()
+            .map(|(label, c)| ConnectionHealth {
+                label,
+                state: match (c.connected, c.reconnects) {
+                    (true, _) => "connected",
+                    (false, 0) => "connecting",
+                    (false, _) => "reconnecting",
+                },
+                reconnects: c.reconnects,
+            })
+            .collect(),
+    };
+    let bridges: Vec<BridgeHealth> = state
+        .metrics
+        .bridges()
+        .into_iter()
+        .map(|(name, running)| BridgeHealth { name, running })
+        .collect();
+
+    let problems = health_problems(
+        &radio,
+        &bridges,
+        db_writable,
+        state.config.dashboard.health_max_packet_age_secs,
+    );
+    let status = if problems.is_empty() {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    let body = to_json(HealthResponse {
+        status: if problems.is_empty() {
+            "ok"
+        } else {
+            "unhealthy"
+        },
+        problems,
+        radio,
+        bridges,
+        db_writable,
+        api_usage,
+    })?;
+    Ok((status, body).into_response())
+}
+
+/// Why the bot should be considered down; empty when it is healthy.
+fn health_problems(
+    radio: &RadioHealth,
+    bridges: &[BridgeHealth],
+    db_writable: bool,
+    max_packet_age_secs: u64,
+) -> Vec<String> {
+    let mut problems = Vec::new();
+    if radio.connections.is_empty() {
+        problems.push("No radio connection started".to_string());
+    }
+    for connection in &radio.connections {
+        if connection.state != "connected" {
+            problems.push(format!(
+                "Connection {} {}",
+                connection.label, connection.state
+            ));
+        }
+    }
+    if max_packet_age_secs > 0 {
+        match radio.last_packet_secs_ago {
+            Some(age) if age <= max_packet_age_secs as i64 => {}
+            Some(age) => problems.push(format!("No packet received for {}s", age)),
+            None => problems.push("No packet received yet".to_string()),
+        }
+    }
+    for bridge in bridges.iter().filter(|b| !b.running) {
+        problems.push(format!("{} bridge stopped", bridge.name));
+    }
+    if !db_writable {
+        problems.push("Database not writable".to_string());
+    }
+    problems
+}
+
+#[derive(Serialize, ToSchema)]
+struct BrandingLink {
+    title: String,
+    url: String,
+}
+
+#[derive(Serialize, ToSchema)]
+struct BrandingResponse {
+    title: String,
+    logo_url: Option<String>,
+    footer: Option<String>,
+    links: Vec<BrandingLink>,
+}
+
+/// Title, logo, footer and header links from `[dashboard]`, so the frontend
+/// can be branded without rebuilding it.
+#[utoipa::path(
+    get,
+    path = "/api/branding",
+    responses(
+        (status = 200, description = "OK", body = BrandingResponse),
+    )
+)]
+async fn handle_branding(State(state): State<AppState>) -> Json<BrandingResponse> {
+    let dashboard = &state.config.dashboard;
+    let non_empty = |s: &String| (!s.is_empty()).then(|| s.clone());
+    Json(BrandingResponse {
+        title: non_empty(&dashboard.title)
+            .unwrap_or_else(|| format!("{} Dashboard", state.config.bot.name)),
+        logo_url: non_empty(&dashboard.logo_url),
+        footer: non_empty(&dashboard.footer),
+        links: dashboard
+            .links
+            .iter()
+            .map(|l| BrandingLink {
+                title: l.title.clone(),
+                url: l.url.clone(),
+            })
+            .collect(),
+    })
+}
+
+/// OpenAPI description of the routes served on this listener.
+#[utoipa::path(
+    get,
+    path = "/api/openapi.json",
+    responses(
+        (status = 200, description = "OpenAPI 3.1 document", content_type = "application/json"),
+    )
+)]
+async fn handle_openapi(State(state): State<AppState>) -> Json<utoipa::openapi::OpenApi> {
+    Json(state.openapi.as_ref().clone())
+}
+
+/// Version, build, uptime, connected node and radio firmware.
+#[utoipa::path(
+    get,
+    path = "/api/info",
+    responses(
+        (status = 200, description = "OK", body = InfoResponse),
+    )
+)]
+async fn handle_info(State(state): State<AppState>) -> Json<InfoResponse> {
+    let node_id = state.local_node_id.load(Ordering::Relaxed);
+    let firmware_version = state
+        .firmware_version
+        .read()
+        .map(|v| v.clone())
+        .unwrap_or_default();
+    Json(InfoResponse {
+        version: env!("CARGO_PKG_VERSION"),
+        git_hash: env!("MESHENGER_GIT_HASH"),
+        build_time: env!("MESHENGER_BUILD_TIME")
+            .parse::<i64>()
+            .ok()
+            .and_then(|ts| chrono::DateTime::from_timestamp(ts, 0))
+            .map(|dt| dt.to_rfc3339()),
+        uptime_secs: state.started_at.elapsed().as_secs(),
+        node_id: (node_id != 0).then(|| format!("!{:08x}", node_id)),
+        firmware_version,
+    })
+}
+
+/// The last `/api/public-status` body and the requests counted in the
+/// current one-minute window.
+#[derive(Default)]
+struct PublicStatusCache {
+    body: Option<(Instant, serde_json::Value)>,
+    window: Option<(Instant, u32)>,
+}
 
-        let listener = tokio::net::TcpListener::bind(bind).await?;
-        log::info!("Dashboard listening on {}", bind);
-        axum::serve(listener, app).await?;
+impl PublicStatusCache {
+    /// Count a request against `limit` per minute (0 = unlimited), or return
+    /// the seconds until the window resets.
+    fn admit(&mut self, now: Instant, limit: u32) -> Result<(), u64> {
+        if limit == 0 {
+            return Ok(());
+        }
+        let window = Duration::from_secs(60);
+        let (started, count) = match self.window {
+            Some((started, count)) if now.duration_since(started) < window => (started, count),
+            _ => (now, 0),
+        };
+        if count >= limit {
+            let retry = window.saturating_sub(now.duration_since(started));
+            return Err(retry.as_secs().max(1));
+        }
+        self.window = Some((started, count + 1));
         Ok(())
     }
 }
 
+#[derive(Serialize, ToSchema)]
+struct PublicStatusResponse {
+    name: String,
+    #[serde(flatten)]
+    status: crate::db::PublicStatus,
+    uptime_secs: u64,
+}
+
+/// Coarse mesh counters for embedding on other sites, cached for
+/// `[dashboard] public_status_cache_secs` and limited to
+/// `public_status_rate_limit` requests a minute.
+#[utoipa::path(
+    get,
+    path = "/api/public-status",
+    responses(
+        (status = 200, description = "OK", body = PublicStatusResponse),
+        (status = 429, description = "Over `[dashboard] public_status_rate_limit`; see `Retry-After`"),
+    )
+)]
+async fn handle_public_status(State(state): State<AppState>) -> Response {
+    let dashboard = &state.config.dashboard;
+    let max_age = dashboard.public_status_cache_secs;
+    let now = Instant::now();
+    let cached = {
+        let mut cache = state.public_status.lock().unwrap();
+        if let Err(retry_after) = cache.admit(now, dashboard.public_status_rate_limit) {
+            return (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(header::RETRY_AFTER, retry_after.to_string())],
+            )
+                .into_response();
+        }
+        cache
+            .body
+            .as_ref()
+            .filter(|(built, _)| now.duration_since(*built).as_secs() < max_age)
+            .map(|(_, body)| body.clone())
+    };
+    let body = match cached {
+        Some(body) => body,
+        None => {
+            let status = match with_db(&state, "public status", |db| db.public_status()).await {
+                Ok(status) => status,
+                Err(status) => return status.into_response(),
+            };
+            let body = match to_json(PublicStatusResponse {
+                name: state.config.bot.name.clone(),
+                status,
+                uptime_secs: state.started_at.elapsed().as_secs(),
+            }) {
+                Ok(Json(body)) => body,
+                Err(status) => return status.into_response(),
+            };
+            state.public_status.lock().unwrap().body = Some((now, body.clone()));
+            body
+        }
+    };
+    (
+        [(
+            header::CACHE_CONTROL,
+            format!("public, max-age={}", max_age),
+        )],
+        Json(body),
+    )
+        .into_response()
+}
+
+/// Node count, message and packet totals and MQTT command counts.
+#[utoipa::path(
+    get,
+    path = "/api/overview",
+    params(WindowParam),
+    responses(
+        (status = 200, description = "OK", body = DashboardOverview),
+    )
+)]
 async fn handle_overview(
     State(state): State<AppState>,
-    Query(params): Query<HoursParam>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
+    Query(params): Query<WindowParam>,
+) -> Result<Json<DashboardOverview>, StatusCode> {
     let filter = MqttFilter::from_str(&params.mqtt);
-    let overview = state
-        .db
-        .dashboard_overview(params.hours, filter, &state.config.bot.name)
-        .map_err(|e| {
-            log::error!("Dashboard overview error: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-    to_json(overview)
+    let bot_name = state.config.bot.name.clone();
+    let overview = with_db(&state, "overview", move |db| {
+        db.dashboard_overview(params.hours, filter, &bot_name)
+    })
+    .await?;
+    Ok(Json(overview))
 }
 
+/// Nodes heard in the window with hop summary and freshness.
+#[utoipa::path(
+    get,
+    path = "/api/nodes",
+    params(WindowParam),
+    responses(
+        (status = 200, description = "OK", body = Vec<FreshNode>),
+    )
+)]
 async fn handle_nodes(
     State(state): State<AppState>,
-    Query(params): Query<HoursParam>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
+    Query(params): Query<WindowParam>,
+) -> Result<Json<Vec<FreshNode>>, StatusCode> {
     let filter = MqttFilter::from_str(&params.mqtt);
-    let nodes = state
-        .db
-        .dashboard_nodes(params.hours, filter)
-        .map_err(|e| {
-            log::error!("Dashboard nodes error: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-    to_json(nodes)
+    let nodes = with_db(&state, "nodes", move |db| {
+        db.dashboard_nodes(params.hours, filter)
+    })
+    .await?;
+    let nodes = public_view(&state, nodes);
+    Ok(Json(with_freshness(nodes, &state.config.freshness)))
 }
 
+/// Text message throughput per bucket.
+#[utoipa::path(
+    get,
+    path = "/api/throughput",
+    params(WindowParam),
+    responses(
+        (status = 200, description = "OK", body = Vec<ThroughputBucket>),
+    )
+)]
 async fn handle_throughput(
     State(state): State<AppState>,
-    Query(params): Query<HoursParam>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
+    Query(params): Query<WindowParam>,
+) -> Result<Json<Vec<ThroughputBucket>>, StatusCode> {
     let filter = MqttFilter::from_str(&params.mqtt);
-    let buckets = state
-        .db
-        .dashboard_throughput(params.hours, filter)
-        .map_err(|e| {
-            log::error!("Dashboard throughput error: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-    to_json(buckets)
+    let buckets = with_db(&state, "throughput", move |db| {
+        db.dashboard_throughput(params.hours, filter)
+    })
+    .await?;
+    Ok(Json(buckets))
 }
 
+/// Packet throughput per bucket, of the given types.
+#[utoipa::path(
+    get,
+    path = "/api/packet-throughput",
+    params(PacketThroughputParam),
+    responses(
+        (status = 200, description = "OK", body = Vec<ThroughputBucket>),
+    )
+)]
 async fn handle_packet_throughput(
     State(state): State<AppState>,
     Query(params): Query<PacketThroughputParam>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
+) -> Result<Json<Vec<ThroughputBucket>>, StatusCode> {
     let filter = MqttFilter::from_str(&params.mqtt);
-    let packet_types: Option<Vec<String>> = params.types.map(|t| {
-        t.split(',')
-            .map(|s| s.trim().to_string())
-            .filter(|s| !s.is_empty())
-            .collect()
-    });
-    let buckets = state
-        .db
-        .dashboard_packet_throughput(params.hours, filter, packet_types.as_deref())
-        .map_err(|e| {
-            log::error!("Dashboard packet throughput error: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-    to_json(buckets)
+    let packet_types = params.types.as_deref().and_then(PacketType::parse_list);
+    let buckets = with_db(&state, "packet throughput", move |db| {
+        db.dashboard_packet_throughput(params.hours, filter, packet_types.as_deref())
+    })
+    .await?;
+    Ok(Json(buckets))
 }
 
+/// RSSI distribution.
+#[utoipa::path(
+    get,
+    path = "/api/rssi",
+    params(WindowParam),
+    responses(
+        (status = 200, description = "OK", body = Vec<DistributionBucket>),
+    )
+)]
 async fn handle_rssi(
     State(state): State<AppState>,
-    Query(params): Query<HoursParam>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
+    Query(params): Query<WindowParam>,
+) -> Result<Json<Vec<DistributionBucket>>, StatusCode> {
     let filter = MqttFilter::from_str(&params.mqtt);
-    let buckets = state.db.dashboard_rssi(params.hours, filter).map_err(|e| {
-        log::error!("Dashboard RSSI error: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
-    to_json(buckets)
+    let buckets = with_db(&state, "RSSI", move |db| {
+        db.dashboard_rssi(params.hours, filter)
+    })
+    .await?;
+    Ok(Json(buckets))
 }
 
+/// SNR distribution.
+#[utoipa::path(
+    get,
+    path = "/api/snr",
+    params(WindowParam),
+    responses(
+        (status = 200, description = "OK", body = Vec<DistributionBucket>),
+    )
+)]
 async fn handle_snr(
     State(state): State<AppState>,
-    Query(params): Query<HoursParam>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
+    Query(params): Query<WindowParam>,
+) -> Result<Json<Vec<DistributionBucket>>, StatusCode> {
     let filter = MqttFilter::from_str(&params.mqtt);
-    let buckets = state.db.dashboard_snr(params.hours, filter).map_err(|e| {
-        log::error!("Dashboard SNR error: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
-    to_json(buckets)
+    let buckets = with_db(&state, "SNR", move |db| {
+        db.dashboard_snr(params.hours, filter)
+    })
+    .await?;
+    Ok(Json(buckets))
 }
 
+/// Hop count distribution.
+#[utoipa::path(
+    get,
+    path = "/api/hops",
+    params(WindowParam),
+    responses(
+        (status = 200, description = "OK", body = Vec<DistributionBucket>),
+    )
+)]
 async fn handle_hops(
     State(state): State<AppState>,
-    Query(params): Query<HoursParam>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
+    Query(params): Query<WindowParam>,
+) -> Result<Json<Vec<DistributionBucket>>, StatusCode> {
     let filter = MqttFilter::from_str(&params.mqtt);
-    let buckets = state.db.dashboard_hops(params.hours, filter).map_err(|e| {
-        log::error!("Dashboard hops error: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
-    to_json(buckets)
+    let buckets = with_db(&state, "hops", move |db| {
+        db.dashboard_hops(params.hours, filter)
+    })
+    .await?;
+    Ok(Json(buckets))
+}
+
+/// Per source node RF hop stats toward the local node.
+#[utoipa::path(
+    get,
+    path = "/api/hops-to-me",
+    params(HopsToMeParam),
+    responses(
+        (status = 200, description = "OK", body = Vec<HopsToMe>),
+    )
+)]
+async fn handle_hops_to_me(
+    State(state): State<AppState>,
+    Query(params): Query<HopsToMeParam>,
+) -> Result<Json<Vec<HopsToMe>>, StatusCode> {
+    let rows = with_db(&state, "hops-to-me", move |db| {
+        db.dashboard_hops_to_me(params.hours, params.compare)
+    })
+    .await?;
+    Ok(Json(rows))
 }
 
+/// How often the same packet was heard more than once.
+#[utoipa::path(
+    get,
+    path = "/api/dedup",
+    params(HoursParam),
+    responses(
+        (status = 200, description = "OK", body = DedupStats),
+    )
+)]
+async fn handle_dedup(
+    State(state): State<AppState>,
+    Query(params): Query<HoursParam>,
+) -> Result<Json<DedupStats>, StatusCode> {
+    let stats = with_db(&state, "dedup", move |db| {
+        db.dashboard_dedup_stats(params.hours)
+    })
+    .await?;
+    Ok(Json(stats))
+}
+
+/// Nodes with a known position and their freshness.
+#[utoipa::path(
+    get,
+    path = "/api/positions",
+    responses(
+        (status = 200, description = "OK", body = Vec<FreshNode>),
+    )
+)]
 async fn handle_positions(
     State(state): State<AppState>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    let positions = state.db.dashboard_positions().map_err(|e| {
-        log::error!("Dashboard positions error: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
-    to_json(positions)
+) -> Result<Json<Vec<FreshNode>>, StatusCode> {
+    let positions = with_db(&state, "positions", move |db| db.dashboard_positions()).await?;
+    let positions = public_view(&state, positions);
+    Ok(Json(with_freshness(positions, &state.config.freshness)))
 }
 
+/// Nodes that sent traceroute requests to the local node.
+#[utoipa::path(
+    get,
+    path = "/api/traceroute-requesters",
+    params(WindowParam),
+    responses(
+        (status = 200, description = "OK", body = Vec<TracerouteRequester>),
+    )
+)]
 async fn handle_traceroute_requesters(
     State(state): State<AppState>,
-    Query(params): Query<HoursParam>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
+    Query(params): Query<WindowParam>,
+) -> Result<Json<Vec<TracerouteRequester>>, StatusCode> {
     let local_node_id = state.local_node_id.load(Ordering::Relaxed);
     if local_node_id == 0 {
-        return to_json(Vec::<serde_json::Value>::new());
+        return Ok(Json(Vec::new()));
     }
 
     let filter = MqttFilter::from_str(&params.mqtt);
-    let rows = state
-        .db
-        .dashboard_traceroute_requesters(local_node_id, params.hours, filter)
-        .map_err(|e| {
-            log::error!("Dashboard traceroute requesters error: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-    to_json(rows)
+    let rows = with_db(&state, "traceroute requesters", move |db| {
+        db.dashboard_traceroute_requesters(local_node_id, params.hours, filter)
+    })
+    .await?;
+    Ok(Json(rows))
 }
 
+/// Recent incoming traceroute events.
+#[utoipa::path(
+    get,
+    path = "/api/traceroute-events",
+    params(WindowParam),
+    responses(
+        (status = 200, description = "OK", body = Vec<TracerouteEvent>),
+    )
+)]
 async fn handle_traceroute_events(
     State(state): State<AppState>,
-    Query(params): Query<HoursParam>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
+    Query(params): Query<WindowParam>,
+) -> Result<Json<Vec<TracerouteEvent>>, StatusCode> {
     let filter = MqttFilter::from_str(&params.mqtt);
-    let rows = state
-        .db
-        .dashboard_traceroute_events(params.hours, filter, 200)
-        .map_err(|e| {
-            log::error!("Dashboard traceroute events error: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-    to_json(rows)
+    let rows = with_db(&state, "traceroute events", move |db| {
+        db.dashboard_traceroute_events(params.hours, filter, 200)
+    })
+    .await?;
+    Ok(Json(rows))
 }
 
+/// Traceroute destination summary.
+#[utoipa::path(
+    get,
+    path = "/api/traceroute-destinations",
+    params(WindowParam),
+    responses(
+        (status = 200, description = "OK", body = Vec<TracerouteDestinationSummary>),
+    )
+)]
 async fn handle_traceroute_destinations(
     State(state): State<AppState>,
-    Query(params): Query<HoursParam>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
+    Query(params): Query<WindowParam>,
+) -> Result<Json<Vec<TracerouteDestinationSummary>>, StatusCode> {
     let filter = MqttFilter::from_str(&params.mqtt);
-    let rows = state
-        .db
-        .dashboard_traceroute_destinations(params.hours, filter)
-        .map_err(|e| {
-            log::error!("Dashboard traceroute destinations error: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-    to_json(rows)
+    let rows = with_db(&state, "traceroute destinations", move |db| {
+        db.dashboard_traceroute_destinations(params.hours, filter)
+    })
+    .await?;
+    Ok(Json(rows))
 }
 
+/// Correlated traceroute sessions with their hops.
+#[utoipa::path(
+    get,
+    path = "/api/traceroute-sessions",
+    params(TracerouteSessionsParam),
+    responses(
+        (status = 200, description = "OK", body = Vec<TracerouteSession>),
+    )
+)]
 async fn handle_traceroute_sessions(
     State(state): State<AppState>,
-    Query(params): Query<HoursParam>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    let rows = state
-        .db
-        .dashboard_traceroute_sessions(params.hours, 300)
-        .map_err(|e| {
-            log::error!("Dashboard traceroute sessions error: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-    to_json(rows)
+    Query(params): Query<TracerouteSessionsParam>,
+) -> Result<Json<Vec<TracerouteSession>>, StatusCode> {
+    let rows = with_db(&state, "traceroute sessions", move |db| {
+        db.dashboard_traceroute_sessions(params.hours, 300, params.include_expired)
+    })
+    .await?;
+    Ok(Json(rows))
+}
+
+/// A session's request and response routes for the map.
+#[utoipa::path(
+    get,
+    path = "/api/traceroute-sessions/{id}/geo",
+    params(("id" = i64, Path)),
+    responses(
+        (status = 200, description = "OK", body = TracerouteGeo),
+        (status = 404, description = "No such session"),
+    )
+)]
+async fn handle_traceroute_session_geo(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<TracerouteGeo>, StatusCode> {
+    match with_db(&state, "traceroute geo", move |db| {
+        db.traceroute_session_geo(id)
+    })
+    .await?
+    {
+        Some(mut geo) => {
+            if let Some(decimals) = position_decimals(&state) {
+                reduce_geo_precision(&mut geo, decimals);
+            }
+            Ok(Json(geo))
+        }
+        None => Err(StatusCode::NOT_FOUND),
+    }
+}
+
+/// Recent net sessions and their check-ins.
+#[utoipa::path(
+    get,
+    path = "/api/nets",
+    params(LimitParam),
+    responses(
+        (status = 200, description = "OK", body = Vec<NetLog>),
+    )
+)]
+async fn handle_nets(
+    State(state): State<AppState>,
+    Query(params): Query<LimitParam>,
+) -> Result<Json<Vec<NetLog>>, StatusCode> {
+    let nets = with_db(&state, "nets", move |db| {
+        db.recent_nets(params.limit.min(100))
+    })
+    .await?;
+    Ok(Json(nets))
+}
+
+/// Emergency incidents raised by `[emergency] keywords`.
+#[utoipa::path(
+    get,
+    path = "/api/incidents",
+    params(LimitParam),
+    responses(
+        (status = 200, description = "OK", body = Vec<Incident>),
+    )
+)]
+async fn handle_incidents(
+    State(state): State<AppState>,
+    Query(params): Query<LimitParam>,
+) -> Result<Json<Vec<Incident>>, StatusCode> {
+    let mut incidents = with_db(&state, "incidents", move |db| {
+        db.recent_incidents(params.limit.min(100))
+    })
+    .await?;
+    // The sender's position when raised, rounded like any other
+    if let Some(decimals) = position_decimals(&state) {
+        for incident in &mut incidents {
+            incident.latitude = incident.latitude.map(|v| round_coordinate(v, decimals));
+            incident.longitude = incident.longitude.map(|v| round_coordinate(v, decimals));
+        }
+    }
+    Ok(Json(incidents))
+}
+
+/// Self-test reachability over the last week (see `[selftest]`).
+#[utoipa::path(
+    get,
+    path = "/api/selftest",
+    responses(
+        (status = 200, description = "OK", body = SelfTestReport),
+    )
+)]
+async fn handle_selftest(
+    State(state): State<AppState>,
+) -> Result<Json<SelfTestReport>, StatusCode> {
+    let since = chrono::Utc::now().timestamp() - 7 * 24 * 60 * 60;
+    let report = with_db(&state, "selftest", move |db| db.selftest_report(since)).await?;
+    Ok(Json(report))
+}
+
+/// Redirect a link shortened for the mesh (see `[bridge.links]`).
+#[utoipa::path(
+    get,
+    path = "/l/{code}",
+    params(("code" = String, Path)),
+    responses(
+        (status = 307, description = "Redirect to the original URL"),
+        (status = 404, description = "Unknown code"),
+    )
+)]
+async fn handle_short_link(
+    State(state): State<AppState>,
+    Path(code): Path<String>,
+) -> Result<Redirect, StatusCode> {
+    match with_db(&state, "short link", move |db| db.resolve_short_link(&code)).await? {
+        Some(url) => Ok(Redirect::temporary(&url)),
+        None => Err(StatusCode::NOT_FOUND),
+    }
 }
 
+/// Outgoing queue depth.
+#[utoipa::path(
+    get,
+    path = "/api/queue",
+    responses(
+        (status = 200, description = "OK", body = QueueResponse),
+    )
+)]
 async fn handle_queue(State(state): State<AppState>) -> Json<QueueResponse> {
     Json(QueueResponse {
         depth: state.queue_depth.load(Ordering::Relaxed),
     })
 }
 
+/// `[[bridge.rules]]` hit counts, redactions and blocked messages.
+#[utoipa::path(
+    get,
+    path = "/api/bridge-rules",
+    responses(
+        (status = 200, description = "OK", body = BridgeFilterStats),
+    )
+)]
+async fn handle_bridge_rules(
+    State(state): State<AppState>,
+) -> Result<Json<BridgeFilterStats>, StatusCode> {
+    Ok(Json(state.bridge_filter.stats()))
+}
+
+/// Messages a bridge relayed in either direction, newest first.
+#[utoipa::path(
+    get,
+    path = "/api/bridges/{name}/log",
+    params(
+        ("name" = String, Path, description = "`telegram`, `discord`, `mqtt`, `signal`, `webhook` or `dashboard`."),
+        BridgeLogParam,
+    ),
+    responses(
+        (status = 200, description = "OK", body = Vec<BridgeLogEntry>),
+        (status = 404, description = "Unknown bridge"),
+    )
+)]
+async fn handle_bridge_log(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Query(params): Query<BridgeLogParam>,
+) -> Result<Json<Vec<BridgeLogEntry>>, StatusCode> {
+    if !crate::bridge::BRIDGE_NAMES.contains(&name.as_str()) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let log = with_db(&state, "bridge log", move |db| {
+        db.bridge_log(&name, params.hours, params.limit.min(100))
+    })
+    .await?;
+    Ok(Json(log))
+}
+
+/// Text message history for the chat view, newest first. `next_before`
+/// is set when there may be older messages.
+#[utoipa::path(
+    get,
+    path = "/api/messages",
+    params(MessagesParam),
+    responses(
+        (status = 200, description = "OK", body = MessagesPage),
+        (status = 400, description = "Bad node ID"),
+    )
+)]
+async fn handle_messages(
+    State(state): State<AppState>,
+    Query(params): Query<MessagesParam>,
+) -> Result<Json<MessagesPage>, StatusCode> {
+    let node = match params.node.as_deref() {
+        Some(node) => Some(parse_node_id(node).ok_or(StatusCode::BAD_REQUEST)?),
+        None => None,
+    };
+    let limit = params.limit.clamp(1, 100);
+    let messages = with_db(&state, "messages", move |db| {
+        db.dashboard_messages(params.hours, params.channel, node, params.before, limit)
+    })
+    .await?;
+    let next_before = (messages.len() == limit)
+        .then(|| messages.last().map(|m| m.id))
+        .flatten();
+    Ok(Json(MessagesPage {
+        messages,
+        next_before,
+    }))
+}
+
+/// Logged packets of every type for the packet browser, newest first.
+/// `next_offset` is set when there may be more.
+#[utoipa::path(
+    get,
+    path = "/api/packets",
+    params(PacketsParam),
+    responses(
+        (status = 200, description = "OK", body = PacketsPage),
+        (status = 400, description = "Bad node ID or direction"),
+    )
+)]
+async fn handle_packets(
+    State(state): State<AppState>,
+    Query(params): Query<PacketsParam>,
+) -> Result<Json<PacketsPage>, StatusCode> {
+    let node = |id: Option<&str>| match id {
+        Some(id) => parse_node_id(id).map(Some).ok_or(StatusCode::BAD_REQUEST),
+        None => Ok(None),
+    };
+    let direction = match params.direction.as_deref() {
+        None | Some("") => None,
+        Some(d @ ("in" | "out")) => Some(d.to_string()),
+        Some(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+    let query = PacketQuery {
+        from_node: node(params.from.as_deref())?,
+        to_node: node(params.to.as_deref())?,
+        packet_types: params
+            .packet_type
+            .as_deref()
+            .and_then(PacketType::parse_list),
+        direction,
+        mqtt: MqttFilter::from_str(&params.mqtt),
+        since: params.since,
+        limit: params.limit.clamp(1, 100),
+        offset: params.offset,
+    };
+    let (limit, offset) = (query.limit, query.offset);
+    let packets = with_db(&state, "packets", move |db| db.query_packets(&query)).await?;
+    let next_offset = (packets.len() == limit).then_some(offset + limit);
+    Ok(Json(PacketsPage {
+        packets,
+        next_offset,
+    }))
+}
+
+/// Queue a message from the chat view, sent as the bot. Needs the admin
+/// token.
+#[utoipa::path(
+    post,
+    path = "/api/send",
+    request_body = SendMessageRequest,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Queued for the mesh", body = SendResponse),
+        (status = 400, description = "No text, bad channel or bad node ID"),
+        (status = 401, description = "Missing or wrong bearer token"),
+        (status = 404, description = "No token configured"),
+        (status = 413, description = "Over `[bridge] max_message_bytes`"),
+        (status = 503, description = "The bot is no longer taking messages"),
+    )
+)]
+async fn handle_send(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<SendMessageRequest>,
+) -> Result<Json<SendResponse>, StatusCode> {
+    check_admin(&state, &headers)?;
+    let outgoing_tx = state.outgoing_tx.as_ref().ok_or(StatusCode::NOT_FOUND)?;
+    let text = req.text.trim();
+    if text.is_empty() || MeshChannel::new(req.channel).is_err() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let to_node = match req.to.as_deref() {
+        Some(to) => Some(parse_node_id(to).ok_or(StatusCode::BAD_REQUEST)?),
+        None => None,
+    };
+    check_mesh_length(text, state.config.bridge.max_message_bytes)
+        .map_err(|_| StatusCode::PAYLOAD_TOO_LARGE)?;
+    log::info!("Dashboard message for the mesh: {}", text);
+    outgoing_tx
+        .send(OutgoingBridgeMessage {
+            text: text.to_string(),
+            channel: req.channel,
+            source: "dashboard".to_string(),
+            sender: "dashboard".to_string(),
+            dm_session: None,
+            to_node,
+            received_at: Instant::now(),
+        })
+        .await
+        .map_err(|e| {
+            log::error!("Failed to send dashboard message to mesh: {}", e);
+            StatusCode::SERVICE_UNAVAILABLE
+        })?;
+    Ok(Json(SendResponse { queued: true }))
+}
+
+/// Check the request's bearer token against `dashboard.admin_token`.
+/// Admin endpoints answer 404 when no token is configured.
+fn check_admin(state: &AppState, headers: &HeaderMap) -> Result<(), StatusCode> {
+    let expected = match state.config.dashboard.admin_token.as_deref() {
+        Some(token) if !token.is_empty() => token,
+        _ => return Err(StatusCode::NOT_FOUND),
+    };
+    let provided = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    if provided == Some(expected) {
+        Ok(())
+    } else {
+        Err(StatusCode::UNAUTHORIZED)
+    }
+}
+
+/// Send a message to the mesh through the webhook bridge.
+#[utoipa::path(
+    post,
+    path = "/api/bridge/send",
+    request_body = SendRequest,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Queued for the mesh", body = BridgeSendResponse),
+        (status = 400, description = "No text"),
+        (status = 401, description = "Missing or wrong bearer token"),
+        (status = 404, description = "Webhook bridge not enabled"),
+        (status = 413, description = "Over `[bridge] max_message_bytes`"),
+        (status = 503, description = "The bot is no longer taking bridge messages"),
+    )
+)]
+async fn handle_bridge_send(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<SendRequest>,
+) -> Result<Json<BridgeSendResponse>, StatusCode> {
+    let webhook = state.webhook.as_ref().ok_or(StatusCode::NOT_FOUND)?;
+    let authorization = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok());
+    if !webhook.authorized(authorization) {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+    match webhook.send(req).await {
+        Ok(notice) => Ok(Json(BridgeSendResponse {
+            queued: true,
+            notice,
+        })),
+        Err(SendError::Empty) => Err(StatusCode::BAD_REQUEST),
+        Err(SendError::TooLong(feedback)) => {
+            log::warn!("Webhook bridge: {}", feedback);
+            Err(StatusCode::PAYLOAD_TOO_LARGE)
+        }
+        Err(SendError::Closed) => Err(StatusCode::SERVICE_UNAVAILABLE),
+    }
+}
+
+/// Operator notes added with `!note`, newest first.
+#[utoipa::path(
+    get,
+    path = "/api/nodes/{node_id}/notes",
+    params(
+        ("node_id" = String, Path, description = "Hex `!a1b2c3d4` or decimal node ID."),
+    ),
+    responses(
+        (status = 200, description = "OK", body = Vec<NodeNote>),
+        (status = 400, description = "Bad node ID"),
+    )
+)]
+async fn handle_node_notes(
+    State(state): State<AppState>,
+    Path(node_id): Path<String>,
+) -> Result<Json<Vec<NodeNote>>, StatusCode> {
+    let node_id = parse_node_id(&node_id).ok_or(StatusCode::BAD_REQUEST)?;
+    let notes = with_db(&state, "node notes", move |db| {
+        db.get_node_notes(node_id, 50)
+    })
+    .await?;
+    Ok(Json(notes))
+}
+
+/// Nodes reporting environment telemetry with their latest reading.
+#[utoipa::path(
+    get,
+    path = "/api/environment",
+    responses(
+        (status = 200, description = "OK", body = Vec<EnvironmentSensor>),
+    )
+)]
+async fn handle_environment_sensors(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<EnvironmentSensor>>, StatusCode> {
+    let sensors = with_db(&state, "environment sensors", move |db| {
+        db.environment_sensors()
+    })
+    .await?;
+    Ok(Json(sensors))
+}
+
+/// The node's environment readings, oldest first.
+#[utoipa::path(
+    get,
+    path = "/api/environment/{node_id}",
+    params(
+        ("node_id" = String, Path, description = "Hex `!a1b2c3d4` or decimal node ID."),
+        HoursParam,
+    ),
+    responses(
+        (status = 200, description = "OK", body = Vec<EnvironmentReading>),
+        (status = 400, description = "Bad node ID"),
+    )
+)]
+async fn handle_environment_series(
+    State(state): State<AppState>,
+    Path(node_id): Path<String>,
+    Query(params): Query<HoursParam>,
+) -> Result<Json<Vec<EnvironmentReading>>, StatusCode> {
+    let node_id = parse_node_id(&node_id).ok_or(StatusCode::BAD_REQUEST)?;
+    let readings = with_db(&state, "environment series", move |db| {
+        db.environment_series(node_id, params.hours)
+    })
+    .await?;
+    Ok(Json(readings))
+}
+
+/// The node's device metrics, oldest first.
+#[utoipa::path(
+    get,
+    path = "/api/telemetry/{node_id}",
+    params(
+        ("node_id" = String, Path, description = "Hex `!a1b2c3d4` or decimal node ID."),
+        HoursParam,
+    ),
+    responses(
+        (status = 200, description = "OK", body = Vec<DeviceTelemetry>),
+        (status = 400, description = "Bad node ID"),
+    )
+)]
+async fn handle_node_telemetry(
+    State(state): State<AppState>,
+    Path(node_id): Path<String>,
+    Query(params): Query<HoursParam>,
+) -> Result<Json<Vec<DeviceTelemetry>>, StatusCode> {
+    let node_id = parse_node_id(&node_id).ok_or(StatusCode::BAD_REQUEST)?;
+    let readings = with_db(&state, "node telemetry", move |db| {
+        db.get_node_telemetry(node_id, params.hours)
+    })
+    .await?;
+    Ok(Json(readings))
+}
+
+/// The node's reported positions, oldest first.
+#[utoipa::path(
+    get,
+    path = "/api/nodes/{node_id}/track",
+    params(
+        ("node_id" = String, Path, description = "Hex `!a1b2c3d4` or decimal node ID."),
+        HoursParam,
+    ),
+    responses(
+        (status = 200, description = "OK", body = Vec<TrackPoint>),
+        (status = 400, description = "Bad node ID"),
+    )
+)]
+async fn handle_node_track(
+    State(state): State<AppState>,
+    Path(node_id): Path<String>,
+    Query(params): Query<HoursParam>,
+) -> Result<Json<Vec<TrackPoint>>, StatusCode> {
+    let node_id = parse_node_id(&node_id).ok_or(StatusCode::BAD_REQUEST)?;
+    let mut track = with_db(&state, "node track", move |db| {
+        db.node_track(node_id, params.hours)
+    })
+    .await?;
+    if let Some(decimals) = position_decimals(&state) {
+        for point in &mut track {
+            point.latitude = round_coordinate(point.latitude, decimals);
+            point.longitude = round_coordinate(point.longitude, decimals);
+        }
+        // Rounding merges nearby points; keep one per spot
+        track.dedup_by(|b, a| a.latitude == b.latitude && a.longitude == b.longitude);
+    }
+    Ok(Json(track))
+}
+
+/// Mesh topology from NeighborInfo reports.
+#[utoipa::path(
+    get,
+    path = "/api/network-graph",
+    params(HoursParam),
+    responses(
+        (status = 200, description = "OK", body = NetworkGraph),
+    )
+)]
+async fn handle_network_graph(
+    State(state): State<AppState>,
+    Query(params): Query<HoursParam>,
+) -> Result<Json<NetworkGraph>, StatusCode> {
+    let graph = with_db(&state, "network graph", move |db| {
+        db.network_graph(params.hours)
+    })
+    .await?;
+    Ok(Json(graph))
+}
+
+/// DMs sent per destination with acknowledgement rate.
+#[utoipa::path(
+    get,
+    path = "/api/delivery",
+    params(HoursParam),
+    responses(
+        (status = 200, description = "OK", body = Vec<NodeDelivery>),
+    )
+)]
+async fn handle_delivery(
+    State(state): State<AppState>,
+    Query(params): Query<HoursParam>,
+) -> Result<Json<Vec<NodeDelivery>>, StatusCode> {
+    let stats = with_db(&state, "delivery", move |db| {
+        db.delivery_stats(params.hours)
+    })
+    .await?;
+    Ok(Json(stats))
+}
+
+/// Hourly snapshots of the node list's size.
+#[utoipa::path(
+    get,
+    path = "/api/node-history",
+    params(HoursParam),
+    responses(
+        (status = 200, description = "OK", body = Vec<NodeCounts>),
+    )
+)]
+async fn handle_node_history(
+    State(state): State<AppState>,
+    Query(params): Query<HoursParam>,
+) -> Result<Json<Vec<NodeCounts>>, StatusCode> {
+    let history = with_db(&state, "node history", move |db| {
+        db.node_history(params.hours)
+    })
+    .await?;
+    Ok(Json(history))
+}
+
+/// Node-to-node hop matrix over the past week.
+#[utoipa::path(
+    get,
+    path = "/api/mesh-hops",
+    responses(
+        (status = 200, description = "OK", body = MeshHopMatrix),
+    )
+)]
+async fn handle_mesh_hops(
+    State(state): State<AppState>,
+) -> Result<Json<MeshHopMatrix>, StatusCode> {
+    let matrix = with_db(&state, "mesh hops", move |db| db.mesh_hop_matrix()).await?;
+    Ok(Json(matrix))
+}
+
+/// Nodes whose loss would split the mesh.
+#[utoipa::path(
+    get,
+    path = "/api/router-suggestions",
+    responses(
+        (status = 200, description = "OK", body = Vec<RouterSuggestion>),
+    )
+)]
+async fn handle_router_suggestions(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<RouterSuggestion>>, StatusCode> {
+    let suggestions = with_db(&state, "router suggestions", move |db| {
+        db.router_suggestions()
+    })
+    .await?;
+    Ok(Json(suggestions))
+}
+
+/// Purge everything stored about a node.
+#[utoipa::path(
+    delete,
+    path = "/api/admin/nodes/{node_id}",
+    params(
+        ("node_id" = String, Path, description = "Hex `!a1b2c3d4` or decimal node ID."),
+    ),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Rows removed, per table", body = NodePurgeSummary),
+        (status = 400, description = "Bad node ID"),
+        (status = 401, description = "Missing or wrong bearer token"),
+        (status = 404, description = "No token configured"),
+    )
+)]
+async fn handle_purge_node(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(node_id): Path<String>,
+) -> Result<Json<NodePurgeSummary>, StatusCode> {
+    check_admin(&state, &headers)?;
+    let node_id = parse_node_id(&node_id).ok_or(StatusCode::BAD_REQUEST)?;
+    let summary = with_db(&state, "purge node", move |db| db.purge_node_data(node_id)).await?;
+    log::info!(
+        "Purged data for !{:08x} via admin API: {:?}",
+        node_id,
+        summary
+    );
+    let _ = state.sse_tx.send(LiveEvent::Refresh);
+    Ok(Json(summary))
+}
+
+/// Database size and rows per table.
+#[utoipa::path(
+    get,
+    path = "/api/db",
+    responses(
+        (status = 200, description = "OK", body = DbStats),
+    )
+)]
+async fn handle_db_stats(State(state): State<AppState>) -> Result<Json<DbStats>, StatusCode> {
+    let stats = with_db(&state, "db stats", move |db| db.db_stats()).await?;
+    Ok(Json(stats))
+}
+
+/// Stored reply latency percentiles, oldest first.
+#[utoipa::path(
+    get,
+    path = "/api/latency",
+    params(HoursParam),
+    responses(
+        (status = 200, description = "OK", body = Vec<LatencyWindow>),
+    )
+)]
+async fn handle_latency(
+    State(state): State<AppState>,
+    Query(params): Query<HoursParam>,
+) -> Result<Json<Vec<LatencyWindow>>, StatusCode> {
+    let windows = with_db(&state, "latency", move |db| {
+        db.latency_history(params.hours)
+    })
+    .await?;
+    Ok(Json(windows))
+}
+
+/// VACUUM and ANALYZE the database, answering once done.
+#[utoipa::path(
+    post,
+    path = "/api/admin/db/vacuum",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "OK", body = VacuumResponse),
+        (status = 401, description = "Missing or wrong bearer token"),
+        (status = 404, description = "No token configured"),
+    )
+)]
+async fn handle_db_vacuum(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<VacuumResponse>, StatusCode> {
+    check_admin(&state, &headers)?;
+    log::info!("Database VACUUM requested via admin API");
+    let (before, after) = with_db(&state, "db vacuum", |db| db.vacuum()).await?;
+    log::info!("Database VACUUM done: {} -> {} bytes", before, after);
+    Ok(Json(VacuumResponse {
+        before_bytes: before,
+        after_bytes: after,
+    }))
+}
+
+/// Each dashboard-editable setting: its value in the database if overridden
+/// there, otherwise in the running config, and whether the bot runs with it
+/// yet (overrides apply on the next restart, or reload for those
+/// `!admin reload` applies).
+async fn settings_json(state: &AppState) -> Result<Json<Vec<SettingEntry>>, StatusCode> {
+    let stored: std::collections::HashMap<String, String> =
+        with_db(state, "settings", |db| db.settings())
+            .await?
+            .into_iter()
+            .collect();
+    let modules_config = Arc::clone(&state.modules_config.read().unwrap());
+    let settings = Setting::ALL
+        .iter()
+        .map(|setting| {
+            let running = if setting.applies_on_reload() {
+                setting.get(&modules_config)
+            } else {
+                setting.get(&state.config)
+            };
+            let stored = stored
+                .get(setting.key())
+                .and_then(|raw| serde_json::from_str::<serde_json::Value>(raw).ok());
+            SettingEntry {
+                key: setting.key(),
+                description: setting.description(),
+                overridden: stored.is_some(),
+                applied: stored.as_ref().is_none_or(|v| *v == running),
+                value: stored.unwrap_or(running),
+            }
+        })
+        .collect();
+    Ok(Json(settings))
+}
+
+/// Options editable from the dashboard, with their current values.
+#[utoipa::path(
+    get,
+    path = "/api/settings",
+    responses(
+        (status = 200, description = "OK", body = Vec<SettingEntry>),
+    )
+)]
+async fn handle_settings(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<SettingEntry>>, StatusCode> {
+    settings_json(&state).await
+}
+
+/// Store setting overrides from a `{key: value}` object; `null` removes one.
+/// Nothing is stored unless every entry is valid.
+#[utoipa::path(
+    put,
+    path = "/api/settings",
+    request_body(content = HashMap<String, serde_json::Value>, description = "Setting keys with their new values; null restores the config file's value"),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "OK", body = Vec<SettingEntry>),
+        (status = 400, description = "Unknown setting or invalid value; the body says which", content_type = "text/plain", body = String),
+        (status = 401, description = "Missing or wrong bearer token"),
+        (status = 404, description = "No token configured"),
+    )
+)]
+async fn handle_put_settings(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(changes): Json<serde_json::Map<String, serde_json::Value>>,
+) -> Result<Json<Vec<SettingEntry>>, (StatusCode, String)> {
+    check_admin(&state, &headers).map_err(|status| (status, String::new()))?;
+    for (key, value) in &changes {
+        let setting = Setting::from_key(key)
+            .ok_or_else(|| (StatusCode::BAD_REQUEST, format!("unknown setting {}", key)))?;
+        if !value.is_null() {
+            setting
+                .validate(value)
+                .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+        }
+    }
+    for (key, value) in changes {
+        let value = (!value.is_null()).then(|| value.to_string());
+        let stored_key = key.clone();
+        with_db(&state, "settings update", move |db| {
+            db.set_setting(&stored_key, value.as_deref())
+        })
+        .await
+        .map_err(|status| (status, String::new()))?;
+        log::info!("Setting {} changed via dashboard", key);
+    }
+    settings_json(&state)
+        .await
+        .map_err(|status| (status, String::new()))
+}
+
+/// Server-sent `refresh` events whenever new data arrives, plus `packet`, `node`,
+/// `message_sent`, `queue_depth` and `traceroute` events on the full listener.
+#[utoipa::path(
+    get,
+    path = "/api/events",
+    responses(
+        (status = 200, description = "Event stream", content_type = "text/event-stream", body = String),
+    )
+)]
 async fn handle_sse(
     State(state): State<AppState>,
 ) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
     let rx = state.sse_tx.subscribe();
-    let stream = BroadcastStream::new(rx).map(|_| Ok(Event::default().event("refresh").data("")));
+    let public = state.public;
+    let stream = BroadcastStream::new(rx).filter_map(move |event| {
+        // A client that fell behind missed events; it re-fetches instead
+        let event = event.unwrap_or(LiveEvent::Refresh);
+        if public && !matches!(event, LiveEvent::Refresh) {
+            return None;
+        }
+        Some(Ok(Event::default().event(event.name()).data(event.data())))
+    });
     Sse::new(stream).keep_alive(
         axum::response::sse::KeepAlive::new()
             .interval(std::time::Duration::from_secs(30))
             .text("ping"),
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn test_reduce_precision() {
+        let db = Db::open(std::path::Path::new(":memory:")).unwrap();
+        db.upsert_node(0x11111111, "HOME", "Home", false).unwrap();
+        db.update_position(0x11111111, 25.033964, 121.564468)
+            .unwrap();
+        let mut nodes = db.dashboard_positions().unwrap();
+        reduce_precision(&mut nodes, 2);
+        assert_eq!(nodes[0].latitude, Some(25.03));
+        assert_eq!(nodes[0].longitude, Some(121.56));
+    }
+
+    #[test]
+    fn test_reduce_geo_precision() {
+        let hop = |node_id: &str, position: Option<(f64, f64)>| crate::db::GeoHop {
+            node_id: node_id.to_string(),
+            short_name: None,
+            long_name: None,
+            latitude: position.map(|p| p.0),
+            longitude: position.map(|p| p.1),
+        };
+        let mut geo = TracerouteGeo {
+            id: 1,
+            status: "complete".to_string(),
+            request: vec![
+                hop("!11111111", Some((25.033964, 121.564468))),
+                hop("!22222222", None),
+            ],
+            response: vec![hop("!22222222", Some((25.047924, 121.517081)))],
+        };
+        reduce_geo_precision(&mut geo, 2);
+        assert_eq!(geo.request[0].latitude, Some(25.03));
+        assert_eq!(geo.request[0].longitude, Some(121.56));
+        assert_eq!(geo.request[1].latitude, None);
+        assert_eq!(geo.response[0].latitude, Some(25.05));
+        assert_eq!(geo.response[0].longitude, Some(121.52));
+    }
+
+    #[test]
+    fn test_public_status_rate_limit() {
+        let mut cache = PublicStatusCache::default();
+        let start = Instant::now();
+        assert_eq!(cache.admit(start, 2), Ok(()));
+        assert_eq!(cache.admit(start + Duration::from_secs(10), 2), Ok(()));
+        assert_eq!(cache.admit(start + Duration::from_secs(15), 2), Err(45));
+        assert_eq!(cache.admit(start + Duration::from_secs(60), 2), Ok(()));
+        for _ in 0..10 {
+            assert_eq!(cache.admit(start, 0), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_health_problems() {
+        let connection = |state| ConnectionHealth {
+            label: "radio".to_string(),
+            state,
+            reconnects: 0,
+        };
+        let radio = |state, last_packet_secs_ago| RadioHealth {
+            node_id: Some("!a1b2c3d4".to_string()),
+            last_packet_secs_ago,
+            connections: vec![connection(state)],
+        };
+        let running = |running| BridgeHealth {
+            name: "telegram".to_string(),
+            running,
+        };
+
+        let healthy = radio("connected", Some(30));
+        assert!(health_problems(&healthy, &[running(true)], true, 0).is_empty());
+        assert!(health_problems(&healthy, &[running(true)], true, 60).is_empty());
+
+        assert_eq!(
+            health_problems(&radio("reconnecting", Some(30)), &[], true, 0),
+            vec!["Connection radio reconnecting"]
+        );
+        assert_eq!(
+            health_problems(&radio("connected", Some(120)), &[], true, 60),
+            vec!["No packet received for 120s"]
+        );
+        assert_eq!(
+            health_problems(&radio("connected", None), &[], true, 60),
+            vec!["No packet received yet"]
+        );
+        assert_eq!(
+            health_problems(&healthy, &[running(false)], false, 0),
+            vec!["telegram bridge stopped", "Database not writable"]
+        );
+        let no_radio = RadioHealth {
+            node_id: None,
+            last_packet_secs_ago: None,
+            connections: Vec::new(),
+        };
+        assert_eq!(
+            health_problems(&no_radio, &[], true, 0),
+            vec!["No radio connection started"]
+        );
+    }
+
+    #[test]
+    fn test_openapi_from_routes() {
+        let paths = |api: &utoipa::openapi::OpenApi| -> BTreeSet<String> {
+            api.paths.paths.keys().cloned().collect()
+        };
+        let public = Dashboard::public_routes().into_openapi();
+        let full = Dashboard::full_routes().into_openapi();
+        assert_eq!(paths(&public).len(), 9);
+        assert!(paths(&public).is_subset(&paths(&full)));
+        assert!(!paths(&public).contains("/api/health"));
+
+        let spec = serde_json::to_value(crate::openapi::finish(full, "Mesh")).unwrap();
+        assert_eq!(spec["openapi"], "3.1.0");
+        assert_eq!(spec["info"]["title"], "Mesh");
+        let nodes = &spec["paths"]["/api/nodes"]["get"];
+        assert_eq!(nodes["parameters"][1]["name"], "mqtt");
+        assert_eq!(nodes["parameters"][1]["schema"]["default"], "all");
+        assert_eq!(
+            nodes["responses"]["200"]["content"]["application/json"]["schema"]["items"]["$ref"],
+            "#/components/schemas/FreshNode"
+        );
+        // Schemas come from the serialized types, flattened fields included
+        let schemas = &spec["components"]["schemas"];
+        assert!(schemas["DashboardNode"]["properties"]["last_seen"].is_object());
+        assert_eq!(
+            schemas["FreshNode"]["allOf"][0]["$ref"],
+            "#/components/schemas/DashboardNode"
+        );
+        assert!(schemas["TracerouteSession"]["properties"]["hops"].is_object());
+
+        let purge = &spec["paths"]["/api/admin/nodes/{node_id}"]["delete"];
+        assert_eq!(purge["security"][0]["bearer"], serde_json::json!([]));
+        assert_eq!(purge["parameters"][0]["in"], "path");
+        assert!(purge["responses"]["401"].is_object());
+        assert_eq!(
+            spec["components"]["securitySchemes"]["bearer"]["scheme"],
+            "bearer"
+        );
+        let settings = &spec["paths"]["/api/settings"];
+        assert!(settings["get"]["security"].is_null());
+        assert!(settings["put"]["requestBody"].is_object());
+    }
+
+    #[test]
+    fn test_coarsest() {
+        assert_eq!(coarsest(None, None), None);
+        assert_eq!(coarsest(Some(3), None), Some(3));
+        assert_eq!(coarsest(None, Some(2)), Some(2));
+        assert_eq!(coarsest(Some(3), Some(2)), Some(2));
+    }
+}
@@ -1,12 +1,35 @@
 use chrono::Utc;
-use rusqlite::{params, Connection};
+use rusqlite::{params, Connection, OpenFlags};
 use serde::Serialize;
-use std::path::Path;
-use std::sync::Mutex;
-
-#[cfg(test)]
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use utoipa::ToSchema;
+
+use crate::mesh_graph::CutNode;
+use crate::packet_type::PacketType;
 use crate::util::parse_node_id;
 
+/// Version of the schema created by `init_schema`, stored in SQLite's
+/// `user_version`. Bump it whenever a table or column is added so older
+/// binaries refuse to open the upgraded database.
+pub const SCHEMA_VERSION: i64 = 27;
+
+/// Rows `prune_packets_older_than` deletes per statement.
+const PRUNE_BATCH_ROWS: usize = 5000;
+/// How long a connection waits for another one's lock on the database file
+/// (a checkpoint, a `--backfill-hops` run) before a query fails as busy.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// `bot_state` key holding the meshenger version that last opened the DB.
+const WRITER_VERSION_KEY: &str = "db_writer_version";
+/// `bot_state` key holding when `store_mesh_graph` last ran.
+const MESH_GRAPH_UPDATED_KEY: &str = "mesh_graph_updated_at";
+/// `bot_state` key holding the primary radio's node ID.
+const LOCAL_NODE_KEY: &str = "local_node_id";
+
 #[derive(Debug, Clone, Copy)]
 pub enum MqttFilter {
     All,
@@ -32,17 +55,233 @@ impl MqttFilter {
     }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct DashboardOverview {
     pub node_count: u64,
     pub messages_in: u64,
     pub messages_out: u64,
     pub packets_in: u64,
     pub packets_out: u64,
+    pub mqtt_commands: MqttCommandCounts,
     pub bot_name: String,
 }
 
-#[derive(Debug, Serialize)]
+/// Counts for `/api/public-status`: nothing that identifies a node.
+#[derive(Debug, Serialize, PartialEq, ToSchema)]
+pub struct PublicStatus {
+    pub node_count: u64,
+    /// Text messages received since midnight UTC.
+    pub messages_today: u64,
+    pub last_packet_at: Option<i64>,
+}
+
+/// Commands heard via MQTT, by how `bot.mqtt_commands` handled them.
+#[derive(Debug, Default, Serialize, PartialEq, ToSchema)]
+pub struct MqttCommandCounts {
+    pub replied: u64,
+    pub dm: u64,
+    pub ignored: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct MailMessage {
+    pub id: i64,
+    pub timestamp: i64,
+    pub from_node: u32,
+    pub body: String,
+}
+
+/// A node that set an away message with `!away`.
+#[derive(Debug, Clone)]
+pub struct AwayNode {
+    pub node_id: u32,
+    pub short_name: String,
+    pub long_name: String,
+    pub message: String,
+}
+
+/// An operator note attached to a node with `!note`.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct NodeNote {
+    pub id: i64,
+    /// Display name of the node that wrote the note, or its `!id`.
+    pub author: String,
+    pub note: String,
+    pub created_at: i64,
+}
+
+/// A poll started with `!poll start`.
+#[derive(Debug, Clone)]
+pub struct Poll {
+    pub id: i64,
+    pub question: String,
+    pub options: Vec<String>,
+    /// Mesh channel the poll was started on; results are announced there.
+    pub channel: u32,
+    /// When the poll closes on its own (unix seconds), if it does.
+    pub closes_at: Option<i64>,
+    pub closed_at: Option<i64>,
+}
+
+/// A net session with its check-ins, as recorded by `!checkin`.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct NetLog {
+    pub id: i64,
+    pub name: String,
+    pub channel: u32,
+    pub started_at: i64,
+    pub ends_at: i64,
+    pub checkins: Vec<NetCheckin>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct NetCheckin {
+    pub node_id: String,
+    pub callsign: String,
+    pub comment: Option<String>,
+    pub checked_in_at: i64,
+}
+
+/// An emergency keyword seen on the mesh.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Incident {
+    pub id: i64,
+    pub node_id: String,
+    pub node_name: String,
+    pub channel: u32,
+    pub keyword: String,
+    pub text: String,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub created_at: i64,
+}
+
+/// Reachability of one node over the self-test transmissions in a report.
+#[derive(Debug, Clone, Serialize, PartialEq, ToSchema)]
+pub struct SelfTestNode {
+    pub node_id: String,
+    pub node_name: String,
+    /// Tests this node acked or was heard rebroadcasting.
+    pub responses: u64,
+    pub last_response: Option<i64>,
+}
+
+/// Which known nodes answered the self-test transmissions since `since`.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct SelfTestReport {
+    pub since: i64,
+    pub runs: u64,
+    /// Nodes heard over RF in the period, most responsive first.
+    pub nodes: Vec<SelfTestNode>,
+}
+
+/// Calls made today (UTC) to an external API.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ApiUsage {
+    pub api: String,
+    pub calls: u64,
+}
+
+/// A public text message heard on the mesh.
+#[derive(Debug, Clone)]
+pub struct ChatMessage {
+    pub timestamp: i64,
+    pub from_name: String,
+    pub text: String,
+}
+
+/// Tables holding data about a node, with the rows that are about node
+/// `?1`, in the order [`Db::purge_node_data`] deletes them. Hops go first:
+/// both the node's own hop rows and all hops of sessions about to be removed.
+const NODE_DATA_TABLES: &[(&str, &str)] = &[
+    (
+        "traceroute_session_hops",
+        "node_id = ?1 OR session_id IN (
+            SELECT id FROM traceroute_sessions WHERE src_node = ?1 OR dst_node = ?1
+        )",
+    ),
+    ("traceroute_sessions", "src_node = ?1 OR dst_node = ?1"),
+    ("packets", "from_node = ?1 OR to_node = ?1"),
+    ("mail", "from_node = ?1 OR to_node = ?1"),
+    ("nodes", "node_id = ?1"),
+    ("away_replies", "node_id = ?1 OR sender_id = ?1"),
+    ("away_status", "node_id = ?1"),
+    ("muted_nodes", "node_id = ?1"),
+    ("node_notes", "node_id = ?1"),
+    // Polls the node started go with everyone's votes on them
+    (
+        "poll_votes",
+        "node_id = ?1 OR poll_id IN (SELECT id FROM polls WHERE created_by = ?1)",
+    ),
+    ("polls", "created_by = ?1"),
+    ("net_checkins", "node_id = ?1"),
+    ("incidents", "node_id = ?1"),
+    ("environment_readings", "node_id = ?1"),
+    ("telemetry", "node_id = ?1"),
+    ("position_history", "node_id = ?1"),
+    ("neighbors", "node_id = ?1 OR neighbor_id = ?1"),
+    ("mesh_hops", "from_node = ?1 OR to_node = ?1"),
+    ("mesh_cut_nodes", "node_id = ?1 OR cut_off_node = ?1"),
+    ("pending_sends", "to_node = ?1"),
+    ("battery_alerts", "node_id = ?1"),
+    ("alert_state", "node_id = ?1"),
+    ("store_forward_routers", "node_id = ?1"),
+    ("traceroute_hop_stats", "node_id = ?1"),
+    ("mqtt_commands", "node_id = ?1"),
+    ("selftest_responses", "node_id = ?1"),
+    // Mesh messages the bridges relayed carry the sender as `!id`
+    (
+        "bridge_log",
+        "direction = 'to_bridge' AND source = printf('!%08x', ?1)",
+    ),
+];
+
+/// Rows removed by [`Db::purge_node_data`], per table.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct NodePurgeSummary {
+    pub tables: BTreeMap<&'static str, usize>,
+}
+
+impl NodePurgeSummary {
+    /// Rows removed from `table`.
+    #[cfg(test)]
+    pub fn count(&self, table: &str) -> usize {
+        self.tables.get(table).copied().unwrap_or(0)
+    }
+
+    pub fn total(&self) -> usize {
+        self.tables.values().sum()
+    }
+
+    /// Non-empty tables, e.g. "3 packets, 1 mail".
+    pub fn describe(&self) -> String {
+        self.tables
+            .iter()
+            .filter(|(_, &n)| n > 0)
+            .map(|(table, n)| format!("{} {}", n, table))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// A node with its display name (long name, or `!id`) and last-heard time.
+#[derive(Debug, PartialEq)]
+pub struct LastHeardNode {
+    pub node_id: u32,
+    pub name: String,
+    pub last_seen: i64,
+}
+
+/// A node whose `online` flag `update_node_liveness` just flipped.
+#[derive(Debug, PartialEq)]
+pub struct LivenessChange {
+    pub node_id: u32,
+    pub online: bool,
+    pub last_seen: i64,
+    pub via_mqtt: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
 pub struct DashboardNode {
     pub node_id: String,
     pub short_name: String,
@@ -56,22 +295,346 @@ pub struct DashboardNode {
     pub min_hop: Option<u32>,
     pub avg_hop: Option<f64>,
     pub hop_samples: u32,
+    /// Hop statistics from packets plus traceroute routes (see
+    /// `backfill_traceroute_hop_stats`), covering relays that are only ever
+    /// seen inside traceroutes.
+    pub combined_min_hop: Option<u32>,
+    pub combined_avg_hop: Option<f64>,
+    pub combined_hop_samples: u32,
+    /// Most recent operator note, if any.
+    pub latest_note: Option<String>,
+    /// False once the node went unheard for `[freshness] stale_mins`, as of
+    /// the bot's last liveness check.
+    pub online: bool,
+}
+
+/// Inbound RF hop statistics for one source node over one time window.
+#[derive(Debug, Serialize, PartialEq, ToSchema)]
+pub struct HopWindow {
+    pub samples: u32,
+    pub min_hop: Option<u32>,
+    pub avg_hop: Option<f64>,
+}
+
+/// How many hops a node's packets take to reach the local node, optionally
+/// compared with the preceding window of the same length.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct HopsToMe {
+    pub node_id: String,
+    pub short_name: String,
+    pub long_name: String,
+    pub current: HopWindow,
+    /// `None` unless a comparison was requested (and `hours > 0`).
+    pub previous: Option<HopWindow>,
+    /// `current.avg_hop - previous.avg_hop`; positive means paths got longer.
+    pub avg_hop_change: Option<f64>,
+}
+
+/// A node and how many packets were heard from it, for `!top`.
+#[derive(Debug, PartialEq)]
+pub struct ActiveNode {
+    pub node_id: u32,
+    pub short_name: String,
+    pub long_name: String,
+    pub packets: u64,
+}
+
+/// How often packets were heard more than once.
+#[derive(Debug, Default, Serialize, PartialEq, ToSchema)]
+pub struct DedupCounts {
+    /// Distinct (sender, packet ID) pairs.
+    pub unique_packets: u64,
+    /// Every copy heard, including the first.
+    pub copies: u64,
+    /// Extra copies heard over RF (rebroadcasts by other routers).
+    pub rf_duplicates: u64,
+    /// Extra copies heard via MQTT (echoes of packets already heard).
+    pub mqtt_duplicates: u64,
+    /// `copies / unique_packets`; 1.0 means no packet was heard twice.
+    pub redundancy: f64,
+}
+
+impl DedupCounts {
+    /// Every extra copy that wasn't an RF rebroadcast arrived via MQTT.
+    fn new(unique_packets: u64, copies: u64, rf_duplicates: u64) -> Self {
+        let mut counts = Self {
+            unique_packets,
+            copies,
+            rf_duplicates,
+            mqtt_duplicates: copies - unique_packets - rf_duplicates,
+            redundancy: 0.0,
+        };
+        counts.update_redundancy();
+        counts
+    }
+
+    fn add(&mut self, other: &DedupCounts) {
+        self.unique_packets += other.unique_packets;
+        self.copies += other.copies;
+        self.rf_duplicates += other.rf_duplicates;
+        self.mqtt_duplicates += other.mqtt_duplicates;
+        self.update_redundancy();
+    }
+
+    fn update_redundancy(&mut self) {
+        self.redundancy = if self.unique_packets == 0 {
+            0.0
+        } else {
+            self.copies as f64 / self.unique_packets as f64
+        };
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct NodeDedup {
+    pub node_id: String,
+    pub short_name: String,
+    pub long_name: String,
+    #[serde(flatten)]
+    pub counts: DedupCounts,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DedupStats {
+    pub overall: DedupCounts,
+    /// Per sender, most redundant first.
+    pub nodes: Vec<NodeDedup>,
+}
+
+/// One EnvironmentMetrics telemetry report; absent sensors are `None`.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct EnvironmentReading {
+    pub timestamp: i64,
+    /// Degrees Celsius.
+    pub temperature: Option<f64>,
+    /// Percent.
+    pub relative_humidity: Option<f64>,
+    /// hPa.
+    pub barometric_pressure: Option<f64>,
+}
+
+/// One DeviceMetrics telemetry report; absent values are `None`.
+/// Temperature and humidity come as EnvironmentMetrics and are kept in
+/// `environment_readings` (see `environment_series`).
+#[derive(Debug, Clone, Serialize, PartialEq, ToSchema)]
+pub struct DeviceTelemetry {
+    pub timestamp: i64,
+    /// Percent, above 100 when on external power.
+    pub battery_level: Option<u32>,
+    /// Volts.
+    pub voltage: Option<f64>,
+    /// Percent of airtime used by all traffic the node hears.
+    pub channel_utilization: Option<f64>,
+    /// Percent of airtime the node itself transmitted in the last hour.
+    pub air_util_tx: Option<f64>,
+    pub uptime_seconds: Option<u32>,
+}
+
+/// A node heard running the Store & Forward module as a router.
+#[derive(Debug, Clone)]
+pub struct StoreForwardRouter {
+    pub node_id: u32,
+    pub last_seen: i64,
+    /// From the router's last stats reply, if any.
+    pub messages_saved: Option<u32>,
+    pub messages_max: Option<u32>,
+}
+
+/// A DM we sent that hasn't been acknowledged yet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingSend {
+    pub id: i64,
+    pub to_node: u32,
+    pub mesh_channel: u32,
+    pub text: String,
+    pub reply_id: Option<u32>,
+    /// Transmissions so far, including the first.
+    pub attempts: u32,
+}
+
+/// How our DMs to one node fared, for `/api/delivery`.
+#[derive(Debug, Serialize, PartialEq, ToSchema)]
+pub struct NodeDelivery {
+    pub node_id: String,
+    pub short_name: String,
+    pub long_name: String,
+    pub sent: u64,
+    pub acked: u64,
+    pub failed: u64,
+    /// Share of sent DMs that were acknowledged (0.0-1.0).
+    pub success_rate: f64,
+}
+
+/// A node in the `/api/network-graph` topology.
+#[derive(Debug, Serialize, PartialEq, ToSchema)]
+pub struct GraphNode {
+    pub node_id: String,
+    pub short_name: String,
+    pub long_name: String,
+}
+
+/// A NeighborInfo edge: `to` was heard directly by `from` at `snr` dB.
+#[derive(Debug, Serialize, PartialEq, ToSchema)]
+pub struct GraphEdge {
+    pub from: String,
+    pub to: String,
+    pub snr: f64,
+    pub last_seen: i64,
+}
+
+/// Nodes and edges reported by NeighborInfo packets.
+#[derive(Debug, Serialize, PartialEq, ToSchema)]
+pub struct NetworkGraph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
 }
 
-#[derive(Debug, Serialize)]
+/// A node along a traceroute, with its last known position (if any).
+#[derive(Debug, Serialize, PartialEq, ToSchema)]
+pub struct GeoHop {
+    pub node_id: String,
+    pub short_name: Option<String>,
+    pub long_name: Option<String>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+}
+
+/// A traceroute session's routes for drawing on a map: `request` runs from
+/// the source through its relays to the destination, `response` back again.
+/// `response` is empty until a reply was heard.
+#[derive(Debug, Serialize, PartialEq, ToSchema)]
+pub struct TracerouteGeo {
+    pub id: i64,
+    pub status: String,
+    pub request: Vec<GeoHop>,
+    pub response: Vec<GeoHop>,
+}
+
+/// How many nodes the node list held at `timestamp`, for `/api/node-history`.
+#[derive(Debug, Serialize, PartialEq, ToSchema)]
+pub struct NodeCounts {
+    pub timestamp: i64,
+    pub total: u64,
+    /// Nodes last heard over RF
+    pub rf: u64,
+    /// Nodes last heard through MQTT
+    pub mqtt: u64,
+}
+
+/// Latency percentiles of one stage of message handling over the window
+/// ending at `timestamp`, for `/api/latency`.
+#[derive(Debug, Serialize, PartialEq, ToSchema)]
+pub struct LatencyWindow {
+    pub timestamp: i64,
+    /// `handling` (packet received to reply queued) or `queue` (queued to
+    /// handed to the radio)
+    pub stage: String,
+    pub samples: u32,
+    pub p50_ms: u32,
+    pub p90_ms: u32,
+    pub p99_ms: u32,
+    pub max_ms: u32,
+}
+
+/// A message relayed by a bridge, for `/api/bridges/{name}/log`.
+#[derive(Debug, Clone, Serialize, PartialEq, ToSchema)]
+pub struct BridgeLogEntry {
+    pub id: i64,
+    pub timestamp: i64,
+    /// `to_bridge` (mesh to chat) or `to_mesh`
+    pub direction: String,
+    /// `BridgeEvent` name; `message` for chat sent to the mesh
+    pub event: String,
+    /// Mesh node (`!a1b2c3d4`) or chat user the message came from
+    pub source: String,
+    pub channel: u32,
+    pub text: String,
+    /// The mesh packet relayed, or sent for a chat message; `None` for
+    /// events and for chat messages not (yet) sent to the radio.
+    pub mesh_packet_id: Option<u32>,
+}
+
+/// One reported position of a node, for `/api/nodes/{id}/track`.
+#[derive(Debug, Clone, Serialize, PartialEq, ToSchema)]
+pub struct TrackPoint {
+    pub timestamp: i64,
+    pub latitude: f64,
+    pub longitude: f64,
+    /// Metres above mean sea level
+    pub altitude: Option<i32>,
+    /// Ground speed in m/s
+    pub speed: Option<u32>,
+}
+
+/// Row count of one table, for `/api/db` and `!admin db stats`.
+#[derive(Debug, Serialize, PartialEq, ToSchema)]
+pub struct TableRows {
+    pub name: String,
+    pub rows: u64,
+}
+
+/// Size of the database and its tables, largest first.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DbStats {
+    /// Pages in the main database file, in bytes (the WAL is not counted)
+    pub size_bytes: u64,
+    /// Of which unused pages that a VACUUM would give back
+    pub free_bytes: u64,
+    pub tables: Vec<TableRows>,
+}
+
+/// Fewest hops between two nodes over the links known to the mesh graph.
+#[derive(Debug, Serialize, PartialEq, ToSchema)]
+pub struct MeshHops {
+    pub from: String,
+    pub to: String,
+    pub hops: u32,
+}
+
+/// The node-to-node hop matrix for `/api/mesh-hops`, one entry per pair of
+/// nodes that can reach each other (listed once, `from` < `to`).
+#[derive(Debug, Serialize, PartialEq, ToSchema)]
+pub struct MeshHopMatrix {
+    /// When the matrix was last computed; `None` before the first run.
+    pub updated_at: Option<i64>,
+    pub nodes: Vec<GraphNode>,
+    pub pairs: Vec<MeshHops>,
+}
+
+/// A node the mesh depends on: without it `cut_off` could no longer reach
+/// the rest, so a router placed to bridge them would add redundancy.
+#[derive(Debug, Serialize, PartialEq, ToSchema)]
+pub struct RouterSuggestion {
+    pub node_id: String,
+    pub short_name: String,
+    pub long_name: String,
+    pub cut_off: Vec<GraphNode>,
+}
+
+/// A node reporting environment telemetry, with its latest reading.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct EnvironmentSensor {
+    pub node_id: String,
+    pub short_name: String,
+    pub long_name: String,
+    #[serde(flatten)]
+    pub latest: EnvironmentReading,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ThroughputBucket {
     pub hour: String,
     pub incoming: u64,
     pub outgoing: u64,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct DistributionBucket {
     pub label: String,
     pub count: u64,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct TracerouteRequester {
     pub node_id: String,
     pub short_name: String,
@@ -81,7 +644,7 @@ pub struct TracerouteRequester {
     pub via_mqtt: bool,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct TracerouteEvent {
     pub timestamp: i64,
     pub from_node: String,
@@ -97,7 +660,67 @@ pub struct TracerouteEvent {
     pub snr: Option<f32>,
 }
 
-#[derive(Debug, Serialize)]
+/// A text message, sent or received, for the dashboard chat view.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DashboardMessage {
+    /// Row ID; pass as `before` to page back from here.
+    pub id: i64,
+    pub timestamp: i64,
+    pub direction: String,
+    pub from_node: String,
+    pub from_short_name: String,
+    pub from_long_name: String,
+    /// `None` for a channel message.
+    pub to_node: Option<String>,
+    pub channel: u32,
+    pub text: String,
+    pub via_mqtt: bool,
+    /// Outgoing DMs: `acked`, `failed`, or `None` while unanswered.
+    pub ack_status: Option<String>,
+}
+
+/// Filters for `query_packets`; unset fields match everything.
+#[derive(Debug)]
+pub struct PacketQuery {
+    pub from_node: Option<u32>,
+    pub to_node: Option<u32>,
+    /// An empty list matches nothing.
+    pub packet_types: Option<Vec<PacketType>>,
+    /// "in" or "out"
+    pub direction: Option<String>,
+    pub mqtt: MqttFilter,
+    /// Only packets logged after this unix time.
+    pub since: Option<i64>,
+    pub limit: usize,
+    pub offset: usize,
+}
+
+/// A row of the packets table, for the dashboard's packet browser.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PacketRecord {
+    pub id: i64,
+    pub timestamp: i64,
+    pub direction: String,
+    pub packet_type: String,
+    pub from_node: String,
+    pub from_short_name: String,
+    /// `None` for a broadcast.
+    pub to_node: Option<String>,
+    pub channel: u32,
+    pub via_mqtt: bool,
+    /// Connection an incoming packet was heard on.
+    pub radio: Option<String>,
+    pub rssi: Option<i32>,
+    pub snr: Option<f32>,
+    pub hop_count: Option<u32>,
+    pub hop_start: Option<u32>,
+    pub mesh_packet_id: Option<u32>,
+    /// Message text for text packets, empty otherwise.
+    pub text: String,
+    pub ack_status: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
 pub struct TracerouteDestinationSummary {
     pub destination_node: String,
     pub destination_short_name: String,
@@ -110,8 +733,55 @@ pub struct TracerouteDestinationSummary {
     pub avg_hops: Option<f64>,
 }
 
+/// A node along one side of a traceroute session.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TracerouteSessionHop {
+    /// `request` or `response`
+    pub direction: String,
+    pub hop_index: i64,
+    pub node_id: String,
+    pub short_name: Option<String>,
+    pub long_name: Option<String>,
+}
+
+/// A traceroute request and its response correlated into one session, for
+/// `/api/traceroute-sessions`.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TracerouteSession {
+    pub id: i64,
+    pub trace_key: String,
+    pub first_seen: i64,
+    pub last_seen: i64,
+    pub src_node: String,
+    pub src_short_name: Option<String>,
+    pub src_long_name: Option<String>,
+    pub dst_node: Option<String>,
+    pub dst_short_name: Option<String>,
+    pub dst_long_name: Option<String>,
+    pub via_mqtt: bool,
+    pub request_hops: Option<i64>,
+    pub request_hop_start: Option<i64>,
+    pub response_hops: Option<i64>,
+    pub response_hop_start: Option<i64>,
+    /// `observed`, `partial`, `complete` or `expired`
+    pub status: String,
+    pub sample_count: i64,
+    /// Transport each side was heard on: `rf`, `mqtt` or `both`
+    pub request_path: Option<String>,
+    pub response_path: Option<String>,
+    pub hops: Vec<TracerouteSessionHop>,
+}
+
 pub struct Db {
     conn: Mutex<Connection>,
+    /// Read-only connections for dashboard queries
+    /// (`dashboard.read_connections`), so they never hold the writer's lock
+    /// or queue behind each other. Empty means they use the writer.
+    readers: Vec<Mutex<Connection>>,
+    /// Where `read_conn` waits when every reader is busy.
+    next_reader: AtomicUsize,
+    /// The database file, for connections opened beside the writer
+    path: PathBuf,
 }
 
 #[cfg(test)]
@@ -124,6 +794,7 @@ pub struct Node {
     pub first_seen: i64,
     pub last_seen: i64,
     pub last_welcomed: Option<i64>,
+    pub source: String,
 }
 
 #[derive(Debug, Clone)]
@@ -137,7 +808,22 @@ pub struct NodeWithHop {
 
 impl Db {
     pub fn open(path: &Path) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        Self::open_inner(path, false)
+    }
+
+    /// Like `open`, but also opens a database written by a newer schema
+    /// version (`--force`).
+    pub fn open_forced(path: &Path) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        Self::open_inner(path, true)
+    }
+
+    fn open_inner(
+        path: &Path,
+        force: bool,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
         let conn = Connection::open(path)?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        let found_version = Self::check_schema_version(&conn, path, force)?;
         // WAL mode: reads never block writes; persists across reconnects.
         // synchronous=NORMAL: safe with WAL (no data loss on OS crash).
         // optimize: update query planner stats for tables changed since last run.
@@ -148,11 +834,121 @@ impl Db {
         )?;
         let db = Self {
             conn: Mutex::new(conn),
+            readers: Vec::new(),
+            next_reader: AtomicUsize::new(0),
+            path: path.to_path_buf(),
         };
         db.init_schema()?;
+        {
+            let conn = db.conn.lock().unwrap();
+            // A forced open keeps the newer version so that binary still
+            // recognises the database as its own.
+            if found_version < SCHEMA_VERSION {
+                conn.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+            }
+        }
+        db.set_state(WRITER_VERSION_KEY, env!("CARGO_PKG_VERSION"))?;
         Ok(db)
     }
 
+    /// Open `size` read-only connections to the database file at `path` for
+    /// the dashboard queries. WAL mode lets them read while the writer
+    /// connection keeps logging packets.
+    pub fn open_read_pool(
+        &mut self,
+        path: &Path,
+        size: usize,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if path.as_os_str() == ":memory:" {
+            return Err("dashboard.read_connections needs a database file, not :memory:".into());
+        }
+        self.readers = (0..size)
+            .map(|_| {
+                let reader = Connection::open_with_flags(
+                    path,
+                    OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+                )?;
+                reader.busy_timeout(BUSY_TIMEOUT)?;
+                Ok(Mutex::new(reader))
+            })
+            .collect::<Result<_, rusqlite::Error>>()?;
+        Ok(())
+    }
+
+    /// Run `f` on a blocking thread. Every method here holds a SQLite
+    /// connection for as long as its query runs, so async code (the bot,
+    /// modules, the dashboard) calls them through this rather than directly:
+    /// a slow query then never stalls the runtime's workers and the radio
+    /// loop with them.
+    pub async fn run<T, F>(
+        self: &Arc<Self>,
+        f: F,
+    ) -> Result<T, Box<dyn std::error::Error + Send + Sync>>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Db) -> Result<T, Box<dyn std::error::Error + Send + Sync>> + Send + 'static,
+    {
+        let db = Arc::clone(self);
+        tokio::task::spawn_blocking(move || f(&db)).await?
+    }
+
+    /// Connection for dashboard queries: an idle reader from the pool, or
+    /// the next one in turn when all are busy; the writer when there is no
+    /// pool.
+    fn read_conn(&self) -> MutexGuard<'_, Connection> {
+        if self.readers.is_empty() {
+            return self.conn.lock().unwrap();
+        }
+        for reader in &self.readers {
+            if let Ok(conn) = reader.try_lock() {
+                return conn;
+            }
+        }
+        let next = self.next_reader.fetch_add(1, Ordering::Relaxed) % self.readers.len();
+        self.readers[next].lock().unwrap()
+    }
+
+    /// Returns the schema version stored in the database, or an error if it
+    /// is newer than this build understands and `force` is not set.
+    fn check_schema_version(
+        conn: &Connection,
+        path: &Path,
+        force: bool,
+    ) -> Result<i64, Box<dyn std::error::Error + Send + Sync>> {
+        let found: i64 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        if found <= SCHEMA_VERSION {
+            return Ok(found);
+        }
+
+        let writer = conn
+            .query_row(
+                "SELECT value FROM bot_state WHERE key = ?1",
+                params![WRITER_VERSION_KEY],
+                |row| row.get::<_, String>(0),
+            )
+            .unwrap_or_else(|_| "a newer version".to_string());
+        if force {
+            log::warn!(
+                "Opening {} written by meshenger {} (schema v{}) with schema v{} because of --force",
+                path.display(),
+                writer,
+                found,
+                SCHEMA_VERSION
+            );
+            return Ok(found);
+        }
+        Err(format!(
+            "Database {} was written by meshenger {} (schema v{}), but this is meshenger {} (schema v{}). \
+             Upgrade meshenger, or pass --force to open it anyway at the risk of corrupting it.",
+            path.display(),
+            writer,
+            found,
+            env!("CARGO_PKG_VERSION"),
+            SCHEMA_VERSION
+        )
+        .into())
+    }
+
     /// Run PRAGMA optimize to update query planner statistics.
     /// Safe to call periodically on a live connection — only analyzes tables
     /// that have changed significantly since the last run.
@@ -162,6 +958,114 @@ impl Db {
         Ok(())
     }
 
+    /// Size of the database file, in bytes.
+    pub fn size_bytes(&self) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.read_conn();
+        let size = conn.query_row(
+            "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(size)
+    }
+
+    /// Row counts of every table, and the size of the database file.
+    pub fn db_stats(&self) -> Result<DbStats, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.read_conn();
+        let page_size: u64 = conn.query_row("PRAGMA page_size", [], |row| row.get(0))?;
+        let page_count: u64 = conn.query_row("PRAGMA page_count", [], |row| row.get(0))?;
+        let free_pages: u64 = conn.query_row("PRAGMA freelist_count", [], |row| row.get(0))?;
+        let mut stmt = conn.prepare(
+            "SELECT name FROM sqlite_master
+             WHERE type = 'table' AND name NOT LIKE 'sqlite_%'
+             ORDER BY name",
+        )?;
+        let names = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut tables = Vec::with_capacity(names.len());
+        for name in names {
+            let rows =
+                conn.query_row(&format!("SELECT COUNT(*) FROM \"{}\"", name), [], |row| {
+                    row.get(0)
+                })?;
+            tables.push(TableRows { name, rows });
+        }
+        tables.sort_by_key(|t| std::cmp::Reverse(t.rows));
+        Ok(DbStats {
+            size_bytes: page_count * page_size,
+            free_bytes: free_pages * page_size,
+            tables,
+        })
+    }
+
+    /// Rebuild the database file with VACUUM, then refresh planner
+    /// statistics with ANALYZE. Runs on a connection of its own so the
+    /// writer's lock stays free: reads go on, while writes wait for SQLite's
+    /// lock (up to `BUSY_TIMEOUT` each). On a large database it takes a
+    /// while: call it off the async runtime. Returns the file size before
+    /// and after, in bytes.
+    pub fn vacuum(&self) -> Result<(u64, u64), Box<dyn std::error::Error + Send + Sync>> {
+        let run = |conn: &Connection| -> rusqlite::Result<(u64, u64)> {
+            let size = |conn: &Connection| -> rusqlite::Result<u64> {
+                conn.query_row(
+                    "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+                    [],
+                    |row| row.get(0),
+                )
+            };
+            let before = size(conn)?;
+            conn.execute_batch("VACUUM; ANALYZE;")?;
+            let after = size(conn)?;
+            Ok((before, after))
+        };
+        // An in-memory database can't be opened twice
+        if self.path.as_os_str() == ":memory:" {
+            return Ok(run(&self.conn.lock().unwrap())?);
+        }
+        let conn = Connection::open(&self.path)?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        Ok(run(&conn)?)
+    }
+
+    /// Switch the database to `auto_vacuum = INCREMENTAL` so pages freed by
+    /// pruning can be returned with [`Db::incremental_vacuum`]. Changing the
+    /// mode takes a full VACUUM, so the first call on an existing database
+    /// blocks for a while; later calls find it done. Returns whether the
+    /// mode was changed.
+    pub fn enable_incremental_vacuum(
+        &self,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        let mode: i64 = conn.pragma_query_value(None, "auto_vacuum", |row| row.get(0))?;
+        // 2 = INCREMENTAL
+        if mode == 2 {
+            return Ok(false);
+        }
+        conn.execute_batch("PRAGMA auto_vacuum = INCREMENTAL; VACUUM;")?;
+        Ok(true)
+    }
+
+    /// Return free pages to the filesystem (`PRAGMA incremental_vacuum`).
+    /// Does nothing unless [`Db::enable_incremental_vacuum`] ran. Returns the
+    /// bytes released.
+    pub fn incremental_vacuum(&self) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        let size = |conn: &Connection| -> rusqlite::Result<u64> {
+            conn.query_row(
+                "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+                [],
+                |row| row.get(0),
+            )
+        };
+        let before = size(&conn)?;
+        // Frees one page per step, so run it to the end
+        let mut stmt = conn.prepare("PRAGMA incremental_vacuum")?;
+        let mut rows = stmt.query([])?;
+        while rows.next()?.is_some() {}
+        Ok(before.saturating_sub(size(&conn)?))
+    }
+
     fn init_schema(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         let conn = self.conn.lock().unwrap();
 
@@ -175,7 +1079,10 @@ impl Db {
                 last_welcomed  INTEGER,
                 latitude       REAL,
                 longitude      REAL,
-                via_mqtt       INTEGER NOT NULL DEFAULT 0
+                via_mqtt       INTEGER NOT NULL DEFAULT 0,
+                source         TEXT NOT NULL DEFAULT 'observed',
+                online         INTEGER NOT NULL DEFAULT 1,
+                rf_heard       INTEGER NOT NULL DEFAULT 0
             );
 
             CREATE TABLE IF NOT EXISTS packets (
@@ -192,7 +1099,10 @@ impl Db {
                 hop_count  INTEGER,
                 hop_start  INTEGER,
                 mesh_packet_id INTEGER,
-                packet_type TEXT NOT NULL DEFAULT 'text'
+                packet_type TEXT NOT NULL DEFAULT 'text',
+                radio      TEXT,
+                ack_status TEXT,
+                acked_at   INTEGER
             );
 
             CREATE TABLE IF NOT EXISTS mail (
@@ -204,16 +1114,308 @@ impl Db {
                 read       INTEGER NOT NULL DEFAULT 0
             );
 
-            CREATE INDEX IF NOT EXISTS idx_packets_rf_hops_lookup
-            ON packets (from_node, direction, via_mqtt, timestamp DESC, id DESC)
-            WHERE hop_count IS NOT NULL;
+            CREATE INDEX IF NOT EXISTS idx_mail_to_unread
+            ON mail (to_node, read, timestamp);
 
-            CREATE INDEX IF NOT EXISTS idx_packets_rf_last_seen
-            ON packets (from_node, direction, via_mqtt, timestamp DESC, id DESC);
+            CREATE INDEX IF NOT EXISTS idx_mail_from
+            ON mail (from_node);
 
-            CREATE INDEX IF NOT EXISTS idx_packets_rf_hops_stats
+            CREATE TABLE IF NOT EXISTS away_status (
+                node_id    INTEGER PRIMARY KEY,
+                message    TEXT NOT NULL,
+                since      INTEGER NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS away_replies (
+                node_id    INTEGER NOT NULL,
+                sender_id  INTEGER NOT NULL,
+                replied_at INTEGER NOT NULL,
+                PRIMARY KEY (node_id, sender_id)
+            );
+
+            CREATE TABLE IF NOT EXISTS muted_nodes (
+                node_id    INTEGER PRIMARY KEY,
+                muted_at   INTEGER NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS node_notes (
+                id         INTEGER PRIMARY KEY AUTOINCREMENT,
+                node_id    INTEGER NOT NULL,
+                author_id  INTEGER NOT NULL,
+                note       TEXT NOT NULL,
+                created_at INTEGER NOT NULL
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_node_notes_node
+            ON node_notes (node_id, created_at);
+
+            CREATE TABLE IF NOT EXISTS polls (
+                id         INTEGER PRIMARY KEY AUTOINCREMENT,
+                question   TEXT NOT NULL,
+                options    TEXT NOT NULL,
+                channel    INTEGER NOT NULL,
+                created_by INTEGER NOT NULL,
+                created_at INTEGER NOT NULL,
+                closes_at  INTEGER,
+                closed_at  INTEGER
+            );
+
+            CREATE TABLE IF NOT EXISTS poll_votes (
+                poll_id      INTEGER NOT NULL,
+                node_id      INTEGER NOT NULL,
+                option_index INTEGER NOT NULL,
+                voted_at     INTEGER NOT NULL,
+                PRIMARY KEY (poll_id, node_id)
+            );
+
+            CREATE TABLE IF NOT EXISTS nets (
+                id            INTEGER PRIMARY KEY AUTOINCREMENT,
+                name          TEXT NOT NULL,
+                channel       INTEGER NOT NULL,
+                started_at    INTEGER NOT NULL,
+                ends_at       INTEGER NOT NULL,
+                roster_posted INTEGER NOT NULL DEFAULT 0,
+                UNIQUE (name, started_at)
+            );
+
+            CREATE TABLE IF NOT EXISTS net_checkins (
+                net_id        INTEGER NOT NULL,
+                node_id       INTEGER NOT NULL,
+                callsign      TEXT NOT NULL,
+                comment       TEXT,
+                checked_in_at INTEGER NOT NULL,
+                PRIMARY KEY (net_id, node_id)
+            );
+
+            CREATE TABLE IF NOT EXISTS incidents (
+                id             INTEGER PRIMARY KEY AUTOINCREMENT,
+                node_id        INTEGER NOT NULL,
+                mesh_packet_id INTEGER NOT NULL,
+                channel        INTEGER NOT NULL,
+                keyword        TEXT NOT NULL,
+                text           TEXT NOT NULL,
+                latitude       REAL,
+                longitude      REAL,
+                created_at     INTEGER NOT NULL,
+                UNIQUE (node_id, mesh_packet_id)
+            );
+
+            CREATE TABLE IF NOT EXISTS api_usage (
+                day   TEXT NOT NULL,
+                api   TEXT NOT NULL,
+                calls INTEGER NOT NULL,
+                PRIMARY KEY (day, api)
+            );
+
+            CREATE TABLE IF NOT EXISTS bot_state (
+                key   TEXT PRIMARY KEY,
+                value TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS settings (
+                key        TEXT PRIMARY KEY,
+                value      TEXT NOT NULL,
+                updated_at INTEGER NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS short_links (
+                code       TEXT PRIMARY KEY,
+                url        TEXT NOT NULL,
+                created_at INTEGER NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS environment_readings (
+                id                  INTEGER PRIMARY KEY AUTOINCREMENT,
+                node_id             INTEGER NOT NULL,
+                timestamp           INTEGER NOT NULL,
+                temperature         REAL,
+                relative_humidity   REAL,
+                barometric_pressure REAL
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_environment_node_time
+            ON environment_readings (node_id, timestamp);
+
+            CREATE TABLE IF NOT EXISTS position_history (
+                node_id   INTEGER NOT NULL,
+                timestamp INTEGER NOT NULL,
+                latitude  REAL NOT NULL,
+                longitude REAL NOT NULL,
+                altitude  INTEGER,
+                speed     INTEGER
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_position_history_node_time
+            ON position_history (node_id, timestamp);
+
+            CREATE TABLE IF NOT EXISTS pending_sends (
+                id           INTEGER PRIMARY KEY AUTOINCREMENT,
+                packet_id    INTEGER NOT NULL,
+                to_node      INTEGER NOT NULL,
+                mesh_channel INTEGER NOT NULL,
+                text         TEXT NOT NULL,
+                reply_id     INTEGER,
+                attempts     INTEGER NOT NULL DEFAULT 1,
+                created_at   INTEGER NOT NULL,
+                sent_at      INTEGER NOT NULL,
+                failed       INTEGER NOT NULL DEFAULT 0
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_pending_sends_to_node
+            ON pending_sends (to_node);
+
+            CREATE TABLE IF NOT EXISTS neighbors (
+                node_id     INTEGER NOT NULL,
+                neighbor_id INTEGER NOT NULL,
+                snr         REAL NOT NULL,
+                last_seen   INTEGER NOT NULL,
+                PRIMARY KEY (node_id, neighbor_id)
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_neighbors_last_seen
+            ON neighbors (last_seen);
+
+            CREATE TABLE IF NOT EXISTS node_counts (
+                timestamp INTEGER PRIMARY KEY,
+                total     INTEGER NOT NULL,
+                rf        INTEGER NOT NULL,
+                mqtt      INTEGER NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS latency_stats (
+                timestamp INTEGER NOT NULL,
+                stage     TEXT NOT NULL,
+                samples   INTEGER NOT NULL,
+                p50_ms    INTEGER NOT NULL,
+                p90_ms    INTEGER NOT NULL,
+                p99_ms    INTEGER NOT NULL,
+                max_ms    INTEGER NOT NULL,
+                PRIMARY KEY (timestamp, stage)
+            );
+
+            CREATE TABLE IF NOT EXISTS mesh_hops (
+                from_node  INTEGER NOT NULL,
+                to_node    INTEGER NOT NULL,
+                hops       INTEGER NOT NULL,
+                updated_at INTEGER NOT NULL,
+                PRIMARY KEY (from_node, to_node)
+            );
+
+            CREATE TABLE IF NOT EXISTS mesh_cut_nodes (
+                node_id      INTEGER NOT NULL,
+                cut_off_node INTEGER NOT NULL,
+                updated_at   INTEGER NOT NULL,
+                PRIMARY KEY (node_id, cut_off_node)
+            );
+
+            CREATE TABLE IF NOT EXISTS telemetry (
+                id                  INTEGER PRIMARY KEY AUTOINCREMENT,
+                node_id             INTEGER NOT NULL,
+                timestamp           INTEGER NOT NULL,
+                battery_level       INTEGER,
+                voltage             REAL,
+                channel_utilization REAL,
+                air_util_tx         REAL,
+                uptime_seconds      INTEGER
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_telemetry_node_time
+            ON telemetry (node_id, timestamp);
+
+            CREATE TABLE IF NOT EXISTS selftest_runs (
+                id        INTEGER PRIMARY KEY AUTOINCREMENT,
+                packet_id INTEGER NOT NULL,
+                channel   INTEGER NOT NULL,
+                sent_at   INTEGER NOT NULL
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_selftest_runs_packet
+            ON selftest_runs (packet_id);
+
+            CREATE TABLE IF NOT EXISTS selftest_responses (
+                run_id      INTEGER NOT NULL,
+                node_id     INTEGER NOT NULL,
+                kind        TEXT NOT NULL,
+                observed_at INTEGER NOT NULL,
+                PRIMARY KEY (run_id, node_id)
+            );
+
+            CREATE TABLE IF NOT EXISTS battery_alerts (
+                node_id       INTEGER PRIMARY KEY,
+                level         TEXT NOT NULL,
+                battery_level INTEGER NOT NULL,
+                updated_at    INTEGER NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS alert_state (
+                rule     TEXT NOT NULL,
+                node_id  INTEGER NOT NULL,
+                fired_at INTEGER NOT NULL,
+                PRIMARY KEY (rule, node_id)
+            );
+
+            CREATE TABLE IF NOT EXISTS bridge_log (
+                id             INTEGER PRIMARY KEY AUTOINCREMENT,
+                bridge         TEXT NOT NULL,
+                timestamp      INTEGER NOT NULL,
+                direction      TEXT NOT NULL,
+                event          TEXT NOT NULL,
+                source         TEXT NOT NULL,
+                channel        INTEGER NOT NULL,
+                text           TEXT NOT NULL,
+                mesh_packet_id INTEGER
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_bridge_log_bridge_time
+            ON bridge_log (bridge, timestamp);
+
+            CREATE TABLE IF NOT EXISTS weather_alerts (
+                alert_id    TEXT PRIMARY KEY,
+                received_at INTEGER NOT NULL,
+                expires_at  INTEGER
+            );
+
+            CREATE TABLE IF NOT EXISTS mqtt_commands (
+                id        INTEGER PRIMARY KEY AUTOINCREMENT,
+                timestamp INTEGER NOT NULL,
+                node_id   INTEGER NOT NULL,
+                command   TEXT NOT NULL,
+                action    TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS store_forward_routers (
+                node_id        INTEGER PRIMARY KEY,
+                first_seen     INTEGER NOT NULL,
+                last_seen      INTEGER NOT NULL,
+                messages_saved INTEGER,
+                messages_max   INTEGER
+            );
+
+            CREATE TABLE IF NOT EXISTS traceroute_hop_stats (
+                node_id    INTEGER PRIMARY KEY,
+                min_hop    INTEGER NOT NULL,
+                hop_sum    INTEGER NOT NULL,
+                samples    INTEGER NOT NULL,
+                updated_at INTEGER NOT NULL
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_packets_rf_hops_lookup
+            ON packets (from_node, direction, via_mqtt, timestamp DESC, id DESC)
+            WHERE hop_count IS NOT NULL;
+
+            CREATE INDEX IF NOT EXISTS idx_packets_rf_last_seen
+            ON packets (from_node, direction, via_mqtt, timestamp DESC, id DESC);
+
+            CREATE INDEX IF NOT EXISTS idx_packets_rf_hops_stats
             ON packets (direction, via_mqtt, from_node, hop_count)
-            WHERE hop_count IS NOT NULL;",
+            WHERE hop_count IS NOT NULL;
+
+            -- Packet browser: sender lookups use idx_packets_rf_last_seen
+            CREATE INDEX IF NOT EXISTS idx_packets_to_node_time
+            ON packets (to_node, timestamp);
+
+            CREATE INDEX IF NOT EXISTS idx_packets_type_time
+            ON packets (packet_type, timestamp);",
         )?;
 
         let has_mesh_packet_id: i64 = conn.query_row(
@@ -225,6 +1427,37 @@ impl Db {
             conn.execute("ALTER TABLE packets ADD COLUMN mesh_packet_id INTEGER", [])?;
         }
 
+        // Label of the connection an incoming packet was received on.
+        let has_radio: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('packets') WHERE name = 'radio'",
+            [],
+            |row| row.get(0),
+        )?;
+        if has_radio == 0 {
+            conn.execute("ALTER TABLE packets ADD COLUMN radio TEXT", [])?;
+        }
+
+        // Delivery of our DMs: 'acked' or 'failed' from routing replies, set
+        // at `acked_at`; NULL while unanswered.
+        for (column, sql_type) in [("ack_status", "TEXT"), ("acked_at", "INTEGER")] {
+            let exists: i64 = conn.query_row(
+                "SELECT COUNT(*) FROM pragma_table_info('packets') WHERE name = ?1",
+                params![column],
+                |row| row.get(0),
+            )?;
+            if exists == 0 {
+                conn.execute(
+                    &format!("ALTER TABLE packets ADD COLUMN {} {}", column, sql_type),
+                    [],
+                )?;
+            }
+        }
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_packets_out_mesh_id
+             ON packets (mesh_packet_id) WHERE direction = 'out'",
+            [],
+        )?;
+
         conn.execute_batch(
             "CREATE TABLE IF NOT EXISTS traceroute_sessions (
                 id                 INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -269,8 +1502,119 @@ impl Db {
             ON traceroute_session_hops (session_id, direction, hop_index);
 
             CREATE INDEX IF NOT EXISTS idx_tr_hops_packet_ref
-            ON traceroute_session_hops (packet_id_ref);",
+            ON traceroute_session_hops (packet_id_ref);
+
+            CREATE INDEX IF NOT EXISTS idx_tr_hops_observed_at
+            ON traceroute_session_hops (observed_at);",
+        )?;
+
+        // Unread mail past the expiry window is flagged here until its sender
+        // has been told, then deleted.
+        let has_mail_expired_at: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('mail') WHERE name = 'expired_at'",
+            [],
+            |row| row.get(0),
+        )?;
+        if has_mail_expired_at == 0 {
+            conn.execute("ALTER TABLE mail ADD COLUMN expired_at INTEGER", [])?;
+        }
+
+        // PKI public key from the node's first NodeInfo that carried one.
+        let has_public_key: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('nodes') WHERE name = 'public_key'",
+            [],
+            |row| row.get(0),
+        )?;
+        if has_public_key == 0 {
+            conn.execute("ALTER TABLE nodes ADD COLUMN public_key BLOB", [])?;
+        }
+
+        // How the node was first recorded: 'nodedb' when imported from the
+        // radio's node database on connect, 'observed' when heard directly.
+        let has_source: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('nodes') WHERE name = 'source'",
+            [],
+            |row| row.get(0),
+        )?;
+        if has_source == 0 {
+            conn.execute(
+                "ALTER TABLE nodes ADD COLUMN source TEXT NOT NULL DEFAULT 'observed'",
+                [],
+            )?;
+        }
+
+        // Cleared by the liveness check once a node goes silent, set again
+        // when it is heard.
+        let has_online: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('nodes') WHERE name = 'online'",
+            [],
+            |row| row.get(0),
         )?;
+        if has_online == 0 {
+            conn.execute(
+                "ALTER TABLE nodes ADD COLUMN online INTEGER NOT NULL DEFAULT 1",
+                [],
+            )?;
+        }
+
+        // Set once the node is heard over RF and never cleared, so a node
+        // last relayed through MQTT, or whose RF packets were pruned, still
+        // counts as local. Existing nodes are judged by what is logged.
+        let has_rf_heard: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('nodes') WHERE name = 'rf_heard'",
+            [],
+            |row| row.get(0),
+        )?;
+        if has_rf_heard == 0 {
+            conn.execute_batch(
+                "ALTER TABLE nodes ADD COLUMN rf_heard INTEGER NOT NULL DEFAULT 0;
+                 UPDATE nodes SET rf_heard = 1
+                 WHERE via_mqtt = 0
+                    OR EXISTS (
+                        SELECT 1 FROM packets p
+                        WHERE p.from_node = nodes.node_id AND p.direction = 'in' AND p.via_mqtt = 0
+                    );",
+            )?;
+        }
+
+        // Set when mail is read with `[mail] read_receipts` on, cleared once
+        // the sender has been told.
+        let has_receipt_pending: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('mail') WHERE name = 'receipt_pending'",
+            [],
+            |row| row.get(0),
+        )?;
+        if has_receipt_pending == 0 {
+            conn.execute(
+                "ALTER TABLE mail ADD COLUMN receipt_pending INTEGER NOT NULL DEFAULT 0",
+                [],
+            )?;
+        }
+
+        // Optional auto-close time given with `!poll start`.
+        let has_closes_at: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('polls') WHERE name = 'closes_at'",
+            [],
+            |row| row.get(0),
+        )?;
+        if has_closes_at == 0 {
+            conn.execute("ALTER TABLE polls ADD COLUMN closes_at INTEGER", [])?;
+        }
+
+        // Which transport each side of a session was heard on: 'rf', 'mqtt' or 'both'.
+        for column in ["request_path", "response_path"] {
+            let exists: i64 = conn.query_row(
+                "SELECT COUNT(*) FROM pragma_table_info('traceroute_sessions') WHERE name = ?1",
+                params![column],
+                |row| row.get(0),
+            )?;
+            if exists == 0 {
+                conn.execute(
+                    &format!("ALTER TABLE traceroute_sessions ADD COLUMN {} TEXT", column),
+                    [],
+                )?;
+            }
+        }
 
         Ok(())
     }
@@ -285,18 +1629,60 @@ impl Db {
         let conn = self.conn.lock().unwrap();
         let now = Utc::now().timestamp();
         conn.execute(
-            "INSERT INTO nodes (node_id, short_name, long_name, first_seen, last_seen, via_mqtt)
-             VALUES (?1, ?2, ?3, ?4, ?4, ?5)
+            "INSERT INTO nodes (node_id, short_name, long_name, first_seen, last_seen, via_mqtt, rf_heard)
+             VALUES (?1, ?2, ?3, ?4, ?4, ?5, ?5 = 0 OR EXISTS (
+                 SELECT 1 FROM packets WHERE from_node = ?1 AND direction = 'in' AND via_mqtt = 0
+             ))
              ON CONFLICT(node_id) DO UPDATE SET
                 short_name = CASE WHEN ?2 != '' THEN ?2 ELSE short_name END,
                 long_name  = CASE WHEN ?3 != '' THEN ?3 ELSE long_name END,
                 last_seen  = ?4,
-                via_mqtt   = ?5",
+                via_mqtt   = ?5,
+                rf_heard   = MAX(rf_heard, ?5 = 0)",
             params![node_id as i64, short_name, long_name, now, via_mqtt as i64],
         )?;
         Ok(())
     }
 
+    /// Upsert a node from the radio's NodeDB, dated by when the radio last
+    /// heard it rather than now. A node `created` by the import (checked
+    /// before any module upserted it) takes `heard_at` as both first and last
+    /// seen and is marked `source = 'nodedb'`; a known node only has
+    /// `first_seen` moved earlier and `last_seen` moved later.
+    pub fn import_node(
+        &self,
+        node_id: u32,
+        short_name: &str,
+        long_name: &str,
+        via_mqtt: bool,
+        heard_at: i64,
+        created: bool,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        let heard_at = heard_at.min(Utc::now().timestamp());
+        conn.execute(
+            "INSERT INTO nodes (node_id, short_name, long_name, first_seen, last_seen, via_mqtt, rf_heard, source)
+             VALUES (?1, ?2, ?3, ?4, ?4, ?5, ?5 = 0, 'nodedb')
+             ON CONFLICT(node_id) DO UPDATE SET
+                short_name = CASE WHEN ?2 != '' THEN ?2 ELSE short_name END,
+                long_name  = CASE WHEN ?3 != '' THEN ?3 ELSE long_name END,
+                first_seen = CASE WHEN ?6 THEN ?4 ELSE MIN(first_seen, ?4) END,
+                last_seen  = CASE WHEN ?6 THEN ?4 ELSE MAX(last_seen, ?4) END,
+                via_mqtt   = ?5,
+                rf_heard   = MAX(rf_heard, ?5 = 0),
+                source     = CASE WHEN ?6 THEN 'nodedb' ELSE source END",
+            params![
+                node_id as i64,
+                short_name,
+                long_name,
+                heard_at,
+                via_mqtt as i64,
+                created
+            ],
+        )?;
+        Ok(())
+    }
+
     pub fn is_node_new(
         &self,
         node_id: u32,
@@ -310,6 +1696,40 @@ impl Db {
         Ok(count == 0)
     }
 
+    /// Mark nodes last heard before `cutoff` offline and those heard since
+    /// online again, returning the nodes that changed.
+    pub fn update_node_liveness(
+        &self,
+        cutoff: i64,
+    ) -> Result<Vec<LivenessChange>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let changes = {
+            let mut stmt = tx.prepare(
+                "SELECT node_id, last_seen >= ?1, last_seen, via_mqtt FROM nodes
+                 WHERE online != (last_seen >= ?1)
+                 ORDER BY last_seen",
+            )?;
+            let changes = stmt
+                .query_map(params![cutoff], |row| {
+                    Ok(LivenessChange {
+                        node_id: row.get::<_, i64>(0)? as u32,
+                        online: row.get(1)?,
+                        last_seen: row.get(2)?,
+                        via_mqtt: row.get(3)?,
+                    })
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            changes
+        };
+        tx.execute(
+            "UPDATE nodes SET online = (last_seen >= ?1) WHERE online != (last_seen >= ?1)",
+            params![cutoff],
+        )?;
+        tx.commit()?;
+        Ok(changes)
+    }
+
     pub fn is_node_absent(
         &self,
         node_id: u32,
@@ -346,7 +1766,7 @@ impl Db {
     pub fn get_all_nodes(&self) -> Result<Vec<Node>, Box<dyn std::error::Error + Send + Sync>> {
         let conn = self.conn.lock().unwrap();
         let mut stmt = conn.prepare(
-            "SELECT node_id, short_name, long_name, first_seen, last_seen, last_welcomed
+            "SELECT node_id, short_name, long_name, first_seen, last_seen, last_welcomed, source
              FROM nodes ORDER BY last_seen DESC",
         )?;
         let nodes = stmt
@@ -358,6 +1778,7 @@ impl Db {
                     first_seen: row.get(3)?,
                     last_seen: row.get(4)?,
                     last_welcomed: row.get(5)?,
+                    source: row.get(6)?,
                 })
             })?
             .collect::<Result<Vec<_>, _>>()?;
@@ -427,6 +1848,44 @@ impl Db {
         }
     }
 
+    /// Record the public key a node announced. The first key is kept, so a
+    /// spoofed NodeInfo can't replace it: returns `false` when a different
+    /// key is already stored. Purging the node forgets its key.
+    pub fn remember_public_key(
+        &self,
+        node_id: u32,
+        public_key: &[u8],
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        {
+            let conn = self.conn.lock().unwrap();
+            conn.execute(
+                "UPDATE nodes SET public_key = ?2 WHERE node_id = ?1 AND public_key IS NULL",
+                params![node_id as i64, public_key],
+            )?;
+        }
+        Ok(self
+            .get_public_key(node_id)?
+            .is_none_or(|stored| stored == public_key))
+    }
+
+    /// The public key stored by `remember_public_key`, if any.
+    pub fn get_public_key(
+        &self,
+        node_id: u32,
+    ) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        let row = conn.query_row(
+            "SELECT public_key FROM nodes WHERE node_id = ?1",
+            params![node_id as i64],
+            |row| row.get::<_, Option<Vec<u8>>>(0),
+        );
+        match row {
+            Ok(key) => Ok(key),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
     pub fn update_position(
         &self,
         node_id: u32,
@@ -442,7 +1901,63 @@ impl Db {
         Ok(())
     }
 
-    pub fn purge_nodes_not_seen_within(
+    /// Append a reported position to the node's track. The latest position
+    /// itself lives in `nodes` (`update_position`).
+    pub fn log_position(
+        &self,
+        node_id: u32,
+        point: &TrackPoint,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO position_history (node_id, timestamp, latitude, longitude, altitude, speed)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                node_id as i64,
+                point.timestamp,
+                point.latitude,
+                point.longitude,
+                point.altitude,
+                point.speed
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// The node's positions from the last `hours` (0 = all), oldest first.
+    pub fn node_track(
+        &self,
+        node_id: u32,
+        hours: u32,
+    ) -> Result<Vec<TrackPoint>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.read_conn();
+        let since = if hours == 0 {
+            0
+        } else {
+            Utc::now().timestamp() - (hours as i64 * 3600)
+        };
+        let mut stmt = conn.prepare(
+            "SELECT timestamp, latitude, longitude, altitude, speed
+             FROM position_history
+             WHERE node_id = ?1 AND timestamp > ?2
+             ORDER BY timestamp",
+        )?;
+        let rows = stmt
+            .query_map(params![node_id as i64, since], |row| {
+                Ok(TrackPoint {
+                    timestamp: row.get(0)?,
+                    latitude: row.get(1)?,
+                    longitude: row.get(2)?,
+                    altitude: row.get(3)?,
+                    speed: row.get(4)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Delete track points reported more than `max_age_secs` ago.
+    pub fn prune_position_history_older_than(
         &self,
         max_age_secs: u64,
     ) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
@@ -450,1748 +1965,6797 @@ impl Db {
             .map_err(|_| "max_age_secs too large for timestamp arithmetic")?;
         let cutoff = Utc::now().timestamp() - max_age_secs;
         let conn = self.conn.lock().unwrap();
-        let deleted = conn.execute("DELETE FROM nodes WHERE last_seen < ?1", params![cutoff])?;
+        let deleted = conn.execute(
+            "DELETE FROM position_history WHERE timestamp < ?1",
+            params![cutoff],
+        )?;
         Ok(deleted)
     }
 
-    pub fn get_node_position(
-        &self,
-        node_id: u32,
-    ) -> Result<Option<(f64, f64)>, Box<dyn std::error::Error + Send + Sync>> {
-        let conn = self.conn.lock().unwrap();
-        let result: Result<(Option<f64>, Option<f64>), _> = conn.query_row(
-            "SELECT latitude, longitude FROM nodes WHERE node_id = ?1",
-            params![node_id as i64],
-            |row| Ok((row.get(0)?, row.get(1)?)),
-        );
-        match result {
-            Ok((Some(lat), Some(lon))) if lat != 0.0 || lon != 0.0 => Ok(Some((lat, lon))),
-            _ => Ok(None),
-        }
-    }
-
-    pub fn message_count(
+    /// Delete `packet_type` packets logged more than `max_age_secs` ago, a
+    /// batch at a time so packet logging isn't held up for long on a big
+    /// table.
+    pub fn prune_packets_older_than(
         &self,
-        direction: &str,
-    ) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
-        let conn = self.conn.lock().unwrap();
-        let count: i64 = conn.query_row(
-            "SELECT COUNT(*) FROM packets WHERE direction = ?1",
-            params![direction],
-            |row| row.get(0),
-        )?;
-        Ok(count as u64)
-    }
-
-    pub fn node_count(&self) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
-        let conn = self.conn.lock().unwrap();
-        let count: i64 = conn.query_row("SELECT COUNT(*) FROM nodes", [], |row| row.get(0))?;
-        Ok(count as u64)
-    }
-
-    #[cfg(test)]
-    pub fn find_node_by_name(
-        &self,
-        name: &str,
-    ) -> Result<Option<u32>, Box<dyn std::error::Error + Send + Sync>> {
-        // Try parsing as node ID (hex with/without prefix, or decimal)
-        if let Some(id) = parse_node_id(name) {
+        packet_type: PacketType,
+        max_age_secs: u64,
+    ) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        let max_age_secs = i64::try_from(max_age_secs)
+            .map_err(|_| "max_age_secs too large for timestamp arithmetic")?;
+        let cutoff = Utc::now().timestamp() - max_age_secs;
+        let mut deleted = 0;
+        loop {
             let conn = self.conn.lock().unwrap();
-            let exists: i64 = conn.query_row(
-                "SELECT COUNT(*) FROM nodes WHERE node_id = ?1",
-                params![id as i64],
-                |row| row.get(0),
+            let batch = conn.execute(
+                "DELETE FROM packets WHERE id IN (
+                    SELECT id FROM packets
+                    WHERE packet_type = ?1 AND timestamp < ?2
+                    LIMIT ?3
+                 )",
+                params![packet_type, cutoff, PRUNE_BATCH_ROWS],
             )?;
-            if exists > 0 {
-                return Ok(Some(id));
+            deleted += batch;
+            if batch < PRUNE_BATCH_ROWS {
+                return Ok(deleted);
             }
         }
-
-        // Try matching by short_name or long_name (case-insensitive)
-        let conn = self.conn.lock().unwrap();
-        let result: Result<i64, _> = conn.query_row(
-            "SELECT node_id FROM nodes WHERE lower(short_name) = lower(?1) OR lower(long_name) = lower(?1) LIMIT 1",
-            params![name],
-            |row| row.get(0),
-        );
-        match result {
-            Ok(id) => Ok(Some(id as u32)),
-            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-            Err(e) => Err(e.into()),
-        }
-    }
-
-    /// Return up to `limit` most recently seen RF nodes missing inbound RF hop metadata.
-    pub fn recent_rf_nodes_missing_hops(
-        &self,
-        max_age_secs: u64,
-        exclude_node_id: Option<u32>,
-        limit: usize,
-    ) -> Result<Vec<u32>, Box<dyn std::error::Error + Send + Sync>> {
-        let conn = self.conn.lock().unwrap();
-        let since = Utc::now().timestamp() - (max_age_secs as i64);
-        let exclude = exclude_node_id.unwrap_or(0) as i64;
-        let mut stmt = conn.prepare(
-            "SELECT n.node_id
-             FROM nodes n
-             WHERE n.via_mqtt = 0
-               AND n.last_seen > ?1
-               AND (?2 = 0 OR n.node_id != ?2)
-               AND NOT EXISTS (
-                   SELECT 1
-                   FROM packets p
-                   WHERE p.from_node = n.node_id
-                     AND p.direction = 'in'
-                     AND p.via_mqtt = 0
-                     AND p.hop_count IS NOT NULL
-               )
-             ORDER BY n.last_seen DESC
-             LIMIT ?3",
-        )?;
-        let rows = stmt
-            .query_map(params![since, exclude, limit as i64], |row| {
-                row.get::<_, i64>(0)
-            })?
-            .collect::<Result<Vec<_>, _>>()?;
-        Ok(rows.into_iter().map(|id| id as u32).collect())
     }
 
-    // --- Packet logging ---
+    // --- Bridge log ---
 
+    /// Record a message relayed by `bridge`; returns its row ID.
     #[allow(clippy::too_many_arguments)]
-    fn log_packet_inner(
+    pub fn log_bridge_relay(
         &self,
-        from_node: u32,
-        to_node: Option<u32>,
+        bridge: &str,
+        direction: &str,
+        event: &str,
+        source: &str,
         channel: u32,
         text: &str,
-        direction: &str,
-        via_mqtt: bool,
-        rssi: Option<i32>,
-        snr: Option<f32>,
-        hop_count: Option<u32>,
-        hop_start: Option<u32>,
         mesh_packet_id: Option<u32>,
-        packet_type: &str,
     ) -> Result<i64, Box<dyn std::error::Error + Send + Sync>> {
         let conn = self.conn.lock().unwrap();
-        let now = Utc::now().timestamp();
         conn.execute(
-            "INSERT INTO packets (timestamp, from_node, to_node, channel, text, direction, via_mqtt, rssi, snr, hop_count, hop_start, mesh_packet_id, packet_type)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+            "INSERT INTO bridge_log
+                (bridge, timestamp, direction, event, source, channel, text, mesh_packet_id)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
             params![
-                now,
-                from_node as i64,
-                to_node.map(|n| n as i64),
-                channel as i64,
-                text,
+                bridge,
+                Utc::now().timestamp(),
                 direction,
-                via_mqtt as i64,
-                rssi,
-                snr,
-                hop_count.map(|h| h as i64),
-                hop_start.map(|h| h as i64),
-                mesh_packet_id.map(|m| m as i64),
-                packet_type,
+                event,
+                source,
+                channel,
+                text,
+                mesh_packet_id.map(|id| id as i64)
             ],
         )?;
         Ok(conn.last_insert_rowid())
     }
 
-    #[allow(clippy::too_many_arguments)]
-    pub fn log_packet(
+    /// Note the packet a logged chat message went out as. Long messages go
+    /// out in several; the first one is kept.
+    pub fn set_bridge_log_packet(
         &self,
-        from_node: u32,
-        to_node: Option<u32>,
-        channel: u32,
-        text: &str,
-        direction: &str,
-        via_mqtt: bool,
-        rssi: Option<i32>,
-        snr: Option<f32>,
-        hop_count: Option<u32>,
-        hop_start: Option<u32>,
-        packet_type: &str,
+        id: i64,
+        mesh_packet_id: u32,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        self.log_packet_inner(
-            from_node,
-            to_node,
-            channel,
-            text,
-            direction,
-            via_mqtt,
-            rssi,
-            snr,
-            hop_count,
-            hop_start,
-            None,
-            packet_type,
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE bridge_log SET mesh_packet_id = ?2
+             WHERE id = ?1 AND mesh_packet_id IS NULL",
+            params![id, mesh_packet_id as i64],
         )?;
         Ok(())
     }
 
-    #[allow(clippy::too_many_arguments)]
-    pub fn log_packet_with_mesh_id(
-        &self,
-        from_node: u32,
-        to_node: Option<u32>,
-        channel: u32,
-        text: &str,
-        direction: &str,
-        via_mqtt: bool,
-        rssi: Option<i32>,
-        snr: Option<f32>,
-        hop_count: Option<u32>,
-        hop_start: Option<u32>,
-        mesh_packet_id: Option<u32>,
-        packet_type: &str,
-    ) -> Result<i64, Box<dyn std::error::Error + Send + Sync>> {
-        self.log_packet_inner(
-            from_node,
-            to_node,
-            channel,
-            text,
-            direction,
-            via_mqtt,
-            rssi,
-            snr,
-            hop_count,
-            hop_start,
-            mesh_packet_id,
-            packet_type,
-        )
-    }
-
-    // --- Dashboard queries ---
-
-    pub fn dashboard_overview(
+    /// `bridge`'s relays from the last `hours` (0 = all), newest first.
+    pub fn bridge_log(
         &self,
+        bridge: &str,
         hours: u32,
-        filter: MqttFilter,
-        bot_name: &str,
-    ) -> Result<DashboardOverview, Box<dyn std::error::Error + Send + Sync>> {
-        let conn = self.conn.lock().unwrap();
-        let node_count: i64 = conn.query_row("SELECT COUNT(*) FROM nodes", [], |row| row.get(0))?;
+        limit: usize,
+    ) -> Result<Vec<BridgeLogEntry>, Box<dyn std::error::Error + Send + Sync>> {
         let since = if hours == 0 {
             0
         } else {
-            Utc::now().timestamp() - (hours as i64 * 3600)
+            Utc::now().timestamp() - hours as i64 * 3600
         };
+        let conn = self.read_conn();
+        let mut stmt = conn.prepare(
+            "SELECT id, timestamp, direction, event, source, channel, text, mesh_packet_id
+             FROM bridge_log
+             WHERE bridge = ?1 AND timestamp >= ?2
+             ORDER BY timestamp DESC, id DESC
+             LIMIT ?3",
+        )?;
+        let rows = stmt
+            .query_map(params![bridge, since, limit as i64], |row| {
+                Ok(BridgeLogEntry {
+                    id: row.get(0)?,
+                    timestamp: row.get(1)?,
+                    direction: row.get(2)?,
+                    event: row.get(3)?,
+                    source: row.get(4)?,
+                    channel: row.get(5)?,
+                    text: row.get(6)?,
+                    mesh_packet_id: row.get::<_, Option<i64>>(7)?.map(|id| id as u32),
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
 
-        let mqtt_clause = filter.sql_clause();
-
-        // Text messages only
-        let query_msg_in = format!(
-            "SELECT COUNT(*) FROM packets WHERE direction = 'in' AND packet_type = 'text' AND timestamp > ?1{}",
-            mqtt_clause
-        );
-        let messages_in: i64 = conn.query_row(&query_msg_in, params![since], |row| row.get(0))?;
-
-        let query_msg_out = format!(
-            "SELECT COUNT(*) FROM packets WHERE direction = 'out' AND packet_type = 'text' AND timestamp > ?1{}",
-            mqtt_clause
-        );
-        let messages_out: i64 = conn.query_row(&query_msg_out, params![since], |row| row.get(0))?;
-
-        // All packet types
-        let query_pkt_in = format!(
-            "SELECT COUNT(*) FROM packets WHERE direction = 'in' AND timestamp > ?1{}",
-            mqtt_clause
-        );
-        let packets_in: i64 = conn.query_row(&query_pkt_in, params![since], |row| row.get(0))?;
-
-        let query_pkt_out = format!(
-            "SELECT COUNT(*) FROM packets WHERE direction = 'out' AND timestamp > ?1{}",
-            mqtt_clause
-        );
-        let packets_out: i64 = conn.query_row(&query_pkt_out, params![since], |row| row.get(0))?;
+    /// Delete bridge log rows older than `max_age_secs`.
+    pub fn prune_bridge_log_older_than(
+        &self,
+        max_age_secs: u64,
+    ) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        let max_age_secs = i64::try_from(max_age_secs)
+            .map_err(|_| "max_age_secs too large for timestamp arithmetic")?;
+        let cutoff = Utc::now().timestamp() - max_age_secs;
+        let conn = self.conn.lock().unwrap();
+        let deleted = conn.execute(
+            "DELETE FROM bridge_log WHERE timestamp < ?1",
+            params![cutoff],
+        )?;
+        Ok(deleted)
+    }
 
-        Ok(DashboardOverview {
-            node_count: node_count as u64,
-            messages_in: messages_in as u64,
-            messages_out: messages_out as u64,
-            packets_in: packets_in as u64,
-            packets_out: packets_out as u64,
-            bot_name: bot_name.to_string(),
-        })
+    /// Remember a weather alert, returning whether it is news: neither it
+    /// nor any alert it updates was recorded before.
+    pub fn record_weather_alert(
+        &self,
+        alert_id: &str,
+        references: &[String],
+        expires_at: Option<i64>,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        let mut known = false;
+        for id in std::iter::once(alert_id).chain(references.iter().map(String::as_str)) {
+            let count: i64 = conn.query_row(
+                "SELECT COUNT(*) FROM weather_alerts WHERE alert_id = ?1",
+                params![id],
+                |row| row.get(0),
+            )?;
+            known |= count > 0;
+        }
+        conn.execute(
+            "INSERT OR REPLACE INTO weather_alerts (alert_id, received_at, expires_at)
+             VALUES (?1, ?2, ?3)",
+            params![alert_id, Utc::now().timestamp(), expires_at],
+        )?;
+        Ok(!known)
     }
 
-    pub fn dashboard_nodes(
+    /// Forget weather alerts that expired, or that were received more than
+    /// `max_age_secs` ago without an expiry.
+    pub fn prune_weather_alerts(
         &self,
-        hours: u32,
-        filter: MqttFilter,
-    ) -> Result<Vec<DashboardNode>, Box<dyn std::error::Error + Send + Sync>> {
+        max_age_secs: u64,
+    ) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        let max_age_secs = i64::try_from(max_age_secs)
+            .map_err(|_| "max_age_secs too large for timestamp arithmetic")?;
+        let now = Utc::now().timestamp();
         let conn = self.conn.lock().unwrap();
-        let since = if hours == 0 {
-            0
-        } else {
-            Utc::now().timestamp() - (hours as i64 * 3600)
-        };
+        let deleted = conn.execute(
+            "DELETE FROM weather_alerts
+             WHERE expires_at < ?1 OR (expires_at IS NULL AND received_at < ?2)",
+            params![now, now - max_age_secs],
+        )?;
+        Ok(deleted)
+    }
 
-        let where_clause = match filter {
-            MqttFilter::All => String::new(),
-            MqttFilter::LocalOnly => " WHERE n.via_mqtt = 0".to_string(),
-            MqttFilter::MqttOnly => " WHERE n.via_mqtt = 1".to_string(),
-        };
-
-        let query = format!(
-            "WITH rf_last AS (
-                SELECT
-                    from_node,
-                    timestamp,
-                    ROW_NUMBER() OVER (PARTITION BY from_node ORDER BY timestamp DESC, id DESC) AS rn
-                FROM packets
-                WHERE direction = 'in' AND via_mqtt = 0
-             ),
-             rf_hops AS (
-                SELECT
-                    from_node,
-                    hop_count,
-                    ROW_NUMBER() OVER (PARTITION BY from_node ORDER BY timestamp DESC, id DESC) AS rn
-                FROM packets
-                WHERE direction = 'in' AND via_mqtt = 0 AND hop_count IS NOT NULL
-             ),
-             rf_stats AS (
-                SELECT
-                    from_node,
-                    MIN(hop_count) AS min_hop,
-                    AVG(hop_count) AS avg_hop,
-                    COUNT(*) AS hop_samples
-                FROM packets
-                WHERE direction = 'in' AND via_mqtt = 0 AND hop_count IS NOT NULL
-                  AND timestamp > ?1
-                GROUP BY from_node
-             )
-             SELECT
-                n.node_id, n.short_name, n.long_name, n.last_seen, lr.timestamp AS last_rf_seen, n.latitude, n.longitude, n.via_mqtt,
-                lh.hop_count AS last_hop,
-                rs.min_hop,
-                rs.avg_hop,
-                COALESCE(rs.hop_samples, 0) AS hop_samples
-             FROM nodes n
-             LEFT JOIN rf_last lr ON lr.from_node = n.node_id AND lr.rn = 1
-             LEFT JOIN rf_hops lh ON lh.from_node = n.node_id AND lh.rn = 1
-             LEFT JOIN rf_stats rs ON rs.from_node = n.node_id
-             {} ORDER BY n.last_seen DESC",
-            where_clause
-        );
-        let mut stmt = conn.prepare(&query)?;
-        let nodes = stmt
-            .query_map(params![since], |row| {
-                let nid: i64 = row.get(0)?;
-                let via_mqtt_val: i64 = row.get(7)?;
-                let last_hop: Option<i64> = row.get(8)?;
-                let min_hop: Option<i64> = row.get(9)?;
-                let avg_hop: Option<f64> = row.get(10)?;
-                let hop_samples: i64 = row.get(11)?;
-                Ok(DashboardNode {
-                    node_id: format!("!{:08x}", nid as u32),
-                    short_name: row.get(1)?,
-                    long_name: row.get(2)?,
-                    last_seen: row.get(3)?,
-                    last_rf_seen: row.get(4)?,
-                    latitude: row.get(5)?,
-                    longitude: row.get(6)?,
-                    via_mqtt: via_mqtt_val != 0,
-                    last_hop: last_hop.map(|h| h as u32),
-                    min_hop: min_hop.map(|h| h as u32),
-                    avg_hop,
-                    hop_samples: hop_samples as u32,
-                })
-            })?
-            .collect::<Result<Vec<_>, _>>()?;
-        Ok(nodes)
+    pub fn purge_nodes_not_seen_within(
+        &self,
+        max_age_secs: u64,
+    ) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        let max_age_secs = i64::try_from(max_age_secs)
+            .map_err(|_| "max_age_secs too large for timestamp arithmetic")?;
+        let cutoff = Utc::now().timestamp() - max_age_secs;
+        let conn = self.conn.lock().unwrap();
+        let deleted = conn.execute("DELETE FROM nodes WHERE last_seen < ?1", params![cutoff])?;
+        Ok(deleted)
     }
 
-    /// Throughput of text messages only (existing chart).
-    pub fn dashboard_throughput(
+    /// Delete the least recently seen nodes until at most `max_nodes` remain.
+    /// Nodes ever heard over RF (`rf_heard`), nodes with notes and `admins`
+    /// are never evicted, so the count can stay above the cap. Keeping the
+    /// admins keeps the public key their commands are checked against.
+    pub fn evict_nodes_over(
         &self,
-        hours: u32,
-        filter: MqttFilter,
-    ) -> Result<Vec<ThroughputBucket>, Box<dyn std::error::Error + Send + Sync>> {
+        max_nodes: usize,
+        admins: &[u32],
+    ) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        // Node IDs are integers, so they can go into the query directly
+        let ids: Vec<String> = admins.iter().map(|id| id.to_string()).collect();
         let conn = self.conn.lock().unwrap();
-        let since = if hours == 0 {
-            0
-        } else {
-            Utc::now().timestamp() - (hours as i64 * 3600)
-        };
+        let deleted = conn.execute(
+            &format!(
+                "DELETE FROM nodes WHERE node_id IN (
+                    SELECT n.node_id FROM nodes n
+                    WHERE n.rf_heard = 0
+                      AND n.node_id NOT IN ({})
+                      AND NOT EXISTS (SELECT 1 FROM node_notes nn WHERE nn.node_id = n.node_id)
+                    ORDER BY n.last_seen ASC
+                    LIMIT max(0, (SELECT COUNT(*) FROM nodes) - ?1)
+                )",
+                ids.join(",")
+            ),
+            params![max_nodes as i64],
+        )?;
+        Ok(deleted)
+    }
 
-        let bucket_expr = if hours > 48 {
-            "strftime('%Y-%m-%d', timestamp, 'unixepoch')"
-        } else {
-            "strftime('%Y-%m-%d %H:00', timestamp, 'unixepoch')"
-        };
+    // --- Mail ---
 
-        let query = format!(
-            "SELECT
-                {bucket} AS bucket,
-                SUM(CASE WHEN direction = 'in' THEN 1 ELSE 0 END) AS incoming,
-                SUM(CASE WHEN direction = 'out' THEN 1 ELSE 0 END) AS outgoing
-             FROM packets
-             WHERE packet_type = 'text' AND timestamp > ?1{mqtt}
-             GROUP BY bucket
-             ORDER BY bucket",
-            bucket = bucket_expr,
-            mqtt = filter.sql_clause()
-        );
-        let mut stmt = conn.prepare(&query)?;
-        let buckets = stmt
-            .query_map(params![since], |row| {
-                Ok(ThroughputBucket {
-                    hour: row.get(0)?,
-                    incoming: row.get::<_, i64>(1)? as u64,
-                    outgoing: row.get::<_, i64>(2)? as u64,
-                })
-            })?
-            .collect::<Result<Vec<_>, _>>()?;
-        Ok(buckets)
+    /// Number of unread, unexpired mails waiting for `to_node`.
+    pub fn unread_mail_count(
+        &self,
+        to_node: u32,
+    ) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        let count: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM mail WHERE to_node = ?1 AND read = 0 AND expired_at IS NULL",
+            params![to_node as i64],
+            |row| row.get(0),
+        )?;
+        Ok(count as u64)
     }
 
-    /// Throughput of all or filtered packet types.
-    pub fn dashboard_packet_throughput(
+    /// Store a mail and return its number.
+    pub fn store_mail(
         &self,
-        hours: u32,
-        filter: MqttFilter,
-        packet_types: Option<&[String]>,
-    ) -> Result<Vec<ThroughputBucket>, Box<dyn std::error::Error + Send + Sync>> {
+        from_node: u32,
+        to_node: u32,
+        body: &str,
+    ) -> Result<i64, Box<dyn std::error::Error + Send + Sync>> {
+        let now = Utc::now().timestamp();
         let conn = self.conn.lock().unwrap();
-        let since = if hours == 0 {
-            0
-        } else {
-            Utc::now().timestamp() - (hours as i64 * 3600)
-        };
-
-        let bucket_expr = if hours > 48 {
-            "strftime('%Y-%m-%d', timestamp, 'unixepoch')"
-        } else {
-            "strftime('%Y-%m-%d %H:00', timestamp, 'unixepoch')"
-        };
-
-        const VALID_PACKET_TYPES: &[&str] = &[
-            "text",
-            "position",
-            "telemetry",
-            "nodeinfo",
-            "traceroute",
-            "neighborinfo",
-            "routing",
-            "other",
-        ];
+        conn.execute(
+            "INSERT INTO mail (timestamp, from_node, to_node, body) VALUES (?1, ?2, ?3, ?4)",
+            params![now, from_node as i64, to_node as i64, body],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
 
-        let type_clause = match packet_types {
-            Some(types) if !types.is_empty() => {
-                let safe: Vec<&&str> = types
-                    .iter()
-                    .filter_map(|t| VALID_PACKET_TYPES.iter().find(|&&v| v == t.as_str()))
-                    .collect();
-                if safe.is_empty() {
-                    return Ok(vec![]);
-                }
-                let placeholders: Vec<String> = safe.iter().map(|t| format!("'{}'", t)).collect();
-                format!(" AND packet_type IN ({})", placeholders.join(","))
-            }
-            _ => String::new(),
+    /// Return up to `limit` unread mails for `to_node`, oldest first, and mark
+    /// them read. With `receipts`, their senders are owed a read receipt
+    /// (see `take_mail_receipts`).
+    pub fn take_unread_mail(
+        &self,
+        to_node: u32,
+        limit: usize,
+        receipts: bool,
+    ) -> Result<Vec<MailMessage>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let mails = {
+            let mut stmt = tx.prepare(
+                "SELECT id, timestamp, from_node, body FROM mail
+                 WHERE to_node = ?1 AND read = 0 AND expired_at IS NULL
+                 ORDER BY timestamp, id
+                 LIMIT ?2",
+            )?;
+            let rows = stmt
+                .query_map(params![to_node as i64, limit as i64], |row| {
+                    Ok(MailMessage {
+                        id: row.get(0)?,
+                        timestamp: row.get(1)?,
+                        from_node: row.get::<_, i64>(2)? as u32,
+                        body: row.get(3)?,
+                    })
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            rows
         };
-
-        let query = format!(
-            "SELECT
-                {bucket} AS bucket,
-                SUM(CASE WHEN direction = 'in' THEN 1 ELSE 0 END) AS incoming,
-                SUM(CASE WHEN direction = 'out' THEN 1 ELSE 0 END) AS outgoing
-             FROM packets
-             WHERE timestamp > ?1{mqtt}{types}
-             GROUP BY bucket
-             ORDER BY bucket",
-            bucket = bucket_expr,
-            mqtt = filter.sql_clause(),
-            types = type_clause,
-        );
-        let mut stmt = conn.prepare(&query)?;
-        let buckets = stmt
-            .query_map(params![since], |row| {
-                Ok(ThroughputBucket {
-                    hour: row.get(0)?,
-                    incoming: row.get::<_, i64>(1)? as u64,
-                    outgoing: row.get::<_, i64>(2)? as u64,
-                })
-            })?
-            .collect::<Result<Vec<_>, _>>()?;
-        Ok(buckets)
+        for mail in &mails {
+            tx.execute(
+                "UPDATE mail SET read = 1, receipt_pending = ?2 WHERE id = ?1",
+                params![mail.id, receipts as i64],
+            )?;
+        }
+        tx.commit()?;
+        Ok(mails)
     }
 
-    pub fn dashboard_rssi(
+    /// Flag unread mail older than `max_age_secs` as expired so it is no longer
+    /// delivered. Returns the number of mails expired.
+    pub fn expire_unread_mail(
         &self,
-        hours: u32,
-        filter: MqttFilter,
-    ) -> Result<Vec<DistributionBucket>, Box<dyn std::error::Error + Send + Sync>> {
+        max_age_secs: u64,
+    ) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        let max_age_secs = i64::try_from(max_age_secs)
+            .map_err(|_| "max_age_secs too large for timestamp arithmetic")?;
+        let now = Utc::now().timestamp();
         let conn = self.conn.lock().unwrap();
-        let since = if hours == 0 {
-            0
-        } else {
-            Utc::now().timestamp() - (hours as i64 * 3600)
-        };
-
-        // Bucket RSSI into 10 dBm ranges
-        let query = format!(
-            "SELECT
-                (rssi / 10) * 10 AS bucket,
-                COUNT(*) AS cnt
-             FROM packets
-             WHERE direction = 'in' AND rssi IS NOT NULL AND timestamp > ?1{}
-             GROUP BY bucket
-             ORDER BY bucket",
-            filter.sql_clause()
-        );
-        let mut stmt = conn.prepare(&query)?;
-        let buckets = stmt
-            .query_map(params![since], |row| {
-                let bucket: i32 = row.get(0)?;
-                Ok(DistributionBucket {
-                    label: format!("{} dBm", bucket),
-                    count: row.get::<_, i64>(1)? as u64,
-                })
-            })?
-            .collect::<Result<Vec<_>, _>>()?;
-        Ok(buckets)
+        let updated = conn.execute(
+            "UPDATE mail SET expired_at = ?1
+             WHERE read = 0 AND expired_at IS NULL AND timestamp < ?2",
+            params![now, now - max_age_secs],
+        )?;
+        Ok(updated)
     }
 
-    pub fn dashboard_snr(
+    /// Delete expired mail sent by `from_node` and return how many expired per
+    /// recipient, so the sender can be told once.
+    pub fn take_expired_mail_for_sender(
         &self,
-        hours: u32,
-        filter: MqttFilter,
-    ) -> Result<Vec<DistributionBucket>, Box<dyn std::error::Error + Send + Sync>> {
-        let conn = self.conn.lock().unwrap();
-        let since = if hours == 0 {
-            0
-        } else {
-            Utc::now().timestamp() - (hours as i64 * 3600)
+        from_node: u32,
+    ) -> Result<Vec<(u32, u64)>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let expired = {
+            let mut stmt = tx.prepare(
+                "SELECT to_node, COUNT(*) FROM mail
+                 WHERE from_node = ?1 AND expired_at IS NOT NULL
+                 GROUP BY to_node
+                 ORDER BY to_node",
+            )?;
+            let rows = stmt
+                .query_map(params![from_node as i64], |row| {
+                    Ok((row.get::<_, i64>(0)? as u32, row.get::<_, i64>(1)? as u64))
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            rows
         };
+        if !expired.is_empty() {
+            tx.execute(
+                "DELETE FROM mail WHERE from_node = ?1 AND expired_at IS NOT NULL",
+                params![from_node as i64],
+            )?;
+        }
+        tx.commit()?;
+        Ok(expired)
+    }
 
-        // Bucket SNR into 2.5 dB ranges
-        let query = format!(
-            "SELECT
-                CAST(ROUND(snr / 2.5) * 2.5 AS TEXT) AS bucket,
-                COUNT(*) AS cnt
-             FROM packets
-             WHERE direction = 'in' AND snr IS NOT NULL AND timestamp > ?1{}
-             GROUP BY bucket
-             ORDER BY CAST(bucket AS REAL)",
-            filter.sql_clause()
-        );
-        let mut stmt = conn.prepare(&query)?;
-        let buckets = stmt
-            .query_map(params![since], |row| {
-                let bucket: String = row.get(0)?;
-                Ok(DistributionBucket {
-                    label: format!("{} dB", bucket),
-                    count: row.get::<_, i64>(1)? as u64,
-                })
-            })?
-            .collect::<Result<Vec<_>, _>>()?;
-        Ok(buckets)
+    /// Mail sent by `from_node` that was read since it was last told, as
+    /// `(mail number, recipient)`. Clears the pending receipts.
+    pub fn take_mail_receipts(
+        &self,
+        from_node: u32,
+    ) -> Result<Vec<(i64, u32)>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let receipts = {
+            let mut stmt = tx.prepare(
+                "SELECT id, to_node FROM mail
+                 WHERE from_node = ?1 AND receipt_pending = 1
+                 ORDER BY id",
+            )?;
+            let rows = stmt
+                .query_map(params![from_node as i64], |row| {
+                    Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)? as u32))
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            rows
+        };
+        if !receipts.is_empty() {
+            tx.execute(
+                "UPDATE mail SET receipt_pending = 0 WHERE from_node = ?1 AND receipt_pending = 1",
+                params![from_node as i64],
+            )?;
+        }
+        tx.commit()?;
+        Ok(receipts)
     }
 
-    pub fn dashboard_hops(
+    // --- Away status ---
+
+    /// Set (or replace) a node's away message. Resets the one-time reply
+    /// tracking so everyone gets the new message once.
+    pub fn set_away(
         &self,
-        hours: u32,
-        filter: MqttFilter,
-    ) -> Result<Vec<DistributionBucket>, Box<dyn std::error::Error + Send + Sync>> {
-        let conn = self.conn.lock().unwrap();
-        let since = if hours == 0 {
-            0
-        } else {
-            Utc::now().timestamp() - (hours as i64 * 3600)
-        };
+        node_id: u32,
+        message: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let now = Utc::now().timestamp();
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT INTO away_status (node_id, message, since) VALUES (?1, ?2, ?3)
+             ON CONFLICT(node_id) DO UPDATE SET message = ?2, since = ?3",
+            params![node_id as i64, message, now],
+        )?;
+        tx.execute(
+            "DELETE FROM away_replies WHERE node_id = ?1",
+            params![node_id as i64],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
 
-        let query = format!(
-            "SELECT
-                hop_count,
-                COUNT(*) AS cnt
-             FROM packets
-             WHERE direction = 'in' AND hop_count IS NOT NULL AND timestamp > ?1{}
-             GROUP BY hop_count
-             ORDER BY hop_count",
-            filter.sql_clause()
-        );
-        let mut stmt = conn.prepare(&query)?;
-        let buckets = stmt
-            .query_map(params![since], |row| {
-                let hops: i32 = row.get(0)?;
-                Ok(DistributionBucket {
-                    label: format!("{} hop{}", hops, if hops == 1 { "" } else { "s" }),
-                    count: row.get::<_, i64>(1)? as u64,
-                })
-            })?
-            .collect::<Result<Vec<_>, _>>()?;
-        Ok(buckets)
+    /// Clear a node's away status. Returns the number of auto-replies sent
+    /// while away, or `None` if the node was not away.
+    pub fn clear_away(
+        &self,
+        node_id: u32,
+    ) -> Result<Option<u64>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let replies: i64 = tx.query_row(
+            "SELECT COUNT(*) FROM away_replies WHERE node_id = ?1",
+            params![node_id as i64],
+            |row| row.get(0),
+        )?;
+        tx.execute(
+            "DELETE FROM away_replies WHERE node_id = ?1",
+            params![node_id as i64],
+        )?;
+        let removed = tx.execute(
+            "DELETE FROM away_status WHERE node_id = ?1",
+            params![node_id as i64],
+        )?;
+        tx.commit()?;
+        Ok(if removed > 0 {
+            Some(replies as u64)
+        } else {
+            None
+        })
     }
 
-    pub fn dashboard_positions(
+    pub fn get_away_nodes(
         &self,
-    ) -> Result<Vec<DashboardNode>, Box<dyn std::error::Error + Send + Sync>> {
+    ) -> Result<Vec<AwayNode>, Box<dyn std::error::Error + Send + Sync>> {
         let conn = self.conn.lock().unwrap();
         let mut stmt = conn.prepare(
-            "WITH rf_last AS (
-                SELECT
-                    from_node,
-                    timestamp,
-                    ROW_NUMBER() OVER (PARTITION BY from_node ORDER BY timestamp DESC, id DESC) AS rn
-                FROM packets
-                WHERE direction = 'in' AND via_mqtt = 0
-             ),
-             rf_hops AS (
-                SELECT
-                    from_node,
-                    hop_count,
-                    ROW_NUMBER() OVER (PARTITION BY from_node ORDER BY timestamp DESC, id DESC) AS rn
-                FROM packets
-                WHERE direction = 'in' AND via_mqtt = 0 AND hop_count IS NOT NULL
-             ),
-             rf_stats AS (
-                SELECT
-                    from_node,
-                    MIN(hop_count) AS min_hop,
-                    AVG(hop_count) AS avg_hop,
-                    COUNT(*) AS hop_samples
-                FROM packets
-                WHERE direction = 'in' AND via_mqtt = 0 AND hop_count IS NOT NULL
-                GROUP BY from_node
-             )
-             SELECT
-                n.node_id, n.short_name, n.long_name, n.last_seen, lr.timestamp AS last_rf_seen, n.latitude, n.longitude, n.via_mqtt,
-                lh.hop_count AS last_hop,
-                rs.min_hop,
-                rs.avg_hop,
-                COALESCE(rs.hop_samples, 0) AS hop_samples
-             FROM nodes n
-             LEFT JOIN rf_last lr ON lr.from_node = n.node_id AND lr.rn = 1
-             LEFT JOIN rf_hops lh ON lh.from_node = n.node_id AND lh.rn = 1
-             LEFT JOIN rf_stats rs ON rs.from_node = n.node_id
-             WHERE n.latitude IS NOT NULL AND n.longitude IS NOT NULL
-               AND (n.latitude != 0.0 OR n.longitude != 0.0)
-             ORDER BY n.last_seen DESC",
+            "SELECT a.node_id, COALESCE(n.short_name, ''), COALESCE(n.long_name, ''), a.message
+             FROM away_status a
+             LEFT JOIN nodes n ON n.node_id = a.node_id
+             ORDER BY a.since",
         )?;
-        let nodes = stmt
+        let rows = stmt
             .query_map([], |row| {
-                let nid: i64 = row.get(0)?;
-                let via_mqtt_val: i64 = row.get(7)?;
-                let last_hop: Option<i64> = row.get(8)?;
-                let min_hop: Option<i64> = row.get(9)?;
-                let avg_hop: Option<f64> = row.get(10)?;
-                let hop_samples: i64 = row.get(11)?;
-                Ok(DashboardNode {
-                    node_id: format!("!{:08x}", nid as u32),
+                Ok(AwayNode {
+                    node_id: row.get::<_, i64>(0)? as u32,
                     short_name: row.get(1)?,
                     long_name: row.get(2)?,
-                    last_seen: row.get(3)?,
-                    last_rf_seen: row.get(4)?,
-                    latitude: row.get(5)?,
-                    longitude: row.get(6)?,
-                    via_mqtt: via_mqtt_val != 0,
-                    last_hop: last_hop.map(|h| h as u32),
-                    min_hop: min_hop.map(|h| h as u32),
-                    avg_hop,
-                    hop_samples: hop_samples as u32,
+                    message: row.get(3)?,
                 })
             })?
             .collect::<Result<Vec<_>, _>>()?;
-        Ok(nodes)
+        Ok(rows)
     }
 
-    pub fn dashboard_traceroute_requesters(
+    /// A node's away message, or `None` if it is not away.
+    pub fn away_message(
         &self,
-        target_node: u32,
-        hours: u32,
-        filter: MqttFilter,
-    ) -> Result<Vec<TracerouteRequester>, Box<dyn std::error::Error + Send + Sync>> {
+        node_id: u32,
+    ) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
         let conn = self.conn.lock().unwrap();
-        let since = if hours == 0 {
-            0
+        match conn.query_row(
+            "SELECT message FROM away_status WHERE node_id = ?1",
+            params![node_id as i64],
+            |row| row.get(0),
+        ) {
+            Ok(message) => Ok(Some(message)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Record that `sender_id` got the away auto-reply for `node_id`.
+    /// Returns `false` if they already had it during this away period.
+    pub fn mark_away_reply(
+        &self,
+        node_id: u32,
+        sender_id: u32,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let now = Utc::now().timestamp();
+        let conn = self.conn.lock().unwrap();
+        let inserted = conn.execute(
+            "INSERT OR IGNORE INTO away_replies (node_id, sender_id, replied_at) VALUES (?1, ?2, ?3)",
+            params![node_id as i64, sender_id as i64, now],
+        )?;
+        Ok(inserted > 0)
+    }
+
+    // --- Muted nodes ---
+
+    /// Mute or unmute a node (`!mute`). Returns `false` if it already was.
+    pub fn set_muted(
+        &self,
+        node_id: u32,
+        muted: bool,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        let changed = if muted {
+            conn.execute(
+                "INSERT OR IGNORE INTO muted_nodes (node_id, muted_at) VALUES (?1, ?2)",
+                params![node_id as i64, Utc::now().timestamp()],
+            )?
         } else {
-            Utc::now().timestamp() - (hours as i64 * 3600)
+            conn.execute(
+                "DELETE FROM muted_nodes WHERE node_id = ?1",
+                params![node_id as i64],
+            )?
         };
+        Ok(changed > 0)
+    }
 
-        let mqtt_clause = match filter {
-            MqttFilter::All => "",
-            MqttFilter::LocalOnly => " AND p.via_mqtt = 0",
-            MqttFilter::MqttOnly => " AND p.via_mqtt = 1",
-        };
+    /// Whether a node asked not to get unsolicited messages.
+    pub fn is_muted(&self, node_id: u32) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        let muted = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM muted_nodes WHERE node_id = ?1)",
+            params![node_id as i64],
+            |row| row.get(0),
+        )?;
+        Ok(muted)
+    }
 
-        let query = format!(
-            "SELECT
-                p.from_node,
-                COALESCE(n.short_name, '') AS short_name,
-                COALESCE(n.long_name, '') AS long_name,
-                COUNT(*) AS request_count,
-                MAX(p.timestamp) AS last_request,
-                MAX(p.via_mqtt) AS via_mqtt
-             FROM packets p
-             LEFT JOIN nodes n ON n.node_id = p.from_node
-             WHERE p.direction = 'in'
-               AND p.packet_type = 'traceroute'
-               AND p.to_node = ?1
-               AND p.timestamp > ?2
-               {mqtt_clause}
-             GROUP BY p.from_node, n.short_name, n.long_name
-             ORDER BY last_request DESC"
-        );
+    // --- Node notes ---
 
-        let rows = conn
-            .prepare(&query)?
-            .query_map(params![target_node as i64, since], |row| {
-                let node_id_i64: i64 = row.get(0)?;
-                let short_name: String = row.get(1)?;
-                let long_name: String = row.get(2)?;
-                let request_count: i64 = row.get(3)?;
-                let last_request: i64 = row.get(4)?;
-                let via_mqtt: i64 = row.get(5)?;
-                Ok(TracerouteRequester {
-                    node_id: format!("!{:08x}", node_id_i64 as u32),
-                    short_name,
-                    long_name,
-                    request_count: request_count as u64,
-                    last_request,
-                    via_mqtt: via_mqtt != 0,
+    pub fn add_node_note(
+        &self,
+        node_id: u32,
+        author_id: u32,
+        note: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let now = Utc::now().timestamp();
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO node_notes (node_id, author_id, note, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![node_id as i64, author_id as i64, note, now],
+        )?;
+        Ok(())
+    }
+
+    /// Notes for a node, newest first.
+    pub fn get_node_notes(
+        &self,
+        node_id: u32,
+        limit: usize,
+    ) -> Result<Vec<NodeNote>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT nn.id, nn.author_id, n.long_name, n.short_name, nn.note, nn.created_at
+             FROM node_notes nn
+             LEFT JOIN nodes n ON n.node_id = nn.author_id
+             WHERE nn.node_id = ?1
+             ORDER BY nn.created_at DESC, nn.id DESC
+             LIMIT ?2",
+        )?;
+        let notes = stmt
+            .query_map(params![node_id as i64, limit as i64], |row| {
+                let author_id = row.get::<_, i64>(1)? as u32;
+                let long_name: Option<String> = row.get(2)?;
+                let short_name: Option<String> = row.get(3)?;
+                let author = long_name
+                    .filter(|n| !n.is_empty())
+                    .or(short_name.filter(|n| !n.is_empty()))
+                    .unwrap_or_else(|| format!("!{:08x}", author_id));
+                Ok(NodeNote {
+                    id: row.get(0)?,
+                    author,
+                    note: row.get(4)?,
+                    created_at: row.get(5)?,
                 })
             })?
             .collect::<Result<Vec<_>, _>>()?;
-
-        Ok(rows)
+        Ok(notes)
     }
 
-    pub fn dashboard_traceroute_events(
+    // --- Polls ---
+
+    /// Start a poll. Options are stored as a JSON array. With `closes_at`
+    /// the poll is closed by `take_expired_polls` once that time passes.
+    pub fn create_poll(
         &self,
-        hours: u32,
-        filter: MqttFilter,
-        limit: u32,
-    ) -> Result<Vec<TracerouteEvent>, Box<dyn std::error::Error + Send + Sync>> {
+        question: &str,
+        options: &[String],
+        channel: u32,
+        created_by: u32,
+        closes_at: Option<i64>,
+    ) -> Result<i64, Box<dyn std::error::Error + Send + Sync>> {
+        let now = Utc::now().timestamp();
+        let options = serde_json::to_string(options)?;
         let conn = self.conn.lock().unwrap();
-        let since = if hours == 0 {
-            0
+        conn.execute(
+            "INSERT INTO polls (question, options, channel, created_by, created_at, closes_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                question,
+                options,
+                channel as i64,
+                created_by as i64,
+                now,
+                closes_at
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    fn load_polls(
+        conn: &Connection,
+        sql: &str,
+        param: i64,
+    ) -> Result<Vec<Poll>, Box<dyn std::error::Error + Send + Sync>> {
+        let rows = conn
+            .prepare(sql)?
+            .query_map(params![param], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, i64>(3)?,
+                    row.get::<_, Option<i64>>(4)?,
+                    row.get::<_, Option<i64>>(5)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut polls = Vec::new();
+        for (id, question, options, channel, closes_at, closed_at) in rows {
+            polls.push(Poll {
+                id,
+                question,
+                options: serde_json::from_str(&options)?,
+                channel: channel as u32,
+                closes_at,
+                closed_at,
+            });
+        }
+        Ok(polls)
+    }
+
+    /// The most recently started poll that has not been closed.
+    pub fn get_open_poll(&self) -> Result<Option<Poll>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        let polls = Self::load_polls(
+            &conn,
+            "SELECT id, question, options, channel, closes_at, closed_at FROM polls
+             WHERE closed_at IS NULL
+             ORDER BY id DESC LIMIT ?1",
+            1,
+        )?;
+        Ok(polls.into_iter().next())
+    }
+
+    /// The most recently started poll, open or closed.
+    pub fn get_latest_poll(
+        &self,
+    ) -> Result<Option<Poll>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        let polls = Self::load_polls(
+            &conn,
+            "SELECT id, question, options, channel, closes_at, closed_at FROM polls
+             ORDER BY id DESC LIMIT ?1",
+            1,
+        )?;
+        Ok(polls.into_iter().next())
+    }
+
+    /// Open polls whose `closes_at` has passed. Closes them.
+    pub fn take_expired_polls(
+        &self,
+        now: i64,
+    ) -> Result<Vec<Poll>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let mut polls = Self::load_polls(
+            &tx,
+            "SELECT id, question, options, channel, closes_at, closed_at FROM polls
+             WHERE closed_at IS NULL AND closes_at <= ?1
+             ORDER BY id",
+            now,
+        )?;
+        tx.execute(
+            "UPDATE polls SET closed_at = ?1 WHERE closed_at IS NULL AND closes_at <= ?1",
+            params![now],
+        )?;
+        tx.commit()?;
+        for poll in &mut polls {
+            poll.closed_at = Some(now);
+        }
+        Ok(polls)
+    }
+
+    /// Record a vote. Returns `false` if the node already voted in this poll.
+    pub fn cast_poll_vote(
+        &self,
+        poll_id: i64,
+        node_id: u32,
+        option_index: usize,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let now = Utc::now().timestamp();
+        let conn = self.conn.lock().unwrap();
+        let inserted = conn.execute(
+            "INSERT OR IGNORE INTO poll_votes (poll_id, node_id, option_index, voted_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![poll_id, node_id as i64, option_index as i64, now],
+        )?;
+        Ok(inserted > 0)
+    }
+
+    /// Vote counts per option index, sized to `option_count`.
+    pub fn poll_tally(
+        &self,
+        poll_id: i64,
+        option_count: usize,
+    ) -> Result<Vec<u64>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT option_index, COUNT(*) FROM poll_votes WHERE poll_id = ?1 GROUP BY option_index",
+        )?;
+        let mut tally = vec![0u64; option_count];
+        let rows = stmt.query_map(params![poll_id], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?))
+        })?;
+        for row in rows {
+            let (index, count) = row?;
+            if let Some(slot) = usize::try_from(index).ok().and_then(|i| tally.get_mut(i)) {
+                *slot = count as u64;
+            }
+        }
+        Ok(tally)
+    }
+
+    pub fn close_poll(&self, poll_id: i64) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let now = Utc::now().timestamp();
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE polls SET closed_at = ?2 WHERE id = ?1 AND closed_at IS NULL",
+            params![poll_id, now],
+        )?;
+        Ok(())
+    }
+
+    // --- External API usage ---
+
+    /// Count one call to an external API against today's (UTC) quota.
+    /// Returns `false` without counting once `daily_quota` calls were made
+    /// today (0 = unlimited).
+    pub fn try_consume_api_call(
+        &self,
+        api: &str,
+        daily_quota: u64,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let day = Utc::now().format("%Y-%m-%d").to_string();
+        let quota = if daily_quota == 0 {
+            i64::MAX
         } else {
-            Utc::now().timestamp() - (hours as i64 * 3600)
+            i64::try_from(daily_quota).unwrap_or(i64::MAX)
         };
-        let mqtt_clause = match filter {
-            MqttFilter::All => "",
-            MqttFilter::LocalOnly => " AND p.via_mqtt = 0",
-            MqttFilter::MqttOnly => " AND p.via_mqtt = 1",
+        let conn = self.conn.lock().unwrap();
+        let changed = conn.execute(
+            "INSERT INTO api_usage (day, api, calls) VALUES (?1, ?2, 1)
+             ON CONFLICT(day, api) DO UPDATE SET calls = calls + 1 WHERE calls < ?3",
+            params![day, api, quota],
+        )?;
+        Ok(changed > 0)
+    }
+
+    // --- Settings ---
+
+    /// Dashboard-edited config overrides (see `settings`), as `(key, JSON
+    /// value)`.
+    pub fn settings(
+        &self,
+    ) -> Result<Vec<(String, String)>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT key, value FROM settings ORDER BY key")?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Store an override, or with `None` remove it so the config file's
+    /// value applies again.
+    pub fn set_setting(
+        &self,
+        key: &str,
+        value: Option<&str>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        match value {
+            Some(value) => conn.execute(
+                "INSERT INTO settings (key, value, updated_at) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(key) DO UPDATE SET value = excluded.value,
+                                                updated_at = excluded.updated_at",
+                params![key, value, Utc::now().timestamp()],
+            )?,
+            None => conn.execute("DELETE FROM settings WHERE key = ?1", params![key])?,
         };
+        Ok(())
+    }
 
-        let query = format!(
-            "SELECT
-                p.timestamp,
-                p.from_node,
-                COALESCE(nf.short_name, '') AS from_short_name,
-                COALESCE(nf.long_name, '') AS from_long_name,
-                p.to_node,
-                COALESCE(nt.short_name, '') AS to_short_name,
-                COALESCE(nt.long_name, '') AS to_long_name,
-                p.via_mqtt,
-                p.hop_count,
-                p.hop_start,
-                p.rssi,
-                p.snr
-             FROM packets p
-             LEFT JOIN nodes nf ON nf.node_id = p.from_node
-             LEFT JOIN nodes nt ON nt.node_id = p.to_node
-             WHERE p.direction = 'in'
-               AND p.packet_type = 'traceroute'
-               AND p.timestamp > ?1
-               {mqtt_clause}
-             ORDER BY p.timestamp DESC, p.id DESC
-             LIMIT ?2"
-        );
+    // --- Bot state ---
 
-        let rows = conn
-            .prepare(&query)?
-            .query_map(params![since, limit as i64], |row| {
-                let from_node_i64: i64 = row.get(1)?;
-                let to_node_i64: Option<i64> = row.get(4)?;
-                let via_mqtt_i64: i64 = row.get(7)?;
-                let hop_count_i64: Option<i64> = row.get(8)?;
-                let hop_start_i64: Option<i64> = row.get(9)?;
-                Ok(TracerouteEvent {
-                    timestamp: row.get(0)?,
-                    from_node: format!("!{:08x}", from_node_i64 as u32),
-                    from_short_name: row.get(2)?,
-                    from_long_name: row.get(3)?,
-                    to_node: to_node_i64
-                        .map(|n| format!("!{:08x}", n as u32))
-                        .unwrap_or_else(|| "broadcast".to_string()),
-                    to_short_name: row.get(5)?,
-                    to_long_name: row.get(6)?,
-                    via_mqtt: via_mqtt_i64 != 0,
-                    hop_count: hop_count_i64.map(|h| h as u32),
-                    hop_start: hop_start_i64.map(|h| h as u32),
-                    rssi: row.get(10)?,
-                    snr: row.get(11)?,
+    /// Read a value the bot persisted across restarts.
+    pub fn get_state(
+        &self,
+        key: &str,
+    ) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        match conn.query_row(
+            "SELECT value FROM bot_state WHERE key = ?1",
+            params![key],
+            |row| row.get(0),
+        ) {
+            Ok(value) => Ok(Some(value)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn set_state(
+        &self,
+        key: &str,
+        value: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO bot_state (key, value) VALUES (?1, ?2)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            params![key, value],
+        )?;
+        Ok(())
+    }
+
+    /// Remember the primary radio's node ID, for modules that need the
+    /// bot's own position.
+    pub fn set_local_node_id(
+        &self,
+        node_id: u32,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.set_state(LOCAL_NODE_KEY, &node_id.to_string())
+    }
+
+    pub fn local_node_id(&self) -> Result<Option<u32>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self
+            .get_state(LOCAL_NODE_KEY)?
+            .and_then(|value| value.parse().ok()))
+    }
+
+    // --- Environment telemetry ---
+
+    /// Store an EnvironmentMetrics report. Reports without temperature,
+    /// humidity or pressure are ignored.
+    pub fn log_environment(
+        &self,
+        node_id: u32,
+        temperature: Option<f32>,
+        relative_humidity: Option<f32>,
+        barometric_pressure: Option<f32>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if temperature.is_none() && relative_humidity.is_none() && barometric_pressure.is_none() {
+            return Ok(());
+        }
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO environment_readings
+                (node_id, timestamp, temperature, relative_humidity, barometric_pressure)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                node_id as i64,
+                Utc::now().timestamp(),
+                temperature.map(|v| v as f64),
+                relative_humidity.map(|v| v as f64),
+                barometric_pressure.map(|v| v as f64),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Nodes that reported environment telemetry, most recently heard first.
+    pub fn environment_sensors(
+        &self,
+    ) -> Result<Vec<EnvironmentSensor>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "WITH latest AS (
+                SELECT
+                    node_id, timestamp, temperature, relative_humidity, barometric_pressure,
+                    ROW_NUMBER() OVER (PARTITION BY node_id ORDER BY timestamp DESC, id DESC) AS rn
+                FROM environment_readings
+             )
+             SELECT
+                l.node_id, COALESCE(n.short_name, ''), COALESCE(n.long_name, ''),
+                l.timestamp, l.temperature, l.relative_humidity, l.barometric_pressure
+             FROM latest l
+             LEFT JOIN nodes n ON n.node_id = l.node_id
+             WHERE l.rn = 1
+             ORDER BY l.timestamp DESC",
+        )?;
+        let sensors = stmt
+            .query_map([], |row| {
+                let nid: i64 = row.get(0)?;
+                Ok(EnvironmentSensor {
+                    node_id: format!("!{:08x}", nid as u32),
+                    short_name: row.get(1)?,
+                    long_name: row.get(2)?,
+                    latest: EnvironmentReading {
+                        timestamp: row.get(3)?,
+                        temperature: row.get(4)?,
+                        relative_humidity: row.get(5)?,
+                        barometric_pressure: row.get(6)?,
+                    },
                 })
             })?
             .collect::<Result<Vec<_>, _>>()?;
-        Ok(rows)
+        Ok(sensors)
     }
 
-    pub fn dashboard_traceroute_destinations(
+    /// A node's environment readings over the last `hours` (0 = all), oldest first.
+    pub fn environment_series(
         &self,
+        node_id: u32,
         hours: u32,
-        filter: MqttFilter,
-    ) -> Result<Vec<TracerouteDestinationSummary>, Box<dyn std::error::Error + Send + Sync>> {
+    ) -> Result<Vec<EnvironmentReading>, Box<dyn std::error::Error + Send + Sync>> {
         let conn = self.conn.lock().unwrap();
         let since = if hours == 0 {
             0
         } else {
             Utc::now().timestamp() - (hours as i64 * 3600)
         };
-        let mqtt_clause = match filter {
-            MqttFilter::All => "",
-            MqttFilter::LocalOnly => " AND p.via_mqtt = 0",
-            MqttFilter::MqttOnly => " AND p.via_mqtt = 1",
-        };
-
-        let query = format!(
-            "SELECT
-                p.to_node,
-                COALESCE(nt.short_name, '') AS to_short_name,
-                COALESCE(nt.long_name, '') AS to_long_name,
-                COUNT(*) AS requests,
-                COUNT(DISTINCT p.from_node) AS unique_requesters,
-                MAX(p.timestamp) AS last_seen,
-                SUM(CASE WHEN p.via_mqtt = 0 THEN 1 ELSE 0 END) AS rf_count,
-                SUM(CASE WHEN p.via_mqtt = 1 THEN 1 ELSE 0 END) AS mqtt_count,
-                AVG(p.hop_count) AS avg_hops
-             FROM packets p
-             LEFT JOIN nodes nt ON nt.node_id = p.to_node
-             WHERE p.direction = 'in'
-               AND p.packet_type = 'traceroute'
-               AND p.timestamp > ?1
-               {mqtt_clause}
-             GROUP BY p.to_node, nt.short_name, nt.long_name
-             ORDER BY last_seen DESC"
-        );
-
-        let rows = conn
-            .prepare(&query)?
-            .query_map(params![since], |row| {
-                let to_node_i64: Option<i64> = row.get(0)?;
-                let requests: i64 = row.get(3)?;
-                let unique_requesters: i64 = row.get(4)?;
-                let rf_count: i64 = row.get(6)?;
-                let mqtt_count: i64 = row.get(7)?;
-                Ok(TracerouteDestinationSummary {
-                    destination_node: to_node_i64
-                        .map(|n| format!("!{:08x}", n as u32))
-                        .unwrap_or_else(|| "broadcast".to_string()),
-                    destination_short_name: row.get(1)?,
-                    destination_long_name: row.get(2)?,
-                    requests: requests as u64,
-                    unique_requesters: unique_requesters as u64,
-                    last_seen: row.get(5)?,
-                    rf_count: rf_count as u64,
-                    mqtt_count: mqtt_count as u64,
-                    avg_hops: row.get(8)?,
+        let mut stmt = conn.prepare(
+            "SELECT timestamp, temperature, relative_humidity, barometric_pressure
+             FROM environment_readings
+             WHERE node_id = ?1 AND timestamp > ?2
+             ORDER BY timestamp, id",
+        )?;
+        let readings = stmt
+            .query_map(params![node_id as i64, since], |row| {
+                Ok(EnvironmentReading {
+                    timestamp: row.get(0)?,
+                    temperature: row.get(1)?,
+                    relative_humidity: row.get(2)?,
+                    barometric_pressure: row.get(3)?,
                 })
             })?
             .collect::<Result<Vec<_>, _>>()?;
+        Ok(readings)
+    }
 
-        Ok(rows)
+    // --- Delivery tracking ---
+
+    /// Record a routing reply to one of our packets: an ACK from `from`
+    /// (only the DM's destination counts) or a routing error. An ACK
+    /// overrides an earlier error. Returns whether a packet was updated.
+    pub fn record_packet_ack(
+        &self,
+        mesh_packet_id: u32,
+        from: u32,
+        acked: bool,
+        now: i64,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        let updated = if acked {
+            conn.execute(
+                "UPDATE packets SET ack_status = 'acked', acked_at = ?3
+                 WHERE direction = 'out' AND mesh_packet_id = ?1 AND to_node = ?2",
+                params![mesh_packet_id as i64, from as i64, now],
+            )?
+        } else {
+            conn.execute(
+                "UPDATE packets SET ack_status = 'failed', acked_at = ?2
+                 WHERE direction = 'out' AND mesh_packet_id = ?1 AND ack_status IS NULL",
+                params![mesh_packet_id as i64, now],
+            )?
+        };
+        Ok(updated > 0)
     }
 
-    fn traceroute_status(
-        trace_key: &str,
-        request_hops: Option<u32>,
-        response_hops: Option<u32>,
-        request_route_len: usize,
-        response_route_len: usize,
-    ) -> &'static str {
-        let req_present = request_hops.is_some() || request_route_len > 0;
-        let res_present = response_hops.is_some() || response_route_len > 0;
-        // Only req: sessions (probes sent by us) may reach complete — we initiated
-        // the exchange and received the reply, so we have full end-to-end knowledge.
-        // in: sessions are passive observations; even with both sides seen we cap at
-        // partial because we cannot confirm the full path or that the exchange completed.
-        let can_be_complete = trace_key.starts_with("req:");
-        if req_present && res_present && can_be_complete {
-            "complete"
-        } else if req_present || res_present {
-            "partial"
+    /// Per destination node, how many of the DMs we sent in the last `hours`
+    /// (0 = all) were acknowledged or failed, most messaged first.
+    pub fn delivery_stats(
+        &self,
+        hours: u32,
+    ) -> Result<Vec<NodeDelivery>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.read_conn();
+        let since = if hours == 0 {
+            0
         } else {
-            "observed"
-        }
+            Utc::now().timestamp() - (hours as i64 * 3600)
+        };
+        let mut stmt = conn.prepare(
+            "SELECT
+                p.to_node, COALESCE(n.short_name, ''), COALESCE(n.long_name, ''),
+                COUNT(*),
+                SUM(CASE WHEN p.ack_status = 'acked' THEN 1 ELSE 0 END),
+                SUM(CASE WHEN p.ack_status = 'failed' THEN 1 ELSE 0 END)
+             FROM packets p
+             LEFT JOIN nodes n ON n.node_id = p.to_node
+             WHERE p.direction = 'out' AND p.packet_type = 'text'
+               AND p.to_node IS NOT NULL AND p.mesh_packet_id IS NOT NULL
+               AND p.timestamp > ?1
+             GROUP BY p.to_node
+             ORDER BY COUNT(*) DESC, p.to_node",
+        )?;
+        let stats = stmt
+            .query_map(params![since], |row| {
+                let nid: i64 = row.get(0)?;
+                let sent = row.get::<_, i64>(3)? as u64;
+                let acked = row.get::<_, i64>(4)? as u64;
+                Ok(NodeDelivery {
+                    node_id: format!("!{:08x}", nid as u32),
+                    short_name: row.get(1)?,
+                    long_name: row.get(2)?,
+                    sent,
+                    acked,
+                    failed: row.get::<_, i64>(5)? as u64,
+                    success_rate: acked as f64 / sent as f64,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(stats)
     }
 
-    #[allow(clippy::too_many_arguments)]
-    pub fn log_traceroute_observation(
+    /// Nodes owed an expired-mail notice, a read receipt or a DM retry; with
+    /// `node`, just that node if it is.
+    pub fn nodes_owed_deliveries(
         &self,
-        packet_row_id: i64,
-        trace_key: &str,
-        src_node: u32,
-        dst_node: Option<u32>,
-        via_mqtt: bool,
-        request_hops: Option<u32>,
-        request_hop_start: Option<u32>,
-        response_hops: Option<u32>,
-        response_hop_start: Option<u32>,
-        request_route: &[u32],
-        response_route: &[u32],
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let now = Utc::now().timestamp();
-        let mut conn = self.conn.lock().unwrap();
-        let tx = conn.transaction()?;
+        node: Option<u32>,
+    ) -> Result<Vec<u32>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT from_node FROM mail
+             WHERE (expired_at IS NOT NULL OR receipt_pending = 1)
+               AND (?1 IS NULL OR from_node = ?1)
+             UNION
+             SELECT to_node FROM pending_sends WHERE ?1 IS NULL OR to_node = ?1",
+        )?;
+        let rows = stmt
+            .query_map(params![node.map(|id| id as i64)], |row| {
+                Ok(row.get::<_, i64>(0)? as u32)
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
 
-        let session_id = {
-            let mut find_stmt = tx.prepare(
-                "SELECT id, first_seen, request_hops, request_hop_start, response_hops, response_hop_start, sample_count
-                 FROM traceroute_sessions
-                 WHERE trace_key = ?1
-                 LIMIT 1",
-            )?;
-            let existing = find_stmt.query_row(params![trace_key], |row| {
-                Ok((
-                    row.get::<_, i64>(0)?,
-                    row.get::<_, i64>(1)?,
-                    row.get::<_, Option<i64>>(2)?,
-                    row.get::<_, Option<i64>>(3)?,
-                    row.get::<_, Option<i64>>(4)?,
-                    row.get::<_, Option<i64>>(5)?,
-                    row.get::<_, i64>(6)?,
-                ))
-            });
+    // --- Pending DM sends ---
 
-            match existing {
-                Ok((
-                    id,
-                    first_seen,
-                    req_hops_prev,
-                    req_start_prev,
-                    res_hops_prev,
-                    res_start_prev,
-                    sample_count,
-                )) => {
-                    let merged_req_hops = request_hops.or(req_hops_prev.map(|v| v as u32));
-                    let merged_req_start = request_hop_start.or(req_start_prev.map(|v| v as u32));
-                    let merged_res_hops = response_hops.or(res_hops_prev.map(|v| v as u32));
-                    let merged_res_start = response_hop_start.or(res_start_prev.map(|v| v as u32));
-                    let status = Self::traceroute_status(
-                        trace_key,
-                        merged_req_hops,
-                        merged_res_hops,
-                        request_route.len(),
-                        response_route.len(),
-                    );
-                    tx.execute(
-                        "UPDATE traceroute_sessions
-                         SET first_seen = ?2,
-                             last_seen = ?3,
-                             src_node = ?4,
-                             dst_node = ?5,
-                             via_mqtt = ?6,
-                             request_hops = ?7,
-                             request_hop_start = ?8,
-                             response_hops = ?9,
-                             response_hop_start = ?10,
-                             request_packet_id = CASE WHEN ?7 IS NOT NULL THEN COALESCE(request_packet_id, ?11) ELSE request_packet_id END,
-                             response_packet_id = CASE WHEN ?9 IS NOT NULL THEN COALESCE(response_packet_id, ?11) ELSE response_packet_id END,
-                             status = ?12,
-                             sample_count = ?13
-                         WHERE id = ?1",
-                        params![
-                            id,
-                            std::cmp::min(first_seen, now),
-                            now,
-                            src_node as i64,
-                            dst_node.map(|n| n as i64),
-                            via_mqtt as i64,
-                            merged_req_hops.map(|v| v as i64),
-                            merged_req_start.map(|v| v as i64),
-                            merged_res_hops.map(|v| v as i64),
-                            merged_res_start.map(|v| v as i64),
-                            packet_row_id,
-                            status,
-                            sample_count + 1,
-                        ],
-                    )?;
-                    id
-                }
-                Err(rusqlite::Error::QueryReturnedNoRows) => {
-                    let status = Self::traceroute_status(
-                        trace_key,
-                        request_hops,
-                        response_hops,
-                        request_route.len(),
-                        response_route.len(),
-                    );
-                    tx.execute(
-                        "INSERT INTO traceroute_sessions
-                         (trace_key, first_seen, last_seen, src_node, dst_node, via_mqtt, request_hops, request_hop_start, response_hops, response_hop_start, request_packet_id, response_packet_id, status, sample_count)
-                         VALUES (?1, ?2, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, 1)",
-                        params![
-                            trace_key,
-                            now,
-                            src_node as i64,
-                            dst_node.map(|n| n as i64),
-                            via_mqtt as i64,
-                            request_hops.map(|v| v as i64),
-                            request_hop_start.map(|v| v as i64),
-                            response_hops.map(|v| v as i64),
-                            response_hop_start.map(|v| v as i64),
-                            if request_hops.is_some() {
-                                Some(packet_row_id)
-                            } else {
-                                None
-                            },
-                            if response_hops.is_some() {
-                                Some(packet_row_id)
-                            } else {
-                                None
-                            },
-                            status,
-                        ],
-                    )?;
-                    tx.last_insert_rowid()
-                }
-                Err(e) => return Err(e.into()),
-            }
-        };
+    /// Track a DM we just transmitted (or failed to), keyed by its packet ID.
+    pub fn add_pending_send(
+        &self,
+        packet_id: u32,
+        send: &PendingSend,
+        failed: bool,
+        now: i64,
+    ) -> Result<i64, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO pending_sends
+                (packet_id, to_node, mesh_channel, text, reply_id, attempts, created_at,
+                 sent_at, failed)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?7, ?8)",
+            params![
+                packet_id as i64,
+                send.to_node as i64,
+                send.mesh_channel as i64,
+                send.text,
+                send.reply_id.map(|id| id as i64),
+                send.attempts as i64,
+                now,
+                failed as i64,
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
 
-        for (idx, node) in request_route.iter().enumerate() {
-            tx.execute(
-                "INSERT INTO traceroute_session_hops (session_id, direction, hop_index, node_id, observed_at, packet_id_ref, source_kind)
-                 VALUES (?1, 'request', ?2, ?3, ?4, ?5, 'route')",
-                params![session_id, idx as i64, *node as i64, now, packet_row_id],
-            )?;
-        }
-        for (idx, node) in response_route.iter().enumerate() {
-            tx.execute(
-                "INSERT INTO traceroute_session_hops (session_id, direction, hop_index, node_id, observed_at, packet_id_ref, source_kind)
-                 VALUES (?1, 'response', ?2, ?3, ?4, ?5, 'route_back')",
-                params![session_id, idx as i64, *node as i64, now, packet_row_id],
-            )?;
-        }
+    /// Count another attempt at a pending DM, queued at `now`; it isn't due
+    /// again until that attempt times out.
+    pub fn retry_pending_send(
+        &self,
+        id: i64,
+        now: i64,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE pending_sends SET attempts = attempts + 1, sent_at = ?2, failed = 0
+             WHERE id = ?1",
+            params![id, now],
+        )?;
+        Ok(())
+    }
 
-        tx.commit()?;
+    /// Record the packet ID a retried DM went out with.
+    pub fn pending_send_transmitted(
+        &self,
+        id: i64,
+        packet_id: u32,
+        failed: bool,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE pending_sends SET packet_id = ?2, failed = ?3 WHERE id = ?1",
+            params![id, packet_id as i64, failed as i64],
+        )?;
         Ok(())
     }
 
-    /// Check whether a traceroute session with the given trace_key was first seen
-    /// at or after `since_ts`. Used to correlate incoming RouteReply packets with
-    /// outgoing probe sessions within a bounded time window.
-    pub fn traceroute_session_exists_since(
+    /// A routing ACK from `from` for `packet_id`: the DM was delivered and
+    /// is forgotten. Returns whether it was pending.
+    pub fn ack_pending_send(
         &self,
-        trace_key: &str,
-        since_ts: i64,
+        packet_id: u32,
+        from: u32,
     ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
         let conn = self.conn.lock().unwrap();
-        let result: Result<i64, _> = conn.query_row(
-            "SELECT 1 FROM traceroute_sessions
-             WHERE trace_key = ?1 AND first_seen >= ?2
-             LIMIT 1",
-            params![trace_key, since_ts],
-            |row| row.get(0),
-        );
-        match result {
-            Ok(_) => Ok(true),
-            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(false),
-            Err(e) => Err(e.into()),
-        }
+        let deleted = conn.execute(
+            "DELETE FROM pending_sends WHERE packet_id = ?1 AND to_node = ?2",
+            params![packet_id as i64, from as i64],
+        )?;
+        Ok(deleted > 0)
     }
 
-    pub fn dashboard_traceroute_sessions(
+    /// A routing error for `packet_id`: retry the DM as soon as its
+    /// destination is heard. Returns whether it was pending.
+    pub fn fail_pending_send(
         &self,
-        hours: u32,
-        limit: usize,
-    ) -> Result<Vec<serde_json::Value>, Box<dyn std::error::Error + Send + Sync>> {
-        use std::collections::HashMap;
+        packet_id: u32,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        let updated = conn.execute(
+            "UPDATE pending_sends SET failed = 1 WHERE packet_id = ?1",
+            params![packet_id as i64],
+        )?;
+        Ok(updated > 0)
+    }
 
+    /// Pending DMs to `to_node` first sent after `since` and due for another
+    /// try: those that failed, and those unacknowledged after
+    /// `ack_timeout_secs` doubled per attempt.
+    pub fn due_pending_sends(
+        &self,
+        to_node: u32,
+        ack_timeout_secs: i64,
+        since: i64,
+        now: i64,
+    ) -> Result<Vec<PendingSend>, Box<dyn std::error::Error + Send + Sync>> {
         let conn = self.conn.lock().unwrap();
-        let since = if hours == 0 {
-            0i64
-        } else {
-            Utc::now().timestamp() - (hours as i64 * 3600)
-        };
+        let mut stmt = conn.prepare(
+            "SELECT id, to_node, mesh_channel, text, reply_id, attempts
+             FROM pending_sends
+             WHERE to_node = ?1 AND created_at > ?3
+               AND (failed = 1 OR sent_at + (?2 << (attempts - 1)) <= ?4)
+             ORDER BY id",
+        )?;
+        let rows = stmt
+            .query_map(
+                params![to_node as i64, ack_timeout_secs, since, now],
+                |row| {
+                    Ok(PendingSend {
+                        id: row.get(0)?,
+                        to_node: row.get::<_, i64>(1)? as u32,
+                        mesh_channel: row.get::<_, i64>(2)? as u32,
+                        text: row.get(3)?,
+                        reply_id: row.get::<_, Option<i64>>(4)?.map(|id| id as u32),
+                        attempts: row.get::<_, i64>(5)? as u32,
+                    })
+                },
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
 
-        // Query sessions with resolved node names.
-        let sessions_sql = "
-            SELECT
-                s.id,
-                s.trace_key,
-                s.first_seen,
-                s.last_seen,
-                s.src_node,
-                COALESCE(ns.short_name, NULL) AS src_short_name,
-                COALESCE(ns.long_name, NULL) AS src_long_name,
-                s.dst_node,
-                COALESCE(nd.short_name, NULL) AS dst_short_name,
-                COALESCE(nd.long_name, NULL) AS dst_long_name,
-                s.via_mqtt,
-                s.request_hops,
-                s.request_hop_start,
-                s.response_hops,
-                s.response_hop_start,
-                s.status,
-                s.sample_count
-            FROM traceroute_sessions s
-            LEFT JOIN nodes ns ON ns.node_id = s.src_node
-            LEFT JOIN nodes nd ON nd.node_id = s.dst_node
-            WHERE s.last_seen >= ?1
-            ORDER BY s.last_seen DESC, s.id DESC
-            LIMIT ?2";
+    pub fn delete_pending_send(
+        &self,
+        id: i64,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM pending_sends WHERE id = ?1", params![id])?;
+        Ok(())
+    }
 
-        struct SessionRow {
-            id: i64,
-            trace_key: String,
-            first_seen: i64,
-            last_seen: i64,
-            src_node: i64,
-            src_short_name: Option<String>,
-            src_long_name: Option<String>,
-            dst_node: Option<i64>,
-            dst_short_name: Option<String>,
-            dst_long_name: Option<String>,
-            via_mqtt: i64,
-            request_hops: Option<i64>,
-            request_hop_start: Option<i64>,
-            response_hops: Option<i64>,
-            response_hop_start: Option<i64>,
-            status: String,
-            sample_count: i64,
+    /// Drop pending DMs first sent before `before`; returns how many.
+    pub fn prune_pending_sends(
+        &self,
+        before: i64,
+    ) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        let deleted = conn.execute(
+            "DELETE FROM pending_sends WHERE created_at < ?1",
+            params![before],
+        )?;
+        Ok(deleted)
+    }
+
+    // --- Neighbor info ---
+
+    /// Record the neighbors a node reported as `(neighbor_id, snr)`. Edges
+    /// it no longer reports are kept and age out of `network_graph`.
+    pub fn record_neighbors(
+        &self,
+        node_id: u32,
+        neighbors: &[(u32, f32)],
+        now: i64,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        for (neighbor_id, snr) in neighbors {
+            tx.execute(
+                "INSERT INTO neighbors (node_id, neighbor_id, snr, last_seen)
+                 VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(node_id, neighbor_id) DO UPDATE SET
+                    snr = ?3,
+                    last_seen = ?4",
+                params![node_id as i64, *neighbor_id as i64, *snr as f64, now],
+            )?;
         }
+        tx.commit()?;
+        Ok(())
+    }
 
-        let rows: Vec<SessionRow> = conn
-            .prepare(sessions_sql)?
-            .query_map(params![since, limit as i64], |row| {
-                Ok(SessionRow {
-                    id: row.get(0)?,
-                    trace_key: row.get(1)?,
-                    first_seen: row.get(2)?,
-                    last_seen: row.get(3)?,
-                    src_node: row.get(4)?,
-                    src_short_name: row.get(5)?,
-                    src_long_name: row.get(6)?,
-                    dst_node: row.get(7)?,
-                    dst_short_name: row.get(8)?,
-                    dst_long_name: row.get(9)?,
-                    via_mqtt: row.get(10)?,
-                    request_hops: row.get(11)?,
-                    request_hop_start: row.get(12)?,
-                    response_hops: row.get(13)?,
-                    response_hop_start: row.get(14)?,
-                    status: row.get(15)?,
-                    sample_count: row.get(16)?,
-                })
+    /// Edges reported in the last `hours` (0 = all) and the nodes they
+    /// connect, for drawing the mesh topology.
+    pub fn network_graph(
+        &self,
+        hours: u32,
+    ) -> Result<NetworkGraph, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        let since = if hours == 0 {
+            0
+        } else {
+            Utc::now().timestamp() - (hours as i64 * 3600)
+        };
+        let mut stmt = conn.prepare(
+            "SELECT node_id, neighbor_id, snr, last_seen
+             FROM neighbors
+             WHERE last_seen > ?1
+             ORDER BY node_id, neighbor_id",
+        )?;
+        let edges = stmt
+            .query_map(params![since], |row| {
+                Ok((
+                    row.get::<_, i64>(0)? as u32,
+                    row.get::<_, i64>(1)? as u32,
+                    row.get::<_, f64>(2)?,
+                    row.get::<_, i64>(3)?,
+                ))
             })?
             .collect::<Result<Vec<_>, _>>()?;
 
-        if rows.is_empty() {
-            return Ok(Vec::new());
-        }
+        let mut stmt = conn.prepare(
+            "SELECT DISTINCT g.node_id, COALESCE(n.short_name, ''), COALESCE(n.long_name, '')
+             FROM (
+                SELECT node_id FROM neighbors WHERE last_seen > ?1
+                UNION
+                SELECT neighbor_id FROM neighbors WHERE last_seen > ?1
+             ) g
+             LEFT JOIN nodes n ON n.node_id = g.node_id
+             ORDER BY g.node_id",
+        )?;
+        let nodes = stmt
+            .query_map(params![since], |row| {
+                let nid: i64 = row.get(0)?;
+                Ok(GraphNode {
+                    node_id: format!("!{:08x}", nid as u32),
+                    short_name: row.get(1)?,
+                    long_name: row.get(2)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
 
-        // Collect session IDs for the hops query.
-        let session_ids: Vec<i64> = rows.iter().map(|r| r.id).collect();
-        let placeholders = session_ids
-            .iter()
-            .enumerate()
-            .map(|(i, _)| format!("?{}", i + 1))
-            .collect::<Vec<_>>()
-            .join(", ");
+        Ok(NetworkGraph {
+            nodes,
+            edges: edges
+                .into_iter()
+                .map(|(from, to, snr, last_seen)| GraphEdge {
+                    from: format!("!{:08x}", from),
+                    to: format!("!{:08x}", to),
+                    snr,
+                    last_seen,
+                })
+                .collect(),
+        })
+    }
 
-        let hops_sql = format!(
-            "SELECT h.session_id, h.direction, h.hop_index, h.node_id,
-                    n.short_name, n.long_name
-             FROM traceroute_session_hops h
-             LEFT JOIN nodes n ON n.node_id = h.node_id
-             WHERE h.session_id IN ({})
-             ORDER BY h.session_id, h.direction, h.hop_index",
-            placeholders
-        );
+    /// Radio links seen since `since`: NeighborInfo edges plus consecutive
+    /// nodes of traceroute routes. A route only reaches its destination
+    /// once the reply proves it, so the last leg is left out before that.
+    pub fn mesh_links(
+        &self,
+        since: i64,
+    ) -> Result<Vec<(u32, u32)>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.read_conn();
+        let mut links = conn
+            .prepare("SELECT node_id, neighbor_id FROM neighbors WHERE last_seen > ?1")?
+            .query_map(params![since], |row| {
+                Ok((row.get::<_, i64>(0)? as u32, row.get::<_, i64>(1)? as u32))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
 
-        struct HopRow {
-            session_id: i64,
-            direction: String,
-            hop_index: i64,
-            node_id: i64,
-            short_name: Option<String>,
-            long_name: Option<String>,
+        let sessions = conn
+            .prepare(
+                "SELECT id, src_node, dst_node, response_hops IS NOT NULL
+                 FROM traceroute_sessions WHERE last_seen > ?1",
+            )?
+            .query_map(params![since], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, i64>(1)? as u32,
+                    row.get::<_, Option<i64>>(2)?.map(|n| n as u32),
+                    row.get::<_, bool>(3)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut hops_stmt = conn.prepare(
+            "SELECT node_id FROM traceroute_session_hops
+             WHERE session_id = ?1 AND direction = ?2
+             ORDER BY hop_index",
+        )?;
+        for (id, src, dst, responded) in sessions {
+            for direction in ["request", "response"] {
+                let relays = hops_stmt
+                    .query_map(params![id, direction], |row| {
+                        Ok(row.get::<_, i64>(0)? as u32)
+                    })?
+                    .collect::<Result<Vec<_>, _>>()?;
+                let (start, end) = if direction == "request" {
+                    (Some(src), dst.filter(|_| responded))
+                } else if responded {
+                    (dst, Some(src))
+                } else {
+                    continue;
+                };
+                let route: Vec<u32> = start.into_iter().chain(relays).chain(end).collect();
+                // Relays that didn't identify themselves break the chain
+                links.extend(
+                    route
+                        .windows(2)
+                        .filter(|pair| !pair.contains(&u32::MAX))
+                        .map(|pair| (pair[0], pair[1])),
+                );
+            }
         }
+        Ok(links)
+    }
 
-        let mut hops_by_session: HashMap<i64, Vec<HopRow>> = HashMap::new();
+    /// Replace the stored hop matrix (`(from, to, hops)`, `from` < `to`) and
+    /// articulation points with a fresh computation.
+    pub fn store_mesh_graph(
+        &self,
+        hops: &[(u32, u32, u32)],
+        cut_nodes: &[CutNode],
+        now: i64,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM mesh_hops", [])?;
+        tx.execute("DELETE FROM mesh_cut_nodes", [])?;
         {
-            let mut hops_stmt = conn.prepare(&hops_sql)?;
-            let params_refs: Vec<&dyn rusqlite::ToSql> = session_ids
-                .iter()
-                .map(|id| id as &dyn rusqlite::ToSql)
-                .collect();
-            let hop_rows = hops_stmt
-                .query_map(params_refs.as_slice(), |row| {
-                    Ok(HopRow {
-                        session_id: row.get(0)?,
-                        direction: row.get(1)?,
-                        hop_index: row.get(2)?,
-                        node_id: row.get(3)?,
+            let mut stmt = tx.prepare(
+                "INSERT INTO mesh_hops (from_node, to_node, hops, updated_at)
+                 VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            for &(from, to, count) in hops {
+                stmt.execute(params![from as i64, to as i64, count as i64, now])?;
+            }
+            let mut stmt = tx.prepare(
+                "INSERT INTO mesh_cut_nodes (node_id, cut_off_node, updated_at)
+                 VALUES (?1, ?2, ?3)",
+            )?;
+            for cut in cut_nodes {
+                for &node in &cut.cut_off {
+                    stmt.execute(params![cut.node as i64, node as i64, now])?;
+                }
+            }
+        }
+        // Remember when the last run happened even if it found no links
+        tx.execute(
+            "INSERT INTO bot_state (key, value) VALUES (?1, ?2)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            params![MESH_GRAPH_UPDATED_KEY, now.to_string()],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn mesh_hop_matrix(
+        &self,
+    ) -> Result<MeshHopMatrix, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.read_conn();
+        let updated_at = match conn.query_row(
+            "SELECT value FROM bot_state WHERE key = ?1",
+            params![MESH_GRAPH_UPDATED_KEY],
+            |row| row.get::<_, String>(0),
+        ) {
+            Ok(value) => value.parse().ok(),
+            Err(rusqlite::Error::QueryReturnedNoRows) => None,
+            Err(e) => return Err(e.into()),
+        };
+        let nodes = conn
+            .prepare(
+                "SELECT g.node_id, COALESCE(n.short_name, ''), COALESCE(n.long_name, '')
+                 FROM (
+                    SELECT from_node AS node_id FROM mesh_hops
+                    UNION
+                    SELECT to_node FROM mesh_hops
+                 ) g
+                 LEFT JOIN nodes n ON n.node_id = g.node_id
+                 ORDER BY g.node_id",
+            )?
+            .query_map([], |row| {
+                let nid: i64 = row.get(0)?;
+                Ok(GraphNode {
+                    node_id: format!("!{:08x}", nid as u32),
+                    short_name: row.get(1)?,
+                    long_name: row.get(2)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        let pairs = conn
+            .prepare("SELECT from_node, to_node, hops FROM mesh_hops ORDER BY from_node, to_node")?
+            .query_map([], |row| {
+                Ok(MeshHops {
+                    from: format!("!{:08x}", row.get::<_, i64>(0)? as u32),
+                    to: format!("!{:08x}", row.get::<_, i64>(1)? as u32),
+                    hops: row.get::<_, i64>(2)? as u32,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(MeshHopMatrix {
+            updated_at,
+            nodes,
+            pairs,
+        })
+    }
+
+    /// Articulation points of the mesh graph, those cutting off the most
+    /// nodes first.
+    pub fn router_suggestions(
+        &self,
+    ) -> Result<Vec<RouterSuggestion>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.read_conn();
+        let rows = conn
+            .prepare(
+                "SELECT c.node_id, COALESCE(n.short_name, ''), COALESCE(n.long_name, ''),
+                        c.cut_off_node, COALESCE(o.short_name, ''), COALESCE(o.long_name, '')
+                 FROM mesh_cut_nodes c
+                 LEFT JOIN nodes n ON n.node_id = c.node_id
+                 LEFT JOIN nodes o ON o.node_id = c.cut_off_node
+                 ORDER BY c.node_id, c.cut_off_node",
+            )?
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, i64>(0)? as u32,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    GraphNode {
+                        node_id: format!("!{:08x}", row.get::<_, i64>(3)? as u32),
                         short_name: row.get(4)?,
                         long_name: row.get(5)?,
-                    })
-                })?
-                .collect::<Result<Vec<_>, _>>()?;
-            for hop in hop_rows {
-                hops_by_session.entry(hop.session_id).or_default().push(hop);
+                    },
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut suggestions: Vec<RouterSuggestion> = Vec::new();
+        for (node, short_name, long_name, cut_off) in rows {
+            let node_id = format!("!{:08x}", node);
+            match suggestions.last_mut() {
+                Some(last) if last.node_id == node_id => last.cut_off.push(cut_off),
+                _ => suggestions.push(RouterSuggestion {
+                    node_id,
+                    short_name,
+                    long_name,
+                    cut_off: vec![cut_off],
+                }),
             }
         }
+        suggestions.sort_by_key(|s| std::cmp::Reverse(s.cut_off.len()));
+        Ok(suggestions)
+    }
 
-        let result = rows
-            .into_iter()
-            .map(|s| {
-                let hops_json: Vec<serde_json::Value> = hops_by_session
-                    .get(&s.id)
-                    .map(|hops| {
-                        hops.iter()
-                            .map(|h| {
-                                serde_json::json!({
-                                    "direction": h.direction,
-                                    "hop_index": h.hop_index,
-                                    "node_id": format!("!{:08x}", h.node_id as u32),
-                                    "short_name": h.short_name,
-                                    "long_name": h.long_name,
-                                })
-                            })
-                            .collect()
-                    })
-                    .unwrap_or_default();
-
-                let dst_node_str = s.dst_node.map(|n| format!("!{:08x}", n as u32));
-
-                serde_json::json!({
-                    "id": s.id,
-                    "trace_key": s.trace_key,
-                    "first_seen": s.first_seen,
-                    "last_seen": s.last_seen,
-                    "src_node": format!("!{:08x}", s.src_node as u32),
-                    "src_short_name": s.src_short_name,
-                    "src_long_name": s.src_long_name,
-                    "dst_node": dst_node_str,
-                    "dst_short_name": s.dst_short_name,
-                    "dst_long_name": s.dst_long_name,
-                    "via_mqtt": s.via_mqtt != 0,
-                    "request_hops": s.request_hops,
-                    "request_hop_start": s.request_hop_start,
-                    "response_hops": s.response_hops,
-                    "response_hop_start": s.response_hop_start,
-                    "status": s.status,
-                    "sample_count": s.sample_count,
-                    "hops": hops_json,
+    // --- Device telemetry ---
+
+    /// Store a DeviceMetrics report.
+    pub fn log_device_telemetry(
+        &self,
+        node_id: u32,
+        metrics: &DeviceTelemetry,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO telemetry
+                (node_id, timestamp, battery_level, voltage, channel_utilization,
+                 air_util_tx, uptime_seconds)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                node_id as i64,
+                metrics.timestamp,
+                metrics.battery_level,
+                metrics.voltage,
+                metrics.channel_utilization,
+                metrics.air_util_tx,
+                metrics.uptime_seconds,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// A node's device telemetry over the last `hours` (0 = all), oldest first.
+    pub fn get_node_telemetry(
+        &self,
+        node_id: u32,
+        hours: u32,
+    ) -> Result<Vec<DeviceTelemetry>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        let since = if hours == 0 {
+            0
+        } else {
+            Utc::now().timestamp() - (hours as i64 * 3600)
+        };
+        let mut stmt = conn.prepare(
+            "SELECT timestamp, battery_level, voltage, channel_utilization, air_util_tx,
+                    uptime_seconds
+             FROM telemetry
+             WHERE node_id = ?1 AND timestamp > ?2
+             ORDER BY timestamp, id",
+        )?;
+        let rows = stmt
+            .query_map(params![node_id as i64, since], |row| {
+                Ok(DeviceTelemetry {
+                    timestamp: row.get(0)?,
+                    battery_level: row.get(1)?,
+                    voltage: row.get(2)?,
+                    channel_utilization: row.get(3)?,
+                    air_util_tx: row.get(4)?,
+                    uptime_seconds: row.get(5)?,
                 })
-            })
-            .collect();
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
 
-        Ok(result)
+    // --- Battery alerts ---
+
+    /// The alert level (`low`/`critical`) last raised for a node's battery.
+    pub fn battery_alert_level(
+        &self,
+        node_id: u32,
+    ) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        match conn.query_row(
+            "SELECT level FROM battery_alerts WHERE node_id = ?1",
+            params![node_id as i64],
+            |row| row.get(0),
+        ) {
+            Ok(level) => Ok(Some(level)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Record the node's current alert level; `None` clears it once the
+    /// battery has recovered.
+    pub fn set_battery_alert_level(
+        &self,
+        node_id: u32,
+        level: Option<&str>,
+        battery_level: u32,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        match level {
+            Some(level) => conn.execute(
+                "INSERT INTO battery_alerts (node_id, level, battery_level, updated_at)
+                 VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(node_id) DO UPDATE SET
+                    level = excluded.level,
+                    battery_level = excluded.battery_level,
+                    updated_at = excluded.updated_at",
+                params![
+                    node_id as i64,
+                    level,
+                    battery_level as i64,
+                    Utc::now().timestamp()
+                ],
+            )?,
+            None => conn.execute(
+                "DELETE FROM battery_alerts WHERE node_id = ?1",
+                params![node_id as i64],
+            )?,
+        };
+        Ok(())
+    }
+
+    // --- Alert rules ---
+
+    /// Nodes (0 for mesh-wide conditions) that `[[alerts]]` rule `rule`
+    /// has fired for and that haven't cleared since.
+    pub fn alert_firing_nodes(
+        &self,
+        rule: &str,
+    ) -> Result<Vec<u32>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT node_id FROM alert_state WHERE rule = ?1")?;
+        let nodes = stmt
+            .query_map(params![rule], |row| Ok(row.get::<_, i64>(0)? as u32))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(nodes)
+    }
+
+    /// Record that `rule` fired for `node_id`; `None` clears it once the
+    /// condition no longer holds.
+    pub fn set_alert_fired(
+        &self,
+        rule: &str,
+        node_id: u32,
+        fired_at: Option<i64>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        match fired_at {
+            Some(fired_at) => conn.execute(
+                "INSERT OR REPLACE INTO alert_state (rule, node_id, fired_at)
+                 VALUES (?1, ?2, ?3)",
+                params![rule, node_id as i64, fired_at],
+            )?,
+            None => conn.execute(
+                "DELETE FROM alert_state WHERE rule = ?1 AND node_id = ?2",
+                params![rule, node_id as i64],
+            )?,
+        };
+        Ok(())
+    }
+
+    /// When a node was last heard, or `None` if it never was.
+    pub fn node_last_seen(
+        &self,
+        node_id: u32,
+    ) -> Result<Option<i64>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        match conn.query_row(
+            "SELECT last_seen FROM nodes WHERE node_id = ?1",
+            params![node_id as i64],
+            |row| row.get(0),
+        ) {
+            Ok(last_seen) => Ok(Some(last_seen)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Each node's most recent DeviceMetrics report.
+    pub fn latest_device_telemetry(
+        &self,
+    ) -> Result<Vec<(u32, DeviceTelemetry)>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT node_id, timestamp, battery_level, voltage, channel_utilization,
+                    air_util_tx, uptime_seconds
+             FROM (
+                SELECT *, ROW_NUMBER() OVER (
+                    PARTITION BY node_id ORDER BY timestamp DESC, id DESC
+                ) AS rn
+                FROM telemetry
+             )
+             WHERE rn = 1
+             ORDER BY node_id",
+        )?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, i64>(0)? as u32,
+                    DeviceTelemetry {
+                        timestamp: row.get(1)?,
+                        battery_level: row.get(2)?,
+                        voltage: row.get(3)?,
+                        channel_utilization: row.get(4)?,
+                        air_util_tx: row.get(5)?,
+                        uptime_seconds: row.get(6)?,
+                    },
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Nodes first heard directly (not imported from a radio's NodeDB)
+    /// at or after `since`.
+    pub fn nodes_first_seen_since(
+        &self,
+        since: i64,
+    ) -> Result<Vec<u32>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT node_id FROM nodes
+             WHERE first_seen >= ?1 AND source = 'observed'
+             ORDER BY first_seen",
+        )?;
+        let nodes = stmt
+            .query_map(params![since], |row| Ok(row.get::<_, i64>(0)? as u32))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(nodes)
+    }
+
+    /// When the last packet was received, or `None` before the first one.
+    pub fn last_packet_in_at(
+        &self,
+    ) -> Result<Option<i64>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        Ok(conn.query_row(
+            "SELECT MAX(timestamp) FROM packets WHERE direction = 'in'",
+            [],
+            |row| row.get(0),
+        )?)
+    }
+
+    /// When the oldest packet still logged was received, or `None` before
+    /// the first one.
+    pub fn first_packet_in_at(
+        &self,
+    ) -> Result<Option<i64>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        Ok(conn.query_row(
+            "SELECT MIN(timestamp) FROM packets WHERE direction = 'in'",
+            [],
+            |row| row.get(0),
+        )?)
+    }
+
+    /// Text messages received on mesh channel `channel` at or after `since`.
+    pub fn text_messages_in_since(
+        &self,
+        channel: u32,
+        since: i64,
+    ) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        let count: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM packets
+             WHERE packet_type = 'text' AND timestamp >= ?2
+               AND direction = 'in' AND channel = ?1",
+            params![channel as i64, since],
+            |row| row.get(0),
+        )?;
+        Ok(count as u64)
+    }
+
+    // --- MQTT command policy ---
+
+    /// Record how a command heard via MQTT was handled (`replied`, `dm` or `ignored`).
+    pub fn log_mqtt_command(
+        &self,
+        node_id: u32,
+        command: &str,
+        action: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO mqtt_commands (timestamp, node_id, command, action)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![Utc::now().timestamp(), node_id as i64, command, action],
+        )?;
+        Ok(())
+    }
+
+    // --- Store & Forward ---
+
+    /// Note that `node_id` answered as a Store & Forward router, with its
+    /// `(saved, max)` message counts when the packet carried stats.
+    pub fn record_store_forward_router(
+        &self,
+        node_id: u32,
+        stats: Option<(u32, u32)>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        let now = Utc::now().timestamp();
+        conn.execute(
+            "INSERT INTO store_forward_routers
+                (node_id, first_seen, last_seen, messages_saved, messages_max)
+             VALUES (?1, ?2, ?2, ?3, ?4)
+             ON CONFLICT(node_id) DO UPDATE SET
+                last_seen = excluded.last_seen,
+                messages_saved = COALESCE(excluded.messages_saved, messages_saved),
+                messages_max = COALESCE(excluded.messages_max, messages_max)",
+            params![
+                node_id as i64,
+                now,
+                stats.map(|(saved, _)| saved as i64),
+                stats.map(|(_, max)| max as i64),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Known Store & Forward routers, most recently heard first.
+    pub fn store_forward_routers(
+        &self,
+    ) -> Result<Vec<StoreForwardRouter>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT node_id, last_seen, messages_saved, messages_max
+             FROM store_forward_routers
+             ORDER BY last_seen DESC, node_id",
+        )?;
+        let routers = stmt
+            .query_map([], |row| {
+                let node_id: i64 = row.get(0)?;
+                Ok(StoreForwardRouter {
+                    node_id: node_id as u32,
+                    last_seen: row.get(1)?,
+                    messages_saved: row.get::<_, Option<i64>>(2)?.map(|n| n as u32),
+                    messages_max: row.get::<_, Option<i64>>(3)?.map(|n| n as u32),
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(routers)
+    }
+
+    /// Insert a text message replayed by a Store & Forward router under its
+    /// original timestamp. Returns `false` if the packet was already logged
+    /// (heard live or replayed before).
+    pub fn merge_store_forward_text(
+        &self,
+        from_node: u32,
+        to_node: Option<u32>,
+        channel: u32,
+        text: &str,
+        mesh_packet_id: u32,
+        timestamp: i64,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        let known: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM packets
+             WHERE from_node = ?1 AND mesh_packet_id = ?2 AND packet_type = 'text'",
+            params![from_node as i64, mesh_packet_id as i64],
+            |row| row.get(0),
+        )?;
+        if known > 0 {
+            return Ok(false);
+        }
+        conn.execute(
+            "INSERT INTO packets (timestamp, from_node, to_node, channel, text, direction, via_mqtt, mesh_packet_id, packet_type)
+             VALUES (?1, ?2, ?3, ?4, ?5, 'in', 0, ?6, 'text')",
+            params![
+                timestamp,
+                from_node as i64,
+                to_node.map(|n| n as i64),
+                channel as i64,
+                text,
+                mesh_packet_id as i64,
+            ],
+        )?;
+        Ok(true)
+    }
+
+    // --- Short links ---
+
+    /// Store `url` under `code`. Returns `false` if the code is already taken
+    /// by a different URL.
+    pub fn store_short_link(
+        &self,
+        code: &str,
+        url: &str,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR IGNORE INTO short_links (code, url, created_at) VALUES (?1, ?2, ?3)",
+            params![code, url, Utc::now().timestamp()],
+        )?;
+        let stored: String = conn.query_row(
+            "SELECT url FROM short_links WHERE code = ?1",
+            params![code],
+            |row| row.get(0),
+        )?;
+        Ok(stored == url)
+    }
+
+    pub fn resolve_short_link(
+        &self,
+        code: &str,
+    ) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        match conn.query_row(
+            "SELECT url FROM short_links WHERE code = ?1",
+            params![code],
+            |row| row.get(0),
+        ) {
+            Ok(url) => Ok(Some(url)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Calls made today (UTC) per external API.
+    pub fn api_usage_today(
+        &self,
+    ) -> Result<Vec<ApiUsage>, Box<dyn std::error::Error + Send + Sync>> {
+        let day = Utc::now().format("%Y-%m-%d").to_string();
+        let conn = self.conn.lock().unwrap();
+        let mut stmt =
+            conn.prepare("SELECT api, calls FROM api_usage WHERE day = ?1 ORDER BY api")?;
+        let rows = stmt
+            .query_map(params![day], |row| {
+                Ok(ApiUsage {
+                    api: row.get(0)?,
+                    calls: row.get::<_, i64>(1)? as u64,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    // --- Incidents ---
+
+    /// Record an emergency incident. Returns `None` if this packet was already
+    /// recorded (e.g. heard again via MQTT), otherwise the incident ID.
+    pub fn log_incident(
+        &self,
+        node_id: u32,
+        mesh_packet_id: u32,
+        channel: u32,
+        keyword: &str,
+        text: &str,
+        position: Option<(f64, f64)>,
+    ) -> Result<Option<i64>, Box<dyn std::error::Error + Send + Sync>> {
+        let now = Utc::now().timestamp();
+        let conn = self.conn.lock().unwrap();
+        let inserted = conn.execute(
+            "INSERT OR IGNORE INTO incidents
+                (node_id, mesh_packet_id, channel, keyword, text, latitude, longitude, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                node_id as i64,
+                mesh_packet_id as i64,
+                channel as i64,
+                keyword,
+                text,
+                position.map(|p| p.0),
+                position.map(|p| p.1),
+                now
+            ],
+        )?;
+        Ok(if inserted > 0 {
+            Some(conn.last_insert_rowid())
+        } else {
+            None
+        })
+    }
+
+    /// Most recent incidents, newest first.
+    pub fn recent_incidents(
+        &self,
+        limit: usize,
+    ) -> Result<Vec<Incident>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT i.id, i.node_id, COALESCE(NULLIF(n.long_name, ''), n.short_name, ''),
+                    i.channel, i.keyword, i.text, i.latitude, i.longitude, i.created_at
+             FROM incidents i
+             LEFT JOIN nodes n ON n.node_id = i.node_id
+             ORDER BY i.created_at DESC, i.id DESC
+             LIMIT ?1",
+        )?;
+        let rows = stmt
+            .query_map(params![limit as i64], |row| {
+                Ok(Incident {
+                    id: row.get(0)?,
+                    node_id: format!("!{:08x}", row.get::<_, i64>(1)? as u32),
+                    node_name: row.get(2)?,
+                    channel: row.get::<_, i64>(3)? as u32,
+                    keyword: row.get(4)?,
+                    text: row.get(5)?,
+                    latitude: row.get(6)?,
+                    longitude: row.get(7)?,
+                    created_at: row.get(8)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    // --- Net logs ---
+
+    /// Check a node into the net session starting at `started_at`, creating the
+    /// session on first check-in. Checking in again updates callsign and comment.
+    /// Returns the number of nodes checked in so far.
+    #[allow(clippy::too_many_arguments)]
+    pub fn net_checkin(
+        &self,
+        name: &str,
+        channel: u32,
+        started_at: i64,
+        ends_at: i64,
+        node_id: u32,
+        callsign: &str,
+        comment: Option<&str>,
+    ) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        let now = Utc::now().timestamp();
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT OR IGNORE INTO nets (name, channel, started_at, ends_at) VALUES (?1, ?2, ?3, ?4)",
+            params![name, channel as i64, started_at, ends_at],
+        )?;
+        let net_id: i64 = tx.query_row(
+            "SELECT id FROM nets WHERE name = ?1 AND started_at = ?2",
+            params![name, started_at],
+            |row| row.get(0),
+        )?;
+        tx.execute(
+            "INSERT INTO net_checkins (net_id, node_id, callsign, comment, checked_in_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT(net_id, node_id) DO UPDATE SET callsign = ?3, comment = ?4",
+            params![net_id, node_id as i64, callsign, comment, now],
+        )?;
+        let count: i64 = tx.query_row(
+            "SELECT COUNT(*) FROM net_checkins WHERE net_id = ?1",
+            params![net_id],
+            |row| row.get(0),
+        )?;
+        tx.commit()?;
+        Ok(count as u64)
+    }
+
+    fn load_net_checkins(
+        conn: &Connection,
+        net_id: i64,
+    ) -> Result<Vec<NetCheckin>, rusqlite::Error> {
+        let mut stmt = conn.prepare(
+            "SELECT node_id, callsign, comment, checked_in_at FROM net_checkins
+             WHERE net_id = ?1 ORDER BY checked_in_at, node_id",
+        )?;
+        let rows = stmt
+            .query_map(params![net_id], |row| {
+                Ok(NetCheckin {
+                    node_id: format!("!{:08x}", row.get::<_, i64>(0)? as u32),
+                    callsign: row.get(1)?,
+                    comment: row.get(2)?,
+                    checked_in_at: row.get(3)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    fn load_nets(conn: &Connection, sql: &str, param: i64) -> Result<Vec<NetLog>, rusqlite::Error> {
+        let mut stmt = conn.prepare(sql)?;
+        let mut nets = stmt
+            .query_map(params![param], |row| {
+                Ok(NetLog {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    channel: row.get::<_, i64>(2)? as u32,
+                    started_at: row.get(3)?,
+                    ends_at: row.get(4)?,
+                    checkins: Vec::new(),
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        for net in &mut nets {
+            net.checkins = Self::load_net_checkins(conn, net.id)?;
+        }
+        Ok(nets)
+    }
+
+    /// Net sessions that have ended but whose roster was not posted yet.
+    /// Marks them as posted.
+    pub fn take_finished_nets(
+        &self,
+        now: i64,
+    ) -> Result<Vec<NetLog>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let nets = Self::load_nets(
+            &tx,
+            "SELECT id, name, channel, started_at, ends_at FROM nets
+             WHERE roster_posted = 0 AND ends_at <= ?1
+             ORDER BY ends_at",
+            now,
+        )?;
+        tx.execute(
+            "UPDATE nets SET roster_posted = 1 WHERE roster_posted = 0 AND ends_at <= ?1",
+            params![now],
+        )?;
+        tx.commit()?;
+        Ok(nets)
+    }
+
+    /// Most recent net sessions with their check-ins, newest first.
+    pub fn recent_nets(
+        &self,
+        limit: usize,
+    ) -> Result<Vec<NetLog>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        let nets = Self::load_nets(
+            &conn,
+            "SELECT id, name, channel, started_at, ends_at FROM nets
+             ORDER BY started_at DESC LIMIT ?1",
+            limit as i64,
+        )?;
+        Ok(nets)
+    }
+
+    // --- Self-test ---
+
+    /// Record a self-test transmission sent as mesh packet `packet_id`.
+    pub fn record_selftest_run(
+        &self,
+        packet_id: u32,
+        channel: u32,
+    ) -> Result<i64, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO selftest_runs (packet_id, channel, sent_at) VALUES (?1, ?2, ?3)",
+            params![packet_id as i64, channel as i64, Utc::now().timestamp()],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// Record that `node_id` acked or rebroadcast the self-test sent as
+    /// `packet_id` within the last `window_secs`. Returns false when no such
+    /// test is pending or the node was already recorded for it.
+    pub fn record_selftest_response(
+        &self,
+        packet_id: u32,
+        node_id: u32,
+        kind: &str,
+        window_secs: u64,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let now = Utc::now().timestamp();
+        let conn = self.conn.lock().unwrap();
+        let inserted = conn.execute(
+            "INSERT OR IGNORE INTO selftest_responses (run_id, node_id, kind, observed_at)
+             SELECT id, ?2, ?3, ?4 FROM selftest_runs
+             WHERE packet_id = ?1 AND sent_at >= ?5
+             ORDER BY sent_at DESC LIMIT 1",
+            params![
+                packet_id as i64,
+                node_id as i64,
+                kind,
+                now,
+                now - window_secs as i64
+            ],
+        )?;
+        Ok(inserted > 0)
+    }
+
+    /// The most recently seen RF node whose ID ends in `relay_byte`, the
+    /// only part of a relayer's ID carried in `MeshPacket.relay_node`.
+    pub fn node_for_relay_byte(
+        &self,
+        relay_byte: u8,
+    ) -> Result<Option<u32>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        match conn.query_row(
+            "SELECT node_id FROM nodes
+             WHERE via_mqtt = 0 AND (node_id & 255) = ?1
+             ORDER BY last_seen DESC LIMIT 1",
+            params![relay_byte as i64],
+            |row| row.get::<_, i64>(0),
+        ) {
+            Ok(node) => Ok(Some(node as u32)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Self-test runs since `since` and, for every node heard over RF since
+    /// then, how many of them it answered.
+    pub fn selftest_report(
+        &self,
+        since: i64,
+    ) -> Result<SelfTestReport, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.read_conn();
+        let runs: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM selftest_runs WHERE sent_at >= ?1",
+            params![since],
+            |row| row.get(0),
+        )?;
+        let mut stmt = conn.prepare(
+            "SELECT n.node_id, COALESCE(NULLIF(n.long_name, ''), n.short_name),
+                    COUNT(r.run_id), MAX(r.observed_at)
+             FROM nodes n
+             LEFT JOIN selftest_responses r ON r.node_id = n.node_id
+                 AND r.run_id IN (SELECT id FROM selftest_runs WHERE sent_at >= ?1)
+             WHERE n.via_mqtt = 0 AND n.last_seen >= ?1
+             GROUP BY n.node_id
+             ORDER BY COUNT(r.run_id) DESC, n.last_seen DESC",
+        )?;
+        let nodes = stmt
+            .query_map(params![since], |row| {
+                Ok(SelfTestNode {
+                    node_id: format!("!{:08x}", row.get::<_, i64>(0)? as u32),
+                    node_name: row.get(1)?,
+                    responses: row.get::<_, i64>(2)? as u64,
+                    last_response: row.get(3)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(SelfTestReport {
+            since,
+            runs: runs as u64,
+            nodes,
+        })
+    }
+
+    /// Delete everything stored about a node: its node row (name, position),
+    /// packets sent by or addressed to it, mail, away and mute status,
+    /// operator notes, poll votes, net check-ins, incidents, messages relayed
+    /// to bridges, and traceroute sessions and hops it took part in. Returns
+    /// the rows removed from each table.
+    pub fn purge_node_data(
+        &self,
+        node_id: u32,
+    ) -> Result<NodePurgeSummary, Box<dyn std::error::Error + Send + Sync>> {
+        let node = node_id as i64;
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let mut tables = BTreeMap::new();
+        for (table, filter) in NODE_DATA_TABLES {
+            let removed = tx.execute(
+                &format!("DELETE FROM {} WHERE {}", table, filter),
+                params![node],
+            )?;
+            tables.insert(*table, removed);
+        }
+        tx.commit()?;
+        Ok(NodePurgeSummary { tables })
+    }
+
+    pub fn get_node_position(
+        &self,
+        node_id: u32,
+    ) -> Result<Option<(f64, f64)>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        let result: Result<(Option<f64>, Option<f64>), _> = conn.query_row(
+            "SELECT latitude, longitude FROM nodes WHERE node_id = ?1",
+            params![node_id as i64],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        );
+        match result {
+            Ok((Some(lat), Some(lon))) if lat != 0.0 || lon != 0.0 => Ok(Some((lat, lon))),
+            _ => Ok(None),
+        }
+    }
+
+    /// When the node last sent a position packet, or when it was last seen
+    /// if those have been pruned.
+    pub fn position_reported_at(
+        &self,
+        node_id: u32,
+    ) -> Result<Option<i64>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        let result = conn.query_row(
+            "SELECT COALESCE(
+                 (SELECT MAX(timestamp) FROM packets
+                  WHERE from_node = n.node_id AND packet_type = ?2),
+                 n.last_seen)
+             FROM nodes n WHERE n.node_id = ?1",
+            params![node_id as i64, PacketType::Position],
+            |row| row.get(0),
+        );
+        match result {
+            Ok(timestamp) => Ok(Some(timestamp)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn message_count(
+        &self,
+        direction: &str,
+    ) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        let count: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM packets WHERE direction = ?1",
+            params![direction],
+            |row| row.get(0),
+        )?;
+        Ok(count as u64)
+    }
+
+    /// Most recent public text messages received, newest first. DMs are never
+    /// included.
+    pub fn recent_chat_messages(
+        &self,
+        limit: usize,
+    ) -> Result<Vec<ChatMessage>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT p.timestamp, p.from_node, COALESCE(NULLIF(n.long_name, ''), n.short_name, ''),
+                    p.text
+             FROM packets p
+             LEFT JOIN nodes n ON n.node_id = p.from_node
+             WHERE p.direction = 'in' AND p.packet_type = 'text' AND p.text != ''
+               AND (p.to_node IS NULL OR p.to_node = ?2)
+             ORDER BY p.id DESC
+             LIMIT ?1",
+        )?;
+        let rows = stmt
+            .query_map(params![limit as i64, u32::MAX as i64], |row| {
+                let name: String = row.get(2)?;
+                Ok(ChatMessage {
+                    timestamp: row.get(0)?,
+                    from_name: if name.is_empty() {
+                        format!("!{:08x}", row.get::<_, i64>(1)? as u32)
+                    } else {
+                        name
+                    },
+                    text: row.get(3)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// RF nodes last heard in `(after, until]`, for noticing nodes that just
+    /// went quiet.
+    pub fn rf_nodes_last_heard_between(
+        &self,
+        after: i64,
+        until: i64,
+    ) -> Result<Vec<LastHeardNode>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT node_id, long_name, last_seen FROM nodes
+             WHERE via_mqtt = 0 AND last_seen > ?1 AND last_seen <= ?2
+             ORDER BY last_seen",
+        )?;
+        let rows = stmt
+            .query_map(params![after, until], |row| {
+                let node_id = row.get::<_, i64>(0)? as u32;
+                let long_name: String = row.get(1)?;
+                let name = if long_name.is_empty() {
+                    format!("!{:08x}", node_id)
+                } else {
+                    long_name
+                };
+                Ok(LastHeardNode {
+                    node_id,
+                    name,
+                    last_seen: row.get(2)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    pub fn node_count(&self) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM nodes", [], |row| row.get(0))?;
+        Ok(count as u64)
+    }
+
+    /// Store the current node counts, so mesh growth can be plotted after
+    /// stale nodes are purged.
+    pub fn record_node_counts(
+        &self,
+        now: i64,
+    ) -> Result<NodeCounts, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        let (total, mqtt): (i64, i64) = conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(via_mqtt), 0) FROM nodes",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        let counts = NodeCounts {
+            timestamp: now,
+            total: total as u64,
+            rf: (total - mqtt) as u64,
+            mqtt: mqtt as u64,
+        };
+        conn.execute(
+            "INSERT OR REPLACE INTO node_counts (timestamp, total, rf, mqtt)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![now, total, total - mqtt, mqtt],
+        )?;
+        Ok(counts)
+    }
+
+    /// Node count snapshots from the last `hours` (0 = all), oldest first.
+    pub fn node_history(
+        &self,
+        hours: u32,
+    ) -> Result<Vec<NodeCounts>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.read_conn();
+        let since = if hours == 0 {
+            0
+        } else {
+            Utc::now().timestamp() - (hours as i64 * 3600)
+        };
+        let mut stmt = conn.prepare(
+            "SELECT timestamp, total, rf, mqtt FROM node_counts
+             WHERE timestamp > ?1
+             ORDER BY timestamp",
+        )?;
+        let rows = stmt
+            .query_map(params![since], |row| {
+                Ok(NodeCounts {
+                    timestamp: row.get(0)?,
+                    total: row.get::<_, i64>(1)? as u64,
+                    rf: row.get::<_, i64>(2)? as u64,
+                    mqtt: row.get::<_, i64>(3)? as u64,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Store one window of latency percentiles, dropping windows older than
+    /// `keep_secs`.
+    pub fn record_latency(
+        &self,
+        window: &LatencyWindow,
+        keep_secs: i64,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO latency_stats
+                (timestamp, stage, samples, p50_ms, p90_ms, p99_ms, max_ms)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                window.timestamp,
+                window.stage,
+                window.samples,
+                window.p50_ms,
+                window.p90_ms,
+                window.p99_ms,
+                window.max_ms
+            ],
+        )?;
+        conn.execute(
+            "DELETE FROM latency_stats WHERE timestamp < ?1",
+            params![window.timestamp - keep_secs],
+        )?;
+        Ok(())
+    }
+
+    /// Latency windows from the last `hours` (0 = all), oldest first.
+    pub fn latency_history(
+        &self,
+        hours: u32,
+    ) -> Result<Vec<LatencyWindow>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.read_conn();
+        let since = if hours == 0 {
+            0
+        } else {
+            Utc::now().timestamp() - (hours as i64 * 3600)
+        };
+        let mut stmt = conn.prepare(
+            "SELECT timestamp, stage, samples, p50_ms, p90_ms, p99_ms, max_ms
+             FROM latency_stats
+             WHERE timestamp > ?1
+             ORDER BY timestamp, stage",
+        )?;
+        let rows = stmt
+            .query_map(params![since], |row| {
+                Ok(LatencyWindow {
+                    timestamp: row.get(0)?,
+                    stage: row.get(1)?,
+                    samples: row.get(2)?,
+                    p50_ms: row.get(3)?,
+                    p90_ms: row.get(4)?,
+                    p99_ms: row.get(5)?,
+                    max_ms: row.get(6)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    pub fn find_node_by_name(
+        &self,
+        name: &str,
+    ) -> Result<Option<u32>, Box<dyn std::error::Error + Send + Sync>> {
+        // Try parsing as node ID (hex with/without prefix, or decimal)
+        if let Some(id) = parse_node_id(name) {
+            let conn = self.conn.lock().unwrap();
+            let exists: i64 = conn.query_row(
+                "SELECT COUNT(*) FROM nodes WHERE node_id = ?1",
+                params![id as i64],
+                |row| row.get(0),
+            )?;
+            if exists > 0 {
+                return Ok(Some(id));
+            }
+        }
+
+        // Try matching by short_name or long_name (case-insensitive)
+        let conn = self.conn.lock().unwrap();
+        let result: Result<i64, _> = conn.query_row(
+            "SELECT node_id FROM nodes WHERE lower(short_name) = lower(?1) OR lower(long_name) = lower(?1) LIMIT 1",
+            params![name],
+            |row| row.get(0),
+        );
+        match result {
+            Ok(id) => Ok(Some(id as u32)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Return up to `limit` most recently seen RF nodes missing inbound RF hop metadata.
+    pub fn recent_rf_nodes_missing_hops(
+        &self,
+        max_age_secs: u64,
+        exclude_node_id: Option<u32>,
+        limit: usize,
+    ) -> Result<Vec<u32>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        let since = Utc::now().timestamp() - (max_age_secs as i64);
+        let exclude = exclude_node_id.unwrap_or(0) as i64;
+        let mut stmt = conn.prepare(
+            "SELECT n.node_id
+             FROM nodes n
+             WHERE n.via_mqtt = 0
+               AND n.last_seen > ?1
+               AND (?2 = 0 OR n.node_id != ?2)
+               AND NOT EXISTS (
+                   SELECT 1
+                   FROM packets p
+                   WHERE p.from_node = n.node_id
+                     AND p.direction = 'in'
+                     AND p.via_mqtt = 0
+                     AND p.hop_count IS NOT NULL
+               )
+             ORDER BY n.last_seen DESC
+             LIMIT ?3",
+        )?;
+        let rows = stmt
+            .query_map(params![since, exclude, limit as i64], |row| {
+                row.get::<_, i64>(0)
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows.into_iter().map(|id| id as u32).collect())
+    }
+
+    // --- Packet logging ---
+
+    #[allow(clippy::too_many_arguments)]
+    fn log_packet_inner(
+        &self,
+        from_node: u32,
+        to_node: Option<u32>,
+        channel: u32,
+        text: &str,
+        direction: &str,
+        via_mqtt: bool,
+        rssi: Option<i32>,
+        snr: Option<f32>,
+        hop_count: Option<u32>,
+        hop_start: Option<u32>,
+        mesh_packet_id: Option<u32>,
+        packet_type: PacketType,
+        radio: Option<&str>,
+    ) -> Result<i64, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        let now = Utc::now().timestamp();
+        conn.execute(
+            "INSERT INTO packets (timestamp, from_node, to_node, channel, text, direction, via_mqtt, rssi, snr, hop_count, hop_start, mesh_packet_id, packet_type, radio)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+            params![
+                now,
+                from_node as i64,
+                to_node.map(|n| n as i64),
+                channel as i64,
+                text,
+                direction,
+                via_mqtt as i64,
+                rssi,
+                snr,
+                hop_count.map(|h| h as i64),
+                hop_start.map(|h| h as i64),
+                mesh_packet_id.map(|m| m as i64),
+                packet_type,
+                radio,
+            ],
+        )?;
+        let id = conn.last_insert_rowid();
+        if direction == "in" && !via_mqtt {
+            conn.execute(
+                "UPDATE nodes SET rf_heard = 1 WHERE node_id = ?1 AND rf_heard = 0",
+                params![from_node as i64],
+            )?;
+        }
+        Ok(id)
+    }
+
+    #[cfg(test)]
+    pub fn last_packet_radio(&self) -> Option<String> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT radio FROM packets ORDER BY id DESC LIMIT 1",
+            [],
+            |row| row.get(0),
+        )
+        .ok()
+        .flatten()
+    }
+
+    #[cfg(test)]
+    pub fn last_packet_text(&self) -> Option<String> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT text FROM packets ORDER BY id DESC LIMIT 1",
+            [],
+            |row| row.get(0),
+        )
+        .ok()
+    }
+
+    #[cfg(test)]
+    #[allow(clippy::too_many_arguments)]
+    pub fn log_packet(
+        &self,
+        from_node: u32,
+        to_node: Option<u32>,
+        channel: u32,
+        text: &str,
+        direction: &str,
+        via_mqtt: bool,
+        rssi: Option<i32>,
+        snr: Option<f32>,
+        hop_count: Option<u32>,
+        hop_start: Option<u32>,
+        packet_type: PacketType,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.log_packet_inner(
+            from_node,
+            to_node,
+            channel,
+            text,
+            direction,
+            via_mqtt,
+            rssi,
+            snr,
+            hop_count,
+            hop_start,
+            None,
+            packet_type,
+            None,
+        )?;
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn log_packet_with_mesh_id(
+        &self,
+        from_node: u32,
+        to_node: Option<u32>,
+        channel: u32,
+        text: &str,
+        direction: &str,
+        via_mqtt: bool,
+        rssi: Option<i32>,
+        snr: Option<f32>,
+        hop_count: Option<u32>,
+        hop_start: Option<u32>,
+        mesh_packet_id: Option<u32>,
+        packet_type: PacketType,
+    ) -> Result<i64, Box<dyn std::error::Error + Send + Sync>> {
+        self.log_packet_inner(
+            from_node,
+            to_node,
+            channel,
+            text,
+            direction,
+            via_mqtt,
+            rssi,
+            snr,
+            hop_count,
+            hop_start,
+            mesh_packet_id,
+            packet_type,
+            None,
+        )
+    }
+
+    /// Log an incoming packet, tagged with the label of the connection
+    /// (`[[connection]]`) it was received on.
+    #[allow(clippy::too_many_arguments)]
+    pub fn log_received_packet(
+        &self,
+        radio: Option<&str>,
+        from_node: u32,
+        to_node: Option<u32>,
+        channel: u32,
+        text: &str,
+        via_mqtt: bool,
+        rssi: Option<i32>,
+        snr: Option<f32>,
+        hop_count: Option<u32>,
+        hop_start: Option<u32>,
+        mesh_packet_id: Option<u32>,
+        packet_type: PacketType,
+    ) -> Result<i64, Box<dyn std::error::Error + Send + Sync>> {
+        self.log_packet_inner(
+            from_node,
+            to_node,
+            channel,
+            text,
+            "in",
+            via_mqtt,
+            rssi,
+            snr,
+            hop_count,
+            hop_start,
+            mesh_packet_id,
+            packet_type,
+            radio,
+        )
+    }
+
+    // --- Dashboard queries ---
+
+    pub fn public_status(&self) -> Result<PublicStatus, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.read_conn();
+        let midnight = Utc::now()
+            .date_naive()
+            .and_hms_opt(0, 0, 0)
+            .map(|dt| dt.and_utc().timestamp())
+            .unwrap_or_default();
+        let node_count: i64 = conn.query_row("SELECT COUNT(*) FROM nodes", [], |row| row.get(0))?;
+        let (messages_today, last_packet_at): (i64, Option<i64>) = conn.query_row(
+            "SELECT
+                COUNT(CASE WHEN packet_type = 'text' AND timestamp >= ?1 THEN 1 END),
+                MAX(timestamp)
+             FROM packets WHERE direction = 'in'",
+            params![midnight],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        Ok(PublicStatus {
+            node_count: node_count as u64,
+            messages_today: messages_today as u64,
+            last_packet_at,
+        })
+    }
+
+    pub fn dashboard_overview(
+        &self,
+        hours: u32,
+        filter: MqttFilter,
+        bot_name: &str,
+    ) -> Result<DashboardOverview, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.read_conn();
+        let node_count: i64 = conn.query_row("SELECT COUNT(*) FROM nodes", [], |row| row.get(0))?;
+        let since = if hours == 0 {
+            0
+        } else {
+            Utc::now().timestamp() - (hours as i64 * 3600)
+        };
+
+        let mqtt_clause = filter.sql_clause();
+
+        // Text messages only
+        let query_msg_in = format!(
+            "SELECT COUNT(*) FROM packets WHERE direction = 'in' AND packet_type = 'text' AND timestamp > ?1{}",
+            mqtt_clause
+        );
+        let messages_in: i64 = conn.query_row(&query_msg_in, params![since], |row| row.get(0))?;
+
+        let query_msg_out = format!(
+            "SELECT COUNT(*) FROM packets WHERE direction = 'out' AND packet_type = 'text' AND timestamp > ?1{}",
+            mqtt_clause
+        );
+        let messages_out: i64 = conn.query_row(&query_msg_out, params![since], |row| row.get(0))?;
+
+        // All packet types
+        let query_pkt_in = format!(
+            "SELECT COUNT(*) FROM packets WHERE direction = 'in' AND timestamp > ?1{}",
+            mqtt_clause
+        );
+        let packets_in: i64 = conn.query_row(&query_pkt_in, params![since], |row| row.get(0))?;
+
+        let query_pkt_out = format!(
+            "SELECT COUNT(*) FROM packets WHERE direction = 'out' AND timestamp > ?1{}",
+            mqtt_clause
+        );
+        let packets_out: i64 = conn.query_row(&query_pkt_out, params![since], |row| row.get(0))?;
+
+        let mut mqtt_commands = MqttCommandCounts::default();
+        let mut stmt = conn.prepare(
+            "SELECT action, COUNT(*) FROM mqtt_commands WHERE timestamp > ?1 GROUP BY action",
+        )?;
+        let mut rows = stmt.query(params![since])?;
+        while let Some(row) = rows.next()? {
+            let action: String = row.get(0)?;
+            let count = row.get::<_, i64>(1)? as u64;
+            match action.as_str() {
+                "replied" => mqtt_commands.replied = count,
+                "dm" => mqtt_commands.dm = count,
+                "ignored" => mqtt_commands.ignored = count,
+                _ => {}
+            }
+        }
+
+        Ok(DashboardOverview {
+            node_count: node_count as u64,
+            messages_in: messages_in as u64,
+            messages_out: messages_out as u64,
+            packets_in: packets_in as u64,
+            packets_out: packets_out as u64,
+            mqtt_commands,
+            bot_name: bot_name.to_string(),
+        })
+    }
+
+    pub fn dashboard_nodes(
+        &self,
+        hours: u32,
+        filter: MqttFilter,
+    ) -> Result<Vec<DashboardNode>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.read_conn();
+        let since = if hours == 0 {
+            0
+        } else {
+            Utc::now().timestamp() - (hours as i64 * 3600)
+        };
+
+        let where_clause = match filter {
+            MqttFilter::All => String::new(),
+            MqttFilter::LocalOnly => " WHERE n.via_mqtt = 0".to_string(),
+            MqttFilter::MqttOnly => " WHERE n.via_mqtt = 1".to_string(),
+        };
+
+        let query = format!(
+            "WITH rf_last AS (
+                SELECT
+                    from_node,
+                    timestamp,
+                    ROW_NUMBER() OVER (PARTITION BY from_node ORDER BY timestamp DESC, id DESC) AS rn
+                FROM packets
+                WHERE direction = 'in' AND via_mqtt = 0
+             ),
+             rf_hops AS (
+                SELECT
+                    from_node,
+                    hop_count,
+                    ROW_NUMBER() OVER (PARTITION BY from_node ORDER BY timestamp DESC, id DESC) AS rn
+                FROM packets
+                WHERE direction = 'in' AND via_mqtt = 0 AND hop_count IS NOT NULL
+             ),
+             rf_stats AS (
+                SELECT
+                    from_node,
+                    MIN(hop_count) AS min_hop,
+                    AVG(hop_count) AS avg_hop,
+                    SUM(hop_count) AS hop_sum,
+                    COUNT(*) AS hop_samples
+                FROM packets
+                WHERE direction = 'in' AND via_mqtt = 0 AND hop_count IS NOT NULL
+                  AND timestamp > ?1
+                GROUP BY from_node
+             )
+             SELECT
+                n.node_id, n.short_name, n.long_name, n.last_seen, lr.timestamp AS last_rf_seen, n.latitude, n.longitude, n.via_mqtt,
+                lh.hop_count AS last_hop,
+                rs.min_hop,
+                rs.avg_hop,
+                COALESCE(rs.hop_samples, 0) AS hop_samples,
+                (SELECT nn.note FROM node_notes nn WHERE nn.node_id = n.node_id
+                 ORDER BY nn.created_at DESC, nn.id DESC LIMIT 1) AS latest_note,
+                CASE
+                    WHEN rs.min_hop IS NULL THEN ths.min_hop
+                    WHEN ths.min_hop IS NULL THEN rs.min_hop
+                    ELSE MIN(rs.min_hop, ths.min_hop)
+                END AS combined_min_hop,
+                (COALESCE(rs.hop_sum, 0) + COALESCE(ths.hop_sum, 0)) * 1.0
+                    / NULLIF(COALESCE(rs.hop_samples, 0) + COALESCE(ths.samples, 0), 0)
+                    AS combined_avg_hop,
+                COALESCE(rs.hop_samples, 0) + COALESCE(ths.samples, 0) AS combined_hop_samples,
+                n.online
+             FROM nodes n
+             LEFT JOIN rf_last lr ON lr.from_node = n.node_id AND lr.rn = 1
+             LEFT JOIN rf_hops lh ON lh.from_node = n.node_id AND lh.rn = 1
+             LEFT JOIN rf_stats rs ON rs.from_node = n.node_id
+             LEFT JOIN traceroute_hop_stats ths ON ths.node_id = n.node_id
+             {} ORDER BY n.last_seen DESC",
+            where_clause
+        );
+        let mut stmt = conn.prepare(&query)?;
+        let nodes = stmt
+            .query_map(params![since], |row| {
+                let nid: i64 = row.get(0)?;
+                let via_mqtt_val: i64 = row.get(7)?;
+                let last_hop: Option<i64> = row.get(8)?;
+                let min_hop: Option<i64> = row.get(9)?;
+                let avg_hop: Option<f64> = row.get(10)?;
+                let hop_samples: i64 = row.get(11)?;
+                Ok(DashboardNode {
+                    node_id: format!("!{:08x}", nid as u32),
+                    short_name: row.get(1)?,
+                    long_name: row.get(2)?,
+                    last_seen: row.get(3)?,
+                    last_rf_seen: row.get(4)?,
+                    latitude: row.get(5)?,
+                    longitude: row.get(6)?,
+                    via_mqtt: via_mqtt_val != 0,
+                    last_hop: last_hop.map(|h| h as u32),
+                    min_hop: min_hop.map(|h| h as u32),
+                    avg_hop,
+                    hop_samples: hop_samples as u32,
+                    latest_note: row.get(12)?,
+                    combined_min_hop: row.get::<_, Option<i64>>(13)?.map(|h| h as u32),
+                    combined_avg_hop: row.get(14)?,
+                    combined_hop_samples: row.get::<_, i64>(15)? as u32,
+                    online: row.get::<_, i64>(16)? != 0,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(nodes)
+    }
+
+    /// Throughput of text messages only (existing chart).
+    pub fn dashboard_throughput(
+        &self,
+        hours: u32,
+        filter: MqttFilter,
+    ) -> Result<Vec<ThroughputBucket>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.read_conn();
+        let since = if hours == 0 {
+            0
+        } else {
+            Utc::now().timestamp() - (hours as i64 * 3600)
+        };
+
+        let bucket_expr = if hours > 48 {
+            "strftime('%Y-%m-%d', timestamp, 'unixepoch')"
+        } else {
+            "strftime('%Y-%m-%d %H:00', timestamp, 'unixepoch')"
+        };
+
+        let query = format!(
+            "SELECT
+                {bucket} AS bucket,
+                SUM(CASE WHEN direction = 'in' THEN 1 ELSE 0 END) AS incoming,
+                SUM(CASE WHEN direction = 'out' THEN 1 ELSE 0 END) AS outgoing
+             FROM packets
+             WHERE packet_type = 'text' AND timestamp > ?1{mqtt}
+             GROUP BY bucket
+             ORDER BY bucket",
+            bucket = bucket_expr,
+            mqtt = filter.sql_clause()
+        );
+        let mut stmt = conn.prepare(&query)?;
+        let buckets = stmt
+            .query_map(params![since], |row| {
+                Ok(ThroughputBucket {
+                    hour: row.get(0)?,
+                    incoming: row.get::<_, i64>(1)? as u64,
+                    outgoing: row.get::<_, i64>(2)? as u64,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(buckets)
+    }
+
+    /// Throughput of all or filtered packet types.
+    pub fn dashboard_packet_throughput(
+        &self,
+        hours: u32,
+        filter: MqttFilter,
+        packet_types: Option<&[PacketType]>,
+    ) -> Result<Vec<ThroughputBucket>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.read_conn();
+        let since = if hours == 0 {
+            0
+        } else {
+            Utc::now().timestamp() - (hours as i64 * 3600)
+        };
+
+        let bucket_expr = if hours > 48 {
+            "strftime('%Y-%m-%d', timestamp, 'unixepoch')"
+        } else {
+            "strftime('%Y-%m-%d %H:00', timestamp, 'unixepoch')"
+        };
+
+        // An empty filter matches nothing; type names are fixed strings, so
+        // they can go into the query directly
+        let type_clause = match packet_types {
+            Some([]) => return Ok(vec![]),
+            Some(types) => {
+                let names: Vec<String> = types.iter().map(|t| format!("'{}'", t)).collect();
+                format!(" AND packet_type IN ({})", names.join(","))
+            }
+            None => String::new(),
+        };
+
+        let query = format!(
+            "SELECT
+                {bucket} AS bucket,
+                SUM(CASE WHEN direction = 'in' THEN 1 ELSE 0 END) AS incoming,
+                SUM(CASE WHEN direction = 'out' THEN 1 ELSE 0 END) AS outgoing
+             FROM packets
+             WHERE timestamp > ?1{mqtt}{types}
+             GROUP BY bucket
+             ORDER BY bucket",
+            bucket = bucket_expr,
+            mqtt = filter.sql_clause(),
+            types = type_clause,
+        );
+        let mut stmt = conn.prepare(&query)?;
+        let buckets = stmt
+            .query_map(params![since], |row| {
+                Ok(ThroughputBucket {
+                    hour: row.get(0)?,
+                    incoming: row.get::<_, i64>(1)? as u64,
+                    outgoing: row.get::<_, i64>(2)? as u64,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(buckets)
+    }
+
+    pub fn dashboard_rssi(
+        &self,
+        hours: u32,
+        filter: MqttFilter,
+    ) -> Result<Vec<DistributionBucket>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.read_conn();
+        let since = if hours == 0 {
+            0
+        } else {
+            Utc::now().timestamp() - (hours as i64 * 3600)
+        };
+
+        // Bucket RSSI into 10 dBm ranges
+        let query = format!(
+            "SELECT
+                (rssi / 10) * 10 AS bucket,
+                COUNT(*) AS cnt
+             FROM packets
+             WHERE direction = 'in' AND rssi IS NOT NULL AND timestamp > ?1{}
+             GROUP BY bucket
+             ORDER BY bucket",
+            filter.sql_clause()
+        );
+        let mut stmt = conn.prepare(&query)?;
+        let buckets = stmt
+            .query_map(params![since], |row| {
+                let bucket: i32 = row.get(0)?;
+                Ok(DistributionBucket {
+                    label: format!("{} dBm", bucket),
+                    count: row.get::<_, i64>(1)? as u64,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(buckets)
+    }
+
+    pub fn dashboard_snr(
+        &self,
+        hours: u32,
+        filter: MqttFilter,
+    ) -> Result<Vec<DistributionBucket>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.read_conn();
+        let since = if hours == 0 {
+            0
+        } else {
+            Utc::now().timestamp() - (hours as i64 * 3600)
+        };
+
+        // Bucket SNR into 2.5 dB ranges
+        let query = format!(
+            "SELECT
+                CAST(ROUND(snr / 2.5) * 2.5 AS TEXT) AS bucket,
+                COUNT(*) AS cnt
+             FROM packets
+             WHERE direction = 'in' AND snr IS NOT NULL AND timestamp > ?1{}
+             GROUP BY bucket
+             ORDER BY CAST(bucket AS REAL)",
+            filter.sql_clause()
+        );
+        let mut stmt = conn.prepare(&query)?;
+        let buckets = stmt
+            .query_map(params![since], |row| {
+                let bucket: String = row.get(0)?;
+                Ok(DistributionBucket {
+                    label: format!("{} dB", bucket),
+                    count: row.get::<_, i64>(1)? as u64,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(buckets)
+    }
+
+    pub fn dashboard_hops(
+        &self,
+        hours: u32,
+        filter: MqttFilter,
+    ) -> Result<Vec<DistributionBucket>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.read_conn();
+        let since = if hours == 0 {
+            0
+        } else {
+            Utc::now().timestamp() - (hours as i64 * 3600)
+        };
+
+        let query = format!(
+            "SELECT
+                hop_count,
+                COUNT(*) AS cnt
+             FROM packets
+             WHERE direction = 'in' AND hop_count IS NOT NULL AND timestamp > ?1{}
+             GROUP BY hop_count
+             ORDER BY hop_count",
+            filter.sql_clause()
+        );
+        let mut stmt = conn.prepare(&query)?;
+        let buckets = stmt
+            .query_map(params![since], |row| {
+                let hops: i32 = row.get(0)?;
+                Ok(DistributionBucket {
+                    label: format!("{} hop{}", hops, if hops == 1 { "" } else { "s" }),
+                    count: row.get::<_, i64>(1)? as u64,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(buckets)
+    }
+
+    /// Per-source hop counts of inbound RF packets over the last `hours`.
+    /// With `compare`, each node also gets the stats for the window before
+    /// that, so a trend can be shown; nodes heard in either window are listed.
+    pub fn dashboard_hops_to_me(
+        &self,
+        hours: u32,
+        compare: bool,
+    ) -> Result<Vec<HopsToMe>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.read_conn();
+        let compare = compare && hours > 0;
+        let window = hours as i64 * 3600;
+        let since = if hours == 0 {
+            0
+        } else {
+            Utc::now().timestamp() - window
+        };
+        let previous_since = if compare { since - window } else { since };
+
+        let mut stmt = conn.prepare(
+            "SELECT
+                p.from_node,
+                COALESCE(n.short_name, ''),
+                COALESCE(n.long_name, ''),
+                SUM(CASE WHEN p.timestamp > ?1 THEN 1 ELSE 0 END),
+                MIN(CASE WHEN p.timestamp > ?1 THEN p.hop_count END),
+                AVG(CASE WHEN p.timestamp > ?1 THEN p.hop_count END),
+                SUM(CASE WHEN p.timestamp <= ?1 THEN 1 ELSE 0 END),
+                MIN(CASE WHEN p.timestamp <= ?1 THEN p.hop_count END),
+                AVG(CASE WHEN p.timestamp <= ?1 THEN p.hop_count END)
+             FROM packets p
+             LEFT JOIN nodes n ON n.node_id = p.from_node
+             WHERE p.direction = 'in' AND p.via_mqtt = 0 AND p.hop_count IS NOT NULL
+               AND p.timestamp > ?2
+             GROUP BY p.from_node
+             ORDER BY AVG(CASE WHEN p.timestamp > ?1 THEN p.hop_count END) IS NULL,
+                      AVG(CASE WHEN p.timestamp > ?1 THEN p.hop_count END),
+                      p.from_node",
+        )?;
+        let rows = stmt
+            .query_map(params![since, previous_since], |row| {
+                let nid: i64 = row.get(0)?;
+                let current = HopWindow {
+                    samples: row.get::<_, i64>(3)? as u32,
+                    min_hop: row.get::<_, Option<i64>>(4)?.map(|h| h as u32),
+                    avg_hop: row.get(5)?,
+                };
+                let previous = if compare {
+                    Some(HopWindow {
+                        samples: row.get::<_, i64>(6)? as u32,
+                        min_hop: row.get::<_, Option<i64>>(7)?.map(|h| h as u32),
+                        avg_hop: row.get(8)?,
+                    })
+                } else {
+                    None
+                };
+                let avg_hop_change = match (current.avg_hop, previous.as_ref()) {
+                    (
+                        Some(now),
+                        Some(HopWindow {
+                            avg_hop: Some(before),
+                            ..
+                        }),
+                    ) => Some(now - before),
+                    _ => None,
+                };
+                Ok(HopsToMe {
+                    node_id: format!("!{:08x}", nid as u32),
+                    short_name: row.get(1)?,
+                    long_name: row.get(2)?,
+                    current,
+                    previous,
+                    avg_hop_change,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// The `limit` nodes we heard the most packets from over the last
+    /// `hours`, busiest first.
+    pub fn most_active_nodes(
+        &self,
+        hours: u32,
+        limit: usize,
+    ) -> Result<Vec<ActiveNode>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.read_conn();
+        let since = if hours == 0 {
+            0
+        } else {
+            Utc::now().timestamp() - (hours as i64 * 3600)
+        };
+        let mut stmt = conn.prepare(
+            "SELECT p.from_node, COALESCE(n.short_name, ''), COALESCE(n.long_name, ''), COUNT(*)
+             FROM packets p
+             LEFT JOIN nodes n ON n.node_id = p.from_node
+             WHERE p.direction = 'in' AND p.timestamp > ?1
+             GROUP BY p.from_node
+             ORDER BY COUNT(*) DESC, p.from_node
+             LIMIT ?2",
+        )?;
+        let nodes = stmt
+            .query_map(params![since, limit as i64], |row| {
+                Ok(ActiveNode {
+                    node_id: row.get::<_, i64>(0)? as u32,
+                    short_name: row.get(1)?,
+                    long_name: row.get(2)?,
+                    packets: row.get::<_, i64>(3)? as u64,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(nodes)
+    }
+
+    /// Count how often the same mesh packet ID was heard more than once,
+    /// split into RF rebroadcasts and MQTT echoes.
+    pub fn dashboard_dedup_stats(
+        &self,
+        hours: u32,
+    ) -> Result<DedupStats, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.read_conn();
+        let since = if hours == 0 {
+            0
+        } else {
+            Utc::now().timestamp() - (hours as i64 * 3600)
+        };
+
+        let mut stmt = conn.prepare(
+            "WITH heard AS (
+                SELECT
+                    from_node,
+                    COUNT(*) AS copies,
+                    SUM(CASE WHEN via_mqtt = 0 THEN 1 ELSE 0 END) AS rf_copies
+                FROM packets
+                WHERE direction = 'in' AND mesh_packet_id IS NOT NULL AND mesh_packet_id != 0
+                  AND timestamp > ?1
+                GROUP BY from_node, mesh_packet_id
+             )
+             SELECT
+                h.from_node,
+                COALESCE(n.short_name, ''),
+                COALESCE(n.long_name, ''),
+                COUNT(*) AS unique_packets,
+                SUM(h.copies) AS copies,
+                SUM(MAX(h.rf_copies - 1, 0)) AS rf_duplicates
+             FROM heard h
+             LEFT JOIN nodes n ON n.node_id = h.from_node
+             GROUP BY h.from_node
+             ORDER BY SUM(h.copies) * 1.0 / COUNT(*) DESC, h.from_node",
+        )?;
+        let nodes = stmt
+            .query_map(params![since], |row| {
+                let nid: i64 = row.get(0)?;
+                Ok(NodeDedup {
+                    node_id: format!("!{:08x}", nid as u32),
+                    short_name: row.get(1)?,
+                    long_name: row.get(2)?,
+                    counts: DedupCounts::new(
+                        row.get::<_, i64>(3)? as u64,
+                        row.get::<_, i64>(4)? as u64,
+                        row.get::<_, i64>(5)? as u64,
+                    ),
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut overall = DedupCounts::default();
+        for node in &nodes {
+            overall.add(&node.counts);
+        }
+        Ok(DedupStats { overall, nodes })
+    }
+
+    pub fn dashboard_positions(
+        &self,
+    ) -> Result<Vec<DashboardNode>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.read_conn();
+        let mut stmt = conn.prepare(
+            "WITH rf_last AS (
+                SELECT
+                    from_node,
+                    timestamp,
+                    ROW_NUMBER() OVER (PARTITION BY from_node ORDER BY timestamp DESC, id DESC) AS rn
+                FROM packets
+                WHERE direction = 'in' AND via_mqtt = 0
+             ),
+             rf_hops AS (
+                SELECT
+                    from_node,
+                    hop_count,
+                    ROW_NUMBER() OVER (PARTITION BY from_node ORDER BY timestamp DESC, id DESC) AS rn
+                FROM packets
+                WHERE direction = 'in' AND via_mqtt = 0 AND hop_count IS NOT NULL
+             ),
+             rf_stats AS (
+                SELECT
+                    from_node,
+                    MIN(hop_count) AS min_hop,
+                    AVG(hop_count) AS avg_hop,
+                    SUM(hop_count) AS hop_sum,
+                    COUNT(*) AS hop_samples
+                FROM packets
+                WHERE direction = 'in' AND via_mqtt = 0 AND hop_count IS NOT NULL
+                GROUP BY from_node
+             )
+             SELECT
+                n.node_id, n.short_name, n.long_name, n.last_seen, lr.timestamp AS last_rf_seen, n.latitude, n.longitude, n.via_mqtt,
+                lh.hop_count AS last_hop,
+                rs.min_hop,
+                rs.avg_hop,
+                COALESCE(rs.hop_samples, 0) AS hop_samples,
+                (SELECT nn.note FROM node_notes nn WHERE nn.node_id = n.node_id
+                 ORDER BY nn.created_at DESC, nn.id DESC LIMIT 1) AS latest_note,
+                CASE
+                    WHEN rs.min_hop IS NULL THEN ths.min_hop
+                    WHEN ths.min_hop IS NULL THEN rs.min_hop
+                    ELSE MIN(rs.min_hop, ths.min_hop)
+                END AS combined_min_hop,
+                (COALESCE(rs.hop_sum, 0) + COALESCE(ths.hop_sum, 0)) * 1.0
+                    / NULLIF(COALESCE(rs.hop_samples, 0) + COALESCE(ths.samples, 0), 0)
+                    AS combined_avg_hop,
+                COALESCE(rs.hop_samples, 0) + COALESCE(ths.samples, 0) AS combined_hop_samples,
+                n.online
+             FROM nodes n
+             LEFT JOIN rf_last lr ON lr.from_node = n.node_id AND lr.rn = 1
+             LEFT JOIN rf_hops lh ON lh.from_node = n.node_id AND lh.rn = 1
+             LEFT JOIN rf_stats rs ON rs.from_node = n.node_id
+             LEFT JOIN traceroute_hop_stats ths ON ths.node_id = n.node_id
+             WHERE n.latitude IS NOT NULL AND n.longitude IS NOT NULL
+               AND (n.latitude != 0.0 OR n.longitude != 0.0)
+             ORDER BY n.last_seen DESC",
+        )?;
+        let nodes = stmt
+            .query_map([], |row| {
+                let nid: i64 = row.get(0)?;
+                let via_mqtt_val: i64 = row.get(7)?;
+                let last_hop: Option<i64> = row.get(8)?;
+                let min_hop: Option<i64> = row.get(9)?;
+                let avg_hop: Option<f64> = row.get(10)?;
+                let hop_samples: i64 = row.get(11)?;
+                Ok(DashboardNode {
+                    node_id: format!("!{:08x}", nid as u32),
+                    short_name: row.get(1)?,
+                    long_name: row.get(2)?,
+                    last_seen: row.get(3)?,
+                    last_rf_seen: row.get(4)?,
+                    latitude: row.get(5)?,
+                    longitude: row.get(6)?,
+                    via_mqtt: via_mqtt_val != 0,
+                    last_hop: last_hop.map(|h| h as u32),
+                    min_hop: min_hop.map(|h| h as u32),
+                    avg_hop,
+                    hop_samples: hop_samples as u32,
+                    latest_note: row.get(12)?,
+                    combined_min_hop: row.get::<_, Option<i64>>(13)?.map(|h| h as u32),
+                    combined_avg_hop: row.get(14)?,
+                    combined_hop_samples: row.get::<_, i64>(15)? as u32,
+                    online: row.get::<_, i64>(16)? != 0,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(nodes)
+    }
+
+    pub fn dashboard_traceroute_requesters(
+        &self,
+        target_node: u32,
+        hours: u32,
+        filter: MqttFilter,
+    ) -> Result<Vec<TracerouteRequester>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.read_conn();
+        let since = if hours == 0 {
+            0
+        } else {
+            Utc::now().timestamp() - (hours as i64 * 3600)
+        };
+
+        let mqtt_clause = match filter {
+            MqttFilter::All => "",
+            MqttFilter::LocalOnly => " AND p.via_mqtt = 0",
+            MqttFilter::MqttOnly => " AND p.via_mqtt = 1",
+        };
+
+        let query = format!(
+            "SELECT
+                p.from_node,
+                COALESCE(n.short_name, '') AS short_name,
+                COALESCE(n.long_name, '') AS long_name,
+                COUNT(*) AS request_count,
+                MAX(p.timestamp) AS last_request,
+                MAX(p.via_mqtt) AS via_mqtt
+             FROM packets p
+             LEFT JOIN nodes n ON n.node_id = p.from_node
+             WHERE p.direction = 'in'
+               AND p.packet_type = 'traceroute'
+               AND p.to_node = ?1
+               AND p.timestamp > ?2
+               {mqtt_clause}
+             GROUP BY p.from_node, n.short_name, n.long_name
+             ORDER BY last_request DESC"
+        );
+
+        let rows = conn
+            .prepare(&query)?
+            .query_map(params![target_node as i64, since], |row| {
+                let node_id_i64: i64 = row.get(0)?;
+                let short_name: String = row.get(1)?;
+                let long_name: String = row.get(2)?;
+                let request_count: i64 = row.get(3)?;
+                let last_request: i64 = row.get(4)?;
+                let via_mqtt: i64 = row.get(5)?;
+                Ok(TracerouteRequester {
+                    node_id: format!("!{:08x}", node_id_i64 as u32),
+                    short_name,
+                    long_name,
+                    request_count: request_count as u64,
+                    last_request,
+                    via_mqtt: via_mqtt != 0,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(rows)
+    }
+
+    /// Text messages from the last `hours` (0 = all), newest first, older
+    /// than row `before` when given. `channel` and `node` (sender or
+    /// recipient) narrow them down.
+    pub fn dashboard_messages(
+        &self,
+        hours: u32,
+        channel: Option<u32>,
+        node: Option<u32>,
+        before: Option<i64>,
+        limit: usize,
+    ) -> Result<Vec<DashboardMessage>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.read_conn();
+        let since = if hours == 0 {
+            0
+        } else {
+            Utc::now().timestamp() - (hours as i64 * 3600)
+        };
+        let mut stmt = conn.prepare(
+            "SELECT
+                p.id,
+                p.timestamp,
+                p.direction,
+                p.from_node,
+                COALESCE(n.short_name, ''),
+                COALESCE(n.long_name, ''),
+                p.to_node,
+                p.channel,
+                p.text,
+                p.via_mqtt,
+                p.ack_status
+             FROM packets p
+             LEFT JOIN nodes n ON n.node_id = p.from_node
+             WHERE p.packet_type = 'text'
+               AND p.timestamp > ?1
+               AND (?2 IS NULL OR p.channel = ?2)
+               AND (?3 IS NULL OR p.from_node = ?3 OR p.to_node = ?3)
+               AND (?4 IS NULL OR p.id < ?4)
+             ORDER BY p.id DESC
+             LIMIT ?5",
+        )?;
+        let rows = stmt
+            .query_map(
+                params![
+                    since,
+                    channel.map(|c| c as i64),
+                    node.map(|n| n as i64),
+                    before,
+                    limit as i64
+                ],
+                |row| {
+                    let from_node: i64 = row.get(3)?;
+                    let to_node: Option<i64> = row.get(6)?;
+                    let via_mqtt: i64 = row.get(9)?;
+                    Ok(DashboardMessage {
+                        id: row.get(0)?,
+                        timestamp: row.get(1)?,
+                        direction: row.get(2)?,
+                        from_node: format!("!{:08x}", from_node as u32),
+                        from_short_name: row.get(4)?,
+                        from_long_name: row.get(5)?,
+                        to_node: to_node.map(|n| format!("!{:08x}", n as u32)),
+                        channel: row.get(7)?,
+                        text: row.get(8)?,
+                        via_mqtt: via_mqtt != 0,
+                        ack_status: row.get(10)?,
+                    })
+                },
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Packets matching `query`, newest first, `query.limit` at a time from
+    /// `query.offset`. Every filter value is bound as a parameter.
+    pub fn query_packets(
+        &self,
+        query: &PacketQuery,
+    ) -> Result<Vec<PacketRecord>, Box<dyn std::error::Error + Send + Sync>> {
+        use rusqlite::types::Value;
+
+        // Each condition refers to the value pushed just before it
+        let mut conditions: Vec<String> = Vec::new();
+        let mut values: Vec<Value> = Vec::new();
+        if let Some(from) = query.from_node {
+            values.push(Value::Integer(from as i64));
+            conditions.push(format!("p.from_node = ?{}", values.len()));
+        }
+        if let Some(to) = query.to_node {
+            values.push(Value::Integer(to as i64));
+            conditions.push(format!("p.to_node = ?{}", values.len()));
+        }
+        if let Some(direction) = &query.direction {
+            values.push(Value::Text(direction.clone()));
+            conditions.push(format!("p.direction = ?{}", values.len()));
+        }
+        if let Some(since) = query.since {
+            values.push(Value::Integer(since));
+            conditions.push(format!("p.timestamp > ?{}", values.len()));
+        }
+        match &query.packet_types {
+            Some(types) if types.is_empty() => return Ok(vec![]),
+            Some(types) => {
+                let placeholders: Vec<String> = types
+                    .iter()
+                    .map(|t| {
+                        values.push(Value::Text(t.as_str().to_string()));
+                        format!("?{}", values.len())
+                    })
+                    .collect();
+                conditions.push(format!("p.packet_type IN ({})", placeholders.join(",")));
+            }
+            None => {}
+        }
+        let mut where_clause = if conditions.is_empty() {
+            "1 = 1".to_string()
+        } else {
+            conditions.join(" AND ")
+        };
+        where_clause.push_str(&query.mqtt.sql_clause().replace("via_mqtt", "p.via_mqtt"));
+        values.push(Value::Integer(query.limit as i64));
+        values.push(Value::Integer(query.offset as i64));
+        let sql = format!(
+            "SELECT
+                p.id,
+                p.timestamp,
+                p.direction,
+                p.packet_type,
+                p.from_node,
+                COALESCE(n.short_name, ''),
+                p.to_node,
+                p.channel,
+                p.via_mqtt,
+                p.radio,
+                p.rssi,
+                p.snr,
+                p.hop_count,
+                p.hop_start,
+                p.mesh_packet_id,
+                p.text,
+                p.ack_status
+             FROM packets p
+             LEFT JOIN nodes n ON n.node_id = p.from_node
+             WHERE {}
+             ORDER BY p.id DESC
+             LIMIT ?{} OFFSET ?{}",
+            where_clause,
+            values.len() - 1,
+            values.len()
+        );
+
+        let conn = self.read_conn();
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt
+            .query_map(rusqlite::params_from_iter(values.iter()), |row| {
+                let from_node: i64 = row.get(4)?;
+                let to_node: Option<i64> = row.get(6)?;
+                let via_mqtt: i64 = row.get(8)?;
+                let mesh_packet_id: Option<i64> = row.get(14)?;
+                Ok(PacketRecord {
+                    id: row.get(0)?,
+                    timestamp: row.get(1)?,
+                    direction: row.get(2)?,
+                    packet_type: row.get(3)?,
+                    from_node: format!("!{:08x}", from_node as u32),
+                    from_short_name: row.get(5)?,
+                    to_node: to_node.map(|n| format!("!{:08x}", n as u32)),
+                    channel: row.get(7)?,
+                    via_mqtt: via_mqtt != 0,
+                    radio: row.get(9)?,
+                    rssi: row.get(10)?,
+                    snr: row.get(11)?,
+                    hop_count: row.get(12)?,
+                    hop_start: row.get(13)?,
+                    mesh_packet_id: mesh_packet_id.map(|id| id as u32),
+                    text: row.get(15)?,
+                    ack_status: row.get(16)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    pub fn dashboard_traceroute_events(
+        &self,
+        hours: u32,
+        filter: MqttFilter,
+        limit: u32,
+    ) -> Result<Vec<TracerouteEvent>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.read_conn();
+        let since = if hours == 0 {
+            0
+        } else {
+            Utc::now().timestamp() - (hours as i64 * 3600)
+        };
+        let mqtt_clause = match filter {
+            MqttFilter::All => "",
+            MqttFilter::LocalOnly => " AND p.via_mqtt = 0",
+            MqttFilter::MqttOnly => " AND p.via_mqtt = 1",
+        };
+
+        let query = format!(
+            "SELECT
+                p.timestamp,
+                p.from_node,
+                COALESCE(nf.short_name, '') AS from_short_name,
+                COALESCE(nf.long_name, '') AS from_long_name,
+                p.to_node,
+                COALESCE(nt.short_name, '') AS to_short_name,
+                COALESCE(nt.long_name, '') AS to_long_name,
+                p.via_mqtt,
+                p.hop_count,
+                p.hop_start,
+                p.rssi,
+                p.snr
+             FROM packets p
+             LEFT JOIN nodes nf ON nf.node_id = p.from_node
+             LEFT JOIN nodes nt ON nt.node_id = p.to_node
+             WHERE p.direction = 'in'
+               AND p.packet_type = 'traceroute'
+               AND p.timestamp > ?1
+               {mqtt_clause}
+             ORDER BY p.timestamp DESC, p.id DESC
+             LIMIT ?2"
+        );
+
+        let rows = conn
+            .prepare(&query)?
+            .query_map(params![since, limit as i64], |row| {
+                let from_node_i64: i64 = row.get(1)?;
+                let to_node_i64: Option<i64> = row.get(4)?;
+                let via_mqtt_i64: i64 = row.get(7)?;
+                let hop_count_i64: Option<i64> = row.get(8)?;
+                let hop_start_i64: Option<i64> = row.get(9)?;
+                Ok(TracerouteEvent {
+                    timestamp: row.get(0)?,
+                    from_node: format!("!{:08x}", from_node_i64 as u32),
+                    from_short_name: row.get(2)?,
+                    from_long_name: row.get(3)?,
+                    to_node: to_node_i64
+                        .map(|n| format!("!{:08x}", n as u32))
+                        .unwrap_or_else(|| "broadcast".to_string()),
+                    to_short_name: row.get(5)?,
+                    to_long_name: row.get(6)?,
+                    via_mqtt: via_mqtt_i64 != 0,
+                    hop_count: hop_count_i64.map(|h| h as u32),
+                    hop_start: hop_start_i64.map(|h| h as u32),
+                    rssi: row.get(10)?,
+                    snr: row.get(11)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    pub fn dashboard_traceroute_destinations(
+        &self,
+        hours: u32,
+        filter: MqttFilter,
+    ) -> Result<Vec<TracerouteDestinationSummary>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.read_conn();
+        let since = if hours == 0 {
+            0
+        } else {
+            Utc::now().timestamp() - (hours as i64 * 3600)
+        };
+        let mqtt_clause = match filter {
+            MqttFilter::All => "",
+            MqttFilter::LocalOnly => " AND p.via_mqtt = 0",
+            MqttFilter::MqttOnly => " AND p.via_mqtt = 1",
+        };
+
+        let query = format!(
+            "SELECT
+                p.to_node,
+                COALESCE(nt.short_name, '') AS to_short_name,
+                COALESCE(nt.long_name, '') AS to_long_name,
+                COUNT(*) AS requests,
+                COUNT(DISTINCT p.from_node) AS unique_requesters,
+                MAX(p.timestamp) AS last_seen,
+                SUM(CASE WHEN p.via_mqtt = 0 THEN 1 ELSE 0 END) AS rf_count,
+                SUM(CASE WHEN p.via_mqtt = 1 THEN 1 ELSE 0 END) AS mqtt_count,
+                AVG(p.hop_count) AS avg_hops
+             FROM packets p
+             LEFT JOIN nodes nt ON nt.node_id = p.to_node
+             WHERE p.direction = 'in'
+               AND p.packet_type = 'traceroute'
+               AND p.timestamp > ?1
+               {mqtt_clause}
+             GROUP BY p.to_node, nt.short_name, nt.long_name
+             ORDER BY last_seen DESC"
+        );
+
+        let rows = conn
+            .prepare(&query)?
+            .query_map(params![since], |row| {
+                let to_node_i64: Option<i64> = row.get(0)?;
+                let requests: i64 = row.get(3)?;
+                let unique_requesters: i64 = row.get(4)?;
+                let rf_count: i64 = row.get(6)?;
+                let mqtt_count: i64 = row.get(7)?;
+                Ok(TracerouteDestinationSummary {
+                    destination_node: to_node_i64
+                        .map(|n| format!("!{:08x}", n as u32))
+                        .unwrap_or_else(|| "broadcast".to_string()),
+                    destination_short_name: row.get(1)?,
+                    destination_long_name: row.get(2)?,
+                    requests: requests as u64,
+                    unique_requesters: unique_requesters as u64,
+                    last_seen: row.get(5)?,
+                    rf_count: rf_count as u64,
+                    mqtt_count: mqtt_count as u64,
+                    avg_hops: row.get(8)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(rows)
+    }
+
+    fn traceroute_status(
+        trace_key: &str,
+        request_hops: Option<u32>,
+        response_hops: Option<u32>,
+        request_route_len: usize,
+        response_route_len: usize,
+    ) -> &'static str {
+        let req_present = request_hops.is_some() || request_route_len > 0;
+        let res_present = response_hops.is_some() || response_route_len > 0;
+        // Only req: sessions (probes sent by us) may reach complete — we initiated
+        // the exchange and received the reply, so we have full end-to-end knowledge.
+        // in: sessions are passive observations; even with both sides seen we cap at
+        // partial because we cannot confirm the full path or that the exchange completed.
+        let can_be_complete = trace_key.starts_with("req:");
+        if req_present && res_present && can_be_complete {
+            "complete"
+        } else if req_present || res_present {
+            "partial"
+        } else {
+            "observed"
+        }
+    }
+
+    /// Merge the transport a traceroute side was just heard on into the
+    /// previously recorded path (`rf`, `mqtt` or `both`).
+    fn merge_traceroute_path(prev: Option<&str>, via_mqtt: bool) -> &'static str {
+        let current = if via_mqtt { "mqtt" } else { "rf" };
+        match prev {
+            None => current,
+            Some(p) if p == current => current,
+            Some(_) => "both",
+        }
+    }
+
+    /// Record a traceroute packet against its session, creating the session
+    /// the first time `trace_key` is seen. Returns the session's ID.
+    #[allow(clippy::too_many_arguments)]
+    pub fn log_traceroute_observation(
+        &self,
+        packet_row_id: i64,
+        trace_key: &str,
+        src_node: u32,
+        dst_node: Option<u32>,
+        via_mqtt: bool,
+        request_hops: Option<u32>,
+        request_hop_start: Option<u32>,
+        response_hops: Option<u32>,
+        response_hop_start: Option<u32>,
+        request_route: &[u32],
+        response_route: &[u32],
+    ) -> Result<i64, Box<dyn std::error::Error + Send + Sync>> {
+        let now = Utc::now().timestamp();
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        let session_id = {
+            let mut find_stmt = tx.prepare(
+                "SELECT id, first_seen, request_hops, request_hop_start, response_hops, response_hop_start, sample_count,
+                        request_path, response_path
+                 FROM traceroute_sessions
+                 WHERE trace_key = ?1
+                 LIMIT 1",
+            )?;
+            let existing = find_stmt.query_row(params![trace_key], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, Option<i64>>(2)?,
+                    row.get::<_, Option<i64>>(3)?,
+                    row.get::<_, Option<i64>>(4)?,
+                    row.get::<_, Option<i64>>(5)?,
+                    row.get::<_, i64>(6)?,
+                    row.get::<_, Option<String>>(7)?,
+                    row.get::<_, Option<String>>(8)?,
+                ))
+            });
+
+            let request_seen = request_hops.is_some() || !request_route.is_empty();
+            let response_seen = response_hops.is_some() || !response_route.is_empty();
+
+            match existing {
+                Ok((
+                    id,
+                    first_seen,
+                    req_hops_prev,
+                    req_start_prev,
+                    res_hops_prev,
+                    res_start_prev,
+                    sample_count,
+                    req_path_prev,
+                    res_path_prev,
+                )) => {
+                    // Hop counts heard over MQTT describe the path to some other
+                    // gateway, so never let them replace hop info we heard on RF.
+                    let keep_prev = |path: &Option<String>, hops: Option<i64>| {
+                        via_mqtt
+                            && hops.is_some()
+                            && matches!(path.as_deref(), Some("rf") | Some("both"))
+                    };
+                    let (merged_req_hops, merged_req_start) =
+                        if keep_prev(&req_path_prev, req_hops_prev) {
+                            (
+                                req_hops_prev.map(|v| v as u32),
+                                req_start_prev.map(|v| v as u32),
+                            )
+                        } else {
+                            (
+                                request_hops.or(req_hops_prev.map(|v| v as u32)),
+                                request_hop_start.or(req_start_prev.map(|v| v as u32)),
+                            )
+                        };
+                    let (merged_res_hops, merged_res_start) =
+                        if keep_prev(&res_path_prev, res_hops_prev) {
+                            (
+                                res_hops_prev.map(|v| v as u32),
+                                res_start_prev.map(|v| v as u32),
+                            )
+                        } else {
+                            (
+                                response_hops.or(res_hops_prev.map(|v| v as u32)),
+                                response_hop_start.or(res_start_prev.map(|v| v as u32)),
+                            )
+                        };
+                    let merged_req_path = if request_seen {
+                        Some(Self::merge_traceroute_path(
+                            req_path_prev.as_deref(),
+                            via_mqtt,
+                        ))
+                    } else {
+                        req_path_prev.as_deref()
+                    };
+                    let merged_res_path = if response_seen {
+                        Some(Self::merge_traceroute_path(
+                            res_path_prev.as_deref(),
+                            via_mqtt,
+                        ))
+                    } else {
+                        res_path_prev.as_deref()
+                    };
+                    let status = Self::traceroute_status(
+                        trace_key,
+                        merged_req_hops,
+                        merged_res_hops,
+                        request_route.len(),
+                        response_route.len(),
+                    );
+                    tx.execute(
+                        "UPDATE traceroute_sessions
+                         SET first_seen = ?2,
+                             last_seen = ?3,
+                             src_node = ?4,
+                             dst_node = ?5,
+                             via_mqtt = MIN(via_mqtt, ?6),
+                             request_hops = ?7,
+                             request_hop_start = ?8,
+                             response_hops = ?9,
+                             response_hop_start = ?10,
+                             request_packet_id = CASE WHEN ?7 IS NOT NULL THEN COALESCE(request_packet_id, ?11) ELSE request_packet_id END,
+                             response_packet_id = CASE WHEN ?9 IS NOT NULL THEN COALESCE(response_packet_id, ?11) ELSE response_packet_id END,
+                             status = ?12,
+                             sample_count = ?13,
+                             request_path = ?14,
+                             response_path = ?15
+                         WHERE id = ?1",
+                        params![
+                            id,
+                            std::cmp::min(first_seen, now),
+                            now,
+                            src_node as i64,
+                            dst_node.map(|n| n as i64),
+                            via_mqtt as i64,
+                            merged_req_hops.map(|v| v as i64),
+                            merged_req_start.map(|v| v as i64),
+                            merged_res_hops.map(|v| v as i64),
+                            merged_res_start.map(|v| v as i64),
+                            packet_row_id,
+                            status,
+                            sample_count + 1,
+                            merged_req_path,
+                            merged_res_path,
+                        ],
+                    )?;
+                    id
+                }
+                Err(rusqlite::Error::QueryReturnedNoRows) => {
+                    let status = Self::traceroute_status(
+                        trace_key,
+                        request_hops,
+                        response_hops,
+                        request_route.len(),
+                        response_route.len(),
+                    );
+                    tx.execute(
+                        "INSERT INTO traceroute_sessions
+                         (trace_key, first_seen, last_seen, src_node, dst_node, via_mqtt, request_hops, request_hop_start, response_hops, response_hop_start, request_packet_id, response_packet_id, status, sample_count, request_path, response_path)
+                         VALUES (?1, ?2, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, 1, ?13, ?14)",
+                        params![
+                            trace_key,
+                            now,
+                            src_node as i64,
+                            dst_node.map(|n| n as i64),
+                            via_mqtt as i64,
+                            request_hops.map(|v| v as i64),
+                            request_hop_start.map(|v| v as i64),
+                            response_hops.map(|v| v as i64),
+                            response_hop_start.map(|v| v as i64),
+                            if request_hops.is_some() {
+                                Some(packet_row_id)
+                            } else {
+                                None
+                            },
+                            if response_hops.is_some() {
+                                Some(packet_row_id)
+                            } else {
+                                None
+                            },
+                            status,
+                            request_seen
+                                .then(|| Self::merge_traceroute_path(None, via_mqtt)),
+                            response_seen
+                                .then(|| Self::merge_traceroute_path(None, via_mqtt)),
+                        ],
+                    )?;
+                    tx.last_insert_rowid()
+                }
+                Err(e) => return Err(e.into()),
+            }
+        };
+
+        // The same packet can be heard on RF and from one or more MQTT gateways;
+        // record each direction's route only once per session.
+        let has_hops = |direction: &str| -> rusqlite::Result<bool> {
+            tx.query_row(
+                "SELECT EXISTS(SELECT 1 FROM traceroute_session_hops WHERE session_id = ?1 AND direction = ?2)",
+                params![session_id, direction],
+                |row| row.get(0),
+            )
+        };
+        let request_route: &[u32] = if has_hops("request")? {
+            &[]
+        } else {
+            request_route
+        };
+        let response_route: &[u32] = if has_hops("response")? {
+            &[]
+        } else {
+            response_route
+        };
+
+        for (idx, node) in request_route.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO traceroute_session_hops (session_id, direction, hop_index, node_id, observed_at, packet_id_ref, source_kind)
+                 VALUES (?1, 'request', ?2, ?3, ?4, ?5, 'route')",
+                params![session_id, idx as i64, *node as i64, now, packet_row_id],
+            )?;
+        }
+        for (idx, node) in response_route.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO traceroute_session_hops (session_id, direction, hop_index, node_id, observed_at, packet_id_ref, source_kind)
+                 VALUES (?1, 'response', ?2, ?3, ?4, ?5, 'route_back')",
+                params![session_id, idx as i64, *node as i64, now, packet_row_id],
+            )?;
+        }
+
+        tx.commit()?;
+        Ok(session_id)
+    }
+
+    /// Check whether a traceroute session with the given trace_key was first seen
+    /// at or after `since_ts`. Used to correlate incoming RouteReply packets with
+    /// outgoing probe sessions within a bounded time window.
+    pub fn traceroute_session_exists_since(
+        &self,
+        trace_key: &str,
+        since_ts: i64,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        let result: Result<i64, _> = conn.query_row(
+            "SELECT 1 FROM traceroute_sessions
+             WHERE trace_key = ?1 AND first_seen >= ?2
+             LIMIT 1",
+            params![trace_key, since_ts],
+            |row| row.get(0),
+        );
+        match result {
+            Ok(_) => Ok(true),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Mark traceroute sessions that never completed as `expired` once they have
+    /// gone `max_age_secs` without a new observation. Returns the number of
+    /// sessions transitioned.
+    pub fn expire_stale_traceroute_sessions(
+        &self,
+        max_age_secs: u64,
+    ) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        let max_age_secs = i64::try_from(max_age_secs)
+            .map_err(|_| "max_age_secs too large for timestamp arithmetic")?;
+        let cutoff = Utc::now().timestamp() - max_age_secs;
+        let conn = self.conn.lock().unwrap();
+        let updated = conn.execute(
+            "UPDATE traceroute_sessions
+             SET status = 'expired'
+             WHERE status IN ('observed', 'partial') AND last_seen < ?1",
+            params![cutoff],
+        )?;
+        Ok(updated)
+    }
+
+    /// Recompute `traceroute_hop_stats`: how many hops each relay listed in a
+    /// traceroute route is from the local node. Only traces that start or end
+    /// at the local node (found as the source of our own `req:` probes) are
+    /// used; endpoints are skipped since their hops are already in `packets`.
+    /// Returns the number of nodes with statistics.
+    pub fn backfill_traceroute_hop_stats(
+        &self,
+    ) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        let now = Utc::now().timestamp();
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM traceroute_hop_stats", [])?;
+        let nodes = tx.execute(
+            "WITH locals AS (
+                SELECT DISTINCT src_node AS node_id FROM traceroute_sessions
+                WHERE trace_key LIKE 'req:%'
+             ),
+             route_len AS (
+                SELECT session_id, direction, COUNT(*) AS len
+                FROM traceroute_session_hops
+                GROUP BY session_id, direction
+             ),
+             samples AS (
+                -- Our probes: the route runs outward from us, route_back runs back to us
+                SELECT h.node_id,
+                       CASE h.direction
+                           WHEN 'request' THEN h.hop_index + 1
+                           ELSE rl.len - h.hop_index
+                       END AS hops
+                FROM traceroute_session_hops h
+                JOIN traceroute_sessions s ON s.id = h.session_id
+                JOIN route_len rl ON rl.session_id = h.session_id AND rl.direction = h.direction
+                WHERE s.src_node IN (SELECT node_id FROM locals)
+                UNION ALL
+                -- Traces towards us: the route arrives at us
+                SELECT h.node_id, rl.len - h.hop_index AS hops
+                FROM traceroute_session_hops h
+                JOIN traceroute_sessions s ON s.id = h.session_id
+                JOIN route_len rl ON rl.session_id = h.session_id AND rl.direction = h.direction
+                WHERE h.direction = 'request'
+                  AND s.dst_node IN (SELECT node_id FROM locals)
+                  AND s.src_node NOT IN (SELECT node_id FROM locals)
+             )
+             INSERT INTO traceroute_hop_stats (node_id, min_hop, hop_sum, samples, updated_at)
+             SELECT node_id, MIN(hops), SUM(hops), COUNT(*), ?1
+             FROM samples
+             WHERE node_id NOT IN (SELECT node_id FROM locals)
+               AND node_id != 4294967295
+             GROUP BY node_id",
+            params![now],
+        )?;
+        tx.commit()?;
+        Ok(nodes)
+    }
+
+    /// Delete traceroute hop rows observed more than `max_age_secs` ago.
+    /// Session rows are kept so historical counts remain available.
+    pub fn prune_traceroute_hops_older_than(
+        &self,
+        max_age_secs: u64,
+    ) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        let max_age_secs = i64::try_from(max_age_secs)
+            .map_err(|_| "max_age_secs too large for timestamp arithmetic")?;
+        let cutoff = Utc::now().timestamp() - max_age_secs;
+        let conn = self.conn.lock().unwrap();
+        let deleted = conn.execute(
+            "DELETE FROM traceroute_session_hops WHERE observed_at < ?1",
+            params![cutoff],
+        )?;
+        Ok(deleted)
+    }
+
+    pub fn dashboard_traceroute_sessions(
+        &self,
+        hours: u32,
+        limit: usize,
+        include_expired: bool,
+    ) -> Result<Vec<TracerouteSession>, Box<dyn std::error::Error + Send + Sync>> {
+        use std::collections::HashMap;
+
+        let conn = self.read_conn();
+        let since = if hours == 0 {
+            0i64
+        } else {
+            Utc::now().timestamp() - (hours as i64 * 3600)
+        };
+
+        let expired_clause = if include_expired {
+            ""
+        } else {
+            " AND s.status != 'expired'"
+        };
+
+        // Query sessions with resolved node names.
+        let sessions_sql = format!(
+            "
+            SELECT
+                s.id,
+                s.trace_key,
+                s.first_seen,
+                s.last_seen,
+                s.src_node,
+                COALESCE(ns.short_name, NULL) AS src_short_name,
+                COALESCE(ns.long_name, NULL) AS src_long_name,
+                s.dst_node,
+                COALESCE(nd.short_name, NULL) AS dst_short_name,
+                COALESCE(nd.long_name, NULL) AS dst_long_name,
+                s.via_mqtt,
+                s.request_hops,
+                s.request_hop_start,
+                s.response_hops,
+                s.response_hop_start,
+                s.status,
+                s.sample_count,
+                s.request_path,
+                s.response_path
+            FROM traceroute_sessions s
+            LEFT JOIN nodes ns ON ns.node_id = s.src_node
+            LEFT JOIN nodes nd ON nd.node_id = s.dst_node
+            WHERE s.last_seen >= ?1{expired_clause}
+            ORDER BY s.last_seen DESC, s.id DESC
+            LIMIT ?2"
+        );
+
+        struct SessionRow {
+            id: i64,
+            trace_key: String,
+            first_seen: i64,
+            last_seen: i64,
+            src_node: i64,
+            src_short_name: Option<String>,
+            src_long_name: Option<String>,
+            dst_node: Option<i64>,
+            dst_short_name: Option<String>,
+            dst_long_name: Option<String>,
+            via_mqtt: i64,
+            request_hops: Option<i64>,
+            request_hop_start: Option<i64>,
+            response_hops: Option<i64>,
+            response_hop_start: Option<i64>,
+            status: String,
+            sample_count: i64,
+            request_path: Option<String>,
+            response_path: Option<String>,
+        }
+
+        let rows: Vec<SessionRow> = conn
+            .prepare(&sessions_sql)?
+            .query_map(params![since, limit as i64], |row| {
+                Ok(SessionRow {
+                    id: row.get(0)?,
+                    trace_key: row.get(1)?,
+                    first_seen: row.get(2)?,
+                    last_seen: row.get(3)?,
+                    src_node: row.get(4)?,
+                    src_short_name: row.get(5)?,
+                    src_long_name: row.get(6)?,
+                    dst_node: row.get(7)?,
+                    dst_short_name: row.get(8)?,
+                    dst_long_name: row.get(9)?,
+                    via_mqtt: row.get(10)?,
+                    request_hops: row.get(11)?,
+                    request_hop_start: row.get(12)?,
+                    response_hops: row.get(13)?,
+                    response_hop_start: row.get(14)?,
+                    status: row.get(15)?,
+                    sample_count: row.get(16)?,
+                    request_path: row.get(17)?,
+                    response_path: row.get(18)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if rows.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // Collect session IDs for the hops query.
+        let session_ids: Vec<i64> = rows.iter().map(|r| r.id).collect();
+        let placeholders = session_ids
+            .iter()
+            .enumerate()
+            .map(|(i, _)| format!("?{}", i + 1))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let hops_sql = format!(
+            "SELECT h.session_id, h.direction, h.hop_index, h.node_id,
+                    n.short_name, n.long_name
+             FROM traceroute_session_hops h
+             LEFT JOIN nodes n ON n.node_id = h.node_id
+             WHERE h.session_id IN ({})
+             ORDER BY h.session_id, h.direction, h.hop_index",
+            placeholders
+        );
+
+        struct HopRow {
+            session_id: i64,
+            direction: String,
+            hop_index: i64,
+            node_id: i64,
+            short_name: Option<String>,
+            long_name: Option<String>,
+        }
+
+        let mut hops_by_session: HashMap<i64, Vec<HopRow>> = HashMap::new();
+        {
+            let mut hops_stmt = conn.prepare(&hops_sql)?;
+            let params_refs: Vec<&dyn rusqlite::ToSql> = session_ids
+                .iter()
+                .map(|id| id as &dyn rusqlite::ToSql)
+                .collect();
+            let hop_rows = hops_stmt
+                .query_map(params_refs.as_slice(), |row| {
+                    Ok(HopRow {
+                        session_id: row.get(0)?,
+                        direction: row.get(1)?,
+                        hop_index: row.get(2)?,
+                        node_id: row.get(3)?,
+                        short_name: row.get(4)?,
+                        long_name: row.get(5)?,
+                    })
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            for hop in hop_rows {
+                hops_by_session.entry(hop.session_id).or_default().push(hop);
+            }
+        }
+
+        let result = rows
+            .into_iter()
+            .map(|s| {
+                let hops = hops_by_session
+                    .remove(&s.id)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|h| TracerouteSessionHop {
+                        direction: h.direction,
+                        hop_index: h.hop_index,
+                        node_id: format!("!{:08x}", h.node_id as u32),
+                        short_name: h.short_name,
+                        long_name: h.long_name,
+                    })
+                    .collect();
+
+                TracerouteSession {
+                    id: s.id,
+                    trace_key: s.trace_key,
+                    first_seen: s.first_seen,
+                    last_seen: s.last_seen,
+                    src_node: format!("!{:08x}", s.src_node as u32),
+                    src_short_name: s.src_short_name,
+                    src_long_name: s.src_long_name,
+                    dst_node: s.dst_node.map(|n| format!("!{:08x}", n as u32)),
+                    dst_short_name: s.dst_short_name,
+                    dst_long_name: s.dst_long_name,
+                    via_mqtt: s.via_mqtt != 0,
+                    request_hops: s.request_hops,
+                    request_hop_start: s.request_hop_start,
+                    response_hops: s.response_hops,
+                    response_hop_start: s.response_hop_start,
+                    status: s.status,
+                    sample_count: s.sample_count,
+                    request_path: s.request_path,
+                    response_path: s.response_path,
+                    hops,
+                }
+            })
+            .collect();
+
+        Ok(result)
+    }
+
+    /// The routes of traceroute session `id`, endpoints included, with each
+    /// node's known position; `None` if there is no such session.
+    pub fn traceroute_session_geo(
+        &self,
+        id: i64,
+    ) -> Result<Option<TracerouteGeo>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.read_conn();
+        let session = conn.query_row(
+            "SELECT src_node, dst_node, status, response_hops IS NOT NULL
+             FROM traceroute_sessions WHERE id = ?1",
+            params![id],
+            |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, Option<i64>>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, bool>(3)?,
+                ))
+            },
+        );
+        let (src_node, dst_node, status, responded) = match session {
+            Ok(session) => session,
+            Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        let geo_hop = |node_id: i64| -> rusqlite::Result<GeoHop> {
+            let node = conn.query_row(
+                "SELECT short_name, long_name, latitude, longitude FROM nodes WHERE node_id = ?1",
+                params![node_id],
+                |row| {
+                    Ok((
+                        row.get::<_, Option<String>>(0)?,
+                        row.get::<_, Option<String>>(1)?,
+                        row.get::<_, Option<f64>>(2)?,
+                        row.get::<_, Option<f64>>(3)?,
+                    ))
+                },
+            );
+            let (short_name, long_name, latitude, longitude) = match node {
+                Ok(node) => node,
+                Err(rusqlite::Error::QueryReturnedNoRows) => (None, None, None, None),
+                Err(e) => return Err(e),
+            };
+            // 0,0 is what nodes without a fix report
+            let (latitude, longitude) = match (latitude, longitude) {
+                (Some(lat), Some(lon)) if lat != 0.0 || lon != 0.0 => (Some(lat), Some(lon)),
+                _ => (None, None),
+            };
+            Ok(GeoHop {
+                node_id: format!("!{:08x}", node_id as u32),
+                short_name,
+                long_name,
+                latitude,
+                longitude,
+            })
+        };
+        let relays = |direction: &str| -> rusqlite::Result<Vec<i64>> {
+            conn.prepare(
+                "SELECT node_id FROM traceroute_session_hops
+                 WHERE session_id = ?1 AND direction = ?2
+                 ORDER BY hop_index",
+            )?
+            .query_map(params![id, direction], |row| row.get(0))?
+            .collect()
+        };
+
+        let mut request = vec![src_node];
+        request.extend(relays("request")?);
+        request.extend(dst_node);
+        let mut response = Vec::new();
+        if responded {
+            response.extend(dst_node);
+            response.extend(relays("response")?);
+            response.push(src_node);
+        }
+        Ok(Some(TracerouteGeo {
+            id,
+            status,
+            request: request.into_iter().map(geo_hop).collect::<Result<_, _>>()?,
+            response: response
+                .into_iter()
+                .map(geo_hop)
+                .collect::<Result<_, _>>()?,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup_db() -> Db {
+        Db::open(Path::new(":memory:")).unwrap()
+    }
+
+    // --- Store & Forward tests ---
+
+    #[test]
+    fn test_store_forward_routers_and_history_merge() {
+        let db = setup_db();
+        db.record_store_forward_router(0xAAAAAAAA, Some((12, 300)))
+            .unwrap();
+        db.record_store_forward_router(0xAAAAAAAA, None).unwrap();
+        let routers = db.store_forward_routers().unwrap();
+        assert_eq!(routers.len(), 1);
+        assert_eq!(routers[0].messages_saved, Some(12));
+        assert_eq!(routers[0].messages_max, Some(300));
+
+        // Heard live, then replayed: not merged twice
+        db.log_packet_with_mesh_id(
+            0xBBBBBBBB,
+            None,
+            0,
+            "live",
+            "in",
+            false,
+            None,
+            None,
+            None,
+            None,
+            Some(7),
+            PacketType::Text,
+        )
+        .unwrap();
+        assert!(!db
+            .merge_store_forward_text(0xBBBBBBBB, None, 0, "live", 7, 1000)
+            .unwrap());
+        assert!(db
+            .merge_store_forward_text(0xBBBBBBBB, None, 0, "missed", 8, 1000)
+            .unwrap());
+        assert!(!db
+            .merge_store_forward_text(0xBBBBBBBB, None, 0, "missed", 8, 1000)
+            .unwrap());
+        let conn = db.conn.lock().unwrap();
+        let timestamp: i64 = conn
+            .query_row(
+                "SELECT timestamp FROM packets WHERE mesh_packet_id = 8",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(timestamp, 1000);
+    }
+
+    // --- Environment telemetry tests ---
+
+    #[test]
+    fn test_environment_readings() {
+        let db = setup_db();
+        db.upsert_node(0xAAAAAAAA, "PUMP", "Pump House", false)
+            .unwrap();
+        db.log_environment(0xAAAAAAAA, Some(4.5), Some(80.0), None)
+            .unwrap();
+        db.log_environment(0xAAAAAAAA, Some(3.0), None, Some(1012.5))
+            .unwrap();
+        db.log_environment(0xBBBBBBBB, None, None, None).unwrap();
+
+        let sensors = db.environment_sensors().unwrap();
+        assert_eq!(sensors.len(), 1);
+        assert_eq!(sensors[0].long_name, "Pump House");
+        assert_eq!(sensors[0].latest.temperature, Some(3.0));
+        assert_eq!(sensors[0].latest.barometric_pressure, Some(1012.5));
+
+        let series = db.environment_series(0xAAAAAAAA, 24).unwrap();
+        assert_eq!(series.len(), 2);
+        assert_eq!(series[0].relative_humidity, Some(80.0));
+        assert!(db.environment_series(0xBBBBBBBB, 0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_delivery_stats() {
+        let db = setup_db();
+        db.upsert_node(0xAAAAAAAA, "AAA", "Alpha", false).unwrap();
+        let me = 0x11111111;
+        for (id, to) in [
+            (1, 0xAAAAAAAA),
+            (2, 0xAAAAAAAA),
+            (3, 0xAAAAAAAA),
+            (4, 0xBBBBBBBB),
+        ] {
+            db.log_packet_with_mesh_id(
+                me,
+                Some(to),
+                0,
+                "hi",
+                "out",
+                false,
+                None,
+                None,
+                None,
+                None,
+                Some(id),
+                PacketType::Text,
+            )
+            .unwrap();
+        }
+        // Broadcasts have no destination to be acknowledged by
+        db.log_packet_with_mesh_id(
+            me,
+            None,
+            0,
+            "all",
+            "out",
+            false,
+            None,
+            None,
+            None,
+            None,
+            Some(5),
+            PacketType::Text,
+        )
+        .unwrap();
+
+        assert!(db.record_packet_ack(1, 0xAAAAAAAA, true, 100).unwrap());
+        // Only the destination's ACK counts
+        assert!(!db.record_packet_ack(2, 0xBBBBBBBB, true, 100).unwrap());
+        assert!(db.record_packet_ack(2, me, false, 100).unwrap());
+        // An error doesn't override an ACK, but a later ACK overrides an error
+        assert!(!db.record_packet_ack(1, me, false, 110).unwrap());
+        assert!(db.record_packet_ack(4, me, false, 100).unwrap());
+        assert!(db.record_packet_ack(4, 0xBBBBBBBB, true, 120).unwrap());
+
+        let stats = db.delivery_stats(0).unwrap();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].node_id, "!aaaaaaaa");
+        assert_eq!(stats[0].short_name, "AAA");
+        assert_eq!((stats[0].sent, stats[0].acked, stats[0].failed), (3, 1, 1));
+        assert!((stats[0].success_rate - 1.0 / 3.0).abs() < 1e-9);
+        assert_eq!(stats[1].node_id, "!bbbbbbbb");
+        assert_eq!((stats[1].sent, stats[1].acked, stats[1].failed), (1, 1, 0));
+        assert_eq!(stats[1].success_rate, 1.0);
+    }
+
+    #[test]
+    fn test_pending_sends() {
+        let db = setup_db();
+        let send = PendingSend {
+            id: 0,
+            to_node: 0xAAAAAAAA,
+            mesh_channel: 1,
+            text: "hello".to_string(),
+            reply_id: Some(42),
+            attempts: 1,
+        };
+        let due = |now| db.due_pending_sends(0xAAAAAAAA, 120, 0, now).unwrap();
+        let id = db.add_pending_send(100, &send, false, 1000).unwrap();
+        db.add_pending_send(200, &send, false, 1000).unwrap();
+
+        // Not due until the ACK timeout has passed
+        assert!(due(1119).is_empty());
+        assert_eq!(due(1120).len(), 2);
+
+        // An ACK from anyone but the destination doesn't count
+        assert!(!db.ack_pending_send(200, 0xBBBBBBBB).unwrap());
+        assert!(db.ack_pending_send(200, 0xAAAAAAAA).unwrap());
+
+        // The timeout doubles with each attempt; a routing error makes it due
+        db.retry_pending_send(id, 1200).unwrap();
+        db.pending_send_transmitted(id, 101, false).unwrap();
+        assert!(due(1439).is_empty());
+        assert_eq!(
+            due(1440),
+            vec![PendingSend {
+                id,
+                attempts: 2,
+                ..send.clone()
+            }]
+        );
+        assert!(db.fail_pending_send(101).unwrap());
+        assert_eq!(due(1201).len(), 1);
+        assert!(db
+            .due_pending_sends(0xAAAAAAAA, 120, 1000, 1201)
+            .unwrap()
+            .is_empty());
+
+        assert_eq!(db.prune_pending_sends(1001).unwrap(), 1);
+        assert!(due(9999).is_empty());
+    }
+
+    #[test]
+    fn test_mesh_links_and_graph() {
+        let db = setup_db();
+        let now = Utc::now().timestamp();
+        db.record_neighbors(0xAAAAAAAA, &[(0xBBBBBBBB, 6.5)], now)
+            .unwrap();
+        db.record_neighbors(0xDDDDDDDD, &[(0xEEEEEEEE, 1.0)], now - 3 * 3600)
+            .unwrap();
+        // An unanswered trace only proves the links up to its last relay
+        let packet = db
+            .log_packet_with_mesh_id(
+                0xBBBBBBBB,
+                Some(0x12345678),
+                0,
+                "",
+                "in",
+                false,
+                None,
+                None,
+                None,
+                None,
+                Some(9),
+                PacketType::Traceroute,
+            )
+            .unwrap();
+        db.log_traceroute_observation(
+            packet,
+            "in:bbbbbbbb:12345678:9",
+            0xBBBBBBBB,
+            Some(0x12345678),
+            false,
+            Some(2),
+            Some(3),
+            None,
+            None,
+            &[0xCCCCCCCC, u32::MAX],
+            &[],
+        )
+        .unwrap();
+
+        let mut links = db.mesh_links(now - 3600).unwrap();
+        links.sort_unstable();
+        assert_eq!(
+            links,
+            vec![(0xAAAAAAAA, 0xBBBBBBBB), (0xBBBBBBBB, 0xCCCCCCCC)]
+        );
+
+        db.upsert_node(0xBBBBBBBB, "BBB", "Bravo", false).unwrap();
+        assert_eq!(db.mesh_hop_matrix().unwrap().updated_at, None);
+        db.store_mesh_graph(
+            &[
+                (0xAAAAAAAA, 0xBBBBBBBB, 1),
+                (0xAAAAAAAA, 0xCCCCCCCC, 2),
+                (0xBBBBBBBB, 0xCCCCCCCC, 1),
+            ],
+            &[CutNode {
+                node: 0xBBBBBBBB,
+                cut_off: vec![0xAAAAAAAA],
+            }],
+            now,
+        )
+        .unwrap();
+        let matrix = db.mesh_hop_matrix().unwrap();
+        assert_eq!(matrix.updated_at, Some(now));
+        assert_eq!(matrix.nodes.len(), 3);
+        assert_eq!(
+            matrix.pairs[1],
+            MeshHops {
+                from: "!aaaaaaaa".to_string(),
+                to: "!cccccccc".to_string(),
+                hops: 2,
+            }
+        );
+        let suggestions = db.router_suggestions().unwrap();
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].node_id, "!bbbbbbbb");
+        assert_eq!(suggestions[0].short_name, "BBB");
+        assert_eq!(suggestions[0].cut_off[0].node_id, "!aaaaaaaa");
+
+        db.purge_node_data(0xBBBBBBBB).unwrap();
+        assert!(db.router_suggestions().unwrap().is_empty());
+        assert_eq!(db.mesh_hop_matrix().unwrap().pairs.len(), 1);
+    }
+
+    #[test]
+    fn test_network_graph() {
+        let db = setup_db();
+        let now = Utc::now().timestamp();
+        db.upsert_node(0xAAAAAAAA, "AAA", "Alpha", false).unwrap();
+        db.record_neighbors(0xAAAAAAAA, &[(0xBBBBBBBB, 6.5)], now - 3 * 3600)
+            .unwrap();
+        db.record_neighbors(0xAAAAAAAA, &[(0xBBBBBBBB, 8.0), (0xCCCCCCCC, -4.25)], now)
+            .unwrap();
+        db.record_neighbors(0xDDDDDDDD, &[(0xEEEEEEEE, 1.0)], now - 3 * 3600)
+            .unwrap();
+
+        let graph = db.network_graph(1).unwrap();
+        assert_eq!(
+            graph.nodes,
+            vec![
+                GraphNode {
+                    node_id: "!aaaaaaaa".to_string(),
+                    short_name: "AAA".to_string(),
+                    long_name: "Alpha".to_string(),
+                },
+                GraphNode {
+                    node_id: "!bbbbbbbb".to_string(),
+                    short_name: String::new(),
+                    long_name: String::new(),
+                },
+                GraphNode {
+                    node_id: "!cccccccc".to_string(),
+                    short_name: String::new(),
+                    long_name: String::new(),
+                },
+            ]
+        );
+        assert_eq!(
+            graph.edges,
+            vec![
+                GraphEdge {
+                    from: "!aaaaaaaa".to_string(),
+                    to: "!bbbbbbbb".to_string(),
+                    snr: 8.0,
+                    last_seen: now,
+                },
+                GraphEdge {
+                    from: "!aaaaaaaa".to_string(),
+                    to: "!cccccccc".to_string(),
+                    snr: -4.25,
+                    last_seen: now,
+                },
+            ]
+        );
+        assert_eq!(db.network_graph(0).unwrap().edges.len(), 3);
+
+        db.purge_node_data(0xBBBBBBBB).unwrap();
+        assert_eq!(db.network_graph(0).unwrap().edges.len(), 2);
+    }
+
+    #[test]
+    fn test_device_telemetry() {
+        let db = setup_db();
+        let now = Utc::now().timestamp();
+        let reading = |timestamp, battery_level| DeviceTelemetry {
+            timestamp,
+            battery_level,
+            voltage: Some(3.75),
+            channel_utilization: Some(12.5),
+            air_util_tx: None,
+            uptime_seconds: Some(3600),
+        };
+        db.log_device_telemetry(0xAAAAAAAA, &reading(now - 2 * 3600, Some(80)))
+            .unwrap();
+        db.log_device_telemetry(0xAAAAAAAA, &reading(now - 60, Some(79)))
+            .unwrap();
+        db.log_device_telemetry(0xBBBBBBBB, &reading(now, None))
+            .unwrap();
+
+        let series = db.get_node_telemetry(0xAAAAAAAA, 0).unwrap();
+        assert_eq!(
+            series,
+            vec![
+                reading(now - 2 * 3600, Some(80)),
+                reading(now - 60, Some(79))
+            ]
+        );
+        assert_eq!(db.get_node_telemetry(0xAAAAAAAA, 1).unwrap().len(), 1);
+
+        db.purge_node_data(0xAAAAAAAA).unwrap();
+        assert!(db.get_node_telemetry(0xAAAAAAAA, 0).unwrap().is_empty());
+        assert_eq!(db.get_node_telemetry(0xBBBBBBBB, 0).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_read_pool_serves_dashboard_queries() {
+        let path =
+            std::env::temp_dir().join(format!("meshenger-replica-test-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut db = Db::open(&path).unwrap();
+        assert!(Db::open(Path::new(":memory:"))
+            .unwrap()
+            .open_read_pool(Path::new(":memory:"), 2)
+            .is_err());
+        db.open_read_pool(&path, 2).unwrap();
+
+        db.log_packet(
+            0x11111111,
+            None,
+            0,
+            "hi",
+            "in",
+            false,
+            None,
+            None,
+            None,
+            None,
+            PacketType::Text,
+        )
+        .unwrap();
+        let overview = db.dashboard_overview(24, MqttFilter::All, "Bot").unwrap();
+        assert_eq!(overview.messages_in, 1);
+        assert!(db.read_conn().execute("DELETE FROM packets", []).is_err());
+
+        // A busy reader doesn't hold up the next query
+        let busy = db.read_conn();
+        let overview = db.dashboard_overview(24, MqttFilter::All, "Bot").unwrap();
+        assert_eq!(overview.messages_in, 1);
+        let timeout: i64 = busy
+            .query_row("PRAGMA busy_timeout", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(timeout, BUSY_TIMEOUT.as_millis() as i64);
+        drop(busy);
+
+        drop(db);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+    }
+
+    // --- Schema version tests ---
+
+    #[test]
+    fn test_newer_schema_is_refused_unless_forced() {
+        let path =
+            std::env::temp_dir().join(format!("meshenger-schema-test-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let db = Db::open(&path).unwrap();
+        assert_eq!(
+            db.get_state(WRITER_VERSION_KEY).unwrap().as_deref(),
+            Some(env!("CARGO_PKG_VERSION"))
+        );
+        {
+            let conn = db.conn.lock().unwrap();
+            conn.pragma_update(None, "user_version", SCHEMA_VERSION + 1)
+                .unwrap();
+        }
+        db.set_state(WRITER_VERSION_KEY, "99.0.0").unwrap();
+        drop(db);
+
+        let err = Db::open(&path).err().unwrap().to_string();
+        assert!(err.contains("meshenger 99.0.0"), "{}", err);
+        assert!(err.contains("--force"), "{}", err);
+
+        let db = Db::open_forced(&path).unwrap();
+        let version: i64 = db
+            .conn
+            .lock()
+            .unwrap()
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, SCHEMA_VERSION + 1);
+        drop(db);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+    }
+
+    // --- Node tests ---
+
+    #[test]
+    fn test_upsert_and_get_node() {
+        let db = setup_db();
+
+        db.upsert_node(0x12345678, "ABCD", "Alice's Node", false)
+            .unwrap();
+
+        let nodes = db.get_all_nodes().unwrap();
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(nodes[0].node_id, 0x12345678);
+        assert_eq!(nodes[0].short_name, "ABCD");
+        assert_eq!(nodes[0].long_name, "Alice's Node");
+    }
+
+    #[test]
+    fn test_is_node_new() {
+        let db = setup_db();
+
+        assert!(db.is_node_new(0x12345678).unwrap());
+
+        db.upsert_node(0x12345678, "ABCD", "Alice", false).unwrap();
+
+        assert!(!db.is_node_new(0x12345678).unwrap());
+    }
+
+    #[test]
+    fn test_import_node_backdates() {
+        let db = setup_db();
+        let now = Utc::now().timestamp();
+
+        // New from the NodeDB: dated by the radio, marked as imported
+        db.import_node(0xAAAAAAAA, "A", "Alice", false, now - 86400, true)
+            .unwrap();
+        // Already known: first_seen only moves earlier, last_seen later
+        db.upsert_node(0xBBBBBBBB, "B", "Bob", false).unwrap();
+        db.import_node(0xBBBBBBBB, "", "", false, now - 3600, false)
+            .unwrap();
+        // Upserted by a module just before the import still counts as imported
+        db.upsert_node(0xCCCCCCCC, "C", "Carol", false).unwrap();
+        db.import_node(0xCCCCCCCC, "C", "Carol", false, now - 7200, true)
+            .unwrap();
+
+        let nodes = db.get_all_nodes().unwrap();
+        let node = |id: u32| nodes.iter().find(|n| n.node_id == id).unwrap();
+        assert_eq!(node(0xAAAAAAAA).first_seen, now - 86400);
+        assert_eq!(node(0xAAAAAAAA).last_seen, now - 86400);
+        assert_eq!(node(0xAAAAAAAA).source, "nodedb");
+        assert_eq!(node(0xBBBBBBBB).first_seen, now - 3600);
+        assert!(node(0xBBBBBBBB).last_seen >= now);
+        assert_eq!(node(0xBBBBBBBB).long_name, "Bob");
+        assert_eq!(node(0xBBBBBBBB).source, "observed");
+        assert_eq!(node(0xCCCCCCCC).first_seen, now - 7200);
+        assert_eq!(node(0xCCCCCCCC).source, "nodedb");
+    }
+
+    #[test]
+    fn test_get_node_name_long() {
+        let db = setup_db();
+        db.upsert_node(0x12345678, "ABCD", "Alice's Node", false)
+            .unwrap();
+
+        let name = db.get_node_name(0x12345678).unwrap();
+        assert_eq!(name, "Alice's Node");
+    }
+
+    #[test]
+    fn test_get_node_name_short_fallback() {
+        let db = setup_db();
+        db.upsert_node(0x12345678, "ABCD", "", false).unwrap();
+
+        let name = db.get_node_name(0x12345678).unwrap();
+        assert_eq!(name, "ABCD");
+    }
+
+    #[test]
+    fn test_get_node_name_hex_fallback() {
+        let db = setup_db();
+        db.upsert_node(0x12345678, "", "", false).unwrap();
+
+        let name = db.get_node_name(0x12345678).unwrap();
+        assert_eq!(name, "!12345678");
+    }
+
+    #[test]
+    fn test_get_node_name_unknown() {
+        let db = setup_db();
+        let name = db.get_node_name(0x99999999).unwrap();
+        assert_eq!(name, "!99999999");
+    }
+
+    #[test]
+    fn test_purge_nodes_not_seen_within() {
+        let db = setup_db();
+        db.upsert_node(0xAAAAAAAA, "A", "Alice", false).unwrap();
+        db.upsert_node(0xBBBBBBBB, "B", "Bob", false).unwrap();
+
+        let now = Utc::now().timestamp();
+        let stale_ts = now - (8 * 24 * 3600);
+        let recent_ts = now - (2 * 24 * 3600);
+        {
+            let conn = db.conn.lock().unwrap();
+            conn.execute(
+                "UPDATE nodes SET last_seen = ?1 WHERE node_id = ?2",
+                params![stale_ts, 0xAAAAAAAAu32 as i64],
+            )
+            .unwrap();
+            conn.execute(
+                "UPDATE nodes SET last_seen = ?1 WHERE node_id = ?2",
+                params![recent_ts, 0xBBBBBBBBu32 as i64],
+            )
+            .unwrap();
+        }
+
+        let purged = db.purge_nodes_not_seen_within(7 * 24 * 3600).unwrap();
+        assert_eq!(purged, 1);
+
+        let nodes = db.get_all_nodes().unwrap();
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(nodes[0].node_id, 0xBBBBBBBB);
+    }
+
+    #[test]
+    fn test_find_node_by_hex_id() {
+        let db = setup_db();
+        db.upsert_node(0xaabbccdd, "ABCD", "Alice", false).unwrap();
+
+        assert_eq!(db.find_node_by_name("!aabbccdd").unwrap(), Some(0xaabbccdd));
+        assert_eq!(db.find_node_by_name("aabbccdd").unwrap(), Some(0xaabbccdd));
+    }
+
+    #[test]
+    fn test_find_node_by_decimal_id() {
+        let db = setup_db();
+        // Use a number with digits > 9 to avoid hex ambiguity
+        db.upsert_node(3954221518, "ABCD", "Alice", false).unwrap();
+
+        assert_eq!(
+            db.find_node_by_name("3954221518").unwrap(),
+            Some(3954221518)
+        );
+    }
+
+    #[test]
+    fn test_find_node_by_name() {
+        let db = setup_db();
+        db.upsert_node(0x12345678, "ABCD", "Alice", false).unwrap();
+
+        assert_eq!(db.find_node_by_name("Alice").unwrap(), Some(0x12345678));
+        assert_eq!(db.find_node_by_name("alice").unwrap(), Some(0x12345678)); // case insensitive
+        assert_eq!(db.find_node_by_name("ABCD").unwrap(), Some(0x12345678));
+    }
+
+    #[test]
+    fn test_find_node_not_found() {
+        let db = setup_db();
+        assert_eq!(db.find_node_by_name("Unknown").unwrap(), None);
+    }
+
+    #[test]
+    fn test_get_recent_nodes_with_last_hop() {
+        let db = setup_db();
+        db.upsert_node(0xAAAAAAAA, "A", "Alice", false).unwrap();
+        db.upsert_node(0xBBBBBBBB, "B", "Bob", false).unwrap();
+
+        db.log_packet(
+            0xAAAAAAAA,
+            None,
+            0,
+            "a1",
+            "in",
+            false,
+            Some(-80),
+            Some(5.0),
+            Some(2),
+            Some(7),
+            PacketType::Text,
+        )
+        .unwrap();
+        db.log_packet(
+            0xAAAAAAAA,
+            None,
+            0,
+            "a2",
+            "in",
+            false,
+            Some(-78),
+            Some(5.2),
+            Some(4),
+            Some(7),
+            PacketType::Text,
+        )
+        .unwrap();
+
+        let nodes = db.get_recent_nodes_with_last_hop(10).unwrap();
+        assert_eq!(nodes.len(), 2);
+        let limited = db.get_recent_nodes_with_last_hop(1).unwrap();
+        assert_eq!(limited.len(), 1);
+
+        let alice = nodes.iter().find(|n| n.node_id == 0xAAAAAAAA).unwrap();
+        let bob = nodes.iter().find(|n| n.node_id == 0xBBBBBBBB).unwrap();
+        assert_eq!(alice.last_hop, Some(4));
+        assert_eq!(bob.last_hop, None);
+    }
+
+    #[test]
+    fn test_recent_rf_node_missing_hops() {
+        let db = setup_db();
+        db.upsert_node(0xAAAAAAAA, "A", "Alice", false).unwrap();
+        db.upsert_node(0xBBBBBBBB, "B", "Bob", false).unwrap();
+
+        // Bob already has hop metadata
+        db.log_packet(
+            0xBBBBBBBB,
+            None,
+            0,
+            "hi",
+            "in",
+            false,
+            Some(-80),
+            Some(5.0),
+            Some(2),
+            Some(3),
+            PacketType::Text,
+        )
+        .unwrap();
+
+        let candidate = db
+            .recent_rf_nodes_missing_hops(3600, None, 1)
+            .unwrap()
+            .into_iter()
+            .next();
+        assert_eq!(candidate, Some(0xAAAAAAAA));
+    }
+
+    #[test]
+    fn test_recent_rf_node_missing_hops_excludes_node() {
+        let db = setup_db();
+        db.upsert_node(0xAAAAAAAA, "A", "Alice", false).unwrap();
+        db.upsert_node(0xBBBBBBBB, "B", "Bob", false).unwrap();
+
+        let candidate = db
+            .recent_rf_nodes_missing_hops(3600, Some(0xAAAAAAAA), 1)
+            .unwrap()
+            .into_iter()
+            .next();
+        assert_eq!(candidate, Some(0xBBBBBBBB));
+    }
+
+    #[test]
+    fn test_recent_rf_nodes_missing_hops_returns_multiple_in_recency_order() {
+        let db = setup_db();
+        db.upsert_node(0xAAAAAAAA, "A", "Alice", false).unwrap();
+        db.upsert_node(0xBBBBBBBB, "B", "Bob", false).unwrap();
+        db.upsert_node(0xCCCCCCCC, "C", "Carol", false).unwrap();
+
+        let now = Utc::now().timestamp();
+        {
+            let conn = db.conn.lock().unwrap();
+            conn.execute(
+                "UPDATE nodes SET last_seen = ?1 WHERE node_id = ?2",
+                params![now - 30, 0xAAAAAAAAu32 as i64],
+            )
+            .unwrap();
+            conn.execute(
+                "UPDATE nodes SET last_seen = ?1 WHERE node_id = ?2",
+                params![now - 10, 0xBBBBBBBBu32 as i64],
+            )
+            .unwrap();
+            conn.execute(
+                "UPDATE nodes SET last_seen = ?1 WHERE node_id = ?2",
+                params![now - 20, 0xCCCCCCCCu32 as i64],
+            )
+            .unwrap();
+        }
+
+        let candidates = db.recent_rf_nodes_missing_hops(3600, None, 2).unwrap();
+        assert_eq!(candidates, vec![0xBBBBBBBB, 0xCCCCCCCC]);
+    }
+
+    // --- Position tests ---
+
+    #[test]
+    fn test_update_and_get_position() {
+        let db = setup_db();
+        db.upsert_node(0x12345678, "ABCD", "Alice", false).unwrap();
+        db.update_position(0x12345678, 25.0, 121.0).unwrap();
+
+        let pos = db.get_node_position(0x12345678).unwrap();
+        assert_eq!(pos, Some((25.0, 121.0)));
+    }
+
+    #[test]
+    fn test_get_position_none() {
+        let db = setup_db();
+        db.upsert_node(0x12345678, "ABCD", "Alice", false).unwrap();
+
+        let pos = db.get_node_position(0x12345678).unwrap();
+        assert_eq!(pos, None);
+    }
+
+    #[test]
+    fn test_get_position_zero_is_none() {
+        let db = setup_db();
+        db.upsert_node(0x12345678, "ABCD", "Alice", false).unwrap();
+        db.update_position(0x12345678, 0.0, 0.0).unwrap();
+
+        let pos = db.get_node_position(0x12345678).unwrap();
+        assert_eq!(pos, None); // 0,0 is treated as no position
+    }
+
+    #[test]
+    fn test_node_track_and_pruning() {
+        let db = setup_db();
+        let now = Utc::now().timestamp();
+        let point = |age: i64, latitude: f64| TrackPoint {
+            timestamp: now - age,
+            latitude,
+            longitude: 121.0,
+            altitude: Some(12),
+            speed: None,
+        };
+        db.log_position(0x12345678, &point(3 * 86400, 25.0))
+            .unwrap();
+        db.log_position(0x12345678, &point(7200, 25.1)).unwrap();
+        db.log_position(0x12345678, &point(60, 25.2)).unwrap();
+        db.log_position(0x87654321, &point(60, 30.0)).unwrap();
+
+        let track = db.node_track(0x12345678, 24).unwrap();
+        assert_eq!(track, vec![point(7200, 25.1), point(60, 25.2)]);
+        assert_eq!(db.node_track(0x12345678, 0).unwrap().len(), 3);
+
+        assert_eq!(db.prune_position_history_older_than(86400).unwrap(), 1);
+        assert_eq!(db.node_track(0x12345678, 0).unwrap().len(), 2);
+
+        db.purge_node_data(0x12345678).unwrap();
+        assert!(db.node_track(0x12345678, 0).unwrap().is_empty());
+        assert_eq!(db.node_track(0x87654321, 0).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_position_reported_at() {
+        let db = setup_db();
+        assert_eq!(db.position_reported_at(0x12345678).unwrap(), None);
+        db.upsert_node(0x12345678, "ABCD", "Alice", false).unwrap();
+        let conn = db.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE nodes SET last_seen = 5000 WHERE node_id = ?1",
+            params![0x12345678i64],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO packets (timestamp, from_node, channel, text, direction, packet_type)
+             VALUES (1000, ?1, 0, '', 'in', 'position'), (3000, ?1, 0, '', 'in', 'text')",
+            params![0x12345678i64],
+        )
+        .unwrap();
+        drop(conn);
+        assert_eq!(db.position_reported_at(0x12345678).unwrap(), Some(1000));
+
+        db.upsert_node(0x87654321, "B", "Bob", false).unwrap();
+        assert!(db.position_reported_at(0x87654321).unwrap().is_some());
+        assert_eq!(db.local_node_id().unwrap(), None);
+        db.set_local_node_id(0x87654321).unwrap();
+        assert_eq!(db.local_node_id().unwrap(), Some(0x87654321));
+    }
+
+    // --- Packet logging tests ---
+
+    #[test]
+    fn test_recent_chat_messages_skips_dms_and_outgoing() {
+        let db = setup_db();
+        db.upsert_node(0x11111111, "ALCE", "Alice", false).unwrap();
+        let log = |from: u32, to: Option<u32>, text: &str, dir: &str, kind: PacketType| {
+            db.log_packet(from, to, 0, text, dir, false, None, None, None, None, kind)
+                .unwrap();
+        };
+        log(0x11111111, None, "first", "in", PacketType::Text);
+        log(0x22222222, Some(u32::MAX), "second", "in", PacketType::Text);
+        log(
+            0x11111111,
+            Some(0x33333333),
+            "private",
+            "in",
+            PacketType::Text,
+        );
+        log(1, None, "bot reply", "out", PacketType::Text);
+        log(0x11111111, None, "", "in", PacketType::Position);
+
+        let messages = db.recent_chat_messages(5).unwrap();
+        let texts: Vec<&str> = messages.iter().map(|m| m.text.as_str()).collect();
+        assert_eq!(texts, vec!["second", "first"]);
+        assert_eq!(messages[0].from_name, "!22222222");
+        assert_eq!(messages[1].from_name, "Alice");
+        assert_eq!(db.recent_chat_messages(1).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_message_count() {
+        let db = setup_db();
+
+        assert_eq!(db.message_count("in").unwrap(), 0);
+        assert_eq!(db.message_count("out").unwrap(), 0);
+
+        db.log_packet(
+            0x12345678,
+            None,
+            0,
+            "Hello",
+            "in",
+            false,
+            Some(-80),
+            Some(5.0),
+            Some(1),
+            Some(3),
+            PacketType::Text,
+        )
+        .unwrap();
+        db.log_packet(
+            0x12345678,
+            None,
+            0,
+            "World",
+            "in",
+            false,
+            Some(-90),
+            Some(3.0),
+            Some(2),
+            Some(3),
+            PacketType::Text,
+        )
+        .unwrap();
+        db.log_packet(
+            0x12345678,
+            Some(0xaaaaaaaa),
+            0,
+            "Reply",
+            "out",
+            false,
+            None,
+            None,
+            None,
+            None,
+            PacketType::Text,
+        )
+        .unwrap();
+
+        assert_eq!(db.message_count("in").unwrap(), 2);
+        assert_eq!(db.message_count("out").unwrap(), 1);
+    }
+
+    #[test]
+    fn test_node_history() {
+        let db = setup_db();
+        let now = Utc::now().timestamp();
+        db.upsert_node(0xAAAAAAAA, "AAA", "Alpha", false).unwrap();
+        db.record_node_counts(now - 2 * 3600).unwrap();
+        db.upsert_node(0xBBBBBBBB, "BBB", "Bravo", true).unwrap();
+        db.upsert_node(0xCCCCCCCC, "CCC", "Charlie", false).unwrap();
+        let counts = db.record_node_counts(now).unwrap();
+        assert_eq!((counts.total, counts.rf, counts.mqtt), (3, 2, 1));
+
+        let history = db.node_history(0).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].timestamp, now - 2 * 3600);
+        assert_eq!(
+            (history[0].total, history[0].rf, history[0].mqtt),
+            (1, 1, 0)
+        );
+        assert_eq!(history[1], counts);
+        assert_eq!(db.node_history(1).unwrap(), vec![counts]);
+    }
+
+    #[test]
+    fn test_db_stats_and_vacuum() {
+        let db = setup_db();
+        db.upsert_node(0xAAAAAAAA, "AAA", "Alpha", false).unwrap();
+        db.upsert_node(0xBBBBBBBB, "BBB", "Bravo", false).unwrap();
+        db.store_mail(0xAAAAAAAA, 0xBBBBBBBB, "hi").unwrap();
+
+        let stats = db.db_stats().unwrap();
+        assert!(stats.size_bytes > 0);
+        assert!(stats.free_bytes <= stats.size_bytes);
+        assert!(stats.tables.windows(2).all(|w| w[0].rows >= w[1].rows));
+        let rows = |name: &str| stats.tables.iter().find(|t| t.name == name).map(|t| t.rows);
+        assert_eq!(rows("nodes"), Some(2));
+        assert_eq!(rows("mail"), Some(1));
+        assert_eq!(rows("sqlite_sequence"), None);
+
+        let (before, after) = db.vacuum().unwrap();
+        assert!(before > 0 && after > 0);
+        assert_eq!(db.db_stats().unwrap().free_bytes, 0);
+    }
+
+    #[test]
+    fn test_vacuum_leaves_writer_free() {
+        let path =
+            std::env::temp_dir().join(format!("meshenger-vacuum-test-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let db = Db::open(&path).unwrap();
+        db.upsert_node(0xAAAAAAAA, "AAA", "Alpha", false).unwrap();
+        {
+            // Would deadlock if VACUUM ran on the writer connection
+            let _writer = db.conn.lock().unwrap();
+            let (before, after) = db.vacuum().unwrap();
+            assert!(before > 0 && after > 0);
+        }
+        assert_eq!(db.get_node_name(0xAAAAAAAA).unwrap(), "Alpha");
+
+        drop(db);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+    }
+
+    #[test]
+    fn test_prune_packets_and_incremental_vacuum() {
+        let db = setup_db();
+        assert!(db.enable_incremental_vacuum().unwrap());
+        assert!(!db.enable_incremental_vacuum().unwrap());
+        let now = Utc::now().timestamp();
+        {
+            // More old text packets than one prune batch, an old position
+            // and a recent text
+            let conn = db.conn.lock().unwrap();
+            conn.execute(
+                "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < ?1)
+                 INSERT INTO packets (timestamp, from_node, channel, text, direction, packet_type)
+                 SELECT ?2, 1, 0, printf('%0200d', i), 'in', 'text' FROM n",
+                params![PRUNE_BATCH_ROWS as i64 + 10, now - 3 * 86400],
+            )
+            .unwrap();
+            conn.execute(
+                "INSERT INTO packets (timestamp, from_node, channel, text, direction, packet_type)
+                 VALUES (?1, 1, 0, '', 'in', 'position'), (?2, 1, 0, 'new', 'in', 'text')",
+                params![now - 3 * 86400, now],
+            )
+            .unwrap();
+        }
+
+        assert_eq!(
+            db.prune_packets_older_than(PacketType::Text, 86400)
+                .unwrap(),
+            PRUNE_BATCH_ROWS + 10
+        );
+        assert_eq!(
+            db.prune_packets_older_than(PacketType::Position, 7 * 86400)
+                .unwrap(),
+            0
+        );
+        // The recent text and the position, kept a week
+        assert_eq!(db.message_count("in").unwrap(), 2);
+        assert!(db.incremental_vacuum().unwrap() > 0);
+        assert_eq!(db.db_stats().unwrap().free_bytes, 0);
+    }
+
+    #[test]
+    fn test_latency_history() {
+        let db = setup_db();
+        let now = Utc::now().timestamp();
+        let window = |timestamp: i64, stage: &str, p50_ms: u32| LatencyWindow {
+            timestamp,
+            stage: stage.to_string(),
+            samples: 10,
+            p50_ms,
+            p90_ms: p50_ms * 2,
+            p99_ms: p50_ms * 3,
+            max_ms: p50_ms * 4,
+        };
+        db.record_latency(&window(now - 7200, "queue", 900), 86400)
+            .unwrap();
+        db.record_latency(&window(now, "queue", 1000), 86400)
+            .unwrap();
+        db.record_latency(&window(now, "handling", 15), 86400)
+            .unwrap();
+
+        let history = db.latency_history(1).unwrap();
+        assert_eq!(
+            history,
+            vec![window(now, "handling", 15), window(now, "queue", 1000)]
+        );
+        assert_eq!(db.latency_history(0).unwrap().len(), 3);
+
+        // Older windows are dropped past `keep_secs`
+        db.record_latency(&window(now + 60, "queue", 5), 3600)
+            .unwrap();
+        assert_eq!(db.latency_history(0).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_node_count() {
+        let db = setup_db();
+
+        assert_eq!(db.node_count().unwrap(), 0);
+
+        db.upsert_node(0xAAAAAAAA, "A", "Alice", false).unwrap();
+        assert_eq!(db.node_count().unwrap(), 1);
+
+        db.upsert_node(0xBBBBBBBB, "B", "Bob", false).unwrap();
+        assert_eq!(db.node_count().unwrap(), 2);
+
+        // Upsert same node doesn't increase count
+        db.upsert_node(0xAAAAAAAA, "A", "Alice Updated", false)
+            .unwrap();
+        assert_eq!(db.node_count().unwrap(), 2);
+    }
+
+    // --- Upsert behavior tests ---
+
+    #[test]
+    fn test_upsert_updates_existing() {
+        let db = setup_db();
+
+        db.upsert_node(0x12345678, "OLD", "Old Name", false)
+            .unwrap();
+        db.upsert_node(0x12345678, "NEW", "New Name", false)
+            .unwrap();
+
+        let nodes = db.get_all_nodes().unwrap();
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(nodes[0].short_name, "NEW");
+        assert_eq!(nodes[0].long_name, "New Name");
+    }
+
+    #[test]
+    fn test_upsert_preserves_nonempty_names() {
+        let db = setup_db();
+
+        db.upsert_node(0x12345678, "ABCD", "Alice", false).unwrap();
+        db.upsert_node(0x12345678, "", "", false).unwrap(); // Empty names shouldn't overwrite
+
+        let nodes = db.get_all_nodes().unwrap();
+        assert_eq!(nodes[0].short_name, "ABCD");
+        assert_eq!(nodes[0].long_name, "Alice");
+    }
+
+    #[test]
+    fn test_upsert_via_mqtt() {
+        let db = setup_db();
+
+        db.upsert_node(0x12345678, "ABCD", "Alice", false).unwrap();
+        let nodes = db.dashboard_nodes(24, MqttFilter::All).unwrap();
+        assert!(!nodes[0].via_mqtt);
+
+        db.upsert_node(0x12345678, "ABCD", "Alice", true).unwrap();
+        let nodes = db.dashboard_nodes(24, MqttFilter::All).unwrap();
+        assert!(nodes[0].via_mqtt);
+    }
+
+    // --- Dashboard query tests ---
+
+    #[test]
+    fn test_public_status() {
+        let db = setup_db();
+        assert_eq!(
+            db.public_status().unwrap(),
+            PublicStatus {
+                node_count: 0,
+                messages_today: 0,
+                last_packet_at: None
+            }
+        );
+        db.upsert_node(0xAAAAAAAA, "A", "Alice", false).unwrap();
+        for (text, direction, packet_type) in [
+            ("Hello", "in", PacketType::Text),
+            ("", "in", PacketType::Position),
+            ("Reply", "out", PacketType::Text),
+        ] {
+            db.log_packet(
+                0xAAAAAAAA,
+                None,
+                0,
+                text,
+                direction,
+                false,
+                None,
+                None,
+                None,
+                None,
+                packet_type,
+            )
+            .unwrap();
+        }
+        let status = db.public_status().unwrap();
+        assert_eq!(status.node_count, 1);
+        assert_eq!(status.messages_today, 1);
+        assert!(status.last_packet_at.is_some());
+    }
+
+    #[test]
+    fn test_dashboard_overview() {
+        let db = setup_db();
+        db.upsert_node(0xAAAAAAAA, "A", "Alice", false).unwrap();
+        db.upsert_node(0xBBBBBBBB, "B", "Bob", false).unwrap();
+        db.log_packet(
+            0xAAAAAAAA,
+            None,
+            0,
+            "Hello",
+            "in",
+            false,
+            Some(-80),
+            Some(5.0),
+            Some(1),
+            Some(3),
+            PacketType::Text,
+        )
+        .unwrap();
+        db.log_packet(
+            0xBBBBBBBB,
+            None,
+            0,
+            "Hi",
+            "in",
+            true,
+            Some(-70),
+            Some(8.0),
+            Some(0),
+            Some(3),
+            PacketType::Text,
+        )
+        .unwrap();
+        db.log_packet(
+            0xAAAAAAAA,
+            Some(0xBBBBBBBB),
+            0,
+            "Reply",
+            "out",
+            false,
+            None,
+            None,
+            None,
+            None,
+            PacketType::Text,
+        )
+        .unwrap();
+        // Non-text packet
+        db.log_packet(
+            0xAAAAAAAA,
+            None,
+            0,
+            "",
+            "in",
+            false,
+            Some(-75),
+            Some(6.0),
+            Some(1),
+            Some(3),
+            PacketType::Position,
+        )
+        .unwrap();
+
+        let overview = db
+            .dashboard_overview(24, MqttFilter::All, "TestBot")
+            .unwrap();
+        assert_eq!(overview.node_count, 2);
+        assert_eq!(overview.messages_in, 2);
+        assert_eq!(overview.messages_out, 1);
+        assert_eq!(overview.packets_in, 3); // 2 text + 1 position
+        assert_eq!(overview.packets_out, 1);
+        assert_eq!(overview.bot_name, "TestBot");
+
+        let local = db
+            .dashboard_overview(24, MqttFilter::LocalOnly, "TestBot")
+            .unwrap();
+        assert_eq!(local.messages_in, 1);
+
+        let mqtt = db
+            .dashboard_overview(24, MqttFilter::MqttOnly, "TestBot")
+            .unwrap();
+        assert_eq!(mqtt.messages_in, 1);
+    }
+
+    #[test]
+    fn test_third_party_traceroute_correlation_key_lookup() {
+        // Simulates the key lookup used to correlate a third-party RouteReply with
+        // the RouteRequest session that was previously observed.
+        //
+        // Scenario:
+        //   - ti6W (0x11111111) sends traceroute to arto (0x22222222), request_id=999
+        //   - We observe the RouteRequest → session key: in:11111111:22222222:999
+        //   - We observe the RouteReply (arto→ti6W, data.request_id=999)
+        //     → we try key: in:{to_node=ti6W}:{from_node=arto}:{request_id=999}
+        //     → = in:11111111:22222222:999 → matches!
+        let db = setup_db();
+        let since = chrono::Utc::now().timestamp() - 60;
+
+        let p1 = db
+            .log_packet_with_mesh_id(
+                0x11111111,
+                Some(0x22222222),
+                0,
+                "",
+                "in",
+                false,
+                None,
+                None,
+                Some(1),
+                Some(5),
+                Some(999),
+                PacketType::Traceroute,
+            )
+            .unwrap();
+
+        // Insert the RouteRequest session
+        let request_key = "in:11111111:22222222:999";
+        db.log_traceroute_observation(
+            p1,
+            request_key,
+            0x11111111,
+            Some(0x22222222),
+            false,
+            Some(1),
+            Some(5),
+            None,
+            None,
+            &[0xaabbccdd],
+            &[],
+        )
+        .unwrap();
+
+        // The reply correlation lookup: in:{initiator=ti6W}:{from=arto}:{request_id=999}
+        let correlation_key = format!("in:{:08x}:{:08x}:{}", 0x11111111u32, 0x22222222u32, 999);
+        assert_eq!(correlation_key, request_key);
+        assert!(db
+            .traceroute_session_exists_since(&correlation_key, since)
+            .unwrap());
+
+        // Old key for the reply (in:{arto}:{ti6W}:{reply_id}) should NOT exist
+        let old_reply_key = "in:22222222:11111111:1234";
+        assert!(!db
+            .traceroute_session_exists_since(old_reply_key, since)
+            .unwrap());
+    }
+
+    #[test]
+    fn test_third_party_traceroute_reply_merges_response_hops() {
+        // Verify that correlating a RouteReply into an existing RouteRequest session
+        // adds response hops without duplicating request hops.
+        let db = setup_db();
+
+        let request_key = "in:11111111:22222222:999";
+
+        let p1 = db
+            .log_packet_with_mesh_id(
+                0x11111111,
+                Some(0x22222222),
+                0,
+                "",
+                "in",
+                false,
+                None,
+                None,
+                Some(1),
+                Some(5),
+                Some(999),
+                PacketType::Traceroute,
+            )
+            .unwrap();
+        let p2 = db
+            .log_packet_with_mesh_id(
+                0x22222222,
+                Some(0x11111111),
+                0,
+                "",
+                "in",
+                false,
+                None,
+                None,
+                Some(0),
+                Some(2),
+                Some(1000),
+                PacketType::Traceroute,
+            )
+            .unwrap();
+
+        // Step 1: observe RouteRequest (ti6W → arto), relay node a1ce in route
+        db.log_traceroute_observation(
+            p1,
+            request_key,
+            0x11111111,
+            Some(0x22222222),
+            false,
+            Some(1),
+            Some(5),
+            None,
+            None,
+            &[0xa1ce0000], // request route: one relay
+            &[],
+        )
+        .unwrap();
+
+        // Step 2: correlate RouteReply (arto → ti6W); pass &[] for request route
+        // (already logged above) and route_back as response route.
+        db.log_traceroute_observation(
+            p2,
+            request_key,
+            0x11111111, // obs_src stays as initiator
+            Some(0x22222222),
+            false,
+            Some(1), // req_hops from route.len()
+            None,
+            Some(0), // res_hops from RF metadata
+            Some(2),
+            &[],           // no request hops (already inserted)
+            &[0xa1ce0000], // response route_back
+        )
+        .unwrap();
+
+        // Session uses in: key so capped at partial even with both sides present
+        let sessions = db.dashboard_traceroute_sessions(0, 10, false).unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].status, "partial");
+        assert_eq!(sessions[0].sample_count, 2);
+
+        // Hops: one 'request' hop + one 'response' hop, no duplicates
+        let hops = &sessions[0].hops;
+        assert_eq!(hops.len(), 2);
+        let directions: Vec<&str> = hops.iter().map(|h| h.direction.as_str()).collect();
+        assert!(directions.contains(&"request"));
+        assert!(directions.contains(&"response"));
+    }
+
+    #[test]
+    fn test_traceroute_session_geo() {
+        let db = setup_db();
+        db.upsert_node(0xaaaaaaaa, "SRC", "Source", false).unwrap();
+        db.upsert_node(0xcccccccc, "RLY", "Relay", false).unwrap();
+        db.update_position(0xaaaaaaaa, 25.0, 121.5).unwrap();
+        db.update_position(0xcccccccc, 25.1, 121.6).unwrap();
+        let trace_key = "req:aaaaaaaa:bbbbbbbb:5";
+        let probe = db
+            .log_packet_with_mesh_id(
+                0xaaaaaaaa,
+                Some(0xbbbbbbbb),
+                0,
+                "",
+                "out",
+                false,
+                None,
+                None,
+                None,
+                None,
+                Some(5),
+                PacketType::Traceroute,
+            )
+            .unwrap();
+        db.log_traceroute_observation(
+            probe,
+            trace_key,
+            0xaaaaaaaa,
+            Some(0xbbbbbbbb),
+            false,
+            Some(1),
+            Some(3),
+            None,
+            None,
+            &[0xcccccccc],
+            &[],
+        )
+        .unwrap();
+        let id = db.dashboard_traceroute_sessions(0, 10, false).unwrap()[0].id;
+
+        let geo = db.traceroute_session_geo(id).unwrap().unwrap();
+        let nodes: Vec<&str> = geo.request.iter().map(|h| h.node_id.as_str()).collect();
+        assert_eq!(nodes, ["!aaaaaaaa", "!cccccccc", "!bbbbbbbb"]);
+        assert_eq!(geo.request[1].short_name.as_deref(), Some("RLY"));
+        assert_eq!(geo.request[1].latitude, Some(25.1));
+        // The destination was never heard, so has no position
+        assert_eq!(geo.request[2].latitude, None);
+        assert!(geo.response.is_empty());
+
+        db.log_traceroute_observation(
+            probe,
+            trace_key,
+            0xaaaaaaaa,
+            Some(0xbbbbbbbb),
+            false,
+            None,
+            None,
+            Some(1),
+            Some(3),
+            &[],
+            &[0xcccccccc],
+        )
+        .unwrap();
+        let geo = db.traceroute_session_geo(id).unwrap().unwrap();
+        let nodes: Vec<&str> = geo.response.iter().map(|h| h.node_id.as_str()).collect();
+        assert_eq!(nodes, ["!bbbbbbbb", "!cccccccc", "!aaaaaaaa"]);
+        assert_eq!(db.traceroute_session_geo(id + 1).unwrap(), None);
+    }
+
+    #[test]
+    fn test_traceroute_reply_merges_rf_and_mqtt_observations() {
+        // The reply to our probe is first heard from an MQTT gateway, then on RF.
+        // RF hop info must win, hops must not duplicate, and both paths are reported.
+        let db = setup_db();
+        let trace_key = "req:aaaaaaaa:bbbbbbbb:77";
+        let probe = db
+            .log_packet_with_mesh_id(
+                0xaaaaaaaa,
+                Some(0xbbbbbbbb),
+                0,
+                "",
+                "out",
+                false,
+                None,
+                None,
+                None,
+                None,
+                Some(77),
+                PacketType::Traceroute,
+            )
+            .unwrap();
+        db.log_traceroute_observation(
+            probe,
+            trace_key,
+            0xaaaaaaaa,
+            Some(0xbbbbbbbb),
+            false,
+            None,
+            None,
+            None,
+            None,
+            &[],
+            &[],
+        )
+        .unwrap();
+
+        let reply = db
+            .log_packet_with_mesh_id(
+                0xbbbbbbbb,
+                Some(0xaaaaaaaa),
+                0,
+                "",
+                "in",
+                true,
+                None,
+                None,
+                None,
+                None,
+                Some(78),
+                PacketType::Traceroute,
+            )
+            .unwrap();
+        // Via MQTT: hop count from route_back length, no hop_start
+        db.log_traceroute_observation(
+            reply,
+            trace_key,
+            0xaaaaaaaa,
+            Some(0xbbbbbbbb),
+            true,
+            Some(1),
+            None,
+            Some(1),
+            None,
+            &[0xc0c00000],
+            &[0xc0c00000],
+        )
+        .unwrap();
+
+        let sessions = db.dashboard_traceroute_sessions(0, 10, false).unwrap();
+        assert_eq!(sessions[0].status, "complete");
+        assert_eq!(sessions[0].response_path.as_deref(), Some("mqtt"));
+        assert!(!sessions[0].via_mqtt);
+
+        // Same reply heard on RF with real hop metadata
+        db.log_traceroute_observation(
+            reply,
+            trace_key,
+            0xaaaaaaaa,
+            Some(0xbbbbbbbb),
+            false,
+            Some(1),
+            None,
+            Some(2),
+            Some(5),
+            &[0xc0c00000],
+            &[0xc0c00000],
+        )
+        .unwrap();
+        // A later MQTT copy must not overwrite RF hop info
+        db.log_traceroute_observation(
+            reply,
+            trace_key,
+            0xaaaaaaaa,
+            Some(0xbbbbbbbb),
+            true,
+            Some(1),
+            None,
+            Some(1),
+            None,
+            &[0xc0c00000],
+            &[0xc0c00000],
+        )
+        .unwrap();
 
-    fn setup_db() -> Db {
-        Db::open(Path::new(":memory:")).unwrap()
+        let sessions = db.dashboard_traceroute_sessions(0, 10, false).unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].response_path.as_deref(), Some("both"));
+        assert_eq!(sessions[0].response_hops, Some(2));
+        assert_eq!(sessions[0].response_hop_start, Some(5));
+        assert_eq!(sessions[0].sample_count, 4);
+        assert_eq!(sessions[0].hops.len(), 2);
     }
 
-    // --- Node tests ---
-
     #[test]
-    fn test_upsert_and_get_node() {
-        let db = setup_db();
+    fn test_alert_state_and_queries() {
+        let db = Db::open(Path::new(":memory:")).unwrap();
+        assert_eq!(db.last_packet_in_at().unwrap(), None);
+        db.log_received_packet(
+            None,
+            0x11111111,
+            None,
+            0,
+            "hi",
+            false,
+            None,
+            None,
+            None,
+            None,
+            None,
+            PacketType::Text,
+        )
+        .unwrap();
+        assert!(db.last_packet_in_at().unwrap().is_some());
+        assert_eq!(
+            db.first_packet_in_at().unwrap(),
+            db.last_packet_in_at().unwrap()
+        );
+        assert_eq!(db.text_messages_in_since(0, 0).unwrap(), 1);
+        assert_eq!(db.text_messages_in_since(1, 0).unwrap(), 0);
 
-        db.upsert_node(0x12345678, "ABCD", "Alice's Node", false)
+        db.set_alert_fired("Quiet", 0, Some(100)).unwrap();
+        db.set_alert_fired("Battery", 0x11111111, Some(100))
             .unwrap();
-
-        let nodes = db.get_all_nodes().unwrap();
-        assert_eq!(nodes.len(), 1);
-        assert_eq!(nodes[0].node_id, 0x12345678);
-        assert_eq!(nodes[0].short_name, "ABCD");
-        assert_eq!(nodes[0].long_name, "Alice's Node");
+        db.set_alert_fired("Battery", 0x22222222, Some(100))
+            .unwrap();
+        db.set_alert_fired("Battery", 0x11111111, None).unwrap();
+        assert_eq!(db.alert_firing_nodes("Quiet").unwrap(), vec![0]);
+        assert_eq!(db.alert_firing_nodes("Battery").unwrap(), vec![0x22222222]);
+        db.purge_node_data(0x22222222).unwrap();
+        assert!(db.alert_firing_nodes("Battery").unwrap().is_empty());
     }
 
     #[test]
-    fn test_is_node_new() {
-        let db = setup_db();
-
-        assert!(db.is_node_new(0x12345678).unwrap());
+    fn test_rf_nodes_last_heard_between() {
+        let db = Db::open(Path::new(":memory:")).unwrap();
+        db.upsert_node(0x11111111, "A", "Alpha", false).unwrap();
+        db.upsert_node(0x22222222, "", "", false).unwrap();
+        db.upsert_node(0x33333333, "M", "Mqtt", true).unwrap();
+        {
+            let conn = db.conn.lock().unwrap();
+            conn.execute("UPDATE nodes SET last_seen = 100", [])
+                .unwrap();
+            conn.execute(
+                "UPDATE nodes SET last_seen = 200 WHERE node_id = ?1",
+                params![0x22222222i64],
+            )
+            .unwrap();
+        }
+        assert_eq!(
+            db.rf_nodes_last_heard_between(50, 150).unwrap(),
+            vec![LastHeardNode {
+                node_id: 0x11111111,
+                name: "Alpha".to_string(),
+                last_seen: 100
+            }]
+        );
+        assert_eq!(
+            db.rf_nodes_last_heard_between(100, 200).unwrap(),
+            vec![LastHeardNode {
+                node_id: 0x22222222,
+                name: "!22222222".to_string(),
+                last_seen: 200
+            }]
+        );
+    }
 
-        db.upsert_node(0x12345678, "ABCD", "Alice", false).unwrap();
+    #[test]
+    fn test_update_node_liveness() {
+        let db = Db::open(Path::new(":memory:")).unwrap();
+        db.upsert_node(0x11111111, "A", "Alpha", false).unwrap();
+        db.upsert_node(0x22222222, "M", "Mqtt", true).unwrap();
+        {
+            let conn = db.conn.lock().unwrap();
+            conn.execute("UPDATE nodes SET last_seen = 100", [])
+                .unwrap();
+            conn.execute(
+                "UPDATE nodes SET last_seen = 200 WHERE node_id = ?1",
+                params![0x22222222i64],
+            )
+            .unwrap();
+        }
+        assert_eq!(
+            db.update_node_liveness(150).unwrap(),
+            vec![LivenessChange {
+                node_id: 0x11111111,
+                online: false,
+                last_seen: 100,
+                via_mqtt: false
+            }]
+        );
+        // Only changes are reported
+        assert!(db.update_node_liveness(150).unwrap().is_empty());
+        let online: Vec<(String, bool)> = db
+            .dashboard_nodes(0, MqttFilter::All)
+            .unwrap()
+            .into_iter()
+            .map(|n| (n.node_id, n.online))
+            .collect();
+        assert!(online.contains(&("!11111111".to_string(), false)));
+        assert!(online.contains(&("!22222222".to_string(), true)));
 
-        assert!(!db.is_node_new(0x12345678).unwrap());
+        assert_eq!(
+            db.update_node_liveness(50).unwrap(),
+            vec![LivenessChange {
+                node_id: 0x11111111,
+                online: true,
+                last_seen: 100,
+                via_mqtt: false
+            }]
+        );
     }
 
     #[test]
-    fn test_get_node_name_long() {
+    fn test_bridge_log() {
         let db = setup_db();
-        db.upsert_node(0x12345678, "ABCD", "Alice's Node", false)
+        let relayed = db
+            .log_bridge_relay(
+                "telegram",
+                "to_bridge",
+                "message",
+                "!11111111",
+                0,
+                "hello",
+                Some(42),
+            )
             .unwrap();
+        let sent = db
+            .log_bridge_relay("telegram", "to_mesh", "message", "alice", 1, "hi", None)
+            .unwrap();
+        db.log_bridge_relay("discord", "to_mesh", "message", "bob", 0, "yo", None)
+            .unwrap();
+        db.set_bridge_log_packet(sent, 7).unwrap();
+        // Later chunks don't replace the first packet
+        db.set_bridge_log_packet(sent, 8).unwrap();
+
+        let log = db.bridge_log("telegram", 24, 10).unwrap();
+        assert_eq!(log.len(), 2);
+        assert_eq!(log[0].id, sent);
+        assert_eq!(log[0].source, "alice");
+        assert_eq!(log[0].mesh_packet_id, Some(7));
+        assert_eq!(log[1].id, relayed);
+        assert_eq!(log[1].direction, "to_bridge");
+        assert_eq!(log[1].mesh_packet_id, Some(42));
+        assert_eq!(db.bridge_log("telegram", 0, 1).unwrap().len(), 1);
+        assert!(db.bridge_log("signal", 0, 10).unwrap().is_empty());
 
-        let name = db.get_node_name(0x12345678).unwrap();
-        assert_eq!(name, "Alice's Node");
+        {
+            let conn = db.conn.lock().unwrap();
+            conn.execute("UPDATE bridge_log SET timestamp = 100", [])
+                .unwrap();
+        }
+        assert_eq!(db.prune_bridge_log_older_than(86400).unwrap(), 3);
     }
 
     #[test]
-    fn test_get_node_name_short_fallback() {
+    fn test_query_packets() {
         let db = setup_db();
-        db.upsert_node(0x12345678, "ABCD", "", false).unwrap();
+        db.upsert_node(0xaaaa0001, "RDGE", "Ridge", false).unwrap();
+        let log = |from: u32, to: Option<u32>, direction: &str, via_mqtt: bool, t: PacketType| {
+            db.log_packet(
+                from, to, 0, "", direction, via_mqtt, None, None, None, None, t,
+            )
+            .unwrap();
+        };
+        log(0xaaaa0001, None, "in", false, PacketType::Position);
+        log(0xaaaa0001, Some(0xbbbb0002), "in", false, PacketType::Text);
+        log(0xaaaa0002, Some(0xbbbb0002), "in", true, PacketType::Text);
+        log(
+            0xbbbb0002,
+            Some(0xaaaa0001),
+            "out",
+            false,
+            PacketType::Traceroute,
+        );
+        let query = |f: &dyn Fn(&mut PacketQuery)| {
+            let mut query = PacketQuery {
+                from_node: None,
+                to_node: None,
+                packet_types: None,
+                direction: None,
+                mqtt: MqttFilter::All,
+                since: None,
+                limit: 10,
+                offset: 0,
+            };
+            f(&mut query);
+            db.query_packets(&query).unwrap()
+        };
 
-        let name = db.get_node_name(0x12345678).unwrap();
-        assert_eq!(name, "ABCD");
+        let all = query(&|_| {});
+        assert_eq!(all.len(), 4);
+        assert_eq!(all[0].packet_type, "traceroute");
+        assert_eq!(all[3].from_short_name, "RDGE");
+        assert_eq!(query(&|q| q.from_node = Some(0xaaaa0001)).len(), 2);
+        let to = query(&|q| q.to_node = Some(0xbbbb0002));
+        assert_eq!(to.len(), 2);
+        assert_eq!(to[0].to_node.as_deref(), Some("!bbbb0002"));
+        assert_eq!(query(&|q| q.direction = Some("out".to_string())).len(), 1);
+        assert_eq!(query(&|q| q.mqtt = MqttFilter::MqttOnly).len(), 1);
+        let text_or_position = query(&|q| {
+            q.packet_types = Some(vec![PacketType::Text, PacketType::Position]);
+            q.mqtt = MqttFilter::LocalOnly;
+        });
+        assert_eq!(text_or_position.len(), 2);
+        assert!(query(&|q| q.packet_types = Some(vec![])).is_empty());
+        assert!(query(&|q| q.since = Some(Utc::now().timestamp() + 60)).is_empty());
+        // A quote in a filter value is data, not SQL
+        assert!(query(&|q| q.direction = Some("in' OR '1'='1".to_string())).is_empty());
+
+        let page = query(&|q| {
+            q.limit = 3;
+            q.offset = 2;
+        });
+        assert_eq!(page.len(), 2);
+        assert_eq!(page[0].id, all[2].id);
+
+        // Recipient lookups don't scan the whole table
+        let conn = db.conn.lock().unwrap();
+        let plan: Vec<String> = conn
+            .prepare(
+                "EXPLAIN QUERY PLAN SELECT id FROM packets WHERE to_node = 1 AND timestamp > 0",
+            )
+            .unwrap()
+            .query_map([], |row| row.get(3))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert!(
+            plan.iter().any(|p| p.contains("idx_packets_to_node_time")),
+            "{:?}",
+            plan
+        );
     }
 
     #[test]
-    fn test_get_node_name_hex_fallback() {
+    fn test_settings() {
         let db = setup_db();
-        db.upsert_node(0x12345678, "", "", false).unwrap();
-
-        let name = db.get_node_name(0x12345678).unwrap();
-        assert_eq!(name, "!12345678");
+        db.set_setting("welcome.message", Some("\"Hi\"")).unwrap();
+        db.set_setting("welcome.enabled", Some("false")).unwrap();
+        db.set_setting("welcome.enabled", Some("true")).unwrap();
+        assert_eq!(
+            db.settings().unwrap(),
+            vec![
+                ("welcome.enabled".to_string(), "true".to_string()),
+                ("welcome.message".to_string(), "\"Hi\"".to_string()),
+            ]
+        );
+        db.set_setting("welcome.message", None).unwrap();
+        assert_eq!(db.settings().unwrap().len(), 1);
     }
 
     #[test]
-    fn test_get_node_name_unknown() {
+    fn test_muted_nodes() {
         let db = setup_db();
-        let name = db.get_node_name(0x99999999).unwrap();
-        assert_eq!(name, "!99999999");
+        assert!(!db.is_muted(0x11111111).unwrap());
+        assert!(db.set_muted(0x11111111, true).unwrap());
+        assert!(!db.set_muted(0x11111111, true).unwrap());
+        assert!(db.is_muted(0x11111111).unwrap());
+        assert!(!db.is_muted(0x22222222).unwrap());
+        assert!(db.set_muted(0x11111111, false).unwrap());
+        assert!(!db.set_muted(0x11111111, false).unwrap());
+        assert!(!db.is_muted(0x11111111).unwrap());
     }
 
     #[test]
-    fn test_purge_nodes_not_seen_within() {
+    fn test_record_weather_alert() {
         let db = setup_db();
-        db.upsert_node(0xAAAAAAAA, "A", "Alice", false).unwrap();
-        db.upsert_node(0xBBBBBBBB, "B", "Bob", false).unwrap();
-
         let now = Utc::now().timestamp();
-        let stale_ts = now - (8 * 24 * 3600);
-        let recent_ts = now - (2 * 24 * 3600);
+        assert!(db.record_weather_alert("a", &[], Some(now + 3600)).unwrap());
+        assert!(!db.record_weather_alert("a", &[], Some(now + 3600)).unwrap());
+        // An update of a known alert isn't news, and is itself remembered
+        assert!(!db
+            .record_weather_alert("b", &["a".to_string()], Some(now - 60))
+            .unwrap());
+        assert!(!db.record_weather_alert("b", &[], None).unwrap());
+        assert!(db
+            .record_weather_alert("c", &["x".to_string()], None)
+            .unwrap());
+
         {
             let conn = db.conn.lock().unwrap();
             conn.execute(
-                "UPDATE nodes SET last_seen = ?1 WHERE node_id = ?2",
-                params![stale_ts, 0xAAAAAAAAu32 as i64],
-            )
-            .unwrap();
-            conn.execute(
-                "UPDATE nodes SET last_seen = ?1 WHERE node_id = ?2",
-                params![recent_ts, 0xBBBBBBBBu32 as i64],
+                "UPDATE weather_alerts SET received_at = 100 WHERE alert_id = 'c'",
+                [],
             )
             .unwrap();
         }
-
-        let purged = db.purge_nodes_not_seen_within(7 * 24 * 3600).unwrap();
-        assert_eq!(purged, 1);
-
-        let nodes = db.get_all_nodes().unwrap();
-        assert_eq!(nodes.len(), 1);
-        assert_eq!(nodes[0].node_id, 0xBBBBBBBB);
+        // "b" now has no expiry but was just received; "c" is old
+        assert_eq!(db.prune_weather_alerts(86400).unwrap(), 1);
+        assert!(!db.record_weather_alert("a", &[], None).unwrap());
+        assert!(db.record_weather_alert("c", &[], None).unwrap());
     }
 
     #[test]
-    fn test_find_node_by_hex_id() {
+    fn test_mail_store_and_take_unread() {
         let db = setup_db();
-        db.upsert_node(0xaabbccdd, "ABCD", "Alice", false).unwrap();
-
-        assert_eq!(db.find_node_by_name("!aabbccdd").unwrap(), Some(0xaabbccdd));
-        assert_eq!(db.find_node_by_name("aabbccdd").unwrap(), Some(0xaabbccdd));
+        db.store_mail(0x11111111, 0x22222222, "first").unwrap();
+        db.store_mail(0x11111111, 0x22222222, "second").unwrap();
+        assert_eq!(db.unread_mail_count(0x22222222).unwrap(), 2);
+
+        let mails = db.take_unread_mail(0x22222222, 1, false).unwrap();
+        assert_eq!(mails.len(), 1);
+        assert_eq!(mails[0].body, "first");
+        assert_eq!(mails[0].from_node, 0x11111111);
+        assert_eq!(db.unread_mail_count(0x22222222).unwrap(), 1);
     }
 
     #[test]
-    fn test_find_node_by_decimal_id() {
+    fn test_mail_read_receipts() {
         let db = setup_db();
-        // Use a number with digits > 9 to avoid hex ambiguity
-        db.upsert_node(3954221518, "ABCD", "Alice", false).unwrap();
+        db.store_mail(0x11111111, 0x22222222, "no receipt").unwrap();
+        db.take_unread_mail(0x22222222, 10, false).unwrap();
+        let first = db.store_mail(0x11111111, 0x22222222, "first").unwrap();
+        let second = db.store_mail(0x11111111, 0x33333333, "second").unwrap();
+        db.store_mail(0x44444444, 0x22222222, "other sender")
+            .unwrap();
+        assert!(db.take_mail_receipts(0x11111111).unwrap().is_empty());
 
+        db.take_unread_mail(0x22222222, 10, true).unwrap();
+        db.take_unread_mail(0x33333333, 10, true).unwrap();
         assert_eq!(
-            db.find_node_by_name("3954221518").unwrap(),
-            Some(3954221518)
+            db.take_mail_receipts(0x11111111).unwrap(),
+            vec![(first, 0x22222222), (second, 0x33333333)]
         );
+        // Receipts are handed out only once
+        assert!(db.take_mail_receipts(0x11111111).unwrap().is_empty());
+        assert_eq!(db.take_mail_receipts(0x44444444).unwrap().len(), 1);
     }
 
     #[test]
-    fn test_find_node_by_name() {
+    fn test_mail_expiry_and_sender_notice() {
         let db = setup_db();
-        db.upsert_node(0x12345678, "ABCD", "Alice", false).unwrap();
+        db.store_mail(0x11111111, 0x22222222, "old").unwrap();
+        db.store_mail(0x11111111, 0x22222222, "older").unwrap();
+        db.store_mail(0x11111111, 0x33333333, "fresh").unwrap();
+        {
+            let conn = db.conn.lock().unwrap();
+            let old = Utc::now().timestamp() - 10 * 86400;
+            conn.execute(
+                "UPDATE mail SET timestamp = ?1 WHERE to_node = ?2",
+                params![old, 0x22222222i64],
+            )
+            .unwrap();
+        }
 
-        assert_eq!(db.find_node_by_name("Alice").unwrap(), Some(0x12345678));
-        assert_eq!(db.find_node_by_name("alice").unwrap(), Some(0x12345678)); // case insensitive
-        assert_eq!(db.find_node_by_name("ABCD").unwrap(), Some(0x12345678));
-    }
+        assert_eq!(db.expire_unread_mail(7 * 86400).unwrap(), 2);
+        assert_eq!(db.unread_mail_count(0x22222222).unwrap(), 0);
+        assert_eq!(db.unread_mail_count(0x33333333).unwrap(), 1);
 
-    #[test]
-    fn test_find_node_not_found() {
-        let db = setup_db();
-        assert_eq!(db.find_node_by_name("Unknown").unwrap(), None);
+        let notices = db.take_expired_mail_for_sender(0x11111111).unwrap();
+        assert_eq!(notices, vec![(0x22222222, 2)]);
+        // Notices are handed out only once
+        assert!(db
+            .take_expired_mail_for_sender(0x11111111)
+            .unwrap()
+            .is_empty());
     }
 
     #[test]
-    fn test_get_recent_nodes_with_last_hop() {
+    fn test_away_status_lifecycle() {
         let db = setup_db();
-        db.upsert_node(0xAAAAAAAA, "A", "Alice", false).unwrap();
-        db.upsert_node(0xBBBBBBBB, "B", "Bob", false).unwrap();
+        db.upsert_node(0x11111111, "AWAY", "Away Node", false)
+            .unwrap();
+        db.set_away(0x11111111, "Gone fishing").unwrap();
 
-        db.log_packet(
-            0xAAAAAAAA,
-            None,
-            0,
-            "a1",
-            "in",
-            false,
-            Some(-80),
-            Some(5.0),
-            Some(2),
-            Some(7),
-            "text",
-        )
-        .unwrap();
-        db.log_packet(
-            0xAAAAAAAA,
-            None,
-            0,
-            "a2",
-            "in",
-            false,
-            Some(-78),
-            Some(5.2),
-            Some(4),
-            Some(7),
-            "text",
-        )
-        .unwrap();
+        let away = db.get_away_nodes().unwrap();
+        assert_eq!(away.len(), 1);
+        assert_eq!(away[0].long_name, "Away Node");
+        assert_eq!(away[0].message, "Gone fishing");
 
-        let nodes = db.get_recent_nodes_with_last_hop(10).unwrap();
-        assert_eq!(nodes.len(), 2);
-        let limited = db.get_recent_nodes_with_last_hop(1).unwrap();
-        assert_eq!(limited.len(), 1);
+        assert!(db.mark_away_reply(0x11111111, 0x22222222).unwrap());
+        assert!(!db.mark_away_reply(0x11111111, 0x22222222).unwrap());
 
-        let alice = nodes.iter().find(|n| n.node_id == 0xAAAAAAAA).unwrap();
-        let bob = nodes.iter().find(|n| n.node_id == 0xBBBBBBBB).unwrap();
-        assert_eq!(alice.last_hop, Some(4));
-        assert_eq!(bob.last_hop, None);
+        // A new away message resets one-time replies
+        db.set_away(0x11111111, "Back Monday").unwrap();
+        assert!(db.mark_away_reply(0x11111111, 0x22222222).unwrap());
+
+        assert_eq!(db.clear_away(0x11111111).unwrap(), Some(1));
+        assert_eq!(db.clear_away(0x11111111).unwrap(), None);
+        assert!(db.get_away_nodes().unwrap().is_empty());
     }
 
     #[test]
-    fn test_recent_rf_node_missing_hops() {
+    fn test_node_notes() {
         let db = setup_db();
-        db.upsert_node(0xAAAAAAAA, "A", "Alice", false).unwrap();
-        db.upsert_node(0xBBBBBBBB, "B", "Bob", false).unwrap();
+        let target = 0x11111111;
+        let admin = 0x22222222;
+        db.upsert_node(target, "RPT", "Repeater", false).unwrap();
+        db.upsert_node(admin, "ADM", "Admin", false).unwrap();
 
-        // Bob already has hop metadata
-        db.log_packet(
-            0xBBBBBBBB,
-            None,
-            0,
-            "hi",
-            "in",
-            false,
-            Some(-80),
-            Some(5.0),
-            Some(2),
-            Some(3),
-            "text",
-        )
-        .unwrap();
+        db.add_node_note(target, admin, "solar panel replaced")
+            .unwrap();
+        db.add_node_note(target, 0x33333333, "battery low in winter")
+            .unwrap();
 
-        let candidate = db
-            .recent_rf_nodes_missing_hops(3600, None, 1)
-            .unwrap()
-            .into_iter()
-            .next();
-        assert_eq!(candidate, Some(0xAAAAAAAA));
+        let notes = db.get_node_notes(target, 10).unwrap();
+        assert_eq!(notes.len(), 2);
+        assert_eq!(notes[0].note, "battery low in winter");
+        assert_eq!(notes[0].author, "!33333333");
+        assert_eq!(notes[1].author, "Admin");
+        assert_eq!(db.get_node_notes(target, 1).unwrap().len(), 1);
+
+        let nodes = db.dashboard_nodes(0, MqttFilter::All).unwrap();
+        let node = nodes.iter().find(|n| n.node_id == "!11111111").unwrap();
+        assert_eq!(node.latest_note.as_deref(), Some("battery low in winter"));
+
+        db.purge_node_data(target).unwrap();
+        assert!(db.get_node_notes(target, 10).unwrap().is_empty());
     }
 
     #[test]
-    fn test_recent_rf_node_missing_hops_excludes_node() {
+    fn test_poll_single_vote_and_tally() {
         let db = setup_db();
-        db.upsert_node(0xAAAAAAAA, "A", "Alice", false).unwrap();
-        db.upsert_node(0xBBBBBBBB, "B", "Bob", false).unwrap();
+        let options = vec!["A".to_string(), "B".to_string()];
+        let id = db
+            .create_poll("Net night?", &options, 0, 0x11111111, None)
+            .unwrap();
 
-        let candidate = db
-            .recent_rf_nodes_missing_hops(3600, Some(0xAAAAAAAA), 1)
-            .unwrap()
-            .into_iter()
-            .next();
-        assert_eq!(candidate, Some(0xBBBBBBBB));
+        let poll = db.get_open_poll().unwrap().unwrap();
+        assert_eq!(poll.id, id);
+        assert_eq!(poll.options, options);
+
+        assert!(db.cast_poll_vote(id, 0x22222222, 1).unwrap());
+        assert!(!db.cast_poll_vote(id, 0x22222222, 0).unwrap());
+        assert!(db.cast_poll_vote(id, 0x33333333, 1).unwrap());
+        assert_eq!(db.poll_tally(id, 2).unwrap(), vec![0, 2]);
+
+        db.close_poll(id).unwrap();
+        assert!(db.get_open_poll().unwrap().is_none());
     }
 
     #[test]
-    fn test_recent_rf_nodes_missing_hops_returns_multiple_in_recency_order() {
-        let db = setup_db();
-        db.upsert_node(0xAAAAAAAA, "A", "Alice", false).unwrap();
-        db.upsert_node(0xBBBBBBBB, "B", "Bob", false).unwrap();
-        db.upsert_node(0xCCCCCCCC, "C", "Carol", false).unwrap();
-
-        let now = Utc::now().timestamp();
-        {
-            let conn = db.conn.lock().unwrap();
-            conn.execute(
-                "UPDATE nodes SET last_seen = ?1 WHERE node_id = ?2",
-                params![now - 30, 0xAAAAAAAAu32 as i64],
-            )
-            .unwrap();
-            conn.execute(
-                "UPDATE nodes SET last_seen = ?1 WHERE node_id = ?2",
-                params![now - 10, 0xBBBBBBBBu32 as i64],
-            )
-            .unwrap();
-            conn.execute(
-                "UPDATE nodes SET last_seen = ?1 WHERE node_id = ?2",
-                params![now - 20, 0xCCCCCCCCu32 as i64],
-            )
+    fn test_take_expired_polls() {
+        let db = setup_db();
+        let options = vec!["A".to_string(), "B".to_string()];
+        let open = db.create_poll("Open?", &options, 0, 1, None).unwrap();
+        let timed = db
+            .create_poll("Timed?", &options, 2, 1, Some(1000))
             .unwrap();
-        }
 
-        let candidates = db.recent_rf_nodes_missing_hops(3600, None, 2).unwrap();
-        assert_eq!(candidates, vec![0xBBBBBBBB, 0xCCCCCCCC]);
+        assert!(db.take_expired_polls(999).unwrap().is_empty());
+        let expired = db.take_expired_polls(1000).unwrap();
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].id, timed);
+        assert_eq!(expired[0].channel, 2);
+        assert_eq!(expired[0].closed_at, Some(1000));
+        assert!(db.take_expired_polls(2000).unwrap().is_empty());
+
+        assert_eq!(db.get_open_poll().unwrap().unwrap().id, open);
+        let latest = db.get_latest_poll().unwrap().unwrap();
+        assert_eq!((latest.id, latest.closed_at), (timed, Some(1000)));
     }
 
-    // --- Position tests ---
-
     #[test]
-    fn test_update_and_get_position() {
+    fn test_net_checkins_and_roster() {
         let db = setup_db();
-        db.upsert_node(0x12345678, "ABCD", "Alice", false).unwrap();
-        db.update_position(0x12345678, 25.0, 121.0).unwrap();
+        let start = Utc::now().timestamp() - 600;
+        let end = start + 3600;
 
-        let pos = db.get_node_position(0x12345678).unwrap();
-        assert_eq!(pos, Some((25.0, 121.0)));
+        assert_eq!(
+            db.net_checkin("Weekly", 0, start, end, 0x11111111, "K1ABC", None)
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            db.net_checkin(
+                "Weekly",
+                0,
+                start,
+                end,
+                0x22222222,
+                "W2XYZ",
+                Some("portable")
+            )
+            .unwrap(),
+            2
+        );
+        // Checking in again updates the entry instead of adding one
+        assert_eq!(
+            db.net_checkin("Weekly", 0, start, end, 0x11111111, "K1ABC", Some("mobile"))
+                .unwrap(),
+            2
+        );
+
+        // Still running: nothing to post
+        assert!(db.take_finished_nets(end - 1).unwrap().is_empty());
+
+        let finished = db.take_finished_nets(end).unwrap();
+        assert_eq!(finished.len(), 1);
+        assert_eq!(finished[0].checkins.len(), 2);
+        assert_eq!(finished[0].checkins[0].comment.as_deref(), Some("mobile"));
+        assert!(db.take_finished_nets(end).unwrap().is_empty());
+
+        assert_eq!(db.recent_nets(10).unwrap().len(), 1);
     }
 
     #[test]
-    fn test_get_position_none() {
+    fn test_log_incident_dedups_same_packet() {
         let db = setup_db();
-        db.upsert_node(0x12345678, "ABCD", "Alice", false).unwrap();
+        db.upsert_node(0x11111111, "HIKR", "Hiker", false).unwrap();
 
-        let pos = db.get_node_position(0x12345678).unwrap();
-        assert_eq!(pos, None);
+        let first = db
+            .log_incident(
+                0x11111111,
+                42,
+                0,
+                "sos",
+                "SOS broken leg",
+                Some((25.0, 121.5)),
+            )
+            .unwrap();
+        assert!(first.is_some());
+        // Same packet heard again (e.g. via MQTT)
+        assert!(db
+            .log_incident(
+                0x11111111,
+                42,
+                0,
+                "sos",
+                "SOS broken leg",
+                Some((25.0, 121.5))
+            )
+            .unwrap()
+            .is_none());
+
+        let incidents = db.recent_incidents(10).unwrap();
+        assert_eq!(incidents.len(), 1);
+        assert_eq!(incidents[0].node_name, "Hiker");
+        assert_eq!(incidents[0].latitude, Some(25.0));
     }
 
     #[test]
-    fn test_get_position_zero_is_none() {
+    fn test_selftest_responses_and_report() {
         let db = setup_db();
-        db.upsert_node(0x12345678, "ABCD", "Alice", false).unwrap();
-        db.update_position(0x12345678, 0.0, 0.0).unwrap();
+        db.upsert_node(0x11111111, "AAAA", "Alpha", false).unwrap();
+        db.upsert_node(0x22222222, "BBBB", "Bravo", false).unwrap();
+        db.upsert_node(0x33333333, "CCCC", "Cloud", true).unwrap();
 
-        let pos = db.get_node_position(0x12345678).unwrap();
-        assert_eq!(pos, None); // 0,0 is treated as no position
+        db.record_selftest_run(500, 2).unwrap();
+        assert!(db
+            .record_selftest_response(500, 0x11111111, "ack", 300)
+            .unwrap());
+        // A node is counted once per test, and unknown packets are ignored
+        assert!(!db
+            .record_selftest_response(500, 0x11111111, "rebroadcast", 300)
+            .unwrap());
+        assert!(!db
+            .record_selftest_response(501, 0x22222222, "ack", 300)
+            .unwrap());
+        assert_eq!(db.node_for_relay_byte(0x22).unwrap(), Some(0x22222222));
+        assert_eq!(db.node_for_relay_byte(0x33).unwrap(), None);
+
+        let report = db.selftest_report(0).unwrap();
+        assert_eq!(report.runs, 1);
+        // MQTT-only nodes are not expected to answer
+        assert_eq!(report.nodes.len(), 2);
+        assert_eq!(report.nodes[0].node_name, "Alpha");
+        assert_eq!(report.nodes[0].responses, 1);
+        assert_eq!(report.nodes[1].responses, 0);
     }
 
-    // --- Packet logging tests ---
-
     #[test]
-    fn test_message_count() {
+    fn test_api_call_quota() {
         let db = setup_db();
+        assert!(db.try_consume_api_call("weather", 2).unwrap());
+        assert!(db.try_consume_api_call("weather", 2).unwrap());
+        assert!(!db.try_consume_api_call("weather", 2).unwrap());
+        // Unlimited APIs are still counted
+        for _ in 0..3 {
+            assert!(db.try_consume_api_call("geocoding", 0).unwrap());
+        }
 
-        assert_eq!(db.message_count("in").unwrap(), 0);
-        assert_eq!(db.message_count("out").unwrap(), 0);
+        let usage = db.api_usage_today().unwrap();
+        assert_eq!(usage.len(), 2);
+        assert_eq!((usage[0].api.as_str(), usage[0].calls), ("geocoding", 3));
+        assert_eq!((usage[1].api.as_str(), usage[1].calls), ("weather", 2));
+    }
 
+    #[test]
+    fn test_evict_nodes_over_cap() {
+        let db = setup_db();
+        for (i, node) in [0xA1u32, 0xA2, 0xA3, 0xA4, 0xA5].iter().enumerate() {
+            db.upsert_node(*node, "", "", true).unwrap();
+            let conn = db.conn.lock().unwrap();
+            conn.execute(
+                "UPDATE nodes SET last_seen = ?1 WHERE node_id = ?2",
+                params![1_000 + i as i64, *node as i64],
+            )
+            .unwrap();
+        }
+        // Oldest node heard over RF once, second oldest annotated
         db.log_packet(
-            0x12345678,
-            None,
-            0,
-            "Hello",
-            "in",
-            false,
-            Some(-80),
-            Some(5.0),
-            Some(1),
-            Some(3),
-            "text",
-        )
-        .unwrap();
-        db.log_packet(
-            0x12345678,
+            0xA1,
             None,
             0,
-            "World",
+            "",
             "in",
             false,
-            Some(-90),
-            Some(3.0),
-            Some(2),
-            Some(3),
-            "text",
-        )
-        .unwrap();
-        db.log_packet(
-            0x12345678,
-            Some(0xaaaaaaaa),
-            0,
-            "Reply",
-            "out",
-            false,
             None,
             None,
             None,
             None,
-            "text",
+            PacketType::Text,
         )
         .unwrap();
-
-        assert_eq!(db.message_count("in").unwrap(), 2);
-        assert_eq!(db.message_count("out").unwrap(), 1);
-    }
-
-    #[test]
-    fn test_node_count() {
-        let db = setup_db();
-
-        assert_eq!(db.node_count().unwrap(), 0);
-
-        db.upsert_node(0xAAAAAAAA, "A", "Alice", false).unwrap();
-        assert_eq!(db.node_count().unwrap(), 1);
-
-        db.upsert_node(0xBBBBBBBB, "B", "Bob", false).unwrap();
-        assert_eq!(db.node_count().unwrap(), 2);
-
-        // Upsert same node doesn't increase count
-        db.upsert_node(0xAAAAAAAA, "A", "Alice Updated", false)
+        db.add_node_note(0xA2, 0x11111111, "repeater").unwrap();
+        // Later only relayed through MQTT, and its RF packet pruned
+        db.upsert_node(0xA1, "", "", true).unwrap();
+        db.conn
+            .lock()
+            .unwrap()
+            .execute("UPDATE packets SET timestamp = 0", [])
+            .unwrap();
+        assert_eq!(
+            db.prune_packets_older_than(PacketType::Text, 3600).unwrap(),
+            1
+        );
+        db.conn
+            .lock()
+            .unwrap()
+            .execute("UPDATE nodes SET last_seen = 1000 WHERE node_id = 0xA1", [])
             .unwrap();
-        assert_eq!(db.node_count().unwrap(), 2);
-    }
-
-    // --- Upsert behavior tests ---
 
-    #[test]
-    fn test_upsert_updates_existing() {
-        let db = setup_db();
+        assert_eq!(db.evict_nodes_over(0, &[]).unwrap(), 3);
+        let mut left: Vec<u32> = db
+            .get_all_nodes()
+            .unwrap()
+            .iter()
+            .map(|n| n.node_id)
+            .collect();
+        left.sort();
+        assert_eq!(left, vec![0xA1, 0xA2]);
 
-        db.upsert_node(0x12345678, "OLD", "Old Name", false)
-            .unwrap();
-        db.upsert_node(0x12345678, "NEW", "New Name", false)
+        db.upsert_node(0xA6, "", "", true).unwrap();
+        db.conn
+            .lock()
+            .unwrap()
+            .execute("UPDATE nodes SET last_seen = 2000 WHERE node_id = 0xA6", [])
             .unwrap();
-
-        let nodes = db.get_all_nodes().unwrap();
-        assert_eq!(nodes.len(), 1);
-        assert_eq!(nodes[0].short_name, "NEW");
-        assert_eq!(nodes[0].long_name, "New Name");
+        db.upsert_node(0xA7, "", "", true).unwrap();
+        assert_eq!(db.evict_nodes_over(3, &[]).unwrap(), 1);
+        assert!(db.is_node_new(0xA6).unwrap());
+        assert!(!db.is_node_new(0xA7).unwrap());
+        assert_eq!(db.evict_nodes_over(3, &[]).unwrap(), 0);
     }
 
     #[test]
-    fn test_upsert_preserves_nonempty_names() {
+    fn test_evict_nodes_keeps_admins() {
         let db = setup_db();
+        let admin = 0xB1;
+        db.upsert_node(admin, "ADM", "Admin", true).unwrap();
+        assert!(db.remember_public_key(admin, &[7u8; 32]).unwrap());
+        db.upsert_node(0xB2, "", "", true).unwrap();
+        db.conn
+            .lock()
+            .unwrap()
+            .execute("UPDATE nodes SET last_seen = 1000", [])
+            .unwrap();
 
-        db.upsert_node(0x12345678, "ABCD", "Alice", false).unwrap();
-        db.upsert_node(0x12345678, "", "", false).unwrap(); // Empty names shouldn't overwrite
-
-        let nodes = db.get_all_nodes().unwrap();
-        assert_eq!(nodes[0].short_name, "ABCD");
-        assert_eq!(nodes[0].long_name, "Alice");
+        assert_eq!(db.evict_nodes_over(0, &[admin]).unwrap(), 1);
+        assert_eq!(db.get_public_key(admin).unwrap(), Some(vec![7u8; 32]));
+        assert!(db.is_node_new(0xB2).unwrap());
     }
 
     #[test]
-    fn test_upsert_via_mqtt() {
+    fn test_purge_node_data() {
         let db = setup_db();
+        let target = 0x11111111;
+        let other = 0x22222222;
+        db.upsert_node(target, "TGT", "Target", false).unwrap();
+        db.upsert_node(other, "OTH", "Other", false).unwrap();
+        db.update_position(target, 25.0, 121.0).unwrap();
 
-        db.upsert_node(0x12345678, "ABCD", "Alice", false).unwrap();
-        let nodes = db.dashboard_nodes(24, MqttFilter::All).unwrap();
-        assert!(!nodes[0].via_mqtt);
-
-        db.upsert_node(0x12345678, "ABCD", "Alice", true).unwrap();
-        let nodes = db.dashboard_nodes(24, MqttFilter::All).unwrap();
-        assert!(nodes[0].via_mqtt);
-    }
-
-    // --- Dashboard query tests ---
-
-    #[test]
-    fn test_dashboard_overview() {
-        let db = setup_db();
-        db.upsert_node(0xAAAAAAAA, "A", "Alice", false).unwrap();
-        db.upsert_node(0xBBBBBBBB, "B", "Bob", false).unwrap();
         db.log_packet(
-            0xAAAAAAAA,
+            target,
             None,
             0,
-            "Hello",
+            "hi",
             "in",
             false,
-            Some(-80),
-            Some(5.0),
-            Some(1),
-            Some(3),
-            "text",
-        )
-        .unwrap();
-        db.log_packet(
-            0xBBBBBBBB,
             None,
-            0,
-            "Hi",
-            "in",
-            true,
-            Some(-70),
-            Some(8.0),
-            Some(0),
-            Some(3),
-            "text",
+            None,
+            None,
+            None,
+            PacketType::Text,
         )
         .unwrap();
         db.log_packet(
-            0xAAAAAAAA,
-            Some(0xBBBBBBBB),
+            other,
+            Some(target),
             0,
-            "Reply",
-            "out",
+            "dm",
+            "in",
             false,
             None,
             None,
             None,
             None,
-            "text",
+            PacketType::Text,
         )
         .unwrap();
-        // Non-text packet
         db.log_packet(
-            0xAAAAAAAA,
+            other,
             None,
             0,
-            "",
+            "keep",
             "in",
             false,
-            Some(-75),
-            Some(6.0),
-            Some(1),
-            Some(3),
-            "position",
+            None,
+            None,
+            None,
+            None,
+            PacketType::Text,
         )
         .unwrap();
-
-        let overview = db
-            .dashboard_overview(24, MqttFilter::All, "TestBot")
-            .unwrap();
-        assert_eq!(overview.node_count, 2);
-        assert_eq!(overview.messages_in, 2);
-        assert_eq!(overview.messages_out, 1);
-        assert_eq!(overview.packets_in, 3); // 2 text + 1 position
-        assert_eq!(overview.packets_out, 1);
-        assert_eq!(overview.bot_name, "TestBot");
-
-        let local = db
-            .dashboard_overview(24, MqttFilter::LocalOnly, "TestBot")
+        {
+            let conn = db.conn.lock().unwrap();
+            conn.execute(
+                "INSERT INTO mail (timestamp, from_node, to_node, body) VALUES (0, ?1, ?2, 'x')",
+                params![other as i64, target as i64],
+            )
             .unwrap();
-        assert_eq!(local.messages_in, 1);
-
-        let mqtt = db
-            .dashboard_overview(24, MqttFilter::MqttOnly, "TestBot")
+        }
+        let p = db
+            .log_packet_with_mesh_id(
+                other,
+                Some(0x33333333),
+                0,
+                "",
+                "in",
+                false,
+                None,
+                None,
+                Some(1),
+                Some(3),
+                Some(5),
+                PacketType::Traceroute,
+            )
             .unwrap();
-        assert_eq!(mqtt.messages_in, 1);
-    }
+        db.log_traceroute_observation(
+            p,
+            "in:22222222:33333333:5",
+            other,
+            Some(0x33333333),
+            false,
+            Some(1),
+            Some(3),
+            None,
+            None,
+            &[target],
+            &[],
+        )
+        .unwrap();
 
-    #[test]
-    fn test_third_party_traceroute_correlation_key_lookup() {
-        // Simulates the key lookup used to correlate a third-party RouteReply with
-        // the RouteRequest session that was previously observed.
-        //
-        // Scenario:
-        //   - ti6W (0x11111111) sends traceroute to arto (0x22222222), request_id=999
-        //   - We observe the RouteRequest → session key: in:11111111:22222222:999
-        //   - We observe the RouteReply (arto→ti6W, data.request_id=999)
-        //     → we try key: in:{to_node=ti6W}:{from_node=arto}:{request_id=999}
-        //     → = in:11111111:22222222:999 → matches!
-        let db = setup_db();
-        let since = chrono::Utc::now().timestamp() - 60;
+        let options = ["Yes".to_string(), "No".to_string()];
+        let started = db.create_poll("Net?", &options, 0, target, None).unwrap();
+        db.cast_poll_vote(started, other, 0).unwrap();
+        let kept = db.create_poll("Picnic?", &options, 0, other, None).unwrap();
+        db.cast_poll_vote(kept, target, 1).unwrap();
+        db.cast_poll_vote(kept, other, 0).unwrap();
+
+        db.set_muted(target, true).unwrap();
+        for (source, text) in [("!11111111", "hi"), ("!22222222", "keep")] {
+            db.log_bridge_relay("telegram", "to_bridge", "message", source, 0, text, None)
+                .unwrap();
+        }
 
+        let summary = db.purge_node_data(target).unwrap();
+        assert_eq!(summary.count("nodes"), 1);
+        assert_eq!(summary.count("packets"), 2);
+        assert_eq!(summary.count("mail"), 1);
+        assert_eq!(summary.count("traceroute_session_hops"), 1);
+        // Session between two other nodes survives, only the relay hop is removed
+        assert_eq!(summary.count("traceroute_sessions"), 0);
+        assert_eq!(summary.count("muted_nodes"), 1);
+        assert_eq!(summary.count("bridge_log"), 1);
+        assert_eq!(summary.count("polls"), 1);
+        // Their vote elsewhere, and the other node's vote on their poll
+        assert_eq!(summary.count("poll_votes"), 2);
+        assert_eq!(summary.tables.len(), NODE_DATA_TABLES.len());
+        assert!(!db.is_muted(target).unwrap());
+        let relayed = db.bridge_log("telegram", 0, 10).unwrap();
+        assert_eq!(relayed.len(), 1);
+        assert_eq!(relayed[0].text, "keep");
+
+        let nodes = db.get_all_nodes().unwrap();
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(nodes[0].node_id, other);
+        // "keep" text + traceroute packet remain
+        assert_eq!(db.message_count("in").unwrap(), 2);
+
+        // Purging again is a no-op
+        assert_eq!(db.purge_node_data(target).unwrap().total(), 0);
+    }
+
+    #[test]
+    fn test_expire_stale_traceroute_sessions() {
+        let db = setup_db();
         let p1 = db
             .log_packet_with_mesh_id(
                 0x11111111,
@@ -2204,16 +8768,13 @@ mod tests {
                 None,
                 Some(1),
                 Some(5),
-                Some(999),
-                "traceroute",
+                Some(42),
+                PacketType::Traceroute,
             )
             .unwrap();
-
-        // Insert the RouteRequest session
-        let request_key = "in:11111111:22222222:999";
         db.log_traceroute_observation(
             p1,
-            request_key,
+            "in:11111111:22222222:42",
             0x11111111,
             Some(0x22222222),
             false,
@@ -2221,33 +8782,40 @@ mod tests {
             Some(5),
             None,
             None,
-            &[0xaabbccdd],
+            &[0xa1ce0000],
             &[],
         )
         .unwrap();
 
-        // The reply correlation lookup: in:{initiator=ti6W}:{from=arto}:{request_id=999}
-        let correlation_key = format!("in:{:08x}:{:08x}:{}", 0x11111111u32, 0x22222222u32, 999);
-        assert_eq!(correlation_key, request_key);
-        assert!(db
-            .traceroute_session_exists_since(&correlation_key, since)
-            .unwrap());
+        // Fresh session is left alone
+        assert_eq!(db.expire_stale_traceroute_sessions(3600).unwrap(), 0);
 
-        // Old key for the reply (in:{arto}:{ti6W}:{reply_id}) should NOT exist
-        let old_reply_key = "in:22222222:11111111:1234";
-        assert!(!db
-            .traceroute_session_exists_since(old_reply_key, since)
-            .unwrap());
+        // Backdate the session past the expiry window
+        {
+            let conn = db.conn.lock().unwrap();
+            let old = Utc::now().timestamp() - 7200;
+            conn.execute(
+                "UPDATE traceroute_sessions SET last_seen = ?1",
+                params![old],
+            )
+            .unwrap();
+        }
+        assert_eq!(db.expire_stale_traceroute_sessions(3600).unwrap(), 1);
+        // Already expired sessions are not counted again
+        assert_eq!(db.expire_stale_traceroute_sessions(3600).unwrap(), 0);
+
+        assert!(db
+            .dashboard_traceroute_sessions(0, 10, false)
+            .unwrap()
+            .is_empty());
+        let all = db.dashboard_traceroute_sessions(0, 10, true).unwrap();
+        assert_eq!(all.len(), 1);
+        assert_eq!(all[0].status, "expired");
     }
 
     #[test]
-    fn test_third_party_traceroute_reply_merges_response_hops() {
-        // Verify that correlating a RouteReply into an existing RouteRequest session
-        // adds response hops without duplicating request hops.
+    fn test_prune_traceroute_hops_older_than() {
         let db = setup_db();
-
-        let request_key = "in:11111111:22222222:999";
-
         let p1 = db
             .log_packet_with_mesh_id(
                 0x11111111,
@@ -2258,77 +8826,41 @@ mod tests {
                 false,
                 None,
                 None,
-                Some(1),
-                Some(5),
-                Some(999),
-                "traceroute",
-            )
-            .unwrap();
-        let p2 = db
-            .log_packet_with_mesh_id(
-                0x22222222,
-                Some(0x11111111),
-                0,
-                "",
-                "in",
-                false,
-                None,
-                None,
-                Some(0),
                 Some(2),
-                Some(1000),
-                "traceroute",
+                Some(5),
+                Some(43),
+                PacketType::Traceroute,
             )
             .unwrap();
-
-        // Step 1: observe RouteRequest (ti6W → arto), relay node a1ce in route
         db.log_traceroute_observation(
             p1,
-            request_key,
+            "in:11111111:22222222:43",
             0x11111111,
             Some(0x22222222),
             false,
-            Some(1),
+            Some(2),
             Some(5),
             None,
             None,
-            &[0xa1ce0000], // request route: one relay
+            &[0xa1ce0000, 0xb0b00000],
             &[],
         )
         .unwrap();
 
-        // Step 2: correlate RouteReply (arto → ti6W); pass &[] for request route
-        // (already logged above) and route_back as response route.
-        db.log_traceroute_observation(
-            p2,
-            request_key,
-            0x11111111, // obs_src stays as initiator
-            Some(0x22222222),
-            false,
-            Some(1), // req_hops from route.len()
-            None,
-            Some(0), // res_hops from RF metadata
-            Some(2),
-            &[],           // no request hops (already inserted)
-            &[0xa1ce0000], // response route_back
-        )
-        .unwrap();
-
-        // Session uses in: key so capped at partial even with both sides present
-        let sessions = db.dashboard_traceroute_sessions(0, 10).unwrap();
-        assert_eq!(sessions.len(), 1);
-        assert_eq!(sessions[0]["status"], "partial");
-        assert_eq!(sessions[0]["sample_count"], 2);
+        assert_eq!(db.prune_traceroute_hops_older_than(86400).unwrap(), 0);
+        {
+            let conn = db.conn.lock().unwrap();
+            let old = Utc::now().timestamp() - 2 * 86400;
+            conn.execute(
+                "UPDATE traceroute_session_hops SET observed_at = ?1 WHERE hop_index = 0",
+                params![old],
+            )
+            .unwrap();
+        }
+        assert_eq!(db.prune_traceroute_hops_older_than(86400).unwrap(), 1);
 
-        // Hops: one 'request' hop + one 'response' hop, no duplicates
-        let hops = sessions[0]["hops"].as_array().unwrap();
-        assert_eq!(hops.len(), 2);
-        let directions: Vec<&str> = hops
-            .iter()
-            .map(|h| h["direction"].as_str().unwrap())
-            .collect();
-        assert!(directions.contains(&"request"));
-        assert!(directions.contains(&"response"));
+        let sessions = db.dashboard_traceroute_sessions(0, 10, false).unwrap();
+        assert_eq!(sessions[0].hops.len(), 1);
     }
 
     #[test]
@@ -2352,7 +8884,7 @@ mod tests {
             Some(1.0),
             Some(1),
             Some(3),
-            "traceroute",
+            PacketType::Traceroute,
         )
         .unwrap();
         db.log_packet(
@@ -2366,7 +8898,7 @@ mod tests {
             Some(1.2),
             Some(1),
             Some(3),
-            "traceroute",
+            PacketType::Traceroute,
         )
         .unwrap();
         db.log_packet(
@@ -2380,7 +8912,7 @@ mod tests {
             Some(5.0),
             Some(0),
             Some(3),
-            "traceroute",
+            PacketType::Traceroute,
         )
         .unwrap();
         db.log_packet(
@@ -2394,7 +8926,7 @@ mod tests {
             Some(5.0),
             Some(0),
             Some(3),
-            "traceroute",
+            PacketType::Traceroute,
         )
         .unwrap();
 
@@ -2438,7 +8970,7 @@ mod tests {
             Some(1.5),
             Some(2),
             Some(3),
-            "traceroute",
+            PacketType::Traceroute,
         )
         .unwrap();
         db.log_packet(
@@ -2452,7 +8984,7 @@ mod tests {
             Some(6.0),
             Some(0),
             Some(3),
-            "traceroute",
+            PacketType::Traceroute,
         )
         .unwrap();
 
@@ -2471,6 +9003,70 @@ mod tests {
         assert!(!local_only[0].via_mqtt);
     }
 
+    #[test]
+    fn test_dashboard_messages() {
+        let db = setup_db();
+        db.upsert_node(0xAAAAAAAA, "ALC", "Alice", false).unwrap();
+        let text = |from: u32, to: Option<u32>, channel: u32, text: &str, direction: &str| {
+            db.log_packet(
+                from,
+                to,
+                channel,
+                text,
+                direction,
+                false,
+                None,
+                None,
+                None,
+                None,
+                PacketType::Text,
+            )
+            .unwrap();
+        };
+        text(0xAAAAAAAA, None, 0, "morning all", "in");
+        text(0xBBBBBBBB, None, 1, "quiet channel", "in");
+        text(1, Some(0xAAAAAAAA), 0, "hi Alice", "out");
+        db.log_packet(
+            0xAAAAAAAA,
+            None,
+            0,
+            "",
+            "in",
+            false,
+            None,
+            None,
+            None,
+            None,
+            PacketType::Position,
+        )
+        .unwrap();
+        text(0xBBBBBBBB, None, 0, "anyone?", "in");
+
+        let all = db.dashboard_messages(24, None, None, None, 10).unwrap();
+        let texts: Vec<&str> = all.iter().map(|m| m.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            vec!["anyone?", "hi Alice", "quiet channel", "morning all"]
+        );
+        assert_eq!(all[3].from_long_name, "Alice");
+        assert_eq!(all[1].to_node.as_deref(), Some("!aaaaaaaa"));
+        assert_eq!(all[0].to_node, None);
+
+        let alice = db
+            .dashboard_messages(24, None, Some(0xAAAAAAAA), None, 10)
+            .unwrap();
+        assert_eq!(alice.len(), 2);
+        let channel_1 = db.dashboard_messages(24, Some(1), None, None, 10).unwrap();
+        assert_eq!(channel_1[0].text, "quiet channel");
+
+        let page = db.dashboard_messages(24, None, None, None, 2).unwrap();
+        let next = db
+            .dashboard_messages(24, None, None, Some(page[1].id), 2)
+            .unwrap();
+        assert_eq!(next[0].text, "quiet channel");
+        assert_eq!(next.len(), 2);
+    }
+
     #[test]
     fn test_dashboard_traceroute_destinations() {
         let db = setup_db();
@@ -2489,7 +9085,7 @@ mod tests {
             Some(1.0),
             Some(1),
             Some(3),
-            "traceroute",
+            PacketType::Traceroute,
         )
         .unwrap();
         db.log_packet(
@@ -2503,7 +9099,7 @@ mod tests {
             Some(2.0),
             Some(2),
             Some(3),
-            "traceroute",
+            PacketType::Traceroute,
         )
         .unwrap();
         db.log_packet(
@@ -2517,7 +9113,7 @@ mod tests {
             Some(1.7),
             Some(0),
             Some(3),
-            "traceroute",
+            PacketType::Traceroute,
         )
         .unwrap();
 
@@ -2558,7 +9154,7 @@ mod tests {
             Some(5.0),
             Some(2),
             Some(3),
-            "text",
+            PacketType::Text,
         )
         .unwrap();
         db.log_packet(
@@ -2572,7 +9168,7 @@ mod tests {
             Some(5.2),
             Some(1),
             Some(3),
-            "text",
+            PacketType::Text,
         )
         .unwrap();
 
@@ -2588,6 +9184,86 @@ mod tests {
         assert!(nodes[0].last_rf_seen.is_some());
     }
 
+    #[test]
+    fn test_backfill_traceroute_hop_stats() {
+        let db = setup_db();
+        let me = 0x01020304;
+        db.upsert_node(0xAAAAAAAA, "A", "Alice", false).unwrap();
+        db.upsert_node(0xBBBBBBBB, "B", "Bob", false).unwrap();
+        for hops in [2, 1] {
+            db.log_packet(
+                0xAAAAAAAA,
+                None,
+                0,
+                "Hello",
+                "in",
+                false,
+                None,
+                None,
+                Some(hops),
+                Some(3),
+                PacketType::Text,
+            )
+            .unwrap();
+        }
+        let probe = db
+            .log_packet_with_mesh_id(
+                me,
+                Some(0xCCCCCCCC),
+                0,
+                "",
+                "out",
+                false,
+                None,
+                None,
+                None,
+                None,
+                Some(7),
+                PacketType::Traceroute,
+            )
+            .unwrap();
+        // Our probe to C went out via Alice then Bob and came back the same way
+        db.log_traceroute_observation(
+            probe,
+            "req:01020304:cccccccc:7",
+            me,
+            Some(0xCCCCCCCC),
+            false,
+            Some(2),
+            None,
+            Some(2),
+            None,
+            &[0xAAAAAAAA, 0xBBBBBBBB],
+            &[0xBBBBBBBB, 0xAAAAAAAA],
+        )
+        .unwrap();
+
+        // Nothing is combined until the backfill has run
+        let nodes = db.dashboard_nodes(24, MqttFilter::All).unwrap();
+        let bob = nodes.iter().find(|n| n.node_id == "!bbbbbbbb").unwrap();
+        assert_eq!(bob.combined_hop_samples, 0);
+        assert_eq!(bob.combined_avg_hop, None);
+
+        assert_eq!(db.backfill_traceroute_hop_stats().unwrap(), 2);
+        let nodes = db.dashboard_nodes(24, MqttFilter::All).unwrap();
+        let alice = nodes.iter().find(|n| n.node_id == "!aaaaaaaa").unwrap();
+        assert_eq!(alice.hop_samples, 2);
+        assert_eq!(alice.combined_min_hop, Some(1));
+        assert_eq!(alice.combined_avg_hop, Some(1.25));
+        assert_eq!(alice.combined_hop_samples, 4);
+        let bob = nodes.iter().find(|n| n.node_id == "!bbbbbbbb").unwrap();
+        assert_eq!(bob.min_hop, None);
+        assert_eq!(bob.combined_min_hop, Some(2));
+        assert_eq!(bob.combined_avg_hop, Some(2.0));
+        assert_eq!(bob.combined_hop_samples, 2);
+
+        // Recomputing replaces rather than accumulates
+        assert_eq!(db.backfill_traceroute_hop_stats().unwrap(), 2);
+        let nodes = db.dashboard_nodes(24, MqttFilter::All).unwrap();
+        let bob = nodes.iter().find(|n| n.node_id == "!bbbbbbbb").unwrap();
+        assert_eq!(bob.combined_hop_samples, 2);
+    }
+
     #[test]
     fn test_dashboard_nodes_mqtt_filter() {
         let db = setup_db();
@@ -2622,7 +9298,7 @@ mod tests {
             Some(2.0),
             Some(3),
             Some(3),
-            "text",
+            PacketType::Text,
         )
         .unwrap();
         db.log_packet(
@@ -2636,7 +9312,7 @@ mod tests {
             Some(5.0),
             Some(1),
             Some(3),
-            "text",
+            PacketType::Text,
         )
         .unwrap();
 
@@ -2665,6 +9341,161 @@ mod tests {
         assert_eq!(nodes_all[0].hop_samples, 2);
     }
 
+    #[test]
+    fn test_dashboard_dedup_stats() {
+        let db = setup_db();
+        db.upsert_node(0xAAAAAAAA, "A", "Alice", false).unwrap();
+        // Packet 1: heard on RF twice and echoed via MQTT; packet 2: once
+        for (mesh_id, via_mqtt) in [(1, false), (1, false), (1, true), (2, false)] {
+            db.log_packet_with_mesh_id(
+                0xAAAAAAAA,
+                None,
+                0,
+                "",
+                "in",
+                via_mqtt,
+                None,
+                None,
+                None,
+                None,
+                Some(mesh_id),
+                PacketType::Position,
+            )
+            .unwrap();
+        }
+        // Outgoing packets and rows without an ID are ignored
+        db.log_packet(
+            1,
+            None,
+            0,
+            "hi",
+            "out",
+            false,
+            None,
+            None,
+            None,
+            None,
+            PacketType::Text,
+        )
+        .unwrap();
+
+        let stats = db.dashboard_dedup_stats(24).unwrap();
+        let expected = DedupCounts {
+            unique_packets: 2,
+            copies: 4,
+            rf_duplicates: 1,
+            mqtt_duplicates: 1,
+            redundancy: 2.0,
+        };
+        assert_eq!(stats.overall, expected);
+        assert_eq!(stats.nodes.len(), 1);
+        assert_eq!(stats.nodes[0].long_name, "Alice");
+        assert_eq!(stats.nodes[0].counts, expected);
+    }
+
+    #[test]
+    fn test_dashboard_hops_to_me_compares_windows() {
+        let db = setup_db();
+        db.upsert_node(0xAAAAAAAA, "A", "Alice", false).unwrap();
+        for (text, hops) in [("old", 3), ("new", 1), ("ancient", 5)] {
+            db.log_packet(
+                0xAAAAAAAA,
+                None,
+                0,
+                text,
+                "in",
+                false,
+                Some(-80),
+                Some(5.0),
+                Some(hops),
+                Some(5),
+                PacketType::Text,
+            )
+            .unwrap();
+        }
+        {
+            let conn = db.conn.lock().unwrap();
+            let now = Utc::now().timestamp();
+            for (text, age_hours) in [("old", 36), ("ancient", 60)] {
+                conn.execute(
+                    "UPDATE packets SET timestamp = ?1 WHERE text = ?2",
+                    params![now - age_hours * 3600, text],
+                )
+                .unwrap();
+            }
+        }
+
+        let rows = db.dashboard_hops_to_me(24, true).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].long_name, "Alice");
+        assert_eq!(rows[0].current.avg_hop, Some(1.0));
+        assert_eq!(
+            rows[0].previous,
+            Some(HopWindow {
+                samples: 1,
+                min_hop: Some(3),
+                avg_hop: Some(3.0)
+            })
+        );
+        assert_eq!(rows[0].avg_hop_change, Some(-2.0));
+
+        let rows = db.dashboard_hops_to_me(24, false).unwrap();
+        assert!(rows[0].previous.is_none());
+        assert_eq!(rows[0].avg_hop_change, None);
+        assert_eq!(
+            db.dashboard_hops_to_me(0, true).unwrap()[0].current.samples,
+            3
+        );
+    }
+
+    #[test]
+    fn test_most_active_nodes() {
+        let db = setup_db();
+        db.upsert_node(0xAAAAAAAA, "A", "Alice", false).unwrap();
+        let log = |from: u32, direction: &str| {
+            db.log_packet(
+                from,
+                None,
+                0,
+                "",
+                direction,
+                false,
+                None,
+                None,
+                None,
+                None,
+                PacketType::Position,
+            )
+            .unwrap();
+        };
+        for _ in 0..3 {
+            log(0xAAAAAAAA, "in");
+        }
+        log(0xBBBBBBBB, "in");
+        log(0xCCCCCCCC, "in");
+        log(0xDDDDDDDD, "out");
+
+        let nodes = db.most_active_nodes(24, 2).unwrap();
+        assert_eq!(
+            nodes,
+            vec![
+                ActiveNode {
+                    node_id: 0xAAAAAAAA,
+                    short_name: "A".to_string(),
+                    long_name: "Alice".to_string(),
+                    packets: 3,
+                },
+                ActiveNode {
+                    node_id: 0xBBBBBBBB,
+                    short_name: String::new(),
+                    long_name: String::new(),
+                    packets: 1,
+                },
+            ]
+        );
+        assert_eq!(db.most_active_nodes(24, 5).unwrap().len(), 3);
+    }
+
     #[test]
     fn test_dashboard_throughput() {
         let db = setup_db();
@@ -2679,7 +9510,7 @@ mod tests {
             Some(5.0),
             Some(1),
             Some(3),
-            "text",
+            PacketType::Text,
         )
         .unwrap();
         db.log_packet(
@@ -2693,7 +9524,7 @@ mod tests {
             None,
             None,
             None,
-            "text",
+            PacketType::Text,
         )
         .unwrap();
         // Non-text packets should not appear in text throughput
@@ -2708,7 +9539,7 @@ mod tests {
             Some(6.0),
             Some(1),
             Some(3),
-            "position",
+            PacketType::Position,
         )
         .unwrap();
 
@@ -2734,7 +9565,7 @@ mod tests {
             Some(5.0),
             Some(1),
             Some(3),
-            "text",
+            PacketType::Text,
         )
         .unwrap();
         db.log_packet(
@@ -2748,7 +9579,7 @@ mod tests {
             Some(6.0),
             Some(1),
             Some(3),
-            "position",
+            PacketType::Position,
         )
         .unwrap();
         db.log_packet(
@@ -2762,7 +9593,7 @@ mod tests {
             Some(7.0),
             Some(0),
             Some(3),
-            "telemetry",
+            PacketType::Telemetry,
         )
         .unwrap();
 
@@ -2774,7 +9605,7 @@ mod tests {
         assert_eq!(total_in, 3);
 
         // Filter to specific types
-        let types = vec!["position".to_string(), "telemetry".to_string()];
+        let types = [PacketType::Position, PacketType::Telemetry];
         let buckets = db
             .dashboard_packet_throughput(24, MqttFilter::All, Some(&types))
             .unwrap();
@@ -2796,7 +9627,7 @@ mod tests {
             Some(5.0),
             Some(1),
             Some(3),
-            "text",
+            PacketType::Text,
         )
         .unwrap();
         db.log_packet(
@@ -2810,7 +9641,7 @@ mod tests {
             Some(3.0),
             Some(2),
             Some(3),
-            "text",
+            PacketType::Text,
         )
         .unwrap();
 
@@ -2834,7 +9665,7 @@ mod tests {
             Some(5.0),
             Some(1),
             Some(3),
-            "text",
+            PacketType::Text,
         )
         .unwrap();
         db.log_packet(
@@ -2848,7 +9679,7 @@ mod tests {
             Some(3.0),
             Some(2),
             Some(3),
-            "text",
+            PacketType::Text,
         )
         .unwrap();
 
@@ -2883,7 +9714,7 @@ mod tests {
             Some(5.5),
             Some(2),
             Some(3),
-            "text",
+            PacketType::Text,
         )
         .unwrap();
 
@@ -2913,7 +9744,7 @@ mod tests {
             Some(5.0),
             Some(1),
             Some(3),
-            "text",
+            PacketType::Text,
         )
         .unwrap();
         db.log_packet(
@@ -2927,11 +9758,21 @@ mod tests {
             Some(6.0),
             Some(1),
             Some(3),
-            "position",
+            PacketType::Position,
         )
         .unwrap();
         db.log_packet(
-            0xAAAAAAAA, None, 0, "", "in", false, None, None, None, None, "nodeinfo",
+            0xAAAAAAAA,
+            None,
+            0,
+            "",
+            "in",
+            false,
+            None,
+            None,
+            None,
+            None,
+            PacketType::NodeInfo,
         )
         .unwrap();
 
@@ -2941,7 +9782,7 @@ mod tests {
     }
 
     #[test]
-    fn test_packet_throughput_rejects_invalid_types() {
+    fn test_packet_throughput_with_no_valid_types() {
         let db = setup_db();
         db.log_packet(
             0xAAAAAAAA,
@@ -2954,19 +9795,19 @@ mod tests {
             Some(5.0),
             Some(1),
             Some(3),
-            "text",
+            PacketType::Text,
         )
         .unwrap();
 
-        // Invalid type names should be silently filtered out, returning empty
-        let types = vec!["'; DROP TABLE packets; --".to_string()];
+        // Invalid type names are dropped while parsing, leaving nothing
+        let types = PacketType::parse_list("'; DROP TABLE packets; --").unwrap();
         let buckets = db
             .dashboard_packet_throughput(24, MqttFilter::All, Some(&types))
             .unwrap();
         assert!(buckets.is_empty());
 
         // Mix of valid and invalid — only valid types are used
-        let types = vec!["text".to_string(), "fake_injection".to_string()];
+        let types = PacketType::parse_list("text,fake_injection").unwrap();
         let buckets = db
             .dashboard_packet_throughput(24, MqttFilter::All, Some(&types))
             .unwrap();
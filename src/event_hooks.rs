@@ -0,0 +1,275 @@
+//! Event webhooks: JSON POSTs for key mesh and bot events, so integrations
+//! don't have to poll the REST API.
+//!
+//! Events are handed to a background task through a bounded channel and
+//! delivered to each `[[event_hooks.webhooks]]` entry that subscribes to
+//! them, with retries and an optional HMAC-SHA256 signature.
+
+use std::time::Duration;
+
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use tokio::sync::mpsc;
+
+use crate::config::EventWebhookConfig;
+
+/// Events waiting for delivery; more than this and new ones are dropped.
+const CHANNEL_CAPACITY: usize = 256;
+/// Give up on a webhook URL that takes longer than this to answer.
+const POST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Header carrying `sha256=<hex HMAC of the body>` when a secret is set.
+pub const SIGNATURE_HEADER: &str = "X-Meshenger-Signature";
+/// Header carrying the event name.
+pub const EVENT_HEADER: &str = "X-Meshenger-Event";
+/// Names webhooks can subscribe to in `events`.
+pub const EVENT_NAMES: &[&str] = &[
+    "node_new",
+    "node_offline",
+    "mail_stored",
+    "traceroute_completed",
+    "queue_overflow",
+    "alert",
+];
+
+/// An event delivered to the webhooks, serialized with its name as `event`.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum HookEvent {
+    /// A node was heard for the first time.
+    NodeNew {
+        node: String,
+        name: String,
+        short_name: String,
+        via_mqtt: bool,
+    },
+    /// An RF node hasn't been heard for `[freshness] stale_mins`.
+    NodeOffline {
+        node: String,
+        name: String,
+        last_seen: i64,
+    },
+    /// `!mail` stored a message (the body is not included).
+    MailStored { from: String, to: String },
+    /// One of our traceroute probes was answered.
+    TracerouteCompleted {
+        node: String,
+        name: String,
+        hops_towards: usize,
+        hops_back: usize,
+    },
+    /// The outgoing queue reached `[event_hooks] queue_overflow_depth`.
+    QueueOverflow { depth: usize },
+    /// An `[[alerts]]` rule notifying "webhooks" started to hold; `node`
+    /// is null for mesh-wide conditions.
+    Alert {
+        rule: String,
+        condition: String,
+        node: Option<String>,
+        text: String,
+    },
+}
+
+impl HookEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            HookEvent::NodeNew { .. } => "node_new",
+            HookEvent::NodeOffline { .. } => "node_offline",
+            HookEvent::MailStored { .. } => "mail_stored",
+            HookEvent::TracerouteCompleted { .. } => "traceroute_completed",
+            HookEvent::QueueOverflow { .. } => "queue_overflow",
+            HookEvent::Alert { .. } => "alert",
+        }
+    }
+
+    /// JSON body: the event's fields plus `event` and `timestamp`.
+    fn to_json(&self, timestamp: i64) -> serde_json::Value {
+        let mut json = serde_json::to_value(self).unwrap_or_default();
+        if let Some(object) = json.as_object_mut() {
+            object.insert("timestamp".to_string(), timestamp.into());
+        }
+        json
+    }
+}
+
+/// `sha256=<hex>` signature of `body` with `secret`.
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(body);
+    let hex: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    format!("sha256={}", hex)
+}
+
+fn subscribes(webhook: &EventWebhookConfig, event: &str) -> bool {
+    webhook.events.is_empty() || webhook.events.iter().any(|e| e == event)
+}
+
+/// Handle for emitting events; cheap to clone. The default handle has no
+/// webhooks and drops everything.
+#[derive(Debug, Clone, Default)]
+pub struct EventHooks {
+    tx: Option<mpsc::Sender<HookEvent>>,
+}
+
+impl EventHooks {
+    /// Start delivering to `webhooks`; with none, returns a disabled handle.
+    pub fn start(webhooks: &[EventWebhookConfig]) -> Self {
+        if webhooks.is_empty() {
+            return Self::default();
+        }
+        let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+        let webhooks = webhooks.to_vec();
+        tokio::spawn(async move {
+            if let Err(e) = Self::run(webhooks, rx).await {
+                log::error!("Event webhooks error: {}", e);
+            }
+        });
+        Self { tx: Some(tx) }
+    }
+
+    /// A handle whose events land in the returned receiver instead of
+    /// being delivered.
+    #[cfg(test)]
+    pub fn capture() -> (Self, mpsc::Receiver<HookEvent>) {
+        let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+        (Self { tx: Some(tx) }, rx)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.tx.is_some()
+    }
+
+    /// Queue an event for delivery without waiting.
+    pub fn emit(&self, event: HookEvent) {
+        let Some(tx) = &self.tx else {
+            return;
+        };
+        if let Err(e) = tx.try_send(event) {
+            log::warn!("Dropping event webhook: {}", e);
+        }
+    }
+
+    async fn run(
+        webhooks: Vec<EventWebhookConfig>,
+        mut rx: mpsc::Receiver<HookEvent>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        log::info!("Starting event webhooks ({} URL(s))", webhooks.len());
+        let client = reqwest::Client::builder().timeout(POST_TIMEOUT).build()?;
+
+        while let Some(event) = rx.recv().await {
+            let name = event.name();
+            let body = event
+                .to_json(chrono::Utc::now().timestamp())
+                .to_string()
+                .into_bytes();
+            for webhook in webhooks.iter().filter(|w| subscribes(w, name)) {
+                // Each delivery retries on its own, so a slow URL doesn't
+                // hold up the others
+                let client = client.clone();
+                let webhook = webhook.clone();
+                let body = body.clone();
+                tokio::spawn(async move {
+                    Self::deliver(&client, &webhook, name, body).await;
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// POST `body`, retrying failures with a doubling delay (1s, 2s, 4s, ...).
+    async fn deliver(
+        client: &reqwest::Client,
+        webhook: &EventWebhookConfig,
+        event: &str,
+        body: Vec<u8>,
+    ) {
+        let signature = (!webhook.secret.is_empty()).then(|| sign(&webhook.secret, &body));
+        let mut delay = Duration::from_secs(1);
+        for attempt in 0..=webhook.max_retries {
+            if attempt > 0 {
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+            let mut request = client
+                .post(&webhook.url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header(EVENT_HEADER, event)
+                .body(body.clone());
+            if let Some(signature) = &signature {
+                request = request.header(SIGNATURE_HEADER, signature);
+            }
+            match request.send().await {
+                Ok(resp) if resp.status().is_success() => return,
+                Ok(resp) => log::warn!(
+                    "Event webhook {} answered {} for {}",
+                    webhook.url,
+                    resp.status(),
+                    event
+                ),
+                Err(e) => log::warn!("Event webhook {} failed for {}: {}", webhook.url, event, e),
+            }
+        }
+        log::error!(
+            "Giving up on event webhook {} for {} after {} attempt(s)",
+            webhook.url,
+            event,
+            webhook.max_retries + 1
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_json() {
+        let event = HookEvent::MailStored {
+            from: "!11111111".to_string(),
+            to: "!22222222".to_string(),
+        };
+        assert_eq!(event.name(), "mail_stored");
+        assert_eq!(
+            event.to_json(1700000000),
+            serde_json::json!({
+                "event": "mail_stored",
+                "from": "!11111111",
+                "to": "!22222222",
+                "timestamp": 1700000000
+            })
+        );
+        assert_eq!(
+            HookEvent::QueueOverflow { depth: 50 }.to_json(0)["event"],
+            "queue_overflow"
+        );
+    }
+
+    #[test]
+    fn test_sign() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            sign("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_subscribes() {
+        let mut webhook = EventWebhookConfig {
+            url: "http://localhost/hook".to_string(),
+            secret: String::new(),
+            events: Vec::new(),
+            max_retries: 3,
+        };
+        assert!(subscribes(&webhook, "node_new"));
+        webhook.events = vec!["mail_stored".to_string()];
+        assert!(!subscribes(&webhook, "node_new"));
+        assert!(subscribes(&webhook, "mail_stored"));
+    }
+}
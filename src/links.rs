@@ -0,0 +1,111 @@
+//! Local URL shortener for chat messages relayed to the mesh.
+
+use regex::Regex;
+
+use crate::config::LinkShortenerConfig;
+use crate::db::Db;
+
+/// Replaces long URLs with `<base_url>/l/<code>` links that the dashboard
+/// redirects back to the original URL.
+pub struct LinkShortener {
+    pattern: Regex,
+    base_url: String,
+    min_length: usize,
+}
+
+impl LinkShortener {
+    /// Returns `None` when shortening is disabled or has no base URL to link to.
+    pub fn from_config(config: &LinkShortenerConfig) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+        let base_url = config.base_url.trim_end_matches('/');
+        if base_url.is_empty() {
+            log::warn!("Link shortening disabled: bridge.links.base_url is not set");
+            return None;
+        }
+        Some(Self {
+            pattern: Regex::new(r"https?://\S+").expect("valid URL pattern"),
+            base_url: base_url.to_string(),
+            min_length: config.min_length,
+        })
+    }
+
+    /// Shorten every URL in `text` that is long enough and would actually get
+    /// shorter. URLs whose code can't be stored are left as they are.
+    pub fn shorten(&self, text: &str, db: &Db) -> String {
+        self.pattern
+            .replace_all(text, |caps: &regex::Captures| {
+                let url = &caps[0];
+                let code = short_code(url);
+                let short = format!("{}/l/{}", self.base_url, code);
+                if url.len() < self.min_length || short.len() >= url.len() {
+                    return url.to_string();
+                }
+                match db.store_short_link(&code, url) {
+                    Ok(true) => short,
+                    Ok(false) => url.to_string(),
+                    Err(e) => {
+                        log::error!("Failed to store short link: {}", e);
+                        url.to_string()
+                    }
+                }
+            })
+            .into_owned()
+    }
+}
+
+/// Stable 8-character code for a URL (FNV-1a, so the same URL always maps to
+/// the same link).
+fn short_code(url: &str) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in url.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:08x}", (hash ^ (hash >> 32)) as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn shortener(min_length: usize) -> LinkShortener {
+        LinkShortener::from_config(&LinkShortenerConfig {
+            enabled: true,
+            base_url: "http://mesh.local/".to_string(),
+            min_length,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_long_urls_are_shortened_and_resolvable() {
+        let db = Db::open(Path::new(":memory:")).unwrap();
+        let url = "https://example.org/a/very/long/path?with=query&and=more";
+        let text = format!("[TG:alice] look {} and https://x.io", url);
+
+        let out = shortener(40).shorten(&text, &db);
+        let code = short_code(url);
+        assert_eq!(
+            out,
+            format!(
+                "[TG:alice] look http://mesh.local/l/{} and https://x.io",
+                code
+            )
+        );
+        assert_eq!(db.resolve_short_link(&code).unwrap().as_deref(), Some(url));
+        // Same URL, same link
+        assert_eq!(shortener(40).shorten(&text, &db), out);
+    }
+
+    #[test]
+    fn test_disabled_or_short_urls_untouched() {
+        let db = Db::open(Path::new(":memory:")).unwrap();
+        assert!(LinkShortener::from_config(&LinkShortenerConfig::default()).is_none());
+        let text = "see https://example.org/a/very/long/path";
+        assert_eq!(shortener(100).shorten(text, &db), text);
+        assert_eq!(db.resolve_short_link("missing").unwrap(), None);
+    }
+}
@@ -0,0 +1,160 @@
+//! Events streamed to dashboard clients over `/api/events`.
+//!
+//! `Refresh` is the coarse "something changed, re-fetch" ping and is still
+//! sent for every change. The others say what changed, so a client can
+//! update in place. The public listener only passes `Refresh` on.
+
+use serde::Serialize;
+
+use crate::packet_type::PacketType;
+
+/// Serialized with its `type`, which is also the SSE event name.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LiveEvent {
+    Refresh,
+    /// A packet heard from the mesh.
+    Packet {
+        timestamp: i64,
+        from_node: String,
+        to_node: Option<String>,
+        packet_type: &'static str,
+        channel: u32,
+        via_mqtt: bool,
+        rssi: Option<i32>,
+        snr: Option<f32>,
+        hops: Option<u32>,
+        /// Text messages only, and not DMs kept out of the database.
+        text: Option<String>,
+    },
+    /// A node announced itself (NodeInfo).
+    Node {
+        node_id: String,
+        short_name: String,
+        long_name: String,
+        via_mqtt: bool,
+    },
+    /// A text message the bot transmitted.
+    MessageSent {
+        timestamp: i64,
+        from_node: String,
+        to_node: Option<String>,
+        channel: u32,
+        text: String,
+    },
+    /// The outgoing queue grew or shrank.
+    QueueDepth {
+        depth: usize,
+    },
+    /// A traceroute session was started or heard again
+    /// (`/api/traceroute-sessions`).
+    Traceroute {
+        session_id: i64,
+    },
+}
+
+impl LiveEvent {
+    #[allow(clippy::too_many_arguments)]
+    pub fn packet(
+        from: u32,
+        to: Option<u32>,
+        packet_type: PacketType,
+        channel: u32,
+        via_mqtt: bool,
+        rssi: Option<i32>,
+        snr: Option<f32>,
+        hops: Option<u32>,
+        text: Option<&str>,
+    ) -> Self {
+        LiveEvent::Packet {
+            timestamp: chrono::Utc::now().timestamp(),
+            from_node: node_id(from),
+            to_node: to.map(node_id),
+            packet_type: packet_type.as_str(),
+            channel,
+            via_mqtt,
+            rssi,
+            snr,
+            hops,
+            text: text.map(str::to_string),
+        }
+    }
+
+    pub fn node(num: u32, short_name: &str, long_name: &str, via_mqtt: bool) -> Self {
+        LiveEvent::Node {
+            node_id: node_id(num),
+            short_name: short_name.to_string(),
+            long_name: long_name.to_string(),
+            via_mqtt,
+        }
+    }
+
+    pub fn message_sent(from: u32, to: Option<u32>, channel: u32, text: &str) -> Self {
+        LiveEvent::MessageSent {
+            timestamp: chrono::Utc::now().timestamp(),
+            from_node: node_id(from),
+            to_node: to.map(node_id),
+            channel,
+            text: text.to_string(),
+        }
+    }
+
+    /// The SSE event name.
+    pub fn name(&self) -> &'static str {
+        match self {
+            LiveEvent::Refresh => "refresh",
+            LiveEvent::Packet { .. } => "packet",
+            LiveEvent::Node { .. } => "node",
+            LiveEvent::MessageSent { .. } => "message_sent",
+            LiveEvent::QueueDepth { .. } => "queue_depth",
+            LiveEvent::Traceroute { .. } => "traceroute",
+        }
+    }
+
+    /// The SSE data line: the event as JSON, empty for `Refresh`.
+    pub fn data(&self) -> String {
+        match self {
+            LiveEvent::Refresh => String::new(),
+            event => serde_json::to_string(event).unwrap_or_default(),
+        }
+    }
+}
+
+fn node_id(num: u32) -> String {
+    format!("!{:08x}", num)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_payloads() {
+        let event = LiveEvent::packet(
+            0xaaaa0001,
+            None,
+            PacketType::Position,
+            2,
+            false,
+            Some(-90),
+            None,
+            Some(1),
+            None,
+        );
+        assert_eq!(event.name(), "packet");
+        let data: serde_json::Value = serde_json::from_str(&event.data()).unwrap();
+        assert_eq!(data["type"], event.name());
+        assert_eq!(data["from_node"], "!aaaa0001");
+        assert_eq!(data["packet_type"], "position");
+        assert_eq!(data["rssi"], -90);
+        assert!(data["to_node"].is_null());
+
+        assert_eq!(
+            LiveEvent::QueueDepth { depth: 3 }.data(),
+            "{\"type\":\"queue_depth\",\"depth\":3}"
+        );
+        assert_eq!(LiveEvent::Traceroute { session_id: 7 }.name(), "traceroute");
+        assert_eq!(LiveEvent::Refresh.name(), "refresh");
+        assert_eq!(LiveEvent::Refresh.data(), "");
+    }
+}
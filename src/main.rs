@@ -1,27 +1,50 @@
 mod bot;
 mod bridge;
+mod bridge_filter;
 mod bridges;
 mod config;
 mod dashboard;
 mod db;
+mod event_hooks;
+mod links;
+mod live;
+mod mesh_graph;
 mod message;
+mod metrics;
 mod module;
 mod modules;
+mod mqtt;
+mod openapi;
+mod packet_type;
+mod scheduler;
+mod settings;
+#[cfg(test)]
+mod test_support;
 mod util;
 
 use std::io::Write;
 use std::path::Path;
 use std::sync::Arc;
 
-use bridge::create_bridge_channels;
-use bridges::discord::BridgeDirection as DiscordDirection;
+use bridge::{create_bridge_channels, BridgeLog, MeshStatus};
+use bridge_filter::BridgeFilter;
+use bridges::discord::{
+    parse_color, BridgeDirection as DiscordDirection, ChannelMapping, EmbedStyle,
+};
+use bridges::mqtt::BridgeDirection as MqttDirection;
+use bridges::signal::BridgeDirection as SignalDirection;
+use bridges::telegram::{ChatMapping, TelegramStatusSource};
+use bridges::webhook::BridgeDirection as WebhookDirection;
 use bridges::{
-    BridgeDirection, DiscordBridge, DiscordBridgeConfig, TelegramBridge, TelegramBridgeConfig,
+    BridgeDirection, DiscordBridge, DiscordBridgeConfig, MqttBridge, MqttBridgeConfig,
+    SignalBridge, SignalBridgeConfig, TelegramBridge, TelegramBridgeConfig, WebhookBridge,
+    WebhookBridgeConfig, WebhookInbound,
 };
 use chrono::Local;
 use config::Config;
 use dashboard::Dashboard;
 use db::Db;
+use event_hooks::EventHooks;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
@@ -41,8 +64,23 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     });
     logger.init();
 
-    let config_path = std::env::args()
-        .nth(1)
+    // `--force` opens a database written by a newer meshenger schema.
+    // `--backfill-hops` recomputes hop stats from traceroute sessions and exits.
+    let (flags, positional): (Vec<String>, Vec<String>) =
+        std::env::args().skip(1).partition(|a| a.starts_with("--"));
+    let force = flags.iter().any(|f| f == "--force");
+    let backfill_hops = flags.iter().any(|f| f == "--backfill-hops");
+    if let Some(unknown) = flags
+        .iter()
+        .find(|f| *f != "--force" && *f != "--backfill-hops")
+    {
+        eprintln!("Unknown option: {}", unknown);
+        eprintln!("Usage: meshenger [--force] [--backfill-hops] [config.toml]");
+        std::process::exit(1);
+    }
+    let config_path = positional
+        .into_iter()
+        .next()
         .unwrap_or_else(|| "config.toml".to_string());
 
     let path = Path::new(&config_path);
@@ -53,15 +91,25 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         std::process::exit(1);
     }
 
-    let config = Arc::new(Config::load(path)?);
+    let mut config = Config::load(path)?;
     let config_path_display = path
         .canonicalize()
         .map(|p| p.display().to_string())
         .unwrap_or_else(|_| config_path.clone());
+    let target = config
+        .connections
+        .iter()
+        .map(|c| match c.mode.to_lowercase().as_str() {
+            "mqtt" => c.mqtt.broker.clone(),
+            "both" => format!("{} + {}", c.address, c.mqtt.broker),
+            _ => c.address.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
     log::info!(
         "Starting Meshenger (config={}, target={})",
         config_path_display,
-        config.connection.address
+        target
     );
 
     let db_path = Path::new(&config.bot.db_path);
@@ -79,17 +127,79 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         }
     }
 
-    let db = Arc::new(Db::open(db_path)?);
+    let mut db = if force {
+        Db::open_forced(db_path)?
+    } else {
+        Db::open(db_path)?
+    };
     log::info!("Database opened at {}", config.bot.db_path);
+    if config.db.incremental_vacuum {
+        log::info!("Checking the database is set up for incremental vacuum");
+        if db.enable_incremental_vacuum()? {
+            log::info!("Database switched to incremental vacuum");
+        }
+    }
+    if config.dashboard.read_replica.is_some() {
+        log::warn!("dashboard.read_replica is deprecated, set dashboard.read_connections instead");
+    }
+    let read_connections = config.dashboard.read_pool_size();
+    if config.dashboard.enabled && read_connections > 0 {
+        if db_path.as_os_str() == ":memory:" {
+            log::info!("Dashboard queries use the writer connection (in-memory database)");
+        } else {
+            db.open_read_pool(db_path, read_connections)?;
+            log::info!(
+                "Dashboard queries use {} read-only connection(s)",
+                read_connections
+            );
+        }
+    }
+    let db = Arc::new(db);
+
+    // Options edited on the dashboard override the config file
+    settings::apply(&mut config, &db.settings()?);
+    let config = Arc::new(config);
 
-    let registry = modules::build_registry(&config);
+    if backfill_hops {
+        let nodes = db.backfill_traceroute_hop_stats()?;
+        log::info!("Backfilled traceroute hop stats for {} node(s)", nodes);
+        return Ok(());
+    }
+
+    let hooks = EventHooks::start(&config.event_hooks.webhooks);
+    let registry = modules::build_registry(&config, &hooks);
     log::info!("Registered {} module(s)", registry.all().len());
 
-    // SSE broadcast channel for dashboard real-time updates
-    let (sse_tx, _) = tokio::sync::broadcast::channel::<()>(16);
+    // SSE broadcast channel for dashboard real-time updates; roomy enough
+    // for a burst of packet events between a client's reads
+    let (sse_tx, _) = tokio::sync::broadcast::channel::<live::LiveEvent>(256);
 
     // Create bridge channels
     let (bridge_tx, outgoing_tx, outgoing_rx) = create_bridge_channels();
+    let bridge_filter = Arc::new(BridgeFilter::from_config(&config.bridge.rules)?);
+    if !config.bridge.rules.is_empty() {
+        log::info!("Loaded {} bridge rule(s)", config.bridge.rules.len());
+    }
+
+    // Create bot with bridge channels
+    let bot = bot::Bot::new(Arc::clone(&config), Arc::clone(&db), registry)
+        .with_config_path(path.to_path_buf())
+        .with_event_hooks(hooks)
+        .with_bridge_channels(bridge_tx.clone(), outgoing_rx)
+        .with_bridge_filter(Arc::clone(&bridge_filter))
+        .with_sse_sender(sse_tx.clone());
+
+    let mesh_status = MeshStatus::new(
+        bot.mesh_online(),
+        config.bridge.offline_reply,
+        config.bridge.max_queue_age_secs,
+    );
+    // Relays are counted for `/metrics`, and logged for
+    // `/api/bridges/{name}/log` unless `[bridge] log_days = 0`
+    let bridge_log = |name: &'static str| {
+        let log_db = (config.bridge.log_days > 0).then(|| Arc::clone(&db));
+        BridgeLog::new(log_db, bot.metrics(), name)
+    };
 
     // Start Telegram bridge if configured
     if let Some(telegram_config) = &config.bridge.telegram {
@@ -98,21 +208,39 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
 
             let tg_config = TelegramBridgeConfig {
                 bot_token: telegram_config.bot_token.clone(),
-                chat_id: telegram_config.chat_id,
-                mesh_channel: telegram_config.mesh_channel,
-                direction: BridgeDirection::from_str(&telegram_config.direction),
+                mappings: telegram_config
+                    .all_mappings()
+                    .into_iter()
+                    .map(|m| ChatMapping {
+                        chat_id: m.chat_id,
+                        mesh_channel: m.mesh_channel,
+                        direction: BridgeDirection::from_str(&m.direction),
+                    })
+                    .collect(),
+                dm_chat_id: telegram_config.dm_chat_id,
                 format: telegram_config.format.clone(),
+                max_message_bytes: config.bridge.max_message_bytes,
+                mesh_status: mesh_status.clone(),
             };
 
-            let bridge = TelegramBridge::new(tg_config);
+            let bridge = TelegramBridge::new(tg_config)
+                .with_status_source(TelegramStatusSource {
+                    db: Arc::clone(&db),
+                    queue_depth: bot.queue_depth(),
+                })
+                .with_log(bridge_log("telegram"));
             let mesh_rx = bridge_tx.subscribe();
             let tx = outgoing_tx.clone();
 
+            let metrics = bot.metrics();
+            metrics.set_bridge_running("telegram", true);
+
             // Spawn bridge in background
             tokio::spawn(async move {
                 if let Err(e) = bridge.run(mesh_rx, tx).await {
                     log::error!("Telegram bridge error: {}", e);
                 }
+                metrics.set_bridge_running("telegram", false);
             });
         }
     }
@@ -124,39 +252,190 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
 
             let dc_config = DiscordBridgeConfig {
                 bot_token: discord_config.bot_token.clone(),
-                channel_id: discord_config.channel_id,
-                mesh_channel: discord_config.mesh_channel,
-                direction: DiscordDirection::from_str(&discord_config.direction),
+                mappings: discord_config
+                    .all_mappings()
+                    .into_iter()
+                    .map(|m| ChannelMapping {
+                        channel_id: m.channel_id,
+                        mesh_channel: m.mesh_channel,
+                        direction: DiscordDirection::from_str(&m.direction),
+                    })
+                    .collect(),
+                dm_channel_id: discord_config.dm_channel_id,
                 format: discord_config.format.clone(),
+                embeds: discord_config
+                    .embeds
+                    .iter()
+                    .map(|(event, embed)| {
+                        let color = if embed.color.is_empty() {
+                            None
+                        } else {
+                            let color = parse_color(&embed.color);
+                            if color.is_none() {
+                                log::warn!(
+                                    "Ignoring invalid Discord embed color {:?} for {}",
+                                    embed.color,
+                                    event
+                                );
+                            }
+                            color
+                        };
+                        let style = EmbedStyle {
+                            title: embed.title.clone(),
+                            color,
+                        };
+                        (event.clone(), style)
+                    })
+                    .collect(),
+                max_message_bytes: config.bridge.max_message_bytes,
+                mesh_status: mesh_status.clone(),
             };
 
-            let bridge = DiscordBridge::new(dc_config);
+            let bridge = DiscordBridge::new(dc_config).with_log(bridge_log("discord"));
             let mesh_rx = bridge_tx.subscribe();
             let tx = outgoing_tx.clone();
 
+            let metrics = bot.metrics();
+            metrics.set_bridge_running("discord", true);
+
             // Spawn bridge in background
             tokio::spawn(async move {
                 if let Err(e) = bridge.run(mesh_rx, tx).await {
                     log::error!("Discord bridge error: {}", e);
                 }
+                metrics.set_bridge_running("discord", false);
             });
         }
     }
 
-    // Create bot with bridge channels
-    let bot = bot::Bot::new(Arc::clone(&config), Arc::clone(&db), registry)
-        .with_bridge_channels(bridge_tx, outgoing_rx)
-        .with_sse_sender(sse_tx.clone());
+    // Start MQTT bridge if configured
+    if let Some(mqtt_config) = &config.bridge.mqtt {
+        if mqtt_config.enabled {
+            log::info!("Starting MQTT bridge...");
+
+            let mq_config = MqttBridgeConfig {
+                broker: mqtt_config.broker.clone(),
+                username: mqtt_config.username.clone(),
+                password: mqtt_config.password.clone(),
+                publish_topic: mqtt_config.publish_topic.clone(),
+                command_topic: mqtt_config.command_topic.clone(),
+                mesh_channel: mqtt_config.mesh_channel,
+                direction: MqttDirection::from_str(&mqtt_config.direction),
+                max_message_bytes: config.bridge.max_message_bytes,
+                mesh_status: mesh_status.clone(),
+            };
+
+            let bridge = MqttBridge::new(mq_config).with_log(bridge_log("mqtt"));
+            let mesh_rx = bridge_tx.subscribe();
+            let tx = outgoing_tx.clone();
+
+            let metrics = bot.metrics();
+            metrics.set_bridge_running("mqtt", true);
+
+            // Spawn bridge in background
+            tokio::spawn(async move {
+                if let Err(e) = bridge.run(mesh_rx, tx).await {
+                    log::error!("MQTT bridge error: {}", e);
+                }
+                metrics.set_bridge_running("mqtt", false);
+            });
+        }
+    }
+
+    // Start Signal bridge if configured
+    if let Some(signal_config) = &config.bridge.signal {
+        if signal_config.enabled {
+            log::info!("Starting Signal bridge...");
+
+            let sg_config = SignalBridgeConfig {
+                api_url: signal_config.api_url.clone(),
+                number: signal_config.number.clone(),
+                group_id: signal_config.group_id.clone(),
+                mesh_channel: signal_config.mesh_channel,
+                direction: SignalDirection::from_str(&signal_config.direction),
+                format: signal_config.format.clone(),
+                max_message_bytes: config.bridge.max_message_bytes,
+                mesh_status: mesh_status.clone(),
+            };
+
+            let bridge = SignalBridge::new(sg_config).with_log(bridge_log("signal"));
+            let mesh_rx = bridge_tx.subscribe();
+            let tx = outgoing_tx.clone();
+
+            let metrics = bot.metrics();
+            metrics.set_bridge_running("signal", true);
+
+            // Spawn bridge in background
+            tokio::spawn(async move {
+                if let Err(e) = bridge.run(mesh_rx, tx).await {
+                    log::error!("Signal bridge error: {}", e);
+                }
+                metrics.set_bridge_running("signal", false);
+            });
+        }
+    }
+
+    // Start webhook bridge if configured; its inbound side is served by the dashboard
+    let mut webhook_inbound = None;
+    if let Some(webhook_config) = &config.bridge.webhook {
+        if webhook_config.enabled {
+            let direction = WebhookDirection::from_str(&webhook_config.direction);
+
+            if direction.forwards_to_webhook() && !webhook_config.urls.is_empty() {
+                log::info!("Starting webhook bridge...");
+                let bridge = WebhookBridge::new(WebhookBridgeConfig {
+                    urls: webhook_config.urls.clone(),
+                    mesh_channel: webhook_config.mesh_channel,
+                })
+                .with_log(bridge_log("webhook"));
+                let mesh_rx = bridge_tx.subscribe();
+                let metrics = bot.metrics();
+                metrics.set_bridge_running("webhook", true);
+
+                // Spawn bridge in background
+                tokio::spawn(async move {
+                    if let Err(e) = bridge.run(mesh_rx).await {
+                        log::error!("Webhook bridge error: {}", e);
+                    }
+                    metrics.set_bridge_running("webhook", false);
+                });
+            }
+
+            if direction.forwards_to_mesh() {
+                if webhook_config.token.is_empty() {
+                    log::warn!("Webhook bridge: no token set, /api/bridge/send is disabled");
+                } else if !config.dashboard.enabled {
+                    log::warn!("Webhook bridge: /api/bridge/send needs the dashboard enabled");
+                } else {
+                    webhook_inbound = Some(WebhookInbound {
+                        token: webhook_config.token.clone(),
+                        mesh_channel: webhook_config.mesh_channel,
+                        max_message_bytes: config.bridge.max_message_bytes,
+                        mesh_status: mesh_status.clone(),
+                        outgoing_tx: outgoing_tx.clone(),
+                    });
+                }
+            }
+        }
+    }
 
     // Start dashboard if enabled
     if config.dashboard.enabled {
-        let dashboard = Dashboard::new(
+        let mut dashboard = Dashboard::new(
             Arc::clone(&config),
             Arc::clone(&db),
             bot.queue_depth(),
             bot.local_node_id(),
+            bot.firmware_version(),
+            bridge_filter,
             sse_tx.clone(),
-        );
+        )
+        .with_metrics(bot.metrics())
+        .with_modules_config(bot.modules_config())
+        .with_sender(outgoing_tx.clone());
+        if let Some(inbound) = webhook_inbound {
+            dashboard = dashboard.with_webhook(inbound);
+        }
         tokio::spawn(async move {
             if let Err(e) = dashboard.run().await {
                 log::error!("Dashboard error: {}", e);
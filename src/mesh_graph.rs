@@ -0,0 +1,142 @@
+//! Reachability over the mesh's known radio links: NeighborInfo reports and
+//! consecutive nodes in traceroute routes, treated as undirected.
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+/// Links older than this are left out of the graph.
+pub const LINK_WINDOW_SECS: i64 = 7 * 24 * 60 * 60;
+
+/// A node whose loss would split the mesh, and the nodes that would then be
+/// cut off from the largest remaining part.
+#[derive(Debug, PartialEq)]
+pub struct CutNode {
+    pub node: u32,
+    pub cut_off: Vec<u32>,
+}
+
+pub struct MeshGraph {
+    adjacency: BTreeMap<u32, BTreeSet<u32>>,
+}
+
+impl MeshGraph {
+    pub fn new(links: &[(u32, u32)]) -> Self {
+        let mut adjacency: BTreeMap<u32, BTreeSet<u32>> = BTreeMap::new();
+        for &(a, b) in links {
+            if a == b {
+                continue;
+            }
+            adjacency.entry(a).or_default().insert(b);
+            adjacency.entry(b).or_default().insert(a);
+        }
+        Self { adjacency }
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = u32> + '_ {
+        self.adjacency.keys().copied()
+    }
+
+    /// Fewest links from `from` to every other node it can reach; direct
+    /// neighbours are 1 hop away.
+    pub fn hops_from(&self, from: u32) -> BTreeMap<u32, u32> {
+        self.reachable(from, None)
+            .into_iter()
+            .filter(|&(node, _)| node != from)
+            .collect()
+    }
+
+    /// Every node that connects parts of the mesh that have no other link
+    /// between them, those cutting off the most nodes first.
+    pub fn articulation_points(&self) -> Vec<CutNode> {
+        let mut points = Vec::new();
+        for (&node, neighbours) in &self.adjacency {
+            if neighbours.len() < 2 {
+                continue;
+            }
+            // The parts the neighbours fall into once `node` is gone
+            let mut parts: Vec<Vec<u32>> = Vec::new();
+            let mut seen = BTreeSet::new();
+            for &neighbour in neighbours {
+                if seen.contains(&neighbour) {
+                    continue;
+                }
+                let part: Vec<u32> = self.reachable(neighbour, Some(node)).into_keys().collect();
+                seen.extend(part.iter().copied());
+                parts.push(part);
+            }
+            if parts.len() < 2 {
+                continue;
+            }
+            let largest = (0..parts.len())
+                .max_by_key(|&i| parts[i].len())
+                .unwrap_or(0);
+            let mut cut_off: Vec<u32> = parts
+                .into_iter()
+                .enumerate()
+                .filter(|&(i, _)| i != largest)
+                .flat_map(|(_, part)| part)
+                .collect();
+            cut_off.sort_unstable();
+            points.push(CutNode { node, cut_off });
+        }
+        points.sort_by(|a, b| {
+            b.cut_off
+                .len()
+                .cmp(&a.cut_off.len())
+                .then(a.node.cmp(&b.node))
+        });
+        points
+    }
+
+    /// Breadth-first hop counts from `from`, never passing through `without`.
+    fn reachable(&self, from: u32, without: Option<u32>) -> BTreeMap<u32, u32> {
+        let mut hops = BTreeMap::from([(from, 0)]);
+        let mut queue = VecDeque::from([from]);
+        while let Some(node) = queue.pop_front() {
+            let next = hops[&node] + 1;
+            for &neighbour in self.adjacency.get(&node).into_iter().flatten() {
+                if Some(neighbour) == without || hops.contains_key(&neighbour) {
+                    continue;
+                }
+                hops.insert(neighbour, next);
+                queue.push_back(neighbour);
+            }
+        }
+        hops
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hops_from() {
+        // 1 - 2 - 3 - 4, with a shortcut 1 - 3; 5 - 6 apart
+        let graph = MeshGraph::new(&[(1, 2), (2, 3), (3, 4), (1, 3), (5, 6), (6, 6)]);
+        assert_eq!(graph.hops_from(1), BTreeMap::from([(2, 1), (3, 1), (4, 2)]));
+        assert_eq!(graph.hops_from(6), BTreeMap::from([(5, 1)]));
+        assert!(graph.hops_from(9).is_empty());
+    }
+
+    #[test]
+    fn test_articulation_points() {
+        // A triangle 1-2-3 (plus 6 linked to 1 and 2) with a tail 3 - 4 - 5
+        let graph = MeshGraph::new(&[(1, 2), (2, 3), (3, 1), (6, 1), (6, 2), (3, 4), (4, 5)]);
+        assert_eq!(
+            graph.articulation_points(),
+            vec![
+                CutNode {
+                    node: 3,
+                    cut_off: vec![4, 5],
+                },
+                CutNode {
+                    node: 4,
+                    cut_off: vec![5],
+                },
+            ]
+        );
+        assert!(MeshGraph::new(&[(1, 2), (2, 3), (3, 1)])
+            .articulation_points()
+            .is_empty());
+    }
+}
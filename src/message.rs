@@ -1,3 +1,7 @@
+use serde::Serialize;
+
+use crate::util::escape_html;
+
 #[derive(Debug, Clone, PartialEq)]
 #[allow(dead_code)]
 pub enum CommandScope {
@@ -38,6 +42,8 @@ pub struct MessageContext {
     pub hop_start: u32,
     pub hop_limit: u32,
     pub via_mqtt: bool,
+    /// Public key a DM was PKI-encrypted with, `None` for anything else
+    pub pki_key: Option<Vec<u8>>,
     /// The incoming mesh packet's unique ID (used for reply threading)
     pub packet_id: u32,
 }
@@ -49,6 +55,122 @@ pub struct Response {
     pub channel: u32,
     /// When set, the outgoing message references this incoming packet ID
     pub reply_id: Option<u32>,
+    /// Structured part of the reply. The mesh gets it rendered compactly
+    /// after `text`; richer consumers render it in their own format.
+    pub data: Option<ResponseData>,
+}
+
+impl Response {
+    /// The text actually sent over the mesh: `text` followed by the compact
+    /// rendering of `data` (same line for key-value, one row per line for tables).
+    pub fn mesh_text(&self) -> String {
+        let data = match &self.data {
+            Some(data) => data,
+            None => return self.text.clone(),
+        };
+        let separator = match data {
+            ResponseData::KeyValue(_) => " ",
+            ResponseData::Table { .. } => "\n",
+        };
+        if self.text.is_empty() {
+            data.render_compact()
+        } else {
+            format!("{}{}{}", self.text, separator, data.render_compact())
+        }
+    }
+}
+
+/// Structured payload carried alongside a text reply, so a module builds its
+/// output once and each consumer renders it in its own format.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+#[allow(dead_code)]
+pub enum ResponseData {
+    KeyValue(Vec<(String, String)>),
+    Table {
+        headers: Vec<String>,
+        rows: Vec<Vec<String>>,
+    },
+}
+
+impl ResponseData {
+    /// Compact rendering for the mesh: `key: value` pairs on one line, table
+    /// rows one per line without headers.
+    pub fn render_compact(&self) -> String {
+        match self {
+            ResponseData::KeyValue(pairs) => pairs
+                .iter()
+                .map(|(k, v)| format!("{}: {}", k, v))
+                .collect::<Vec<_>>()
+                .join(" "),
+            ResponseData::Table { rows, .. } => rows
+                .iter()
+                .map(|row| row.join(" "))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+
+    /// Markdown rendering (Discord): one bold key per line, tables in a code
+    /// block with aligned columns.
+    pub fn render_markdown(&self) -> String {
+        match self {
+            ResponseData::KeyValue(pairs) => pairs
+                .iter()
+                .map(|(k, v)| format!("**{}:** {}", k, v))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            ResponseData::Table { headers, rows } => {
+                format!("```\n{}\n```", aligned_table(headers, rows))
+            }
+        }
+    }
+
+    /// HTML rendering (Telegram, dashboard). Values are escaped.
+    pub fn render_html(&self) -> String {
+        match self {
+            ResponseData::KeyValue(pairs) => pairs
+                .iter()
+                .map(|(k, v)| format!("<b>{}:</b> {}", escape_html(k), escape_html(v)))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            ResponseData::Table { headers, rows } => {
+                format!("<pre>{}</pre>", escape_html(&aligned_table(headers, rows)))
+            }
+        }
+    }
+}
+
+fn aligned_table(headers: &[String], rows: &[Vec<String>]) -> String {
+    let columns = std::iter::once(headers)
+        .chain(rows.iter().map(Vec::as_slice))
+        .map(<[String]>::len)
+        .max()
+        .unwrap_or(0);
+    let widths: Vec<usize> = (0..columns)
+        .map(|i| {
+            std::iter::once(headers)
+                .chain(rows.iter().map(Vec::as_slice))
+                .filter_map(|row| row.get(i))
+                .map(|cell| cell.chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    std::iter::once(headers)
+        .filter(|h| !h.is_empty())
+        .chain(rows.iter().map(Vec::as_slice))
+        .map(|row| {
+            row.iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                .collect::<Vec<_>>()
+                .join("  ")
+                .trim_end()
+                .to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
 #[derive(Debug, Clone)]
@@ -74,6 +196,33 @@ pub enum MeshEvent {
         lon: f64,
         altitude: i32,
     },
+    /// A text message that was not a bot command: public chat, or a DM
+    /// between other nodes that we overheard. `text` is empty for overheard
+    /// DMs on `[privacy] omit_dm_text_channels`.
+    TextMessage {
+        sender_id: u32,
+        to_node: Option<u32>,
+        channel: u32,
+        text: String,
+    },
+    /// A node's position report moved it into or out of a `[[geofence]]`.
+    GeofenceTransition {
+        node_id: u32,
+        fence: String,
+        entered: bool,
+        lat: f64,
+        lon: f64,
+        /// The fence's configured channel
+        channel: u32,
+    },
+    /// A node hasn't been heard for `[freshness] stale_mins`.
+    NodeOffline {
+        node_id: u32,
+        last_seen: i64,
+        via_mqtt: bool,
+    },
+    /// A node that had gone offline was heard again.
+    NodeBackOnline { node_id: u32, via_mqtt: bool },
 }
 
 #[cfg(test)]
@@ -111,4 +260,39 @@ mod tests {
         assert!(scope.allows(false)); // allows public
         assert!(scope.allows(true)); // allows DM
     }
+
+    fn table() -> ResponseData {
+        ResponseData::Table {
+            headers: vec!["Node".to_string(), "Hops".to_string()],
+            rows: vec![
+                vec!["Alice".to_string(), "1".to_string()],
+                vec!["Bob <b>".to_string(), "3".to_string()],
+            ],
+        }
+    }
+
+    #[test]
+    fn test_response_data_compact() {
+        let kv = ResponseData::KeyValue(vec![
+            ("RSSI".to_string(), "-70".to_string()),
+            ("SNR".to_string(), "5.5".to_string()),
+        ]);
+        assert_eq!(kv.render_compact(), "RSSI: -70 SNR: 5.5");
+        assert_eq!(table().render_compact(), "Alice 1\nBob <b> 3");
+    }
+
+    #[test]
+    fn test_response_data_rich() {
+        let kv = ResponseData::KeyValue(vec![("Msg".to_string(), "a<b".to_string())]);
+        assert_eq!(kv.render_markdown(), "**Msg:** a<b");
+        assert_eq!(kv.render_html(), "<b>Msg:</b> a&lt;b");
+        assert_eq!(
+            table().render_markdown(),
+            "```\nNode     Hops\nAlice    1\nBob <b>  3\n```"
+        );
+        assert_eq!(
+            table().render_html(),
+            "<pre>Node     Hops\nAlice    1\nBob &lt;b&gt;  3</pre>"
+        );
+    }
 }
@@ -0,0 +1,291 @@
+//! Counters and gauges for `/metrics`, in the Prometheus text format.
+//!
+//! Counters live in memory and start from zero with the process, as
+//! Prometheus expects; the bot, bridges and dashboard share one `Metrics`.
+//! `/api/health` reads the connection and bridge states from here too.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use crate::packet_type::PacketType;
+
+#[derive(Debug, Default, Clone)]
+pub struct ConnectionMetrics {
+    pub connected: bool,
+    pub reconnects: u64,
+}
+
+#[derive(Default)]
+pub struct Metrics {
+    /// Packets logged, by direction ("in"/"out") and type.
+    packets: Mutex<BTreeMap<(&'static str, &'static str), u64>>,
+    /// Messages relayed, by bridge and direction ("to_bridge"/"to_mesh").
+    bridge_messages: Mutex<BTreeMap<(String, &'static str), u64>>,
+    /// By `[[connection]]` label.
+    connections: Mutex<BTreeMap<String, ConnectionMetrics>>,
+    /// Whether each started bridge task is still running, by bridge name.
+    bridges: Mutex<BTreeMap<String, bool>>,
+    rate_limited: AtomicU64,
+}
+
+/// Gauges read from elsewhere when `/metrics` is scraped.
+pub struct Snapshot {
+    pub queue_depth: usize,
+    pub db_size_bytes: Option<u64>,
+    pub uptime_secs: u64,
+}
+
+impl Metrics {
+    pub fn packet(&self, direction: &'static str, packet_type: PacketType) {
+        *self
+            .packets
+            .lock()
+            .unwrap()
+            .entry((direction, packet_type.as_str()))
+            .or_default() += 1;
+    }
+
+    pub fn bridge_message(&self, bridge: &str, direction: &'static str) {
+        *self
+            .bridge_messages
+            .lock()
+            .unwrap()
+            .entry((bridge.to_string(), direction))
+            .or_default() += 1;
+    }
+
+    pub fn set_connected(&self, connection: &str, connected: bool) {
+        self.connections
+            .lock()
+            .unwrap()
+            .entry(connection.to_string())
+            .or_default()
+            .connected = connected;
+    }
+
+    pub fn reconnect(&self, connection: &str) {
+        self.connections
+            .lock()
+            .unwrap()
+            .entry(connection.to_string())
+            .or_default()
+            .reconnects += 1;
+    }
+
+    pub fn set_bridge_running(&self, bridge: &str, running: bool) {
+        self.bridges
+            .lock()
+            .unwrap()
+            .insert(bridge.to_string(), running);
+    }
+
+    /// Each connection seen so far, by label.
+    pub fn connections(&self) -> Vec<(String, ConnectionMetrics)> {
+        self.connections
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(label, c)| (label.clone(), c.clone()))
+            .collect()
+    }
+
+    /// Each started bridge and whether its task is still running.
+    pub fn bridges(&self) -> Vec<(String, bool)> {
+        self.bridges
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, running)| (name.clone(), *running))
+            .collect()
+    }
+
+    /// A command dropped by a rate limiter.
+    pub fn rate_limited(&self) {
+        self.rate_limited.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Everything in the Prometheus text exposition format.
+    pub fn render(&self, snapshot: &Snapshot) -> String {
+        let mut out = String::new();
+
+        header(
+            &mut out,
+            "meshenger_packets_total",
+            "counter",
+            "Packets received and sent, by type.",
+        );
+        for ((direction, packet_type), count) in self.packets.lock().unwrap().iter() {
+            let _ = writeln!(
+                out,
+                "meshenger_packets_total{{direction=\"{}\",type=\"{}\"}} {}",
+                direction, packet_type, count
+            );
+        }
+
+        header(
+            &mut out,
+            "meshenger_queue_depth",
+            "gauge",
+            "Messages waiting in the outgoing queue.",
+        );
+        let _ = writeln!(out, "meshenger_queue_depth {}", snapshot.queue_depth);
+
+        let connections = self.connections.lock().unwrap();
+        header(
+            &mut out,
+            "meshenger_connected",
+            "gauge",
+            "Whether a connection is up (1) or down (0).",
+        );
+        for (label, connection) in connections.iter() {
+            let _ = writeln!(
+                out,
+                "meshenger_connected{{connection=\"{}\"}} {}",
+                escape_label(label),
+                u8::from(connection.connected)
+            );
+        }
+        header(
+            &mut out,
+            "meshenger_reconnects_total",
+            "counter",
+            "Times a connection was lost and retried.",
+        );
+        for (label, connection) in connections.iter() {
+            let _ = writeln!(
+                out,
+                "meshenger_reconnects_total{{connection=\"{}\"}} {}",
+                escape_label(label),
+                connection.reconnects
+            );
+        }
+        drop(connections);
+
+        header(
+            &mut out,
+            "meshenger_bridge_messages_total",
+            "counter",
+            "Messages relayed by each bridge, by direction.",
+        );
+        for ((bridge, direction), count) in self.bridge_messages.lock().unwrap().iter() {
+            let _ = writeln!(
+                out,
+                "meshenger_bridge_messages_total{{bridge=\"{}\",direction=\"{}\"}} {}",
+                escape_label(bridge),
+                direction,
+                count
+            );
+        }
+
+        header(
+            &mut out,
+            "meshenger_bridge_up",
+            "gauge",
+            "Whether a bridge task is running (1) or has stopped (0).",
+        );
+        for (bridge, running) in self.bridges.lock().unwrap().iter() {
+            let _ = writeln!(
+                out,
+                "meshenger_bridge_up{{bridge=\"{}\"}} {}",
+                escape_label(bridge),
+                u8::from(*running)
+            );
+        }
+
+        header(
+            &mut out,
+            "meshenger_rate_limited_total",
+            "counter",
+            "Commands dropped by a rate limit.",
+        );
+        let _ = writeln!(
+            out,
+            "meshenger_rate_limited_total {}",
+            self.rate_limited.load(Ordering::Relaxed)
+        );
+
+        if let Some(size) = snapshot.db_size_bytes {
+            header(
+                &mut out,
+                "meshenger_db_size_bytes",
+                "gauge",
+                "Size of the SQLite database file.",
+            );
+            let _ = writeln!(out, "meshenger_db_size_bytes {}", size);
+        }
+
+        header(
+            &mut out,
+            "meshenger_uptime_seconds",
+            "gauge",
+            "Seconds since meshenger started.",
+        );
+        let _ = writeln!(out, "meshenger_uptime_seconds {}", snapshot.uptime_secs);
+
+        out
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let metrics = Metrics::default();
+        metrics.packet("in", PacketType::Text);
+        metrics.packet("in", PacketType::Text);
+        metrics.packet("out", PacketType::Traceroute);
+        metrics.bridge_message("telegram", "to_mesh");
+        metrics.set_connected("192.168.1.5:4403", true);
+        metrics.set_connected("roof \"east\"", false);
+        metrics.reconnect("roof \"east\"");
+        metrics.set_bridge_running("telegram", true);
+        metrics.set_bridge_running("signal", false);
+        metrics.rate_limited();
+
+        let text = metrics.render(&Snapshot {
+            queue_depth: 3,
+            db_size_bytes: Some(4096),
+            uptime_secs: 60,
+        });
+        for line in [
+            "# TYPE meshenger_packets_total counter",
+            "meshenger_packets_total{direction=\"in\",type=\"text\"} 2",
+            "meshenger_packets_total{direction=\"out\",type=\"traceroute\"} 1",
+            "meshenger_queue_depth 3",
+            "meshenger_connected{connection=\"192.168.1.5:4403\"} 1",
+            "meshenger_connected{connection=\"roof \\\"east\\\"\"} 0",
+            "meshenger_reconnects_total{connection=\"roof \\\"east\\\"\"} 1",
+            "meshenger_bridge_messages_total{bridge=\"telegram\",direction=\"to_mesh\"} 1",
+            "meshenger_bridge_up{bridge=\"telegram\"} 1",
+            "meshenger_bridge_up{bridge=\"signal\"} 0",
+            "meshenger_rate_limited_total 1",
+            "meshenger_db_size_bytes 4096",
+            "meshenger_uptime_seconds 60",
+        ] {
+            assert!(text.lines().any(|l| l == line), "missing {:?}", line);
+        }
+
+        let text = metrics.render(&Snapshot {
+            queue_depth: 0,
+            db_size_bytes: None,
+            uptime_secs: 0,
+        });
+        assert!(!text.contains("meshenger_db_size_bytes"));
+    }
+}
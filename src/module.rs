@@ -1,8 +1,16 @@
 use async_trait::async_trait;
+use std::sync::Arc;
 
 use crate::db::Db;
 use crate::message::{CommandScope, MeshEvent, MessageContext, Response};
 
+/// Per-node command limit: at most `max_commands` per `window_secs` (0 = unlimited).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub max_commands: usize,
+    pub window_secs: u64,
+}
+
 #[async_trait]
 pub trait Module: Send + Sync {
     fn name(&self) -> &str;
@@ -10,18 +18,33 @@ pub trait Module: Send + Sync {
     fn commands(&self) -> &[&str];
     fn scope(&self) -> CommandScope;
 
+    /// Limit for this module's commands that replaces the global
+    /// `bot.rate_limit_*`; `[modules.<name>] rate_limit` overrides it.
+    fn rate_limit(&self) -> Option<RateLimit> {
+        None
+    }
+
+    /// Whether `command` with these `args` is for `[bot] admin_nodes` only.
+    /// The bot checks the sender before `handle_command` is called, so
+    /// modules don't keep their own admin lists.
+    fn admin_only(&self, _command: &str, _args: &str) -> bool {
+        false
+    }
+
+    /// Answer a command. Database calls go through [`Db::run`] so they
+    /// don't block the async runtime.
     async fn handle_command(
         &self,
         command: &str,
         args: &str,
         ctx: &MessageContext,
-        db: &Db,
+        db: &Arc<Db>,
     ) -> Result<Option<Vec<Response>>, Box<dyn std::error::Error + Send + Sync>>;
 
     async fn handle_event(
         &self,
         _event: &MeshEvent,
-        _db: &Db,
+        _db: &Arc<Db>,
     ) -> Result<Option<Vec<Response>>, Box<dyn std::error::Error + Send + Sync>> {
         Ok(None)
     }
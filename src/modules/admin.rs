@@ -0,0 +1,182 @@
+use async_trait::async_trait;
+use std::sync::Arc;
+
+use crate::db::Db;
+use crate::message::{CommandScope, Destination, MessageContext, Response};
+use crate::module::Module;
+use crate::util::{format_bytes, parse_node_id};
+
+const USAGE: &str = "Usage: !admin queue | broadcast <ch> <text> | purge <node> | db stats | db vacuum | reload | node";
+/// Tables listed by `!admin db stats`, largest first.
+const MAX_TABLES: usize = 5;
+
+/// `!admin` for `[bot] admin_nodes`, by DM only. `queue`, `broadcast`,
+/// `db vacuum`, `reload` and `node` need the bot's queue and config or run in the
+/// background, and are answered by the bot (see `Bot::admin_command`); the
+/// rest lands here.
+pub struct AdminModule;
+
+impl AdminModule {
+    fn purge(target: &str, db: &Db) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        if target.is_empty() {
+            return Ok("Usage: !admin purge <node>".to_string());
+        }
+        // Nodes already gone from the node list may still have packets
+        let node_id = match db
+            .find_node_by_name(target)?
+            .or_else(|| parse_node_id(target))
+        {
+            Some(id) => id,
+            None => return Ok(format!("Unknown node: {}", target)),
+        };
+        let summary = db.purge_node_data(node_id)?;
+        log::info!("Purged data for !{:08x} by admin: {:?}", node_id, summary);
+        if summary.total() == 0 {
+            return Ok(format!("Nothing stored about !{:08x}.", node_id));
+        }
+        Ok(format!("Purged !{:08x}: {}.", node_id, summary.describe()))
+    }
+
+    /// `!admin db stats`: file size and the biggest tables.
+    fn db_stats(db: &Db) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let stats = db.db_stats()?;
+        let tables: Vec<String> = stats
+            .tables
+            .iter()
+            .take(MAX_TABLES)
+            .map(|t| format!("{} {}", t.name, t.rows))
+            .collect();
+        Ok(format!(
+            "DB {} ({} free). {}",
+            format_bytes(stats.size_bytes),
+            format_bytes(stats.free_bytes),
+            tables.join(", ")
+        ))
+    }
+}
+
+#[async_trait]
+impl Module for AdminModule {
+    fn name(&self) -> &str {
+        "admin"
+    }
+
+    fn description(&self) -> &str {
+        "Bot administration (admins only)"
+    }
+
+    fn commands(&self) -> &[&str] {
+        &["admin"]
+    }
+
+    fn scope(&self) -> CommandScope {
+        CommandScope::DM
+    }
+
+    fn admin_only(&self, _command: &str, _args: &str) -> bool {
+        true
+    }
+
+    async fn handle_command(
+        &self,
+        _command: &str,
+        args: &str,
+        ctx: &MessageContext,
+        db: &Arc<Db>,
+    ) -> Result<Option<Vec<Response>>, Box<dyn std::error::Error + Send + Sync>> {
+        let (subcommand, rest) = match args.split_once(' ') {
+            Some((sub, rest)) => (sub, rest.trim()),
+            None => (args, ""),
+        };
+        let text = match subcommand.to_lowercase().as_str() {
+            "purge" => {
+                let target = rest.to_string();
+                db.run(move |db| Self::purge(&target, db)).await?
+            }
+            "db" if rest.eq_ignore_ascii_case("stats") => db.run(Self::db_stats).await?,
+            "db" => "Usage: !admin db stats | db vacuum".to_string(),
+            _ => USAGE.to_string(),
+        };
+        Ok(Some(vec![Response {
+            text,
+            destination: Destination::Sender,
+            channel: ctx.channel,
+            reply_id: None,
+            data: None,
+        }]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    const ADMIN: u32 = 0x11111111;
+    const TARGET: u32 = 0x33333333;
+
+    fn test_context(sender_id: u32) -> MessageContext {
+        MessageContext {
+            sender_id,
+            sender_name: "TestNode".to_string(),
+            channel: 0,
+            is_dm: true,
+            rssi: -70,
+            snr: 5.0,
+            hop_count: 1,
+            hop_start: 3,
+            hop_limit: 2,
+            via_mqtt: false,
+            pki_key: None,
+            packet_id: 0,
+        }
+    }
+
+    async fn run(module: &AdminModule, args: &str, from: u32, db: &Arc<Db>) -> Option<String> {
+        module
+            .handle_command("admin", args, &test_context(from), db)
+            .await
+            .unwrap()
+            .map(|mut r| r.remove(0).text)
+    }
+
+    #[tokio::test]
+    async fn test_purge_by_admin_only() {
+        let module = AdminModule;
+        let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+        db.upsert_node(TARGET, "TGT", "Target", false).unwrap();
+
+        // The bot checks the sender is an admin
+        assert!(module.admin_only("admin", "purge TGT"));
+        assert_eq!(
+            run(&module, "purge TGT", ADMIN, &db).await.unwrap(),
+            "Purged !33333333: 1 nodes."
+        );
+        assert_eq!(db.find_node_by_name("TGT").unwrap(), None);
+        assert_eq!(
+            run(&module, "purge !33333333", ADMIN, &db).await.unwrap(),
+            "Nothing stored about !33333333."
+        );
+        assert_eq!(
+            run(&module, "purge nobody", ADMIN, &db).await.unwrap(),
+            "Unknown node: nobody"
+        );
+        assert_eq!(run(&module, "", ADMIN, &db).await.unwrap(), USAGE);
+    }
+
+    #[tokio::test]
+    async fn test_db_stats() {
+        let module = AdminModule;
+        let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+        db.upsert_node(TARGET, "TGT", "Target", false).unwrap();
+
+        let text = run(&module, "db stats", ADMIN, &db).await.unwrap();
+        assert!(text.starts_with("DB "), "{}", text);
+        assert!(text.contains("nodes 1"), "{}", text);
+        assert!(text.len() <= 220);
+        assert_eq!(
+            run(&module, "db", ADMIN, &db).await.unwrap(),
+            "Usage: !admin db stats | db vacuum"
+        );
+    }
+}
@@ -0,0 +1,265 @@
+use async_trait::async_trait;
+use std::sync::Arc;
+
+use crate::db::{AwayNode, Db};
+use crate::message::{CommandScope, Destination, MeshEvent, MessageContext, Response};
+use crate::module::Module;
+
+const DEFAULT_AWAY_MESSAGE: &str = "I'm away right now.";
+const MAX_AWAY_MESSAGE_LEN: usize = 160;
+
+pub struct AwayModule;
+
+/// Whether `word` occurs in `text` without a letter, digit or `_` right
+/// before or after it, so "bob" isn't found in "bobcat".
+fn contains_word(text: &str, word: &str) -> bool {
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+    text.match_indices(word).any(|(start, _)| {
+        let before = text[..start].chars().next_back();
+        let after = text[start + word.len()..].chars().next();
+        !before.is_some_and(is_word_char) && !after.is_some_and(is_word_char)
+    })
+}
+
+impl AwayModule {
+    /// Whether a public message refers to an away node: its `!id`, `@short`
+    /// name, or full long name (case-insensitive, as whole words).
+    fn is_mentioned(node: &AwayNode, text: &str) -> bool {
+        let text = text.to_lowercase();
+        if contains_word(&text, &format!("!{:08x}", node.node_id)) {
+            return true;
+        }
+        if !node.short_name.is_empty()
+            && contains_word(&text, &format!("@{}", node.short_name.to_lowercase()))
+        {
+            return true;
+        }
+        !node.long_name.is_empty() && contains_word(&text, &node.long_name.to_lowercase())
+    }
+
+    fn display_name(node: &AwayNode) -> String {
+        if !node.long_name.is_empty() {
+            node.long_name.clone()
+        } else if !node.short_name.is_empty() {
+            node.short_name.clone()
+        } else {
+            format!("!{:08x}", node.node_id)
+        }
+    }
+}
+
+#[async_trait]
+impl Module for AwayModule {
+    fn name(&self) -> &str {
+        "away"
+    }
+
+    fn description(&self) -> &str {
+        "Away auto-reply"
+    }
+
+    fn commands(&self) -> &[&str] {
+        &["away", "back"]
+    }
+
+    fn scope(&self) -> CommandScope {
+        CommandScope::Both
+    }
+
+    async fn handle_command(
+        &self,
+        command: &str,
+        args: &str,
+        ctx: &MessageContext,
+        db: &Arc<Db>,
+    ) -> Result<Option<Vec<Response>>, Box<dyn std::error::Error + Send + Sync>> {
+        let sender = ctx.sender_id;
+        let text = match command {
+            "away" => {
+                let message = if args.is_empty() {
+                    DEFAULT_AWAY_MESSAGE.to_string()
+                } else {
+                    args.chars().take(MAX_AWAY_MESSAGE_LEN).collect()
+                };
+                let away = message.clone();
+                db.run(move |db| db.set_away(sender, &away)).await?;
+                format!("Away set: {}", message)
+            }
+            "back" => match db.run(move |db| db.clear_away(sender)).await? {
+                Some(0) => "Welcome back! Nobody was auto-replied.".to_string(),
+                Some(n) => format!("Welcome back! Sent {} auto-reply(s) while away.", n),
+                None => "You were not marked away.".to_string(),
+            },
+            _ => return Ok(None),
+        };
+
+        Ok(Some(vec![Response {
+            text,
+            destination: Destination::Sender,
+            channel: ctx.channel,
+            reply_id: None,
+            data: None,
+        }]))
+    }
+
+    async fn handle_event(
+        &self,
+        event: &MeshEvent,
+        db: &Arc<Db>,
+    ) -> Result<Option<Vec<Response>>, Box<dyn std::error::Error + Send + Sync>> {
+        let (sender_id, to_node, channel, text) = match event {
+            MeshEvent::TextMessage {
+                sender_id,
+                to_node,
+                channel,
+                text,
+            } => (*sender_id, *to_node, *channel, text.clone()),
+            _ => return Ok(None),
+        };
+
+        let responses = db
+            .run(move |db| {
+                let mut responses = Vec::new();
+                for node in &db.get_away_nodes()? {
+                    if node.node_id == sender_id {
+                        continue;
+                    }
+                    let addressed = match to_node {
+                        Some(to) => to == node.node_id,
+                        None => Self::is_mentioned(node, &text),
+                    };
+                    if addressed && db.mark_away_reply(node.node_id, sender_id)? {
+                        responses.push(Response {
+                            text: format!("{} is away: {}", Self::display_name(node), node.message),
+                            destination: Destination::Sender,
+                            channel,
+                            reply_id: None,
+                            data: None,
+                        });
+                    }
+                }
+                Ok(responses)
+            })
+            .await?;
+
+        Ok(if responses.is_empty() {
+            None
+        } else {
+            Some(responses)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    const AWAY_NODE: u32 = 0x11111111;
+    const OTHER_NODE: u32 = 0x22222222;
+
+    fn test_context(sender_id: u32) -> MessageContext {
+        MessageContext {
+            sender_id,
+            sender_name: "TestNode".to_string(),
+            channel: 0,
+            is_dm: true,
+            rssi: -70,
+            snr: 5.0,
+            hop_count: 1,
+            hop_start: 3,
+            hop_limit: 2,
+            via_mqtt: false,
+            pki_key: None,
+            packet_id: 0,
+        }
+    }
+
+    fn text_event(sender_id: u32, to_node: Option<u32>, text: &str) -> MeshEvent {
+        MeshEvent::TextMessage {
+            sender_id,
+            to_node,
+            channel: 0,
+            text: text.to_string(),
+        }
+    }
+
+    async fn setup_away(db: &Arc<Db>) {
+        db.upsert_node(AWAY_NODE, "ALCE", "Alice", false).unwrap();
+        AwayModule
+            .handle_command("away", "Hiking until Sunday", &test_context(AWAY_NODE), db)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_public_mention_replies_once() {
+        let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+        setup_away(&db).await;
+
+        let event = text_event(OTHER_NODE, None, "anyone heard from alice?");
+        let responses = AwayModule.handle_event(&event, &db).await.unwrap().unwrap();
+        assert_eq!(responses.len(), 1);
+        assert_eq!(responses[0].text, "Alice is away: Hiking until Sunday");
+
+        // Second mention from the same sender is ignored
+        assert!(AwayModule
+            .handle_event(&event, &db)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_mentions_match_whole_words() {
+        let node = AwayNode {
+            node_id: AWAY_NODE,
+            short_name: "BOB".to_string(),
+            long_name: "Bob".to_string(),
+            message: String::new(),
+        };
+        assert!(AwayModule::is_mentioned(&node, "Is Bob around?"));
+        assert!(AwayModule::is_mentioned(&node, "@bob, ping"));
+        assert!(AwayModule::is_mentioned(&node, "ask !11111111"));
+        assert!(!AwayModule::is_mentioned(&node, "saw a bobcat"));
+        assert!(!AwayModule::is_mentioned(&node, "@bobby hi"));
+        assert!(!AwayModule::is_mentioned(&node, "!111111112 is not it"));
+    }
+
+    #[tokio::test]
+    async fn test_dm_to_away_node_and_unrelated_text() {
+        let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+        setup_away(&db).await;
+
+        let unrelated = text_event(OTHER_NODE, None, "good morning mesh");
+        assert!(AwayModule
+            .handle_event(&unrelated, &db)
+            .await
+            .unwrap()
+            .is_none());
+
+        let dm = text_event(OTHER_NODE, Some(AWAY_NODE), "hey");
+        assert!(AwayModule.handle_event(&dm, &db).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_back_clears_and_reports() {
+        let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+        setup_away(&db).await;
+        AwayModule
+            .handle_event(&text_event(OTHER_NODE, None, "@alce ping"), &db)
+            .await
+            .unwrap();
+
+        let responses = AwayModule
+            .handle_command("back", "", &test_context(AWAY_NODE), &db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            responses[0].text,
+            "Welcome back! Sent 1 auto-reply(s) while away."
+        );
+        assert!(db.get_away_nodes().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,149 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use std::sync::Arc;
+
+use crate::db::{Db, EnvironmentReading};
+use crate::message::{CommandScope, Destination, MessageContext, Response};
+use crate::module::Module;
+use crate::util::format_ago;
+
+/// How many sensors `!env` without a node lists.
+const SENSORS_SHOWN: usize = 5;
+
+pub struct EnvModule;
+
+impl EnvModule {
+    fn format_reading(reading: &EnvironmentReading) -> String {
+        let mut parts = Vec::new();
+        if let Some(t) = reading.temperature {
+            parts.push(format!("{:.1}°C", t));
+        }
+        if let Some(h) = reading.relative_humidity {
+            parts.push(format!("{:.0}% RH", h));
+        }
+        if let Some(p) = reading.barometric_pressure {
+            parts.push(format!("{:.1} hPa", p));
+        }
+        parts.join(", ")
+    }
+
+    fn env(args: &str, db: &Db) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let now = Utc::now().timestamp();
+        if args.is_empty() {
+            let sensors = db.environment_sensors()?;
+            if sensors.is_empty() {
+                return Ok("No environment sensors heard yet.".to_string());
+            }
+            let lines: Vec<String> = sensors
+                .iter()
+                .take(SENSORS_SHOWN)
+                .map(|s| {
+                    let name = if s.short_name.is_empty() {
+                        &s.node_id
+                    } else {
+                        &s.short_name
+                    };
+                    format!(
+                        "{}: {} ({})",
+                        name,
+                        Self::format_reading(&s.latest),
+                        format_ago(now - s.latest.timestamp)
+                    )
+                })
+                .collect();
+            return Ok(lines.join("\n"));
+        }
+
+        let node_id = match db.find_node_by_name(args)? {
+            Some(id) => id,
+            None => return Ok(format!("Unknown node: {}", args)),
+        };
+        let name = db.get_node_name(node_id)?;
+        let latest = db
+            .environment_sensors()?
+            .into_iter()
+            .find(|s| s.node_id == format!("!{:08x}", node_id));
+        Ok(match latest {
+            Some(sensor) => format!(
+                "{} ({}): {}",
+                name,
+                format_ago(now - sensor.latest.timestamp),
+                Self::format_reading(&sensor.latest)
+            ),
+            None => format!("No environment data from {}", name),
+        })
+    }
+}
+
+#[async_trait]
+impl Module for EnvModule {
+    fn name(&self) -> &str {
+        "env"
+    }
+
+    fn description(&self) -> &str {
+        "Environment sensor readings"
+    }
+
+    fn commands(&self) -> &[&str] {
+        &["env"]
+    }
+
+    fn scope(&self) -> CommandScope {
+        CommandScope::Both
+    }
+
+    async fn handle_command(
+        &self,
+        _command: &str,
+        args: &str,
+        ctx: &MessageContext,
+        db: &Arc<Db>,
+    ) -> Result<Option<Vec<Response>>, Box<dyn std::error::Error + Send + Sync>> {
+        let args = args.trim().to_string();
+        let text = db.run(move |db| Self::env(&args, db)).await?;
+        Ok(Some(vec![Response {
+            text,
+            destination: Destination::Sender,
+            channel: ctx.channel,
+            reply_id: None,
+            data: None,
+        }]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    const PUMP: u32 = 0x11111111;
+    const ROOF: u32 = 0x22222222;
+
+    #[test]
+    fn test_env_for_node_and_sensor_list() {
+        let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+        db.upsert_node(PUMP, "PUMP", "Pump House", false).unwrap();
+        db.upsert_node(ROOF, "ROOF", "Roof Station", false).unwrap();
+        assert_eq!(
+            EnvModule::env("", &db).unwrap(),
+            "No environment sensors heard yet."
+        );
+
+        db.log_environment(PUMP, Some(-1.25), Some(81.0), Some(1012.34))
+            .unwrap();
+        assert_eq!(
+            EnvModule::env("pump", &db).unwrap(),
+            "Pump House (0s ago): -1.2°C, 81% RH, 1012.3 hPa"
+        );
+        assert_eq!(
+            EnvModule::env("", &db).unwrap(),
+            "PUMP: -1.2°C, 81% RH, 1012.3 hPa (0s ago)"
+        );
+        assert_eq!(
+            EnvModule::env("ROOF", &db).unwrap(),
+            "No environment data from Roof Station"
+        );
+        assert_eq!(EnvModule::env("nope", &db).unwrap(), "Unknown node: nope");
+    }
+}
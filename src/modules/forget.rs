@@ -0,0 +1,154 @@
+use async_trait::async_trait;
+use std::sync::Arc;
+
+use crate::db::Db;
+use crate::message::{CommandScope, Destination, MessageContext, Response};
+use crate::module::Module;
+
+/// `!forgetme`: delete what the bot stored about the sender, after a
+/// `confirm`.
+pub struct ForgetModule {
+    /// `[bot] command_prefix`, for the confirmation prompt
+    prefix: String,
+}
+
+impl ForgetModule {
+    pub fn new(prefix: &str) -> Self {
+        Self {
+            prefix: prefix.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl Module for ForgetModule {
+    fn name(&self) -> &str {
+        "forgetme"
+    }
+
+    fn description(&self) -> &str {
+        "Delete your stored data"
+    }
+
+    fn commands(&self) -> &[&str] {
+        &["forgetme"]
+    }
+
+    fn scope(&self) -> CommandScope {
+        CommandScope::DM
+    }
+
+    async fn handle_command(
+        &self,
+        _command: &str,
+        args: &str,
+        ctx: &MessageContext,
+        db: &Arc<Db>,
+    ) -> Result<Option<Vec<Response>>, Box<dyn std::error::Error + Send + Sync>> {
+        let text = if args.trim().eq_ignore_ascii_case("confirm") {
+            let node_id = ctx.sender_id;
+            let summary = db.run(move |db| db.purge_node_data(node_id)).await?;
+            log::info!(
+                "Purged data for !{:08x} on request: {:?}",
+                ctx.sender_id,
+                summary
+            );
+            if summary.total() == 0 {
+                "Nothing stored about your node.".to_string()
+            } else {
+                format!(
+                    "Forgotten: {}. New traffic from your node will still be logged.",
+                    summary.describe()
+                )
+            }
+        } else {
+            format!(
+                "This deletes your node info, position, packets and mail from this bot. Send {}forgetme confirm to proceed.",
+                self.prefix
+            )
+        };
+
+        Ok(Some(vec![Response {
+            text,
+            destination: Destination::Sender,
+            channel: ctx.channel,
+            reply_id: None,
+            data: None,
+        }]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet_type::PacketType;
+    use std::path::Path;
+
+    fn test_context() -> MessageContext {
+        MessageContext {
+            sender_id: 0x12345678,
+            sender_name: "TestNode".to_string(),
+            channel: 0,
+            is_dm: true,
+            rssi: -70,
+            snr: 5.0,
+            hop_count: 1,
+            hop_start: 3,
+            hop_limit: 2,
+            via_mqtt: false,
+            pki_key: None,
+            packet_id: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_forgetme_requires_confirmation() {
+        let module = ForgetModule::new("!");
+        let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+        let ctx = test_context();
+        db.upsert_node(ctx.sender_id, "TN", "TestNode", false)
+            .unwrap();
+
+        let responses = module
+            .handle_command("forgetme", "", &ctx, &db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(responses[0].text.contains("!forgetme confirm"));
+        assert_eq!(db.node_count().unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_forgetme_confirm_purges_sender() {
+        let module = ForgetModule::new("!");
+        let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+        let ctx = test_context();
+        db.upsert_node(ctx.sender_id, "TN", "TestNode", false)
+            .unwrap();
+        db.log_packet(
+            ctx.sender_id,
+            None,
+            0,
+            "hello",
+            "in",
+            false,
+            None,
+            None,
+            None,
+            None,
+            PacketType::Text,
+        )
+        .unwrap();
+
+        let responses = module
+            .handle_command("forgetme", "confirm", &ctx, &db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(responses[0]
+            .text
+            .starts_with("Forgotten: 1 nodes, 1 packets."));
+        assert_eq!(db.node_count().unwrap(), 0);
+        assert_eq!(db.message_count("in").unwrap(), 0);
+    }
+}
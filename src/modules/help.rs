@@ -1,4 +1,5 @@
 use async_trait::async_trait;
+use std::sync::Arc;
 
 use crate::db::Db;
 use crate::message::{CommandScope, Destination, MessageContext, Response};
@@ -29,7 +30,7 @@ impl Module for HelpModule {
         _command: &str,
         _args: &str,
         ctx: &MessageContext,
-        _db: &Db,
+        _db: &Arc<Db>,
     ) -> Result<Option<Vec<Response>>, Box<dyn std::error::Error + Send + Sync>> {
         // The help text is injected by the bot when it calls this module,
         // since the module itself doesn't have access to the registry.
@@ -39,6 +40,7 @@ impl Module for HelpModule {
             destination: Destination::Sender,
             channel: ctx.channel,
             reply_id: None,
+            data: None,
         }]))
     }
 }
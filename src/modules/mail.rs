@@ -0,0 +1,311 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use std::sync::Arc;
+
+use crate::db::Db;
+use crate::event_hooks::{EventHooks, HookEvent};
+use crate::message::{CommandScope, Destination, MessageContext, Response};
+use crate::module::Module;
+use crate::util::format_ago;
+
+/// How many unread mails `!inbox` delivers per call.
+const INBOX_BATCH: usize = 3;
+
+pub struct MailModule {
+    max_unread_per_recipient: usize,
+    max_body_len: usize,
+    /// Number stored mail and have the bot tell senders when it was read.
+    read_receipts: bool,
+    hooks: EventHooks,
+}
+
+impl MailModule {
+    pub fn new(
+        max_unread_per_recipient: usize,
+        max_body_len: usize,
+        read_receipts: bool,
+        hooks: EventHooks,
+    ) -> Self {
+        Self {
+            max_unread_per_recipient,
+            max_body_len,
+            read_receipts,
+            hooks,
+        }
+    }
+
+    async fn send(
+        &self,
+        args: &str,
+        ctx: &MessageContext,
+        db: &Arc<Db>,
+    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let (target, body) = match args.split_once(' ') {
+            Some((target, body)) if !body.trim().is_empty() => (target, body.trim()),
+            _ => return Ok("Usage: !mail <node> <message>".to_string()),
+        };
+
+        let name_query = target.to_string();
+        let to_node = match db.run(move |db| db.find_node_by_name(&name_query)).await? {
+            Some(id) => id,
+            None => return Ok(format!("Unknown node: {}", target)),
+        };
+
+        let body_len = body.chars().count();
+        if self.max_body_len > 0 && body_len > self.max_body_len {
+            return Ok(format!(
+                "Message too long ({}/{} chars)",
+                body_len, self.max_body_len
+            ));
+        }
+
+        let (name, unread) = db
+            .run(move |db| Ok((db.get_node_name(to_node)?, db.unread_mail_count(to_node)?)))
+            .await?;
+        if self.max_unread_per_recipient > 0 && unread as usize >= self.max_unread_per_recipient {
+            return Ok(format!("Mailbox of {} is full, try again later", name));
+        }
+
+        let (from, body) = (ctx.sender_id, body.to_string());
+        let (id, away) = db
+            .run(move |db| {
+                let id = db.store_mail(from, to_node, &body)?;
+                // The one-time away auto-reply, as for a DM to the recipient
+                let away = match db.away_message(to_node)? {
+                    Some(message)
+                        if from != to_node
+                            && !db.is_muted(from)?
+                            && db.mark_away_reply(to_node, from)? =>
+                    {
+                        Some(message)
+                    }
+                    _ => None,
+                };
+                Ok((id, away))
+            })
+            .await?;
+        self.hooks.emit(HookEvent::MailStored {
+            from: format!("!{:08x}", ctx.sender_id),
+            to: format!("!{:08x}", to_node),
+        });
+        // The number is how the read receipt refers to this mail
+        let mut reply = if self.read_receipts {
+            format!("Mail #{} stored for {}", id, name)
+        } else {
+            format!("Mail stored for {}", name)
+        };
+        if let Some(message) = away {
+            reply.push_str(&format!("\n{} is away: {}", name, message));
+        }
+        Ok(reply)
+    }
+
+    async fn inbox(
+        &self,
+        ctx: &MessageContext,
+        db: &Arc<Db>,
+    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let node_id = ctx.sender_id;
+        let read_receipts = self.read_receipts;
+        db.run(move |db| {
+            let mails = db.take_unread_mail(node_id, INBOX_BATCH, read_receipts)?;
+            if mails.is_empty() {
+                return Ok("No new mail.".to_string());
+            }
+
+            let now = Utc::now().timestamp();
+            let mut lines: Vec<String> = mails
+                .iter()
+                .map(|m| {
+                    let from = db
+                        .get_node_name(m.from_node)
+                        .unwrap_or_else(|_| format!("!{:08x}", m.from_node));
+                    format!("{} ({}): {}", from, format_ago(now - m.timestamp), m.body)
+                })
+                .collect();
+            let remaining = db.unread_mail_count(node_id)?;
+            if remaining > 0 {
+                lines.push(format!("{} more, send !inbox again", remaining));
+            }
+            Ok(lines.join("\n"))
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl Module for MailModule {
+    fn name(&self) -> &str {
+        "mail"
+    }
+
+    fn description(&self) -> &str {
+        "Store-and-forward mail"
+    }
+
+    fn commands(&self) -> &[&str] {
+        &["mail", "inbox"]
+    }
+
+    fn scope(&self) -> CommandScope {
+        CommandScope::DM
+    }
+
+    async fn handle_command(
+        &self,
+        command: &str,
+        args: &str,
+        ctx: &MessageContext,
+        db: &Arc<Db>,
+    ) -> Result<Option<Vec<Response>>, Box<dyn std::error::Error + Send + Sync>> {
+        let text = match command {
+            "mail" => self.send(args, ctx, db).await?,
+            "inbox" => self.inbox(ctx, db).await?,
+            _ => return Ok(None),
+        };
+
+        Ok(Some(vec![Response {
+            text,
+            destination: Destination::Sender,
+            channel: ctx.channel,
+            reply_id: None,
+            data: None,
+        }]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    const SENDER: u32 = 0x11111111;
+    const RECIPIENT: u32 = 0x22222222;
+
+    fn test_context(sender_id: u32) -> MessageContext {
+        MessageContext {
+            sender_id,
+            sender_name: "TestNode".to_string(),
+            channel: 0,
+            is_dm: true,
+            rssi: -70,
+            snr: 5.0,
+            hop_count: 1,
+            hop_start: 3,
+            hop_limit: 2,
+            via_mqtt: false,
+            pki_key: None,
+            packet_id: 0,
+        }
+    }
+
+    fn setup_db() -> Arc<Db> {
+        let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+        db.upsert_node(SENDER, "SEND", "Sender", false).unwrap();
+        db.upsert_node(RECIPIENT, "RCPT", "Recipient", false)
+            .unwrap();
+        db
+    }
+
+    async fn run(
+        module: &MailModule,
+        command: &str,
+        args: &str,
+        from: u32,
+        db: &Arc<Db>,
+    ) -> String {
+        module
+            .handle_command(command, args, &test_context(from), db)
+            .await
+            .unwrap()
+            .unwrap()
+            .remove(0)
+            .text
+    }
+
+    #[tokio::test]
+    async fn test_mail_roundtrip() {
+        let module = MailModule::new(10, 200, false, EventHooks::default());
+        let db = setup_db();
+
+        let reply = run(&module, "mail", "RCPT hello there", SENDER, &db).await;
+        assert_eq!(reply, "Mail stored for Recipient");
+
+        let inbox = run(&module, "inbox", "", RECIPIENT, &db).await;
+        assert!(inbox.starts_with("Sender ("));
+        assert!(inbox.ends_with("hello there"));
+        assert_eq!(
+            run(&module, "inbox", "", RECIPIENT, &db).await,
+            "No new mail."
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mail_numbered_with_read_receipts() {
+        let module = MailModule::new(10, 200, true, EventHooks::default());
+        let db = setup_db();
+
+        let reply = run(&module, "mail", "RCPT hello there", SENDER, &db).await;
+        assert_eq!(reply, "Mail #1 stored for Recipient");
+        run(&module, "inbox", "", RECIPIENT, &db).await;
+        assert_eq!(db.take_mail_receipts(SENDER).unwrap(), vec![(1, RECIPIENT)]);
+    }
+
+    #[tokio::test]
+    async fn test_mail_to_away_node_replies_once() {
+        let module = MailModule::new(10, 200, false, EventHooks::default());
+        let db = setup_db();
+        db.set_away(RECIPIENT, "Hiking until Sunday").unwrap();
+
+        let reply = run(&module, "mail", "RCPT hello", SENDER, &db).await;
+        assert_eq!(
+            reply,
+            "Mail stored for Recipient\nRecipient is away: Hiking until Sunday"
+        );
+        let reply = run(&module, "mail", "RCPT again", SENDER, &db).await;
+        assert_eq!(reply, "Mail stored for Recipient");
+        assert_eq!(db.clear_away(RECIPIENT).unwrap(), Some(1));
+    }
+
+    #[tokio::test]
+    async fn test_mail_body_length_limit() {
+        let module = MailModule::new(10, 5, false, EventHooks::default());
+        let db = setup_db();
+
+        let reply = run(&module, "mail", "RCPT too long body", SENDER, &db).await;
+        assert_eq!(reply, "Message too long (13/5 chars)");
+        assert_eq!(db.unread_mail_count(RECIPIENT).unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_mail_unread_quota() {
+        let module = MailModule::new(2, 200, false, EventHooks::default());
+        let db = setup_db();
+
+        run(&module, "mail", "RCPT one", SENDER, &db).await;
+        run(&module, "mail", "RCPT two", SENDER, &db).await;
+        let reply = run(&module, "mail", "RCPT three", SENDER, &db).await;
+        assert_eq!(reply, "Mailbox of Recipient is full, try again later");
+        assert_eq!(db.unread_mail_count(RECIPIENT).unwrap(), 2);
+
+        // Reading frees up space
+        run(&module, "inbox", "", RECIPIENT, &db).await;
+        let reply = run(&module, "mail", "RCPT three", SENDER, &db).await;
+        assert_eq!(reply, "Mail stored for Recipient");
+    }
+
+    #[tokio::test]
+    async fn test_mail_unknown_node_and_usage() {
+        let module = MailModule::new(10, 200, false, EventHooks::default());
+        let db = setup_db();
+
+        assert_eq!(
+            run(&module, "mail", "NOPE hi", SENDER, &db).await,
+            "Unknown node: NOPE"
+        );
+        assert_eq!(
+            run(&module, "mail", "RCPT", SENDER, &db).await,
+            "Usage: !mail <node> <message>"
+        );
+    }
+}
@@ -1,40 +1,111 @@
+mod admin;
+mod away;
+mod env;
+mod forget;
 mod help;
+mod mail;
+mod mute;
+mod netlog;
 mod node_info;
+mod notes;
 mod ping;
+mod poll;
+mod position;
+mod queue;
+mod stats;
+mod traceroute;
 mod uptime;
 mod weather;
 mod welcome;
 
 use crate::config::Config;
+use crate::event_hooks::EventHooks;
 use crate::module::ModuleRegistry;
 
-pub fn build_registry(config: &Config) -> ModuleRegistry {
+pub(crate) use poll::closed_text as poll_closed_text;
+pub(crate) use weather::{fetch_nws_alerts, AlertSeverity, WeatherAlert};
+
+/// Modules enabled in `config`; `hooks` receives events they raise (`!mail`).
+pub fn build_registry(config: &Config, hooks: &EventHooks) -> ModuleRegistry {
     let mut registry = ModuleRegistry::new();
 
     if config.is_module_enabled("ping") {
         registry.register(Box::new(ping::PingModule));
     }
     if config.is_module_enabled("nodes") {
-        registry.register(Box::new(node_info::NodeInfoModule));
+        registry.register(Box::new(node_info::NodeInfoModule::new(
+            config.freshness.clone(),
+        )));
     }
     if config.is_module_enabled("weather") {
         registry.register(Box::new(weather::WeatherModule::new(
             config.weather.latitude,
             config.weather.longitude,
             config.weather.units.clone(),
+            config.api_quota("weather"),
         )));
     }
     if config.is_module_enabled("welcome") {
-        registry.register(Box::new(welcome::WelcomeModule::new(
-            config.welcome.message.clone(),
-            config.welcome.welcome_back_message.clone(),
-            config.welcome.absence_threshold_hours,
-            config.welcome.whitelist.clone(),
-        )));
+        registry.register(Box::new(
+            welcome::WelcomeModule::new(
+                config.welcome.message.clone(),
+                config.welcome.welcome_back_message.clone(),
+                config.welcome.absence_threshold_hours,
+                config.welcome.whitelist.clone(),
+                config.welcome.rules.clone(),
+            )
+            .with_quiet_hours(&config.welcome.quiet_hours),
+        ));
     }
     if config.is_module_enabled("uptime") {
         registry.register(Box::new(uptime::UptimeModule::new()));
     }
+    if config.is_module_enabled("away") {
+        registry.register(Box::new(away::AwayModule));
+    }
+    if config.is_module_enabled("mail") {
+        registry.register(Box::new(mail::MailModule::new(
+            config.mail.max_unread_per_recipient,
+            config.mail.max_body_len,
+            config.mail.read_receipts,
+            hooks.clone(),
+        )));
+    }
+    if config.is_module_enabled("note") {
+        registry.register(Box::new(notes::NotesModule));
+    }
+    if config.is_module_enabled("poll") {
+        registry.register(Box::new(poll::PollModule));
+    }
+    if config.is_module_enabled("netlog") {
+        registry.register(Box::new(netlog::NetlogModule::new(&config.netlog.nets)));
+    }
+    if config.is_module_enabled("forgetme") {
+        registry.register(Box::new(forget::ForgetModule::new(
+            &config.bot.command_prefix,
+        )));
+    }
+    if config.is_module_enabled("mute") {
+        registry.register(Box::new(mute::MuteModule));
+    }
+    if config.is_module_enabled("env") {
+        registry.register(Box::new(env::EnvModule));
+    }
+    if config.is_module_enabled("stats") {
+        registry.register(Box::new(stats::StatsModule));
+    }
+    if config.is_module_enabled("position") {
+        registry.register(Box::new(position::PositionModule));
+    }
+    if config.is_module_enabled("traceroute") {
+        registry.register(Box::new(traceroute::TracerouteModule));
+    }
+    if config.is_module_enabled("admin") {
+        registry.register(Box::new(admin::AdminModule));
+    }
+    if config.is_module_enabled("queue") {
+        registry.register(Box::new(queue::QueueModule));
+    }
     if config.is_module_enabled("help") {
         registry.register(Box::new(help::HelpModule));
     }
@@ -0,0 +1,113 @@
+use async_trait::async_trait;
+use std::sync::Arc;
+
+use crate::db::Db;
+use crate::message::{CommandScope, Destination, MessageContext, Response};
+use crate::module::Module;
+
+/// `!mute` / `!unmute`: opt a node out of messages it didn't ask for
+/// (welcomes, mail notices, away auto-replies). Replies to its own commands
+/// still go out; the bot checks the flag where it sends the rest.
+pub struct MuteModule;
+
+#[async_trait]
+impl Module for MuteModule {
+    fn name(&self) -> &str {
+        "mute"
+    }
+
+    fn description(&self) -> &str {
+        "Stop or resume unsolicited messages"
+    }
+
+    fn commands(&self) -> &[&str] {
+        &["mute", "unmute"]
+    }
+
+    fn scope(&self) -> CommandScope {
+        CommandScope::DM
+    }
+
+    async fn handle_command(
+        &self,
+        command: &str,
+        _args: &str,
+        ctx: &MessageContext,
+        db: &Arc<Db>,
+    ) -> Result<Option<Vec<Response>>, Box<dyn std::error::Error + Send + Sync>> {
+        let sender = ctx.sender_id;
+        let text = match command {
+            "mute" => {
+                if db.run(move |db| db.set_muted(sender, true)).await? {
+                    log::info!("!{:08x} muted unsolicited messages", ctx.sender_id);
+                }
+                "Muted: no more welcomes or notices from this bot. Commands still work; !unmute to undo."
+            }
+            "unmute" => {
+                if db.run(move |db| db.set_muted(sender, false)).await? {
+                    log::info!("!{:08x} unmuted unsolicited messages", ctx.sender_id);
+                    "Unmuted: notices will reach you again."
+                } else {
+                    "You were not muted."
+                }
+            }
+            _ => return Ok(None),
+        };
+
+        Ok(Some(vec![Response {
+            text: text.to_string(),
+            destination: Destination::Sender,
+            channel: ctx.channel,
+            reply_id: None,
+            data: None,
+        }]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[tokio::test]
+    async fn test_mute_and_unmute() {
+        let module = MuteModule;
+        let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+        let ctx = MessageContext {
+            sender_id: 0x12345678,
+            sender_name: "TestNode".to_string(),
+            channel: 0,
+            is_dm: true,
+            rssi: -70,
+            snr: 5.0,
+            hop_count: 1,
+            hop_start: 3,
+            hop_limit: 2,
+            via_mqtt: false,
+            pki_key: None,
+            packet_id: 0,
+        };
+
+        let responses = module
+            .handle_command("mute", "", &ctx, &db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(responses[0].text.starts_with("Muted"));
+        assert!(db.is_muted(ctx.sender_id).unwrap());
+
+        let responses = module
+            .handle_command("unmute", "", &ctx, &db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(responses[0].text.starts_with("Unmuted"));
+        assert!(!db.is_muted(ctx.sender_id).unwrap());
+        let responses = module
+            .handle_command("unmute", "", &ctx, &db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(responses[0].text, "You were not muted.");
+    }
+}
@@ -0,0 +1,283 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Datelike, Duration, Local, NaiveTime, TimeZone, Weekday};
+use std::sync::Arc;
+
+use crate::config::NetScheduleConfig;
+use crate::db::Db;
+use crate::message::{CommandScope, Destination, MessageContext, Response};
+use crate::module::Module;
+
+const MAX_CALLSIGN_LEN: usize = 12;
+const MAX_COMMENT_LEN: usize = 80;
+
+struct NetSchedule {
+    name: String,
+    days: Vec<Weekday>,
+    start: NaiveTime,
+    duration: Duration,
+    channel: u32,
+}
+
+/// The window of a scheduled net that is running right now.
+struct ActiveNet<'a> {
+    net: &'a NetSchedule,
+    started_at: i64,
+    ends_at: i64,
+}
+
+pub struct NetlogModule {
+    nets: Vec<NetSchedule>,
+}
+
+impl NetlogModule {
+    pub fn new(nets: &[NetScheduleConfig]) -> Self {
+        let nets = nets
+            .iter()
+            .filter_map(|net| {
+                let start = match NaiveTime::parse_from_str(&net.start, "%H:%M") {
+                    Ok(start) => start,
+                    Err(e) => {
+                        log::warn!(
+                            "Ignoring net {:?}: bad start {:?}: {}",
+                            net.name,
+                            net.start,
+                            e
+                        );
+                        return None;
+                    }
+                };
+                let mut days = Vec::new();
+                for day in &net.days {
+                    match day.parse::<Weekday>() {
+                        Ok(day) => days.push(day),
+                        Err(_) => {
+                            log::warn!("Ignoring net {:?}: bad day {:?}", net.name, day);
+                            return None;
+                        }
+                    }
+                }
+                Some(NetSchedule {
+                    name: net.name.clone(),
+                    days,
+                    start,
+                    duration: Duration::minutes(net.duration_mins as i64),
+                    channel: net.channel,
+                })
+            })
+            .collect();
+        Self { nets }
+    }
+
+    /// Find the net whose window contains `now`. Yesterday's start is checked
+    /// too so windows running past midnight are found.
+    fn active_net(&self, now: DateTime<Local>) -> Option<ActiveNet<'_>> {
+        let today = now.date_naive();
+        for net in &self.nets {
+            for date in [today, today.pred_opt()?] {
+                if !net.days.is_empty() && !net.days.contains(&date.weekday()) {
+                    continue;
+                }
+                let start = match Local
+                    .from_local_datetime(&date.and_time(net.start))
+                    .earliest()
+                {
+                    Some(start) => start,
+                    None => continue,
+                };
+                let end = start + net.duration;
+                if start <= now && now < end {
+                    return Some(ActiveNet {
+                        net,
+                        started_at: start.timestamp(),
+                        ends_at: end.timestamp(),
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    async fn checkin(
+        &self,
+        args: &str,
+        ctx: &MessageContext,
+        db: &Arc<Db>,
+        now: DateTime<Local>,
+    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let active = match self.active_net(now) {
+            Some(active) => active,
+            None => return Ok("No net is active right now.".to_string()),
+        };
+        let (callsign, comment) = match args.split_once(' ') {
+            Some((callsign, comment)) => (callsign, comment.trim()),
+            None => (args, ""),
+        };
+        if callsign.is_empty() {
+            return Ok("Usage: !checkin <callsign> [comment]".to_string());
+        }
+        let callsign: String = callsign
+            .chars()
+            .take(MAX_CALLSIGN_LEN)
+            .collect::<String>()
+            .to_uppercase();
+        let comment: Option<String> = if comment.is_empty() {
+            None
+        } else {
+            Some(comment.chars().take(MAX_COMMENT_LEN).collect())
+        };
+
+        let (name, channel) = (active.net.name.clone(), active.net.channel);
+        let (started_at, ends_at, node_id) = (active.started_at, active.ends_at, ctx.sender_id);
+        let checked_in = callsign.clone();
+        let count = db
+            .run(move |db| {
+                db.net_checkin(
+                    &name,
+                    channel,
+                    started_at,
+                    ends_at,
+                    node_id,
+                    &checked_in,
+                    comment.as_deref(),
+                )
+            })
+            .await?;
+        Ok(format!(
+            "Checked in to {} as {} (#{})",
+            active.net.name, callsign, count
+        ))
+    }
+}
+
+#[async_trait]
+impl Module for NetlogModule {
+    fn name(&self) -> &str {
+        "netlog"
+    }
+
+    fn description(&self) -> &str {
+        "Net check-in log"
+    }
+
+    fn commands(&self) -> &[&str] {
+        &["checkin"]
+    }
+
+    fn scope(&self) -> CommandScope {
+        CommandScope::Both
+    }
+
+    async fn handle_command(
+        &self,
+        _command: &str,
+        args: &str,
+        ctx: &MessageContext,
+        db: &Arc<Db>,
+    ) -> Result<Option<Vec<Response>>, Box<dyn std::error::Error + Send + Sync>> {
+        let text = self.checkin(args.trim(), ctx, db, Local::now()).await?;
+        Ok(Some(vec![Response {
+            text,
+            destination: Destination::Sender,
+            channel: ctx.channel,
+            reply_id: None,
+            data: None,
+        }]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn test_context(sender_id: u32) -> MessageContext {
+        MessageContext {
+            sender_id,
+            sender_name: "TestNode".to_string(),
+            channel: 0,
+            is_dm: false,
+            rssi: -70,
+            snr: 5.0,
+            hop_count: 1,
+            hop_start: 3,
+            hop_limit: 2,
+            via_mqtt: false,
+            pki_key: None,
+            packet_id: 0,
+        }
+    }
+
+    fn net(days: &[&str], start: &str, duration_mins: u64) -> NetScheduleConfig {
+        NetScheduleConfig {
+            name: "Weekly Net".to_string(),
+            days: days.iter().map(|d| d.to_string()).collect(),
+            start: start.to_string(),
+            duration_mins,
+            channel: 0,
+        }
+    }
+
+    fn local(y: i32, m: u32, d: u32, h: u32, min: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(y, m, d, h, min, 0).unwrap()
+    }
+
+    #[test]
+    fn test_active_net_window() {
+        // 2025-01-01 is a Wednesday
+        let module = NetlogModule::new(&[net(&["wed"], "20:00", 60)]);
+        assert!(module.active_net(local(2025, 1, 1, 19, 59)).is_none());
+        assert!(module.active_net(local(2025, 1, 1, 20, 0)).is_some());
+        assert!(module.active_net(local(2025, 1, 1, 20, 59)).is_some());
+        assert!(module.active_net(local(2025, 1, 1, 21, 0)).is_none());
+        assert!(module.active_net(local(2025, 1, 8, 20, 30)).is_some());
+        assert!(module.active_net(local(2025, 1, 2, 20, 30)).is_none());
+    }
+
+    #[test]
+    fn test_active_net_past_midnight_and_invalid_config() {
+        let module = NetlogModule::new(&[net(&[], "23:30", 60), net(&["xyz"], "20:00", 60)]);
+        assert_eq!(module.nets.len(), 1);
+        let active = module.active_net(local(2025, 1, 2, 0, 15)).unwrap();
+        assert_eq!(active.started_at, local(2025, 1, 1, 23, 30).timestamp());
+    }
+
+    #[tokio::test]
+    async fn test_checkin_records_callsign_and_comment() {
+        let module = NetlogModule::new(&[net(&[], "20:00", 60)]);
+        let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+        let during = local(2025, 1, 1, 20, 10);
+
+        assert_eq!(
+            module
+                .checkin("", &test_context(1), &db, during)
+                .await
+                .unwrap(),
+            "Usage: !checkin <callsign> [comment]"
+        );
+        assert_eq!(
+            module
+                .checkin("k1abc", &test_context(1), &db, during)
+                .await
+                .unwrap(),
+            "Checked in to Weekly Net as K1ABC (#1)"
+        );
+        assert_eq!(
+            module
+                .checkin("w2xyz portable, 5W", &test_context(2), &db, during)
+                .await
+                .unwrap(),
+            "Checked in to Weekly Net as W2XYZ (#2)"
+        );
+        assert_eq!(
+            module
+                .checkin("k1abc", &test_context(1), &db, local(2025, 1, 1, 22, 0))
+                .await
+                .unwrap(),
+            "No net is active right now."
+        );
+
+        let nets = db.recent_nets(5).unwrap();
+        assert_eq!(nets[0].checkins.len(), 2);
+        assert_eq!(nets[0].checkins[1].comment.as_deref(), Some("portable, 5W"));
+    }
+}
@@ -1,12 +1,22 @@
 use async_trait::async_trait;
 use chrono::Utc;
+use std::sync::Arc;
 
+use crate::config::FreshnessConfig;
 use crate::db::Db;
 use crate::message::{CommandScope, Destination, MessageContext, Response};
 use crate::module::Module;
 use crate::util::format_ago;
 
-pub struct NodeInfoModule;
+pub struct NodeInfoModule {
+    freshness: FreshnessConfig,
+}
+
+impl NodeInfoModule {
+    pub fn new(freshness: FreshnessConfig) -> Self {
+        Self { freshness }
+    }
+}
 
 #[async_trait]
 impl Module for NodeInfoModule {
@@ -31,12 +41,18 @@ impl Module for NodeInfoModule {
         _command: &str,
         args: &str,
         ctx: &MessageContext,
-        db: &Db,
+        db: &Arc<Db>,
     ) -> Result<Option<Vec<Response>>, Box<dyn std::error::Error + Send + Sync>> {
         let count: usize = args.parse().unwrap_or(5).min(20);
 
-        let total_nodes = db.node_count()? as usize;
-        let nodes = db.get_recent_nodes_with_last_hop(count)?;
+        let (total_nodes, nodes) = db
+            .run(move |db| {
+                Ok((
+                    db.node_count()? as usize,
+                    db.get_recent_nodes_with_last_hop(count)?,
+                ))
+            })
+            .await?;
         let now = Utc::now().timestamp();
 
         let mut lines = vec![format!("Nodes seen: {}", total_nodes)];
@@ -48,12 +64,19 @@ impl Module for NodeInfoModule {
             } else {
                 "unknown"
             };
-            let ago = format_ago(now - node.last_seen);
+            let age = now - node.last_seen;
             let hops = node
                 .last_hop
                 .map(|h| format!(" | hops {}", h))
                 .unwrap_or_default();
-            lines.push(format!("!{:08x} {} ({}){}", node.node_id, name, ago, hops));
+            lines.push(format!(
+                "!{:08x} {} ({}, {}){}",
+                node.node_id,
+                name,
+                format_ago(age),
+                self.freshness.classify(age),
+                hops
+            ));
         }
 
         if total_nodes > nodes.len() {
@@ -65,6 +88,7 @@ impl Module for NodeInfoModule {
             destination: Destination::Sender,
             channel: ctx.channel,
             reply_id: None,
+            data: None,
         }]))
     }
 }
@@ -72,6 +96,7 @@ impl Module for NodeInfoModule {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::packet_type::PacketType;
     use std::path::Path;
 
     fn test_context() -> MessageContext {
@@ -86,14 +111,15 @@ mod tests {
             hop_start: 3,
             hop_limit: 3,
             via_mqtt: false,
+            pki_key: None,
             packet_id: 0,
         }
     }
 
     #[tokio::test]
     async fn test_nodes_empty() {
-        let module = NodeInfoModule;
-        let db = Db::open(Path::new(":memory:")).unwrap();
+        let module = NodeInfoModule::new(FreshnessConfig::default());
+        let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
         let ctx = test_context();
 
         let result = module.handle_command("nodes", "", &ctx, &db).await.unwrap();
@@ -105,8 +131,8 @@ mod tests {
 
     #[tokio::test]
     async fn test_nodes_with_data() {
-        let module = NodeInfoModule;
-        let db = Db::open(Path::new(":memory:")).unwrap();
+        let module = NodeInfoModule::new(FreshnessConfig::default());
+        let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
         let ctx = test_context();
 
         // Add some nodes
@@ -124,12 +150,31 @@ mod tests {
         assert!(text.contains("Alice's Node"));
         assert!(text.contains("!11223344"));
         assert!(text.contains("Bob's Node"));
+        assert!(text.contains(", online)"));
+    }
+
+    #[test]
+    fn test_freshness_classes() {
+        let freshness = FreshnessConfig::default();
+        assert_eq!(freshness.classify(0), "online");
+        assert_eq!(freshness.classify(14 * 60 + 59), "online");
+        assert_eq!(freshness.classify(15 * 60), "recent");
+        assert_eq!(freshness.classify(2 * 3600), "stale");
+        assert_eq!(freshness.classify(24 * 3600), "offline");
+
+        let strict = FreshnessConfig {
+            online_mins: 5,
+            recent_mins: 30,
+            stale_mins: 60,
+        };
+        assert_eq!(strict.classify(10 * 60), "recent");
+        assert_eq!(strict.classify(90 * 60), "offline");
     }
 
     #[tokio::test]
     async fn test_nodes_with_count_argument() {
-        let module = NodeInfoModule;
-        let db = Db::open(Path::new(":memory:")).unwrap();
+        let module = NodeInfoModule::new(FreshnessConfig::default());
+        let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
         let ctx = test_context();
 
         // Add 10 nodes
@@ -154,8 +199,8 @@ mod tests {
 
     #[tokio::test]
     async fn test_nodes_max_count_capped() {
-        let module = NodeInfoModule;
-        let db = Db::open(Path::new(":memory:")).unwrap();
+        let module = NodeInfoModule::new(FreshnessConfig::default());
+        let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
         let ctx = test_context();
 
         // Add 25 nodes
@@ -177,8 +222,8 @@ mod tests {
 
     #[tokio::test]
     async fn test_nodes_prefers_long_name() {
-        let module = NodeInfoModule;
-        let db = Db::open(Path::new(":memory:")).unwrap();
+        let module = NodeInfoModule::new(FreshnessConfig::default());
+        let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
         let ctx = test_context();
 
         db.upsert_node(0x12345678, "SHORT", "Long Name Here", false)
@@ -193,8 +238,8 @@ mod tests {
 
     #[tokio::test]
     async fn test_nodes_falls_back_to_short_name() {
-        let module = NodeInfoModule;
-        let db = Db::open(Path::new(":memory:")).unwrap();
+        let module = NodeInfoModule::new(FreshnessConfig::default());
+        let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
         let ctx = test_context();
 
         db.upsert_node(0x12345678, "SHORT", "", false).unwrap();
@@ -207,8 +252,8 @@ mod tests {
 
     #[tokio::test]
     async fn test_nodes_unknown_when_no_name() {
-        let module = NodeInfoModule;
-        let db = Db::open(Path::new(":memory:")).unwrap();
+        let module = NodeInfoModule::new(FreshnessConfig::default());
+        let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
         let ctx = test_context();
 
         db.upsert_node(0x12345678, "", "", false).unwrap();
@@ -221,8 +266,8 @@ mod tests {
 
     #[tokio::test]
     async fn test_nodes_includes_hops_when_available() {
-        let module = NodeInfoModule;
-        let db = Db::open(Path::new(":memory:")).unwrap();
+        let module = NodeInfoModule::new(FreshnessConfig::default());
+        let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
         let ctx = test_context();
 
         db.upsert_node(0x12345678, "N1", "Node 1", false).unwrap();
@@ -237,7 +282,7 @@ mod tests {
             Some(5.0),
             Some(3),
             Some(7),
-            "text",
+            PacketType::Text,
         )
         .unwrap();
 
@@ -249,7 +294,7 @@ mod tests {
 
     #[test]
     fn test_node_info_module_metadata() {
-        let module = NodeInfoModule;
+        let module = NodeInfoModule::new(FreshnessConfig::default());
         assert_eq!(module.name(), "nodes");
         assert_eq!(module.commands(), &["nodes"]);
         assert_eq!(module.scope(), CommandScope::Both);
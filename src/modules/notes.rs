@@ -0,0 +1,169 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use std::sync::Arc;
+
+use crate::db::Db;
+use crate::message::{CommandScope, Destination, MessageContext, Response};
+use crate::module::Module;
+use crate::util::format_ago;
+
+/// How many notes `!note <node>` shows.
+const NOTES_SHOWN: usize = 3;
+const MAX_NOTE_LEN: usize = 200;
+
+pub struct NotesModule;
+
+impl NotesModule {
+    fn note(
+        args: &str,
+        ctx: &MessageContext,
+        db: &Db,
+    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let (target, text) = match args.split_once(' ') {
+            Some((target, text)) => (target, text.trim()),
+            None => (args, ""),
+        };
+        if target.is_empty() {
+            return Ok("Usage: !note <node> [text]".to_string());
+        }
+
+        let node_id = match db.find_node_by_name(target)? {
+            Some(id) => id,
+            None => return Ok(format!("Unknown node: {}", target)),
+        };
+        let name = db.get_node_name(node_id)?;
+
+        if !text.is_empty() {
+            let note: String = text.chars().take(MAX_NOTE_LEN).collect();
+            db.add_node_note(node_id, ctx.sender_id, &note)?;
+            return Ok(format!("Note added for {}", name));
+        }
+
+        let notes = db.get_node_notes(node_id, NOTES_SHOWN)?;
+        if notes.is_empty() {
+            return Ok(format!("No notes for {}", name));
+        }
+        let now = Utc::now().timestamp();
+        let mut lines = vec![format!("Notes for {}:", name)];
+        lines.extend(notes.iter().map(|n| {
+            format!(
+                "{} ({}): {}",
+                n.author,
+                format_ago(now - n.created_at),
+                n.note
+            )
+        }));
+        Ok(lines.join("\n"))
+    }
+}
+
+#[async_trait]
+impl Module for NotesModule {
+    fn name(&self) -> &str {
+        "note"
+    }
+
+    fn description(&self) -> &str {
+        "Operator notes per node"
+    }
+
+    fn commands(&self) -> &[&str] {
+        &["note"]
+    }
+
+    fn scope(&self) -> CommandScope {
+        CommandScope::DM
+    }
+
+    /// Adding a note; reading them is open to all.
+    fn admin_only(&self, _command: &str, args: &str) -> bool {
+        args.split_whitespace().nth(1).is_some()
+    }
+
+    async fn handle_command(
+        &self,
+        _command: &str,
+        args: &str,
+        ctx: &MessageContext,
+        db: &Arc<Db>,
+    ) -> Result<Option<Vec<Response>>, Box<dyn std::error::Error + Send + Sync>> {
+        let (args, owned_ctx) = (args.trim().to_string(), ctx.clone());
+        let text = db.run(move |db| Self::note(&args, &owned_ctx, db)).await?;
+        Ok(Some(vec![Response {
+            text,
+            destination: Destination::Sender,
+            channel: ctx.channel,
+            reply_id: None,
+            data: None,
+        }]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    const ADMIN: u32 = 0x11111111;
+    const USER: u32 = 0x22222222;
+    const TARGET: u32 = 0x33333333;
+
+    fn test_context(sender_id: u32) -> MessageContext {
+        MessageContext {
+            sender_id,
+            sender_name: "TestNode".to_string(),
+            channel: 0,
+            is_dm: true,
+            rssi: -70,
+            snr: 5.0,
+            hop_count: 1,
+            hop_start: 3,
+            hop_limit: 2,
+            via_mqtt: false,
+            pki_key: None,
+            packet_id: 0,
+        }
+    }
+
+    async fn run(module: &NotesModule, args: &str, from: u32, db: &Arc<Db>) -> String {
+        module
+            .handle_command("note", args, &test_context(from), db)
+            .await
+            .unwrap()
+            .unwrap()
+            .remove(0)
+            .text
+    }
+
+    #[tokio::test]
+    async fn test_admin_adds_and_anyone_reads() {
+        let module = NotesModule;
+        let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+        db.upsert_node(ADMIN, "ADM", "Admin", false).unwrap();
+        db.upsert_node(TARGET, "RPT", "Hilltop", false).unwrap();
+
+        // The bot checks the sender is an admin
+        assert!(module.admin_only("note", "RPT No notes"));
+        assert!(!module.admin_only("note", "RPT "));
+        assert_eq!(run(&module, "RPT", USER, &db).await, "No notes for Hilltop");
+
+        assert_eq!(
+            run(&module, "RPT new antenna installed", ADMIN, &db).await,
+            "Note added for Hilltop"
+        );
+        let reply = run(&module, "!33333333", USER, &db).await;
+        assert!(reply.starts_with("Notes for Hilltop:\nAdmin ("));
+        assert!(reply.ends_with("new antenna installed"));
+    }
+
+    #[tokio::test]
+    async fn test_usage_and_unknown_node() {
+        let module = NotesModule;
+        let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+        assert_eq!(
+            run(&module, "", USER, &db).await,
+            "Usage: !note <node> [text]"
+        );
+        assert_eq!(run(&module, "NOPE", USER, &db).await, "Unknown node: NOPE");
+    }
+}
@@ -1,7 +1,8 @@
 use async_trait::async_trait;
+use std::sync::Arc;
 
 use crate::db::Db;
-use crate::message::{CommandScope, Destination, MessageContext, Response};
+use crate::message::{CommandScope, Destination, MessageContext, Response, ResponseData};
 use crate::module::Module;
 
 pub struct PingModule;
@@ -29,18 +30,22 @@ impl Module for PingModule {
         _command: &str,
         _args: &str,
         ctx: &MessageContext,
-        _db: &Db,
+        _db: &Arc<Db>,
     ) -> Result<Option<Vec<Response>>, Box<dyn std::error::Error + Send + Sync>> {
         let mqtt_tag = if ctx.via_mqtt { " (via MQTT)" } else { "" };
-        let text = format!(
-            "Pong! RSSI: {} SNR: {:.1} Hops: {}/{}{}",
-            ctx.rssi, ctx.snr, ctx.hop_count, ctx.hop_start, mqtt_tag
-        );
         Ok(Some(vec![Response {
-            text,
+            text: "Pong!".to_string(),
             destination: Destination::Sender,
             channel: ctx.channel,
             reply_id: None,
+            data: Some(ResponseData::KeyValue(vec![
+                ("RSSI".to_string(), ctx.rssi.to_string()),
+                ("SNR".to_string(), format!("{:.1}", ctx.snr)),
+                (
+                    "Hops".to_string(),
+                    format!("{}/{}{}", ctx.hop_count, ctx.hop_start, mqtt_tag),
+                ),
+            ])),
         }]))
     }
 }
@@ -68,6 +73,7 @@ mod tests {
             hop_start,
             hop_limit: hop_start.saturating_sub(hop_count),
             via_mqtt,
+            pki_key: None,
             packet_id: 0,
         }
     }
@@ -75,7 +81,7 @@ mod tests {
     #[tokio::test]
     async fn test_ping_basic() {
         let module = PingModule;
-        let db = Db::open(Path::new(":memory:")).unwrap();
+        let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
         let ctx = test_context(-70, 5.5, 1, 3, false);
 
         let result = module.handle_command("ping", "", &ctx, &db).await.unwrap();
@@ -83,22 +89,25 @@ mod tests {
 
         let responses = result.unwrap();
         assert_eq!(responses.len(), 1);
-        assert_eq!(responses[0].text, "Pong! RSSI: -70 SNR: 5.5 Hops: 1/3");
+        assert_eq!(
+            responses[0].mesh_text(),
+            "Pong! RSSI: -70 SNR: 5.5 Hops: 1/3"
+        );
         assert!(matches!(responses[0].destination, Destination::Sender));
     }
 
     #[tokio::test]
     async fn test_ping_via_mqtt() {
         let module = PingModule;
-        let db = Db::open(Path::new(":memory:")).unwrap();
+        let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
         let ctx = test_context(-80, 3.0, 2, 5, true);
 
         let result = module.handle_command("ping", "", &ctx, &db).await.unwrap();
         let responses = result.unwrap();
 
-        assert!(responses[0].text.contains("(via MQTT)"));
+        assert!(responses[0].mesh_text().contains("(via MQTT)"));
         assert_eq!(
-            responses[0].text,
+            responses[0].mesh_text(),
             "Pong! RSSI: -80 SNR: 3.0 Hops: 2/5 (via MQTT)"
         );
     }
@@ -106,7 +115,7 @@ mod tests {
     #[tokio::test]
     async fn test_ping_preserves_channel() {
         let module = PingModule;
-        let db = Db::open(Path::new(":memory:")).unwrap();
+        let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
         let mut ctx = test_context(-70, 5.0, 0, 3, false);
         ctx.channel = 5;
 
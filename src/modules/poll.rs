@@ -0,0 +1,464 @@
+use async_trait::async_trait;
+use std::sync::Arc;
+
+use crate::db::{Db, Poll};
+use crate::message::{CommandScope, Destination, MessageContext, Response};
+use crate::module::Module;
+
+const MIN_OPTIONS: usize = 2;
+const MAX_OPTIONS: usize = 8;
+/// Longest auto-close time accepted by `!poll start`.
+const MAX_HOURS: u32 = 30 * 24;
+const START_USAGE: &str = "Usage: !poll start \"Question\" A/B/C [24h]";
+const USAGE: &str =
+    "Usage: !poll, !poll results, !poll start \"Question\" A/B/C [24h], !poll close";
+
+fn format_tally(poll: &Poll, tally: &[u64]) -> String {
+    poll.options
+        .iter()
+        .zip(tally)
+        .map(|(option, count)| format!("{}: {}", option, count))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The results announcement for a poll that was just closed.
+pub(crate) fn closed_text(poll: &Poll, tally: &[u64]) -> String {
+    format!(
+        "Poll closed: {} Results: {} ({} votes)",
+        poll.question,
+        format_tally(poll, tally),
+        tally.iter().sum::<u64>()
+    )
+}
+
+/// `"5h"`, or minutes in the last hour, rounded up.
+fn time_left(secs: i64) -> String {
+    if secs > 3600 {
+        format!("{}h", (secs + 3599) / 3600)
+    } else {
+        format!("{}m", (secs.max(0) + 59) / 60)
+    }
+}
+
+pub struct PollModule;
+
+impl PollModule {
+    /// Split `"Question here" A B C` or `"Question here" A/B/C` into the
+    /// quoted question and options, plus the hours after which the poll
+    /// closes when a trailing `24h` is given.
+    fn parse_start(args: &str) -> Option<(String, Vec<String>, Option<u32>)> {
+        let rest = args.trim().strip_prefix('"')?;
+        let (question, mut options) = rest.split_once('"')?;
+        let question = question.trim();
+        let mut hours = None;
+        if let Some((rest, last)) = options.trim().rsplit_once(char::is_whitespace) {
+            if let Some(n) = last
+                .strip_suffix(['h', 'H'])
+                .and_then(|n| n.parse::<u32>().ok())
+            {
+                if n == 0 || n > MAX_HOURS {
+                    return None;
+                }
+                hours = Some(n);
+                options = rest;
+            }
+        }
+        // Slashes allow options of several words
+        let options: Vec<String> = if options.contains('/') {
+            options
+                .split('/')
+                .map(str::trim)
+                .filter(|o| !o.is_empty())
+                .map(str::to_string)
+                .collect()
+        } else {
+            options.split_whitespace().map(str::to_string).collect()
+        };
+        if question.is_empty() || options.len() < MIN_OPTIONS || options.len() > MAX_OPTIONS {
+            return None;
+        }
+        Some((question.to_string(), options, hours))
+    }
+
+    /// Match a vote by option text (case-insensitive) or 1-based number.
+    fn option_index(poll: &Poll, choice: &str) -> Option<usize> {
+        poll.options
+            .iter()
+            .position(|o| o.eq_ignore_ascii_case(choice))
+            .or_else(|| {
+                choice
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|n| (1..=poll.options.len()).contains(n))
+                    .map(|n| n - 1)
+            })
+    }
+
+    fn reply(text: String, ctx: &MessageContext) -> Response {
+        Response {
+            text,
+            destination: Destination::Sender,
+            channel: ctx.channel,
+            reply_id: None,
+            data: None,
+        }
+    }
+
+    fn poll(
+        args: &str,
+        ctx: &MessageContext,
+        db: &Db,
+    ) -> Result<Vec<Response>, Box<dyn std::error::Error + Send + Sync>> {
+        let (sub, rest) = args.split_once(' ').unwrap_or((args, ""));
+        match sub.to_lowercase().as_str() {
+            "" => {
+                let text = match db.get_open_poll()? {
+                    Some(poll) => {
+                        let tally = db.poll_tally(poll.id, poll.options.len())?;
+                        let closes = poll
+                            .closes_at
+                            .map(|at| {
+                                format!(
+                                    ", closes in {}",
+                                    time_left(at - chrono::Utc::now().timestamp())
+                                )
+                            })
+                            .unwrap_or_default();
+                        format!(
+                            "Poll: {} ({}){}. Vote by DM: !vote <option>",
+                            poll.question,
+                            format_tally(&poll, &tally),
+                            closes
+                        )
+                    }
+                    None => "No open poll.".to_string(),
+                };
+                Ok(vec![Self::reply(text, ctx)])
+            }
+            "results" => {
+                let text = match db.get_latest_poll()? {
+                    Some(poll) => {
+                        let tally = db.poll_tally(poll.id, poll.options.len())?;
+                        let total: u64 = tally.iter().sum();
+                        let state = if poll.closed_at.is_some() {
+                            "closed"
+                        } else {
+                            "open"
+                        };
+                        format!(
+                            "Poll ({}): {} Results: {} ({} votes)",
+                            state,
+                            poll.question,
+                            format_tally(&poll, &tally),
+                            total
+                        )
+                    }
+                    None => "No polls yet.".to_string(),
+                };
+                Ok(vec![Self::reply(text, ctx)])
+            }
+            "start" => {
+                if let Some(poll) = db.get_open_poll()? {
+                    return Ok(vec![Self::reply(
+                        format!("A poll is already open: {}", poll.question),
+                        ctx,
+                    )]);
+                }
+                let (question, options, hours) = match Self::parse_start(rest) {
+                    Some(parsed) => parsed,
+                    None => return Ok(vec![Self::reply(START_USAGE.to_string(), ctx)]),
+                };
+                let closes_at = hours.map(|h| chrono::Utc::now().timestamp() + h as i64 * 3600);
+                db.create_poll(&question, &options, ctx.channel, ctx.sender_id, closes_at)?;
+                log::info!(
+                    "Poll started by !{:08x}: {} {:?} (closes after {:?}h)",
+                    ctx.sender_id,
+                    question,
+                    options,
+                    hours
+                );
+                let closes = hours
+                    .map(|h| format!(" Closes in {}h.", h))
+                    .unwrap_or_default();
+                Ok(vec![Response {
+                    text: format!(
+                        "Poll: {} Options: {}. Vote by DM: !vote <option>{}",
+                        question,
+                        options.join(", "),
+                        closes
+                    ),
+                    destination: Destination::Broadcast,
+                    channel: ctx.channel,
+                    reply_id: None,
+                    data: None,
+                }])
+            }
+            "close" => {
+                let poll = match db.get_open_poll()? {
+                    Some(poll) => poll,
+                    None => return Ok(vec![Self::reply("No open poll.".to_string(), ctx)]),
+                };
+                let tally = db.poll_tally(poll.id, poll.options.len())?;
+                db.close_poll(poll.id)?;
+                Ok(vec![Response {
+                    text: closed_text(&poll, &tally),
+                    destination: Destination::Broadcast,
+                    channel: poll.channel,
+                    reply_id: None,
+                    data: None,
+                }])
+            }
+            _ => Ok(vec![Self::reply(USAGE.to_string(), ctx)]),
+        }
+    }
+
+    fn vote(
+        args: &str,
+        ctx: &MessageContext,
+        db: &Db,
+    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        if !ctx.is_dm {
+            return Ok("Please vote by DM.".to_string());
+        }
+        let poll = match db.get_open_poll()? {
+            Some(poll) => poll,
+            None => return Ok("No open poll.".to_string()),
+        };
+        let index = match Self::option_index(&poll, args) {
+            Some(index) => index,
+            None => {
+                return Ok(format!("Choose one of: {}", poll.options.join(", ")));
+            }
+        };
+        if db.cast_poll_vote(poll.id, ctx.sender_id, index)? {
+            Ok(format!("Vote recorded: {}", poll.options[index]))
+        } else {
+            Ok("You already voted in this poll.".to_string())
+        }
+    }
+}
+
+#[async_trait]
+impl Module for PollModule {
+    fn name(&self) -> &str {
+        "poll"
+    }
+
+    fn description(&self) -> &str {
+        "Mesh-wide polls"
+    }
+
+    fn commands(&self) -> &[&str] {
+        &["poll", "vote"]
+    }
+
+    fn scope(&self) -> CommandScope {
+        CommandScope::Both
+    }
+
+    /// Starting and closing polls; anyone can see results and vote.
+    fn admin_only(&self, command: &str, args: &str) -> bool {
+        let sub = args.split_whitespace().next().unwrap_or("");
+        command == "poll"
+            && (sub.eq_ignore_ascii_case("start") || sub.eq_ignore_ascii_case("close"))
+    }
+
+    async fn handle_command(
+        &self,
+        command: &str,
+        args: &str,
+        ctx: &MessageContext,
+        db: &Arc<Db>,
+    ) -> Result<Option<Vec<Response>>, Box<dyn std::error::Error + Send + Sync>> {
+        let args = args.trim().to_string();
+        let owned_ctx = ctx.clone();
+        match command {
+            "poll" => Ok(Some(
+                db.run(move |db| Self::poll(&args, &owned_ctx, db)).await?,
+            )),
+            "vote" => {
+                let text = db.run(move |db| Self::vote(&args, &owned_ctx, db)).await?;
+                Ok(Some(vec![Self::reply(text, ctx)]))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    const ADMIN: u32 = 0x11111111;
+    const VOTER: u32 = 0x22222222;
+
+    fn test_context(sender_id: u32, is_dm: bool) -> MessageContext {
+        MessageContext {
+            sender_id,
+            sender_name: "TestNode".to_string(),
+            channel: 0,
+            is_dm,
+            rssi: -70,
+            snr: 5.0,
+            hop_count: 1,
+            hop_start: 3,
+            hop_limit: 2,
+            via_mqtt: false,
+            pki_key: None,
+            packet_id: 0,
+        }
+    }
+
+    async fn run(
+        module: &PollModule,
+        command: &str,
+        args: &str,
+        ctx: &MessageContext,
+        db: &Arc<Db>,
+    ) -> Response {
+        module
+            .handle_command(command, args, ctx, db)
+            .await
+            .unwrap()
+            .unwrap()
+            .remove(0)
+    }
+
+    #[tokio::test]
+    async fn test_poll_lifecycle() {
+        let module = PollModule;
+        let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+        let admin = test_context(ADMIN, true);
+        let voter = test_context(VOTER, true);
+
+        let started = run(&module, "poll", "start \"Net at 8pm?\" Yes No", &admin, &db).await;
+        assert!(matches!(started.destination, Destination::Broadcast));
+        assert_eq!(
+            started.text,
+            "Poll: Net at 8pm? Options: Yes, No. Vote by DM: !vote <option>"
+        );
+
+        assert_eq!(
+            run(&module, "vote", "yes", &voter, &db).await.text,
+            "Vote recorded: Yes"
+        );
+        assert_eq!(
+            run(&module, "vote", "2", &voter, &db).await.text,
+            "You already voted in this poll."
+        );
+        assert_eq!(
+            run(&module, "vote", "2", &test_context(ADMIN, true), &db)
+                .await
+                .text,
+            "Vote recorded: No"
+        );
+
+        let closed = run(&module, "poll", "close", &admin, &db).await;
+        assert!(matches!(closed.destination, Destination::Broadcast));
+        assert_eq!(
+            closed.text,
+            "Poll closed: Net at 8pm? Results: Yes: 1, No: 1 (2 votes)"
+        );
+        assert_eq!(
+            run(&module, "vote", "yes", &voter, &db).await.text,
+            "No open poll."
+        );
+    }
+
+    #[tokio::test]
+    async fn test_poll_slash_options_auto_close_and_results() {
+        let module = PollModule;
+        let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+        let admin = test_context(ADMIN, true);
+
+        assert_eq!(
+            run(&module, "poll", "results", &admin, &db).await.text,
+            "No polls yet."
+        );
+        let started = run(
+            &module,
+            "poll",
+            "start \"Pizza night?\" yes/no/not this week 24h",
+            &admin,
+            &db,
+        )
+        .await;
+        assert_eq!(
+            started.text,
+            "Poll: Pizza night? Options: yes, no, not this week. Vote by DM: !vote <option> Closes in 24h."
+        );
+        assert!(db.get_open_poll().unwrap().unwrap().closes_at.is_some());
+        assert!(run(&module, "poll", "", &admin, &db)
+            .await
+            .text
+            .ends_with(", closes in 24h. Vote by DM: !vote <option>"));
+
+        assert_eq!(
+            run(
+                &module,
+                "vote",
+                "Not this week",
+                &test_context(VOTER, true),
+                &db
+            )
+            .await
+            .text,
+            "Vote recorded: not this week"
+        );
+        assert_eq!(
+            run(&module, "poll", "results", &admin, &db).await.text,
+            "Poll (open): Pizza night? Results: yes: 0, no: 0, not this week: 1 (1 votes)"
+        );
+        run(&module, "poll", "close", &admin, &db).await;
+        assert!(run(&module, "poll", "results", &admin, &db)
+            .await
+            .text
+            .starts_with("Poll (closed): Pizza night?"));
+
+        assert_eq!(
+            run(&module, "poll", "start \"Q\" A/B 0h", &admin, &db)
+                .await
+                .text,
+            START_USAGE
+        );
+    }
+
+    #[tokio::test]
+    async fn test_poll_permissions_and_validation() {
+        let module = PollModule;
+        let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+        let admin = test_context(ADMIN, true);
+
+        // The bot checks the sender is an admin
+        assert!(module.admin_only("poll", "start \"Q\" A B"));
+        assert!(module.admin_only("poll", "CLOSE"));
+        assert!(!module.admin_only("poll", "results"));
+        assert!(!module.admin_only("vote", "start"));
+        assert_eq!(
+            run(&module, "poll", "start Q A B", &admin, &db).await.text,
+            START_USAGE
+        );
+        assert_eq!(
+            run(&module, "poll", "start \"Q\" A", &admin, &db)
+                .await
+                .text,
+            START_USAGE
+        );
+
+        run(&module, "poll", "start \"Q\" A B", &admin, &db).await;
+        assert_eq!(
+            run(&module, "vote", "A", &test_context(VOTER, false), &db)
+                .await
+                .text,
+            "Please vote by DM."
+        );
+        assert_eq!(
+            run(&module, "vote", "C", &test_context(VOTER, true), &db)
+                .await
+                .text,
+            "Choose one of: A, B"
+        );
+    }
+}
@@ -0,0 +1,228 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use std::sync::Arc;
+
+use crate::db::Db;
+use crate::message::{CommandScope, Destination, MessageContext, Response};
+use crate::module::Module;
+use crate::util::{distance_km, format_ago};
+
+const COMPASS_POINTS: [&str; 8] = ["N", "NE", "E", "SE", "S", "SW", "W", "NW"];
+
+/// Initial bearing from `from` to `to`, in degrees clockwise from north.
+fn bearing_deg(from: (f64, f64), to: (f64, f64)) -> f64 {
+    let (lat1, lat2) = (from.0.to_radians(), to.0.to_radians());
+    let dlon = (to.1 - from.1).to_radians();
+    let y = dlon.sin() * lat2.cos();
+    let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * dlon.cos();
+    (y.atan2(x).to_degrees() + 360.0) % 360.0
+}
+
+fn compass_point(bearing: f64) -> &'static str {
+    COMPASS_POINTS[((bearing + 22.5) / 45.0) as usize % COMPASS_POINTS.len()]
+}
+
+/// "850 m" below a kilometre, "12.3 km" above.
+fn format_distance(km: f64) -> String {
+    if km < 1.0 {
+        format!("{:.0} m", km * 1000.0)
+    } else {
+        format!("{:.1} km", km)
+    }
+}
+
+/// `!where <node>` and `!distance <node> <node>`, from the positions nodes
+/// broadcast and the bot stores in `nodes`.
+pub struct PositionModule;
+
+impl PositionModule {
+    fn where_is(args: &str, db: &Db) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        if args.is_empty() {
+            return Ok("Usage: !where <node>".to_string());
+        }
+        let node_id = match db.find_node_by_name(args)? {
+            Some(id) => id,
+            None => return Ok(format!("Unknown node: {}", args)),
+        };
+        let name = db.get_node_name(node_id)?;
+        let position = match db.get_node_position(node_id)? {
+            Some(position) => position,
+            None => return Ok(format!("No position known for {}.", name)),
+        };
+        let mut text = format!("{}: {:.5}, {:.5}", name, position.0, position.1);
+        if let Some(reported_at) = db.position_reported_at(node_id)? {
+            text.push_str(&format!(
+                " ({})",
+                format_ago(Utc::now().timestamp() - reported_at)
+            ));
+        }
+        let bot_position = match db.local_node_id()? {
+            Some(id) if id != node_id => db.get_node_position(id)?,
+            _ => None,
+        };
+        if let Some(bot_position) = bot_position {
+            text.push_str(&format!(
+                ", {} {} of me",
+                format_distance(distance_km(bot_position, position)),
+                compass_point(bearing_deg(bot_position, position))
+            ));
+        }
+        Ok(text)
+    }
+
+    fn distance(args: &str, db: &Db) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let names: Vec<&str> = args.split_whitespace().collect();
+        let [from, to] = names[..] else {
+            return Ok("Usage: !distance <node> <node>".to_string());
+        };
+        let mut ends = Vec::with_capacity(2);
+        for name in [from, to] {
+            let node_id = match db.find_node_by_name(name)? {
+                Some(id) => id,
+                None => return Ok(format!("Unknown node: {}", name)),
+            };
+            let name = db.get_node_name(node_id)?;
+            match db.get_node_position(node_id)? {
+                Some(position) => ends.push((name, position)),
+                None => return Ok(format!("No position known for {}.", name)),
+            }
+        }
+        let bearing = bearing_deg(ends[0].1, ends[1].1);
+        Ok(format!(
+            "{} to {}: {}, bearing {:.0}° ({})",
+            ends[0].0,
+            ends[1].0,
+            format_distance(distance_km(ends[0].1, ends[1].1)),
+            bearing,
+            compass_point(bearing)
+        ))
+    }
+}
+
+#[async_trait]
+impl Module for PositionModule {
+    fn name(&self) -> &str {
+        "position"
+    }
+
+    fn description(&self) -> &str {
+        "Node positions and distances"
+    }
+
+    fn commands(&self) -> &[&str] {
+        &["where", "distance"]
+    }
+
+    fn scope(&self) -> CommandScope {
+        CommandScope::Both
+    }
+
+    async fn handle_command(
+        &self,
+        command: &str,
+        args: &str,
+        ctx: &MessageContext,
+        db: &Arc<Db>,
+    ) -> Result<Option<Vec<Response>>, Box<dyn std::error::Error + Send + Sync>> {
+        let args = args.to_string();
+        let text = match command {
+            "distance" => db.run(move |db| Self::distance(&args, db)).await?,
+            _ => db.run(move |db| Self::where_is(args.trim(), db)).await?,
+        };
+        Ok(Some(vec![Response {
+            text,
+            destination: Destination::Sender,
+            channel: ctx.channel,
+            reply_id: None,
+            data: None,
+        }]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn test_context() -> MessageContext {
+        MessageContext {
+            sender_id: 0x12345678,
+            sender_name: "TestNode".to_string(),
+            channel: 0,
+            is_dm: true,
+            rssi: -70,
+            snr: 5.0,
+            hop_count: 1,
+            hop_start: 3,
+            hop_limit: 2,
+            via_mqtt: false,
+            pki_key: None,
+            packet_id: 0,
+        }
+    }
+
+    async fn run(command: &str, args: &str, db: &Arc<Db>) -> String {
+        let text = PositionModule
+            .handle_command(command, args, &test_context(), db)
+            .await
+            .unwrap()
+            .unwrap()
+            .remove(0)
+            .text;
+        // Drop the "(Ns ago)", which depends on when the test runs
+        match (text.find(" ("), text.find("ago)")) {
+            (Some(start), Some(end)) => format!("{}{}", &text[..start], &text[end + 4..]),
+            _ => text,
+        }
+    }
+
+    #[test]
+    fn test_distance_and_bearing() {
+        // Paris to London
+        let paris = (48.8566, 2.3522);
+        let london = (51.5074, -0.1278);
+        let bearing = bearing_deg(paris, london);
+        assert!((bearing - 330.0).abs() < 1.0, "{}", bearing);
+        assert_eq!(compass_point(bearing), "NW");
+        assert_eq!(compass_point(359.0), "N");
+        assert_eq!(format_distance(0.85), "850 m");
+        assert_eq!(format_distance(12.34), "12.3 km");
+    }
+
+    #[tokio::test]
+    async fn test_where_and_distance() {
+        let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+        db.upsert_node(0xAAAAAAAA, "A", "Alice", false).unwrap();
+        db.upsert_node(0xBBBBBBBB, "B", "Bob", false).unwrap();
+        db.upsert_node(0xCCCCCCCC, "C", "Carol", false).unwrap();
+        db.update_position(0xAAAAAAAA, 48.8566, 2.3522).unwrap();
+        db.update_position(0xBBBBBBBB, 51.5074, -0.1278).unwrap();
+
+        assert_eq!(run("where", "bob", &db).await, "Bob: 51.50740, -0.12780");
+        db.set_local_node_id(0xAAAAAAAA).unwrap();
+        assert_eq!(
+            run("where", "bob", &db).await,
+            "Bob: 51.50740, -0.12780, 343.6 km NW of me"
+        );
+        assert_eq!(run("where", "alice", &db).await, "Alice: 48.85660, 2.35220");
+        assert_eq!(
+            run("where", "carol", &db).await,
+            "No position known for Carol."
+        );
+        assert_eq!(run("where", "nobody", &db).await, "Unknown node: nobody");
+        assert_eq!(run("where", "", &db).await, "Usage: !where <node>");
+
+        assert_eq!(
+            run("distance", "alice B", &db).await,
+            "Alice to Bob: 343.6 km, bearing 330° (NW)"
+        );
+        assert_eq!(
+            run("distance", "alice carol", &db).await,
+            "No position known for Carol."
+        );
+        assert_eq!(
+            run("distance", "alice", &db).await,
+            "Usage: !distance <node> <node>"
+        );
+    }
+}
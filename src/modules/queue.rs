@@ -0,0 +1,53 @@
+use async_trait::async_trait;
+use std::sync::Arc;
+
+use crate::db::Db;
+use crate::message::{CommandScope, MessageContext, Response};
+use crate::module::Module;
+
+/// `!queue`: the sender's messages still waiting in the bot's outgoing
+/// queue. The queue belongs to the bot, so it answers the command (see
+/// `Bot::queue_command`); this module only registers it.
+pub struct QueueModule;
+
+#[async_trait]
+impl Module for QueueModule {
+    fn name(&self) -> &str {
+        "queue"
+    }
+
+    fn description(&self) -> &str {
+        "Your messages waiting to be sent"
+    }
+
+    fn commands(&self) -> &[&str] {
+        &["queue"]
+    }
+
+    fn scope(&self) -> CommandScope {
+        CommandScope::Both
+    }
+
+    async fn handle_command(
+        &self,
+        _command: &str,
+        _args: &str,
+        _ctx: &MessageContext,
+        _db: &Arc<Db>,
+    ) -> Result<Option<Vec<Response>>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_queue_module_metadata() {
+        let module = QueueModule;
+        assert_eq!(module.name(), "queue");
+        assert_eq!(module.commands(), &["queue"]);
+        assert_eq!(module.scope(), CommandScope::Both);
+    }
+}
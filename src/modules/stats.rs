@@ -0,0 +1,222 @@
+use async_trait::async_trait;
+use std::sync::Arc;
+
+use crate::db::{Db, MqttFilter};
+use crate::message::{CommandScope, Destination, MessageContext, Response};
+use crate::module::Module;
+
+/// Window the commands report on.
+const HOURS: u32 = 24;
+/// Nodes listed by `!top`.
+const TOP_NODES: usize = 5;
+
+/// Mesh statistics over the air, from the same queries as the dashboard:
+/// `!stats` for traffic, `!top` for the busiest nodes and `!hops <node>`
+/// for how far a node's packets travel to reach us.
+pub struct StatsModule;
+
+impl StatsModule {
+    fn stats(db: &Db) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let overview = db.dashboard_overview(HOURS, MqttFilter::All, "")?;
+        Ok(format!(
+            "Last {}h: {} packets ({} in, {} out), {} messages ({} in, {} out). Nodes known: {}",
+            HOURS,
+            overview.packets_in + overview.packets_out,
+            overview.packets_in,
+            overview.packets_out,
+            overview.messages_in + overview.messages_out,
+            overview.messages_in,
+            overview.messages_out,
+            overview.node_count
+        ))
+    }
+
+    fn top(db: &Db) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let nodes = db.most_active_nodes(HOURS, TOP_NODES)?;
+        if nodes.is_empty() {
+            return Ok(format!("No packets heard in the last {}h.", HOURS));
+        }
+        let lines: Vec<String> = nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| {
+                let name = if !node.short_name.is_empty() {
+                    node.short_name.clone()
+                } else if !node.long_name.is_empty() {
+                    node.long_name.clone()
+                } else {
+                    format!("!{:08x}", node.node_id)
+                };
+                format!("{}. {} {}", i + 1, name, node.packets)
+            })
+            .collect();
+        Ok(format!("Top {}h (packets):\n{}", HOURS, lines.join("\n")))
+    }
+
+    fn hops(args: &str, db: &Db) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        if args.is_empty() {
+            return Ok("Usage: !hops <node>".to_string());
+        }
+        let node_id = match db.find_node_by_name(args)? {
+            Some(id) => id,
+            None => return Ok(format!("Unknown node: {}", args)),
+        };
+        let name = db.get_node_name(node_id)?;
+        let node = format!("!{:08x}", node_id);
+        let window = db
+            .dashboard_hops_to_me(HOURS, false)?
+            .into_iter()
+            .find(|n| n.node_id == node)
+            .map(|n| n.current);
+        Ok(match window {
+            Some(w) if w.samples > 0 => format!(
+                "{}, last {}h: min {}, avg {:.1} hops over {} packets",
+                name,
+                HOURS,
+                w.min_hop.unwrap_or(0),
+                w.avg_hop.unwrap_or(0.0),
+                w.samples
+            ),
+            _ => format!("No RF packets from {} in the last {}h.", name, HOURS),
+        })
+    }
+}
+
+#[async_trait]
+impl Module for StatsModule {
+    fn name(&self) -> &str {
+        "stats"
+    }
+
+    fn description(&self) -> &str {
+        "Mesh traffic, busiest nodes, hops"
+    }
+
+    fn commands(&self) -> &[&str] {
+        &["stats", "top", "hops"]
+    }
+
+    fn scope(&self) -> CommandScope {
+        CommandScope::Both
+    }
+
+    async fn handle_command(
+        &self,
+        command: &str,
+        args: &str,
+        ctx: &MessageContext,
+        db: &Arc<Db>,
+    ) -> Result<Option<Vec<Response>>, Box<dyn std::error::Error + Send + Sync>> {
+        let text = match command {
+            "top" => db.run(Self::top).await?,
+            "hops" => {
+                let args = args.trim().to_string();
+                db.run(move |db| Self::hops(&args, db)).await?
+            }
+            _ => db.run(Self::stats).await?,
+        };
+        Ok(Some(vec![Response {
+            text,
+            destination: Destination::Sender,
+            channel: ctx.channel,
+            reply_id: None,
+            data: None,
+        }]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet_type::PacketType;
+    use std::path::Path;
+
+    fn test_context() -> MessageContext {
+        MessageContext {
+            sender_id: 0x12345678,
+            sender_name: "TestNode".to_string(),
+            channel: 0,
+            is_dm: true,
+            rssi: -70,
+            snr: 5.0,
+            hop_count: 1,
+            hop_start: 3,
+            hop_limit: 2,
+            via_mqtt: false,
+            pki_key: None,
+            packet_id: 0,
+        }
+    }
+
+    async fn run(command: &str, args: &str, db: &Arc<Db>) -> String {
+        StatsModule
+            .handle_command(command, args, &test_context(), db)
+            .await
+            .unwrap()
+            .unwrap()
+            .remove(0)
+            .text
+    }
+
+    fn log_in(db: &Db, from: u32, packet_type: PacketType, hops: u32) {
+        db.log_packet(
+            from,
+            None,
+            0,
+            "",
+            "in",
+            false,
+            Some(-80),
+            Some(5.0),
+            Some(hops),
+            Some(5),
+            packet_type,
+        )
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_stats_and_top() {
+        let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+        assert_eq!(
+            run("top", "", &db).await,
+            "No packets heard in the last 24h."
+        );
+
+        db.upsert_node(0xAAAAAAAA, "A", "Alice", false).unwrap();
+        db.upsert_node(0xBBBBBBBB, "", "Bob", false).unwrap();
+        log_in(&db, 0xAAAAAAAA, PacketType::Text, 1);
+        log_in(&db, 0xAAAAAAAA, PacketType::Position, 1);
+        log_in(&db, 0xBBBBBBBB, PacketType::Telemetry, 2);
+        log_in(&db, 0xCCCCCCCC, PacketType::Telemetry, 2);
+
+        assert_eq!(
+            run("stats", "", &db).await,
+            "Last 24h: 4 packets (4 in, 0 out), 1 messages (1 in, 0 out). Nodes known: 2"
+        );
+        assert_eq!(
+            run("top", "", &db).await,
+            "Top 24h (packets):\n1. A 2\n2. Bob 1\n3. !cccccccc 1"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_hops() {
+        let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+        db.upsert_node(0xAAAAAAAA, "A", "Alice", false).unwrap();
+        db.upsert_node(0xBBBBBBBB, "B", "Bob", false).unwrap();
+        log_in(&db, 0xAAAAAAAA, PacketType::Text, 1);
+        log_in(&db, 0xAAAAAAAA, PacketType::Position, 2);
+
+        assert_eq!(
+            run("hops", "alice", &db).await,
+            "Alice, last 24h: min 1, avg 1.5 hops over 2 packets"
+        );
+        assert_eq!(
+            run("hops", "B", &db).await,
+            "No RF packets from Bob in the last 24h."
+        );
+        assert_eq!(run("hops", "nobody", &db).await, "Unknown node: nobody");
+        assert_eq!(run("hops", "", &db).await, "Usage: !hops <node>");
+    }
+}
@@ -0,0 +1,104 @@
+use async_trait::async_trait;
+use std::sync::Arc;
+
+use crate::db::Db;
+use crate::message::{CommandScope, Destination, MessageContext, Response};
+use crate::module::Module;
+
+/// `!trace <node>`. Sending the traceroute and reporting the route back need
+/// the bot's queue, so the bot answers once the node is known (see
+/// `Bot::trace_command`); this answers everything else.
+pub struct TracerouteModule;
+
+#[async_trait]
+impl Module for TracerouteModule {
+    fn name(&self) -> &str {
+        "traceroute"
+    }
+
+    fn description(&self) -> &str {
+        "Trace the route to a node"
+    }
+
+    fn commands(&self) -> &[&str] {
+        &["trace"]
+    }
+
+    fn scope(&self) -> CommandScope {
+        CommandScope::Both
+    }
+
+    async fn handle_command(
+        &self,
+        _command: &str,
+        args: &str,
+        ctx: &MessageContext,
+        db: &Arc<Db>,
+    ) -> Result<Option<Vec<Response>>, Box<dyn std::error::Error + Send + Sync>> {
+        let target = args.trim();
+        let text = if target.is_empty() {
+            "Usage: !trace <node>".to_string()
+        } else {
+            let name_query = target.to_string();
+            let name = db
+                .run(move |db| match db.find_node_by_name(&name_query)? {
+                    Some(id) => Ok(Some(db.get_node_name(id)?)),
+                    None => Ok(None),
+                })
+                .await?;
+            match name {
+                Some(name) => format!("Can't trace {} right now.", name),
+                None => format!("Unknown node: {}", target),
+            }
+        };
+        Ok(Some(vec![Response {
+            text,
+            destination: Destination::Sender,
+            channel: ctx.channel,
+            reply_id: None,
+            data: None,
+        }]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn test_context() -> MessageContext {
+        MessageContext {
+            sender_id: 0x12345678,
+            sender_name: "TestNode".to_string(),
+            channel: 0,
+            is_dm: true,
+            rssi: -70,
+            snr: 5.0,
+            hop_count: 1,
+            hop_start: 3,
+            hop_limit: 2,
+            via_mqtt: false,
+            pki_key: None,
+            packet_id: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_trace_usage_and_unknown_node() {
+        let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
+        let run = |args: &'static str| {
+            let db = &db;
+            async move {
+                TracerouteModule
+                    .handle_command("trace", args, &test_context(), db)
+                    .await
+                    .unwrap()
+                    .unwrap()
+                    .remove(0)
+                    .text
+            }
+        };
+        assert_eq!(run("").await, "Usage: !trace <node>");
+        assert_eq!(run("nobody").await, "Unknown node: nobody");
+    }
+}
@@ -1,6 +1,7 @@
 use std::time::Instant;
 
 use async_trait::async_trait;
+use std::sync::Arc;
 
 use crate::db::Db;
 use crate::message::{CommandScope, Destination, MessageContext, Response};
@@ -42,12 +43,18 @@ impl Module for UptimeModule {
         _command: &str,
         _args: &str,
         ctx: &MessageContext,
-        db: &Db,
+        db: &Arc<Db>,
     ) -> Result<Option<Vec<Response>>, Box<dyn std::error::Error + Send + Sync>> {
         let uptime = format_duration(self.started.elapsed().as_secs());
-        let msgs_in = db.message_count("in").unwrap_or(0);
-        let msgs_out = db.message_count("out").unwrap_or(0);
-        let nodes = db.node_count().unwrap_or(0);
+        let (msgs_in, msgs_out, nodes) = db
+            .run(|db| {
+                Ok((
+                    db.message_count("in").unwrap_or(0),
+                    db.message_count("out").unwrap_or(0),
+                    db.node_count().unwrap_or(0),
+                ))
+            })
+            .await?;
 
         let text = format!(
             "Uptime: {}\nMessages: {} in / {} out\nNodes seen: {}",
@@ -59,6 +66,7 @@ impl Module for UptimeModule {
             destination: Destination::Sender,
             channel: ctx.channel,
             reply_id: None,
+            data: None,
         }]))
     }
 }
@@ -66,6 +74,7 @@ impl Module for UptimeModule {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::packet_type::PacketType;
     use std::path::Path;
 
     fn test_context() -> MessageContext {
@@ -80,6 +89,7 @@ mod tests {
             hop_start: 3,
             hop_limit: 3,
             via_mqtt: false,
+            pki_key: None,
             packet_id: 0,
         }
     }
@@ -95,7 +105,7 @@ mod tests {
     #[tokio::test]
     async fn test_uptime_response_format() {
         let module = UptimeModule::new();
-        let db = Db::open(Path::new(":memory:")).unwrap();
+        let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
         let ctx = test_context();
 
         let result = module
@@ -116,16 +126,36 @@ mod tests {
     #[tokio::test]
     async fn test_uptime_counts_messages() {
         let module = UptimeModule::new();
-        let db = Db::open(Path::new(":memory:")).unwrap();
+        let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
         let ctx = test_context();
 
         // Log some messages
         db.log_packet(
-            0x12345678, None, 0, "test", "in", false, None, None, None, None, "text",
+            0x12345678,
+            None,
+            0,
+            "test",
+            "in",
+            false,
+            None,
+            None,
+            None,
+            None,
+            PacketType::Text,
         )
         .unwrap();
         db.log_packet(
-            0x12345678, None, 0, "test", "in", false, None, None, None, None, "text",
+            0x12345678,
+            None,
+            0,
+            "test",
+            "in",
+            false,
+            None,
+            None,
+            None,
+            None,
+            PacketType::Text,
         )
         .unwrap();
         db.log_packet(
@@ -139,7 +169,7 @@ mod tests {
             None,
             None,
             None,
-            "text",
+            PacketType::Text,
         )
         .unwrap();
 
@@ -156,7 +186,7 @@ mod tests {
     #[tokio::test]
     async fn test_uptime_counts_nodes() {
         let module = UptimeModule::new();
-        let db = Db::open(Path::new(":memory:")).unwrap();
+        let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
         let ctx = test_context();
 
         // Add some nodes
@@ -176,7 +206,7 @@ mod tests {
     #[tokio::test]
     async fn test_uptime_preserves_channel() {
         let module = UptimeModule::new();
-        let db = Db::open(Path::new(":memory:")).unwrap();
+        let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
         let mut ctx = test_context();
         ctx.channel = 5;
 
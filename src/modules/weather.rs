@@ -1,21 +1,205 @@
 use async_trait::async_trait;
+use std::sync::Arc;
 
 use crate::db::Db;
 use crate::message::{CommandScope, Destination, MessageContext, Response};
-use crate::module::Module;
+use crate::module::{Module, RateLimit};
+
+/// Name under which weather API calls are counted in `api_usage`.
+const API_NAME: &str = "weather";
+
+/// The NWS API refuses requests without a `User-Agent`.
+const NWS_USER_AGENT: &str = concat!("meshenger/", env!("CARGO_PKG_VERSION"));
+
+/// Give up on an alerts request that takes longer than this.
+const ALERTS_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// NWS alert severity, lowest first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AlertSeverity {
+    Unknown,
+    Minor,
+    Moderate,
+    Severe,
+    Extreme,
+}
+
+impl AlertSeverity {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "unknown" => Some(AlertSeverity::Unknown),
+            "minor" => Some(AlertSeverity::Minor),
+            "moderate" => Some(AlertSeverity::Moderate),
+            "severe" => Some(AlertSeverity::Severe),
+            "extreme" => Some(AlertSeverity::Extreme),
+            _ => None,
+        }
+    }
+}
+
+/// An active alert for the `[weather]` location.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeatherAlert {
+    pub id: String,
+    /// Earlier alerts this one updates.
+    pub references: Vec<String>,
+    /// e.g. "Severe Thunderstorm Warning"
+    pub event: String,
+    pub severity: AlertSeverity,
+    /// One-line summary with the issuing office and times, if given.
+    pub headline: Option<String>,
+    pub expires: Option<i64>,
+}
+
+/// Active National Weather Service alerts (US only) for a point.
+pub async fn fetch_nws_alerts(
+    lat: f64,
+    lon: f64,
+) -> Result<Vec<WeatherAlert>, Box<dyn std::error::Error + Send + Sync>> {
+    let client = reqwest::Client::builder()
+        .timeout(ALERTS_TIMEOUT)
+        .user_agent(NWS_USER_AGENT)
+        .build()?;
+    let url = format!(
+        "https://api.weather.gov/alerts/active?point={:.4},{:.4}",
+        lat, lon
+    );
+    let json: serde_json::Value = client
+        .get(&url)
+        .header(reqwest::header::ACCEPT, "application/geo+json")
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    Ok(parse_nws_alerts(&json))
+}
+
+/// Alerts in an NWS `/alerts/active` GeoJSON response. Test messages and
+/// cancellations are left out.
+fn parse_nws_alerts(json: &serde_json::Value) -> Vec<WeatherAlert> {
+    let Some(features) = json["features"].as_array() else {
+        return Vec::new();
+    };
+    features
+        .iter()
+        .filter_map(|feature| {
+            let props = &feature["properties"];
+            if props["status"].as_str() != Some("Actual")
+                || props["messageType"].as_str() == Some("Cancel")
+            {
+                return None;
+            }
+            Some(WeatherAlert {
+                id: props["id"].as_str()?.to_string(),
+                references: props["references"]
+                    .as_array()
+                    .map(|refs| {
+                        refs.iter()
+                            .filter_map(|r| r["identifier"].as_str().map(str::to_string))
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+                event: props["event"]
+                    .as_str()
+                    .unwrap_or("Weather alert")
+                    .to_string(),
+                severity: props["severity"]
+                    .as_str()
+                    .and_then(AlertSeverity::parse)
+                    .unwrap_or(AlertSeverity::Unknown),
+                headline: props["headline"]
+                    .as_str()
+                    .filter(|h| !h.is_empty())
+                    .map(str::to_string),
+                expires: props["expires"]
+                    .as_str()
+                    .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+                    .map(|t| t.timestamp()),
+            })
+        })
+        .collect()
+}
+
+/// What `!weather` reports.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Forecast {
+    Current,
+    Tomorrow,
+    ThreeDay,
+}
+
+/// Split `!weather [3day|tomorrow] [place or node]` into the forecast
+/// asked for and the place (empty for the default location).
+fn parse_args(args: &str) -> (Forecast, &str) {
+    let args = args.trim();
+    let (first, rest) = args.split_once(' ').unwrap_or((args, ""));
+    match first.to_lowercase().as_str() {
+        "tomorrow" => (Forecast::Tomorrow, rest.trim()),
+        "3day" => (Forecast::ThreeDay, rest.trim()),
+        _ => (Forecast::Current, args),
+    }
+}
+
+/// Coordinates of the first Open-Meteo geocoding result, with its name
+/// and country code.
+fn parse_geocoding(json: &serde_json::Value) -> Option<(f64, f64, String)> {
+    let place = json["results"].get(0)?;
+    let lat = place["latitude"].as_f64()?;
+    let lon = place["longitude"].as_f64()?;
+    let name = place["name"].as_str()?;
+    let label = match place["country_code"].as_str() {
+        Some(country) => format!("{}, {}", name, country),
+        None => name.to_string(),
+    };
+    Some((lat, lon, label))
+}
+
+/// GET an Open-Meteo URL as JSON; `Err` holds the reply for an HTTP error.
+async fn get_json(
+    url: &str,
+) -> Result<Result<serde_json::Value, String>, Box<dyn std::error::Error + Send + Sync>> {
+    let resp = reqwest::get(url).await.map_err(|e| {
+        log::error!("Weather API request failed: {}", e);
+        e
+    })?;
+
+    if !resp.status().is_success() {
+        let status = resp.status();
+        log::error!("Weather API returned HTTP {}", status);
+        return Ok(Err(format!(
+            "Weather unavailable (HTTP {})",
+            status.as_u16()
+        )));
+    }
+
+    Ok(Ok(resp.json().await?))
+}
+
+fn reply(ctx: &MessageContext, text: String) -> Response {
+    Response {
+        text,
+        destination: Destination::Sender,
+        channel: ctx.channel,
+        reply_id: None,
+        data: None,
+    }
+}
 
 pub struct WeatherModule {
     latitude: f64,
     longitude: f64,
     units: String,
+    daily_quota: u64,
 }
 
 impl WeatherModule {
-    pub fn new(latitude: f64, longitude: f64, units: String) -> Self {
+    pub fn new(latitude: f64, longitude: f64, units: String, daily_quota: u64) -> Self {
         Self {
             latitude,
             longitude,
             units,
+            daily_quota,
         }
     }
 
@@ -86,6 +270,152 @@ impl WeatherModule {
             primary * 0.621_371
         }
     }
+
+    /// Count one call against `daily_quota`; false when it's used up.
+    async fn consume_api_call(
+        &self,
+        db: &Arc<Db>,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let quota = self.daily_quota;
+        db.run(move |db| db.try_consume_api_call(API_NAME, quota))
+            .await
+    }
+
+    /// Where to report on, with the note shown after "Weather": the
+    /// sender's position, or the configured location, when no place is
+    /// given; otherwise a node's stored position, or the place geocoded.
+    /// `Err` holds the reply when it can't be found.
+    async fn locate(
+        &self,
+        place: &str,
+        ctx: &MessageContext,
+        db: &Arc<Db>,
+    ) -> Result<Result<(f64, f64, String), String>, Box<dyn std::error::Error + Send + Sync>> {
+        if place.is_empty() {
+            // Use sender's position if available, otherwise fall back to configured default
+            let sender = ctx.sender_id;
+            return Ok(Ok(
+                match db.run(move |db| db.get_node_position(sender)).await? {
+                    Some((lat, lon)) => (lat, lon, " (your location)".to_string()),
+                    None => (self.latitude, self.longitude, String::new()),
+                },
+            ));
+        }
+        let name_query = place.to_string();
+        let node = db
+            .run(move |db| match db.find_node_by_name(&name_query)? {
+                Some(node_id) => Ok(Some((
+                    db.get_node_name(node_id)?,
+                    db.get_node_position(node_id)?,
+                ))),
+                None => Ok(None),
+            })
+            .await?;
+        if let Some((name, position)) = node {
+            return Ok(match position {
+                Some((lat, lon)) => Ok((lat, lon, format!(" ({})", name))),
+                None => Err(format!("No position known for {}.", name)),
+            });
+        }
+
+        if !self.consume_api_call(db).await? {
+            return Ok(Err(self.quota_exceeded(ctx).text));
+        }
+        let url = reqwest::Url::parse_with_params(
+            "https://geocoding-api.open-meteo.com/v1/search",
+            &[("name", place), ("count", "1")],
+        )?;
+        let json = match get_json(url.as_str()).await? {
+            Ok(json) => json,
+            Err(text) => return Ok(Err(text)),
+        };
+        Ok(match parse_geocoding(&json) {
+            Some((lat, lon, name)) => Ok((lat, lon, format!(" ({})", name))),
+            None => Err(format!("Unknown place: {}", place)),
+        })
+    }
+
+    fn quota_exceeded(&self, ctx: &MessageContext) -> Response {
+        log::warn!("Weather API daily quota of {} reached", self.daily_quota);
+        reply(
+            ctx,
+            "Weather quota exceeded for today, try tomorrow.".to_string(),
+        )
+    }
+
+    /// Current conditions from an Open-Meteo `current=` response.
+    fn format_current(&self, json: &serde_json::Value, location_note: &str) -> Option<String> {
+        let current = json.get("current").filter(|c| c.is_object())?;
+        let temp = current["temperature_2m"].as_f64().unwrap_or(0.0);
+        let humidity = current["relative_humidity_2m"].as_f64().unwrap_or(0.0);
+        let weather_code = current["weather_code"].as_u64().unwrap_or(0);
+        let wind = current["wind_speed_10m"].as_f64().unwrap_or(0.0);
+
+        let conditions = wmo_code_to_description(weather_code);
+        let temp_secondary = self.secondary_temp_value(temp);
+        let wind_secondary = self.secondary_wind_value(wind);
+
+        Some(format!(
+            "Weather{}: {:.0}{} / {:.0}{} {}\nHumidity: {:.0}% Wind: {:.0}{} / {:.0}{}",
+            location_note,
+            temp,
+            self.temp_symbol(),
+            temp_secondary,
+            self.secondary_temp_symbol(),
+            conditions,
+            humidity,
+            wind,
+            self.wind_symbol(),
+            wind_secondary,
+            self.secondary_wind_symbol(),
+        ))
+    }
+
+    /// One line per day in `days` of an Open-Meteo `daily=` response (day 0
+    /// is today), short enough that a chunk boundary never splits a day.
+    fn format_daily(
+        &self,
+        json: &serde_json::Value,
+        location_note: &str,
+        days: std::ops::Range<usize>,
+    ) -> Option<String> {
+        let daily = json.get("daily").filter(|d| d.is_object())?;
+        let single_day = days.len() == 1;
+        let mut lines = vec![if single_day {
+            format!("Tomorrow{}:", location_note)
+        } else {
+            format!("{}-day forecast{}:", days.len(), location_note)
+        }];
+        for day in days {
+            let date = daily["time"].get(day)?.as_str()?;
+            let max = daily["temperature_2m_max"].get(day)?.as_f64()?;
+            let min = daily["temperature_2m_min"].get(day)?.as_f64()?;
+            let code = daily["weather_code"].get(day)?.as_u64().unwrap_or(0);
+            let mut line = format!(
+                "{:.0}/{:.0}{} {}",
+                max,
+                min,
+                self.temp_symbol(),
+                wmo_code_to_description(code)
+            );
+            if let Some(rain) = daily["precipitation_probability_max"]
+                .get(day)
+                .and_then(|v| v.as_f64())
+            {
+                line.push_str(&format!(", {:.0}% rain", rain));
+            }
+            if single_day {
+                lines[0].push(' ');
+                lines[0].push_str(&line);
+            } else {
+                let weekday = chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                    .map(|d| d.format("%a").to_string())
+                    .unwrap_or_else(|_| date.to_string());
+                lines.push(format!("{}: {}", weekday, line));
+            }
+        }
+        Some(lines.join("\n"))
+    }
 }
 
 fn wmo_code_to_description(code: u64) -> &'static str {
@@ -127,90 +457,67 @@ impl Module for WeatherModule {
         CommandScope::Both
     }
 
+    /// Every call hits the weather API, so keep it well below the global limit.
+    fn rate_limit(&self) -> Option<RateLimit> {
+        Some(RateLimit {
+            max_commands: 2,
+            window_secs: 3600,
+        })
+    }
+
     async fn handle_command(
         &self,
         _command: &str,
-        _args: &str,
+        args: &str,
         ctx: &MessageContext,
-        db: &Db,
+        db: &Arc<Db>,
     ) -> Result<Option<Vec<Response>>, Box<dyn std::error::Error + Send + Sync>> {
-        // Use sender's position if available, otherwise fall back to configured default
-        let (lat, lon, location_note) = match db.get_node_position(ctx.sender_id)? {
-            Some((lat, lon)) => (lat, lon, " (your location)"),
-            None => (self.latitude, self.longitude, ""),
+        let (forecast, place) = parse_args(args);
+        let (lat, lon, location_note) = match self.locate(place, ctx, db).await? {
+            Ok(location) => location,
+            Err(text) => return Ok(Some(vec![reply(ctx, text)])),
         };
 
-        let url = format!(
-            "https://api.open-meteo.com/v1/forecast?latitude={}&longitude={}\
-             &current=temperature_2m,relative_humidity_2m,weather_code,wind_speed_10m\
-             &temperature_unit={}&wind_speed_unit={}",
-            lat,
-            lon,
-            self.temperature_unit(),
-            self.wind_unit(),
-        );
-
-        let resp = reqwest::get(&url).await.map_err(|e| {
-            log::error!("Weather API request failed: {}", e);
-            e
-        })?;
-
-        if !resp.status().is_success() {
-            let status = resp.status();
-            log::error!("Weather API returned HTTP {}", status);
-            return Ok(Some(vec![Response {
-                text: format!("Weather unavailable (HTTP {})", status.as_u16()),
-                destination: Destination::Sender,
-                channel: ctx.channel,
-                reply_id: None,
-            }]));
+        if !self.consume_api_call(db).await? {
+            return Ok(Some(vec![self.quota_exceeded(ctx)]));
         }
 
-        let json: serde_json::Value = resp.json().await?;
-
-        let current = match json.get("current") {
-            Some(c) if c.is_object() => c,
-            _ => {
-                log::error!("Weather API response missing 'current' object: {}", json);
-                return Ok(Some(vec![Response {
-                    text: "Weather unavailable (bad API response)".to_string(),
-                    destination: Destination::Sender,
-                    channel: ctx.channel,
-                    reply_id: None,
-                }]));
-            }
+        let url = match forecast {
+            Forecast::Current => format!(
+                "https://api.open-meteo.com/v1/forecast?latitude={}&longitude={}\
+                 &current=temperature_2m,relative_humidity_2m,weather_code,wind_speed_10m\
+                 &temperature_unit={}&wind_speed_unit={}",
+                lat,
+                lon,
+                self.temperature_unit(),
+                self.wind_unit(),
+            ),
+            Forecast::Tomorrow | Forecast::ThreeDay => format!(
+                "https://api.open-meteo.com/v1/forecast?latitude={}&longitude={}\
+                 &daily=weather_code,temperature_2m_max,temperature_2m_min,\
+                 precipitation_probability_max&forecast_days=3&timezone=auto\
+                 &temperature_unit={}",
+                lat,
+                lon,
+                self.temperature_unit(),
+            ),
+        };
+        let json = match get_json(&url).await? {
+            Ok(json) => json,
+            Err(text) => return Ok(Some(vec![reply(ctx, text)])),
         };
 
-        let temp = current["temperature_2m"].as_f64().unwrap_or(0.0);
-        let humidity = current["relative_humidity_2m"].as_f64().unwrap_or(0.0);
-        let weather_code = current["weather_code"].as_u64().unwrap_or(0);
-        let wind = current["wind_speed_10m"].as_f64().unwrap_or(0.0);
-
-        let conditions = wmo_code_to_description(weather_code);
-        let temp_secondary = self.secondary_temp_value(temp);
-        let wind_secondary = self.secondary_wind_value(wind);
-
-        let text = format!(
-            "Weather{}: {:.0}{} / {:.0}{} {}\nHumidity: {:.0}% Wind: {:.0}{} / {:.0}{}",
-            location_note,
-            temp,
-            self.temp_symbol(),
-            temp_secondary,
-            self.secondary_temp_symbol(),
-            conditions,
-            humidity,
-            wind,
-            self.wind_symbol(),
-            wind_secondary,
-            self.secondary_wind_symbol(),
-        );
+        let text = match forecast {
+            Forecast::Current => self.format_current(&json, &location_note),
+            Forecast::Tomorrow => self.format_daily(&json, &location_note, 1..2),
+            Forecast::ThreeDay => self.format_daily(&json, &location_note, 0..3),
+        };
+        let text = text.unwrap_or_else(|| {
+            log::error!("Weather API response missing forecast data: {}", json);
+            "Weather unavailable (bad API response)".to_string()
+        });
 
-        Ok(Some(vec![Response {
-            text,
-            destination: Destination::Sender,
-            channel: ctx.channel,
-            reply_id: None,
-        }]))
+        Ok(Some(vec![reply(ctx, text)]))
     }
 }
 
@@ -237,7 +544,7 @@ mod tests {
 
     #[test]
     fn test_metric_units() {
-        let module = WeatherModule::new(25.0, 121.0, "metric".to_string());
+        let module = WeatherModule::new(25.0, 121.0, "metric".to_string(), 0);
         assert_eq!(module.temperature_unit(), "celsius");
         assert_eq!(module.temp_symbol(), "°C");
         assert_eq!(module.wind_unit(), "kmh");
@@ -250,7 +557,7 @@ mod tests {
 
     #[test]
     fn test_imperial_units() {
-        let module = WeatherModule::new(25.0, 121.0, "imperial".to_string());
+        let module = WeatherModule::new(25.0, 121.0, "imperial".to_string(), 0);
         assert_eq!(module.temperature_unit(), "fahrenheit");
         assert_eq!(module.temp_symbol(), "°F");
         assert_eq!(module.wind_unit(), "mph");
@@ -263,9 +570,165 @@ mod tests {
 
     #[test]
     fn test_module_metadata() {
-        let module = WeatherModule::new(25.0, 121.0, "metric".to_string());
+        let module = WeatherModule::new(25.0, 121.0, "metric".to_string(), 0);
         assert_eq!(module.name(), "weather");
         assert_eq!(module.commands(), &["weather"]);
         assert_eq!(module.scope(), CommandScope::Both);
     }
+
+    #[test]
+    fn test_parse_nws_alerts() {
+        let json = serde_json::json!({
+            "features": [
+                {"properties": {
+                    "id": "urn:oid:2.49.0.1.840.0.1",
+                    "status": "Actual",
+                    "messageType": "Alert",
+                    "event": "Tornado Warning",
+                    "severity": "Extreme",
+                    "headline": "Tornado Warning issued May 5 at 3:12PM CDT until May 5 at 4:00PM CDT by NWS Fort Worth TX",
+                    "expires": "2025-05-05T16:00:00-05:00",
+                    "references": []
+                }},
+                {"properties": {
+                    "id": "urn:oid:2.49.0.1.840.0.2",
+                    "status": "Actual",
+                    "messageType": "Update",
+                    "event": "Flood Advisory",
+                    "severity": "Minor",
+                    "references": [{"identifier": "urn:oid:2.49.0.1.840.0.0"}]
+                }},
+                {"properties": {
+                    "id": "urn:oid:2.49.0.1.840.0.3",
+                    "status": "Test",
+                    "messageType": "Alert",
+                    "event": "Test Message"
+                }},
+                {"properties": {
+                    "id": "urn:oid:2.49.0.1.840.0.4",
+                    "status": "Actual",
+                    "messageType": "Cancel",
+                    "event": "Tornado Warning"
+                }}
+            ]
+        });
+        let alerts = parse_nws_alerts(&json);
+        assert_eq!(alerts.len(), 2);
+        assert_eq!(alerts[0].event, "Tornado Warning");
+        assert_eq!(alerts[0].severity, AlertSeverity::Extreme);
+        assert_eq!(alerts[0].expires, Some(1746478800));
+        assert!(alerts[0].references.is_empty());
+        assert_eq!(alerts[1].severity, AlertSeverity::Minor);
+        assert_eq!(alerts[1].headline, None);
+        assert_eq!(alerts[1].references, vec!["urn:oid:2.49.0.1.840.0.0"]);
+        assert!(parse_nws_alerts(&serde_json::json!({})).is_empty());
+
+        assert!(AlertSeverity::Severe > AlertSeverity::Moderate);
+        assert_eq!(AlertSeverity::parse("SEVERE"), Some(AlertSeverity::Severe));
+        assert_eq!(AlertSeverity::parse("bad"), None);
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(parse_args(""), (Forecast::Current, ""));
+        assert_eq!(parse_args("3day"), (Forecast::ThreeDay, ""));
+        assert_eq!(
+            parse_args("Tomorrow Berlin"),
+            (Forecast::Tomorrow, "Berlin")
+        );
+        assert_eq!(parse_args(" New York "), (Forecast::Current, "New York"));
+    }
+
+    #[test]
+    fn test_parse_geocoding() {
+        let json = serde_json::json!({"results": [
+            {"name": "Berlin", "latitude": 52.52, "longitude": 13.41, "country_code": "DE"}
+        ]});
+        assert_eq!(
+            parse_geocoding(&json),
+            Some((52.52, 13.41, "Berlin, DE".to_string()))
+        );
+        assert_eq!(parse_geocoding(&serde_json::json!({})), None);
+    }
+
+    #[test]
+    fn test_format_daily() {
+        let module = WeatherModule::new(25.0, 121.0, "metric".to_string(), 0);
+        let json = serde_json::json!({"daily": {
+            "time": ["2025-05-05", "2025-05-06", "2025-05-07"],
+            "weather_code": [0, 61, 95],
+            "temperature_2m_max": [24.4, 19.0, 21.6],
+            "temperature_2m_min": [15.2, 12.8, 14.0],
+            "precipitation_probability_max": [0, 80, 55]
+        }});
+        assert_eq!(
+            module.format_daily(&json, "", 0..3).unwrap(),
+            "3-day forecast:\nMon: 24/15°C Clear sky, 0% rain\n\
+             Tue: 19/13°C Rain, 80% rain\nWed: 22/14°C Thunderstorm, 55% rain"
+        );
+        assert_eq!(
+            module.format_daily(&json, " (Berlin, DE)", 1..2).unwrap(),
+            "Tomorrow (Berlin, DE): 19/13°C Rain, 80% rain"
+        );
+        assert_eq!(module.format_daily(&serde_json::json!({}), "", 1..2), None);
+    }
+
+    #[tokio::test]
+    async fn test_node_without_position() {
+        let module = WeatherModule::new(25.0, 121.0, "metric".to_string(), 0);
+        let db = Arc::new(Db::open(std::path::Path::new(":memory:")).unwrap());
+        db.upsert_node(0xaabbccdd, "HILL", "Hilltop", false)
+            .unwrap();
+        let ctx = MessageContext {
+            sender_id: 0x12345678,
+            sender_name: "TestNode".to_string(),
+            channel: 0,
+            is_dm: true,
+            rssi: -70,
+            snr: 5.0,
+            hop_count: 1,
+            hop_start: 3,
+            hop_limit: 2,
+            via_mqtt: false,
+            pki_key: None,
+            packet_id: 0,
+        };
+        let responses = module
+            .handle_command("weather", "tomorrow hilltop", &ctx, &db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(responses[0].text, "No position known for Hilltop.");
+    }
+
+    #[tokio::test]
+    async fn test_daily_quota_exceeded() {
+        let module = WeatherModule::new(25.0, 121.0, "metric".to_string(), 1);
+        let db = Arc::new(Db::open(std::path::Path::new(":memory:")).unwrap());
+        db.try_consume_api_call(API_NAME, 1).unwrap();
+
+        let ctx = MessageContext {
+            sender_id: 0x12345678,
+            sender_name: "TestNode".to_string(),
+            channel: 0,
+            is_dm: true,
+            rssi: -70,
+            snr: 5.0,
+            hop_count: 1,
+            hop_start: 3,
+            hop_limit: 2,
+            via_mqtt: false,
+            pki_key: None,
+            packet_id: 0,
+        };
+        let responses = module
+            .handle_command("weather", "", &ctx, &db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            responses[0].text,
+            "Weather quota exceeded for today, try tomorrow."
+        );
+    }
 }
@@ -1,7 +1,10 @@
 use std::collections::HashSet;
 
 use async_trait::async_trait;
+use chrono::{Local, Timelike};
+use std::sync::Arc;
 
+use crate::config::WelcomeRuleConfig;
 use crate::db::Db;
 use crate::message::{CommandScope, Destination, MeshEvent, MessageContext, Response};
 use crate::module::Module;
@@ -12,6 +15,19 @@ pub struct WelcomeModule {
     welcome_back_message: String,
     absence_threshold_hours: u64,
     whitelist: Option<HashSet<u32>>,
+    rules: Vec<WelcomeRuleConfig>,
+    /// `(start_hour, end_hour)` during which nobody is greeted
+    quiet_hours: Option<(u32, u32)>,
+}
+
+/// Whether `hour` is in `start..end`, wrapping past midnight when
+/// `end <= start`.
+fn in_window(start: u32, end: u32, hour: u32) -> bool {
+    if start < end {
+        hour >= start && hour < end
+    } else {
+        hour >= start || hour < end
+    }
 }
 
 impl WelcomeModule {
@@ -20,6 +36,7 @@ impl WelcomeModule {
         welcome_back_message: String,
         absence_threshold_hours: u64,
         whitelist: Vec<String>,
+        rules: Vec<WelcomeRuleConfig>,
     ) -> Self {
         let whitelist = if whitelist.is_empty() {
             None
@@ -33,9 +50,25 @@ impl WelcomeModule {
             welcome_back_message,
             absence_threshold_hours,
             whitelist,
+            rules,
+            quiet_hours: None,
         }
     }
 
+    /// Greet nobody during `[welcome] quiet_hours` (`[start, end]`, or empty).
+    pub fn with_quiet_hours(mut self, hours: &[u32]) -> Self {
+        if let [start, end] = *hours {
+            log::info!("Welcome quiet hours: {}:00-{}:00", start, end);
+            self.quiet_hours = Some((start, end));
+        }
+        self
+    }
+
+    fn is_quiet(&self, hour: u32) -> bool {
+        self.quiet_hours
+            .is_some_and(|(start, end)| in_window(start, end, hour))
+    }
+
     fn is_allowed(&self, node_id: u32) -> bool {
         match &self.whitelist {
             None => true,
@@ -43,6 +76,30 @@ impl WelcomeModule {
         }
     }
 
+    /// Pick the template for a greeting: the first rule matching the local
+    /// hour, the node's source and new/returning status, else the defaults.
+    fn select_template(&self, is_new: bool, via_mqtt: bool, hour: u32) -> &str {
+        let rule = self.rules.iter().find(|rule| {
+            let in_window = in_window(rule.start_hour, rule.end_hour, hour);
+            let source_ok = match rule.source.to_lowercase().as_str() {
+                "rf" => !via_mqtt,
+                "mqtt" => via_mqtt,
+                _ => true,
+            };
+            let kind_ok = match rule.kind.to_lowercase().as_str() {
+                "new" => is_new,
+                "back" => !is_new,
+                _ => true,
+            };
+            in_window && source_ok && kind_ok
+        });
+        match rule {
+            Some(rule) => &rule.message,
+            None if is_new => &self.message,
+            None => &self.welcome_back_message,
+        }
+    }
+
     fn format_message(&self, template: &str, name: &str) -> String {
         template.replace("{name}", name)
     }
@@ -71,7 +128,7 @@ impl Module for WelcomeModule {
         _command: &str,
         _args: &str,
         _ctx: &MessageContext,
-        _db: &Db,
+        _db: &Arc<Db>,
     ) -> Result<Option<Vec<Response>>, Box<dyn std::error::Error + Send + Sync>> {
         Ok(None)
     }
@@ -79,14 +136,14 @@ impl Module for WelcomeModule {
     async fn handle_event(
         &self,
         event: &MeshEvent,
-        db: &Db,
+        db: &Arc<Db>,
     ) -> Result<Option<Vec<Response>>, Box<dyn std::error::Error + Send + Sync>> {
         match event {
             MeshEvent::NodeDiscovered {
                 node_id,
                 long_name,
                 short_name,
-                ..
+                via_mqtt,
             } => {
                 if !self.is_allowed(*node_id) {
                     return Ok(None);
@@ -100,33 +157,48 @@ impl Module for WelcomeModule {
                     "friend"
                 };
 
-                let is_new = db.is_node_new(*node_id)?;
-                let is_absent = if !is_new {
-                    db.is_node_absent(*node_id, self.absence_threshold_hours)?
-                } else {
-                    false
-                };
-
-                // Update node in DB before deciding on message
-                db.upsert_node(*node_id, short_name, long_name, false)?;
-
+                let node_id = *node_id;
+                let (short, long) = (short_name.clone(), long_name.clone());
+                let absence_hours = self.absence_threshold_hours;
+                let (is_new, is_absent) = db
+                    .run(move |db| {
+                        let is_new = db.is_node_new(node_id)?;
+                        let is_absent = if !is_new {
+                            db.is_node_absent(node_id, absence_hours)?
+                        } else {
+                            false
+                        };
+                        // Update node in DB before deciding on message
+                        db.upsert_node(node_id, &short, &long, false)?;
+                        Ok((is_new, is_absent))
+                    })
+                    .await?;
+
+                let hour = Local::now().hour();
+                if (is_new || is_absent) && self.is_quiet(hour) {
+                    log::info!("Not greeting {} ({}) in quiet hours", display_name, node_id);
+                    return Ok(None);
+                }
                 let text = if is_new {
                     log::info!("New node discovered: {} ({})", display_name, node_id);
-                    Some(self.format_message(&self.message, display_name))
+                    let template = self.select_template(true, *via_mqtt, hour);
+                    Some(self.format_message(template, display_name))
                 } else if is_absent {
                     log::info!("Returning node: {} ({})", display_name, node_id);
-                    Some(self.format_message(&self.welcome_back_message, display_name))
+                    let template = self.select_template(false, *via_mqtt, hour);
+                    Some(self.format_message(template, display_name))
                 } else {
                     None
                 };
 
                 if let Some(text) = text {
-                    db.mark_welcomed(*node_id)?;
+                    db.run(move |db| db.mark_welcomed(node_id)).await?;
                     Ok(Some(vec![Response {
                         text,
-                        destination: Destination::Node(*node_id),
+                        destination: Destination::Node(node_id),
                         channel: 0,
                         reply_id: None,
+                        data: None,
                     }]))
                 } else {
                     Ok(None)
@@ -148,9 +220,55 @@ mod tests {
             "Welcome back, {name}!".to_string(),
             48,
             whitelist.into_iter().map(|s| s.to_string()).collect(),
+            Vec::new(),
         )
     }
 
+    fn rule(message: &str, start_hour: u32, end_hour: u32, source: &str) -> WelcomeRuleConfig {
+        WelcomeRuleConfig {
+            message: message.to_string(),
+            start_hour,
+            end_hour,
+            source: source.to_string(),
+            kind: "any".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_select_template_rules() {
+        let mut module = create_module(vec![]);
+        module.rules = vec![
+            rule("Evening RF", 17, 23, "rf"),
+            rule("Night", 23, 6, "any"),
+            WelcomeRuleConfig {
+                kind: "back".to_string(),
+                ..rule("Back via MQTT", 0, 24, "mqtt")
+            },
+        ];
+
+        assert_eq!(module.select_template(true, false, 18), "Evening RF");
+        // MQTT-heard new node in the evening falls through to the default
+        assert_eq!(module.select_template(true, true, 18), "Welcome, {name}!");
+        // Window wrapping past midnight
+        assert_eq!(module.select_template(true, true, 2), "Night");
+        assert_eq!(module.select_template(true, false, 23), "Night");
+        assert_eq!(module.select_template(false, true, 12), "Back via MQTT");
+        assert_eq!(
+            module.select_template(false, false, 12),
+            "Welcome back, {name}!"
+        );
+    }
+
+    #[test]
+    fn test_quiet_hours() {
+        let module = create_module(vec![]).with_quiet_hours(&[22, 7]);
+        assert!(module.is_quiet(23));
+        assert!(module.is_quiet(3));
+        assert!(!module.is_quiet(7));
+        assert!(!module.is_quiet(12));
+        assert!(!create_module(vec![]).with_quiet_hours(&[]).is_quiet(23));
+    }
+
     #[test]
     fn test_welcome_module_metadata() {
         let module = create_module(vec![]);
@@ -190,7 +308,7 @@ mod tests {
     #[tokio::test]
     async fn test_welcome_new_node() {
         let module = create_module(vec![]);
-        let db = Db::open(Path::new(":memory:")).unwrap();
+        let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
 
         let event = MeshEvent::NodeDiscovered {
             node_id: 0x12345678,
@@ -214,7 +332,7 @@ mod tests {
     #[tokio::test]
     async fn test_welcome_existing_node_no_message() {
         let module = create_module(vec![]);
-        let db = Db::open(Path::new(":memory:")).unwrap();
+        let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
 
         // Node already exists and was seen recently
         db.upsert_node(0x12345678, "AAAA", "Alice", false).unwrap();
@@ -233,7 +351,7 @@ mod tests {
     #[tokio::test]
     async fn test_welcome_whitelist_blocks() {
         let module = create_module(vec!["!aabbccdd"]);
-        let db = Db::open(Path::new(":memory:")).unwrap();
+        let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
 
         let event = MeshEvent::NodeDiscovered {
             node_id: 0x12345678, // Not in whitelist
@@ -249,7 +367,7 @@ mod tests {
     #[tokio::test]
     async fn test_welcome_whitelist_allows() {
         let module = create_module(vec!["!12345678"]);
-        let db = Db::open(Path::new(":memory:")).unwrap();
+        let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
 
         let event = MeshEvent::NodeDiscovered {
             node_id: 0x12345678, // In whitelist
@@ -265,7 +383,7 @@ mod tests {
     #[tokio::test]
     async fn test_welcome_uses_short_name_fallback() {
         let module = create_module(vec![]);
-        let db = Db::open(Path::new(":memory:")).unwrap();
+        let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
 
         let event = MeshEvent::NodeDiscovered {
             node_id: 0x12345678,
@@ -282,7 +400,7 @@ mod tests {
     #[tokio::test]
     async fn test_welcome_uses_friend_fallback() {
         let module = create_module(vec![]);
-        let db = Db::open(Path::new(":memory:")).unwrap();
+        let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
 
         let event = MeshEvent::NodeDiscovered {
             node_id: 0x12345678,
@@ -299,7 +417,7 @@ mod tests {
     #[tokio::test]
     async fn test_welcome_ignores_position_update() {
         let module = create_module(vec![]);
-        let db = Db::open(Path::new(":memory:")).unwrap();
+        let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
 
         let event = MeshEvent::PositionUpdate {
             node_id: 0x12345678,
@@ -315,7 +433,7 @@ mod tests {
     #[tokio::test]
     async fn test_welcome_marks_welcomed() {
         let module = create_module(vec![]);
-        let db = Db::open(Path::new(":memory:")).unwrap();
+        let db = Arc::new(Db::open(Path::new(":memory:")).unwrap());
 
         let event = MeshEvent::NodeDiscovered {
             node_id: 0x12345678,
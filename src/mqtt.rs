@@ -0,0 +1,352 @@
+use std::time::Duration;
+
+use aes::cipher::{KeyIvInit, StreamCipher};
+use base64::Engine;
+use meshtastic::protobufs::{mesh_packet, Data, MeshPacket, ServiceEnvelope};
+use meshtastic::Message;
+use rumqttc::{AsyncClient, Event, MqttOptions, Packet, QoS, Transport};
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+
+use crate::config::MqttConnectionConfig;
+use crate::util::parse_node_id;
+
+/// The Meshtastic default channel key, selected by the 1-byte PSK `AQ==`.
+const DEFAULT_PSK: [u8; 16] = [
+    0xd4, 0xf1, 0xbb, 0x3a, 0x20, 0x29, 0x07, 0x59, 0xf0, 0xbc, 0xff, 0xab, 0xcf, 0x4e, 0x69, 0x01,
+];
+
+type Aes128Ctr = ctr::Ctr128BE<aes::Aes128>;
+type Aes256Ctr = ctr::Ctr128BE<aes::Aes256>;
+
+struct Channel {
+    name: String,
+    /// `None` for an unencrypted channel.
+    key: Option<Vec<u8>>,
+    /// What the firmware puts in `MeshPacket.channel` for encrypted packets.
+    hash: u32,
+}
+
+/// Expand a channel PSK as the firmware does: empty or `0` means no
+/// encryption, `1..=255` picks a variant of the default key.
+fn expand_key(psk: &str) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error + Send + Sync>> {
+    let raw = base64::engine::general_purpose::STANDARD.decode(psk.trim())?;
+    match raw.len() {
+        0 => Ok(None),
+        1 if raw[0] == 0 => Ok(None),
+        1 => {
+            let mut key = DEFAULT_PSK.to_vec();
+            key[15] = key[15].wrapping_add(raw[0] - 1);
+            Ok(Some(key))
+        }
+        16 | 32 => Ok(Some(raw)),
+        n => Err(format!("channel key must be 0, 1, 16 or 32 bytes, got {}", n).into()),
+    }
+}
+
+fn channel_hash(name: &str, key: Option<&[u8]>) -> u32 {
+    let xor = |bytes: &[u8]| bytes.iter().fold(0u8, |acc, b| acc ^ b);
+    (xor(name.as_bytes()) ^ xor(key.unwrap_or(&[]))) as u32
+}
+
+/// AES-CTR as used by Meshtastic: the nonce is the packet ID (as a u64) then
+/// the sender, little-endian. Encrypting and decrypting are the same operation.
+fn apply_cipher(key: &[u8], packet_id: u32, from: u32, buf: &mut [u8]) {
+    let mut nonce = [0u8; 16];
+    nonce[..8].copy_from_slice(&(packet_id as u64).to_le_bytes());
+    nonce[8..12].copy_from_slice(&from.to_le_bytes());
+    match key.len() {
+        16 => Aes128Ctr::new(key.into(), &nonce.into()).apply_keystream(buf),
+        _ => Aes256Ctr::new(key.into(), &nonce.into()).apply_keystream(buf),
+    }
+}
+
+/// Converts between the broker's `ServiceEnvelope`s and the decoded
+/// `MeshPacket`s the bot works with.
+pub struct MqttCodec {
+    channels: Vec<Channel>,
+    topic_root: String,
+    node_id: Option<u32>,
+}
+
+impl MqttCodec {
+    pub fn new(
+        config: &MqttConnectionConfig,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let channels = config
+            .channels
+            .iter()
+            .map(|c| {
+                let key = expand_key(&c.key)
+                    .map_err(|e| format!("bad key for MQTT channel {:?}: {}", c.name, e))?;
+                Ok(Channel {
+                    hash: channel_hash(&c.name, key.as_deref()),
+                    name: c.name.clone(),
+                    key,
+                })
+            })
+            .collect::<Result<Vec<_>, Box<dyn std::error::Error + Send + Sync>>>()?;
+        let node_id = if config.node_id.is_empty() {
+            None
+        } else {
+            Some(
+                parse_node_id(&config.node_id)
+                    .ok_or_else(|| format!("bad MQTT node_id {:?}", config.node_id))?,
+            )
+        };
+        Ok(Self {
+            channels,
+            topic_root: config.topic_root.trim_end_matches('/').to_string(),
+            node_id,
+        })
+    }
+
+    /// The bot's own node ID on MQTT, if configured.
+    pub fn node_id(&self) -> Option<u32> {
+        self.node_id
+    }
+
+    /// Topic filter for every encrypted channel under the root.
+    pub fn subscription(&self) -> String {
+        format!("{}/2/e/#", self.topic_root)
+    }
+
+    /// Decode and decrypt an envelope. The returned packet is marked
+    /// `via_mqtt` and its `channel` is the index of the matching configured
+    /// channel; packets on unknown channels or with the wrong key are `None`.
+    pub fn decode(&self, payload: &[u8]) -> Option<MeshPacket> {
+        let envelope = ServiceEnvelope::decode(payload).ok()?;
+        if self.node_id.is_some() && parse_node_id(&envelope.gateway_id) == self.node_id {
+            // Our own publish echoed back by the broker
+            return None;
+        }
+        let mut packet = envelope.packet?;
+        let index = self
+            .channels
+            .iter()
+            .position(|c| c.name == envelope.channel_id)
+            .or_else(|| self.channels.iter().position(|c| c.hash == packet.channel))?;
+        let channel = &self.channels[index];
+        if let Some(mesh_packet::PayloadVariant::Encrypted(mut bytes)) = packet.payload_variant {
+            let key = channel.key.as_ref()?;
+            apply_cipher(key, packet.id, packet.from, &mut bytes);
+            let data = Data::decode(bytes.as_slice()).ok()?;
+            if data.portnum == 0 {
+                return None;
+            }
+            packet.payload_variant = Some(mesh_packet::PayloadVariant::Decoded(data));
+        }
+        packet.payload_variant.as_ref()?;
+        packet.channel = index as u32;
+        packet.via_mqtt = true;
+        Some(packet)
+    }
+
+    /// Encrypt a decoded packet (with `channel` set to a configured channel
+    /// index) into the topic and envelope to publish it as.
+    pub fn encode(&self, mut packet: MeshPacket) -> Option<(String, Vec<u8>)> {
+        let node_id = self.node_id?;
+        let channel = self.channels.get(packet.channel as usize)?;
+        if let (Some(key), Some(mesh_packet::PayloadVariant::Decoded(data))) =
+            (&channel.key, &packet.payload_variant)
+        {
+            let mut bytes = data.encode_to_vec();
+            apply_cipher(key, packet.id, packet.from, &mut bytes);
+            packet.payload_variant = Some(mesh_packet::PayloadVariant::Encrypted(bytes));
+            packet.channel = channel.hash;
+        }
+        let gateway_id = format!("!{:08x}", node_id);
+        let topic = format!("{}/2/e/{}/{}", self.topic_root, channel.name, gateway_id);
+        let envelope = ServiceEnvelope {
+            packet: Some(packet),
+            channel_id: channel.name.clone(),
+            gateway_id,
+        };
+        Some((topic, envelope.encode_to_vec()))
+    }
+}
+
+/// Publishing side of a broker connection.
+pub struct MqttLink {
+    client: AsyncClient,
+    codec: std::sync::Arc<MqttCodec>,
+}
+
+impl MqttLink {
+    pub fn node_id(&self) -> Option<u32> {
+        self.codec.node_id()
+    }
+
+    pub async fn publish(
+        &self,
+        packet: MeshPacket,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let (topic, payload) = self
+            .codec
+            .encode(packet)
+            .ok_or("packet has no configured MQTT channel or node_id")?;
+        self.client
+            .publish(topic, QoS::AtMostOnce, false, payload)
+            .await?;
+        Ok(())
+    }
+}
+
+/// Split `mqtt://host:port` / `mqtts://host:port` into host, port and TLS.
+pub(crate) fn parse_broker(
+    broker: &str,
+) -> Result<(String, u16, bool), Box<dyn std::error::Error + Send + Sync>> {
+    let (rest, tls) = if let Some(rest) = broker.strip_prefix("mqtts://") {
+        (rest, true)
+    } else {
+        (broker.strip_prefix("mqtt://").unwrap_or(broker), false)
+    };
+    let rest = rest.trim_end_matches('/');
+    match rest.rsplit_once(':') {
+        Some((host, port)) => Ok((host.to_string(), port.parse()?, tls)),
+        None => Ok((rest.to_string(), if tls { 8883 } else { 1883 }, tls)),
+    }
+}
+
+/// Connect to the broker. Decoded packets arrive on the returned receiver; the
+/// connection task reconnects on its own and stops once the receiver is dropped.
+pub fn connect(
+    config: &MqttConnectionConfig,
+    reconnect_delay: Duration,
+) -> Result<(MqttLink, UnboundedReceiver<MeshPacket>), Box<dyn std::error::Error + Send + Sync>> {
+    let codec = std::sync::Arc::new(MqttCodec::new(config)?);
+    let (host, port, tls) = parse_broker(&config.broker)?;
+    let client_id = format!("meshenger-{:08x}", rand::random::<u32>());
+    let mut options = MqttOptions::new(client_id, host.clone(), port);
+    options.set_keep_alive(Duration::from_secs(30));
+    if !config.username.is_empty() {
+        options.set_credentials(config.username.clone(), config.password.clone());
+    }
+    if tls {
+        options.set_transport(Transport::tls_with_default_config());
+    }
+    let (client, mut eventloop) = AsyncClient::new(options, 64);
+    let (tx, rx) = mpsc::unbounded_channel();
+
+    let subscription = codec.subscription();
+    let task_client = client.clone();
+    let task_codec = std::sync::Arc::clone(&codec);
+    log::info!(
+        "Connecting to MQTT broker {}:{} ({})",
+        host,
+        port,
+        subscription
+    );
+    tokio::spawn(async move {
+        while !tx.is_closed() {
+            match eventloop.poll().await {
+                Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                    log::info!("MQTT connected, subscribing to {}", subscription);
+                    if let Err(e) = task_client.try_subscribe(subscription.clone(), QoS::AtMostOnce)
+                    {
+                        log::error!("MQTT subscribe failed: {}", e);
+                    }
+                }
+                Ok(Event::Incoming(Packet::Publish(publish))) => {
+                    if let Some(packet) = task_codec.decode(&publish.payload) {
+                        if tx.send(packet).is_err() {
+                            break;
+                        }
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    log::error!("MQTT connection error: {}", e);
+                    tokio::time::sleep(reconnect_delay).await;
+                }
+            }
+        }
+        log::info!("MQTT connection task stopped");
+    });
+
+    Ok((MqttLink { client, codec }, rx))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::MqttChannelConfig;
+    use crate::test_support::PacketBuilder;
+
+    fn codec(node_id: &str) -> MqttCodec {
+        MqttCodec::new(&MqttConnectionConfig {
+            node_id: node_id.to_string(),
+            channels: vec![
+                MqttChannelConfig {
+                    name: "LongFast".to_string(),
+                    key: "AQ==".to_string(),
+                },
+                MqttChannelConfig {
+                    name: "Open".to_string(),
+                    key: String::new(),
+                },
+            ],
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_expand_key() {
+        assert_eq!(expand_key("AQ==").unwrap().unwrap(), DEFAULT_PSK.to_vec());
+        assert_eq!(expand_key("Ag==").unwrap().unwrap()[15], 0x02);
+        assert!(expand_key("").unwrap().is_none());
+        assert!(expand_key("AA==").unwrap().is_none());
+        assert!(expand_key("AAAA").is_err());
+    }
+
+    #[test]
+    fn test_encode_decode_round_trip() {
+        let sender = codec("!0000beef");
+        let receiver = codec("!0000cafe");
+        let (topic, payload) = sender
+            .encode(PacketBuilder::text(0xbeef, "hello mesh").id(0x1234).build())
+            .unwrap();
+        assert_eq!(topic, "msh/US/2/e/LongFast/!0000beef");
+
+        // The payload on the wire is encrypted
+        let envelope = ServiceEnvelope::decode(payload.as_slice()).unwrap();
+        assert!(matches!(
+            envelope.packet.unwrap().payload_variant,
+            Some(mesh_packet::PayloadVariant::Encrypted(_))
+        ));
+
+        let packet = receiver.decode(&payload).unwrap();
+        assert!(packet.via_mqtt);
+        assert_eq!(packet.channel, 0);
+        match packet.payload_variant {
+            Some(mesh_packet::PayloadVariant::Decoded(data)) => {
+                assert_eq!(data.payload, b"hello mesh");
+            }
+            other => panic!("expected a decoded packet, got {:?}", other),
+        }
+
+        // Unencrypted channel, and our own echo is dropped
+        let (_, payload) = sender
+            .encode(
+                PacketBuilder::text(0xbeef, "plain")
+                    .channel(1)
+                    .id(0x1234)
+                    .build(),
+            )
+            .unwrap();
+        assert_eq!(receiver.decode(&payload).unwrap().channel, 1);
+        assert!(sender.decode(&payload).is_none());
+    }
+
+    #[test]
+    fn test_parse_broker() {
+        assert_eq!(
+            parse_broker("mqtt://mqtt.meshtastic.org:1883").unwrap(),
+            ("mqtt.meshtastic.org".to_string(), 1883, false)
+        );
+        assert_eq!(
+            parse_broker("mqtts://broker.example").unwrap(),
+            ("broker.example".to_string(), 8883, true)
+        );
+    }
+}
@@ -0,0 +1,38 @@
+//! OpenAPI 3.1 description of the dashboard API, served at
+//! `/api/openapi.json`.
+//!
+//! Every handler in `dashboard.rs` carries a `#[utoipa::path]` and is
+//! registered with `routes!`, so the document is collected from the router
+//! each listener serves; response schemas come from `ToSchema` on the types
+//! the handlers serialize.
+
+use utoipa::openapi::security::{Http, HttpAuthScheme, SecurityScheme};
+use utoipa::openapi::{Components, Info, OpenApi};
+use utoipa::Modify;
+
+/// The parts of the document that aren't tied to a route.
+#[derive(utoipa::OpenApi)]
+#[openapi(modifiers(&BearerAuth))]
+pub struct ApiDoc;
+
+/// The `bearer` scheme routes that need `[dashboard] admin_token` (or the
+/// webhook bridge's token) refer to.
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut OpenApi) {
+        openapi
+            .components
+            .get_or_insert_with(Components::new)
+            .add_security_scheme(
+                "bearer",
+                SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)),
+            );
+    }
+}
+
+/// Title and version a listener's collected document is served with.
+pub fn finish(mut openapi: OpenApi, title: &str) -> OpenApi {
+    openapi.info = Info::new(title, env!("CARGO_PKG_VERSION"));
+    openapi
+}
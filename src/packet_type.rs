@@ -0,0 +1,140 @@
+//! The kinds of packet stored in `packets.packet_type`. New kinds are added
+//! here: the name stored in the database and accepted by the dashboard's
+//! `types` filter, and the port numbers that map to it.
+
+use std::fmt;
+use std::str::FromStr;
+
+use meshtastic::protobufs::PortNum;
+use rusqlite::types::{ToSql, ToSqlOutput};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PacketType {
+    Text,
+    Position,
+    Telemetry,
+    #[serde(rename = "nodeinfo")]
+    NodeInfo,
+    Traceroute,
+    #[serde(rename = "neighborinfo")]
+    NeighborInfo,
+    Routing,
+    StoreForward,
+    /// Any port without a type of its own
+    Other,
+}
+
+impl PacketType {
+    pub const ALL: &'static [PacketType] = &[
+        PacketType::Text,
+        PacketType::Position,
+        PacketType::Telemetry,
+        PacketType::NodeInfo,
+        PacketType::Traceroute,
+        PacketType::NeighborInfo,
+        PacketType::Routing,
+        PacketType::StoreForward,
+        PacketType::Other,
+    ];
+
+    /// Name stored in the database.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PacketType::Text => "text",
+            PacketType::Position => "position",
+            PacketType::Telemetry => "telemetry",
+            PacketType::NodeInfo => "nodeinfo",
+            PacketType::Traceroute => "traceroute",
+            PacketType::NeighborInfo => "neighborinfo",
+            PacketType::Routing => "routing",
+            PacketType::StoreForward => "store_forward",
+            PacketType::Other => "other",
+        }
+    }
+
+    /// Comma-separated names, as in the dashboard's `types` parameter.
+    /// Unknown names are dropped; `None` when no names are given at all.
+    pub fn parse_list(list: &str) -> Option<Vec<PacketType>> {
+        let names: Vec<&str> = list
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .collect();
+        if names.is_empty() {
+            return None;
+        }
+        Some(names.iter().filter_map(|name| name.parse().ok()).collect())
+    }
+
+    /// The type packets on `port` are logged as.
+    pub fn from_portnum(port: PortNum) -> Self {
+        match port {
+            PortNum::TextMessageApp => PacketType::Text,
+            PortNum::PositionApp => PacketType::Position,
+            PortNum::TelemetryApp => PacketType::Telemetry,
+            PortNum::NodeinfoApp => PacketType::NodeInfo,
+            PortNum::TracerouteApp => PacketType::Traceroute,
+            PortNum::NeighborinfoApp => PacketType::NeighborInfo,
+            PortNum::RoutingApp => PacketType::Routing,
+            PortNum::StoreForwardApp => PacketType::StoreForward,
+            _ => PacketType::Other,
+        }
+    }
+}
+
+impl fmt::Display for PacketType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for PacketType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        PacketType::ALL
+            .iter()
+            .copied()
+            .find(|t| t.as_str() == s)
+            .ok_or_else(|| format!("unknown packet type: {}", s))
+    }
+}
+
+impl ToSql for PacketType {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(self.as_str().into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_names_round_trip() {
+        for &packet_type in PacketType::ALL {
+            assert_eq!(packet_type.as_str().parse(), Ok(packet_type));
+            assert_eq!(
+                serde_json::to_value(packet_type).unwrap(),
+                serde_json::Value::from(packet_type.as_str())
+            );
+        }
+        assert!("reaction".parse::<PacketType>().is_err());
+        assert_eq!(
+            PacketType::parse_list("text, store_forward,bogus"),
+            Some(vec![PacketType::Text, PacketType::StoreForward])
+        );
+        assert_eq!(PacketType::parse_list("bogus"), Some(vec![]));
+        assert_eq!(PacketType::parse_list(" , "), None);
+        assert_eq!(
+            PacketType::from_portnum(PortNum::NodeinfoApp),
+            PacketType::NodeInfo
+        );
+        assert_eq!(
+            PacketType::from_portnum(PortNum::RangeTestApp),
+            PacketType::Other
+        );
+    }
+}
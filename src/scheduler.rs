@@ -0,0 +1,161 @@
+//! Cron-style schedules for `[[schedule]]` announcements.
+//!
+//! Supports the standard five fields (minute, hour, day of month, month, day
+//! of week) with `*`, lists (`1,15`), ranges (`9-17`) and steps (`*/15`,
+//! `0-30/10`). Names like `mon` or `jan` are not supported.
+
+use chrono::{DateTime, Datelike, Duration, Local, TimeZone, Timelike};
+
+/// A parsed cron expression; each field is a bitmask of allowed values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Day of month / day of week restricted (not `*`). When both are,
+    /// either one matching is enough, as in cron.
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+/// Parse one field into a bitmask of values in `min..=max`.
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64, String> {
+    let mut mask = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step
+                    .parse()
+                    .map_err(|_| format!("invalid step {:?}", step))?;
+                if step == 0 {
+                    return Err("step must not be 0".to_string());
+                }
+                (range, step)
+            }
+            None => (part, 1),
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((a, b)) = range.split_once('-') {
+            let a = a.parse().map_err(|_| format!("invalid value {:?}", a))?;
+            let b = b.parse().map_err(|_| format!("invalid value {:?}", b))?;
+            (a, b)
+        } else {
+            let v = range
+                .parse()
+                .map_err(|_| format!("invalid value {:?}", range))?;
+            // "5/10" means "from 5 to the end, every 10"
+            (v, if step > 1 { max } else { v })
+        };
+        if start < min || end > max || start > end {
+            return Err(format!("{:?} is outside {}-{}", range, min, max));
+        }
+        for v in (start..=end).step_by(step as usize) {
+            mask |= 1 << v;
+        }
+    }
+    Ok(mask)
+}
+
+impl CronSchedule {
+    /// Parse "minute hour day-of-month month day-of-week".
+    pub fn parse(expr: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(format!("{:?} does not have 5 fields", expr));
+        };
+        let mut weekdays = parse_field(weekday, 0, 7)?;
+        // 7 is Sunday too
+        if weekdays & (1 << 7) != 0 {
+            weekdays |= 1;
+        }
+        Ok(Self {
+            minutes: parse_field(minute, 0, 59)?,
+            hours: parse_field(hour, 0, 23)?,
+            days: parse_field(day, 1, 31)?,
+            months: parse_field(month, 1, 12)?,
+            weekdays,
+            days_restricted: day != "*",
+            weekdays_restricted: weekday != "*",
+        })
+    }
+
+    /// Whether the schedule fires in the minute of `t`.
+    pub fn matches<Tz: TimeZone>(&self, t: &DateTime<Tz>) -> bool {
+        let bit = |mask: u64, v: u32| mask & (1 << v) != 0;
+        let day = bit(self.days, t.day());
+        let weekday = bit(self.weekdays, t.weekday().num_days_from_sunday());
+        let day_ok = match (self.days_restricted, self.weekdays_restricted) {
+            (true, true) => day || weekday,
+            _ => day && weekday,
+        };
+        bit(self.minutes, t.minute())
+            && bit(self.hours, t.hour())
+            && bit(self.months, t.month())
+            && day_ok
+    }
+
+    /// The latest minute at or before `now`, at most `lookback_mins` back,
+    /// in which the schedule fires, as unix seconds.
+    pub fn latest_run(&self, now: DateTime<Local>, lookback_mins: i64) -> Option<i64> {
+        let now = now.with_second(0)?.with_nanosecond(0)?;
+        (0..=lookback_mins)
+            .map(|m| now - Duration::minutes(m))
+            .find(|t| self.matches(t))
+            .map(|t| t.timestamp())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local(y: i32, m: u32, d: u32, h: u32, min: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(y, m, d, h, min, 0).unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert!(CronSchedule::parse("0 18 * * *").is_ok());
+        assert!(CronSchedule::parse("*/15 9-17 1,15 * 1-5").is_ok());
+        assert!(CronSchedule::parse("0 18 * *").is_err());
+        assert!(CronSchedule::parse("60 18 * * *").is_err());
+        assert!(CronSchedule::parse("0 18 0 * *").is_err());
+        assert!(CronSchedule::parse("*/0 * * * *").is_err());
+        assert!(CronSchedule::parse("0 18 * * mon").is_err());
+    }
+
+    #[test]
+    fn test_matches() {
+        // 2026-10-14 is a Wednesday
+        let cron = CronSchedule::parse("0 18 * * *").unwrap();
+        assert!(cron.matches(&local(2026, 10, 14, 18, 0)));
+        assert!(!cron.matches(&local(2026, 10, 14, 18, 1)));
+
+        let cron = CronSchedule::parse("*/20 9-17 * * 1-5").unwrap();
+        assert!(cron.matches(&local(2026, 10, 14, 9, 40)));
+        assert!(!cron.matches(&local(2026, 10, 14, 9, 50)));
+        assert!(!cron.matches(&local(2026, 10, 17, 9, 40)));
+
+        let sunday = CronSchedule::parse("0 8 * * 7").unwrap();
+        assert!(sunday.matches(&local(2026, 10, 18, 8, 0)));
+
+        // Day of month OR day of week when both are restricted
+        let cron = CronSchedule::parse("0 8 1 * 3").unwrap();
+        assert!(cron.matches(&local(2026, 10, 14, 8, 0)));
+        assert!(cron.matches(&local(2026, 10, 1, 8, 0)));
+        assert!(!cron.matches(&local(2026, 10, 2, 8, 0)));
+    }
+
+    #[test]
+    fn test_latest_run() {
+        let cron = CronSchedule::parse("0 18 * * *").unwrap();
+        assert_eq!(
+            cron.latest_run(local(2026, 10, 14, 18, 5), 10),
+            Some(local(2026, 10, 14, 18, 0).timestamp())
+        );
+        assert_eq!(cron.latest_run(local(2026, 10, 14, 18, 11), 10), None);
+    }
+}
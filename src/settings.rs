@@ -0,0 +1,248 @@
+//! Options operators can change from the dashboard (`/api/settings`) without
+//! editing the config file. Values live in the `settings` table as JSON and
+//! are laid over the loaded config at startup, so a change applies after the
+//! next restart; `!admin reload` also lays them over the modules it rebuilds,
+//! which covers the welcome settings.
+//! New options are added here.
+
+use serde_json::Value;
+
+use crate::config::{validate_quiet_hours, Config};
+
+/// Longest welcome text accepted; longer replies are chunked anyway, but a
+/// welcome should stay a message or two.
+const MAX_TEXT_BYTES: usize = 400;
+const MIN_PROBE_INTERVAL_SECS: u64 = 60;
+const MAX_PROBE_INTERVAL_SECS: u64 = 24 * 3600;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Setting {
+    WelcomeEnabled,
+    WelcomeMessage,
+    WelcomeBackMessage,
+    WelcomeQuietHours,
+    TracerouteProbeEnabled,
+    TracerouteProbeIntervalSecs,
+}
+
+impl Setting {
+    pub const ALL: &'static [Setting] = &[
+        Setting::WelcomeEnabled,
+        Setting::WelcomeMessage,
+        Setting::WelcomeBackMessage,
+        Setting::WelcomeQuietHours,
+        Setting::TracerouteProbeEnabled,
+        Setting::TracerouteProbeIntervalSecs,
+    ];
+
+    /// Name stored in the database: the TOML table and key it overrides.
+    pub fn key(self) -> &'static str {
+        match self {
+            Setting::WelcomeEnabled => "welcome.enabled",
+            Setting::WelcomeMessage => "welcome.message",
+            Setting::WelcomeBackMessage => "welcome.welcome_back_message",
+            Setting::WelcomeQuietHours => "welcome.quiet_hours",
+            Setting::TracerouteProbeEnabled => "traceroute_probe.enabled",
+            Setting::TracerouteProbeIntervalSecs => "traceroute_probe.interval_secs",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Setting::ALL.iter().copied().find(|s| s.key() == key)
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Setting::WelcomeEnabled => "Greet nodes heard for the first time or after a while",
+            Setting::WelcomeMessage => "Greeting for new nodes ({name} is their name)",
+            Setting::WelcomeBackMessage => "Greeting for nodes back after a while",
+            Setting::WelcomeQuietHours => {
+                "Local [start, end) hours when nobody is greeted ([] for none)"
+            }
+            Setting::TracerouteProbeEnabled => "Send traceroutes to nodes without hop data",
+            Setting::TracerouteProbeIntervalSecs => "Seconds between traceroute probes",
+        }
+    }
+
+    /// Whether `!admin reload` applies it; the others need a restart.
+    pub fn applies_on_reload(self) -> bool {
+        !matches!(
+            self,
+            Setting::TracerouteProbeEnabled | Setting::TracerouteProbeIntervalSecs
+        )
+    }
+
+    /// The value in `config`.
+    pub fn get(self, config: &Config) -> Value {
+        match self {
+            Setting::WelcomeEnabled => config.welcome.enabled.into(),
+            Setting::WelcomeMessage => config.welcome.message.clone().into(),
+            Setting::WelcomeBackMessage => config.welcome.welcome_back_message.clone().into(),
+            Setting::WelcomeQuietHours => config.welcome.quiet_hours.clone().into(),
+            Setting::TracerouteProbeEnabled => config.traceroute_probe.enabled.into(),
+            Setting::TracerouteProbeIntervalSecs => config.traceroute_probe.interval_secs.into(),
+        }
+    }
+
+    /// Check `value` has the right type and range.
+    pub fn validate(self, value: &Value) -> Result<(), String> {
+        match self {
+            Setting::WelcomeEnabled | Setting::TracerouteProbeEnabled => match value {
+                Value::Bool(_) => Ok(()),
+                _ => Err(format!("{} must be true or false", self.key())),
+            },
+            Setting::WelcomeMessage | Setting::WelcomeBackMessage => match value {
+                Value::String(text) if text.trim().is_empty() => {
+                    Err(format!("{} must not be empty", self.key()))
+                }
+                Value::String(text) if text.len() > MAX_TEXT_BYTES => Err(format!(
+                    "{} must be at most {} bytes",
+                    self.key(),
+                    MAX_TEXT_BYTES
+                )),
+                Value::String(_) => Ok(()),
+                _ => Err(format!("{} must be text", self.key())),
+            },
+            Setting::WelcomeQuietHours => match hours(value) {
+                Some(hours) => validate_quiet_hours(&hours),
+                None => Err(format!("{} must be a list of hours", self.key())),
+            },
+            Setting::TracerouteProbeIntervalSecs => match value.as_u64() {
+                Some(secs)
+                    if (MIN_PROBE_INTERVAL_SECS..=MAX_PROBE_INTERVAL_SECS).contains(&secs) =>
+                {
+                    Ok(())
+                }
+                _ => Err(format!(
+                    "{} must be a whole number from {} to {}",
+                    self.key(),
+                    MIN_PROBE_INTERVAL_SECS,
+                    MAX_PROBE_INTERVAL_SECS
+                )),
+            },
+        }
+    }
+
+    /// Put a validated `value` into `config`.
+    fn set(self, config: &mut Config, value: &Value) {
+        match (self, value) {
+            (Setting::WelcomeEnabled, Value::Bool(on)) => config.welcome.enabled = *on,
+            (Setting::WelcomeMessage, Value::String(text)) => config.welcome.message = text.clone(),
+            (Setting::WelcomeBackMessage, Value::String(text)) => {
+                config.welcome.welcome_back_message = text.clone()
+            }
+            (Setting::WelcomeQuietHours, value) => {
+                if let Some(hours) = hours(value) {
+                    config.welcome.quiet_hours = hours;
+                }
+            }
+            (Setting::TracerouteProbeEnabled, Value::Bool(on)) => {
+                config.traceroute_probe.enabled = *on
+            }
+            (Setting::TracerouteProbeIntervalSecs, value) => {
+                if let Some(secs) = value.as_u64() {
+                    config.traceroute_probe.interval_secs = secs;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A JSON list of hours, if that's what `value` is.
+fn hours(value: &Value) -> Option<Vec<u32>> {
+    value
+        .as_array()?
+        .iter()
+        .map(|hour| hour.as_u64().and_then(|hour| u32::try_from(hour).ok()))
+        .collect()
+}
+
+/// Lay the stored `(key, JSON value)` overrides over `config`. Unknown keys
+/// and values that no longer validate are skipped with a warning.
+pub fn apply(config: &mut Config, overrides: &[(String, String)]) {
+    for (key, raw) in overrides {
+        let Some(setting) = Setting::from_key(key) else {
+            log::warn!("Ignoring unknown setting {:?}", key);
+            continue;
+        };
+        let value: Value = match serde_json::from_str(raw) {
+            Ok(value) => value,
+            Err(e) => {
+                log::warn!("Ignoring setting {}: {}", key, e);
+                continue;
+            }
+        };
+        if let Err(e) = setting.validate(&value) {
+            log::warn!("Ignoring setting: {}", e);
+            continue;
+        }
+        log::info!("Setting {} overridden from the database", key);
+        setting.set(config, &value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_overrides() {
+        let mut config: Config = toml::from_str(
+            r#"
+            [connection]
+            address = "10.0.0.1:4403"
+
+            [bot]
+            name = "Meshenger"
+
+            [welcome]
+            enabled = false
+            message = "Welcome!"
+            welcome_back_message = "Welcome back!"
+            absence_threshold_hours = 48
+
+            [weather]
+            latitude = 0.0
+            longitude = 0.0
+            units = "metric"
+
+            [modules]
+            "#,
+        )
+        .unwrap();
+        let overrides = [
+            ("welcome.enabled", "true"),
+            ("welcome.message", "\"Hi {name}, try !help\""),
+            ("traceroute_probe.interval_secs", "5"),
+            ("welcome.quiet_hours", "[22, 7]"),
+            ("quiet_hours", "[22, 7]"),
+        ]
+        .map(|(k, v)| (k.to_string(), v.to_string()));
+        apply(&mut config, &overrides);
+
+        assert!(config.welcome.enabled);
+        assert_eq!(config.welcome.message, "Hi {name}, try !help");
+        assert_eq!(config.welcome.welcome_back_message, "Welcome back!");
+        assert_eq!(config.welcome.quiet_hours, vec![22, 7]);
+        // Below the minimum interval, so the config file's value stays
+        assert_eq!(config.traceroute_probe.interval_secs, 900);
+    }
+
+    #[test]
+    fn test_validate() {
+        let interval = Setting::from_key("traceroute_probe.interval_secs").unwrap();
+        assert!(interval.validate(&Value::from(600)).is_ok());
+        assert!(interval.validate(&Value::from(-1)).is_err());
+        assert!(interval.validate(&Value::from("600")).is_err());
+        assert!(Setting::WelcomeMessage.validate(&Value::from(" ")).is_err());
+        assert!(Setting::WelcomeEnabled.validate(&Value::from(1)).is_err());
+        let quiet = Setting::WelcomeQuietHours;
+        assert!(quiet.validate(&serde_json::json!([22, 7])).is_ok());
+        assert!(quiet.validate(&serde_json::json!([])).is_ok());
+        assert!(quiet.validate(&serde_json::json!([7, 7])).is_err());
+        assert!(quiet.validate(&serde_json::json!([22, 25])).is_err());
+        assert!(quiet.validate(&serde_json::json!([22])).is_err());
+        assert!(quiet.validate(&Value::from("22-7")).is_err());
+    }
+}
@@ -0,0 +1,278 @@
+//! Fixtures for tests: build the protobufs a radio would hand the bot
+//! instead of spelling out `MeshPacket`/`Data` by hand.
+//!
+//! ```ignore
+//! let packet = PacketBuilder::text(0xaaaa0001, "!ping").to(1).channel(2).build();
+//! let info = NodeInfoBuilder::new(0xaaaa0001).long_name("Ridge").build();
+//! ```
+
+use meshtastic::protobufs::{
+    mesh_packet, routing, telemetry, AdminMessage, Data, DeviceMetrics, EnvironmentMetrics,
+    MeshPacket, NodeInfo, PortNum, Position, RouteDiscovery, Routing, StoreAndForward, Telemetry,
+    User,
+};
+use meshtastic::Message;
+
+/// A `MeshPacket` with a decoded payload. Packets are broadcast on channel
+/// 0 unless set otherwise; every other field keeps its protobuf default.
+pub struct PacketBuilder {
+    packet: MeshPacket,
+}
+
+impl PacketBuilder {
+    /// Any payload on `portnum`.
+    pub fn new(from: u32, portnum: PortNum, payload: Vec<u8>) -> Self {
+        Self {
+            packet: MeshPacket {
+                from,
+                to: u32::MAX,
+                payload_variant: Some(mesh_packet::PayloadVariant::Decoded(Data {
+                    portnum: portnum as i32,
+                    payload,
+                    ..Default::default()
+                })),
+                ..Default::default()
+            },
+        }
+    }
+
+    pub fn text(from: u32, text: &str) -> Self {
+        Self::new(from, PortNum::TextMessageApp, text.as_bytes().to_vec())
+    }
+
+    /// A position report with a fixed altitude (40 m) and speed (3 m/s).
+    pub fn position(from: u32, latitude: f64, longitude: f64) -> Self {
+        let position = Position {
+            latitude_i: Some((latitude * 1e7) as i32),
+            longitude_i: Some((longitude * 1e7) as i32),
+            altitude: Some(40),
+            ground_speed: Some(3),
+            ..Default::default()
+        };
+        Self::new(from, PortNum::PositionApp, position.encode_to_vec())
+    }
+
+    /// A traceroute asking for a response, as sent by `from`.
+    pub fn traceroute_request(from: u32, to: u32) -> Self {
+        Self::new(from, PortNum::TracerouteApp, Vec::new())
+            .to(to)
+            .want_response()
+    }
+
+    /// The answer to traceroute `request_id`: the relays towards `from` and
+    /// those on the way back.
+    pub fn traceroute_response(
+        from: u32,
+        to: u32,
+        request_id: u32,
+        route: &[u32],
+        route_back: &[u32],
+    ) -> Self {
+        let route = RouteDiscovery {
+            route: route.to_vec(),
+            route_back: route_back.to_vec(),
+            ..Default::default()
+        };
+        Self::new(from, PortNum::TracerouteApp, route.encode_to_vec())
+            .to(to)
+            .request_id(request_id)
+    }
+
+    /// A routing reply to `request_id`; `error` 0 is an ack.
+    pub fn routing(from: u32, to: u32, request_id: u32, error: routing::Error) -> Self {
+        let routing = Routing {
+            variant: Some(routing::Variant::ErrorReason(error as i32)),
+        };
+        Self::new(from, PortNum::RoutingApp, routing.encode_to_vec())
+            .to(to)
+            .request_id(request_id)
+    }
+
+    pub fn routing_ack(from: u32, to: u32, request_id: u32) -> Self {
+        Self::routing(from, to, request_id, routing::Error::None)
+    }
+
+    pub fn telemetry(from: u32, variant: telemetry::Variant) -> Self {
+        let telemetry = Telemetry {
+            variant: Some(variant),
+            ..Default::default()
+        };
+        Self::new(from, PortNum::TelemetryApp, telemetry.encode_to_vec())
+    }
+
+    pub fn battery(from: u32, level: u32) -> Self {
+        Self::telemetry(
+            from,
+            telemetry::Variant::DeviceMetrics(DeviceMetrics {
+                battery_level: Some(level),
+                ..Default::default()
+            }),
+        )
+    }
+
+    /// Temperature in °C and relative humidity in %.
+    pub fn environment(from: u32, temperature: f32, humidity: f32) -> Self {
+        Self::telemetry(
+            from,
+            telemetry::Variant::EnvironmentMetrics(EnvironmentMetrics {
+                temperature: Some(temperature),
+                relative_humidity: Some(humidity),
+                ..Default::default()
+            }),
+        )
+    }
+
+    pub fn store_forward(from: u32, message: &StoreAndForward) -> Self {
+        Self::new(from, PortNum::StoreForwardApp, message.encode_to_vec())
+    }
+
+    pub fn admin(from: u32, message: &AdminMessage) -> Self {
+        Self::new(from, PortNum::AdminApp, message.encode_to_vec())
+    }
+
+    pub fn to(mut self, to: u32) -> Self {
+        self.packet.to = to;
+        self
+    }
+
+    pub fn channel(mut self, channel: u32) -> Self {
+        self.packet.channel = channel;
+        self
+    }
+
+    pub fn id(mut self, id: u32) -> Self {
+        self.packet.id = id;
+        self
+    }
+
+    pub fn rx_time(mut self, rx_time: u32) -> Self {
+        self.packet.rx_time = rx_time;
+        self
+    }
+
+    /// Heard `hop_start - hop_limit` hops away.
+    pub fn hops(mut self, hop_start: u32, hop_limit: u32) -> Self {
+        self.packet.hop_start = hop_start;
+        self.packet.hop_limit = hop_limit;
+        self
+    }
+
+    pub fn signal(mut self, rssi: i32, snr: f32) -> Self {
+        self.packet.rx_rssi = rssi;
+        self.packet.rx_snr = snr;
+        self
+    }
+
+    pub fn via_mqtt(mut self) -> Self {
+        self.packet.via_mqtt = true;
+        self
+    }
+
+    pub fn want_response(mut self) -> Self {
+        self.data().want_response = true;
+        self
+    }
+
+    pub fn request_id(mut self, request_id: u32) -> Self {
+        self.data().request_id = request_id;
+        self
+    }
+
+    pub fn build(self) -> MeshPacket {
+        self.packet
+    }
+
+    fn data(&mut self) -> &mut Data {
+        match &mut self.packet.payload_variant {
+            Some(mesh_packet::PayloadVariant::Decoded(data)) => data,
+            _ => unreachable!("PacketBuilder always holds a decoded payload"),
+        }
+    }
+}
+
+/// A `NodeInfo` as found in the radio's NodeDB.
+pub struct NodeInfoBuilder {
+    info: NodeInfo,
+}
+
+impl NodeInfoBuilder {
+    pub fn new(num: u32) -> Self {
+        Self {
+            info: NodeInfo {
+                num,
+                ..Default::default()
+            },
+        }
+    }
+
+    pub fn long_name(mut self, name: &str) -> Self {
+        self.user().long_name = name.to_string();
+        self
+    }
+
+    pub fn short_name(mut self, name: &str) -> Self {
+        self.user().short_name = name.to_string();
+        self
+    }
+
+    pub fn public_key(mut self, key: Vec<u8>) -> Self {
+        self.user().public_key = key;
+        self
+    }
+
+    /// Unix seconds, or seconds since boot on a radio without a clock.
+    pub fn last_heard(mut self, last_heard: u32) -> Self {
+        self.info.last_heard = last_heard;
+        self
+    }
+
+    pub fn position(mut self, latitude: f64, longitude: f64) -> Self {
+        self.info.position = Some(Position {
+            latitude_i: Some((latitude * 1e7) as i32),
+            longitude_i: Some((longitude * 1e7) as i32),
+            ..Default::default()
+        });
+        self
+    }
+
+    pub fn build(self) -> NodeInfo {
+        self.info
+    }
+
+    fn user(&mut self) -> &mut User {
+        self.info.user.get_or_insert_with(User::default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_packet_builder() {
+        let packet = PacketBuilder::traceroute_response(2, 1, 42, &[3], &[])
+            .id(43)
+            .hops(3, 1)
+            .signal(-90, 5.5)
+            .via_mqtt()
+            .build();
+        assert_eq!((packet.from, packet.to, packet.id), (2, 1, 43));
+        assert_eq!(packet.hop_start - packet.hop_limit, 2);
+        assert_eq!((packet.rx_rssi, packet.rx_snr), (-90, 5.5));
+        assert!(packet.via_mqtt);
+        let Some(mesh_packet::PayloadVariant::Decoded(data)) = packet.payload_variant else {
+            panic!("not decoded");
+        };
+        assert_eq!(data.portnum, PortNum::TracerouteApp as i32);
+        assert_eq!(data.request_id, 42);
+        let route = RouteDiscovery::decode(data.payload.as_slice()).unwrap();
+        assert_eq!(route.route, vec![3]);
+
+        let info = NodeInfoBuilder::new(5)
+            .short_name("RDGE")
+            .position(25.5, 121.25)
+            .build();
+        assert_eq!(info.user.unwrap().short_name, "RDGE");
+        assert_eq!(info.position.unwrap().latitude_i, Some(255_000_000));
+    }
+}
@@ -32,6 +32,39 @@ pub fn format_duration(secs: u64) -> String {
     }
 }
 
+/// Format a byte count as "512 B", "3.4 KB", "12.0 MB" or "1.2 GB".
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 3] = ["KB", "MB", "GB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Great-circle (haversine) distance in km between two `(lat, lon)`
+/// positions.
+pub fn distance_km(from: (f64, f64), to: (f64, f64)) -> f64 {
+    const EARTH_RADIUS_KM: f64 = 6371.0;
+    let (lat1, lat2) = (from.0.to_radians(), to.0.to_radians());
+    let dlat = lat2 - lat1;
+    let dlon = (to.1 - from.1).to_radians();
+    let a = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+}
+
+/// Escape text for Telegram/dashboard HTML.
+pub fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
 /// Parse a node ID from a string. Accepts:
 /// - Hex with prefix: "!ebb0a1ce"
 /// - Hex without prefix: "ebb0a1ce"
@@ -48,6 +81,21 @@ pub fn parse_node_id(s: &str) -> Option<u32> {
     }
 }
 
+/// Edit distance between two strings (insertions, deletions, substitutions).
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = prev[j] + usize::from(ca != *cb);
+            cur[j + 1] = substitution.min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        prev = cur;
+    }
+    prev[b.len()]
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -85,6 +133,22 @@ mod tests {
         assert_eq!(format_ago(-1), "in the future");
     }
 
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(3 * 1024 + 512), "3.5 KB");
+        assert_eq!(format_bytes(12 * 1024 * 1024), "12.0 MB");
+        assert_eq!(format_bytes(5 * 1024 * 1024 * 1024 * 1024), "5120.0 GB");
+    }
+
+    #[test]
+    fn test_distance_km() {
+        // Paris to London
+        let km = distance_km((48.8566, 2.3522), (51.5074, -0.1278));
+        assert!((km - 343.5).abs() < 1.0, "{}", km);
+        assert_eq!(distance_km((25.0, 121.0), (25.0, 121.0)), 0.0);
+    }
+
     #[test]
     fn test_format_duration_seconds() {
         assert_eq!(format_duration(0), "0s");
@@ -145,4 +209,12 @@ mod tests {
         assert_eq!(parse_node_id("  !ebb0a1ce  "), Some(0xebb0a1ce));
         assert_eq!(parse_node_id("  123  "), Some(123));
     }
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein("weather", "weather"), 0);
+        assert_eq!(levenshtein("wether", "weather"), 1);
+        assert_eq!(levenshtein("nodse", "nodes"), 2);
+        assert_eq!(levenshtein("", "ping"), 4);
+    }
 }
@@ -1,16 +1,22 @@
-import { useState, useEffect, useCallback, useMemo } from "react";
+import { useState, useEffect, useCallback, useMemo, useRef } from "react";
 import type {
   Overview,
   DashboardNode,
   ThroughputBucket,
   DistributionBucket,
   QueueDepth,
+  Info,
+  Branding,
   TracerouteDestinationRow,
   TracerouteEventRow,
   TracerouteSessionRow,
   MqttFilterValue,
   HoursValue,
   PacketTypeFilter,
+  FeedEntry,
+  LiveMessageSent,
+  LiveNode,
+  LivePacket,
 } from "./types";
 import { MqttFilter } from "./components/MqttFilter";
 import { TimeRangeSelector } from "./components/TimeRangeSelector";
@@ -23,8 +29,25 @@ import { HopChart } from "./components/HopChart";
 import { NodeTable } from "./components/NodeTable";
 import { NodeMap } from "./components/NodeMap";
 import { TracerouteTrafficPanel } from "./components/TracerouteTrafficPanel";
+import { ChatPanel } from "./components/ChatPanel";
+import { PacketFeed } from "./components/PacketFeed";
+import { PacketBrowser } from "./components/PacketBrowser";
+import { SettingsPanel } from "./components/SettingsPanel";
 
 const REFRESH_INTERVAL = 30_000;
+// Live events update the queue, node names and packet feed right away;
+// the charts and tables re-fetch at most this often
+const REFRESH_DEBOUNCE = 5_000;
+const FEED_SIZE = 100;
+
+function formatUptime(secs: number): string {
+  const days = Math.floor(secs / 86_400);
+  const hours = Math.floor((secs % 86_400) / 3_600);
+  const mins = Math.floor((secs % 3_600) / 60);
+  if (days > 0) return `${days}d ${hours}h`;
+  if (hours > 0) return `${hours}h ${mins}m`;
+  return `${mins}m`;
+}
 
 function App() {
   const [mqtt, setMqtt] = useState<MqttFilterValue>("all");
@@ -40,6 +63,8 @@ function App() {
   const [snr, setSnr] = useState<DistributionBucket[] | null>(null);
   const [hops, setHops] = useState<DistributionBucket[] | null>(null);
   const [queue, setQueue] = useState<QueueDepth | null>(null);
+  const [info, setInfo] = useState<Info | null>(null);
+  const [branding, setBranding] = useState<Branding | null>(null);
   const [tracerouteEvents, setTracerouteEvents] = useState<
     TracerouteEventRow[] | null
   >(null);
@@ -49,6 +74,9 @@ function App() {
   const [tracerouteSessions, setTracerouteSessions] = useState<
     TracerouteSessionRow[] | null
   >(null);
+  const [refreshKey, setRefreshKey] = useState(0);
+  const [feed, setFeed] = useState<FeedEntry[]>([]);
+  const feedId = useRef(0);
 
   const params = useMemo(() => {
     const p = new URLSearchParams({ mqtt });
@@ -68,6 +96,7 @@ function App() {
       sn,
       hp,
       qu,
+      inf,
       trEvents,
       trDestinations,
       trSessions,
@@ -82,6 +111,7 @@ function App() {
       fetch(`/api/snr?${p}`).then((r) => (r.ok ? r.json() : null)),
       fetch(`/api/hops?${p}`).then((r) => (r.ok ? r.json() : null)),
       fetch("/api/queue").then((r) => (r.ok ? r.json() : null)),
+      fetch("/api/info").then((r) => (r.ok ? r.json() : null)),
       fetch(`/api/traceroute-events?${p}`).then((r) =>
         r.ok ? r.json() : null,
       ),
@@ -100,11 +130,40 @@ function App() {
     setSnr(sn);
     setHops(hp);
     setQueue(qu);
+    setInfo(inf);
     setTracerouteEvents(trEvents);
     setTracerouteDestinations(trDestinations);
     setTracerouteSessions(trSessions);
+    setRefreshKey((k) => k + 1);
   }, [params, packetFilter]);
 
+  // A traceroute session changed: only its three panels need re-fetching
+  const fetchTraceroutes = useCallback(async () => {
+    const p = params.toString();
+    const [trEvents, trDestinations, trSessions] = await Promise.all([
+      fetch(`/api/traceroute-events?${p}`).then((r) =>
+        r.ok ? r.json() : null,
+      ),
+      fetch(`/api/traceroute-destinations?${p}`).then((r) =>
+        r.ok ? r.json() : null,
+      ),
+      fetch(`/api/traceroute-sessions?${p}`).then((r) =>
+        r.ok ? r.json() : null,
+      ),
+    ]);
+    setTracerouteEvents(trEvents);
+    setTracerouteDestinations(trDestinations);
+    setTracerouteSessions(trSessions);
+  }, [params]);
+
+  // Branding comes from the server config and doesn't change while running
+  useEffect(() => {
+    fetch("/api/branding")
+      .then((r) => (r.ok ? r.json() : null))
+      .then(setBranding)
+      .catch(() => setBranding(null));
+  }, []);
+
   useEffect(() => {
     // Schedule initial fetch on next tick to avoid sync setState in effect body.
     const initialFetchId = setTimeout(() => {
@@ -113,24 +172,73 @@ function App() {
 
     // Use SSE for real-time updates, with polling as fallback
     const es = new EventSource("/api/events");
-    es.addEventListener("refresh", () => fetchAll());
+    let pending: ReturnType<typeof setTimeout> | null = null;
+    es.addEventListener("refresh", () => {
+      if (pending != null) return;
+      pending = setTimeout(() => {
+        pending = null;
+        void fetchAll();
+      }, REFRESH_DEBOUNCE);
+    });
+    es.addEventListener("queue_depth", (e) => {
+      setQueue(JSON.parse(e.data) as QueueDepth);
+    });
+    es.addEventListener("node", (e) => {
+      const node = JSON.parse(e.data) as LiveNode;
+      setNodes((current) =>
+        current?.map((n) =>
+          n.node_id === node.node_id
+            ? { ...n, short_name: node.short_name, long_name: node.long_name }
+            : n,
+        ) ?? null,
+      );
+    });
+    const addToFeed = (entry: FeedEntry) =>
+      setFeed((current) => [entry, ...current].slice(0, FEED_SIZE));
+    es.addEventListener("packet", (e) => {
+      const packet = JSON.parse(e.data) as LivePacket;
+      addToFeed({ kind: "packet", id: ++feedId.current, ...packet });
+    });
+    es.addEventListener("message_sent", (e) => {
+      const sent = JSON.parse(e.data) as LiveMessageSent;
+      addToFeed({ kind: "sent", id: ++feedId.current, ...sent });
+    });
+    es.addEventListener("traceroute", () => {
+      void fetchTraceroutes();
+    });
 
     // Fallback polling in case SSE disconnects silently
     const id = setInterval(fetchAll, REFRESH_INTERVAL);
 
     return () => {
       es.close();
+      if (pending != null) clearTimeout(pending);
       clearTimeout(initialFetchId);
       clearInterval(id);
     };
-  }, [fetchAll]);
+  }, [fetchAll, fetchTraceroutes]);
 
   return (
     <div className="min-h-screen bg-slate-900 text-slate-200">
       <header className="border-b border-slate-700 px-6 py-4 flex items-center justify-between flex-wrap gap-3">
-        <h1 className="text-xl font-bold">
-          {overview?.bot_name ?? "Meshenger"} Dashboard
-        </h1>
+        <div className="flex items-center gap-3">
+          {branding?.logo_url && (
+            <img src={branding.logo_url} alt="" className="h-8 w-auto" />
+          )}
+          <h1 className="text-xl font-bold">
+            {branding?.title ??
+              `${overview?.bot_name ?? "Meshenger"} Dashboard`}
+          </h1>
+          {branding?.links.map((link) => (
+            <a
+              key={link.url}
+              href={link.url}
+              className="text-sm text-sky-400 hover:underline"
+            >
+              {link.title}
+            </a>
+          ))}
+        </div>
         <div className="flex items-center gap-3">
           <TimeRangeSelector value={hours} onChange={setHours} />
           <MqttFilter value={mqtt} onChange={setMqtt} />
@@ -164,10 +272,33 @@ function App() {
           sessions={tracerouteSessions}
         />
 
-        <NodeMap nodes={nodes} />
+        <ChatPanel hours={hours} refreshKey={refreshKey} />
+
+        {/* The public listener only streams refreshes and has no queue */}
+        {queue && <PacketFeed entries={feed} />}
+
+        <PacketBrowser mqtt={mqtt} refreshKey={refreshKey} />
+
+        <NodeMap nodes={nodes} hours={hours} />
 
         <NodeTable nodes={nodes} />
+
+        <SettingsPanel />
       </main>
+
+      {info && (
+        <footer className="border-t border-slate-700 px-6 py-3 text-xs text-slate-500 flex flex-wrap gap-x-4 gap-y-1 justify-center">
+          {branding?.footer && <span>{branding.footer}</span>}
+          <span>
+            Meshenger v{info.version} ({info.git_hash})
+          </span>
+          {info.node_id && <span>Node {info.node_id}</span>}
+          {info.firmware_version && (
+            <span>Firmware {info.firmware_version}</span>
+          )}
+          <span>Up {formatUptime(info.uptime_secs)}</span>
+        </footer>
+      )}
     </div>
   );
 }
@@ -0,0 +1,224 @@
+import { useCallback, useEffect, useState, type FormEvent } from "react";
+import type { ChatMessage, HoursValue, MessagesPage } from "../types";
+
+interface Props {
+  hours: HoursValue;
+  /** Bumped by the parent whenever new data may have arrived. */
+  refreshKey: number;
+}
+
+const PAGE_SIZE = 50;
+const TOKEN_KEY = "meshenger_admin_token";
+const CHANNELS = [0, 1, 2, 3, 4, 5, 6, 7];
+
+function formatTime(timestamp: number): string {
+  return new Date(timestamp * 1000).toLocaleString([], {
+    month: "short",
+    day: "numeric",
+    hour: "2-digit",
+    minute: "2-digit",
+  });
+}
+
+function senderName(message: ChatMessage): string {
+  return (
+    message.from_long_name || message.from_short_name || message.from_node
+  );
+}
+
+function sendError(status: number): string {
+  switch (status) {
+    case 400:
+      return "Check the text, channel and node ID.";
+    case 401:
+      return "Wrong admin token.";
+    case 404:
+      return "Sending needs [dashboard] admin_token set on the server.";
+    case 413:
+      return "Too long for the mesh.";
+    default:
+      return `Not sent (HTTP ${status}).`;
+  }
+}
+
+export function ChatPanel({ hours, refreshKey }: Props) {
+  const [channel, setChannel] = useState<string>("");
+  const [node, setNode] = useState("");
+  const [messages, setMessages] = useState<ChatMessage[]>([]);
+  const [nextBefore, setNextBefore] = useState<number | null>(null);
+  const [available, setAvailable] = useState(true);
+  const [text, setText] = useState("");
+  const [sendChannel, setSendChannel] = useState(0);
+  const [to, setTo] = useState("");
+  const [token, setToken] = useState(
+    () => localStorage.getItem(TOKEN_KEY) ?? "",
+  );
+  const [status, setStatus] = useState<string | null>(null);
+
+  const query = useCallback(
+    (before: number | null) => {
+      const params = new URLSearchParams({
+        hours: String(hours),
+        limit: String(PAGE_SIZE),
+      });
+      if (channel !== "") params.set("channel", channel);
+      if (node.trim() !== "") params.set("node", node.trim());
+      if (before != null) params.set("before", String(before));
+      return fetch(`/api/messages?${params}`).then(async (r) => {
+        // The public listener doesn't serve the chat
+        if (r.status === 404) setAvailable(false);
+        return r.ok ? ((await r.json()) as MessagesPage) : null;
+      });
+    },
+    [hours, channel, node],
+  );
+
+  // Newest page; older pages loaded with "Load older" are dropped on refresh
+  useEffect(() => {
+    const id = setTimeout(() => {
+      void query(null).then((page) => {
+        if (!page) return;
+        setMessages(page.messages);
+        setNextBefore(page.next_before);
+      });
+    }, 0);
+    return () => clearTimeout(id);
+  }, [query, refreshKey]);
+
+  const loadOlder = async () => {
+    if (nextBefore == null) return;
+    const page = await query(nextBefore);
+    if (!page) return;
+    setMessages((current) => [...current, ...page.messages]);
+    setNextBefore(page.next_before);
+  };
+
+  const send = async (event: FormEvent) => {
+    event.preventDefault();
+    if (text.trim() === "") return;
+    localStorage.setItem(TOKEN_KEY, token);
+    const res = await fetch("/api/send", {
+      method: "POST",
+      headers: {
+        "Content-Type": "application/json",
+        Authorization: `Bearer ${token}`,
+      },
+      body: JSON.stringify({
+        text,
+        channel: sendChannel,
+        ...(to.trim() !== "" ? { to: to.trim() } : {}),
+      }),
+    });
+    if (res.ok) {
+      setText("");
+      setStatus("Queued.");
+    } else {
+      setStatus(sendError(res.status));
+    }
+  };
+
+  if (!available) return null;
+
+  return (
+    <div className="bg-slate-800 rounded-lg p-4 border border-slate-700">
+      <div className="flex items-center justify-between flex-wrap gap-3 mb-3">
+        <h3 className="text-sm font-medium text-slate-400">Chat</h3>
+        <div className="flex items-center gap-2 text-xs">
+          <select
+            value={channel}
+            onChange={(e) => setChannel(e.target.value)}
+            className="bg-slate-700 rounded px-2 py-1"
+          >
+            <option value="">All channels</option>
+            {CHANNELS.map((c) => (
+              <option key={c} value={c}>
+                Channel {c}
+              </option>
+            ))}
+          </select>
+          <input
+            value={node}
+            onChange={(e) => setNode(e.target.value)}
+            placeholder="Node, e.g. !a1b2c3d4"
+            className="bg-slate-700 rounded px-2 py-1 w-40"
+          />
+        </div>
+      </div>
+
+      <div className="h-96 overflow-y-auto flex flex-col-reverse gap-1 text-sm">
+        {messages.length === 0 && (
+          <span className="text-slate-500">No messages</span>
+        )}
+        {messages.map((m) => (
+          <div
+            key={m.id}
+            className={m.direction === "out" ? "text-sky-300" : undefined}
+          >
+            <span className="text-xs text-slate-500 mr-2">
+              {formatTime(m.timestamp)} ch{m.channel}
+              {m.to_node && ` → ${m.to_node}`}
+            </span>
+            <span className="font-medium mr-1" title={m.from_node}>
+              {senderName(m)}:
+            </span>
+            {m.text}
+            {m.ack_status === "failed" && (
+              <span className="ml-2 text-xs text-red-400">not delivered</span>
+            )}
+          </div>
+        ))}
+        {nextBefore != null && (
+          <button
+            onClick={() => void loadOlder()}
+            className="self-center text-xs text-sky-400 hover:underline py-1"
+          >
+            Load older
+          </button>
+        )}
+      </div>
+
+      <form
+        onSubmit={(e) => void send(e)}
+        className="mt-3 flex flex-wrap items-center gap-2 text-sm"
+      >
+        <input
+          value={text}
+          onChange={(e) => setText(e.target.value)}
+          placeholder="Message"
+          className="bg-slate-700 rounded px-2 py-1 flex-1 min-w-48"
+        />
+        <select
+          value={sendChannel}
+          onChange={(e) => setSendChannel(Number(e.target.value))}
+          className="bg-slate-700 rounded px-2 py-1"
+        >
+          {CHANNELS.map((c) => (
+            <option key={c} value={c}>
+              Channel {c}
+            </option>
+          ))}
+        </select>
+        <input
+          value={to}
+          onChange={(e) => setTo(e.target.value)}
+          placeholder="DM to (optional)"
+          className="bg-slate-700 rounded px-2 py-1 w-36"
+        />
+        <input
+          type="password"
+          value={token}
+          onChange={(e) => setToken(e.target.value)}
+          placeholder="Admin token"
+          className="bg-slate-700 rounded px-2 py-1 w-32"
+        />
+        <button
+          type="submit"
+          className="bg-sky-700 hover:bg-sky-600 rounded px-3 py-1"
+        >
+          Send
+        </button>
+        {status && <span className="text-xs text-slate-400">{status}</span>}
+      </form>
+    </div>
+  );
+}
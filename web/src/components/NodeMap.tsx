@@ -1,8 +1,14 @@
-import { useMemo } from "react";
-import { MapContainer, TileLayer, Marker, Popup } from "react-leaflet";
+import { useEffect, useMemo, useState } from "react";
+import {
+  MapContainer,
+  TileLayer,
+  Marker,
+  Popup,
+  Polyline,
+} from "react-leaflet";
 import L from "leaflet";
 import "leaflet/dist/leaflet.css";
-import type { DashboardNode } from "../types";
+import type { DashboardNode, HoursValue, TrackPoint } from "../types";
 
 // Fix default marker icons (leaflet CSS expects images in a specific path)
 import markerIcon2x from "leaflet/dist/images/marker-icon-2x.png";
@@ -52,14 +58,31 @@ function formatHopSummary(
 
 interface Props {
   nodes: DashboardNode[] | null;
+  hours: HoursValue;
 }
 
-export function NodeMap({ nodes }: Props) {
+export function NodeMap({ nodes, hours }: Props) {
   const nodesWithPosition = useMemo(
     () =>
       (nodes ?? []).filter((n) => n.latitude != null && n.longitude != null),
     [nodes],
   );
+  // Movement trail of the last clicked node
+  const [selected, setSelected] = useState<string | null>(null);
+  const [track, setTrack] = useState<TrackPoint[]>([]);
+
+  useEffect(() => {
+    if (selected == null) return;
+    let cancelled = false;
+    fetch(`/api/nodes/${encodeURIComponent(selected)}/track?hours=${hours}`)
+      .then((r) => (r.ok ? r.json() : []))
+      .then((points: TrackPoint[]) => {
+        if (!cancelled) setTrack(points);
+      });
+    return () => {
+      cancelled = true;
+    };
+  }, [selected, hours]);
 
   if (nodesWithPosition.length === 0) {
     return (
@@ -97,6 +120,12 @@ export function NodeMap({ nodes }: Props) {
               key={node.node_id}
               position={[node.latitude!, node.longitude!]}
               icon={node.via_mqtt ? mqttIcon : new L.Icon.Default()}
+              eventHandlers={{
+                click: () => {
+                  setTrack([]);
+                  setSelected(node.node_id);
+                },
+              }}
             >
               <Popup>
                 <div className="text-sm">
@@ -115,6 +144,14 @@ export function NodeMap({ nodes }: Props) {
               </Popup>
             </Marker>
           ))}
+          {track.length > 1 && (
+            <Polyline
+              positions={track.map(
+                (p) => [p.latitude, p.longitude] as [number, number],
+              )}
+              pathOptions={{ color: "#38bdf8", weight: 3 }}
+            />
+          )}
         </MapContainer>
       </div>
     </div>
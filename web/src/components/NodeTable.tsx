@@ -181,18 +181,30 @@ export function NodeTable({ nodes }: Props) {
               <td className="py-2 px-2 font-mono text-xs">{node.node_id}</td>
               <td className="py-2 px-2">
                 {node.long_name || node.short_name || "—"}
+                {node.latest_note && (
+                  <div
+                    className="text-xs text-slate-500 truncate max-w-xs"
+                    title={node.latest_note}
+                  >
+                    {node.latest_note}
+                  </div>
+                )}
               </td>
               <td className="py-2 px-2">
                 <SourceBadge viaMqtt={node.via_mqtt} />
               </td>
-              <td className="py-2 px-2 text-slate-400">
+              <td className="py-2 px-2 text-slate-400" title={node.freshness}>
                 {formatAgo(node.last_seen)}
               </td>
               <td className="py-2 px-2 text-slate-400">
                 {node.last_rf_seen != null ? formatAgo(node.last_rf_seen) : "—"}
               </td>
               <td className="py-2 px-2 text-slate-400">
-                {formatHopSummary(node.last_hop, node.avg_hop, node.min_hop)}
+                {formatHopSummary(
+                  node.last_hop,
+                  node.avg_hop ?? node.combined_avg_hop,
+                  node.min_hop ?? node.combined_min_hop,
+                )}
               </td>
               <td className="py-2 px-2 text-slate-400">{node.hop_samples}</td>
               <td className="py-2 px-2 text-slate-400">
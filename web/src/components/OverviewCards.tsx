@@ -46,6 +46,10 @@ export function OverviewCards({ overview, queue, hours }: Props) {
         title={`Packets Out (${label})`}
         value={overview?.packets_out ?? "—"}
       />
+      <Card
+        title={`MQTT Commands Ignored (${label})`}
+        value={overview?.mqtt_commands.ignored ?? "—"}
+      />
       <Card title="Queue Depth" value={queue?.depth ?? 0} />
     </div>
   );
@@ -0,0 +1,180 @@
+import { useCallback, useEffect, useState } from "react";
+import type { MqttFilterValue, PacketRecord, PacketsPage } from "../types";
+
+interface Props {
+  mqtt: MqttFilterValue;
+  /** Bumped by the parent whenever new data may have arrived. */
+  refreshKey: number;
+}
+
+const PAGE_SIZE = 50;
+const TYPES = [
+  "text",
+  "position",
+  "telemetry",
+  "nodeinfo",
+  "traceroute",
+  "neighborinfo",
+  "routing",
+  "store_forward",
+  "other",
+];
+
+function formatTime(timestamp: number): string {
+  return new Date(timestamp * 1000).toLocaleString([], {
+    month: "short",
+    day: "numeric",
+    hour: "2-digit",
+    minute: "2-digit",
+    second: "2-digit",
+  });
+}
+
+function signal(p: PacketRecord): string {
+  const parts = [];
+  if (p.hop_count != null) parts.push(`${p.hop_count} hop(s)`);
+  if (p.rssi != null) parts.push(`${p.rssi} dBm`);
+  if (p.snr != null) parts.push(`SNR ${p.snr.toFixed(1)}`);
+  return parts.join(" · ");
+}
+
+/** Every logged packet, filtered and a page at a time (`/api/packets`). */
+export function PacketBrowser({ mqtt, refreshKey }: Props) {
+  const [from, setFrom] = useState("");
+  const [to, setTo] = useState("");
+  const [type, setType] = useState("");
+  const [direction, setDirection] = useState("");
+  const [offset, setOffset] = useState(0);
+  const [page, setPage] = useState<PacketsPage | null>(null);
+  const [available, setAvailable] = useState(true);
+  const [error, setError] = useState(false);
+
+  const load = useCallback(async () => {
+    const params = new URLSearchParams({
+      mqtt,
+      limit: String(PAGE_SIZE),
+      offset: String(offset),
+    });
+    if (from.trim() !== "") params.set("from", from.trim());
+    if (to.trim() !== "") params.set("to", to.trim());
+    if (type !== "") params.set("type", type);
+    if (direction !== "") params.set("direction", direction);
+    const r = await fetch(`/api/packets?${params}`);
+    // The public listener doesn't serve packets
+    if (r.status === 404) setAvailable(false);
+    setError(r.status === 400);
+    if (r.ok) setPage((await r.json()) as PacketsPage);
+  }, [mqtt, from, to, type, direction, offset]);
+
+  // Only the first page follows new packets; older pages stay put
+  const followKey = offset === 0 ? refreshKey : 0;
+  useEffect(() => {
+    const id = setTimeout(() => void load(), 0);
+    return () => clearTimeout(id);
+  }, [load, followKey]);
+
+  const filter =
+    (set: (value: string) => void) =>
+    (e: { target: { value: string } }) => {
+      set(e.target.value);
+      setOffset(0);
+    };
+
+  if (!available) return null;
+
+  return (
+    <div className="bg-slate-800 rounded-lg p-4 border border-slate-700">
+      <div className="flex items-center justify-between flex-wrap gap-3 mb-3">
+        <h3 className="text-sm font-medium text-slate-400">Packets</h3>
+        <div className="flex items-center gap-2 text-xs">
+          <input
+            value={from}
+            onChange={filter(setFrom)}
+            placeholder="From !id"
+            className="bg-slate-700 rounded px-2 py-1 w-28"
+          />
+          <input
+            value={to}
+            onChange={filter(setTo)}
+            placeholder="To !id"
+            className="bg-slate-700 rounded px-2 py-1 w-28"
+          />
+          <select
+            value={type}
+            onChange={filter(setType)}
+            className="bg-slate-700 rounded px-2 py-1"
+          >
+            <option value="">All types</option>
+            {TYPES.map((t) => (
+              <option key={t} value={t}>
+                {t}
+              </option>
+            ))}
+          </select>
+          <select
+            value={direction}
+            onChange={filter(setDirection)}
+            className="bg-slate-700 rounded px-2 py-1"
+          >
+            <option value="">In and out</option>
+            <option value="in">Received</option>
+            <option value="out">Sent</option>
+          </select>
+        </div>
+      </div>
+
+      {error && (
+        <p className="text-xs text-red-400 mb-2">
+          Node IDs look like !a1b2c3d4.
+        </p>
+      )}
+
+      <div className="h-96 overflow-y-auto font-mono text-xs">
+        <table className="w-full">
+          <tbody>
+            {page?.packets.map((p) => (
+              <tr
+                key={p.id}
+                className={p.direction === "out" ? "text-sky-300" : undefined}
+              >
+                <td className="pr-2 text-slate-500 whitespace-nowrap">
+                  {formatTime(p.timestamp)}
+                </td>
+                <td className="pr-2">{p.packet_type}</td>
+                <td className="pr-2 whitespace-nowrap" title={p.from_node}>
+                  {p.from_short_name || p.from_node}
+                  {p.to_node ? ` → ${p.to_node}` : ""} ch{p.channel}
+                  {p.via_mqtt ? " MQTT" : ""}
+                </td>
+                <td className="pr-2 text-slate-500 whitespace-nowrap">
+                  {signal(p)}
+                </td>
+                <td className="text-slate-300">{p.text}</td>
+              </tr>
+            ))}
+          </tbody>
+        </table>
+        {page?.packets.length === 0 && (
+          <span className="text-slate-500">No packets</span>
+        )}
+      </div>
+
+      <div className="mt-2 flex gap-3 text-xs">
+        <button
+          onClick={() => setOffset(Math.max(0, offset - PAGE_SIZE))}
+          disabled={offset === 0}
+          className="text-sky-400 hover:underline disabled:opacity-40"
+        >
+          Newer
+        </button>
+        <button
+          onClick={() => page?.next_offset != null && setOffset(page.next_offset)}
+          disabled={page?.next_offset == null}
+          className="text-sky-400 hover:underline disabled:opacity-40"
+        >
+          Older
+        </button>
+      </div>
+    </div>
+  );
+}
@@ -0,0 +1,58 @@
+import type { FeedEntry } from "../types";
+
+interface Props {
+  entries: FeedEntry[];
+}
+
+function formatTime(timestamp: number): string {
+  return new Date(timestamp * 1000).toLocaleTimeString([], {
+    hour: "2-digit",
+    minute: "2-digit",
+    second: "2-digit",
+  });
+}
+
+function signal(entry: FeedEntry): string | null {
+  if (entry.kind !== "packet") return null;
+  const parts = [];
+  if (entry.via_mqtt) parts.push("MQTT");
+  if (entry.hops != null) parts.push(`${entry.hops} hop(s)`);
+  if (entry.rssi != null) parts.push(`${entry.rssi} dBm`);
+  if (entry.snr != null) parts.push(`SNR ${entry.snr.toFixed(1)}`);
+  return parts.length > 0 ? parts.join(" · ") : null;
+}
+
+/** Packets as they arrive over `/api/events`, newest first. */
+export function PacketFeed({ entries }: Props) {
+  return (
+    <div className="bg-slate-800 rounded-lg p-4 border border-slate-700">
+      <h3 className="text-sm font-medium text-slate-400 mb-3">Live packets</h3>
+      <div className="h-72 overflow-y-auto font-mono text-xs space-y-1">
+        {entries.length === 0 && (
+          <span className="text-slate-500">Waiting for packets…</span>
+        )}
+        {entries.map((e) => (
+          <div
+            key={e.id}
+            className={e.kind === "sent" ? "text-sky-300" : undefined}
+          >
+            <span className="text-slate-500 mr-2">
+              {formatTime(e.timestamp)}
+            </span>
+            <span className="mr-2">
+              {e.kind === "sent" ? "sent" : e.packet_type}
+            </span>
+            <span className="mr-2">
+              {e.from_node}
+              {e.to_node ? ` → ${e.to_node}` : ""} ch{e.channel}
+            </span>
+            {signal(e) && (
+              <span className="text-slate-500 mr-2">{signal(e)}</span>
+            )}
+            {e.text && <span className="text-slate-300">{e.text}</span>}
+          </div>
+        ))}
+      </div>
+    </div>
+  );
+}
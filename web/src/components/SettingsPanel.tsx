@@ -0,0 +1,139 @@
+import { useEffect, useState } from "react";
+import type { Setting } from "../types";
+
+const TOKEN_KEY = "meshenger_admin_token";
+
+type Value = Setting["value"];
+
+function saveError(status: number, body: string): string {
+  switch (status) {
+    case 400:
+      return body || "Invalid value.";
+    case 401:
+      return "Wrong admin token.";
+    case 404:
+      return "Editing needs [dashboard] admin_token set on the server.";
+    default:
+      return `Not saved (HTTP ${status}).`;
+  }
+}
+
+/** Options stored in the database over the config file (`/api/settings`). */
+export function SettingsPanel() {
+  const [settings, setSettings] = useState<Setting[] | null>(null);
+  const [edits, setEdits] = useState<Record<string, Value | null>>({});
+  const [token, setToken] = useState(
+    () => localStorage.getItem(TOKEN_KEY) ?? "",
+  );
+  const [status, setStatus] = useState<string | null>(null);
+
+  useEffect(() => {
+    fetch("/api/settings")
+      .then((r) => (r.ok ? r.json() : null))
+      .then(setSettings)
+      .catch(() => setSettings(null));
+  }, []);
+
+  // The public listener doesn't serve settings
+  if (!settings) return null;
+
+  const edit = (key: string, value: Value | null) =>
+    setEdits((current) => ({ ...current, [key]: value }));
+
+  const save = async () => {
+    localStorage.setItem(TOKEN_KEY, token);
+    const res = await fetch("/api/settings", {
+      method: "PUT",
+      headers: {
+        "Content-Type": "application/json",
+        Authorization: `Bearer ${token}`,
+      },
+      body: JSON.stringify(edits),
+    });
+    if (res.ok) {
+      setSettings((await res.json()) as Setting[]);
+      setEdits({});
+      setStatus("Saved. Applies after !admin reload or a restart.");
+    } else {
+      setStatus(saveError(res.status, await res.text()));
+    }
+  };
+
+  const input = (setting: Setting) => {
+    const edited = edits[setting.key];
+    const value = edited ?? setting.value;
+    if (typeof setting.value === "boolean") {
+      return (
+        <input
+          type="checkbox"
+          checked={value as boolean}
+          onChange={(e) => edit(setting.key, e.target.checked)}
+        />
+      );
+    }
+    if (typeof setting.value === "number") {
+      return (
+        <input
+          type="number"
+          value={value as number}
+          onChange={(e) => edit(setting.key, Number(e.target.value))}
+          className="bg-slate-700 rounded px-2 py-1 w-32"
+        />
+      );
+    }
+    return (
+      <textarea
+        value={value as string}
+        onChange={(e) => edit(setting.key, e.target.value)}
+        rows={2}
+        className="bg-slate-700 rounded px-2 py-1 w-full"
+      />
+    );
+  };
+
+  return (
+    <div className="bg-slate-800 rounded-lg p-4 border border-slate-700">
+      <h3 className="text-sm font-medium text-slate-400 mb-3">Settings</h3>
+      <div className="space-y-3 text-sm">
+        {settings.map((s) => (
+          <div key={s.key}>
+            <div className="flex items-center gap-2 mb-1">
+              <span className="font-mono text-xs">{s.key}</span>
+              <span className="text-xs text-slate-500">{s.description}</span>
+              {!s.applied && (
+                <span className="text-xs text-amber-400">pending reload</span>
+              )}
+              {s.overridden && (
+                <button
+                  onClick={() => edit(s.key, null)}
+                  className="text-xs text-sky-400 hover:underline"
+                  title="Use the config file's value again"
+                >
+                  {edits[s.key] === null ? "will reset" : "reset"}
+                </button>
+              )}
+            </div>
+            {input(s)}
+          </div>
+        ))}
+      </div>
+      <div className="mt-3 flex flex-wrap items-center gap-2 text-sm">
+        <input
+          type="password"
+          value={token}
+          onChange={(e) => setToken(e.target.value)}
+          placeholder="Admin token"
+          className="bg-slate-700 rounded px-2 py-1 w-32"
+        />
+        <button
+          onClick={() => void save()}
+          disabled={Object.keys(edits).length === 0}
+          className="bg-sky-700 hover:bg-sky-600 disabled:opacity-50 rounded px-3 py-1"
+        >
+          Save
+        </button>
+        {status && <span className="text-xs text-slate-400">{status}</span>}
+      </div>
+    </div>
+  );
+}
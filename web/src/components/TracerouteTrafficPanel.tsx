@@ -5,6 +5,7 @@ import type {
   TracerouteEventRow,
   TracerouteSessionHop,
   TracerouteSessionRow,
+  TraceroutePath,
 } from "../types";
 import { PaginationControls } from "./PaginationControls";
 import { buildPathSegs, buildFullText } from "../utils/pathDisplay";
@@ -171,6 +172,10 @@ function SourceBadge({ viaMqtt }: { viaMqtt: boolean }) {
   );
 }
 
+function pathLabel(path: TraceroutePath): string {
+  return path === "both" ? "RF+MQTT" : path === "mqtt" ? "MQTT" : "RF";
+}
+
 function StatusBadge({
   status,
   traceKey,
@@ -685,6 +690,11 @@ export function TracerouteTrafficPanel({
                     </td>
                     <td className="py-2 px-2">
                       <SourceBadge viaMqtt={row.via_mqtt} />
+                      {row.response_path && (
+                        <span className="ml-1 text-xs text-slate-500">
+                          reply {pathLabel(row.response_path)}
+                        </span>
+                      )}
                     </td>
                     <td className="py-2 px-2 text-slate-400">
                       {row.sample_count}
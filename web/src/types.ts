@@ -4,9 +4,16 @@ export interface Overview {
   messages_out: number;
   packets_in: number;
   packets_out: number;
+  mqtt_commands: MqttCommandCounts;
   bot_name: string;
 }
 
+export interface MqttCommandCounts {
+  replied: number;
+  dm: number;
+  ignored: number;
+}
+
 export interface DashboardNode {
   node_id: string;
   short_name: string;
@@ -20,6 +27,22 @@ export interface DashboardNode {
   min_hop: number | null;
   avg_hop: number | null;
   hop_samples: number;
+  combined_min_hop: number | null;
+  combined_avg_hop: number | null;
+  combined_hop_samples: number;
+  latest_note: string | null;
+  freshness: Freshness;
+  online: boolean;
+}
+
+export type Freshness = "online" | "recent" | "stale" | "offline";
+
+export interface TrackPoint {
+  timestamp: number;
+  latitude: number;
+  longitude: number;
+  altitude: number | null;
+  speed: number | null;
 }
 
 export interface ThroughputBucket {
@@ -37,6 +60,27 @@ export interface QueueDepth {
   depth: number;
 }
 
+export interface Info {
+  version: string;
+  git_hash: string;
+  build_time: string | null;
+  uptime_secs: number;
+  node_id: string | null;
+  firmware_version: string | null;
+}
+
+export interface BrandingLink {
+  title: string;
+  url: string;
+}
+
+export interface Branding {
+  title: string;
+  logo_url: string | null;
+  footer: string | null;
+  links: BrandingLink[];
+}
+
 export interface TracerouteRequester {
   node_id: string;
   short_name: string;
@@ -81,6 +125,8 @@ export interface TracerouteSessionHop {
   long_name: string | null;
 }
 
+export type TraceroutePath = "rf" | "mqtt" | "both";
+
 export interface TracerouteSessionRow {
   id: number;
   trace_key: string;
@@ -99,9 +145,56 @@ export interface TracerouteSessionRow {
   response_hop_start: number | null;
   status: string;
   sample_count: number;
+  request_path: TraceroutePath | null;
+  response_path: TraceroutePath | null;
   hops: TracerouteSessionHop[];
 }
 
+export interface ChatMessage {
+  id: number;
+  timestamp: number;
+  direction: "in" | "out";
+  from_node: string;
+  from_short_name: string;
+  from_long_name: string;
+  to_node: string | null;
+  channel: number;
+  text: string;
+  via_mqtt: boolean;
+  ack_status: string | null;
+}
+
+export interface MessagesPage {
+  messages: ChatMessage[];
+  next_before: number | null;
+}
+
+/** A logged packet from `/api/packets`. */
+export interface PacketRecord {
+  id: number;
+  timestamp: number;
+  direction: "in" | "out";
+  packet_type: string;
+  from_node: string;
+  from_short_name: string;
+  to_node: string | null;
+  channel: number;
+  via_mqtt: boolean;
+  radio: string | null;
+  rssi: number | null;
+  snr: number | null;
+  hop_count: number | null;
+  hop_start: number | null;
+  mesh_packet_id: number | null;
+  text: string;
+  ack_status: string | null;
+}
+
+export interface PacketsPage {
+  packets: PacketRecord[];
+  next_offset: number | null;
+}
+
 export type MqttFilterValue = "all" | "local" | "mqtt_only";
 
 export type HoursValue = 24 | 72 | 168 | 720 | 2160 | 8760 | 0;
@@ -113,3 +206,50 @@ export type PacketTypeFilter =
   | "telemetry"
   | "traceroute"
   | "other";
+
+/** `packet` event on `/api/events`: a packet heard from the mesh. */
+export interface LivePacket {
+  timestamp: number;
+  from_node: string;
+  to_node: string | null;
+  packet_type: string;
+  channel: number;
+  via_mqtt: boolean;
+  rssi: number | null;
+  snr: number | null;
+  hops: number | null;
+  text: string | null;
+}
+
+/** `node` event on `/api/events`: a node announced itself. */
+export interface LiveNode {
+  node_id: string;
+  short_name: string;
+  long_name: string;
+  via_mqtt: boolean;
+}
+
+/** `message_sent` event on `/api/events`: a text the bot transmitted. */
+export interface LiveMessageSent {
+  timestamp: number;
+  from_node: string;
+  to_node: string | null;
+  channel: number;
+  text: string;
+}
+
+/** A row of the live packet feed; `id` only orders rows in the browser. */
+export type FeedEntry =
+  | ({ kind: "packet"; id: number } & LivePacket)
+  | ({ kind: "sent"; id: number } & LiveMessageSent);
+
+/** An entry of `/api/settings`: an option editable from the dashboard. */
+export interface Setting {
+  key: string;
+  description: string;
+  value: boolean | number | string;
+  /** Stored in the database, overriding the config file */
+  overridden: boolean;
+  /** Whether the running bot uses `value` yet (after a reload or restart) */
+  applied: boolean;
+}